@@ -1,10 +1,26 @@
 #version 330 core
 
 in vec2 tex_coord;
+in vec2 lightmap_uv;
+in vec4 color;
 
 uniform sampler2D tex_color;
+uniform sampler2D lightmap;
+uniform bool use_lightmap;
 uniform mat4 proj;
 
+// ALPHA_CUTOFF_GLSL, the renderer sets the cutoff of every draw command
+uniform float alpha_cutoff;
+
+void alpha_test(float alpha) {
+    if (alpha < alpha_cutoff) {
+        discard;
+    }
+}
+
 void main() {
-    gl_FragColor = texture(tex_color, tex_coord);
+    vec4 albedo = texture(tex_color, tex_coord);
+    alpha_test(albedo.a);
+    vec4 light = use_lightmap ? vec4(texture(lightmap, lightmap_uv).rgb, 1.0) : vec4(1.0);
+    gl_FragColor = albedo * color * light;
 }
\ No newline at end of file
@@ -1,16 +1,20 @@
 #version 330 core
 layout (location = 0) in vec3 Ipos;
 layout (location = 1) in vec2 _tex_coord;
+layout (location = 14) in vec2 _lightmap_uv;
+layout (location = 15) in vec4 vertex_color;
 
 uniform mat4 camera_matrix;
-// uniform mat4 model;
-// uniform mat4 view;
-// uniform mat4 proj;
+uniform mat4 model;
 
 out vec2 tex_coord;
+out vec2 lightmap_uv;
+out vec4 color;
 out vec4 pos;
 
 void main() {
-    gl_Position = camera_matrix * vec4(Ipos.x, Ipos.y, Ipos.z, 1.0);
+    gl_Position = camera_matrix * model * vec4(Ipos.x, Ipos.y, Ipos.z, 1.0);
     tex_coord = _tex_coord;
+    lightmap_uv = _lightmap_uv;
+    color = vertex_color;
 }
\ No newline at end of file
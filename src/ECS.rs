@@ -1,4 +1,36 @@
+/// For batch
+pub mod batch;
+/// For camera
+pub mod camera;
+/// For camera2d
+pub mod camera2d;
+/// For commands
+pub mod commands;
+/// For context
+pub mod context;
+/// For frame_step
+pub mod frame_step;
+/// For game_loop
+pub mod game_loop;
+/// For keyboard
+pub mod keyboard;
 /// For mesh
 pub mod mesh;
+/// For minimap
+pub mod minimap;
+/// For mouse
+pub mod mouse;
+/// For object
+pub mod object;
+/// For parallax
+pub mod parallax;
+/// For time
+pub mod time;
+/// For window
+pub mod window;
+/// For world
+pub mod world;
+/// For world_label
+pub mod world_label;
 
 pub use specs::{prelude::*, *};
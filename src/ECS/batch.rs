@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+
+use nalgebra_glm::*;
+
+use super::mesh::{Mesh, SubMesh, VertexTrait};
+use crate::{error::LighthouseError, graphics::vertex::AttrFormat};
+
+/// Triangles of one material waiting to be merged
+struct MaterialGroup<Vertex> {
+    vertices: Vec<Vertex>,
+    indices: Vec<[u32; 3]>,
+}
+
+/// Merges many static meshes into one [Mesh] at load time
+///
+/// The world transform of every mesh is baked into its vertices and the triangles are
+/// grouped by material, so the merged mesh is drawn with one draw call per material
+/// instead of one per mesh
+///
+/// # Example
+/// ```
+/// let mut batcher = StaticBatcher::new();
+/// for (pos, rot) in level.walls {
+///     batcher.add_with_material(&wall_mesh, pos, rot, STONE)?;
+/// }
+/// batcher.add(&house_mesh, house_pos, house_rot)?;
+///
+/// let level_mesh = batcher.build()?;
+/// level_mesh.draw_with_materials(|material| materials[material].bind());
+/// ```
+pub struct StaticBatcher<Vertex: VertexTrait + 'static + Sync + Send> {
+    /// The vertex attributes every added mesh must have
+    vert_attr: Option<Vec<u32>>,
+    /// The merged triangles, keyed by material
+    groups: BTreeMap<usize, MaterialGroup<Vertex>>,
+    /// The mesh the attribute formats, color and lightmap uv attributes are taken from
+    template: Option<(Vec<AttrFormat>, Option<usize>, Option<usize>)>,
+}
+
+impl<Vertex: VertexTrait + 'static + Sync + Send> StaticBatcher<Vertex> {
+    /// Creates a new empty batcher
+    pub fn new() -> Self {
+        StaticBatcher {
+            vert_attr: None,
+            groups: BTreeMap::new(),
+            template: None,
+        }
+    }
+
+    /// Adds mesh placed at pos with rotation rot
+    ///
+    /// Every sub mesh keeps its material, a mesh without sub meshes uses material 0
+    pub fn add(
+        &mut self,
+        mesh: &Mesh<Vertex>,
+        pos: Vec3,
+        rot: Vec4,
+    ) -> Result<(), LighthouseError> {
+        self.check_attributes(mesh)?;
+        if mesh.sub_meshes.is_empty() {
+            let all = SubMesh {
+                first: 0,
+                count: mesh.indicies.len() * 3,
+                base_vertex: 0,
+                material: 0,
+            };
+            self.add_range(mesh, &all, pos, rot)?;
+        }
+        for sub_mesh in &mesh.sub_meshes {
+            self.add_range(mesh, sub_mesh, pos, rot)?;
+        }
+        Ok(())
+    }
+
+    /// Adds mesh placed at pos with rotation rot, every triangle of it uses material
+    pub fn add_with_material(
+        &mut self,
+        mesh: &Mesh<Vertex>,
+        pos: Vec3,
+        rot: Vec4,
+        material: usize,
+    ) -> Result<(), LighthouseError> {
+        self.check_attributes(mesh)?;
+        let all = SubMesh {
+            first: 0,
+            count: mesh.indicies.len() * 3,
+            base_vertex: 0,
+            material,
+        };
+        self.add_range(mesh, &all, pos, rot)
+    }
+
+    /// Merges everything that was added into one mesh with a [SubMesh] per material
+    pub fn build(self) -> Result<Mesh<Vertex>, LighthouseError> {
+        let vert_attr = self.vert_attr.ok_or_else(|| {
+            LighthouseError::InvalidInput("Can't build a static batch without any mesh".to_string())
+        })?;
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut sub_meshes = Vec::new();
+        for (material, group) in self.groups {
+            let offset = vertices.len() as u32;
+            sub_meshes.push(SubMesh {
+                first: indices.len() * 3,
+                count: group.indices.len() * 3,
+                base_vertex: 0,
+                material,
+            });
+            vertices.extend(group.vertices);
+            indices.extend(
+                group
+                    .indices
+                    .into_iter()
+                    .map(|triangle| triangle.map(|index| index + offset)),
+            );
+        }
+
+        let mut mesh = Mesh::new(vertices, vert_attr, indices)?.with_sub_meshes(sub_meshes)?;
+        if let Some((attr_formats, color_attr, lightmap_uv_attr)) = self.template {
+            mesh.attr_formats = attr_formats;
+            mesh.color_attr = color_attr;
+            mesh.lightmap_uv_attr = lightmap_uv_attr;
+        }
+        Ok(mesh)
+    }
+
+    /// Checks that mesh has the same vertex attributes as the meshes added before it
+    fn check_attributes(&mut self, mesh: &Mesh<Vertex>) -> Result<(), LighthouseError> {
+        match &self.vert_attr {
+            Some(vert_attr) if *vert_attr != mesh.vert_attr => Err(LighthouseError::InvalidInput(format!(
+                "Every mesh of a static batch needs the same vertex attributes, expected {:?} but got {:?}",
+                vert_attr, mesh.vert_attr
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                self.vert_attr = Some(mesh.vert_attr.clone());
+                self.template = Some((
+                    mesh.attr_formats.clone(),
+                    mesh.color_attr,
+                    mesh.lightmap_uv_attr,
+                ));
+                Ok(())
+            }
+        }
+    }
+
+    /// Copies the triangles of a range of mesh into the group of its material
+    ///
+    /// Nothing is copied when the range or a triangle in it is out of the mesh
+    fn add_range(
+        &mut self,
+        mesh: &Mesh<Vertex>,
+        range: &SubMesh,
+        pos: Vec3,
+        rot: Vec4,
+    ) -> Result<(), LighthouseError> {
+        let triangles = mesh
+            .indicies
+            .get(range.first / 3..(range.first + range.count) / 3)
+            .ok_or_else(|| {
+                LighthouseError::InvalidInput(format!(
+                    "A sub mesh of {} indices from {} is out of the mesh",
+                    range.count, range.first
+                ))
+            })?;
+        let first_vertex = self
+            .groups
+            .get(&range.material)
+            .map_or(0, |group| group.vertices.len());
+
+        // only the vertices the range uses are copied, remapped to their new index
+        let mut remap = BTreeMap::new();
+        let mut vertices = Vec::new();
+        let mut indices = Vec::with_capacity(triangles.len());
+        for triangle in triangles {
+            let mut remapped = [0; 3];
+            for (new, &index) in remapped.iter_mut().zip(triangle) {
+                let index = index as i64 + range.base_vertex as i64;
+                *new = match remap.get(&index) {
+                    Some(&new) => new,
+                    None => {
+                        let vertex = usize::try_from(index)
+                            .ok()
+                            .and_then(|index| mesh.vertices.get(index))
+                            .ok_or_else(|| {
+                                LighthouseError::InvalidInput(format!(
+                                    "A triangle points to vertex {} but the mesh only has {}",
+                                    index,
+                                    mesh.vertices.len()
+                                ))
+                            })?;
+                        vertices.push(vertex.get_vertex(pos, rot));
+                        let new = (first_vertex + vertices.len() - 1) as u32;
+                        remap.insert(index, new);
+                        new
+                    }
+                };
+            }
+            indices.push(remapped);
+        }
+
+        let group = self
+            .groups
+            .entry(range.material)
+            .or_insert_with(|| MaterialGroup {
+                vertices: Vec::new(),
+                indices: Vec::new(),
+            });
+        group.vertices.extend(vertices);
+        group.indices.extend(indices);
+        Ok(())
+    }
+}
+
+impl<Vertex: VertexTrait + 'static + Sync + Send> Default for StaticBatcher<Vertex> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
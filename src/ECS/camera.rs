@@ -1,5 +1,7 @@
+use super::mouse::Mouse;
 use super::object::Object;
 use super::world::GameObjectTrait;
+use crate::graphics::post_process::PostProcessSettings;
 use crate::graphics::shader::ShaderProgram;
 use crate::graphics::uniform::Uniform;
 use nalgebra_glm::*;
@@ -18,9 +20,12 @@ use nalgebra_glm::*;
 ///     .sensitivity(1.0)
 ///     .near_plane(0.1)
 ///     .far_plane(100.0)
+///     .focus_distance(10.0)
+///     .aperture(0.0)
+///     .post(PostProcessSettings::default())
 ///     .build() // And finally build
 /// ```
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct CameraSettingsBuilder {
     /// This field is supposed to store the width of the screen
     screen_size: Option<Vec2>,
@@ -32,6 +37,12 @@ pub struct CameraSettingsBuilder {
     near_plane: f32,
     /// Anything above this value will be clipped
     far_plane: f32,
+    /// How far away things are in focus
+    focus_distance: f32,
+    /// How blurry things out of focus get
+    aperture: f32,
+    /// How the camera looks
+    post: PostProcessSettings,
     /// The shader program
     shader_program: Option<ShaderProgram>,
 }
@@ -45,6 +56,9 @@ impl CameraSettingsBuilder {
             sensitivity: 1.0,
             near_plane: 0.1,
             far_plane: 100.0,
+            focus_distance: 10.0,
+            aperture: 0.0,
+            post: PostProcessSettings::default(),
             shader_program: None,
         }
     }
@@ -79,6 +93,24 @@ impl CameraSettingsBuilder {
         self
     }
 
+    /// This function is supposed to set the focus_distance. It is optional
+    pub fn focus_distance(&mut self, focus_distance: f32) -> &mut Self {
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    /// This function is supposed to set the aperture. It is optional
+    pub fn aperture(&mut self, aperture: f32) -> &mut Self {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Sets how the camera looks, see [PostProcessSettings]
+    pub fn post(&mut self, post: PostProcessSettings) -> &mut Self {
+        self.post = post;
+        self
+    }
+
     /// This function is supposed to set the shader_program. It must be called
     pub fn shader_program(&mut self, shader_program: ShaderProgram) -> &mut Self {
         self.shader_program = Some(shader_program);
@@ -95,7 +127,10 @@ impl CameraSettingsBuilder {
             sensitivity: self.sensitivity,
             near_plane: 0.1,
             far_plane: 100.0,
-            shader_program: self.shader_program.expect("Error: argument shadeer program is not satisfied\nhelp: you can call .shader_program"),
+            focus_distance: self.focus_distance,
+            aperture: self.aperture,
+            post: self.post,
+            shader_program: self.shader_program.clone().expect("Error: argument shadeer program is not satisfied\nhelp: you can call .shader_program"),
         }
     }
 }
@@ -109,6 +144,9 @@ impl Default for CameraSettingsBuilder {
             sensitivity: 1.0,
             near_plane: 0.1,
             far_plane: 100.0,
+            focus_distance: 10.0,
+            aperture: 0.0,
+            post: PostProcessSettings::default(),
             shader_program: None,
         }
     }
@@ -127,7 +165,7 @@ impl Default for CameraSettingsBuilder {
 /// ```
 /// let camera = Camera::new(pos, rot, settings);
 /// ```
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct CameraSettings {
     /// This field is supposed to store the width of the screen
     pub screen_size: Vec2,
@@ -139,6 +177,16 @@ pub struct CameraSettings {
     pub near_plane: f32,
     /// anything above this value will be clipped
     pub far_plane: f32,
+    /// How far away things are in focus, used by
+    /// [crate::graphics::depth_of_field::DepthOfField]
+    pub focus_distance: f32,
+    /// How blurry things out of focus get, 0 turns depth of field off
+    pub aperture: f32,
+    /// How the camera looks, its exposure, white balance and bloom
+    ///
+    /// Nothing reads it on its own, the game puts it on its stack with
+    /// [PostProcessSettings::apply] before it draws with this camera
+    pub post: PostProcessSettings,
     /// the shader program
     pub shader_program: ShaderProgram,
 }
@@ -180,6 +228,11 @@ pub trait CameraTrait<GameObject: GameObjectTrait + Sized>: Object<GameObject> {
         .set_uniform_matrix(false, (proj * view).into())
     }
 
+    /// Gets how much the mouse moved this frame, scaled by the sensitivity of the camera
+    fn look_delta(&self, mouse: &Mouse) -> Vec2 {
+        mouse.scaled_delta(self.get_camera_settings().sensitivity)
+    }
+
     /// Get the camera settings
     fn get_camera_settings(&self) -> CameraSettings;
 
@@ -0,0 +1,128 @@
+use nalgebra_glm::*;
+
+/// An orthographic camera for 2d games
+///
+/// World units go up and to the right, the camera looks at pos and shows viewport / zoom
+/// world units. Insert it as a resource of the [ecs](super::world::World::ecs) so the 2d
+/// systems(e.g. [ParallaxLayer](super::parallax::ParallaxLayer)) can follow it
+///
+/// For pixel art set [Camera2D::pixel_grid] to the size of one pixel of the art, then the
+/// camera only moves by whole pixels so sprites don't shimmer when it moves. Give the same
+/// grid to the [SpriteBatcher](crate::graphics::sprite::SpriteBatcher) so sprites are
+/// snapped too
+///
+/// # Example
+/// ```
+/// world.ecs.insert(Camera2D::new(world.env.win_size));
+///
+/// 'main_loop: loop {
+///     world.ecs.write_resource::<Camera2D>().pos = player_pos;
+///     world.update();
+///     sprites.push_world(&world.ecs);
+///     sprites.flush(&world.ecs.read_resource::<Camera2D>().view_proj());
+/// }
+///
+/// // pixel art drawn 3 times as big, one pixel of a texture is one world unit
+/// let camera = Camera2D::new(world.env.win_size).zoom(3.0).pixel_grid(1.0);
+/// sprites.pixel_grid = camera.pixel_grid;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    /// The point the camera looks at(in world units)
+    pub pos: Vec2,
+    /// How many pixels one world unit takes
+    pub zoom: f32,
+    /// Counter clockwise rotation of the camera(in radians)
+    pub rotation: f32,
+    /// Size of the area the camera draws into(in pixels)
+    pub viewport: Vec2,
+    /// The size of a virtual pixel the camera snaps to(in world units), None doesn't snap
+    ///
+    /// It should be a whole number of screen pixels, i.e. the grid times the zoom should be
+    /// a whole number
+    pub pixel_grid: Option<f32>,
+}
+
+impl Camera2D {
+    /// Creates a new camera looking at the origin, viewport is the size of the window
+    pub fn new(viewport: Vec2) -> Self {
+        Camera2D {
+            pos: vec2(0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport,
+            pixel_grid: None,
+        }
+    }
+
+    /// Sets the zoom
+    pub fn zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Sets the pixel grid
+    pub fn pixel_grid(mut self, pixel_grid: f32) -> Self {
+        self.pixel_grid = Some(pixel_grid);
+        self
+    }
+
+    /// Gets pos moved to the closest corner of the pixel grid, pos as it is without a grid
+    pub fn snap(&self, pos: Vec2) -> Vec2 {
+        match self.pixel_grid {
+            Some(grid) if grid > 0.0 => (pos / grid).map(f32::round) * grid,
+            _ => pos,
+        }
+    }
+
+    /// Gets how much of the world the camera shows(in world units)
+    pub fn view_size(&self) -> Vec2 {
+        self.viewport / self.zoom
+    }
+
+    /// Gets the bottom left and top right corners of the box around everything the camera
+    /// shows, rotation is taken into account
+    pub fn visible_bounds(&self) -> (Vec2, Vec2) {
+        let half = self.view_size() / 2.0;
+        let (sin, cos) = self.rotation.sin_cos();
+        let extent = vec2(
+            half.x * cos.abs() + half.y * sin.abs(),
+            half.x * sin.abs() + half.y * cos.abs(),
+        );
+        (self.pos - extent, self.pos + extent)
+    }
+
+    /// Gets the matrix that turns world positions into clip space
+    pub fn view_proj(&self) -> Mat4 {
+        let half = self.view_size() / 2.0;
+        let proj = ortho(-half.x, half.x, -half.y, half.y, -1.0, 1.0);
+        let mut pos = self.snap(self.pos);
+        if self.pixel_grid.is_some() {
+            // with an odd viewport the middle is in the middle of a screen pixel, so the
+            // grid is moved half a pixel to line up with the screen pixels again
+            let odd = self.viewport.map(|size| (size.round() as i32 % 2) as f32);
+            pos += odd * 0.5 / self.zoom;
+        }
+        let view = rotate_z(&identity(), -self.rotation)
+            * translate(&identity(), &vec3(-pos.x, -pos.y, 0.0));
+        proj * view
+    }
+
+    /// Turns a position on the screen(in pixels from the top left) into a world position
+    pub fn screen_to_world(&self, screen: Vec2) -> Vec2 {
+        let centered = vec2(
+            screen.x - self.viewport.x / 2.0,
+            self.viewport.y / 2.0 - screen.y,
+        ) / self.zoom;
+        rotate_vec2(&centered, self.rotation) + self.pos
+    }
+
+    /// Turns a world position into a position on the screen(in pixels from the top left)
+    pub fn world_to_screen(&self, world: Vec2) -> Vec2 {
+        let centered = rotate_vec2(&(world - self.pos), -self.rotation) * self.zoom;
+        vec2(
+            centered.x + self.viewport.x / 2.0,
+            self.viewport.y / 2.0 - centered.y,
+        )
+    }
+}
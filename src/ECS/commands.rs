@@ -0,0 +1,148 @@
+use specs::{Builder, Component, Entity, EntityBuilder, WorldExt};
+
+use super::world::{GameObjectTrait, World};
+
+/// A change to the world that is applied after every object has updated
+pub type Command<GameObject> = Box<dyn FnOnce(&mut World<GameObject>)>;
+
+/// Changes to the world that are applied at a safe point, after every object has updated
+///
+/// Objects get the commands through [UpdateCtx::commands](super::context::UpdateCtx::commands).
+/// Systems should use specs' [LazyUpdate](specs::LazyUpdate) instead, it is applied at the
+/// same point when the world calls [maintain](specs::WorldExt::maintain)
+///
+/// # Example
+/// ```
+/// // spawn a bullet entity
+/// ctx.commands.spawn(|builder| builder.with(Position(pos)).with(Bullet));
+///
+/// // despawn an enemy and change a resource
+/// ctx.commands.despawn(enemy);
+/// ctx.commands.insert_resource(Score(10));
+///
+/// // slow motion
+/// ctx.commands.set_time_scale(0.25);
+///
+/// // change the game objects directly
+/// ctx.commands.push(|world| world.objects.bullets.push(Bullet::new(pos)));
+/// ```
+pub struct Commands<GameObject: GameObjectTrait> {
+    commands: Vec<Command<GameObject>>,
+}
+
+impl<GameObject: GameObjectTrait> Commands<GameObject> {
+    /// Creates a new empty command queue
+    pub fn new() -> Self {
+        Commands {
+            commands: Vec::new(),
+        }
+    }
+
+    /// Queues any change to the world
+    pub fn push(&mut self, command: impl FnOnce(&mut World<GameObject>) + 'static) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// Queues the creation of an entity, build adds the components to the entity
+    pub fn spawn<F>(&mut self, build: F)
+    where
+        F: for<'w> FnOnce(EntityBuilder<'w>) -> EntityBuilder<'w> + 'static,
+    {
+        self.push(move |world| {
+            build(world.ecs.create_entity()).build();
+        });
+    }
+
+    /// Queues the deletion of an entity and all its components
+    ///
+    /// Entities that were already deleted are ignored
+    pub fn despawn(&mut self, entity: Entity) {
+        self.push(move |world| {
+            let _ = world.ecs.delete_entity(entity);
+        });
+    }
+
+    /// Queues the insertion of a component into an entity, replacing the old one
+    ///
+    /// Nothing is inserted if the entity is dead by the time the command is applied. The
+    /// component is registered if it wasn't yet
+    pub fn insert<C>(&mut self, entity: Entity, component: C)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default,
+    {
+        self.push(move |world| {
+            world.ecs.register::<C>();
+            let _ = world.ecs.write_storage::<C>().insert(entity, component);
+        });
+    }
+
+    /// Queues the removal of a component from an entity, the component is registered if
+    /// it wasn't yet
+    pub fn remove<C>(&mut self, entity: Entity)
+    where
+        C: Component + Send + Sync,
+        C::Storage: Default,
+    {
+        self.push(move |world| {
+            world.ecs.register::<C>();
+            world.ecs.write_storage::<C>().remove(entity);
+        });
+    }
+
+    /// Queues the insertion of a resource, replacing the old one
+    pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) {
+        self.push(move |world| world.ecs.insert(resource));
+    }
+
+    /// Queues the removal of a resource
+    pub fn remove_resource<R: Send + Sync + 'static>(&mut self) {
+        self.push(move |world| {
+            world.ecs.remove::<R>();
+        });
+    }
+
+    /// Queues a change of how fast game time goes, see [Time::scale](super::time::Time::scale)
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.push(move |world| world.env.time.scale = scale);
+    }
+
+    /// Queues pausing game time, see [Time::pause](super::time::Time::pause)
+    pub fn pause(&mut self) {
+        self.push(|world| world.env.time.pause());
+    }
+
+    /// Queues resuming game time, see [Time::resume](super::time::Time::resume)
+    pub fn resume(&mut self) {
+        self.push(|world| world.env.time.resume());
+    }
+
+    /// Queues pausing or resuming game time, see
+    /// [Time::toggle_pause](super::time::Time::toggle_pause)
+    pub fn toggle_pause(&mut self) {
+        self.push(|world| world.env.time.toggle_pause());
+    }
+
+    /// Queues turning frame stepping on or off, see [FrameStep](super::frame_step::FrameStep)
+    pub fn toggle_frame_step(&mut self) {
+        self.push(|world| world.env.frame_step.toggle());
+    }
+
+    /// Checks if there are no queued commands
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Applies every queued command to the world in the order they were queued
+    pub fn apply(&mut self, world: &mut World<GameObject>) {
+        for command in self.commands.drain(..) {
+            command(world);
+        }
+    }
+}
+
+impl<GameObject: GameObjectTrait> Default for Commands<GameObject> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,59 @@
+use super::{
+    commands::Commands, object::Input, time::Time, window::WindowStateEvent,
+    world::GameObjectTrait,
+};
+use crate::{
+    graphics::{recovery::ContextEvent, window::WindowEvent},
+    profiler::Profiler,
+};
+
+/// Everything an object is allowed to see while it is updating
+///
+/// Objects dont get the whole [World](super::world::World), so they cannot change the structure of the world
+/// while it is updating. Structural changes like spawning and despawning are queued in
+/// [UpdateCtx::commands] and are applied after every object has updated
+pub struct UpdateCtx<'a, GameObject: GameObjectTrait> {
+    /// Time of the world
+    pub time: &'a Time,
+    /// The input for this frame
+    pub input: Input<'a>,
+    /// The events that happened since the last update
+    pub events: &'a [WindowEvent],
+    /// The focus, minimize and resize events that happened since the last update
+    pub window_events: &'a [WindowStateEvent],
+    /// Whether the opengl context was lost or restored since the last update, anything
+    /// uploaded by hand has to be uploaded again on [ContextEvent::Restored]
+    pub context_events: &'a [ContextEvent],
+    /// Changes that will be applied to the world after the update
+    pub commands: &'a mut Commands<GameObject>,
+    /// The profiler of the world if it has one, see [UpdateCtx::begin_section]
+    pub profiler: Option<&'a mut Profiler>,
+}
+
+impl<'a, GameObject: GameObjectTrait> UpdateCtx<'a, GameObject> {
+    /// Starts timing a section called name inside of `update/objects`, does nothing without
+    /// a profiler
+    ///
+    /// # Example
+    /// ```
+    /// fn update(&mut self, ctx: &mut UpdateCtx<Self>) {
+    ///     ctx.begin_section("enemies");
+    ///     for enemy in &mut self.enemies {
+    ///         enemy.update(ctx);
+    ///     }
+    ///     ctx.end_section("enemies");
+    /// }
+    /// ```
+    pub fn begin_section(&mut self, name: &str) {
+        if let Some(profiler) = self.profiler.as_deref_mut() {
+            profiler.begin(name);
+        }
+    }
+
+    /// Stops timing the section called name, see [UpdateCtx::begin_section]
+    pub fn end_section(&mut self, name: &str) {
+        if let Some(profiler) = self.profiler.as_deref_mut() {
+            profiler.end(name);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use device_query::Keycode;
+
+/// How the world updates, see [FrameStep::take_step]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepUpdate {
+    /// Frame stepping is off, the world updates like normal
+    Normal,
+    /// The world takes a step with a fixed delta(in seconds)
+    Step(f32),
+    /// The world waits for a step, only the input and real time are updated
+    Skip,
+}
+
+/// A debug mode where the world only updates when it is stepped, one update at a time
+///
+/// While it is on [World::update](super::world::World::update) skips the objects, the
+/// commands and the ecs, but the input and real time keep going so the game can still be
+/// drawn and looked around in. The window events wait for the next step, mouse movement and
+/// scrolling are merged while they wait and only the newest ones are kept. Every step
+/// updates the world once with a fixed delta, so fast movement and physics can be followed
+/// frame by frame
+///
+/// It is turned on and stepped with keys or through [Enviroment::frame_step](super::world::Enviroment::frame_step).
+/// Objects can turn it on with [Commands::toggle_frame_step](super::commands::Commands::toggle_frame_step),
+/// but they aren't updated while the world waits for a step
+///
+/// # Example
+/// ```
+/// world.env.frame_step = FrameStep::new().keys(Keycode::F9, Keycode::F10);
+///
+/// // or from a debug menu
+/// world.env.frame_step.toggle();
+/// world.env.frame_step.step(10);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrameStep {
+    /// Whether frame stepping is on
+    pub enabled: bool,
+    /// The key that turns frame stepping on and off
+    pub toggle_key: Option<Keycode>,
+    /// The key that steps one frame
+    pub step_key: Option<Keycode>,
+    /// The delta every step updates the world with(in seconds)
+    pub step_delta: f32,
+    /// How many steps are waiting to be taken
+    pending: u32,
+    /// The keys that were down last update, so holding a key only counts once
+    last_keys: Vec<Keycode>,
+}
+
+impl FrameStep {
+    /// Creates a new frame step that is off, without keys and steps 1/60 of a second
+    pub fn new() -> Self {
+        FrameStep {
+            enabled: false,
+            toggle_key: None,
+            step_key: None,
+            step_delta: 1.0 / 60.0,
+            pending: 0,
+            last_keys: Vec::new(),
+        }
+    }
+
+    /// Sets the keys that turn frame stepping on and off and that step one frame
+    pub fn keys(mut self, toggle: Keycode, step: Keycode) -> Self {
+        self.toggle_key = Some(toggle);
+        self.step_key = Some(step);
+        self
+    }
+
+    /// Sets the delta every step updates the world with(in seconds)
+    pub fn step_delta(mut self, step_delta: f32) -> Self {
+        self.step_delta = step_delta;
+        self
+    }
+
+    /// Turns frame stepping on and off, steps that are waiting are dropped
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.pending = 0;
+    }
+
+    /// Queues frames steps, does nothing while frame stepping is off
+    pub fn step(&mut self, frames: u32) {
+        if self.enabled {
+            self.pending += frames;
+        }
+    }
+
+    /// Gets how many steps are waiting to be taken
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+
+    /// Handles the keys that are down this update, a key counts when it is pressed
+    pub fn handle_keys(&mut self, keys: &[Keycode]) {
+        let pressed = |key: Option<Keycode>| {
+            key.is_some_and(|key| keys.contains(&key) && !self.last_keys.contains(&key))
+        };
+        let (toggle, step) = (pressed(self.toggle_key), pressed(self.step_key));
+        if toggle {
+            self.toggle();
+        }
+        if step {
+            self.step(1);
+        }
+        self.last_keys.clear();
+        self.last_keys.extend_from_slice(keys);
+    }
+
+    /// Works out how the world updates this time, taking a step if one is waiting
+    pub fn take_step(&mut self) -> StepUpdate {
+        if !self.enabled {
+            return StepUpdate::Normal;
+        }
+        if self.pending == 0 {
+            return StepUpdate::Skip;
+        }
+        self.pending -= 1;
+        StepUpdate::Step(self.step_delta)
+    }
+}
+
+impl Default for FrameStep {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::Instant;
+
+use super::world::{GameObjectTrait, World};
+
+/// Runs the world at a fixed rate and draws it as often as it can, so movement doesn't
+/// depend on the frame rate
+///
+/// Every frame the real time since the last frame is added up and the world is updated
+/// with [World::fixed_update] once for every [GameLoop::step] that fits in it. What is left
+/// over is the alpha, how far the frame is between the last update and the next one, it is
+/// put in [Time::alpha](super::time::Time::alpha) so positions can be drawn in between
+/// with `mix(previous, current, alpha)`
+///
+/// When the world can't keep up, e.g. after a long load, at most [GameLoop::max_steps]
+/// updates are run in a frame and the rest of the time is dropped, so the game slows down
+/// instead of freezing
+///
+/// # Example
+/// ```
+/// let mut game_loop = GameLoop::new(60.0);
+///
+/// 'main_loop: loop {
+///     if world.poll_events() {
+///         break 'main_loop;
+///     }
+///     game_loop.frame(
+///         &mut world,
+///         |world| physics.step(&mut world.ecs),
+///         |world, alpha| world.objects.player.draw_pos = world.objects.player.lerp_pos(alpha),
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct GameLoop {
+    /// The time between fixed updates(in seconds)
+    pub step: f32,
+    /// The most updates run in a single frame
+    pub max_steps: u32,
+    /// The real time that hasn't been updated yet(in seconds)
+    accumulator: f32,
+    /// When the last frame began
+    last_frame: Option<Instant>,
+}
+
+impl GameLoop {
+    /// Creates a game loop that updates updates_per_second times a second
+    pub fn new(updates_per_second: f32) -> Self {
+        GameLoop {
+            step: 1.0 / updates_per_second.max(0.001),
+            max_steps: 5,
+            accumulator: 0.0,
+            last_frame: None,
+        }
+    }
+
+    /// Sets the most updates run in a single frame
+    pub fn max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Adds delta(in seconds) to the time that hasn't been updated yet and gives how many
+    /// fixed updates have to run for it
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta.max(0.0);
+        let steps = (self.accumulator / self.step) as u32;
+        if steps > self.max_steps {
+            // drop what can't be caught up with
+            self.accumulator = self.step * 0.999;
+            return self.max_steps;
+        }
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    /// Gets how far the time that is left is between the last update and the next one,
+    /// from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    /// Forgets the time that hasn't been updated yet, e.g. after loading a level so the
+    /// loading time doesn't have to be caught up with
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last_frame = None;
+    }
+
+    /// Runs one frame, the world is updated the number of times the real time since the
+    /// last frame needs, fixed_update is called after every update
+    ///
+    /// Then the alpha is put in [Time::alpha](super::time::Time::alpha), render is called
+    /// with it and the world is rendered with [World::render]. The first frame only draws
+    pub fn frame<GameObject: GameObjectTrait>(
+        &mut self,
+        world: &mut World<GameObject>,
+        mut fixed_update: impl FnMut(&mut World<GameObject>),
+        mut render: impl FnMut(&mut World<GameObject>, f32),
+    ) {
+        let now = Instant::now();
+        let delta = self
+            .last_frame
+            .map_or(0.0, |last_frame| (now - last_frame).as_secs_f32());
+        self.last_frame = Some(now);
+
+        for _ in 0..self.advance(delta) {
+            world.fixed_update(self.step);
+            fixed_update(world);
+        }
+        let alpha = self.alpha();
+        world.env.time.alpha = alpha;
+        render(world, alpha);
+        world.render();
+    }
+}
@@ -0,0 +1,95 @@
+use device_query::{DeviceQuery, DeviceState, Keycode};
+
+/// The keyboard, it remembers the keys of the last update so a key that was just pressed
+/// can be told apart from a key that is held
+///
+/// The world updates [Enviroment::keyboard](super::world::Enviroment) at the start of
+/// every update, objects get it through [Input::keyboard](super::object::Input)
+///
+/// # Example
+/// ```
+/// impl ControllableKey for Player {
+///     fn on_key(&mut self, input: &mut Input) {
+///         if input.keyboard.just_pressed(Keycode::Space) {
+///             self.jump();
+///         }
+///         if input.keyboard.held(Keycode::D) {
+///             self.set_pos().x += 0.01;
+///         }
+///         if input.keyboard.just_released(Keycode::LShift) {
+///             self.stop_sprinting();
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Keyboard {
+    /// The keys that are down this update
+    keys: Vec<Keycode>,
+    /// The keys that were down the update before
+    last_keys: Vec<Keycode>,
+}
+
+impl Keyboard {
+    /// Creates a keyboard without keys down
+    pub fn new() -> Self {
+        Keyboard {
+            keys: Vec::new(),
+            last_keys: Vec::new(),
+        }
+    }
+
+    /// Sets the keys that are down this update, the keys before are remembered
+    pub fn update(&mut self, keys: &[Keycode]) {
+        std::mem::swap(&mut self.keys, &mut self.last_keys);
+        self.keys.clear();
+        self.keys.extend_from_slice(keys);
+    }
+
+    /// Sets the keys that are down this update to the keys device has down
+    pub fn update_from(&mut self, device: &DeviceState) {
+        self.update(&device.get_keys());
+    }
+
+    /// Checks if key is down this update but wasn't the update before
+    pub fn just_pressed(&self, key: Keycode) -> bool {
+        self.keys.contains(&key) && !self.last_keys.contains(&key)
+    }
+
+    /// Checks if key is down this update, no matter when it was pressed
+    pub fn held(&self, key: Keycode) -> bool {
+        self.keys.contains(&key)
+    }
+
+    /// Checks if key was down the update before but isn't this update
+    pub fn just_released(&self, key: Keycode) -> bool {
+        !self.keys.contains(&key) && self.last_keys.contains(&key)
+    }
+
+    /// Gets the keys that are down this update
+    pub fn keys(&self) -> &[Keycode] {
+        &self.keys
+    }
+
+    /// Gets the keys that were pressed this update
+    pub fn pressed(&self) -> impl Iterator<Item = &Keycode> {
+        self.keys.iter().filter(|key| !self.last_keys.contains(key))
+    }
+
+    /// Gets the keys that were released this update
+    pub fn released(&self) -> impl Iterator<Item = &Keycode> {
+        self.last_keys.iter().filter(|key| !self.keys.contains(key))
+    }
+
+    /// Forgets every key, e.g. when the window loses focus so no key stays held
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.last_keys.clear();
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
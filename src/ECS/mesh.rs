@@ -1,6 +1,12 @@
-use std::mem::size_of;
-
-use crate::graphics::{buffer::*, vertex::VertexArray, *};
+use crate::animation::morph::MorphTarget;
+use crate::graphics::{
+    arena::{with_frame_arena, FrameArena},
+    buffer::*,
+    shader::ShaderProgram,
+    uniform::Uniform,
+    vertex::{AttrFormat, VertexArray},
+    *,
+};
 use ogl33::*;
 
 use super::*;
@@ -143,7 +149,137 @@ pub trait VertexTrait: Copy {
     fn get_vertex(&self, pos: Vec3, rot: Vec4) -> Self;
 }
 
+/// A vertex that knows the sizes of its attributes, so a [Mesh] of it can be made with
+/// [Mesh::from_vertices] without giving them
+///
+/// The attributes have to add up to [VertexTrait::SIZE], when they don't the mesh doesn't
+/// compile instead of [Mesh::new] failing when the game runs
+///
+/// # Example
+/// ```
+/// impl VertexLayout for Vertex {
+///     // a vec3 position and a vec2 uv
+///     const ATTRIBUTES: &'static [u32] = &[3, 2];
+/// }
+///
+/// let mesh = Mesh::from_vertices(vertices, indices);
+/// ```
+pub trait VertexLayout: VertexTrait {
+    /// The number of elements in every attribute, in the order of [VertexTrait::as_list]
+    const ATTRIBUTES: &'static [u32];
+
+    /// Fails to compile when [VertexLayout::ATTRIBUTES] doesn't add up to [VertexTrait::SIZE]
+    const CHECK: () = assert!(
+        attributes_size(Self::ATTRIBUTES) == Self::SIZE,
+        "The vertex attributes must add up to VertexTrait::SIZE"
+    );
+}
+
+/// Adds up the sizes of the attributes, it is const so [VertexLayout::CHECK] can use it
+const fn attributes_size(attributes: &[u32]) -> u32 {
+    let mut size = 0;
+    let mut i = 0;
+    while i < attributes.len() {
+        size += attributes[i];
+        i += 1;
+    }
+    size
+}
+
+/// The attribute location vertex colors are bound to, see [Mesh::with_color_attr]
+///
+/// Shaders that want vertex colors should declare
+/// `layout (location = 15) in vec4 vertex_color;`, meshes without colors give them white
+pub const COLOR_LOCATION: u32 = 15;
+
+/// The attribute location lightmap uvs are bound to, see [Mesh::with_lightmap_uv_attr]
+///
+/// Shaders that read lightmaps should declare `layout (location = 14) in vec2 lightmap_uv;`
+pub const LIGHTMAP_UV_LOCATION: u32 = 14;
+
+/// A part of a [Mesh] that is drawn with its own material
+///
+/// Models with several materials keep all of their vertices and indices in the buffers of
+/// one mesh, every sub mesh is a range of the indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubMesh {
+    /// The first index of the sub mesh(counted in indices, not triangles)
+    pub first: usize,
+    /// How many indices the sub mesh has
+    pub count: usize,
+    /// Added to every index of the sub mesh, so sub meshes can index their vertices from 0
+    pub base_vertex: i32,
+    /// The index of the material the sub mesh is drawn with
+    pub material: usize,
+}
+
+/// Position, rotation and scale of a 3d mesh, turned into its model matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform3D {
+    /// Position of the mesh(in world units)
+    pub pos: Vec3,
+    /// Rotation of the mesh, xyz is the axis and w is the angle(in radians) like [PosRot]
+    pub rot: Vec4,
+    /// Scale of the mesh on every axis
+    pub scale: Vec3,
+}
+
+impl Transform3D {
+    /// Creates a new transform at pos with no rotation and a scale of 1
+    pub fn new(pos: Vec3) -> Self {
+        Transform3D {
+            pos,
+            rot: vec4(0.0, 1.0, 0.0, 0.0),
+            scale: vec3(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Sets the rotation, xyz is the axis and w is the angle(in radians)
+    pub fn rot(mut self, rot: Vec4) -> Self {
+        self.rot = rot;
+        self
+    }
+
+    /// Sets the scale on every axis
+    pub fn scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Gets the model matrix, it scales, then rotates and then moves
+    pub fn matrix(&self) -> Mat4 {
+        let mut model = translation(&self.pos);
+        let axis = self.rot.xyz();
+        // an axis of length 0 can't be normalized, it is treated as no rotation
+        if self.rot.w != 0.0 && axis.norm_squared() > 0.0 {
+            model = rotate(&model, self.rot.w, &axis);
+        }
+        scale(&model, &self.scale)
+    }
+}
+
+impl Default for Transform3D {
+    fn default() -> Self {
+        Self::new(vec3(0.0, 0.0, 0.0))
+    }
+}
+
 /// Mesh for your object
+///
+/// A mesh is drawn in 3d with the model matrix of its [Mesh::transform], upload it once with
+/// [Mesh::upload] and draw it with [Renderer::draw_mesh](crate::graphics::renderer::Renderer::draw_mesh)
+///
+/// # Example
+/// ```
+/// let mut cube = Mesh::new(vertices, vec![3, 2], indices)?
+///     .with_transform(Transform3D::new(vec3(0.0, 1.0, 0.0)).scale(vec3(2.0, 2.0, 2.0)));
+/// cube.upload();
+///
+/// 'main_loop: loop {
+///     cube.transform.rot.w += 0.01;
+///     renderer.draw_mesh(&program, &cube);
+/// }
+/// ```
 #[derive(Component)]
 pub struct Mesh<Vertex: VertexTrait + 'static + Sync + Send> {
     /// The vertices of your object
@@ -165,26 +301,47 @@ pub struct Mesh<Vertex: VertexTrait + 'static + Sync + Send> {
     /// }
     /// ```
     pub indicies: Vec<[u32; 3]>,
+    /// Which of the vertex attributes is the color of the vertex, if any
+    pub color_attr: Option<usize>,
+    /// Which of the vertex attributes is the lightmap uv of the vertex, if any
+    pub lightmap_uv_attr: Option<usize>,
+    /// The format every vertex attribute is stored in on the gpu, see [Mesh::with_attr_formats]
+    pub attr_formats: Vec<AttrFormat>,
+    /// The parts of the mesh that use different materials, see [Mesh::with_sub_meshes]
+    pub sub_meshes: Vec<SubMesh>,
+    /// The shapes the mesh can blend towards, see [Mesh::with_morph_targets]
+    pub morph_targets: Vec<MorphTarget>,
+    /// Where the mesh is in the world, it is uploaded as the model matrix
+    pub transform: Transform3D,
     vao: VertexArray,
     vbo: Buffer,
     ebo: Buffer,
 }
 
 impl<Vertex: VertexTrait + 'static + Sync + Send> Mesh<Vertex> {
-    /// Creates a new Mesh
+    /// Creates a new Mesh, the sizes in vert_attr have to add up to the size of a vertex
+    ///
+    /// A vertex with a [VertexLayout] can use [Mesh::from_vertices] instead, which checks
+    /// the sizes when it is compiled
     pub fn new(
         vert: Vec<Vertex>,
         vert_attr: Vec<u32>,
         index: Vec<[u32; 3]>,
-    ) -> Result<Mesh<Vertex>, String> {
+    ) -> Result<Mesh<Vertex>, LighthouseError> {
         if vert[0].as_list().len() != (&vert_attr).iter().sum::<u32>().try_into().unwrap() {
-            return Err(format!("The sum of the vertex attributes {} must be equal to the number of element in the vertex {}", (&vert_attr).iter().sum::<u32>(), vert[0].as_list().len()));
+            return Err(LighthouseError::InvalidInput(format!("The sum of the vertex attributes {} must be equal to the number of element in the vertex {}", (&vert_attr).iter().sum::<u32>(), vert[0].as_list().len())));
         }
 
         let out = Mesh {
             vertices: vert,
+            attr_formats: vec![AttrFormat::Float; vert_attr.len()],
             vert_attr,
             indicies: index,
+            color_attr: None,
+            lightmap_uv_attr: None,
+            sub_meshes: Vec::new(),
+            morph_targets: Vec::new(),
+            transform: Transform3D::default(),
             vao: VertexArray::new().expect("Couldn't make a VAO"),
             vbo: Buffer::new().expect("Couldn't make a VBO"),
             ebo: Buffer::new().expect("Couldn't make EBO"),
@@ -193,57 +350,379 @@ impl<Vertex: VertexTrait + 'static + Sync + Send> Mesh<Vertex> {
         Ok(out)
     }
 
+    /// Creates a new Mesh of a vertex with a [VertexLayout]
+    ///
+    /// Unlike [Mesh::new] it can't fail, the attributes are checked when it is compiled
+    pub fn from_vertices(vert: Vec<Vertex>, index: Vec<[u32; 3]>) -> Mesh<Vertex>
+    where
+        Vertex: VertexLayout,
+    {
+        #[allow(clippy::let_unit_value)]
+        let () = Vertex::CHECK;
+
+        Mesh {
+            vertices: vert,
+            attr_formats: vec![AttrFormat::Float; Vertex::ATTRIBUTES.len()],
+            vert_attr: Vertex::ATTRIBUTES.to_vec(),
+            indicies: index,
+            color_attr: None,
+            lightmap_uv_attr: None,
+            sub_meshes: Vec::new(),
+            morph_targets: Vec::new(),
+            transform: Transform3D::default(),
+            vao: VertexArray::new().expect("Couldn't make a VAO"),
+            vbo: Buffer::new().expect("Couldn't make a VBO"),
+            ebo: Buffer::new().expect("Couldn't make EBO"),
+        }
+    }
+
+    /// Marks the vertex attribute at index attr as the color of the vertex
+    ///
+    /// The attribute is bound to [COLOR_LOCATION] instead of its index and has to have 3(rgb)
+    /// or 4(rgba) elements
+    ///
+    /// # Example
+    /// ```
+    /// // position, uv and color
+    /// let mesh = Mesh::new(vertices, vec![3, 2, 4], indices)?.with_color_attr(2)?;
+    /// ```
+    pub fn with_color_attr(mut self, attr: usize) -> Result<Self, LighthouseError> {
+        match self.vert_attr.get(attr) {
+            Some(3) | Some(4) => {
+                self.color_attr = Some(attr);
+                Ok(self)
+            }
+            Some(size) => Err(LighthouseError::InvalidInput(format!(
+                "A color attribute must have 3 or 4 elements, attribute {} has {}",
+                attr, size
+            ))),
+            None => Err(LighthouseError::InvalidInput(format!(
+                "Attribute {} doesn't exist, the mesh has {} attributes",
+                attr,
+                self.vert_attr.len()
+            ))),
+        }
+    }
+
+    /// Sets the format every vertex attribute is stored in, by default they are all floats
+    ///
+    /// The vertices are packed into the formats when they are uploaded
+    ///
+    /// # Example
+    /// ```
+    /// // half float positions and uvs and packed normals
+    /// let mesh = Mesh::new(vertices, vec![3, 2, 3], indices)?.with_attr_formats(vec![
+    ///     AttrFormat::HalfFloat,
+    ///     AttrFormat::HalfFloat,
+    ///     AttrFormat::Int2101010Rev,
+    /// ])?;
+    /// ```
+    pub fn with_attr_formats(mut self, formats: Vec<AttrFormat>) -> Result<Self, LighthouseError> {
+        if formats.len() != self.vert_attr.len() {
+            return Err(LighthouseError::InvalidInput(format!(
+                "There are {} formats but the mesh has {} attributes",
+                formats.len(),
+                self.vert_attr.len()
+            )));
+        }
+        for (i, (format, size)) in formats.iter().zip(&self.vert_attr).enumerate() {
+            if *format == AttrFormat::Int2101010Rev && *size > 4 {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "Attribute {} has {} elements but a packed attribute can have at most 4",
+                    i, size
+                )));
+            }
+        }
+
+        self.attr_formats = formats;
+        Ok(self)
+    }
+
+    /// Splits the mesh into sub meshes that can be drawn with different materials
+    ///
+    /// # Example
+    /// ```
+    /// // the first 2 triangles are wood and the other 10 are metal
+    /// let mesh = Mesh::new(vertices, vec![3, 2], indices)?.with_sub_meshes(vec![
+    ///     SubMesh { first: 0, count: 6, base_vertex: 0, material: 0 },
+    ///     SubMesh { first: 6, count: 30, base_vertex: 0, material: 1 },
+    /// ])?;
+    /// ```
+    pub fn with_sub_meshes(mut self, sub_meshes: Vec<SubMesh>) -> Result<Self, LighthouseError> {
+        let index_count = self.indicies.len() * 3;
+        for (i, sub_mesh) in sub_meshes.iter().enumerate() {
+            if sub_mesh.first + sub_mesh.count > index_count {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "Sub mesh {} goes up to index {} but the mesh only has {} indices",
+                    i,
+                    sub_mesh.first + sub_mesh.count,
+                    index_count
+                )));
+            }
+        }
+
+        self.sub_meshes = sub_meshes;
+        Ok(self)
+    }
+
+    /// Gives the mesh morph targets, every target needs a delta for every vertex
+    ///
+    /// Upload them with [MorphBuffer](crate::animation::morph::MorphBuffer) to morph the
+    /// mesh in the vertex shader
+    pub fn with_morph_targets(mut self, targets: Vec<MorphTarget>) -> Result<Self, LighthouseError> {
+        for target in &targets {
+            if target.position_deltas.len() != self.vertices.len() {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "Morph target {} has {} deltas but the mesh has {} vertices",
+                    target.name,
+                    target.position_deltas.len(),
+                    self.vertices.len()
+                )));
+            }
+        }
+
+        self.morph_targets = targets;
+        Ok(self)
+    }
+
+    /// Gets the index of the morph target called name
+    pub fn find_morph_target(&self, name: &str) -> Option<usize> {
+        self.morph_targets
+            .iter()
+            .position(|target| target.name == name)
+    }
+
+    /// Marks the vertex attribute at index attr as the lightmap uv of the vertex
+    ///
+    /// The attribute is bound to [LIGHTMAP_UV_LOCATION] instead of its index and has to have
+    /// 2 elements, see [crate::graphics::lightmap::Lightmap]
+    ///
+    /// # Example
+    /// ```
+    /// // position, uv, normal and lightmap uv
+    /// let mesh = Mesh::new(vertices, vec![3, 2, 3, 2], indices)?.with_lightmap_uv_attr(3)?;
+    /// ```
+    pub fn with_lightmap_uv_attr(mut self, attr: usize) -> Result<Self, LighthouseError> {
+        match self.vert_attr.get(attr) {
+            Some(2) => {
+                self.lightmap_uv_attr = Some(attr);
+                Ok(self)
+            }
+            Some(size) => Err(LighthouseError::InvalidInput(format!(
+                "A lightmap uv attribute must have 2 elements, attribute {} has {}",
+                attr, size
+            ))),
+            None => Err(LighthouseError::InvalidInput(format!(
+                "Attribute {} doesn't exist, the mesh has {} attributes",
+                attr,
+                self.vert_attr.len()
+            ))),
+        }
+    }
+
+    /// Sets where the mesh is in the world
+    pub fn with_transform(mut self, transform: Transform3D) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Gets the model matrix of the mesh
+    pub fn model_matrix(&self) -> Mat4 {
+        self.transform.matrix()
+    }
+
+    /// Uploads the model matrix to the uniform called name of program, the program has to be
+    /// in use
+    pub fn set_model_uniform(&self, program: &ShaderProgram, name: &str) {
+        Uniform::new(program, name).set_mat4(&self.model_matrix());
+    }
+
+    /// Sets up the mesh and uploads its vertices and indices as they are, the
+    /// [Mesh::transform] is applied by the model matrix when it is drawn
+    ///
+    /// It has to be called again after the vertices or indices change
+    pub fn upload(&self) {
+        self.setup();
+        with_frame_arena(|arena| {
+            buffer_data(
+                BufferType::Array,
+                self.packed_vertices(arena, None),
+                BufferUsage::StaticDraw,
+            )
+        });
+        buffer_data(
+            BufferType::ElementArray,
+            bytemuck::cast_slice(&self.indicies),
+            BufferUsage::StaticDraw,
+        );
+        VertexArray::clear_binding();
+    }
+
+    /// Gets the size of a vertex on the gpu(in bytes)
+    pub fn stride(&self) -> usize {
+        self.vert_attr
+            .iter()
+            .zip(&self.attr_formats)
+            .map(|(size, format)| format.byte_size(*size))
+            .sum()
+    }
+
     /// Setsup the mesh, is used for macro
+    ///
+    /// If the mesh has no color attribute every vertex gets white as its color and without a
+    /// lightmap uv attribute every lightmap uv is 0
     pub fn setup(&self) {
         self.vao.bind();
         self.vbo.bind(BufferType::Array);
         self.ebo.bind(BufferType::ElementArray);
 
-        for (i, attr) in (&self.vert_attr).iter().enumerate() {
-            let pointer: u32 = size_of::<f32>().try_into().unwrap();
-            let pointer = pointer * self.vert_attr[0..i].iter().sum::<u32>();
+        let mut pointer = 0;
+        for (i, (attr, format)) in self.vert_attr.iter().zip(&self.attr_formats).enumerate() {
+            let location = if self.color_attr == Some(i) {
+                COLOR_LOCATION
+            } else if self.lightmap_uv_attr == Some(i) {
+                LIGHTMAP_UV_LOCATION
+            } else {
+                i.try_into().unwrap()
+            };
 
             unsafe {
                 glVertexAttribPointer(
-                    i.try_into().unwrap(),
-                    (*attr).try_into().unwrap(),
-                    GL_FLOAT,
-                    GL_FALSE,
-                    size_of::<Vertex>().try_into().unwrap(),
+                    location,
+                    format.gl_size(*attr),
+                    format.gl_type(),
+                    format.normalized() as u8,
+                    self.stride().try_into().unwrap(),
                     pointer as *const _,
                 );
 
-                glEnableVertexAttribArray(i.try_into().unwrap())
+                glEnableVertexAttribArray(location)
             }
+            pointer += format.byte_size(*attr);
+        }
+
+        if self.color_attr.is_none() {
+            unsafe {
+                glDisableVertexAttribArray(COLOR_LOCATION);
+                glVertexAttrib4f(COLOR_LOCATION, 1.0, 1.0, 1.0, 1.0);
+            }
+        }
+        if self.lightmap_uv_attr.is_none() {
+            unsafe {
+                glDisableVertexAttribArray(LIGHTMAP_UV_LOCATION);
+                glVertexAttrib2f(LIGHTMAP_UV_LOCATION, 0.0, 0.0);
+            }
+        }
+    }
+
+    /// Draws every triangle of the mesh
+    pub fn draw(&self) {
+        self.draw_range(0, self.indicies.len() * 3, 0);
+    }
+
+    /// Draws count indices starting from first, base_vertex is added to every index
+    pub fn draw_range(&self, first: usize, count: usize, base_vertex: i32) {
+        self.vao.bind();
+        unsafe {
+            glDrawElementsBaseVertex(
+                GL_TRIANGLES,
+                count.try_into().unwrap(),
+                GL_UNSIGNED_INT,
+                (first * std::mem::size_of::<u32>()) as *const _,
+                base_vertex,
+            );
+        }
+    }
+
+    /// Draws every triangle of the mesh instances times, the per instance attributes have to
+    /// be set up on the vertex array of the mesh first, see [Mesh::bind]
+    pub fn draw_instanced(&self, instances: usize) {
+        self.vao.bind();
+        unsafe {
+            glDrawElementsInstanced(
+                GL_TRIANGLES,
+                (self.indicies.len() * 3).try_into().unwrap(),
+                GL_UNSIGNED_INT,
+                std::ptr::null(),
+                instances.try_into().unwrap(),
+            );
         }
     }
 
+    /// Binds the vertex array of the mesh, so more attributes can be added to it
+    pub fn bind(&self) {
+        self.vao.bind();
+    }
+
+    /// Draws one of the sub meshes
+    pub fn draw_sub_mesh(&self, sub_mesh: &SubMesh) {
+        self.draw_range(sub_mesh.first, sub_mesh.count, sub_mesh.base_vertex);
+    }
+
+    /// Draws every sub mesh, bind_material is called with the material of each sub mesh
+    /// before it is drawn. A mesh without sub meshes is drawn with material 0
+    ///
+    /// # Example
+    /// ```
+    /// mesh.draw_with_materials(|material| textures[material].bind(TextureTarget::Texture2D));
+    /// ```
+    pub fn draw_with_materials(&self, mut bind_material: impl FnMut(usize)) {
+        if self.sub_meshes.is_empty() {
+            bind_material(0);
+            self.draw();
+            return;
+        }
+
+        for sub_mesh in &self.sub_meshes {
+            bind_material(sub_mesh.material);
+            self.draw_sub_mesh(sub_mesh);
+        }
+    }
+
+    /// Packs the vertices into the formats of the attributes, in the frame arena
+    ///
+    /// With a position and rotation the vertices are moved on the cpu first
+    fn packed_vertices<'a>(&self, arena: &'a FrameArena, pos_rot: Option<(Vec3, Vec4)>) -> &'a [u8] {
+        let out = arena.alloc::<u8>(self.vertices.len() * self.stride());
+        let mut offset = 0;
+        for vertex in &self.vertices {
+            let list = match pos_rot {
+                Some((pos, rot)) => vertex.get_vertex(pos, rot).as_list(),
+                None => vertex.as_list(),
+            };
+            let mut start = 0;
+            for (size, format) in self.vert_attr.iter().zip(&self.attr_formats) {
+                let end = start + *size as usize;
+                offset += format.pack_into(&list[start..end], &mut out[offset..]);
+                start = end;
+            }
+        }
+        out
+    }
+
     /// Updates the mesh
     fn update(&self, pos: Position, rot: Rotation) {
-        buffer_data(
-            BufferType::Array,
-            bytemuck::cast_slice(
-                &self
-                    .vertices
-                    .clone()
-                    .iter()
-                    .flat_map(|vertex| vertex.get_vertex(pos.0, rot.0).as_list())
-                    .collect::<Vec<f32>>(),
-            ),
-            GL_STATIC_DRAW,
-        );
+        with_frame_arena(|arena| {
+            buffer_data(
+                BufferType::Array,
+                self.packed_vertices(arena, Some((pos.0, rot.0))),
+                BufferUsage::StaticDraw,
+            )
+        });
         buffer_data(
             BufferType::ElementArray,
             bytemuck::cast_slice(&self.indicies),
-            GL_STATIC_DRAW,
+            BufferUsage::StaticDraw,
         );
     }
 }
-#[derive(Component)]
-struct Position(Vec3);
+/// The position of an entity with a [Mesh]
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Position(pub Vec3);
 
-#[derive(Component)]
-struct Rotation(Vec4);
+/// The rotation of an entity with a [Mesh], xyz is the axis and w is the angle(in radians)
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Rotation(pub Vec4);
 
 #[macro_export]
 /// implement setup methods systems
@@ -0,0 +1,413 @@
+use nalgebra_glm::*;
+use specs::{Component, Join, VecStorage, WorldExt};
+
+use super::mesh::Position;
+use crate::{
+    error::LighthouseError,
+    graphics::{
+        framebuffer::Framebuffer,
+        render_state::RenderState,
+        shader::ShaderProgram,
+        target_pool::WindowTargets,
+        texture::{Texture, TextureFilter},
+        ui::{QuadInstance, UiBatch},
+        uniform::Uniform,
+        vertex::VertexArray,
+        DynamicImage::ImageRgba8,
+    },
+};
+use ogl33::*;
+
+/// The size of the dot texture markers without an icon use(in pixels)
+const DOT_SIZE: u32 = 32;
+
+const MINIMAP_VERT: &str = "#version 330 core
+uniform vec4 rect;
+uniform vec2 screen_size;
+
+out vec2 local;
+
+void main() {
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    vec2 pos = rect.xy + corner * rect.zw;
+    local = vec2(corner.x - 0.5, 0.5 - corner.y);
+    gl_Position = vec4(pos / screen_size * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+}";
+
+const MINIMAP_FRAG: &str = "#version 330 core
+in vec2 local;
+
+uniform sampler2D map;
+uniform vec2 focus;
+uniform float zoom;
+uniform float rotation;
+uniform bool circular;
+uniform vec4 background;
+
+out vec4 color;
+
+void main() {
+    float edge = circular ? length(local) : max(abs(local.x), abs(local.y));
+    if (edge > 0.5) {
+        discard;
+    }
+
+    // turning the map one way is turning where it is read from the other way
+    float s = sin(-rotation);
+    float c = cos(-rotation);
+    vec2 uv = focus + vec2(c * local.x - s * local.y, s * local.x + c * local.y) / zoom;
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+        color = background;
+    } else {
+        color = texture(map, uv);
+    }
+}";
+
+/// Shows an entity with a [Position] on a [Minimap]
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[storage(VecStorage)]
+pub struct MinimapMarker {
+    /// The color the marker is multiplied by
+    pub color: [f32; 4],
+    /// The size of the marker(in pixels)
+    pub size: f32,
+    /// The id of the texture of the marker, None draws a round dot
+    pub icon: Option<u32>,
+    /// Whether the marker stays on the edge of the minimap when the entity is outside of it,
+    /// e.g. for quest targets
+    pub clamp_to_edge: bool,
+    /// When markers overlap the one with the higher layer is drawn on top
+    pub layer: i32,
+}
+
+impl MinimapMarker {
+    /// Creates a new dot of color that is size pixels big
+    pub fn new(color: [f32; 4], size: f32) -> Self {
+        MinimapMarker {
+            color,
+            size,
+            icon: None,
+            clamp_to_edge: false,
+            layer: 0,
+        }
+    }
+
+    /// Sets the icon
+    pub fn icon(mut self, texture: u32) -> Self {
+        self.icon = Some(texture);
+        self
+    }
+
+    /// Sets whether the marker stays on the edge
+    pub fn clamp_to_edge(mut self, clamp_to_edge: bool) -> Self {
+        self.clamp_to_edge = clamp_to_edge;
+        self
+    }
+
+    /// Sets the layer
+    pub fn layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+/// A map of the world seen from above that is drawn on top of the screen with a
+/// [MinimapMarker] for entities
+///
+/// The map is drawn by a top down orthographic camera into [Minimap::target], it covers
+/// [Minimap::world_size] around [Minimap::center]. The top of the map is -z and the right
+/// is +x. The widget shows the part of the map around [Minimap::focus], it can be zoomed in
+/// and turned, e.g. so the way the player looks is always up
+///
+/// The map only has to be drawn again when the world changes or the focus gets close to the
+/// edge of it, moving around inside of it only changes the widget
+///
+/// # Example
+/// ```
+/// let mut minimap = Minimap::new(512, 200.0)?
+///     .rect(vec2(20.0, 20.0), vec2(180.0, 180.0))
+///     .zoom(4.0)
+///     .circular(true);
+///
+/// // draw the level into the map once
+/// let view_proj = minimap.begin();
+/// Uniform::new(&program, "camera").set_mat4(&view_proj);
+/// level.draw();
+/// minimap.end(world.env.win_size);
+///
+/// world.ecs.create_entity().with(Position(enemy_pos)).with(MinimapMarker::new([1.0, 0.0, 0.0, 1.0], 8.0)).build();
+///
+/// 'main_loop: loop {
+///     minimap.focus = player.pos;
+///     minimap.rotation = player.yaw;
+///     // draw the scene
+///     minimap.draw(&world.ecs, world.env.win_size);
+/// }
+/// ```
+pub struct Minimap {
+    /// The render target the map is drawn into
+    pub target: Framebuffer,
+    /// The middle of the map
+    pub center: Vec3,
+    /// How far the map goes from one side to the other(in world units)
+    pub world_size: f32,
+    /// How high above the center the camera is, it sees down to as far below the
+    /// center(in world units)
+    pub height: f32,
+    /// What is in the middle of the widget, usually the player
+    pub focus: Vec3,
+    /// How much the widget is zoomed in, 1 shows the whole map
+    pub zoom: f32,
+    /// How far the widget is turned counter clockwise(in radians)
+    pub rotation: f32,
+    /// Where the top left of the widget is(in pixels from the top left of the screen)
+    pub pos: Vec2,
+    /// The size of the widget(in pixels)
+    pub size: Vec2,
+    /// Whether the widget is a circle instead of a rectangle
+    pub circular: bool,
+    /// The color of the widget where it shows something outside of the map
+    pub background: [f32; 4],
+    /// The batch the markers are drawn with
+    pub markers: UiBatch,
+    program: ShaderProgram,
+    vao: VertexArray,
+    dot: Texture,
+}
+
+impl Minimap {
+    /// Creates a new minimap whose map is resolution pixels wide and covers world_size
+    /// around the origin
+    pub fn new(resolution: i32, world_size: f32) -> Result<Self, LighthouseError> {
+        let dot = image::RgbaImage::from_fn(DOT_SIZE, DOT_SIZE, |x, y| {
+            let half = DOT_SIZE as f32 / 2.0;
+            let dist = vec2(x as f32 + 0.5 - half, y as f32 + 0.5 - half).norm();
+            // a pixel wide soft edge
+            let alpha = (half - dist).clamp(0.0, 1.0);
+            image::Rgba([255, 255, 255, (alpha * 255.0) as u8])
+        });
+
+        Ok(Minimap {
+            target: Framebuffer::new(resolution, resolution)?,
+            center: vec3(0.0, 0.0, 0.0),
+            world_size,
+            height: 100.0,
+            focus: vec3(0.0, 0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            pos: vec2(10.0, 10.0),
+            size: vec2(150.0, 150.0),
+            circular: false,
+            background: [0.0, 0.0, 0.0, 0.5],
+            markers: UiBatch::new()?,
+            program: ShaderProgram::from_vert_frag(MINIMAP_VERT, MINIMAP_FRAG)?,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+            dot: Texture::builder(ImageRgba8(dot))
+                .filter(TextureFilter::Linear, TextureFilter::Linear)
+                .build()?,
+        })
+    }
+
+    /// Sets the center
+    pub fn center(mut self, center: Vec3) -> Self {
+        self.center = center;
+        self
+    }
+
+    /// Sets the height
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets where the widget is on the screen and its size(in pixels)
+    pub fn rect(mut self, pos: Vec2, size: Vec2) -> Self {
+        self.pos = pos;
+        self.size = size;
+        self
+    }
+
+    /// Sets the zoom
+    pub fn zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Sets the rotation
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets whether the widget is a circle
+    pub fn circular(mut self, circular: bool) -> Self {
+        self.circular = circular;
+        self
+    }
+
+    /// Sets the background
+    pub fn background(mut self, background: [f32; 4]) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Gets the matrix of the top down camera that draws the map
+    pub fn view_proj(&self) -> Mat4 {
+        let eye = self.center + vec3(0.0, self.height, 0.0);
+        // -z is the top of the map
+        let view = look_at(&eye, &self.center, &vec3(0.0, 0.0, -1.0));
+        let half = self.world_size / 2.0;
+        let proj = ortho(-half, half, -half, half, 0.0, self.height * 2.0);
+        proj * view
+    }
+
+    /// Binds the map target and clears it, gives the matrix the map has to be drawn with
+    pub fn begin(&self) -> Mat4 {
+        self.target.bind();
+        unsafe { glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT) }
+        self.view_proj()
+    }
+
+    /// Binds the window again after the map is drawn, screen_size is the size of the
+    /// window(in pixels)
+    pub fn end(&self, screen_size: Vec2) {
+        Framebuffer::unbind();
+        unsafe { glViewport(0, 0, screen_size.x as i32, screen_size.y as i32) }
+    }
+
+    /// Gets where pos is on the map, from 0 to 1 with 0 at the bottom left
+    pub fn map_uv(&self, pos: &Vec3) -> Vec2 {
+        let offset = (pos - self.center) / self.world_size;
+        vec2(offset.x + 0.5, 0.5 - offset.z)
+    }
+
+    /// Gets where pos is in the widget, from -0.5 to 0.5 with y up, it is outside of that
+    /// when pos isn't shown
+    fn widget_local(&self, pos: &Vec3) -> Vec2 {
+        let offset = (self.map_uv(pos) - self.map_uv(&self.focus)) * self.zoom;
+        rotate_vec2(&offset, self.rotation)
+    }
+
+    /// Checks if a local position from [Minimap::widget_local] is inside the widget
+    fn inside(&self, local: &Vec2) -> bool {
+        if self.circular {
+            local.norm() <= 0.5
+        } else {
+            local.x.abs() <= 0.5 && local.y.abs() <= 0.5
+        }
+    }
+
+    /// Gets where pos is shown on the screen(in pixels from the top left), None if it is
+    /// outside of the widget
+    pub fn to_screen(&self, pos: &Vec3) -> Option<Vec2> {
+        let local = self.widget_local(pos);
+        self.inside(&local).then(|| self.local_to_screen(&local))
+    }
+
+    /// Turns a local position from [Minimap::widget_local] into a position on the screen
+    fn local_to_screen(&self, local: &Vec2) -> Vec2 {
+        self.pos + vec2(local.x + 0.5, 0.5 - local.y).component_mul(&self.size)
+    }
+
+    /// Draws the widget and the [MinimapMarker] of every entity with a [Position] on top of
+    /// the screen of size screen_size(in pixels)
+    pub fn draw(&mut self, ecs: &specs::World, screen_size: Vec2) {
+        self.program.use_program();
+        let uniform = |name: &str| Uniform::new(&self.program, name);
+        uniform("rect").set(vec4(self.pos.x, self.pos.y, self.size.x, self.size.y));
+        uniform("screen_size").set(screen_size);
+        uniform("focus").set(self.map_uv(&self.focus));
+        uniform("zoom").set(self.zoom.max(0.0001));
+        uniform("rotation").set(self.rotation);
+        uniform("circular").set(self.circular);
+        uniform("background").set(Vec4::from(self.background));
+        uniform("map").set(0);
+
+        self.vao.bind();
+        unsafe {
+            let depth_test = glIsEnabled(GL_DEPTH_TEST) == GL_TRUE;
+            let blend = glIsEnabled(GL_BLEND) == GL_TRUE;
+            glDisable(GL_DEPTH_TEST);
+            glEnable(GL_BLEND);
+            glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+            glActiveTexture(GL_TEXTURE0);
+            glBindTexture(GL_TEXTURE_2D, self.target.color.id);
+            glDrawArrays(GL_TRIANGLE_STRIP, 0, 4);
+
+            if depth_test {
+                glEnable(GL_DEPTH_TEST);
+            }
+            if !blend {
+                glDisable(GL_BLEND);
+            }
+        }
+        // the blend function isn't put back how it was
+        RenderState::invalidate();
+        VertexArray::clear_binding();
+
+        self.draw_markers(ecs, screen_size);
+    }
+
+    /// Puts every marker into the marker batch and draws it
+    fn draw_markers(&mut self, ecs: &specs::World, screen_size: Vec2) {
+        let positions = ecs.read_storage::<Position>();
+        let markers = ecs.read_storage::<MinimapMarker>();
+        let entities = ecs.entities();
+
+        self.markers.clear();
+        for (entity, pos, marker) in (&entities, &positions, &markers).join() {
+            let mut local = self.widget_local(&pos.0);
+            if !self.inside(&local) {
+                if !marker.clamp_to_edge {
+                    continue;
+                }
+                local = if self.circular {
+                    local.normalize() * 0.5
+                } else {
+                    local / (local.x.abs().max(local.y.abs()) * 2.0)
+                };
+            }
+
+            let screen = self.local_to_screen(&local);
+            let quad = QuadInstance {
+                rect: [
+                    screen.x - marker.size / 2.0,
+                    screen.y - marker.size / 2.0,
+                    marker.size,
+                    marker.size,
+                ],
+                uv: [0.0, 1.0, 1.0, 0.0],
+                color: marker.color,
+            };
+            let texture = marker.icon.unwrap_or(self.dot.id);
+            self.markers.set(
+                &format!("marker.{}", entity.id()),
+                texture,
+                &[quad],
+                marker.layer,
+            );
+        }
+        self.markers.draw(screen_size);
+    }
+
+    /// Deletes the map target, the shader and the marker batch
+    pub fn delete(self) {
+        self.target.delete();
+        self.program.delete();
+        self.dot.delete();
+        self.markers.delete();
+    }
+}
+
+impl WindowTargets for Minimap {
+    /// The map has its own resolution, the widget is placed in pixels every frame
+    fn window_resized(&mut self, _width: i32, _height: i32) -> Result<(), LighthouseError> {
+        Ok(())
+    }
+
+    /// The map is empty afterwards, it has to be drawn again
+    fn recreate_targets(&mut self) -> Result<(), LighthouseError> {
+        self.target.recreate()
+    }
+}
@@ -3,22 +3,23 @@ use std::time::{Duration, Instant};
 use device_query::{DeviceQuery, DeviceState, MouseState};
 use nalgebra_glm::*;
 
+use crate::graphics::window::{Window, WindowEvent};
+
 /// State of the mouse
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum StateOfMouse {
-    /// Mouse cannot moved
-    Locked(Vec2),
+    /// Mouse is hidden and captured by the window, only [Mouse::delta] changes
+    Locked,
     /// Mouse is free
     Free,
 }
 
 impl StateOfMouse {
     /// Switch the values then return. e.g. Locked -> Free and vice versa
-    /// on_locked is for when it switches from free -> locked
-    pub fn switch(&self, on_locked: Vec2) -> Self {
+    pub fn switch(&self) -> Self {
         match self {
-            Self::Locked(_) => Self::Free,
-            Self::Free => Self::Locked(on_locked),
+            Self::Locked => Self::Free,
+            Self::Free => Self::Locked,
         }
     }
 }
@@ -41,15 +42,25 @@ pub enum MousePressed {
 ///
 /// // Check if mouse is locked or not
 /// match mouse.state {
-///     StateOfMouse::Free => println!("Mouse is free!"),
-///     StateOfMouse::Locked(Vec2(x, y)) => println!("Mouse is locked into place at {} {}", x, y);
+///     StateOfMouse::Free => println!("Mouse is free at {}", mouse.position),
+///     StateOfMouse::Locked => println!("Mouse moved by {} this frame", mouse.delta),
 /// }
 /// ```
 pub struct Mouse {
     /// The Inner mouse
     pub mouse: MouseState,
-    /// State of the mouse
+    /// State of the mouse, use [Mouse::lock] and [Mouse::unlock] to change it
     pub state: StateOfMouse,
+    /// Position of the mouse relative to the top left of the window
+    pub position: Vec2,
+    /// How much the mouse moved this frame(in pixels)
+    ///
+    /// It keeps working while the mouse is locked, but is zero while the window is unfocused
+    pub delta: Vec2,
+    /// Whether the window has keyboard focus
+    focused: bool,
+    /// Whether the cursor is drawn by the engine instead of the os
+    software_cursor: bool,
     /// When was the mouse last pressed
     last_pressed: Instant,
 }
@@ -64,10 +75,80 @@ impl Mouse {
         Mouse {
             mouse,
             state,
+            position: vec2(0.0, 0.0),
+            delta: vec2(0.0, 0.0),
+            focused: true,
+            software_cursor: false,
             last_pressed: Instant::now(),
         }
     }
 
+    /// Locks the mouse, it is hidden and captured by win, see [Window::set_relative_mouse]
+    pub fn lock(&mut self, win: &dyn Window) {
+        self.state = StateOfMouse::Locked;
+        if self.focused {
+            win.set_relative_mouse(true);
+        }
+    }
+
+    /// Frees the mouse
+    pub fn unlock(&mut self, win: &dyn Window) {
+        self.state = StateOfMouse::Free;
+        win.set_relative_mouse(false);
+    }
+
+    /// Hides the cursor of the os so the engine can draw its own, see
+    /// [SoftwareCursor](crate::graphics::cursor::SoftwareCursor)
+    ///
+    /// The cursor drawn by the engine goes through post effects and resolution scaling like
+    /// the rest of the ui
+    pub fn set_software_cursor(&mut self, enabled: bool, win: &dyn Window) {
+        self.software_cursor = enabled;
+        win.show_cursor(!enabled);
+    }
+
+    /// Whether the engine draws the cursor, see [Mouse::set_software_cursor]
+    pub fn software_cursor(&self) -> bool {
+        self.software_cursor
+    }
+
+    /// Gets how much the mouse moved this frame, scaled by sensitivity
+    pub fn scaled_delta(&self, sensitivity: f32) -> Vec2 {
+        self.delta * sensitivity
+    }
+
+    /// Updates the position and delta of the mouse from an event
+    ///
+    /// When the window loses focus the mouse is released, it is captured again
+    /// when the window gains focus if the mouse is still locked
+    pub fn handle_event(&mut self, event: &WindowEvent, win: &dyn Window) {
+        match *event {
+            WindowEvent::MouseMotion { pos, delta } => {
+                self.position = pos;
+                if self.focused {
+                    self.delta += delta;
+                }
+            }
+            WindowEvent::FocusLost => {
+                self.focused = false;
+                self.delta = vec2(0.0, 0.0);
+                win.set_relative_mouse(false);
+            }
+            WindowEvent::FocusGained => {
+                self.focused = true;
+                if self.state == StateOfMouse::Locked {
+                    win.set_relative_mouse(true);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Resets the delta, is called by the world at the end of every update
+    pub fn end_frame(&mut self) {
+        self.delta = vec2(0.0, 0.0);
+    }
+
     /// Returns the what buttons are pressed
     /// 
     /// # Example
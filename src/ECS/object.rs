@@ -0,0 +1,75 @@
+use device_query::Keycode;
+use nalgebra_glm::Vec2;
+
+use super::{
+    context::UpdateCtx,
+    keyboard::Keyboard,
+    mesh::PosRot,
+    mouse::Mouse,
+    world::GameObjectTrait,
+};
+use crate::graphics::window::Window;
+
+/// An object that lives in the [World](super::world::World)
+///
+/// # Example
+/// ```
+/// impl Object<GameObject> for MyObject {
+///     fn update(&mut self, ctx: &mut UpdateCtx<GameObject>) {
+///         self.set_pos().x += 1.0 * ctx.time.delta;
+///     }
+/// }
+/// ```
+pub trait Object<GameObject: GameObjectTrait>: PosRot {
+    /// Updates the object, see [UpdateCtx] for what the object can access
+    fn update(&mut self, ctx: &mut UpdateCtx<GameObject>);
+}
+
+/// The input for this frame, it is given to [ControllableKey] and [ControllableMouse]
+pub struct Input<'a> {
+    /// The keys that are pressed this frame
+    pub keys: &'a [Keycode],
+    /// The keyboard, to check if a key was just pressed or released
+    pub keyboard: &'a Keyboard,
+    /// The mouse
+    pub mouse: &'a mut Mouse,
+    /// The window
+    pub win: &'a dyn Window,
+    /// The size of the window
+    pub win_size: Vec2,
+}
+
+/// An object that can be controlled with the keyboard
+///
+/// The [World](super::world::World) calls on_key for every object given by
+/// [GameObjectTrait::get_controllable_keys] every update
+///
+/// # Example
+/// ```
+/// impl ControllableKey for Player {
+///     fn on_key(&mut self, input: &mut Input) {
+///         for key in input.keys {
+///             match key {
+///                 Keycode::W => self.set_pos().z += 0.01,
+///                 _ => (),
+///             }
+///         }
+///         if input.keyboard.just_pressed(Keycode::Space) {
+///             self.jump();
+///         }
+///     }
+/// }
+/// ```
+pub trait ControllableKey {
+    /// Called every update with the input for this frame
+    fn on_key(&mut self, input: &mut Input);
+}
+
+/// An object that can be controlled with the mouse
+///
+/// The [World](super::world::World) calls on_mouse for every object given by
+/// [GameObjectTrait::get_controllable_mice] every update
+pub trait ControllableMouse {
+    /// Called every update with the input for this frame
+    fn on_mouse(&mut self, input: &mut Input);
+}
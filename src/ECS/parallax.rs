@@ -0,0 +1,118 @@
+use nalgebra_glm::*;
+use specs::{Component, Join, ReadExpect, ReadStorage, System, VecStorage, WriteStorage};
+
+use super::camera2d::Camera2D;
+use crate::graphics::sprite::{Sprite, Transform2D};
+
+/// A background layer that scrolls slower or faster than the world
+///
+/// Every update the [Transform2D] of the entity is moved relative to the [Camera2D]
+/// resource, so the [Sprite] of the entity looks further away or closer than it is
+///
+/// A tiled layer repeats its whole texture to always fill the view, its texture should use
+/// `GL_REPEAT` wrapping and the [Transform2D] rotation is ignored
+///
+/// # Example
+/// ```
+/// // far away mountains that move at a fifth of the camera speed
+/// world
+///     .ecs
+///     .create_entity()
+///     .with(Transform2D::default())
+///     .with(Sprite::new(mountains).layer(-10))
+///     .with(ParallaxLayer::new(vec2(0.2, 0.2)).tile_x(true))
+///     .build();
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[storage(VecStorage)]
+pub struct ParallaxLayer {
+    /// How much the layer moves with the world, 1 moves like the world, 0 stays on the
+    /// screen and anything in between looks far away
+    pub factor: Vec2,
+    /// Where the layer is when the camera is at the origin(in world units)
+    pub origin: Vec2,
+    /// Whether the layer repeats forever horizontally
+    pub tile_x: bool,
+    /// Whether the layer repeats forever vertically
+    pub tile_y: bool,
+}
+
+impl ParallaxLayer {
+    /// Creates a new untiled layer at the origin
+    pub fn new(factor: Vec2) -> Self {
+        ParallaxLayer {
+            factor,
+            origin: vec2(0.0, 0.0),
+            tile_x: false,
+            tile_y: false,
+        }
+    }
+
+    /// Sets the origin
+    pub fn origin(mut self, origin: Vec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Sets whether the layer repeats horizontally
+    pub fn tile_x(mut self, tile_x: bool) -> Self {
+        self.tile_x = tile_x;
+        self
+    }
+
+    /// Sets whether the layer repeats vertically
+    pub fn tile_y(mut self, tile_y: bool) -> Self {
+        self.tile_y = tile_y;
+        self
+    }
+
+    /// Gets where the layer is for a camera at camera_pos(in world units)
+    pub fn offset(&self, camera_pos: Vec2) -> Vec2 {
+        self.origin + camera_pos.component_mul(&(vec2(1.0, 1.0) - self.factor))
+    }
+}
+
+/// Moves every [ParallaxLayer] relative to the [Camera2D] resource
+///
+/// It is run by [World::update](super::world::World::update) when the camera resource exists
+pub struct ParallaxSystem;
+
+impl<'a> System<'a> for ParallaxSystem {
+    type SystemData = (
+        ReadExpect<'a, Camera2D>,
+        ReadStorage<'a, ParallaxLayer>,
+        WriteStorage<'a, Transform2D>,
+        WriteStorage<'a, Sprite>,
+    );
+
+    fn run(&mut self, (camera, layers, mut transforms, mut sprites): Self::SystemData) {
+        let (min, max) = camera.visible_bounds();
+        for (layer, transform, sprite) in (&layers, &mut transforms, &mut sprites).join() {
+            let pos = layer.offset(camera.pos);
+            let texture_size = sprite.texture.size;
+            let scale = transform.scale;
+            let [mut x, mut y, mut width, mut height] = [0.0, 0.0, texture_size.x, texture_size.y];
+
+            transform.pos = pos;
+            if layer.tile_x {
+                // where the left of the texture would be if it wasnt tiled
+                let tile_left = pos.x - sprite.pivot.x * texture_size.x * scale.x;
+                width = (max.x - min.x) / scale.x;
+                x = (min.x - tile_left) / scale.x;
+                transform.pos.x = min.x + sprite.pivot.x * width * scale.x;
+            }
+            if layer.tile_y {
+                let tile_bottom = pos.y - sprite.pivot.y * texture_size.y * scale.y;
+                height = (max.y - min.y) / scale.y;
+                // regions go down from the top of the texture
+                y = texture_size.y - (min.y - tile_bottom) / scale.y - height;
+                transform.pos.y = min.y + sprite.pivot.y * height * scale.y;
+            }
+
+            if layer.tile_x || layer.tile_y {
+                transform.rotation = 0.0;
+                sprite.region = Some([x, y, width, height]);
+            }
+        }
+    }
+}
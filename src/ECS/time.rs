@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+use log::info;
+use nalgebra_glm::Vec2;
+
+use crate::graphics::{text::Font, ui::UiBatch};
+
+/// How many frames [Time::fps] is the average of
+const FPS_SAMPLES: usize = 60;
+
+/// Time of the world, it is updated every time the world updates
+///
+/// [Time::delta] and [Time::elapsed] are game time, they are multiplied by [Time::scale]
+/// and stop while the time is paused. Everything that moves the game along like objects,
+/// animations and particles should use them. Things that have to keep working in a pause
+/// menu like the UI and input should use [Time::real_delta] and [Time::real_elapsed]
+///
+/// # Example
+/// ```
+/// impl Object<GameObject> for Player {
+///     fn update(&mut self, ctx: &mut UpdateCtx<GameObject>) {
+///         // move one unit per second no matter the frame rate
+///         self.set_pos().x += 1.0 * ctx.time.delta;
+///     }
+/// }
+///
+/// // open the pause menu, the ui keeps using the real time
+/// ctx.commands.pause();
+/// world.env.time.scale = 0.25; // or slow motion from outside of an update
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Time {
+    /// Game time between this update and the last update(in seconds)
+    pub delta: f32,
+    /// Game time since the world was created(in seconds)
+    pub elapsed: f32,
+    /// Real time between this update and the last update(in seconds)
+    pub real_delta: f32,
+    /// Real time since the world was created(in seconds)
+    pub real_elapsed: f32,
+    /// How fast game time goes compared to real time, 1 is normal speed, less is slow
+    /// motion and 0 is paused
+    pub scale: f32,
+    /// Whether game time is paused, the scale is kept so resuming goes back to it
+    pub paused: bool,
+    /// How far the frame that is drawn is between the last two fixed updates, from 0 to 1.
+    /// It stays 1 without a [GameLoop](super::game_loop::GameLoop)
+    pub alpha: f32,
+    /// How many frames were drawn since the world was created, the first frame is 1
+    pub frame: u64,
+    /// The real time between the last frames(in seconds), for [Time::fps]
+    samples: [f32; FPS_SAMPLES],
+    /// When the world was created
+    start: Instant,
+    /// When the world last updated
+    last_update: Instant,
+    /// When the last frame was drawn
+    last_frame: Instant,
+}
+
+impl Time {
+    /// Creates a new time starting now
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Time {
+            delta: 0.0,
+            elapsed: 0.0,
+            real_delta: 0.0,
+            real_elapsed: 0.0,
+            scale: 1.0,
+            paused: false,
+            alpha: 1.0,
+            frame: 0,
+            samples: [0.0; FPS_SAMPLES],
+            start: now,
+            last_update: now,
+            last_frame: now,
+        }
+    }
+
+    /// Advances the time to now, is called by the world every update
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        self.real_delta = (now - self.last_update).as_secs_f32();
+        self.real_elapsed = (now - self.start).as_secs_f32();
+        self.last_update = now;
+
+        self.delta = self.real_delta * self.current_scale();
+        self.elapsed += self.delta;
+    }
+
+    /// Advances the real time to now and the game time by delta(in seconds) times the
+    /// scale, is called by the world when it is frame stepped and by
+    /// [World::fixed_update](super::world::World::fixed_update)
+    pub fn tick_fixed(&mut self, delta: f32) {
+        self.tick_real();
+        self.delta = delta * self.current_scale();
+        self.elapsed += self.delta;
+    }
+
+    /// Advances the real time to now without moving the game time, is called by the world
+    /// while it is paused because the window is inactive
+    pub fn tick_real(&mut self) {
+        let paused = self.paused;
+        self.paused = true;
+        self.tick();
+        self.paused = paused;
+    }
+
+    /// Counts a drawn frame and remembers how long it took for [Time::fps], is called by
+    /// [World::render](super::world::World::render) once every frame
+    ///
+    /// With a [GameLoop](super::game_loop::GameLoop) there can be no or a few updates in a
+    /// frame, so the frame rate isn't the update rate
+    pub fn end_frame(&mut self) {
+        let now = Instant::now();
+        self.samples[(self.frame % FPS_SAMPLES as u64) as usize] =
+            (now - self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        self.frame += 1;
+    }
+
+    /// Gets how fast game time is going right now, 0 while paused
+    pub fn current_scale(&self) -> f32 {
+        if self.paused {
+            0.0
+        } else {
+            self.scale.max(0.0)
+        }
+    }
+
+    /// Pauses game time
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes game time at [Time::scale]
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Pauses game time if it is running and resumes it if it is paused
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Checks if game time is stopped, because it is paused or the scale is 0
+    pub fn is_paused(&self) -> bool {
+        self.current_scale() == 0.0
+    }
+
+    /// Gets the average real time between the last 60 frames(in seconds), 0 before the
+    /// first frame
+    pub fn average_delta(&self) -> f32 {
+        let count = (self.frame as usize).min(FPS_SAMPLES);
+        if count == 0 {
+            return 0.0;
+        }
+        self.samples[..count].iter().sum::<f32>() / count as f32
+    }
+
+    /// Gets the frames per second, averaged over the last 60 frames so it doesn't jump
+    /// around every frame
+    pub fn fps(&self) -> f32 {
+        let average_delta = self.average_delta();
+        if average_delta > 0.0 {
+            1.0 / average_delta
+        } else {
+            0.0
+        }
+    }
+
+    /// Gets how long ago the last tick was
+    pub fn since_last_tick(&self) -> Duration {
+        self.last_update.elapsed()
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reports the fps of [Time] every so often, to the console with the log crate and on the
+/// screen with [FpsReporter::draw]
+///
+/// The world updates the reporter in [Enviroment::fps_reporter](super::world::Enviroment)
+/// after every frame it draws
+///
+/// # Example
+/// ```
+/// world.env.fps_reporter = Some(FpsReporter::new().interval(Duration::from_secs(5)));
+///
+/// // every frame, to show it in the top left corner too
+/// if let Some(reporter) = &world.env.fps_reporter {
+///     reporter.draw(&mut ui, &font, vec2(10.0, 10.0), 1.0, [1.0, 1.0, 0.0, 1.0], 100);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FpsReporter {
+    /// How long there is between reports
+    pub interval: Duration,
+    /// Whether a report is logged with [log::info]
+    pub console: bool,
+    /// The last report, what is drawn
+    text: String,
+    /// When the last report was
+    last_report: Option<Instant>,
+}
+
+impl FpsReporter {
+    /// Creates a reporter that logs every second
+    pub fn new() -> Self {
+        FpsReporter {
+            interval: Duration::from_secs(1),
+            console: true,
+            text: String::new(),
+            last_report: None,
+        }
+    }
+
+    /// Sets how long there is between reports
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets whether a report is logged, without it the report is only drawn
+    pub fn console(mut self, console: bool) -> Self {
+        self.console = console;
+        self
+    }
+
+    /// Makes a report if the interval has passed since the last one, gives whether it did
+    pub fn update(&mut self, time: &Time) -> bool {
+        let now = Instant::now();
+        match self.last_report {
+            Some(last_report) if now - last_report < self.interval => return false,
+            _ => self.last_report = Some(now),
+        }
+        self.text = format!(
+            "{:.0} fps ({:.2} ms)",
+            time.fps(),
+            time.average_delta() * 1000.0
+        );
+        if self.console {
+            info!("frame {}: {}", time.frame, self.text);
+        }
+        true
+    }
+
+    /// Gets the last report, empty before the first one
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Draws the last report as the element `fps` of a [UiBatch], pos is the top left
+    /// corner(in pixels)
+    pub fn draw(
+        &self,
+        ui: &mut UiBatch,
+        font: &Font,
+        pos: Vec2,
+        scale: f32,
+        color: [f32; 4],
+        layer: i32,
+    ) {
+        ui.text("fps", font, &self.text, pos, scale, color, layer);
+    }
+}
+
+impl Default for FpsReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
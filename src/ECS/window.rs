@@ -0,0 +1,90 @@
+use crate::graphics::window::WindowEvent;
+
+/// Changes to the state of the window, see [UpdateCtx::window_events](super::context::UpdateCtx::window_events)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowStateEvent {
+    /// The window got keyboard focus
+    FocusGained,
+    /// The window lost keyboard focus
+    FocusLost,
+    /// The window was minimized
+    Minimized,
+    /// The window was restored after being minimized
+    Restored,
+    /// The window was resized, contains the new width and height(in pixels)
+    Resized(u32, u32),
+}
+
+impl WindowStateEvent {
+    /// Gets the window state event from an event, if it is one
+    pub fn from_event(event: &WindowEvent) -> Option<Self> {
+        match *event {
+            WindowEvent::FocusGained => Some(Self::FocusGained),
+            WindowEvent::FocusLost => Some(Self::FocusLost),
+            WindowEvent::Minimized => Some(Self::Minimized),
+            WindowEvent::Restored => Some(Self::Restored),
+            WindowEvent::Resized(width, height) => Some(Self::Resized(width, height)),
+            _ => None,
+        }
+    }
+}
+
+/// The state of the window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowState {
+    /// Whether the window has keyboard focus
+    pub focused: bool,
+    /// Whether the window is minimized
+    pub minimized: bool,
+}
+
+impl WindowState {
+    /// Creates a new focused and not minimized window state
+    pub fn new() -> Self {
+        WindowState {
+            focused: true,
+            minimized: false,
+        }
+    }
+
+    /// Updates the state from a window state event
+    pub fn apply(&mut self, event: WindowStateEvent) {
+        match event {
+            WindowStateEvent::FocusGained => self.focused = true,
+            WindowStateEvent::FocusLost => self.focused = false,
+            WindowStateEvent::Minimized => self.minimized = true,
+            WindowStateEvent::Restored => self.minimized = false,
+            WindowStateEvent::Resized(..) => (),
+        }
+    }
+
+    /// Checks if the window is unfocused or minimized
+    pub fn is_inactive(&self) -> bool {
+        !self.focused || self.minimized
+    }
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the world does while the window is unfocused or minimized
+///
+/// # Example
+/// ```
+/// // Save battery by only updating 10 times a second when the player tabs out
+/// world.env.unfocused = UnfocusedBehaviour::Throttle(10.0);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum UnfocusedBehaviour {
+    /// Keep updating like normal
+    #[default]
+    Run,
+    /// Dont update the objects until the window is active again
+    Pause,
+    /// Update at most this many times a second, a rate that isn't above 0 updates like
+    /// [UnfocusedBehaviour::Run]
+    Throttle(f32),
+}
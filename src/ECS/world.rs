@@ -1,40 +1,99 @@
-use beryllium::GlWindow;
-use device_query::DeviceState;
-use nalgebra_glm::Vec2;
+use std::{thread, time::Duration};
 
-use crate::graphics::shader::ShaderProgram;
+use device_query::{DeviceQuery, DeviceState};
+use nalgebra_glm::{vec2, Vec2};
 
-use super::{camera::CameraTrait, mouse::Mouse};
+use crate::{
+    assets::AssetManager,
+    error::LighthouseError,
+    graphics::{
+        arena::reset_frame_arena,
+        recovery::{ContextEvent, ContextRecovery},
+        renderer::{DrawCommand, Renderer},
+        shader::ShaderProgram,
+        sprite::{Sprite, Transform2D},
+        window::{Window, WindowEvent},
+    },
+    profiler::Profiler,
+};
+
+use specs::{RunNow, WorldExt};
+
+use super::{
+    camera::CameraTrait,
+    camera2d::Camera2D,
+    commands::Commands,
+    context::UpdateCtx,
+    frame_step::{FrameStep, StepUpdate},
+    keyboard::Keyboard,
+    mesh::Position,
+    minimap::MinimapMarker,
+    mouse::Mouse,
+    object::{ControllableKey, ControllableMouse, Input},
+    parallax::{ParallaxLayer, ParallaxSystem},
+    time::{FpsReporter, Time},
+    window::{UnfocusedBehaviour, WindowState, WindowStateEvent},
+    world_label::WorldLabel,
+};
+
+/// How long the world sleeps every update while it is paused because the window is inactive
+const PAUSED_SLEEP: Duration = Duration::from_millis(50);
+
+/// How many events can wait for the next frame step, the oldest ones are dropped
+const MAX_WAITING_EVENTS: usize = 256;
 
 /// The world envieorment containing things like the keyboard and window
 pub struct Enviroment {
     /// this is the window size
     pub win_size: Vec2,
-    /// Window
-    pub win: GlWindow,
+    /// Window, see [Window]
+    pub win: Box<dyn Window>,
     /// The shader program
     pub shader_program: ShaderProgram,
     /// device is the [DeviceState] for getting keyboard and mouse
     pub device: DeviceState,
     /// mouse is the [Mouse] wrapper for all things mouse
     pub mouse: Mouse,
+    /// The keys of this and the last update, see [Keyboard]
+    pub keyboard: Keyboard,
+    /// Time of the world, see [Time]
+    pub time: Time,
+    /// Reports the fps when there is one, see [FpsReporter]
+    pub fps_reporter: Option<FpsReporter>,
+    /// Whether the window is focused and minimized
+    pub window_state: WindowState,
+    /// What the world does while the window is inactive, see [UnfocusedBehaviour]
+    pub unfocused: UnfocusedBehaviour,
+    /// The frame stepping debug mode, see [FrameStep]
+    pub frame_step: FrameStep,
+    /// Whether the opengl context was lost and how to restore it, see [ContextRecovery]
+    pub recovery: ContextRecovery,
+    /// Set to true to make [App::run](crate::app::App::run) stop after this frame
+    pub quit: bool,
 }
 
 impl Enviroment {
-    /// Creates a new enviroment
+    /// Creates a new enviroment, the window size is taken from the window
     pub fn new(
-        win_size: Vec2,
-        win: GlWindow,
+        win: Box<dyn Window>,
         shader_program: ShaderProgram,
         device: DeviceState,
         mouse: Mouse,
     ) -> Self {
         Enviroment {
-            win_size,
+            win_size: win.size_vec(),
             win,
             shader_program,
             device,
             mouse,
+            keyboard: Keyboard::new(),
+            time: Time::new(),
+            fps_reporter: None,
+            window_state: WindowState::new(),
+            unfocused: UnfocusedBehaviour::Run,
+            frame_step: FrameStep::new(),
+            recovery: ContextRecovery::new(),
+            quit: false,
         }
     }
 }
@@ -52,9 +111,9 @@ impl Enviroment {
 /// }
 ///
 /// impl GameObjectTrait for GameObject {
-///     fn update(&mut self) {
-///         for i in 0..self.objects.my_objects.len() {
-///             self.objects.my_objects[i].update()(self, i)
+///     fn update(&mut self, ctx: &mut UpdateCtx<Self>) {
+///         for object in &mut self.my_objects {
+///             object.update(ctx)
 ///         }
 ///     }
 /// }
@@ -63,7 +122,7 @@ impl Enviroment {
 pub trait GameObjectTrait {
     /// Updates the objects n game object
     /// See trait level doc for more info
-    fn update(&self) -> fn(world: &mut World<Self>)
+    fn update(&mut self, ctx: &mut UpdateCtx<Self>)
     where
         Self: Sized;
 
@@ -72,6 +131,27 @@ pub trait GameObjectTrait {
 
     /// Sets the camera, must implemt CameraTrait
     fn set_camera(&mut self) -> &mut dyn CameraTrait<Self>;
+
+    /// Gets the objects that respond to the keyboard, see [ControllableKey]
+    ///
+    /// By default there are none
+    fn get_controllable_keys(&mut self) -> Vec<&mut dyn ControllableKey> {
+        Vec::new()
+    }
+
+    /// Gets the objects that respond to the mouse, see [ControllableMouse]
+    ///
+    /// By default there are none
+    fn get_controllable_mice(&mut self) -> Vec<&mut dyn ControllableMouse> {
+        Vec::new()
+    }
+
+    /// Gets the meshes that are drawn by [World::render], see [DrawCommand]
+    ///
+    /// By default there are none
+    fn get_draw_commands(&self) -> Vec<DrawCommand<'_>> {
+        Vec::new()
+    }
 }
 
 /// World struct taht stores everything thats relevant to the world
@@ -80,16 +160,354 @@ pub struct World<GameObject: GameObjectTrait> {
     pub env: Enviroment,
     /// All the objects in the world
     pub objects: GameObject,
+    /// The entities, components and resources of the world
+    pub ecs: specs::World,
+    /// The renderer that draws the world, see [World::render]
+    pub renderer: Renderer,
+    /// Times every update and render when it is set, see [World::update] for what is timed
+    pub profiler: Option<Profiler>,
+    /// The events that happened since the last update
+    events: Vec<WindowEvent>,
+    /// The window state events that happened since the last update
+    window_events: Vec<WindowStateEvent>,
+    /// The context events that happened since the last update the objects saw
+    context_events: Vec<ContextEvent>,
 }
 
 impl<GameObject: GameObjectTrait> World<GameObject> {
     /// Creates a new world struct
     pub fn new(env: Enviroment, objects: GameObject) -> Self {
-        World { env, objects }
+        let mut ecs = specs::World::new();
+        // the built in systems read these even when nothing has them
+        ecs.register::<Sprite>();
+        ecs.register::<Transform2D>();
+        ecs.register::<ParallaxLayer>();
+        ecs.register::<Position>();
+        ecs.register::<MinimapMarker>();
+        ecs.register::<WorldLabel>();
+
+        let mut renderer = Renderer::new();
+        renderer
+            .targets
+            .resize(env.win_size.x as i32, env.win_size.y as i32);
+
+        World {
+            env,
+            objects,
+            ecs,
+            renderer,
+            profiler: None,
+            events: Vec::new(),
+            window_events: Vec::new(),
+            context_events: Vec::new(),
+        }
+    }
+
+    /// Gives an event to the world, the objects will see it in [UpdateCtx::events]
+    /// on the next update
+    ///
+    /// When the window is resized [Enviroment::win_size] is updated and the render targets
+    /// of the [renderer](Renderer::targets) that scale with the window or follow it are
+    /// resized, the ones that fail are logged. When it loses focus the [Keyboard] is cleared
+    pub fn push_event(&mut self, event: WindowEvent) {
+        self.env.mouse.handle_event(&event, self.env.win.as_ref());
+        if let Some(window_event) = WindowStateEvent::from_event(&event) {
+            match window_event {
+                WindowStateEvent::Resized(width, height) => {
+                    self.env.win_size = vec2(width as f32, height as f32);
+                    for (name, error) in self.renderer.targets.resize(width as i32, height as i32) {
+                        log::error!("Couldn't resize the targets {}: {}", name, error);
+                    }
+                }
+                // the key ups go to the other window, so no key stays held
+                WindowStateEvent::FocusLost => self.env.keyboard.clear(),
+                _ => {}
+            }
+            self.env.window_state.apply(window_event);
+            self.window_events.push(window_event);
+        }
+        self.events.push(event);
+    }
+
+    /// Gives every event of the window to the world with [World::push_event], gives true
+    /// when the window was asked to close
+    ///
+    /// # Example
+    /// ```
+    /// 'main_loop: loop {
+    ///     if world.poll_events() {
+    ///         break 'main_loop;
+    ///     }
+    ///     world.update();
+    ///     world.render();
+    /// }
+    /// ```
+    pub fn poll_events(&mut self) -> bool {
+        let mut quit = false;
+        while let Some(event) = self.env.win.poll_event() {
+            match event {
+                WindowEvent::Quit => quit = true,
+                event => self.push_event(event),
+            }
+        }
+        quit
     }
 
     /// Update the world
+    ///
+    /// First the input is given to the controllable objects, then every object is
+    /// updated and finally the queued [Commands] are applied and the [ecs](World::ecs)
+    /// is maintained
+    ///
+    /// If the ecs has a [Camera2D] resource the built in 2d systems are run after the
+    /// commands, e.g. [ParallaxSystem]
+    ///
+    /// The [frame arena](crate::graphics::arena::with_frame_arena) of this thread is reset
+    /// at the start of every update
+    ///
+    /// With a [profiler](World::profiler) a frame starts at every update, the update is
+    /// timed in the sections `update/input`, `update/objects`, `update/commands`,
+    /// `update/systems` and `update/maintain`, and [World::render] in `render`
+    ///
+    /// While the window is inactive the update may be skipped or slowed down, see
+    /// [Enviroment::unfocused]. While [frame stepping](Enviroment::frame_step) the objects
+    /// are only updated when a step is taken
+    ///
+    /// Every update checks if the opengl context was lost, the objects see it in
+    /// [UpdateCtx::context_events], see [World::restore_context]
     pub fn update(&mut self) {
-        self.objects.update()(self);
+        self.update_with(None);
+    }
+
+    /// Updates the world like [World::update] but game time moves by delta(in seconds)
+    /// times the scale instead of the real time since the last update, see
+    /// [GameLoop](super::game_loop::GameLoop)
+    pub fn fixed_update(&mut self, delta: f32) {
+        self.update_with(Some(delta));
+    }
+
+    /// Updates the world, with the fixed delta of [World::fixed_update] if there is one
+    fn update_with(&mut self, fixed_delta: Option<f32>) {
+        if self.env.window_state.is_inactive() && self.window_events.is_empty() {
+            match self.env.unfocused {
+                UnfocusedBehaviour::Run => (),
+                UnfocusedBehaviour::Pause => {
+                    thread::sleep(PAUSED_SLEEP);
+                    self.env.time.tick_real();
+                    self.env.mouse.end_frame();
+                    self.events.clear();
+                    return;
+                }
+                UnfocusedBehaviour::Throttle(ticks_per_second) => {
+                    // a rate that isn't above 0 can't be waited for, so it doesn't throttle
+                    if let Ok(tick) = Duration::try_from_secs_f32(1.0 / ticks_per_second) {
+                        let since_last_tick = self.env.time.since_last_tick();
+                        if since_last_tick < tick {
+                            thread::sleep(tick - since_last_tick);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame();
+            profiler.begin_frame();
+            profiler.begin("update");
+        }
+        reset_frame_arena();
+        if let Some(status) = self.env.recovery.check() {
+            self.context_events.push(ContextEvent::Lost(status));
+        }
+        let keys = self.env.device.get_keys();
+        self.env.keyboard.update(&keys);
+        self.env.frame_step.handle_keys(&keys);
+        #[cfg(feature = "renderdoc")]
+        if let Some(renderdoc) = &mut self.renderer.renderdoc {
+            renderdoc.handle_keys(&keys);
+        }
+        match self.env.frame_step.take_step() {
+            StepUpdate::Normal => match fixed_delta {
+                Some(delta) => self.env.time.tick_fixed(delta),
+                None => self.env.time.tick(),
+            },
+            StepUpdate::Step(delta) => self.env.time.tick_fixed(delta),
+            StepUpdate::Skip => {
+                // the events are kept for the next step so objects don't miss them, but
+                // they can't pile up while it waits
+                coalesce_events(&mut self.events);
+                let excess = self.window_events.len().saturating_sub(MAX_WAITING_EVENTS);
+                self.window_events.drain(..excess);
+                self.env.time.tick_real();
+                self.begin_section("input");
+                self.dispatch_input();
+                self.end_section("input");
+                self.env.mouse.end_frame();
+                self.end_section("update");
+                return;
+            }
+        }
+        self.begin_section("input");
+        self.dispatch_input();
+        self.end_section("input");
+
+        self.begin_section("objects");
+        let mut commands = Commands::new();
+        let mut ctx = UpdateCtx {
+            time: &self.env.time,
+            input: Input {
+                keys: &keys,
+                keyboard: &self.env.keyboard,
+                mouse: &mut self.env.mouse,
+                win: self.env.win.as_ref(),
+                win_size: self.env.win_size,
+            },
+            events: &self.events,
+            window_events: &self.window_events,
+            context_events: &self.context_events,
+            commands: &mut commands,
+            profiler: self.profiler.as_mut(),
+        };
+        self.objects.update(&mut ctx);
+        self.end_section("objects");
+
+        self.begin_section("commands");
+        commands.apply(self);
+        self.end_section("commands");
+        if self.ecs.has_value::<Camera2D>() {
+            self.run_system("parallax", &mut ParallaxSystem);
+        }
+        self.begin_section("maintain");
+        self.ecs.maintain();
+        self.end_section("maintain");
+        self.env.mouse.end_frame();
+        self.events.clear();
+        self.window_events.clear();
+        self.context_events.clear();
+        self.end_section("update");
+    }
+
+    /// Makes a new opengl context after it was lost, runs the restorers of
+    /// [Enviroment::recovery] and makes the render targets of the
+    /// [renderer](Renderer::targets) again, gives the name and error of every restorer or
+    /// target that failed
+    ///
+    /// The objects see [ContextEvent::Restored] on the next update, so they can upload what
+    /// isn't restored by a restorer. The [Enviroment::shader_program] has to be made again
+    /// by a restorer or by hand too
+    pub fn restore_context(
+        &mut self,
+        assets: &AssetManager,
+    ) -> Result<Vec<(String, LighthouseError)>, LighthouseError> {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.forget_gpu_queries();
+        }
+        let mut failed = self.env.recovery.restore(self.env.win.as_ref(), assets)?;
+        failed.extend(self.renderer.targets.recreate());
+        self.context_events.push(ContextEvent::Restored);
+        Ok(failed)
+    }
+
+    /// Runs a system on the [ecs](World::ecs), timed by the [profiler](World::profiler) in
+    /// the section `systems/name` of the section that is open
+    ///
+    /// # Example
+    /// ```
+    /// world.update();
+    /// world.run_system("physics", &mut PhysicsSystem);
+    /// world.render();
+    /// ```
+    pub fn run_system<S>(&mut self, name: &str, system: &mut S)
+    where
+        S: for<'s> RunNow<'s>,
+    {
+        self.begin_section("systems");
+        self.begin_section(name);
+        system.run_now(&self.ecs);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end("systems");
+        }
+    }
+
+    /// Starts the section called name if there is a profiler
+    fn begin_section(&mut self, name: &str) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.begin(name);
+        }
+    }
+
+    /// Ends the section called name if there is a profiler
+    fn end_section(&mut self, name: &str) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end(name);
+        }
+    }
+
+    /// Draws the world and shows it on the window
+    ///
+    /// The [DrawCommand]s of the game objects are sorted and drawn by [World::renderer],
+    /// commands without their own program are drawn with [Enviroment::shader_program]
+    ///
+    /// Nothing is drawn while the opengl context is lost
+    pub fn render(&mut self) {
+        if self.env.recovery.is_lost() {
+            return;
+        }
+        self.begin_section("render");
+        self.renderer
+            .render(&self.env.shader_program, self.objects.get_draw_commands());
+        self.env.win.swap_buffers();
+        self.env.time.end_frame();
+        if let Some(fps_reporter) = &mut self.env.fps_reporter {
+            fps_reporter.update(&self.env.time);
+        }
+        self.end_section("render");
+    }
+
+    /// Gives the input to every [ControllableKey] and [ControllableMouse] object
+    fn dispatch_input(&mut self) {
+        let keys = self.env.keyboard.keys();
+        let mut input = Input {
+            keys,
+            keyboard: &self.env.keyboard,
+            mouse: &mut self.env.mouse,
+            win: self.env.win.as_ref(),
+            win_size: self.env.win_size,
+        };
+
+        for object in self.objects.get_controllable_keys() {
+            object.on_key(&mut input);
+        }
+        for object in self.objects.get_controllable_mice() {
+            object.on_mouse(&mut input);
+        }
+    }
+}
+
+/// Merges the mouse movement and scrolling that follow each other and keeps the newest
+/// [MAX_WAITING_EVENTS] events, for the events that wait for a frame step
+fn coalesce_events(events: &mut Vec<WindowEvent>) {
+    let mut merged: Vec<WindowEvent> = Vec::with_capacity(events.len());
+    for event in events.drain(..) {
+        match (merged.last_mut(), event) {
+            (_, WindowEvent::Other) => (),
+            (
+                Some(WindowEvent::MouseMotion { pos, delta }),
+                WindowEvent::MouseMotion {
+                    pos: new_pos,
+                    delta: new_delta,
+                },
+            ) => {
+                *pos = new_pos;
+                *delta += new_delta;
+            }
+            (Some(WindowEvent::MouseWheel(scroll)), WindowEvent::MouseWheel(new_scroll)) => {
+                *scroll += new_scroll
+            }
+            _ => merged.push(event),
+        }
     }
+    let excess = merged.len().saturating_sub(MAX_WAITING_EVENTS);
+    merged.drain(..excess);
+    *events = merged;
 }
@@ -0,0 +1,442 @@
+use nalgebra_glm::*;
+use specs::{Component, Join, VecStorage, WorldExt};
+
+use super::mesh::Position;
+use crate::{
+    error::LighthouseError,
+    graphics::{
+        text::Font,
+        ui::{QuadInstance, UiBatch},
+    },
+};
+use ogl33::*;
+
+/// How much closer the scene has to be than a label to hide it, so the entity the label is
+/// on doesn't hide it(in depth buffer values)
+const OCCLUSION_BIAS: f32 = 0.0001;
+
+/// Something a [WorldLabel] shows
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelElement {
+    /// A string drawn with the font given to [WorldLabelRenderer::draw]
+    Text {
+        /// The string
+        text: String,
+        /// How big the string is compared to the font
+        scale: f32,
+        /// The color of the string
+        color: [f32; 4],
+    },
+    /// A bar that is partly filled, e.g. a health bar
+    Bar {
+        /// The size of the whole bar(in pixels)
+        size: Vec2,
+        /// How full the bar is, from 0 to 1
+        value: f32,
+        /// The color of the full part
+        fill: [f32; 4],
+        /// The color of the empty part
+        background: [f32; 4],
+    },
+    /// A texture, e.g. a quest marker
+    Icon {
+        /// The id of the texture
+        texture: u32,
+        /// The size of the icon(in pixels)
+        size: Vec2,
+        /// The part of the texture shown, top left then bottom right
+        uv: [f32; 4],
+        /// The color the texture is multiplied by
+        tint: [f32; 4],
+    },
+}
+
+/// 2d UI that follows an entity in the 3d world, like a name tag or a health bar
+///
+/// The elements are stacked from the top to the bottom and centered above the
+/// [Position] of the entity moved by [WorldLabel::offset]. They are drawn by a
+/// [WorldLabelRenderer]
+///
+/// # Example
+/// ```
+/// world
+///     .ecs
+///     .create_entity()
+///     .with(Position(vec3(4.0, 0.0, 2.0)))
+///     .with(
+///         WorldLabel::new()
+///             .offset(vec3(0.0, 2.2, 0.0))
+///             .text("Goblin", 1.0, [1.0; 4])
+///             .bar(vec2(60.0, 6.0), 1.0, [0.8, 0.1, 0.1, 1.0], [0.0, 0.0, 0.0, 0.6])
+///             .scale_with_distance(10.0)
+///             .occlusion_fade(0.25),
+///     )
+///     .build();
+///
+/// // when the goblin gets hit
+/// labels.get_mut(goblin).unwrap().set_bar(1, health / max_health);
+/// ```
+#[derive(Component, Debug, Clone, PartialEq)]
+#[storage(VecStorage)]
+pub struct WorldLabel {
+    /// The elements from the top to the bottom
+    pub elements: Vec<LabelElement>,
+    /// Where the bottom of the label is relative to the entity(in world units)
+    pub offset: Vec3,
+    /// The space between elements(in pixels)
+    pub spacing: f32,
+    /// The distance the label is full size at, it gets smaller further away. None keeps it
+    /// the same size at every distance
+    pub reference_distance: Option<f32>,
+    /// The smallest the label gets when it scales with distance
+    pub min_scale: f32,
+    /// The label fades out further away than this(in world units)
+    pub max_distance: f32,
+    /// Whether the label fades when something is in front of it, see [WorldLabelRenderer::draw]
+    pub occlusion_fade: bool,
+    /// How visible the label is while something is in front of it, from 0 to 1
+    pub occluded_alpha: f32,
+    /// How fast the label fades in and out(in alpha per second)
+    pub fade_speed: f32,
+    /// How visible the label is right now
+    alpha: f32,
+}
+
+impl WorldLabel {
+    /// Creates a new empty label on the entity that is always the same size and never fades
+    pub fn new() -> Self {
+        WorldLabel {
+            elements: Vec::new(),
+            offset: vec3(0.0, 0.0, 0.0),
+            spacing: 2.0,
+            reference_distance: None,
+            min_scale: 0.25,
+            max_distance: f32::INFINITY,
+            occlusion_fade: false,
+            occluded_alpha: 0.0,
+            fade_speed: 4.0,
+            alpha: 1.0,
+        }
+    }
+
+    /// Adds a string under the other elements
+    pub fn text(mut self, text: &str, scale: f32, color: [f32; 4]) -> Self {
+        self.elements.push(LabelElement::Text {
+            text: text.to_string(),
+            scale,
+            color,
+        });
+        self
+    }
+
+    /// Adds a bar under the other elements, value is from 0 to 1
+    pub fn bar(mut self, size: Vec2, value: f32, fill: [f32; 4], background: [f32; 4]) -> Self {
+        self.elements.push(LabelElement::Bar {
+            size,
+            value,
+            fill,
+            background,
+        });
+        self
+    }
+
+    /// Adds the whole texture under the other elements
+    pub fn icon(mut self, texture: u32, size: Vec2, tint: [f32; 4]) -> Self {
+        self.elements.push(LabelElement::Icon {
+            texture,
+            size,
+            uv: [0.0, 1.0, 1.0, 0.0],
+            tint,
+        });
+        self
+    }
+
+    /// Sets the offset
+    pub fn offset(mut self, offset: Vec3) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the spacing
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Makes the label full size at reference_distance and smaller further away, it never
+    /// gets bigger than full size
+    pub fn scale_with_distance(mut self, reference_distance: f32) -> Self {
+        self.reference_distance = Some(reference_distance);
+        self
+    }
+
+    /// Sets the min scale
+    pub fn min_scale(mut self, min_scale: f32) -> Self {
+        self.min_scale = min_scale;
+        self
+    }
+
+    /// Sets the max distance
+    pub fn max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Makes the label fade to occluded_alpha while something is in front of it
+    pub fn occlusion_fade(mut self, occluded_alpha: f32) -> Self {
+        self.occlusion_fade = true;
+        self.occluded_alpha = occluded_alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the fade speed, [f32::INFINITY] fades instantly
+    pub fn fade_speed(mut self, fade_speed: f32) -> Self {
+        self.fade_speed = fade_speed;
+        self
+    }
+
+    /// Changes the text of the element at index, does nothing if it isn't text
+    pub fn set_text(&mut self, index: usize, new_text: &str) {
+        if let Some(LabelElement::Text { text, .. }) = self.elements.get_mut(index) {
+            text.clear();
+            text.push_str(new_text);
+        }
+    }
+
+    /// Changes the value of the bar at index, does nothing if it isn't a bar
+    pub fn set_bar(&mut self, index: usize, new_value: f32) {
+        if let Some(LabelElement::Bar { value, .. }) = self.elements.get_mut(index) {
+            *value = new_value;
+        }
+    }
+
+    /// Gets how visible the label is right now, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Gets the size of the label at full scale(in pixels)
+    pub fn size(&self, font: &Font) -> Vec2 {
+        let sizes = self
+            .elements
+            .iter()
+            .map(|element| element_size(element, font));
+        let (width, height) = sizes.fold((0.0f32, 0.0), |(width, height), size| {
+            (width.max(size.x), height + size.y)
+        });
+        let gaps = self.elements.len().saturating_sub(1) as f32 * self.spacing;
+        vec2(width, height + gaps)
+    }
+}
+
+impl Default for WorldLabel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gets the size of an element at full scale(in pixels)
+fn element_size(element: &LabelElement, font: &Font) -> Vec2 {
+    match element {
+        LabelElement::Text { text, scale, .. } => font.shape(text, *scale).size,
+        LabelElement::Bar { size, .. } | LabelElement::Icon { size, .. } => *size,
+    }
+}
+
+/// Turns a world position into a position on the screen(in pixels from the top left) and
+/// its depth from 0 to 1, gives None when it is behind the camera
+pub fn world_to_screen(view_proj: &Mat4, pos: &Vec3, screen_size: Vec2) -> Option<Vec3> {
+    let clip = view_proj * vec4(pos.x, pos.y, pos.z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = clip.xyz() / clip.w;
+    Some(vec3(
+        (ndc.x * 0.5 + 0.5) * screen_size.x,
+        (0.5 - ndc.y * 0.5) * screen_size.y,
+        ndc.z * 0.5 + 0.5,
+    ))
+}
+
+/// Draws the [WorldLabel] of every entity with a [Position] on top of the screen
+///
+/// Labels are drawn with a [UiBatch], the furthest first so closer ones are on top
+///
+/// # Example
+/// ```
+/// let mut labels = WorldLabelRenderer::new()?;
+///
+/// 'main_loop: loop {
+///     world.update();
+///     // draw the scene
+///     labels.draw(&world.ecs, &font, &(proj * view), camera_pos, world.env.win_size, world.env.time.real_delta);
+///     world.env.win.swap_buffers();
+/// }
+/// ```
+pub struct WorldLabelRenderer {
+    /// The batch the labels are drawn with
+    pub ui: UiBatch,
+}
+
+impl WorldLabelRenderer {
+    /// Creates a new label renderer
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(WorldLabelRenderer {
+            ui: UiBatch::new()?,
+        })
+    }
+
+    /// Fades and draws every label into the bound framebuffer of size screen_size(in
+    /// pixels), delta is the time since the last draw(in seconds)
+    ///
+    /// Labels with [WorldLabel::occlusion_fade] read the depth buffer of the bound
+    /// framebuffer under them, so the scene has to be drawn into it first. Every read
+    /// waits for the gpu to finish drawing, so keep it to the labels that need it
+    pub fn draw(
+        &mut self,
+        ecs: &specs::World,
+        font: &Font,
+        view_proj: &Mat4,
+        camera_pos: Vec3,
+        screen_size: Vec2,
+        delta: f32,
+    ) {
+        let positions = ecs.read_storage::<Position>();
+        let mut labels = ecs.write_storage::<WorldLabel>();
+        let entities = ecs.entities();
+
+        let mut shown = Vec::new();
+        for (entity, pos, label) in (&entities, &positions, &mut labels).join() {
+            let anchor = pos.0 + label.offset;
+            let distance = distance(&anchor, &camera_pos);
+            let screen =
+                world_to_screen(view_proj, &anchor, screen_size).filter(|screen| screen.z <= 1.0);
+
+            let target = match screen {
+                _ if distance > label.max_distance => 0.0,
+                Some(screen) if label.occlusion_fade && occluded(screen, screen_size) => {
+                    label.occluded_alpha
+                }
+                Some(_) => 1.0,
+                None => 0.0,
+            };
+            let step = label.fade_speed * delta;
+            label.alpha = if target > label.alpha {
+                (label.alpha + step).min(target)
+            } else {
+                (label.alpha - step).max(target)
+            };
+
+            if let Some(screen) = screen {
+                if label.alpha > 0.0 {
+                    shown.push((distance, entity.id(), screen.xy(), &*label));
+                }
+            }
+        }
+        // the furthest labels are drawn first, so the closer ones are on top
+        shown.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        self.ui.clear();
+        for (layer, (distance, id, anchor, label)) in shown.into_iter().enumerate() {
+            let scale = label.reference_distance.map_or(1.0, |reference| {
+                (reference / distance.max(0.0001)).clamp(label.min_scale, 1.0)
+            });
+            self.add_label(id, label, font, anchor, scale, layer as i32);
+        }
+        self.ui.draw(screen_size);
+    }
+
+    /// Adds the elements of a label to the batch, anchor is the bottom middle of the label
+    fn add_label(
+        &mut self,
+        id: u32,
+        label: &WorldLabel,
+        font: &Font,
+        anchor: Vec2,
+        scale: f32,
+        layer: i32,
+    ) {
+        let alpha = label.alpha;
+        let faded = |color: &[f32; 4]| [color[0], color[1], color[2], color[3] * alpha];
+        let mut top = anchor.y - label.size(font).y * scale;
+
+        for (i, element) in label.elements.iter().enumerate() {
+            let name = format!("{}.{}", id, i);
+            let size = element_size(element, font) * scale;
+            let pos = vec2(anchor.x - size.x / 2.0, top);
+            match element {
+                LabelElement::Text {
+                    text,
+                    scale: text_scale,
+                    color,
+                } => {
+                    let run = font.shape(text, *text_scale);
+                    let quads: Vec<QuadInstance> = run
+                        .placed(vec2(0.0, 0.0), faded(color))
+                        .map(|mut quad| {
+                            quad.rect = [
+                                pos.x + quad.rect[0] * scale,
+                                pos.y + quad.rect[1] * scale,
+                                quad.rect[2] * scale,
+                                quad.rect[3] * scale,
+                            ];
+                            quad
+                        })
+                        .collect();
+                    self.ui.set(&name, font.texture.id, &quads, layer);
+                }
+                LabelElement::Bar {
+                    value,
+                    fill,
+                    background,
+                    ..
+                } => {
+                    let filled = vec2(size.x * value.clamp(0.0, 1.0), size.y);
+                    self.ui.rects(
+                        &name,
+                        &[(pos, size, faded(background)), (pos, filled, faded(fill))],
+                        layer,
+                    );
+                }
+                LabelElement::Icon {
+                    texture, uv, tint, ..
+                } => {
+                    let quad = QuadInstance {
+                        rect: [pos.x, pos.y, size.x, size.y],
+                        uv: *uv,
+                        color: faded(tint),
+                    };
+                    self.ui.set(&name, *texture, &[quad], layer);
+                }
+            }
+            top += size.y + label.spacing * scale;
+        }
+    }
+
+    /// Deletes the batch
+    pub fn delete(self) {
+        self.ui.delete();
+    }
+}
+
+/// Checks if the depth buffer of the bound framebuffer is closer than screen, a position
+/// and depth from [world_to_screen]
+fn occluded(screen: Vec3, screen_size: Vec2) -> bool {
+    let (x, y) = (screen.x.floor(), (screen_size.y - screen.y).floor());
+    if x < 0.0 || y < 0.0 || x >= screen_size.x || y >= screen_size.y {
+        return false;
+    }
+    let mut depth = 1.0f32;
+    unsafe {
+        glReadPixels(
+            x as i32,
+            y as i32,
+            1,
+            1,
+            GL_DEPTH_COMPONENT,
+            GL_FLOAT,
+            (&mut depth as *mut f32).cast(),
+        );
+    }
+    depth < screen.z - OCCLUSION_BIAS
+}
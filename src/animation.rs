@@ -0,0 +1,16 @@
+/// Module containing all things related to [self::clip::AnimationClip]
+pub mod clip;
+/// Module containing all things related to [self::crowd::CrowdRenderer]
+pub mod crowd;
+/// Module containing all things related to [self::ik::Fabrik]
+pub mod ik;
+/// Module containing all things related to [self::morph::MorphTarget]
+pub mod morph;
+/// Module containing all things related to [self::root_motion::RootMotion]
+pub mod root_motion;
+/// Module containing all things related to [self::skeleton::Skeleton]
+pub mod skeleton;
+/// Module containing all things related to [self::skinning::BonePalette]
+pub mod skinning;
+/// Module containing all things related to [self::timeline::Timeline]
+pub mod timeline;
@@ -0,0 +1,519 @@
+use std::io::{Cursor, Read};
+
+use nalgebra_glm::*;
+
+use super::skeleton::{BoneTransform, Pose};
+use crate::assets::{Asset, AssetError};
+
+/// The first bytes of every `.lhanim` file
+const MAGIC: &[u8; 4] = b"LHAN";
+/// The version of the format this code writes
+const VERSION: u32 = 1;
+/// The biggest value of a quantized rotation component
+const ROTATION_STEPS: f32 = 32767.0;
+
+/// The keyframes of one bone before they are compressed, every keyframe is a time(in seconds)
+/// and a value
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BoneTrack {
+    /// The bone the track animates
+    pub bone: usize,
+    /// Translation keyframes
+    pub translation: Vec<(f32, Vec3)>,
+    /// Rotation keyframes
+    pub rotation: Vec<(f32, Quat)>,
+    /// Scale keyframes
+    pub scale: Vec<(f32, Vec3)>,
+}
+
+/// How much error the curve reduction of [AnimationClip::compress] may add
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Compression {
+    /// The most a translation may be off(in world units)
+    pub translation_tolerance: f32,
+    /// The most a rotation may be off(in radians)
+    pub rotation_tolerance: f32,
+    /// The most a scale may be off
+    pub scale_tolerance: f32,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression {
+            translation_tolerance: 0.001,
+            rotation_tolerance: 0.002,
+            scale_tolerance: 0.001,
+        }
+    }
+}
+
+/// A curve of vectors quantized to 16 bits per component inside the range of the curve
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Vec3Curve {
+    /// Times of the keyframes relative to the duration of the clip
+    times: Vec<u16>,
+    min: Vec3,
+    extent: Vec3,
+    values: Vec<[u16; 3]>,
+}
+
+impl Vec3Curve {
+    fn new(keys: &[(f32, Vec3)], duration: f32) -> Self {
+        if keys.is_empty() {
+            return Vec3Curve::default();
+        }
+        let min = keys
+            .iter()
+            .fold(keys[0].1, |min, (_, value)| min.inf(value));
+        let max = keys
+            .iter()
+            .fold(keys[0].1, |max, (_, value)| max.sup(value));
+        let extent = max - min;
+
+        let quantize = |value: f32, min: f32, extent: f32| {
+            if extent > 0.0 {
+                ((value - min) / extent * 65535.0).round() as u16
+            } else {
+                0
+            }
+        };
+        Vec3Curve {
+            times: keys
+                .iter()
+                .map(|(t, _)| quantize_time(*t, duration))
+                .collect(),
+            min,
+            extent,
+            values: keys
+                .iter()
+                .map(|(_, v)| {
+                    [
+                        quantize(v.x, min.x, extent.x),
+                        quantize(v.y, min.y, extent.y),
+                        quantize(v.z, min.z, extent.z),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    fn value(&self, i: usize) -> Vec3 {
+        let [x, y, z] = self.values[i];
+        self.min + vec3(x as f32, y as f32, z as f32).component_mul(&self.extent) / 65535.0
+    }
+
+    fn sample(&self, time: u16) -> Option<Vec3> {
+        let (a, b, t) = find_keys(&self.times, time)?;
+        Some(lerp(&self.value(a), &self.value(b), t))
+    }
+}
+
+/// A curve of rotations quantized with the smallest three method, 6 bytes per rotation
+#[derive(Debug, Clone, PartialEq, Default)]
+struct QuatCurve {
+    times: Vec<u16>,
+    values: Vec<[u16; 3]>,
+}
+
+impl QuatCurve {
+    fn new(keys: &[(f32, Quat)], duration: f32) -> Self {
+        QuatCurve {
+            times: keys
+                .iter()
+                .map(|(t, _)| quantize_time(*t, duration))
+                .collect(),
+            values: keys.iter().map(|(_, q)| quantize_rotation(q)).collect(),
+        }
+    }
+
+    fn sample(&self, time: u16) -> Option<Quat> {
+        let (a, b, t) = find_keys(&self.times, time)?;
+        Some(quat_slerp(
+            &dequantize_rotation(self.values[a]),
+            &dequantize_rotation(self.values[b]),
+            t,
+        ))
+    }
+}
+
+/// The compressed keyframes of one bone
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedTrack {
+    /// The bone the track animates
+    pub bone: usize,
+    translation: Vec3Curve,
+    rotation: QuatCurve,
+    scale: Vec3Curve,
+}
+
+impl CompressedTrack {
+    /// Overwrites the parts of transform this track animates
+    fn sample(&self, time: u16, transform: &mut BoneTransform) {
+        if let Some(translation) = self.translation.sample(time) {
+            transform.translation = translation;
+        }
+        if let Some(rotation) = self.rotation.sample(time) {
+            transform.rotation = rotation;
+        }
+        if let Some(scale) = self.scale.sample(time) {
+            transform.scale = scale;
+        }
+    }
+}
+
+/// An animation of a [Skeleton](super::skeleton::Skeleton), stored compressed
+///
+/// Rotations are quantized to 6 bytes, translations and scales to 6 bytes inside the range
+/// of their track and keyframes that can be interpolated from their neighbours are removed.
+/// Clips can be saved as `.lhanim` files and loaded with the
+/// [AssetManager](crate::assets::AssetManager)
+///
+/// # Example
+/// ```
+/// let clip = AnimationClip::compress("walk", 1.2, &tracks, Compression::default());
+/// std::fs::write("data/walk.lhanim", clip.to_bytes())?;
+///
+/// let walk: AnimationClip = assets.load("walk.lhanim")?;
+/// let pose = walk.sample(world.env.time.elapsed, true, &skeleton.rest_pose());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    /// Name of the clip
+    pub name: String,
+    /// How long the clip is(in seconds)
+    pub duration: f32,
+    /// The tracks of the animated bones
+    pub tracks: Vec<CompressedTrack>,
+}
+
+impl AnimationClip {
+    /// Compresses raw keyframes into a clip, the keyframes of every track must be sorted by time
+    pub fn compress(
+        name: &str,
+        duration: f32,
+        tracks: &[BoneTrack],
+        compression: Compression,
+    ) -> Self {
+        let tracks = tracks
+            .iter()
+            .map(|track| {
+                let translation = reduce(
+                    &track.translation,
+                    lerp,
+                    distance,
+                    compression.translation_tolerance,
+                );
+                let rotation = reduce(
+                    &track.rotation,
+                    quat_slerp,
+                    |a, b| quat_dot(a, b).abs().min(1.0).acos() * 2.0,
+                    compression.rotation_tolerance,
+                );
+                let scale = reduce(&track.scale, lerp, distance, compression.scale_tolerance);
+
+                CompressedTrack {
+                    bone: track.bone,
+                    translation: Vec3Curve::new(&translation, duration),
+                    rotation: QuatCurve::new(&rotation, duration),
+                    scale: Vec3Curve::new(&scale, duration),
+                }
+            })
+            .collect();
+
+        AnimationClip {
+            name: name.to_string(),
+            duration,
+            tracks,
+        }
+    }
+
+    /// Samples the clip at time(in seconds), bones the clip doesnt animate keep their
+    /// transform from base
+    ///
+    /// When looping the time wraps around the duration, otherwise it is clamped
+    pub fn sample(&self, time: f32, looping: bool, base: &Pose) -> Pose {
+        let mut pose = base.clone();
+        self.sample_into(time, looping, &mut pose);
+        pose
+    }
+
+    /// Same as [AnimationClip::sample] but writes into an existing pose
+    pub fn sample_into(&self, time: f32, looping: bool, pose: &mut Pose) {
+        let time = self.time(time, looping);
+        for track in &self.tracks {
+            if let Some(transform) = pose.local.get_mut(track.bone) {
+                track.sample(time, transform);
+            }
+        }
+    }
+
+    /// Gets the transform of bone at time without sampling the other bones
+    pub fn sample_bone(
+        &self,
+        bone: usize,
+        time: f32,
+        looping: bool,
+        base: BoneTransform,
+    ) -> BoneTransform {
+        let mut transform = base;
+        if let Some(track) = self.tracks.iter().find(|track| track.bone == bone) {
+            track.sample(self.time(time, looping), &mut transform);
+        }
+        transform
+    }
+
+    /// Turns time(in seconds) into the quantized time the keyframes use
+    fn time(&self, time: f32, looping: bool) -> u16 {
+        let time = if looping && self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            time.clamp(0.0, self.duration.max(0.0))
+        };
+        quantize_time(time, self.duration)
+    }
+
+    /// Gets roughly how much memory the keyframes take(in bytes)
+    pub fn memory_size(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|track| {
+                track.translation.times.len() * 8
+                    + track.rotation.times.len() * 8
+                    + track.scale.times.len() * 8
+                    + 48
+            })
+            .sum()
+    }
+
+    /// Turns the clip into the bytes of a `.lhanim` file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.name.len() as u16).to_le_bytes());
+        out.extend_from_slice(self.name.as_bytes());
+        out.extend_from_slice(&self.duration.to_le_bytes());
+        out.extend_from_slice(&(self.tracks.len() as u32).to_le_bytes());
+
+        let write_vec3 = |out: &mut Vec<u8>, v: Vec3| {
+            for c in [v.x, v.y, v.z] {
+                out.extend_from_slice(&c.to_le_bytes());
+            }
+        };
+        let write_u16s = |out: &mut Vec<u8>, values: &[u16]| {
+            for value in values {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+
+        for track in &self.tracks {
+            out.extend_from_slice(&(track.bone as u32).to_le_bytes());
+            for curve in [&track.translation, &track.scale] {
+                out.extend_from_slice(&(curve.times.len() as u32).to_le_bytes());
+                write_vec3(&mut out, curve.min);
+                write_vec3(&mut out, curve.extent);
+                write_u16s(&mut out, &curve.times);
+                write_u16s(&mut out, &curve.values.concat());
+            }
+            out.extend_from_slice(&(track.rotation.times.len() as u32).to_le_bytes());
+            write_u16s(&mut out, &track.rotation.times);
+            write_u16s(&mut out, &track.rotation.values.concat());
+        }
+        out
+    }
+}
+
+impl Asset for AnimationClip {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        let mut reader = Cursor::new(bytes);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(AssetError::Decode("Not a .lhanim file".to_string()));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(AssetError::Decode(format!(
+                "Unsupported .lhanim version {}",
+                version
+            )));
+        }
+
+        let mut name = vec![0; read_u16(&mut reader)? as usize];
+        reader.read_exact(&mut name)?;
+        let name = String::from_bytes(name)?;
+        let duration = read_f32(&mut reader)?;
+        if !duration.is_finite() || duration < 0.0 {
+            return Err(AssetError::Decode(format!(
+                "The clip has a duration of {}",
+                duration
+            )));
+        }
+
+        // the count isn't trusted for the capacity, a broken file could ask for anything
+        let track_count = read_u32(&mut reader)?;
+        let mut tracks = Vec::new();
+        for _ in 0..track_count {
+            let bone = read_u32(&mut reader)? as usize;
+            let mut vec3_curves = Vec::with_capacity(2);
+            for _ in 0..2 {
+                let count = read_u32(&mut reader)? as usize;
+                let min = read_vec3(&mut reader)?;
+                let extent = read_vec3(&mut reader)?;
+                let times = read_u16s(&mut reader, count)?;
+                let values = read_triples(&mut reader, count)?;
+                vec3_curves.push(Vec3Curve {
+                    times,
+                    min,
+                    extent,
+                    values,
+                });
+            }
+            let count = read_u32(&mut reader)? as usize;
+            let rotation = QuatCurve {
+                times: read_u16s(&mut reader, count)?,
+                values: read_triples(&mut reader, count)?,
+            };
+
+            let scale = vec3_curves.pop().unwrap();
+            let translation = vec3_curves.pop().unwrap();
+            tracks.push(CompressedTrack {
+                bone,
+                translation,
+                rotation,
+                scale,
+            });
+        }
+
+        Ok(AnimationClip {
+            name,
+            duration,
+            tracks,
+        })
+    }
+}
+
+/// Removes the keyframes that interpolating their neighbours gets within tolerance of
+fn reduce<T: Copy>(
+    keys: &[(f32, T)],
+    interpolate: impl Fn(&T, &T, f32) -> T,
+    error: impl Fn(&T, &T) -> f32,
+    tolerance: f32,
+) -> Vec<(f32, T)> {
+    if keys.len() <= 2 {
+        return keys.to_vec();
+    }
+
+    let mut kept = vec![keys[0]];
+    let mut last = 0;
+    for next in 2..keys.len() {
+        let (start_time, start) = keys[last];
+        let (end_time, end) = keys[next];
+        // every key between the last kept key and next has to be reproduced
+        let fits = keys[last + 1..next].iter().all(|(time, value)| {
+            let t = (time - start_time) / (end_time - start_time).max(f32::EPSILON);
+            error(&interpolate(&start, &end, t), value) <= tolerance
+        });
+        if !fits {
+            last = next - 1;
+            kept.push(keys[last]);
+        }
+    }
+    kept.push(keys[keys.len() - 1]);
+    kept
+}
+
+/// Finds the keys around time and how far time is between them
+fn find_keys(times: &[u16], time: u16) -> Option<(usize, usize, f32)> {
+    let last = times.len().checked_sub(1)?;
+    let next = times.partition_point(|t| *t <= time);
+    if next == 0 {
+        return Some((0, 0, 0.0));
+    }
+    if next > last {
+        return Some((last, last, 0.0));
+    }
+    let (a, b) = (times[next - 1], times[next]);
+    let t = (time - a) as f32 / (b - a).max(1) as f32;
+    Some((next - 1, next, t))
+}
+
+fn quantize_time(time: f32, duration: f32) -> u16 {
+    if duration > 0.0 {
+        ((time / duration).clamp(0.0, 1.0) * 65535.0).round() as u16
+    } else {
+        0
+    }
+}
+
+/// Quantizes a rotation with the smallest three method
+///
+/// The biggest component is left out since it can be rebuilt from the other three, which
+/// are all between -1/sqrt(2) and 1/sqrt(2). Each gets 15 bits and the index of the left
+/// out component is stored in the top bits of the first two
+fn quantize_rotation(rotation: &Quat) -> [u16; 3] {
+    let q = quat_normalize(rotation);
+    let coords = [q.coords.x, q.coords.y, q.coords.z, q.coords.w];
+    let largest = (0..4)
+        .max_by(|a, b| coords[*a].abs().total_cmp(&coords[*b].abs()))
+        .unwrap();
+    let sign = if coords[largest] < 0.0 { -1.0 } else { 1.0 };
+
+    let mut out = [0; 3];
+    for (slot, i) in (0..4).filter(|i| *i != largest).enumerate() {
+        let value = coords[i] * sign * std::f32::consts::SQRT_2;
+        out[slot] = ((value * 0.5 + 0.5).clamp(0.0, 1.0) * ROTATION_STEPS).round() as u16;
+    }
+    out[0] |= ((largest & 1) as u16) << 15;
+    out[1] |= ((largest >> 1) as u16) << 15;
+    out
+}
+
+fn dequantize_rotation(packed: [u16; 3]) -> Quat {
+    let largest = ((packed[0] >> 15) | ((packed[1] >> 15) << 1)) as usize;
+    let mut coords = [0.0; 4];
+    let mut sum = 0.0;
+    for (slot, i) in (0..4).filter(|i| *i != largest).enumerate() {
+        let value = (packed[slot] & 0x7fff) as f32 / ROTATION_STEPS;
+        coords[i] = (value * 2.0 - 1.0) / std::f32::consts::SQRT_2;
+        sum += coords[i] * coords[i];
+    }
+    coords[largest] = (1.0 - sum).max(0.0).sqrt();
+    Quat::new(coords[3], coords[0], coords[1], coords[2])
+}
+
+fn read_u16(reader: &mut impl Read) -> Result<u16, AssetError> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, AssetError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> Result<f32, AssetError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+fn read_vec3(reader: &mut impl Read) -> Result<Vec3, AssetError> {
+    Ok(vec3(
+        read_f32(reader)?,
+        read_f32(reader)?,
+        read_f32(reader)?,
+    ))
+}
+
+fn read_u16s(reader: &mut impl Read, count: usize) -> Result<Vec<u16>, AssetError> {
+    (0..count).map(|_| read_u16(reader)).collect()
+}
+
+fn read_triples(reader: &mut impl Read, count: usize) -> Result<Vec<[u16; 3]>, AssetError> {
+    (0..count)
+        .map(|_| Ok([read_u16(reader)?, read_u16(reader)?, read_u16(reader)?]))
+        .collect()
+}
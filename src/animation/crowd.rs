@@ -0,0 +1,350 @@
+use std::mem::size_of;
+
+use nalgebra_glm::Mat4;
+
+use super::{clip::AnimationClip, skeleton::Skeleton};
+use crate::{
+    graphics::{
+        buffer::*, shader::ShaderProgram, texture::Texture, uniform::Uniform, vertex::VertexArray,
+        *,
+    },
+    ECS::mesh::{Mesh, VertexTrait},
+};
+
+/// The first attribute location of the per instance data, the model matrix takes
+/// locations 5 to 8 and the clip location 9
+pub const CROWD_INSTANCE_LOCATION: u32 = 5;
+
+/// The vertex shader of instanced crowds
+///
+/// Attributes are the same as the skinned vertex shader plus the model matrix(5 to 8) and
+/// the clip(9) of every instance
+const CROWD_VERT: &str = "#version 330 core
+layout (location = 0) in vec3 Ipos;
+layout (location = 1) in vec2 _tex_coord;
+layout (location = 2) in vec3 Inormal;
+layout (location = 3) in vec4 bone_ids;
+layout (location = 4) in vec4 bone_weights;
+layout (location = 5) in mat4 instance_model;
+layout (location = 9) in vec4 instance_clip;
+layout (location = 15) in vec4 vertex_color;
+
+uniform mat4 camera_matrix;
+uniform sampler2D bone_frames;
+uniform float time;
+
+out vec2 tex_coord;
+out vec3 normal;
+out vec4 color;
+
+mat4 frame_bone(int frame, int i) {
+    return mat4(
+        texelFetch(bone_frames, ivec2(i * 4, frame), 0),
+        texelFetch(bone_frames, ivec2(i * 4 + 1, frame), 0),
+        texelFetch(bone_frames, ivec2(i * 4 + 2, frame), 0),
+        texelFetch(bone_frames, ivec2(i * 4 + 3, frame), 0)
+    );
+}
+
+mat4 blend_skin(int a, int b, float t) {
+    mat4 skin = mat4(0.0);
+    for (int i = 0; i < 4; i++) {
+        int bone = int(bone_ids[i]);
+        skin += mix(frame_bone(a, bone), frame_bone(b, bone), t) * bone_weights[i];
+    }
+    return skin;
+}
+
+void main() {
+    // x is the first frame, y the frame count, z the frames per second and w the time offset
+    float frame = max(time + instance_clip.w, 0.0) * instance_clip.z;
+    float count = instance_clip.y;
+    int a = int(instance_clip.x + mod(floor(frame), count));
+    int b = int(instance_clip.x + mod(floor(frame) + 1.0, count));
+    mat4 skin = blend_skin(a, b, fract(frame));
+
+    gl_Position = camera_matrix * instance_model * skin * vec4(Ipos, 1.0);
+    normal = mat3(instance_model * skin) * Inormal;
+    tex_coord = _tex_coord;
+    color = vertex_color;
+}";
+
+/// Gets the built in vertex shader for instanced crowds
+///
+/// It expects the same vertices as [PaletteKind::vertex_shader](super::skinning::PaletteKind::vertex_shader)
+/// and outputs `tex_coord`, `normal` and `color`
+pub fn crowd_vertex_shader() -> &'static str {
+    CROWD_VERT
+}
+
+/// Where a clip is stored in [BakedAnimations]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BakedClip {
+    /// The row of the first frame
+    pub first_frame: usize,
+    /// How many frames the clip has
+    pub frames: usize,
+    /// How long the clip is(in seconds)
+    pub duration: f32,
+}
+
+/// Animations sampled ahead of time into a texture of bone matrices
+///
+/// Every row of the texture is one frame and every 4 texels of a row are the skinning matrix
+/// of one bone, so the vertex shader can animate each character on its own and the cpu
+/// does no work per character. The texture is deleted when it is dropped
+pub struct BakedAnimations {
+    /// How many bones every frame has
+    pub bones: usize,
+    /// How many frames were baked per second
+    pub fps: f32,
+    /// Where every baked clip is, in the order they were given
+    pub clips: Vec<BakedClip>,
+    texture: Texture,
+}
+
+impl BakedAnimations {
+    /// Samples every clip fps times per second and uploads the skinning matrices
+    ///
+    /// The clips are baked looping, so the last frame blends back into the first
+    pub fn bake(
+        skeleton: &Skeleton,
+        clips: &[&AnimationClip],
+        fps: f32,
+    ) -> Result<Self, LighthouseError> {
+        let bones = skeleton.bones.len();
+        let rest = skeleton.rest_pose();
+
+        let mut baked = Vec::with_capacity(clips.len());
+        let mut data: Vec<f32> = Vec::new();
+        let mut rows = 0;
+        for clip in clips {
+            let frames = ((clip.duration * fps).ceil() as usize).max(1);
+            for frame in 0..frames {
+                let pose = clip.sample(frame as f32 / fps, true, &rest);
+                for matrix in pose.skinning_matrices(skeleton) {
+                    data.extend_from_slice(matrix.as_slice());
+                }
+            }
+            baked.push(BakedClip {
+                first_frame: rows,
+                frames,
+                duration: clip.duration,
+            });
+            rows += frames;
+        }
+
+        let mut max_size = 0;
+        unsafe { glGetIntegerv(GL_MAX_TEXTURE_SIZE, &mut max_size) };
+        if bones * 4 > max_size as usize || rows > max_size as usize {
+            return Err(LighthouseError::Unsupported(format!(
+                "{} bones and {} frames don't fit in a {}x{} texture, bake fewer frames per second",
+                bones, rows, max_size, max_size
+            )));
+        }
+
+        let texture = Texture::new();
+        if texture.id == 0 {
+            return Err(LighthouseError::BufferAlloc(
+                "the baked animation texture".to_string(),
+            ));
+        }
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, texture.id);
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_RGBA32F as i32,
+                (bones * 4) as i32,
+                rows as i32,
+                0,
+                GL_RGBA,
+                GL_FLOAT,
+                data.as_ptr().cast(),
+            );
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_NEAREST as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_NEAREST as i32);
+            glBindTexture(GL_TEXTURE_2D, 0);
+        }
+
+        Ok(BakedAnimations {
+            bones,
+            fps,
+            clips: baked,
+            texture,
+        })
+    }
+
+    /// Binds the texture to unit and tells program to read its bones from it
+    pub fn bind(&self, program: &ShaderProgram, unit: u32) {
+        program.use_program();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0 + unit);
+            glBindTexture(GL_TEXTURE_2D, self.texture.id);
+        }
+        Uniform::new(program, "bone_frames").set_uniform_i(&[unit as i32]);
+    }
+
+    /// Deletes the texture now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+/// One character of a crowd
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrowdInstance {
+    /// Where the character is
+    pub model: Mat4,
+    /// The index of the baked clip it plays
+    pub clip: usize,
+    /// Added to the time(in seconds), so characters playing the same clip are out of step
+    pub time_offset: f32,
+    /// How fast the clip plays, 1 is normal speed
+    pub speed: f32,
+}
+
+impl CrowdInstance {
+    /// Creates a new instance playing clip at normal speed
+    pub fn new(model: Mat4, clip: usize) -> Self {
+        CrowdInstance {
+            model,
+            clip,
+            time_offset: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    /// Sets the time offset
+    pub fn time_offset(mut self, time_offset: f32) -> Self {
+        self.time_offset = time_offset;
+        self
+    }
+
+    /// Sets how fast the clip plays
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+}
+
+/// The per instance data as it is stored on the gpu
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct InstanceData {
+    model: [f32; 16],
+    clip: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for InstanceData {}
+unsafe impl bytemuck::Pod for InstanceData {}
+
+/// Draws many animated characters sharing one mesh with one instanced draw call
+///
+/// # Example
+/// ```
+/// let baked = BakedAnimations::bake(&skeleton, &[&walk, &idle], 30.0)?;
+/// let program = ShaderProgram::from_vert_frag(crowd_vertex_shader(), frag)?;
+/// let mut crowd = CrowdRenderer::new()?;
+/// crowd.attach(&mesh);
+///
+/// 'main_loop: loop {
+///     for (i, person) in people.iter().enumerate() {
+///         crowd.push(CrowdInstance::new(person.model(), person.clip).time_offset(i as f32 * 0.37), &baked);
+///     }
+///     crowd.draw(&mesh, &program, &baked, world.env.time.elapsed);
+/// }
+/// ```
+pub struct CrowdRenderer {
+    /// The instances pushed since the last draw
+    instances: Vec<InstanceData>,
+    vbo: Buffer,
+}
+
+impl CrowdRenderer {
+    /// Creates a new empty crowd renderer
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(CrowdRenderer {
+            instances: Vec::new(),
+            vbo: Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+        })
+    }
+
+    /// Adds the per instance attributes to the vertex array of mesh, call it once after
+    /// the mesh is set up
+    pub fn attach<Vertex: VertexTrait + 'static + Sync + Send>(&self, mesh: &Mesh<Vertex>) {
+        mesh.bind();
+        self.vbo.bind(BufferType::Array);
+        let stride = size_of::<InstanceData>();
+        for column in 0..5 {
+            let location = CROWD_INSTANCE_LOCATION + column;
+            unsafe {
+                glVertexAttribPointer(
+                    location,
+                    4,
+                    GL_FLOAT,
+                    GL_FALSE,
+                    stride as i32,
+                    (column as usize * size_of::<[f32; 4]>()) as *const _,
+                );
+                glEnableVertexAttribArray(location);
+                glVertexAttribDivisor(location, 1);
+            }
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Adds an instance to be drawn on the next draw, instances with a clip that was not
+    /// baked are skipped
+    pub fn push(&mut self, instance: CrowdInstance, baked: &BakedAnimations) {
+        let clip = match baked.clips.get(instance.clip) {
+            Some(clip) => clip,
+            None => return,
+        };
+        self.instances.push(InstanceData {
+            model: instance.model.as_slice().try_into().unwrap(),
+            clip: [
+                clip.first_frame as f32,
+                clip.frames as f32,
+                baked.fps * instance.speed,
+                instance.time_offset,
+            ],
+        });
+    }
+
+    /// Gets how many instances were pushed since the last draw
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Checks if no instances were pushed since the last draw
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Draws every pushed instance of mesh at time(in seconds) and empties the renderer
+    ///
+    /// The mesh must have been given to [CrowdRenderer::attach], the baked animations are
+    /// bound to texture unit 0
+    pub fn draw<Vertex: VertexTrait + 'static + Sync + Send>(
+        &mut self,
+        mesh: &Mesh<Vertex>,
+        program: &ShaderProgram,
+        baked: &BakedAnimations,
+        time: f32,
+    ) {
+        if self.instances.is_empty() {
+            return;
+        }
+
+        baked.bind(program, 0);
+        Uniform::new(program, "time").set_uniform_f(&[time]);
+        self.vbo.bind(BufferType::Array);
+        buffer_data(
+            BufferType::Array,
+            bytemuck::cast_slice(&self.instances),
+            BufferUsage::StreamDraw,
+        );
+        mesh.draw_instanced(self.instances.len());
+        VertexArray::clear_binding();
+        self.instances.clear();
+    }
+}
@@ -0,0 +1,297 @@
+use nalgebra_glm::*;
+
+use super::skeleton::{Pose, Skeleton};
+use crate::error::LighthouseError;
+
+/// Solves a limb of two bones exactly, e.g. a leg or an arm
+///
+/// The upper bone is the thigh or upper arm, the lower bone is the shin or forearm and the
+/// end bone is the foot or hand whose position is moved to the target
+///
+/// # Example
+/// ```
+/// let leg = TwoBoneIk::new(&skeleton, "thigh_l", "shin_l", "foot_l")?.pole(knee_forward);
+///
+/// // plant the foot on the terrain under it
+/// let foot = pose.global_matrices(&skeleton)[leg.end].column(3).xyz();
+/// leg.solve(&skeleton, &mut pose, vec3(foot.x, terrain.height(foot.x, foot.z), foot.z), 1.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwoBoneIk {
+    /// The first bone of the limb
+    pub upper: usize,
+    /// The second bone of the limb, must be a child of upper
+    pub lower: usize,
+    /// The bone at the end of the limb, must be a child of lower
+    pub end: usize,
+    /// A point(in the space of the mesh) the middle joint bends towards, e.g. in front of the
+    /// knee, without one the limb keeps bending the way it already does
+    pub pole: Option<Vec3>,
+}
+
+impl TwoBoneIk {
+    /// Creates a new solver from the names of the bones
+    pub fn new(
+        skeleton: &Skeleton,
+        upper: &str,
+        lower: &str,
+        end: &str,
+    ) -> Result<Self, LighthouseError> {
+        let chain = chain(skeleton, upper, end)?;
+        if chain.len() != 3 || skeleton.bones[chain[1]].name != lower {
+            return Err(LighthouseError::InvalidInput(format!(
+                "{} has to be the child of {} and the parent of {}",
+                lower, upper, end
+            )));
+        }
+        Ok(TwoBoneIk {
+            upper: chain[0],
+            lower: chain[1],
+            end: chain[2],
+            pole: None,
+        })
+    }
+
+    /// Sets the point the middle joint bends towards
+    pub fn pole(mut self, pole: Vec3) -> Self {
+        self.pole = Some(pole);
+        self
+    }
+
+    /// Moves the end bone towards target(in the space of the mesh)
+    ///
+    /// weight is from 0(the pose is not changed) to 1(the end reaches the target if it can),
+    /// targets that are too far away get the limb stretched towards them
+    pub fn solve(&self, skeleton: &Skeleton, pose: &mut Pose, target: Vec3, weight: f32) {
+        let global = pose.global_matrices(skeleton);
+        let rotations = global_rotations(skeleton, pose);
+        let a = position(&global[self.upper]);
+        let b = position(&global[self.lower]);
+        let c = position(&global[self.end]);
+
+        let upper_length = distance(&a, &b);
+        let lower_length = distance(&b, &c);
+        // a limb with a bone of no length, e.g. every joint at the same place, can't bend
+        // and the checks are written so NaN lengths return too
+        if !(upper_length > f32::EPSILON && lower_length > f32::EPSILON) {
+            return;
+        }
+        // slightly shorter than fully stretched so the joint never flips
+        let target_length =
+            distance(&a, &target).clamp(f32::EPSILON, (upper_length + lower_length) * 0.9999);
+
+        // the current angles of the limb and the angles it needs
+        let angle = |x: &Vec3, y: &Vec3| {
+            dot(&safe_normalize(x), &safe_normalize(y))
+                .clamp(-1.0, 1.0)
+                .acos()
+        };
+        let ac_ab = angle(&(c - a), &(b - a));
+        let ba_bc = angle(&(a - b), &(c - b));
+        let ac_at = angle(&(c - a), &(target - a));
+        let wanted_ac_ab = ((lower_length * lower_length
+            - upper_length * upper_length
+            - target_length * target_length)
+            / (-2.0 * upper_length * target_length))
+            .clamp(-1.0, 1.0)
+            .acos();
+        let wanted_ba_bc = ((target_length * target_length
+            - upper_length * upper_length
+            - lower_length * lower_length)
+            / (-2.0 * upper_length * lower_length))
+            .clamp(-1.0, 1.0)
+            .acos();
+
+        let bend_towards = match self.pole {
+            Some(pole) => pole - a,
+            None => b - a,
+        };
+        let bend_axis = safe_normalize(&cross(&(c - a), &bend_towards));
+        let turn_axis = safe_normalize(&cross(&(c - a), &(target - a)));
+        if bend_axis.norm_squared() == 0.0 {
+            return;
+        }
+
+        let upper_rotation = rotations[self.upper];
+        let lower_rotation = rotations[self.lower];
+        let local_axis =
+            |rotation: &Quat, axis: &Vec3| quat_rotate_vec3(&quat_inverse(rotation), axis);
+
+        let mut upper_delta = quat_angle_axis(
+            wanted_ac_ab - ac_ab,
+            &local_axis(&upper_rotation, &bend_axis),
+        );
+        if turn_axis.norm_squared() > 0.0 {
+            upper_delta *= quat_angle_axis(ac_at, &local_axis(&upper_rotation, &turn_axis));
+        }
+        let lower_delta = quat_angle_axis(
+            wanted_ba_bc - ba_bc,
+            &local_axis(&lower_rotation, &bend_axis),
+        );
+
+        let upper = &mut pose.local[self.upper].rotation;
+        *upper = quat_slerp(upper, &(*upper * upper_delta), weight);
+        let lower = &mut pose.local[self.lower].rotation;
+        *lower = quat_slerp(lower, &(*lower * lower_delta), weight);
+    }
+}
+
+/// Solves a chain of any number of bones with FABRIK, e.g. a tail, a spine or a tentacle
+///
+/// FABRIK moves the joints back and forth along the chain until the end reaches the
+/// target, then the bones are rotated to point at their new joints
+///
+/// # Example
+/// ```
+/// let tail = Fabrik::new(&skeleton, "tail_0", "tail_6")?;
+/// tail.solve(&skeleton, &mut pose, ball_pos);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fabrik {
+    /// The bones of the chain, from the root to the end
+    pub chain: Vec<usize>,
+    /// The most times the joints are moved back and forth
+    pub iterations: usize,
+    /// How close the end has to be to the target to stop early(in the units of the mesh)
+    pub tolerance: f32,
+}
+
+impl Fabrik {
+    /// Creates a new solver for the chain from the bone called root to the bone called end
+    pub fn new(skeleton: &Skeleton, root: &str, end: &str) -> Result<Self, LighthouseError> {
+        let chain = chain(skeleton, root, end)?;
+        if chain.len() < 2 {
+            return Err(LighthouseError::InvalidInput(
+                "A FABRIK chain needs at least 2 bones".to_string(),
+            ));
+        }
+        Ok(Fabrik {
+            chain,
+            iterations: 10,
+            tolerance: 0.001,
+        })
+    }
+
+    /// Sets the most times the joints are moved back and forth
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets how close the end has to be to the target to stop early
+    pub fn tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Moves the end of the chain towards target(in the space of the mesh)
+    pub fn solve(&self, skeleton: &Skeleton, pose: &mut Pose, target: Vec3) {
+        let global = pose.global_matrices(skeleton);
+        let mut joints: Vec<Vec3> = self
+            .chain
+            .iter()
+            .map(|bone| position(&global[*bone]))
+            .collect();
+        let lengths: Vec<f32> = joints.windows(2).map(|w| distance(&w[0], &w[1])).collect();
+        let root = joints[0];
+        let last = joints.len() - 1;
+
+        if distance(&root, &target) >= lengths.iter().sum::<f32>() {
+            // out of reach, the chain points straight at the target
+            let direction = safe_normalize(&(target - root));
+            for i in 0..last {
+                joints[i + 1] = joints[i] + direction * lengths[i];
+            }
+        } else {
+            for _ in 0..self.iterations {
+                if distance(&joints[last], &target) <= self.tolerance {
+                    break;
+                }
+                joints[last] = target;
+                for i in (0..last).rev() {
+                    let direction = safe_normalize(&(joints[i] - joints[i + 1]));
+                    joints[i] = joints[i + 1] + direction * lengths[i];
+                }
+                joints[0] = root;
+                for i in 0..last {
+                    let direction = safe_normalize(&(joints[i + 1] - joints[i]));
+                    joints[i + 1] = joints[i] + direction * lengths[i];
+                }
+            }
+        }
+
+        // every bone is turned so its child ends up on the solved joint
+        for i in 0..last {
+            let global = pose.global_matrices(skeleton);
+            let rotations = global_rotations(skeleton, pose);
+            let bone = self.chain[i];
+            let from = position(&global[self.chain[i + 1]]) - position(&global[bone]);
+            let to = joints[i + 1] - joints[i];
+            rotate_global(pose, &rotations, bone, rotation_between(&from, &to));
+        }
+    }
+}
+
+/// Gets the bones from the bone called root down to the bone called end
+pub fn chain(skeleton: &Skeleton, root: &str, end: &str) -> Result<Vec<usize>, LighthouseError> {
+    let root_index = skeleton.find(root).ok_or_else(|| {
+        LighthouseError::InvalidInput(format!("There is no bone called {}", root))
+    })?;
+    let mut bone = skeleton
+        .find(end)
+        .ok_or_else(|| LighthouseError::InvalidInput(format!("There is no bone called {}", end)))?;
+
+    let mut chain = vec![bone];
+    while bone != root_index {
+        bone = skeleton.bones[bone].parent.ok_or_else(|| {
+            LighthouseError::InvalidInput(format!("{} is not a child of {}", end, root))
+        })?;
+        chain.push(bone);
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Gets the rotation of every bone relative to the mesh
+fn global_rotations(skeleton: &Skeleton, pose: &Pose) -> Vec<Quat> {
+    let mut rotations: Vec<Quat> = Vec::with_capacity(pose.local.len());
+    for (bone, local) in skeleton.bones.iter().zip(&pose.local) {
+        rotations.push(match bone.parent {
+            Some(parent) => rotations[parent] * local.rotation,
+            None => local.rotation,
+        });
+    }
+    rotations
+}
+
+/// Rotates bone by rotation in the space of the mesh
+fn rotate_global(pose: &mut Pose, rotations: &[Quat], bone: usize, rotation: Quat) {
+    let global = rotations[bone];
+    let local = &mut pose.local[bone].rotation;
+    // parent * local = global, so the new local is parent^-1 * rotation * global
+    let parent = global * quat_inverse(local);
+    *local = quat_normalize(&(quat_inverse(&parent) * rotation * global));
+}
+
+/// Gets the shortest rotation that turns from into to
+fn rotation_between(from: &Vec3, to: &Vec3) -> Quat {
+    let from = safe_normalize(from);
+    let to = safe_normalize(to);
+    let axis = cross(&from, &to);
+    if axis.norm_squared() < f32::EPSILON {
+        return quat_identity();
+    }
+    quat_angle_axis(dot(&from, &to).clamp(-1.0, 1.0).acos(), &normalize(&axis))
+}
+
+fn position(matrix: &Mat4) -> Vec3 {
+    matrix.column(3).xyz()
+}
+
+fn safe_normalize(v: &Vec3) -> Vec3 {
+    if v.norm_squared() > 0.0 {
+        normalize(v)
+    } else {
+        *v
+    }
+}
@@ -0,0 +1,209 @@
+use nalgebra_glm::Vec3;
+
+use crate::graphics::{recovery::ContextId, shader::ShaderProgram, uniform::Uniform, *};
+
+/// The most morph targets that can be blended at once
+pub const MAX_MORPH_TARGETS: usize = 32;
+
+/// Glsl that declares the morph uniforms, `vec3 morph_position(vec3)` and
+/// `vec3 morph_normal(vec3)`
+///
+/// Paste it into a vertex shader to support morph targets, see [MorphBuffer]
+pub const MORPH_GLSL: &str = "uniform samplerBuffer morph_deltas;
+uniform float morph_weights[32];
+uniform int morph_target_count;
+uniform int morph_vertex_count;
+
+vec3 morph_delta(int target, int kind) {
+    return texelFetch(morph_deltas, (target * morph_vertex_count + gl_VertexID) * 2 + kind).xyz;
+}
+
+vec3 morph_position(vec3 pos) {
+    for (int i = 0; i < morph_target_count; i++) {
+        pos += morph_delta(i, 0) * morph_weights[i];
+    }
+    return pos;
+}
+
+vec3 morph_normal(vec3 normal) {
+    for (int i = 0; i < morph_target_count; i++) {
+        normal += morph_delta(i, 1) * morph_weights[i];
+    }
+    return normalize(normal);
+}";
+
+/// The built in vertex shader for morphed meshes, `{morph}` is replaced by [MORPH_GLSL]
+const MORPH_VERT: &str = "#version 330 core
+layout (location = 0) in vec3 Ipos;
+layout (location = 1) in vec2 _tex_coord;
+layout (location = 2) in vec3 Inormal;
+layout (location = 15) in vec4 vertex_color;
+
+uniform mat4 camera_matrix;
+uniform mat4 model;
+
+{morph}
+
+out vec2 tex_coord;
+out vec3 normal;
+out vec4 color;
+
+void main() {
+    gl_Position = camera_matrix * model * vec4(morph_position(Ipos), 1.0);
+    normal = mat3(model) * morph_normal(Inormal);
+    tex_coord = _tex_coord;
+    color = vertex_color;
+}";
+
+/// Gets the built in vertex shader for morphed meshes
+///
+/// It expects the position, uv and normal at locations 0 to 2 and outputs `tex_coord`,
+/// `normal` and `color`
+pub fn morph_vertex_shader() -> String {
+    MORPH_VERT.replace("{morph}", MORPH_GLSL)
+}
+
+/// A shape a mesh can blend towards, e.g. a smile or a blink
+///
+/// Every vertex of the mesh has a delta, the morphed vertex is the vertex plus the delta
+/// times the weight of the target
+#[derive(Debug, Clone, PartialEq)]
+pub struct MorphTarget {
+    /// Name of the target
+    pub name: String,
+    /// How much every vertex moves
+    pub position_deltas: Vec<Vec3>,
+    /// How much the normal of every vertex changes, empty if the normals dont change
+    pub normal_deltas: Vec<Vec3>,
+}
+
+/// The morph targets of a mesh on the gpu, stored in a buffer texture
+///
+/// The buffer and texture are deleted when it is dropped, see [ContextId]
+///
+/// # Example
+/// ```
+/// let mesh = Mesh::new(vertices, vec![3, 2, 3], indices)?.with_morph_targets(targets)?;
+/// let morphs = MorphBuffer::new(&mesh.morph_targets, mesh.vertices.len())?;
+///
+/// 'main_loop: loop {
+///     let smile = (world.env.time.elapsed.sin() + 1.0) / 2.0;
+///     morphs.bind(&program, 1, &[smile, blink]);
+///     mesh.draw();
+/// }
+/// ```
+pub struct MorphBuffer {
+    /// How many targets there are
+    pub targets: usize,
+    /// How many vertices every target has
+    pub vertex_count: usize,
+    buffer: u32,
+    texture: u32,
+    context: ContextId,
+}
+
+impl MorphBuffer {
+    /// Uploads the targets of a mesh with vertex_count vertices
+    pub fn new(targets: &[MorphTarget], vertex_count: usize) -> Result<Self, LighthouseError> {
+        if targets.len() > MAX_MORPH_TARGETS {
+            return Err(LighthouseError::Unsupported(format!(
+                "At most {} morph targets are supported but got {}",
+                MAX_MORPH_TARGETS,
+                targets.len()
+            )));
+        }
+
+        // every vertex of every target is a position delta then a normal delta, padded to
+        // 4 floats since gl 3.3 has no rgb buffer textures
+        let mut data: Vec<f32> = Vec::with_capacity(targets.len() * vertex_count * 8);
+        for target in targets {
+            if target.position_deltas.len() != vertex_count
+                || !(target.normal_deltas.is_empty() || target.normal_deltas.len() == vertex_count)
+            {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "Morph target {} needs a delta for all {} vertices",
+                    target.name, vertex_count
+                )));
+            }
+            for i in 0..vertex_count {
+                let position = target.position_deltas[i];
+                let normal = target.normal_deltas.get(i).copied().unwrap_or_default();
+                data.extend_from_slice(&[position.x, position.y, position.z, 0.0]);
+                data.extend_from_slice(&[normal.x, normal.y, normal.z, 0.0]);
+            }
+        }
+
+        let mut buffer = 0;
+        let mut texture = 0;
+        unsafe {
+            glGenBuffers(1, &mut buffer);
+            glGenTextures(1, &mut texture);
+        }
+        let morphs = MorphBuffer {
+            targets: targets.len(),
+            vertex_count,
+            buffer,
+            texture,
+            context: ContextId::current(),
+        };
+        if buffer == 0 || texture == 0 {
+            return Err(LighthouseError::BufferAlloc(
+                "the morph target buffer".to_string(),
+            ));
+        }
+
+        unsafe {
+            glBindBuffer(GL_TEXTURE_BUFFER, buffer);
+            glBufferData(
+                GL_TEXTURE_BUFFER,
+                (data.len() * std::mem::size_of::<f32>()) as isize,
+                data.as_ptr().cast(),
+                GL_STATIC_DRAW,
+            );
+            glBindBuffer(GL_TEXTURE_BUFFER, 0);
+        }
+
+        Ok(morphs)
+    }
+
+    /// Makes program morph with weights, unit is the texture unit the deltas are bound to
+    ///
+    /// Targets without a weight get 0
+    pub fn bind(&self, program: &ShaderProgram, unit: u32, weights: &[f32]) {
+        program.use_program();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0 + unit);
+            glBindTexture(GL_TEXTURE_BUFFER, self.texture);
+            glTexBuffer(GL_TEXTURE_BUFFER, GL_RGBA32F, self.buffer);
+        }
+
+        Uniform::new(program, "morph_deltas").set_uniform_i(&[unit as i32]);
+        Uniform::new(program, "morph_target_count").set_uniform_i(&[self.targets as i32]);
+        Uniform::new(program, "morph_vertex_count").set_uniform_i(&[self.vertex_count as i32]);
+        let mut all_weights = [0.0; MAX_MORPH_TARGETS];
+        for (weight, value) in all_weights.iter_mut().zip(weights) {
+            *weight = *value;
+        }
+        unsafe {
+            glUniform1fv(
+                Uniform::new(program, "morph_weights").0,
+                MAX_MORPH_TARGETS as i32,
+                all_weights.as_ptr(),
+            );
+        }
+    }
+
+    /// Deletes the buffer and texture now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for MorphBuffer {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe {
+                glDeleteBuffers(1, &self.buffer);
+                glDeleteTextures(1, &self.texture);
+            }
+        }
+    }
+}
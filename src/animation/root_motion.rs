@@ -0,0 +1,219 @@
+use nalgebra_glm::*;
+
+use super::{
+    clip::AnimationClip,
+    skeleton::{BoneTransform, Pose},
+};
+use crate::ECS::mesh::PosRot;
+
+/// What happens to the motion of the root bone, see [RootMotion]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RootMotionMode {
+    /// The motion is taken out of the pose and returned so it can be applied to the entity
+    #[default]
+    Apply,
+    /// The motion is taken out of the pose and thrown away, the animation plays in place
+    Discard,
+    /// The motion stays in the pose, the mesh walks away from the entity
+    Keep,
+}
+
+/// How much the root bone moved since the last frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RootDelta {
+    /// How far the root moved(in the space of the mesh)
+    pub translation: Vec3,
+    /// How much the root turned
+    pub rotation: Quat,
+}
+
+impl RootDelta {
+    /// The delta that doesnt move anything
+    pub fn zero() -> Self {
+        RootDelta {
+            translation: vec3(0.0, 0.0, 0.0),
+            rotation: quat_identity(),
+        }
+    }
+
+    /// Moves and turns object by the delta
+    ///
+    /// The translation is rotated by the rotation of object first, so a character walks
+    /// the way it faces
+    pub fn apply(&self, object: &mut impl PosRot) {
+        let rotation = axis_angle_to_quat(object.get_rot());
+        *object.set_pos() += quat_rotate_vec3(&rotation, &self.translation);
+        *object.set_rot() = quat_to_axis_angle(&(rotation * self.rotation));
+    }
+
+    /// Gets the delta of doing self and then next
+    fn then(&self, next: &RootDelta) -> RootDelta {
+        RootDelta {
+            translation: self.translation + quat_rotate_vec3(&self.rotation, &next.translation),
+            rotation: self.rotation * next.rotation,
+        }
+    }
+}
+
+impl Default for RootDelta {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+/// Takes the motion of the root bone out of an animation, so walk cycles move the entity
+/// instead of sliding the mesh away from it
+///
+/// # Example
+/// ```
+/// let mut root_motion = RootMotion::new(skeleton.find("hips").unwrap());
+///
+/// 'main_loop: loop {
+///     let mut pose = walk.sample(time, true, &skeleton.rest_pose());
+///     root_motion.extract(&walk, time, true, &mut pose).apply(&mut player);
+///     palette.upload(&pose.skinning_matrices(&skeleton))?;
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootMotion {
+    /// The bone that carries the motion, usually the hips
+    pub root: usize,
+    /// What happens to the motion
+    pub mode: RootMotionMode,
+    /// Which axes of the translation are extracted, 1 to extract and 0 to leave it in the
+    /// pose, by default the vertical axis stays so the hips still bob up and down
+    pub translation_axes: Vec3,
+    /// Whether the rotation of the root is extracted too
+    pub extract_rotation: bool,
+    /// The time(in seconds) of the last extracted frame
+    last_time: Option<f32>,
+}
+
+impl RootMotion {
+    /// Creates a new root motion extractor for bone root
+    pub fn new(root: usize) -> Self {
+        RootMotion {
+            root,
+            mode: RootMotionMode::Apply,
+            translation_axes: vec3(1.0, 0.0, 1.0),
+            extract_rotation: true,
+            last_time: None,
+        }
+    }
+
+    /// Sets what happens to the motion
+    pub fn mode(mut self, mode: RootMotionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets which axes of the translation are extracted
+    pub fn translation_axes(mut self, axes: Vec3) -> Self {
+        self.translation_axes = axes;
+        self
+    }
+
+    /// Sets whether the rotation of the root is extracted
+    pub fn extract_rotation(mut self, extract: bool) -> Self {
+        self.extract_rotation = extract;
+        self
+    }
+
+    /// Forgets the last frame, call it when a different clip starts playing so the jump
+    /// between the clips isnt taken as motion
+    pub fn reset(&mut self) {
+        self.last_time = None;
+    }
+
+    /// Takes the motion of the root since the last call out of pose, which must have been
+    /// sampled from clip at time(in seconds)
+    ///
+    /// Returns how much the root moved, which is zero for [RootMotionMode::Discard],
+    /// [RootMotionMode::Keep] and the first frame after [RootMotion::reset]
+    pub fn extract(
+        &mut self,
+        clip: &AnimationClip,
+        time: f32,
+        looping: bool,
+        pose: &mut Pose,
+    ) -> RootDelta {
+        if self.mode == RootMotionMode::Keep {
+            self.last_time = Some(time);
+            return RootDelta::zero();
+        }
+        let base = match pose.local.get(self.root) {
+            Some(transform) => *transform,
+            None => return RootDelta::zero(),
+        };
+
+        let sample = |time: f32, looping: bool| clip.sample_bone(self.root, time, looping, base);
+        let start = sample(0.0, false);
+        let current = sample(time, looping);
+        let delta = match self.last_time {
+            Some(last_time) if self.mode == RootMotionMode::Apply => {
+                let last = sample(last_time, looping);
+                if looping && clip.duration > 0.0 && time < last_time {
+                    // the clip wrapped around, the motion is the rest of the last loop plus
+                    // the start of the new one
+                    let end = sample(clip.duration, false);
+                    self.delta(&start, &last, &end)
+                        .then(&self.delta(&start, &start, &current))
+                } else {
+                    self.delta(&start, &last, &current)
+                }
+            }
+            _ => RootDelta::zero(),
+        };
+        self.last_time = Some(time);
+
+        // the extracted parts of the root stay where they were at the start of the clip
+        let root = &mut pose.local[self.root];
+        root.translation = current.translation
+            + (start.translation - current.translation).component_mul(&self.translation_axes);
+        if self.extract_rotation {
+            root.rotation = start.rotation;
+        }
+        delta
+    }
+
+    /// Gets the extracted motion between two transforms of the root
+    ///
+    /// The translation is turned so it is relative to the way the root faced at the start
+    /// of the clip, that way it can be applied in the space of the entity
+    fn delta(&self, start: &BoneTransform, from: &BoneTransform, to: &BoneTransform) -> RootDelta {
+        let translation = (to.translation - from.translation).component_mul(&self.translation_axes);
+        if self.extract_rotation {
+            let facing = start.rotation * quat_inverse(&from.rotation);
+            RootDelta {
+                translation: quat_rotate_vec3(&facing, &translation),
+                rotation: quat_inverse(&from.rotation) * to.rotation,
+            }
+        } else {
+            RootDelta {
+                translation,
+                rotation: quat_identity(),
+            }
+        }
+    }
+}
+
+/// Turns the axis(xyz) and angle(w) rotation of a [PosRot] into a quaternion
+fn axis_angle_to_quat(rot: &Vec4) -> Quat {
+    let axis = rot.xyz();
+    if axis.norm_squared() == 0.0 {
+        quat_identity()
+    } else {
+        quat_angle_axis(rot.w, &normalize(&axis))
+    }
+}
+
+/// Turns a quaternion into the axis(xyz) and angle(w) rotation of a [PosRot]
+fn quat_to_axis_angle(rotation: &Quat) -> Vec4 {
+    let angle = quat_angle(rotation);
+    if angle.abs() < f32::EPSILON {
+        vec4(0.0, 1.0, 0.0, 0.0)
+    } else {
+        let axis = quat_axis(rotation);
+        vec4(axis.x, axis.y, axis.z, angle)
+    }
+}
@@ -0,0 +1,150 @@
+use nalgebra_glm::*;
+
+use crate::error::LighthouseError;
+
+/// Translation, rotation and scale of a bone relative to its parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoneTransform {
+    /// Translation of the bone
+    pub translation: Vec3,
+    /// Rotation of the bone
+    pub rotation: Quat,
+    /// Scale of the bone
+    pub scale: Vec3,
+}
+
+impl BoneTransform {
+    /// Gets the transform that doesnt move, rotate or scale anything
+    pub fn identity() -> Self {
+        BoneTransform {
+            translation: vec3(0.0, 0.0, 0.0),
+            rotation: quat_identity(),
+            scale: vec3(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Turns the transform into a matrix, scale is applied first and translation last
+    pub fn matrix(&self) -> Mat4 {
+        translation(&self.translation) * quat_to_mat4(&self.rotation) * scaling(&self.scale)
+    }
+
+    /// Blends between self and other, t is from 0(self) to 1(other)
+    pub fn lerp(&self, other: &BoneTransform, t: f32) -> BoneTransform {
+        BoneTransform {
+            translation: lerp(&self.translation, &other.translation, t),
+            rotation: quat_slerp(&self.rotation, &other.rotation, t),
+            scale: lerp(&self.scale, &other.scale, t),
+        }
+    }
+}
+
+impl Default for BoneTransform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// A bone of a [Skeleton]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bone {
+    /// Name of the bone
+    pub name: String,
+    /// Index of the parent bone, None for the root
+    pub parent: Option<usize>,
+    /// Transform of the bone relative to its parent when the mesh is not animated
+    pub rest: BoneTransform,
+    /// Turns a vertex of the mesh into the space of the bone
+    pub inverse_bind: Mat4,
+}
+
+/// The bones a skinned mesh is animated with
+///
+/// A parent always comes before its children, so the bones can be walked in order
+///
+/// # Example
+/// ```
+/// let skeleton = Skeleton::new(vec![hips, spine, head])?;
+/// let mut pose = skeleton.rest_pose();
+/// pose.local[skeleton.find("head").unwrap()].rotation = quat_angle_axis(0.3, &Vec3::y());
+///
+/// let matrices = pose.skinning_matrices(&skeleton);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    /// The bones of the skeleton
+    pub bones: Vec<Bone>,
+}
+
+impl Skeleton {
+    /// Creates a new skeleton, fails if a bone comes before its parent
+    pub fn new(bones: Vec<Bone>) -> Result<Self, LighthouseError> {
+        for (i, bone) in bones.iter().enumerate() {
+            if let Some(parent) = bone.parent {
+                if parent >= i {
+                    return Err(LighthouseError::InvalidInput(format!(
+                        "Bone {} ({}) comes before its parent {}",
+                        i, bone.name, parent
+                    )));
+                }
+            }
+        }
+        Ok(Skeleton { bones })
+    }
+
+    /// Gets the index of the bone called name
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|bone| bone.name == name)
+    }
+
+    /// Gets the pose every bone has when the mesh is not animated
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            local: self.bones.iter().map(|bone| bone.rest).collect(),
+        }
+    }
+}
+
+/// The transform of every bone of a [Skeleton] at one moment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    /// Transform of every bone relative to its parent
+    pub local: Vec<BoneTransform>,
+}
+
+impl Pose {
+    /// Blends between self and other bone by bone, t is from 0(self) to 1(other)
+    pub fn blend(&self, other: &Pose, t: f32) -> Pose {
+        Pose {
+            local: self
+                .local
+                .iter()
+                .zip(&other.local)
+                .map(|(a, b)| a.lerp(b, t))
+                .collect(),
+        }
+    }
+
+    /// Gets the transform of every bone relative to the mesh
+    pub fn global_matrices(&self, skeleton: &Skeleton) -> Vec<Mat4> {
+        let mut global: Vec<Mat4> = Vec::with_capacity(self.local.len());
+        for (bone, local) in skeleton.bones.iter().zip(&self.local) {
+            let matrix = match bone.parent {
+                Some(parent) => global[parent] * local.matrix(),
+                None => local.matrix(),
+            };
+            global.push(matrix);
+        }
+        global
+    }
+
+    /// Gets the matrices the vertices are skinned with, one per bone
+    ///
+    /// These are what is uploaded to a [BonePalette](super::skinning::BonePalette)
+    pub fn skinning_matrices(&self, skeleton: &Skeleton) -> Vec<Mat4> {
+        self.global_matrices(skeleton)
+            .into_iter()
+            .zip(&skeleton.bones)
+            .map(|(global, bone)| global * bone.inverse_bind)
+            .collect()
+    }
+}
@@ -0,0 +1,248 @@
+use nalgebra_glm::Mat4;
+
+use crate::graphics::{recovery::ContextId, shader::ShaderProgram, uniform::Uniform, *};
+
+/// The most bones a [PaletteKind::Uniform] palette can hold
+pub const MAX_UNIFORM_BONES: usize = 128;
+
+/// The vertex shader of skinned meshes, `{palette}` is replaced by the palette code
+///
+/// Attributes are the position(0), uv(1), normal(2), bone indices as floats(3),
+/// bone weights(4) and the vertex color(15)
+const SKINNED_VERT: &str = "#version 330 core
+layout (location = 0) in vec3 Ipos;
+layout (location = 1) in vec2 _tex_coord;
+layout (location = 2) in vec3 Inormal;
+layout (location = 3) in vec4 bone_ids;
+layout (location = 4) in vec4 bone_weights;
+layout (location = 15) in vec4 vertex_color;
+
+uniform mat4 camera_matrix;
+uniform mat4 model;
+
+{palette}
+
+out vec2 tex_coord;
+out vec3 normal;
+out vec4 color;
+
+void main() {
+    mat4 skin = bone(int(bone_ids.x)) * bone_weights.x
+        + bone(int(bone_ids.y)) * bone_weights.y
+        + bone(int(bone_ids.z)) * bone_weights.z
+        + bone(int(bone_ids.w)) * bone_weights.w;
+
+    gl_Position = camera_matrix * model * skin * vec4(Ipos, 1.0);
+    normal = mat3(model * skin) * Inormal;
+    tex_coord = _tex_coord;
+    color = vertex_color;
+}";
+
+/// Where the bone matrices are stored on the gpu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteKind {
+    /// A uniform buffer, fast but holds at most [MAX_UNIFORM_BONES] bones
+    Uniform,
+    /// A buffer texture, a bit slower but holds as many bones as needed
+    Texture,
+}
+
+impl PaletteKind {
+    /// Gets the glsl that declares the palette and `mat4 bone(int i)`
+    pub fn glsl(self) -> String {
+        match self {
+            PaletteKind::Uniform => format!(
+                "layout (std140) uniform Bones {{
+    mat4 bones[{}];
+}};
+
+mat4 bone(int i) {{
+    return bones[i];
+}}",
+                MAX_UNIFORM_BONES
+            ),
+            PaletteKind::Texture => "uniform samplerBuffer bone_texture;
+
+mat4 bone(int i) {
+    return mat4(
+        texelFetch(bone_texture, i * 4),
+        texelFetch(bone_texture, i * 4 + 1),
+        texelFetch(bone_texture, i * 4 + 2),
+        texelFetch(bone_texture, i * 4 + 3)
+    );
+}"
+            .to_string(),
+        }
+    }
+
+    /// Gets the built in skinned vertex shader for this palette
+    ///
+    /// It expects the position, uv, normal, bone indices and bone weights at locations 0 to 4
+    /// and outputs `tex_coord`, `normal` and `color`
+    pub fn vertex_shader(self) -> String {
+        SKINNED_VERT.replace("{palette}", &self.glsl())
+    }
+}
+
+/// The bone matrices of a skinned mesh on the gpu
+///
+/// The vertices are skinned in the vertex shader, so the cpu only has to sample the
+/// animation and upload one matrix per bone. The buffer and texture are deleted when it is
+/// dropped, see [ContextId]
+///
+/// # Example
+/// ```
+/// let program = ShaderProgram::from_vert_frag(&PaletteKind::Uniform.vertex_shader(), frag)?;
+/// let mut palette = BonePalette::new(PaletteKind::Uniform)?;
+///
+/// 'main_loop: loop {
+///     palette.upload(&pose.skinning_matrices(&skeleton))?;
+///     palette.bind(&program, 0);
+///     mesh.draw();
+/// }
+/// ```
+pub struct BonePalette {
+    /// Where the matrices are stored
+    pub kind: PaletteKind,
+    /// The buffer holding the matrices
+    buffer: u32,
+    /// The buffer texture reading the buffer, 0 for uniform palettes
+    texture: u32,
+    /// How many bones were uploaded last
+    bones: usize,
+    context: ContextId,
+}
+
+impl BonePalette {
+    /// Creates a new empty palette
+    pub fn new(kind: PaletteKind) -> Result<Self, LighthouseError> {
+        let mut buffer = 0;
+        let mut texture = 0;
+        unsafe {
+            glGenBuffers(1, &mut buffer);
+            if kind == PaletteKind::Texture {
+                glGenTextures(1, &mut texture);
+            }
+        }
+        let palette = BonePalette {
+            kind,
+            buffer,
+            texture,
+            bones: 0,
+            context: ContextId::current(),
+        };
+        if buffer == 0 || (kind == PaletteKind::Texture && texture == 0) {
+            return Err(LighthouseError::BufferAlloc(
+                "the bone palette buffer".to_string(),
+            ));
+        }
+
+        if kind == PaletteKind::Uniform {
+            // the whole block has to be backed by the buffer, even the unused bones
+            unsafe {
+                glBindBuffer(GL_UNIFORM_BUFFER, buffer);
+                glBufferData(
+                    GL_UNIFORM_BUFFER,
+                    (MAX_UNIFORM_BONES * std::mem::size_of::<Mat4>()) as isize,
+                    std::ptr::null(),
+                    GL_DYNAMIC_DRAW,
+                );
+                glBindBuffer(GL_UNIFORM_BUFFER, 0);
+            }
+        }
+
+        Ok(palette)
+    }
+
+    /// Gets how many bones were uploaded last
+    pub fn len(&self) -> usize {
+        self.bones
+    }
+
+    /// Checks if no bones were uploaded
+    pub fn is_empty(&self) -> bool {
+        self.bones == 0
+    }
+
+    /// Uploads the skinning matrices, see [Pose::skinning_matrices](super::skeleton::Pose::skinning_matrices)
+    pub fn upload(&mut self, matrices: &[Mat4]) -> Result<(), LighthouseError> {
+        let data: Vec<f32> = matrices
+            .iter()
+            .flat_map(|matrix| matrix.as_slice().iter().copied())
+            .collect();
+        let size = (data.len() * std::mem::size_of::<f32>()) as isize;
+
+        match self.kind {
+            PaletteKind::Uniform => {
+                if matrices.len() > MAX_UNIFORM_BONES {
+                    return Err(LighthouseError::Unsupported(format!(
+                        "A uniform bone palette holds at most {} bones but got {}, use PaletteKind::Texture",
+                        MAX_UNIFORM_BONES,
+                        matrices.len()
+                    )));
+                }
+                unsafe {
+                    glBindBuffer(GL_UNIFORM_BUFFER, self.buffer);
+                    glBufferSubData(GL_UNIFORM_BUFFER, 0, size, data.as_ptr().cast());
+                    glBindBuffer(GL_UNIFORM_BUFFER, 0);
+                }
+            }
+            PaletteKind::Texture => unsafe {
+                glBindBuffer(GL_TEXTURE_BUFFER, self.buffer);
+                glBufferData(
+                    GL_TEXTURE_BUFFER,
+                    size,
+                    data.as_ptr().cast(),
+                    GL_DYNAMIC_DRAW,
+                );
+                glBindBuffer(GL_TEXTURE_BUFFER, 0);
+            },
+        }
+
+        self.bones = matrices.len();
+        Ok(())
+    }
+
+    /// Makes program read its bones from this palette
+    ///
+    /// slot is the uniform buffer binding point for [PaletteKind::Uniform] and the texture
+    /// unit for [PaletteKind::Texture]
+    pub fn bind(&self, program: &ShaderProgram, slot: u32) {
+        program.use_program();
+        unsafe {
+            match self.kind {
+                PaletteKind::Uniform => {
+                    let block = with_cstr("Bones", |name| {
+                        glGetUniformBlockIndex(program.0, name.as_ptr().cast())
+                    });
+                    if block != GL_INVALID_INDEX {
+                        glUniformBlockBinding(program.0, block, slot);
+                    }
+                    glBindBufferBase(GL_UNIFORM_BUFFER, slot, self.buffer);
+                }
+                PaletteKind::Texture => {
+                    glActiveTexture(GL_TEXTURE0 + slot);
+                    glBindTexture(GL_TEXTURE_BUFFER, self.texture);
+                    glTexBuffer(GL_TEXTURE_BUFFER, GL_RGBA32F, self.buffer);
+                    Uniform::new(program, "bone_texture").set_uniform_i(&[slot as i32]);
+                }
+            }
+        }
+    }
+
+    /// Deletes the buffer and texture of the palette now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for BonePalette {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe {
+                glDeleteBuffers(1, &self.buffer);
+                if self.texture != 0 {
+                    glDeleteTextures(1, &self.texture);
+                }
+            }
+        }
+    }
+}
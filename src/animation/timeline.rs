@@ -0,0 +1,463 @@
+use nalgebra_glm::*;
+
+use super::skeleton::BoneTransform;
+use crate::{
+    assets::{json::Json, Asset, AssetError},
+    error::LighthouseError,
+};
+
+/// The keyframes of the transform of one thing in a [Timeline], every keyframe is a
+/// time(in seconds) and a transform
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformTrack {
+    /// The name of what the track moves, the game finds it by name
+    pub target: String,
+    /// The keyframes, sorted by time
+    pub keys: Vec<(f32, BoneTransform)>,
+}
+
+impl TransformTrack {
+    /// Creates a track for target, the keys are sorted by time
+    pub fn new(target: &str, mut keys: Vec<(f32, BoneTransform)>) -> Self {
+        keys.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        TransformTrack {
+            target: target.to_string(),
+            keys,
+        }
+    }
+
+    /// Gets the transform at time, blended between the keyframes around it. Before the
+    /// first and after the last keyframe the transform stays on it
+    pub fn sample(&self, time: f32) -> Option<BoneTransform> {
+        let next = self.keys.partition_point(|(key_time, _)| *key_time <= time);
+        match (next.checked_sub(1), self.keys.get(next)) {
+            (None, Some((_, first))) => Some(*first),
+            (Some(last), None) => Some(self.keys[last].1),
+            (Some(last), Some((next_time, next))) => {
+                let (last_time, last) = self.keys[last];
+                let t = (time - last_time) / (next_time - last_time).max(f32::EPSILON);
+                Some(last.lerp(next, t))
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// Something that happens at a time of a [Timeline]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineCue {
+    /// Switches to the camera called camera
+    Cut {
+        /// The name of the camera
+        camera: String,
+    },
+    /// Starts the animation clip called clip on target
+    Clip {
+        /// The name of what plays the clip
+        target: String,
+        /// The name of the clip
+        clip: String,
+        /// How fast the clip plays, 1 is normal speed
+        speed: f32,
+    },
+    /// Plays the sound called sound
+    Audio {
+        /// The name of the sound
+        sound: String,
+        /// How loud the sound is, from 0 to 1
+        volume: f32,
+    },
+    /// An event the game handles itself, like a door opening
+    Event(String),
+}
+
+/// A cutscene made of keyframed transforms and cues over time, authored as data
+///
+/// Timelines are read from JSON, every array is optional and times are in seconds. A
+/// rotation is a quaternion as x, y, z and w, transform keyframes without a rotation or
+/// scale aren't rotated or scaled. The times of the cues have to be from 0 to the duration
+///
+/// ```json
+/// {
+///     "duration": 8.0,
+///     "transforms": [
+///         {"target": "ship", "keys": [
+///             {"time": 0.0, "pos": [0, 0, -20]},
+///             {"time": 6.0, "pos": [0, 2, 0], "rot": [0, 0.38, 0, 0.92]}
+///         ]}
+///     ],
+///     "cuts": [{"time": 0.0, "camera": "wide"}, {"time": 4.0, "camera": "close"}],
+///     "clips": [{"time": 1.0, "target": "pilot", "clip": "wave", "speed": 1.0}],
+///     "audio": [{"time": 0.5, "sound": "engine.ogg", "volume": 0.8}],
+///     "events": [{"time": 6.0, "name": "land"}]
+/// }
+/// ```
+///
+/// It is played by a [TimelinePlayer]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+    /// How long the timeline is(in seconds)
+    pub duration: f32,
+    /// The tracks of the transforms
+    pub transforms: Vec<TransformTrack>,
+    /// The cues with their time, sorted by time
+    pub cues: Vec<(f32, TimelineCue)>,
+}
+
+impl Timeline {
+    /// Creates an empty timeline duration seconds long
+    pub fn new(duration: f32) -> Self {
+        Timeline {
+            duration: duration.max(0.0),
+            transforms: Vec::new(),
+            cues: Vec::new(),
+        }
+    }
+
+    /// Adds a transform track, see [TransformTrack::new]
+    pub fn transform(mut self, target: &str, keys: Vec<(f32, BoneTransform)>) -> Self {
+        self.transforms.push(TransformTrack::new(target, keys));
+        self
+    }
+
+    /// Adds a cue at time, after the cues at the same time
+    pub fn cue(mut self, time: f32, cue: TimelineCue) -> Self {
+        let index = self.cues.partition_point(|(cue_time, _)| *cue_time <= time);
+        self.cues.insert(index, (time, cue));
+        self
+    }
+
+    /// Reads a timeline from the text of a JSON file, see [Timeline]
+    pub fn from_json(src: &str) -> Result<Self, LighthouseError> {
+        let json = Json::parse(src)?;
+        let duration = number(&json, "duration", "The timeline")?;
+        let mut timeline = Timeline::new(duration);
+
+        for track in array(&json, "transforms") {
+            let target = string(track, "target", "A transform track")?;
+            let keys = array(track, "keys")
+                .iter()
+                .map(|key| {
+                    let what = format!("A keyframe of {}", target);
+                    let mut transform = BoneTransform::identity();
+                    if let Some([x, y, z]) = floats(key, "pos", &what)? {
+                        transform.translation = vec3(x, y, z);
+                    }
+                    if let Some([x, y, z, w]) = floats(key, "rot", &what)? {
+                        let rotation = Quat::new(w, x, y, z);
+                        if quat_length(&rotation) < f32::EPSILON {
+                            return Err(LighthouseError::Parse(format!(
+                                "{} has a rot of length 0",
+                                what
+                            )));
+                        }
+                        transform.rotation = quat_normalize(&rotation);
+                    }
+                    if let Some([x, y, z]) = floats(key, "scale", &what)? {
+                        transform.scale = vec3(x, y, z);
+                    }
+                    Ok((number(key, "time", &what)?, transform))
+                })
+                .collect::<Result<_, LighthouseError>>()?;
+            timeline = timeline.transform(&target, keys);
+        }
+
+        for cut in array(&json, "cuts") {
+            let cue = TimelineCue::Cut {
+                camera: string(cut, "camera", "A cut")?,
+            };
+            timeline = timeline.cue(cue_time(cut, "A cut", duration)?, cue);
+        }
+        for clip in array(&json, "clips") {
+            let cue = TimelineCue::Clip {
+                target: string(clip, "target", "A clip")?,
+                clip: string(clip, "clip", "A clip")?,
+                speed: number(clip, "speed", "A clip").unwrap_or(1.0),
+            };
+            timeline = timeline.cue(cue_time(clip, "A clip", duration)?, cue);
+        }
+        for audio in array(&json, "audio") {
+            let cue = TimelineCue::Audio {
+                sound: string(audio, "sound", "An audio cue")?,
+                volume: number(audio, "volume", "An audio cue").unwrap_or(1.0),
+            };
+            timeline = timeline.cue(cue_time(audio, "An audio cue", duration)?, cue);
+        }
+        for event in array(&json, "events") {
+            let cue = TimelineCue::Event(string(event, "name", "An event")?);
+            timeline = timeline.cue(cue_time(event, "An event", duration)?, cue);
+        }
+        Ok(timeline)
+    }
+
+    /// Gets the track that moves target
+    pub fn track(&self, target: &str) -> Option<&TransformTrack> {
+        self.transforms.iter().find(|track| track.target == target)
+    }
+
+    /// Gets the camera of the last cut at or before time
+    pub fn camera_at(&self, time: f32) -> Option<&str> {
+        self.cues
+            .iter()
+            .take_while(|(cue_time, _)| *cue_time <= time)
+            .filter_map(|(_, cue)| match cue {
+                TimelineCue::Cut { camera } => Some(camera.as_str()),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Gets the cues from start to end, with start and without end unless with_end is set
+    fn cues_between(
+        &self,
+        start: f32,
+        end: f32,
+        with_end: bool,
+    ) -> impl Iterator<Item = &TimelineCue> {
+        self.cues
+            .iter()
+            .filter(move |(time, _)| *time >= start && (*time < end || with_end && *time <= end))
+            .map(|(_, cue)| cue)
+    }
+}
+
+impl Asset for Timeline {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        Timeline::from_json(&String::from_bytes(bytes)?)
+            .map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+/// What a [TimelinePlayer] drives, the game finds the targets by name and does the cues
+///
+/// # Example
+/// ```
+/// impl TimelineHandler for GameObject {
+///     fn transform(&mut self, target: &str, transform: &BoneTransform) {
+///         if let Some(object) = self.find_mut(target) {
+///             *object.set_pos() = transform.translation;
+///         }
+///     }
+///
+///     fn cue(&mut self, cue: &TimelineCue) {
+///         match cue {
+///             TimelineCue::Cut { camera } => self.active_camera = camera.clone(),
+///             TimelineCue::Clip { target, clip, speed } => self.play_clip(target, clip, *speed),
+///             TimelineCue::Audio { sound, volume } => self.audio.play(sound, *volume),
+///             TimelineCue::Event(name) => self.on_event(name),
+///         }
+///     }
+/// }
+/// ```
+pub trait TimelineHandler {
+    /// Moves target to transform, called for every track every update
+    fn transform(&mut self, target: &str, transform: &BoneTransform);
+
+    /// Does a cue, called once when the player passes its time
+    fn cue(&mut self, cue: &TimelineCue);
+
+    /// Called when a timeline that doesn't loop reaches its end
+    fn finished(&mut self) {}
+}
+
+/// Plays a [Timeline], it keeps the time and gives the transforms and cues to a
+/// [TimelineHandler]
+///
+/// # Example
+/// ```
+/// let intro: Timeline = assets.load("cutscenes/intro.json")?;
+/// let mut player = TimelinePlayer::new();
+/// player.play();
+///
+/// // every update
+/// player.update(&intro, ctx.time.delta, &mut handler);
+/// if player.is_finished() {
+///     // back to the game
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelinePlayer {
+    /// Where the player is in the timeline(in seconds)
+    pub time: f32,
+    /// How fast the timeline plays, 1 is normal speed
+    pub speed: f32,
+    /// Whether the timeline starts over at its end
+    pub looping: bool,
+    /// Whether the time is moving
+    playing: bool,
+    /// Whether a timeline that doesn't loop reached its end
+    finished: bool,
+}
+
+impl TimelinePlayer {
+    /// Creates a stopped player at the start
+    pub fn new() -> Self {
+        TimelinePlayer {
+            time: 0.0,
+            speed: 1.0,
+            looping: false,
+            playing: false,
+            finished: false,
+        }
+    }
+
+    /// Sets how fast the timeline plays
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Sets whether the timeline starts over at its end
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Starts or resumes playing, a finished timeline starts over
+    pub fn play(&mut self) {
+        if self.finished {
+            self.time = 0.0;
+            self.finished = false;
+        }
+        self.playing = true;
+    }
+
+    /// Stops the time where it is
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Stops playing and goes back to the start
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.finished = false;
+        self.time = 0.0;
+    }
+
+    /// Jumps to time(in seconds) without doing the cues in between, e.g. to skip a part
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.max(0.0);
+        self.finished = false;
+    }
+
+    /// Checks if the time is moving
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Checks if a timeline that doesn't loop reached its end
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Moves the time by delta(in seconds) times the speed while playing, does the cues it
+    /// passed and moves every target
+    pub fn update(&mut self, timeline: &Timeline, delta: f32, handler: &mut impl TimelineHandler) {
+        if !self.playing {
+            return;
+        }
+        let start = self.time;
+        let end = start + delta * self.speed.max(0.0);
+        if end < timeline.duration {
+            timeline
+                .cues_between(start, end, false)
+                .for_each(|cue| handler.cue(cue));
+            self.time = end;
+        } else if self.looping && timeline.duration > 0.0 {
+            timeline
+                .cues_between(start, timeline.duration, true)
+                .for_each(|cue| handler.cue(cue));
+            // delta can be longer than the whole timeline after a hitch
+            self.time = (end - timeline.duration) % timeline.duration;
+            timeline
+                .cues_between(0.0, self.time, false)
+                .for_each(|cue| handler.cue(cue));
+        } else {
+            timeline
+                .cues_between(start, timeline.duration, true)
+                .for_each(|cue| handler.cue(cue));
+            self.time = timeline.duration;
+            self.playing = false;
+            self.finished = true;
+        }
+        self.apply(timeline, handler);
+        if self.finished {
+            handler.finished();
+        }
+    }
+
+    /// Moves every target to where it is at the current time, e.g. after
+    /// [TimelinePlayer::seek] while paused
+    pub fn apply(&self, timeline: &Timeline, handler: &mut impl TimelineHandler) {
+        for track in &timeline.transforms {
+            if let Some(transform) = track.sample(self.time) {
+                handler.transform(&track.target, &transform);
+            }
+        }
+    }
+}
+
+impl Default for TimelinePlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gets the elements of the array called key, none if there isn't one
+fn array<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+    json.get(key).and_then(Json::as_array).unwrap_or(&[])
+}
+
+/// Gets the number called key of what
+fn number(json: &Json, key: &str, what: &str) -> Result<f32, LighthouseError> {
+    json.get(key)
+        .and_then(Json::as_f64)
+        .map(|number| number as f32)
+        .ok_or_else(|| LighthouseError::Parse(format!("{} has no {}", what, key)))
+}
+
+/// Gets the time of the cue what, cues before the start or after the end would never happen
+fn cue_time(json: &Json, what: &str, duration: f32) -> Result<f32, LighthouseError> {
+    let time = number(json, "time", what)?;
+    if !(0.0..=duration).contains(&time) {
+        return Err(LighthouseError::Parse(format!(
+            "{} at {} is outside of the timeline, it is {} seconds long",
+            what, time, duration
+        )));
+    }
+    Ok(time)
+}
+
+/// Gets the string called key of what
+fn string(json: &Json, key: &str, what: &str) -> Result<String, LighthouseError> {
+    json.get(key)
+        .and_then(Json::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| LighthouseError::Parse(format!("{} has no {}", what, key)))
+}
+
+/// Gets the array of N numbers called key of what, None if there isn't one
+fn floats<const N: usize>(
+    json: &Json,
+    key: &str,
+    what: &str,
+) -> Result<Option<[f32; N]>, LighthouseError> {
+    let Some(array) = json.get(key) else {
+        return Ok(None);
+    };
+    let numbers: Option<Vec<f32>> = array
+        .as_array()
+        .map(|array| {
+            array
+                .iter()
+                .map(|number| number.as_f64().map(|number| number as f32))
+                .collect()
+        })
+        .unwrap_or(None);
+    numbers
+        .and_then(|numbers| numbers.try_into().ok())
+        .map(Some)
+        .ok_or_else(|| {
+            LighthouseError::Parse(format!("{} has a {} that isn't {} numbers", what, key, N))
+        })
+}
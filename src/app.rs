@@ -0,0 +1,225 @@
+use crate::{
+    graphics::{
+        shader::ShaderProgram,
+        window::{Window, WindowEvent},
+    },
+    ECS::{
+        game_loop::GameLoop,
+        mouse::Mouse,
+        world::{Enviroment, GameObjectTrait, World},
+    },
+};
+
+#[cfg(feature = "sdl")]
+use crate::{error::LighthouseError, graphics::window::SdlWindow};
+
+use device_query::{DeviceQuery, DeviceState};
+
+/// A function that sees the events first, see [App::on_event]
+type EventHook = Box<dyn FnMut(&WindowEvent) -> bool>;
+
+/// The settings of an [App]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppConfig {
+    /// The title of the window
+    pub title: String,
+    /// Width of the window(in pixels)
+    pub width: u32,
+    /// Height of the window(in pixels)
+    pub height: u32,
+    /// Whether the window waits for the screen to refresh before it shows a frame
+    pub vsync: bool,
+    /// The color the screen is cleared to every frame
+    pub clear_color: [f32; 4],
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            title: "lighthouse".to_string(),
+            width: 800,
+            height: 600,
+            vsync: true,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl AppConfig {
+    /// Sets the title of the window
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Sets the size of the window(in pixels)
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Sets whether the window waits for the screen to refresh
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    /// Sets the color the screen is cleared to
+    pub fn clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+}
+
+/// A window and the main loop of a game, so it doesn't have to be written by hand
+///
+/// Every frame [App::run] polls the events, stops when the window is closed, updates the
+/// world, calls the frame callback and renders the world, which clears and swaps
+///
+/// # Example
+/// ```
+/// let app = App::new(AppConfig::default().title("My game").clear_color([0.2, 0.3, 0.3, 1.0]))?
+///     .on_event(|event| {
+///         println!("{:?}", event);
+///         true
+///     });
+///
+/// // opengl is loaded now, so the objects can upload their meshes
+/// let objects = GameObject::new();
+/// let shader_program = ShaderProgram::from_vert_frag(vert, frag)?;
+///
+/// app.run(objects, shader_program, |world, dt| {
+///     world.objects.player.set_pos().x += 1.0 * dt;
+///     if world.objects.player.is_dead() {
+///         world.env.quit = true;
+///     }
+/// });
+/// ```
+pub struct App {
+    /// The settings the app was made with
+    pub config: AppConfig,
+    win: Box<dyn Window>,
+    on_event: Option<EventHook>,
+}
+
+impl App {
+    /// Creates the window with [SdlWindow] and loads opengl
+    #[cfg(feature = "sdl")]
+    pub fn new(config: AppConfig) -> Result<Self, LighthouseError> {
+        let win = SdlWindow::new(&config.title, config.width, config.height)?;
+        Ok(App::with_window(config, Box::new(win)))
+    }
+
+    /// Uses a window of any backend, see [Window], and loads opengl with it
+    ///
+    /// The title and size of the config aren't used since the window was already made
+    pub fn with_window(config: AppConfig, win: Box<dyn Window>) -> Self {
+        win.load_gl();
+        win.set_vsync(config.vsync);
+        App {
+            config,
+            win,
+            on_event: None,
+        }
+    }
+
+    /// Gets the window
+    pub fn window(&self) -> &dyn Window {
+        self.win.as_ref()
+    }
+
+    /// Sets a function that sees every event before the world does, when it gives false the
+    /// event is not given to the world. A [WindowEvent::Quit] it gives false for doesn't stop
+    /// the app, e.g. to ask to save first
+    pub fn on_event(mut self, on_event: impl FnMut(&WindowEvent) -> bool + 'static) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    /// Makes the world and runs it until the window is closed or
+    /// [Enviroment::quit] is set, frame is called every frame after the world updated with
+    /// the world and the time since the last frame(in seconds, scaled like [Time::delta])
+    ///
+    /// [Time::delta]: crate::ECS::time::Time::delta
+    pub fn run<GameObject: GameObjectTrait>(
+        self,
+        objects: GameObject,
+        shader_program: ShaderProgram,
+        mut frame: impl FnMut(&mut World<GameObject>, f32),
+    ) {
+        let (mut world, mut on_event) = self.into_world(objects, shader_program);
+        while App::poll(&mut world, &mut on_event) {
+            world.update();
+            let delta = world.env.time.delta;
+            frame(&mut world, delta);
+            world.render();
+        }
+    }
+
+    /// Like [App::run] but the world is updated at the fixed rate of game_loop, see
+    /// [GameLoop]
+    ///
+    /// fixed_update is called after every update of the world and frame before every
+    /// render with the alpha, how far the frame is between the last two updates
+    ///
+    /// # Example
+    /// ```
+    /// app.run_fixed(
+    ///     objects,
+    ///     shader_program,
+    ///     GameLoop::new(60.0),
+    ///     |world| world.run_system("physics", &mut PhysicsSystem),
+    ///     |world, alpha| world.objects.player.interpolate(alpha),
+    /// );
+    /// ```
+    pub fn run_fixed<GameObject: GameObjectTrait>(
+        self,
+        objects: GameObject,
+        shader_program: ShaderProgram,
+        mut game_loop: GameLoop,
+        mut fixed_update: impl FnMut(&mut World<GameObject>),
+        mut frame: impl FnMut(&mut World<GameObject>, f32),
+    ) {
+        let (mut world, mut on_event) = self.into_world(objects, shader_program);
+        while App::poll(&mut world, &mut on_event) {
+            game_loop.frame(&mut world, &mut fixed_update, &mut frame);
+        }
+    }
+
+    /// Makes the world the app runs
+    fn into_world<GameObject: GameObjectTrait>(
+        self,
+        objects: GameObject,
+        shader_program: ShaderProgram,
+    ) -> (World<GameObject>, Option<EventHook>) {
+        let device = DeviceState::new();
+        let mouse: Mouse = device.clone().into();
+        let mut world = World::new(
+            Enviroment::new(self.win, shader_program, device, mouse),
+            objects,
+        );
+        world.renderer.clear_color = self.config.clear_color;
+        (world, self.on_event)
+    }
+
+    /// Gives the events of the window to the world, gives false when the app has to stop
+    fn poll<GameObject: GameObjectTrait>(
+        world: &mut World<GameObject>,
+        on_event: &mut Option<EventHook>,
+    ) -> bool {
+        world.env.mouse.mouse = world.env.device.get_mouse();
+        while let Some(event) = world.env.win.poll_event() {
+            if let Some(on_event) = on_event {
+                if !on_event(&event) {
+                    continue;
+                }
+            }
+            match event {
+                WindowEvent::Quit => world.env.quit = true,
+                event => world.push_event(event),
+            }
+        }
+        !world.env.quit
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use image::DynamicImage;
+
+/// Module containing all things related to [self::json::Json]
+pub mod json;
+/// Module containing all things related to [self::locale::Localization]
+pub mod locale;
+/// Module containing all things related to [self::pack::Pack]
+pub mod pack;
+/// Module containing all things related to [self::xml::XmlElement]
+pub mod xml;
+
+use pack::Pack;
+
+/// This is an asset error, it is used by [AssetManager] and [Pack]
+#[derive(Debug)]
+pub enum AssetError {
+    /// The asset could not be found in any pack or on the disk
+    NotFound(String),
+    /// Reading from the disk failed
+    Io(std::io::Error),
+    /// The pack is not a valid `.lhpack` file
+    InvalidPack(String),
+    /// The asset was found but could not be turned into the requested type
+    Decode(String),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::NotFound(name) => write!(f, "{} could not be found", name),
+            AssetError::Io(err) => write!(f, "{}", err),
+            AssetError::InvalidPack(message) => write!(f, "Invalid pack: {}", message),
+            AssetError::Decode(message) => write!(f, "Couldn't decode the asset: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AssetError {
+    fn from(err: std::io::Error) -> Self {
+        AssetError::Io(err)
+    }
+}
+
+/// Something that can be loaded by the [AssetManager]
+///
+/// # Example
+/// ```
+/// struct MyLevel(String);
+///
+/// impl Asset for MyLevel {
+///     fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+///         Ok(MyLevel(String::from_bytes(bytes)?))
+///     }
+/// }
+///
+/// let level: MyLevel = assets.load("levels/1.txt")?;
+/// ```
+pub trait Asset: Sized {
+    /// Turns the raw bytes of the asset into the asset
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError>;
+}
+
+impl Asset for Vec<u8> {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        Ok(bytes)
+    }
+}
+
+impl Asset for String {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        String::from_utf8(bytes).map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+impl Asset for DynamicImage {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        image::load_from_memory(&bytes).map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+/// Loads assets from mounted [Pack]s and from loose files
+///
+/// Packs are searched from the last mounted to the first, so a pack mounted later
+/// can override the assets of an earlier one. If no pack has the asset it is
+/// loaded from `root` on the disk.
+///
+/// # Example
+/// ```
+/// let mut assets = AssetManager::new("data");
+/// assets.mount("data/textures.lhpack")?;
+///
+/// let img: DynamicImage = assets.load("image.jpg")?;
+/// ```
+pub struct AssetManager {
+    /// The directory loose files are loaded from
+    pub root: PathBuf,
+    /// The mounted packs
+    packs: Vec<Arc<Pack>>,
+}
+
+impl AssetManager {
+    /// Creates a new asset manager that loads loose files from root
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        AssetManager {
+            root: root.into(),
+            packs: Vec::new(),
+        }
+    }
+
+    /// Mounts the pack at path, only the index is read
+    pub fn mount(&mut self, path: impl AsRef<Path>) -> Result<(), AssetError> {
+        self.packs.push(Arc::new(Pack::open(path)?));
+        Ok(())
+    }
+
+    /// Checks if the asset exists in a pack or on the disk
+    pub fn exists(&self, name: &str) -> bool {
+        self.packs.iter().any(|pack| pack.contains(name)) || self.root.join(name).is_file()
+    }
+
+    /// Opens the asset for streaming reads
+    ///
+    /// Assets inside of packs are decompressed while they are read
+    pub fn open(&self, name: &str) -> Result<Box<dyn Read + Send>, AssetError> {
+        open_asset(&self.root, &self.packs, name)
+    }
+
+    /// Reads all the bytes of the asset
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, AssetError> {
+        read_asset(&self.root, &self.packs, name)
+    }
+
+    /// Loads the asset as type A, see [Asset]
+    pub fn load<A: Asset>(&self, name: &str) -> Result<A, AssetError> {
+        A::from_bytes(self.read(name)?)
+    }
+
+    /// Loads the asset on a background thread
+    ///
+    /// This is meant for large assets, the returned [AssetStream] can be polled
+    /// every frame until the asset is ready
+    pub fn stream<A: Asset + Send + 'static>(&self, name: &str) -> AssetStream<A> {
+        let (sender, receiver) = mpsc::channel();
+        let root = self.root.clone();
+        let packs = self.packs.clone();
+        let name = name.to_string();
+
+        thread::spawn(move || {
+            let asset = read_asset(&root, &packs, &name).and_then(A::from_bytes);
+            // the stream may have been dropped, in that case nobody wants the asset
+            let _ = sender.send(asset);
+        });
+
+        AssetStream { receiver }
+    }
+}
+
+/// An asset that is being loaded in the background, see [AssetManager::stream]
+pub struct AssetStream<A> {
+    receiver: Receiver<Result<A, AssetError>>,
+}
+
+impl<A> AssetStream<A> {
+    /// Returns the asset if it has finished loading, None otherwise
+    pub fn poll(&self) -> Option<Result<A, AssetError>> {
+        match self.receiver.try_recv() {
+            Ok(asset) => Some(asset),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(AssetError::Decode(
+                "The loading thread stopped before sending the asset".to_string(),
+            ))),
+        }
+    }
+
+    /// Blocks until the asset has finished loading
+    pub fn wait(self) -> Result<A, AssetError> {
+        self.receiver.recv().unwrap_or_else(|_| {
+            Err(AssetError::Decode(
+                "The loading thread stopped before sending the asset".to_string(),
+            ))
+        })
+    }
+}
+
+fn open_asset(
+    root: &Path,
+    packs: &[Arc<Pack>],
+    name: &str,
+) -> Result<Box<dyn Read + Send>, AssetError> {
+    if let Some(pack) = packs.iter().rev().find(|pack| pack.contains(name)) {
+        return pack.open_entry(name);
+    }
+
+    match File::open(root.join(name)) {
+        Ok(file) => Ok(Box::new(BufReader::new(file))),
+        Err(_) => Err(AssetError::NotFound(name.to_string())),
+    }
+}
+
+fn read_asset(root: &Path, packs: &[Arc<Pack>], name: &str) -> Result<Vec<u8>, AssetError> {
+    if let Some(pack) = packs.iter().rev().find(|pack| pack.contains(name)) {
+        return pack.read(name);
+    }
+
+    fs::read(root.join(name)).map_err(|_| AssetError::NotFound(name.to_string()))
+}
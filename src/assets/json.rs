@@ -0,0 +1,243 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::error::LighthouseError;
+
+/// How deep arrays and objects can be inside each other, so a broken file can't overflow
+/// the stack
+const MAX_DEPTH: u32 = 256;
+
+/// A parsed JSON value, for reading files like glTF scenes
+///
+/// Objects keep their keys in the order of the file
+///
+/// # Example
+/// ```
+/// let json = Json::parse(r#"{"name": "lamp", "size": [1, 2]}"#)?;
+/// assert_eq!(json.get("name").and_then(Json::as_str), Some("lamp"));
+/// assert_eq!(json.get("size").and_then(|size| size.at(1)).and_then(Json::as_f64), Some(2.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// Any number, they are all stored as f64 like in javascript
+    Number(f64),
+    /// A string with its escapes already turned into characters
+    String(String),
+    /// `[...]`
+    Array(Vec<Json>),
+    /// `{...}`, the keys with their values
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses the text of a JSON file
+    pub fn parse(src: &str) -> Result<Json, LighthouseError> {
+        let mut chars = src.chars().peekable();
+        let value = parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(error(format!("Unexpected {} after the value", c))),
+        }
+    }
+
+    /// Gets the value of key if it is an object that has it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Gets the element at index if it is an array that is long enough
+    pub fn at(&self, index: usize) -> Option<&Json> {
+        self.as_array().and_then(|array| array.get(index))
+    }
+
+    /// Gets the number if it is one
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// Gets the number if it is a whole number that isn't negative
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|number| *number >= 0.0 && number.fract() == 0.0)
+            .map(|number| number as usize)
+    }
+
+    /// Gets the bool if it is one
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Gets the string if it is one
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Gets the elements if it is an array
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Makes the error of malformed JSON
+fn error(message: String) -> LighthouseError {
+    LighthouseError::Parse(format!("Invalid JSON: {}", message))
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// Takes the next character if it is expected, otherwise it is an error
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), LighthouseError> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(error(format!("Expected {} but found {}", expected, c))),
+        None => Err(error(format!("Expected {} but the file ended", expected))),
+    }
+}
+
+/// Takes the rest of a keyword after its first character
+fn keyword(chars: &mut Peekable<Chars>, rest: &str, value: Json) -> Result<Json, LighthouseError> {
+    for expected in rest.chars() {
+        expect(chars, expected)?;
+    }
+    Ok(value)
+}
+
+/// Parses a value that is inside of depth arrays and objects
+fn parse_value(chars: &mut Peekable<Chars>, depth: u32) -> Result<Json, LighthouseError> {
+    skip_whitespace(chars);
+    match chars.peek().copied() {
+        Some('{' | '[') if depth >= MAX_DEPTH => Err(error(format!(
+            "Arrays and objects are nested deeper than {}",
+            MAX_DEPTH
+        ))),
+        Some('{') => parse_object(chars, depth),
+        Some('[') => parse_array(chars, depth),
+        Some('"') => parse_string(chars).map(Json::String),
+        Some('t') => keyword(chars, "true", Json::Bool(true)),
+        Some('f') => keyword(chars, "false", Json::Bool(false)),
+        Some('n') => keyword(chars, "null", Json::Null),
+        Some(c) if c == '-' || c.is_ascii_digit() => parse_number(chars),
+        Some(c) => Err(error(format!("Unexpected {}", c))),
+        None => Err(error("The file ended before a value".to_string())),
+    }
+}
+
+fn parse_object(chars: &mut Peekable<Chars>, depth: u32) -> Result<Json, LighthouseError> {
+    expect(chars, '{')?;
+    let mut fields = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Ok(Json::Object(fields));
+    }
+    loop {
+        skip_whitespace(chars);
+        let key = parse_string(chars)?;
+        skip_whitespace(chars);
+        expect(chars, ':')?;
+        fields.push((key, parse_value(chars, depth + 1)?));
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') => return Ok(Json::Object(fields)),
+            _ => return Err(error("Expected , or } in an object".to_string())),
+        }
+    }
+}
+
+fn parse_array(chars: &mut Peekable<Chars>, depth: u32) -> Result<Json, LighthouseError> {
+    expect(chars, '[')?;
+    let mut values = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&']').is_some() {
+        return Ok(Json::Array(values));
+    }
+    loop {
+        values.push(parse_value(chars, depth + 1)?);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some(']') => return Ok(Json::Array(values)),
+            _ => return Err(error("Expected , or ] in an array".to_string())),
+        }
+    }
+}
+
+/// Reads the 4 hex digits of a `\u` escape
+fn parse_hex(chars: &mut Peekable<Chars>) -> Result<u32, LighthouseError> {
+    let mut code = 0;
+    for _ in 0..4 {
+        let digit = chars
+            .next()
+            .and_then(|c| c.to_digit(16))
+            .ok_or_else(|| error("Invalid \\u escape".to_string()))?;
+        code = code * 16 + digit;
+    }
+    Ok(code)
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, LighthouseError> {
+    expect(chars, '"')?;
+    let mut out = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(out),
+            Some('\\') => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('b') => out.push('\u{8}'),
+                Some('f') => out.push('\u{c}'),
+                Some('u') => {
+                    let mut code = parse_hex(chars)?;
+                    // characters outside of the basic plane are written as two escapes
+                    if (0xD800..0xDC00).contains(&code) {
+                        expect(chars, '\\')?;
+                        expect(chars, 'u')?;
+                        let low = parse_hex(chars)?;
+                        code =
+                            0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                    }
+                    out.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                Some(c @ ('"' | '\\' | '/')) => out.push(c),
+                _ => return Err(error("Invalid escape in a string".to_string())),
+            },
+            Some(c) => out.push(c),
+            None => return Err(error("The file ended in a string".to_string())),
+        }
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Result<Json, LighthouseError> {
+    let mut text = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+        text.push(c);
+    }
+    text.parse()
+        .map(Json::Number)
+        .map_err(|_| error(format!("{} is not a number", text)))
+}
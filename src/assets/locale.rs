@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use super::{Asset, AssetError, AssetManager};
+
+/// A table of translated strings, every key has a text for each language
+///
+/// It is loaded from a `.lang` file, a section is started by the language in brackets and
+/// every line after it is a `key = text` pair. Lines starting with `#` are comments and
+/// `\n` in a text is turned into a new line
+///
+/// ```text
+/// # menu.lang
+/// [en]
+/// menu.play = Play
+/// dialogue.guard = [b]Guard:[/b] Halt!\nWho goes there, {name}?
+///
+/// [fr]
+/// menu.play = Jouer
+/// dialogue.guard = [b]Garde :[/b] Halte !\nQui va là, {name} ?
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StringTable {
+    /// The texts of every language, keyed by language then by key
+    pub languages: HashMap<String, HashMap<String, String>>,
+}
+
+impl StringTable {
+    /// Parses a string table from the content of a `.lang` file
+    pub fn parse(src: &str) -> Result<Self, AssetError> {
+        let mut table = StringTable::default();
+        let mut language = None;
+
+        for (i, line) in src.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim().to_string();
+                table.languages.entry(name.clone()).or_default();
+                language = Some(name);
+                continue;
+            }
+
+            let (key, text) = line.split_once('=').ok_or_else(|| {
+                AssetError::Decode(format!("Line {} is not a `key = text` pair", i + 1))
+            })?;
+            let language = language.as_ref().ok_or_else(|| {
+                AssetError::Decode(format!("Line {} is not inside of a language", i + 1))
+            })?;
+            table
+                .languages
+                .get_mut(language)
+                .unwrap()
+                .insert(key.trim().to_string(), text.trim().replace("\\n", "\n"));
+        }
+
+        Ok(table)
+    }
+
+    /// Gets the text of key in language
+    pub fn get(&self, language: &str, key: &str) -> Option<&str> {
+        self.languages
+            .get(language)
+            .and_then(|texts| texts.get(key))
+            .map(String::as_str)
+    }
+}
+
+impl Asset for StringTable {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        StringTable::parse(&String::from_bytes(bytes)?)
+    }
+}
+
+/// Looks up translated strings in the current language
+///
+/// The language can be switched at any time, the next [Localization::tr] returns the text
+/// in the new language. Since [Font](crate::graphics::text::Font) caches by string, text
+/// drawn with the translated strings is shaped again on its own
+///
+/// # Example
+/// ```
+/// let mut locale = Localization::new("en");
+/// locale.load(&assets, "lang/menu.lang")?;
+///
+/// ui.text("play", &font, locale.tr("menu.play"), vec2(10.0, 10.0), 1.0, [1.0; 4], 0);
+///
+/// if settings_changed {
+///     locale.set_language("fr");
+/// }
+/// ```
+pub struct Localization {
+    /// The current language
+    language: String,
+    /// The language used when a key has no text in the current language
+    pub fallback: String,
+    /// The loaded tables, later tables override earlier ones
+    tables: Vec<StringTable>,
+    /// Goes up every time the language or the tables change
+    version: u64,
+}
+
+impl Localization {
+    /// Creates a new localization without any table, language is also the fallback
+    pub fn new(language: &str) -> Self {
+        Localization {
+            language: language.to_string(),
+            fallback: language.to_string(),
+            tables: Vec::new(),
+            version: 0,
+        }
+    }
+
+    /// Loads a string table through the asset manager and adds it
+    pub fn load(&mut self, assets: &AssetManager, name: &str) -> Result<(), AssetError> {
+        self.add(assets.load(name)?);
+        Ok(())
+    }
+
+    /// Adds a string table, its texts override the texts of the tables added before
+    pub fn add(&mut self, table: StringTable) {
+        self.tables.push(table);
+        self.version += 1;
+    }
+
+    /// Gets the current language
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Switches to another language
+    pub fn set_language(&mut self, language: &str) {
+        if self.language != language {
+            self.language = language.to_string();
+            self.version += 1;
+        }
+    }
+
+    /// Gets every language that at least one table has
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages: Vec<&str> = self
+            .tables
+            .iter()
+            .flat_map(|table| table.languages.keys().map(String::as_str))
+            .collect();
+        languages.sort_unstable();
+        languages.dedup();
+        languages
+    }
+
+    /// Gets a number that changes every time the language or tables change, can be used
+    /// to know when cached translated text has to be rebuilt
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Gets the text of key in the current language
+    ///
+    /// If the current language doesnt have it the fallback language is used, if that
+    /// doesnt have it either the key itself is returned so missing texts are easy to spot
+    pub fn tr<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(&self.language, key)
+            .or_else(|| self.lookup(&self.fallback, key))
+            .unwrap_or(key)
+    }
+
+    /// Same as [Localization::tr] but every `{name}` in the text is replaced by its value
+    ///
+    /// # Example
+    /// ```
+    /// let line = locale.tr_with("dialogue.guard", &[("name", &player.name)]);
+    /// ```
+    pub fn tr_with(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut text = self.tr(key).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+
+    /// Gets every key that has a text in the fallback language but not in language,
+    /// useful to find missing translations
+    pub fn missing(&self, language: &str) -> Vec<&str> {
+        let mut missing: Vec<&str> = self
+            .tables
+            .iter()
+            .filter_map(|table| table.languages.get(&self.fallback))
+            .flat_map(|texts| texts.keys().map(String::as_str))
+            .filter(|key| self.lookup(language, key).is_none())
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
+    fn lookup(&self, language: &str, key: &str) -> Option<&str> {
+        self.tables
+            .iter()
+            .rev()
+            .find_map(|table| table.get(language, key))
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+use super::AssetError;
+
+/// The first 4 bytes of every `.lhpack` file
+pub const PACK_MAGIC: [u8; 4] = *b"LHPK";
+/// The version of the `.lhpack` format that is read and written
+pub const PACK_VERSION: u32 = 1;
+
+/// The fewest bytes an entry of the index takes, one with an empty name
+const MIN_ENTRY_SIZE: u64 = 2 + 8 + 8 + 8 + 1;
+/// The most bytes [Pack::read] reserves up front, bigger assets grow while they are read
+const MAX_READ_RESERVE: u64 = 64 * 1024 * 1024;
+
+/// How the blob of a [PackEntry] is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackCompression {
+    /// The blob is stored as is
+    Stored = 0,
+    /// The blob is compressed with deflate
+    Deflate = 1,
+}
+
+impl PackCompression {
+    fn from_u8(value: u8) -> Result<Self, AssetError> {
+        match value {
+            0 => Ok(PackCompression::Stored),
+            1 => Ok(PackCompression::Deflate),
+            _ => Err(AssetError::InvalidPack(format!(
+                "Unknown compression {}",
+                value
+            ))),
+        }
+    }
+}
+
+/// An entry in the index of a [Pack]
+#[derive(Debug, Clone, Copy)]
+pub struct PackEntry {
+    /// Where the blob starts in the pack file
+    pub offset: u64,
+    /// The size of the blob in the pack file
+    pub size: u64,
+    /// The size of the asset after it has been decompressed
+    pub raw_size: u64,
+    /// How the blob is stored
+    pub compression: PackCompression,
+}
+
+/// A `.lhpack` archive
+///
+/// # Format
+/// All numbers are little endian
+/// ```text
+/// magic:   b"LHPK"
+/// version: u32
+/// count:   u32
+/// index:   count * { name_len: u16, name: [u8; name_len], offset: u64, size: u64, raw_size: u64, compression: u8 }
+/// blobs:   the (maybe compressed) bytes of every asset
+/// ```
+///
+/// Only the index is kept in memory, blobs are read from the disk when they are needed
+pub struct Pack {
+    /// Where the pack is on the disk
+    pub path: PathBuf,
+    /// The index of the pack
+    pub entries: HashMap<String, PackEntry>,
+}
+
+impl Pack {
+    /// Opens the pack at path and reads its index
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AssetError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = BufReader::new(File::open(&path)?);
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if magic != PACK_MAGIC {
+            return Err(AssetError::InvalidPack(format!(
+                "{} is not a lhpack file",
+                path.display()
+            )));
+        }
+
+        let version = read_u32(&mut file)?;
+        if version != PACK_VERSION {
+            return Err(AssetError::InvalidPack(format!(
+                "Unsupported pack version {}, expected {}",
+                version, PACK_VERSION
+            )));
+        }
+
+        // the header isn't trusted for the capacity, a broken pack could ask for anything
+        let count = read_u32(&mut file)?;
+        let index_size = file.get_ref().metadata()?.len().saturating_sub(12);
+        let mut entries =
+            HashMap::with_capacity((count as u64).min(index_size / MIN_ENTRY_SIZE) as usize);
+        for _ in 0..count {
+            let mut name_len = [0; 2];
+            file.read_exact(&mut name_len)?;
+            let mut name = vec![0; u16::from_le_bytes(name_len) as usize];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| AssetError::InvalidPack("Asset name is not utf8".to_string()))?;
+
+            let offset = read_u64(&mut file)?;
+            let size = read_u64(&mut file)?;
+            let raw_size = read_u64(&mut file)?;
+            let mut compression = [0; 1];
+            file.read_exact(&mut compression)?;
+
+            entries.insert(
+                name,
+                PackEntry {
+                    offset,
+                    size,
+                    raw_size,
+                    compression: PackCompression::from_u8(compression[0])?,
+                },
+            );
+        }
+
+        Ok(Pack { path, entries })
+    }
+
+    /// Checks if the asset is in the pack
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Opens the asset for streaming reads, it is decompressed while being read
+    pub fn open_entry(&self, name: &str) -> Result<Box<dyn Read + Send>, AssetError> {
+        let entry = *self
+            .entries
+            .get(name)
+            .ok_or_else(|| AssetError::NotFound(name.to_string()))?;
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+        let blob = BufReader::new(file).take(entry.size);
+
+        Ok(match entry.compression {
+            PackCompression::Stored => Box::new(blob),
+            PackCompression::Deflate => Box::new(DeflateDecoder::new(blob)),
+        })
+    }
+
+    /// Reads all the bytes of the asset
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, AssetError> {
+        let raw_size = self.entries.get(name).map_or(0, |entry| entry.raw_size);
+        let mut out = Vec::with_capacity(raw_size.min(MAX_READ_RESERVE) as usize);
+        self.open_entry(name)?.read_to_end(&mut out)?;
+        Ok(out)
+    }
+}
+
+/// Builds a [Pack]
+///
+/// # Example
+/// ```
+/// let mut writer = PackWriter::new();
+/// writer.add_file("image.jpg", "data/image.jpg", true)?;
+/// writer.add("level.txt", b"hello".to_vec(), false)?;
+/// writer.write("data/game.lhpack")?;
+/// ```
+#[derive(Default)]
+pub struct PackWriter {
+    /// name, blob, raw size and compression of every asset
+    entries: Vec<(String, Vec<u8>, u64, PackCompression)>,
+}
+
+impl PackWriter {
+    /// Creates a new empty pack writer
+    pub fn new() -> Self {
+        PackWriter {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds the asset to the pack, compressing it if compress is true
+    pub fn add(&mut self, name: &str, data: Vec<u8>, compress: bool) -> Result<(), AssetError> {
+        if name.len() > u16::MAX as usize {
+            return Err(AssetError::InvalidPack(format!(
+                "Asset name {} is too long",
+                name
+            )));
+        }
+
+        let raw_size = data.len() as u64;
+        let (blob, compression) = if compress {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&data)?;
+            (encoder.finish()?, PackCompression::Deflate)
+        } else {
+            (data, PackCompression::Stored)
+        };
+
+        self.entries.retain(|(entry_name, ..)| entry_name != name);
+        self.entries
+            .push((name.to_string(), blob, raw_size, compression));
+        Ok(())
+    }
+
+    /// Adds the file at path to the pack as name
+    pub fn add_file(
+        &mut self,
+        name: &str,
+        path: impl AsRef<Path>,
+        compress: bool,
+    ) -> Result<(), AssetError> {
+        self.add(name, fs::read(path)?, compress)
+    }
+
+    /// Writes the pack to path
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), AssetError> {
+        let index_size: u64 = self
+            .entries
+            .iter()
+            .map(|(name, ..)| 2 + name.len() as u64 + 8 * 3 + 1)
+            .sum();
+        let mut offset = 4 + 4 + 4 + index_size;
+
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&PACK_MAGIC)?;
+        file.write_all(&PACK_VERSION.to_le_bytes())?;
+        file.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+
+        for (name, blob, raw_size, compression) in &self.entries {
+            file.write_all(&(name.len() as u16).to_le_bytes())?;
+            file.write_all(name.as_bytes())?;
+            file.write_all(&offset.to_le_bytes())?;
+            file.write_all(&(blob.len() as u64).to_le_bytes())?;
+            file.write_all(&raw_size.to_le_bytes())?;
+            file.write_all(&[*compression as u8])?;
+            offset += blob.len() as u64;
+        }
+
+        for (_, blob, ..) in &self.entries {
+            file.write_all(blob)?;
+        }
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, AssetError> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, AssetError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -0,0 +1,229 @@
+use crate::error::LighthouseError;
+
+/// How deep elements can be inside each other, so a broken file can't overflow the stack
+/// of whatever walks the elements
+const MAX_DEPTH: usize = 256;
+
+/// A parsed XML element with its children, for reading files like SVG images
+///
+/// Comments, processing instructions and the doctype are skipped, the text of an element
+/// is all of its text joined together
+///
+/// # Example
+/// ```
+/// let xml = XmlElement::parse(r#"<level name="cave"><spawn x="4" y="2"/></level>"#)?;
+/// assert_eq!(xml.attr("name"), Some("cave"));
+/// assert_eq!(xml.children[0].attr("x"), Some("4"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct XmlElement {
+    /// The name of the tag
+    pub name: String,
+    /// The attributes in the order of the file, their entities already turned into
+    /// characters
+    pub attributes: Vec<(String, String)>,
+    /// The elements inside of this one
+    pub children: Vec<XmlElement>,
+    /// The text inside of this one, without the text of its children
+    pub text: String,
+}
+
+impl XmlElement {
+    /// Parses the text of an XML file, gives its root element
+    pub fn parse(src: &str) -> Result<XmlElement, LighthouseError> {
+        let mut stack: Vec<XmlElement> = Vec::new();
+        let mut root = None;
+        let mut rest = src;
+
+        while let Some(start) = rest.find('<') {
+            if let Some(top) = stack.last_mut() {
+                top.text.push_str(&unescape(&rest[..start]));
+            }
+            rest = &rest[start..];
+
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                rest = after(comment, "-->")?;
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata
+                    .find("]]>")
+                    .ok_or_else(|| error("A CDATA section isn't closed".to_string()))?;
+                if let Some(top) = stack.last_mut() {
+                    top.text.push_str(&cdata[..end]);
+                }
+                rest = &cdata[end + 3..];
+            } else if let Some(instruction) = rest.strip_prefix("<?") {
+                rest = after(instruction, "?>")?;
+            } else if let Some(declaration) = rest.strip_prefix("<!") {
+                rest = skip_declaration(declaration)?;
+            } else if let Some(closing) = rest.strip_prefix("</") {
+                let end = closing
+                    .find('>')
+                    .ok_or_else(|| error("A closing tag isn't closed".to_string()))?;
+                let name = closing[..end].trim();
+                let element = stack
+                    .pop()
+                    .filter(|element| element.name == name)
+                    .ok_or_else(|| error(format!("</{}> doesn't close anything", name)))?;
+                rest = &closing[end + 1..];
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            } else {
+                let (element, self_closing, after_tag) = parse_tag(&rest[1..])?;
+                rest = after_tag;
+                if !self_closing {
+                    if stack.len() >= MAX_DEPTH {
+                        return Err(error(format!(
+                            "Elements are nested deeper than {}",
+                            MAX_DEPTH
+                        )));
+                    }
+                    stack.push(element);
+                    continue;
+                }
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => root = Some(element),
+                }
+            }
+
+            if root.is_some() {
+                break;
+            }
+        }
+
+        if let Some(element) = stack.last() {
+            return Err(error(format!("<{}> isn't closed", element.name)));
+        }
+        root.ok_or_else(|| error("The file has no element".to_string()))
+    }
+
+    /// Gets the value of the attribute called name
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets the children called name
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+/// Makes the error of malformed XML
+fn error(message: String) -> LighthouseError {
+    LighthouseError::Parse(format!("Invalid XML: {}", message))
+}
+
+/// Gets what comes after the next end
+fn after<'a>(src: &'a str, end: &str) -> Result<&'a str, LighthouseError> {
+    src.find(end)
+        .map(|i| &src[i + end.len()..])
+        .ok_or_else(|| error(format!("Expected {} but the file ended", end)))
+}
+
+/// Skips a declaration like the doctype, which can have declarations in brackets inside
+fn skip_declaration(src: &str) -> Result<&str, LighthouseError> {
+    let mut depth = 0;
+    for (i, c) in src.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '>' if depth == 0 => return Ok(&src[i + 1..]),
+            _ => (),
+        }
+    }
+    Err(error("A declaration isn't closed".to_string()))
+}
+
+/// Parses a tag after its `<`, gives the element, whether it closes itself and what comes
+/// after the tag
+fn parse_tag(src: &str) -> Result<(XmlElement, bool, &str), LighthouseError> {
+    let name_end = src
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .ok_or_else(|| error("A tag isn't closed".to_string()))?;
+    let mut element = XmlElement {
+        name: src[..name_end].to_string(),
+        ..Default::default()
+    };
+    if element.name.is_empty() {
+        return Err(error("A tag has no name".to_string()));
+    }
+
+    let mut rest = &src[name_end..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(after_tag) = rest.strip_prefix("/>") {
+            return Ok((element, true, after_tag));
+        }
+        if let Some(after_tag) = rest.strip_prefix('>') {
+            return Ok((element, false, after_tag));
+        }
+
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| error(format!("An attribute of <{}> has no value", element.name)))?;
+        let key = rest[..eq].trim().to_string();
+        rest = rest[eq + 1..].trim_start();
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| error(format!("The value of {} isn't quoted", key)))?;
+        let end = rest[1..]
+            .find(quote)
+            .ok_or_else(|| error(format!("The value of {} isn't closed", key)))?;
+        element
+            .attributes
+            .push((key, unescape(&rest[1..end + 1]).into_owned()));
+        rest = &rest[end + 2..];
+    }
+}
+
+/// Turns entities like `&amp;` into characters
+fn unescape(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains('&') {
+        return text.into();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            // not an entity, keep the & as it is
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.into()
+}
@@ -0,0 +1,364 @@
+use std::{fmt, mem::size_of, time::Instant};
+
+use nalgebra_glm::{look_at, ortho, perspective, vec2, vec3, Mat4, Vec3};
+use ogl33::*;
+
+use crate::{
+    error::LighthouseError,
+    graphics::{
+        buffer::*,
+        clustered::{ClusteredLights, CLUSTERED_GLSL},
+        lighting::PointLight,
+        shader::ShaderProgram,
+        sprite::{Sprite, SpriteBatcher, TextureHandle, Transform2D},
+        temporal::halton,
+        texture::Texture,
+        uniform::Uniform,
+        vertex::VertexArray,
+        window::Window,
+    },
+    profiler::{FrameStats, Profiler},
+};
+
+const MESH_VERT: &str = "#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+
+uniform mat4 view_proj;
+uniform vec3 offset;
+
+out vec3 world_pos;
+out vec3 world_normal;
+
+void main() {
+    world_pos = pos + offset;
+    world_normal = normal;
+    gl_Position = view_proj * vec4(world_pos, 1.0);
+}";
+
+const MESH_FRAG: &str = "in vec3 world_pos;
+in vec3 world_normal;
+
+out vec4 color;
+
+void main() {
+    vec3 albedo = vec3(0.8);
+    color = vec4(albedo * 0.05 + apply_point_lights(albedo, world_pos, world_normal), 1.0);
+}";
+
+/// How big the scene of a [Bench] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchSettings {
+    /// How many sprites are drawn on top of the scene
+    pub sprites: usize,
+    /// How many cubes are drawn, each with its own draw call
+    pub meshes: usize,
+    /// How many point lights light the cubes, at most [crate::graphics::clustered::MAX_POINT_LIGHTS]
+    pub lights: usize,
+    /// How many frames are measured
+    pub frames: usize,
+    /// How many frames are drawn before measuring, so caches and drivers have warmed up
+    pub warmup: usize,
+}
+
+impl BenchSettings {
+    /// Creates new settings for a scene with sprites sprites, meshes cubes and lights lights
+    pub fn new(sprites: usize, meshes: usize, lights: usize) -> Self {
+        BenchSettings {
+            sprites,
+            meshes,
+            lights,
+            ..BenchSettings::default()
+        }
+    }
+
+    /// Sets how many frames are measured
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Sets how many frames are drawn before measuring
+    pub fn warmup(mut self, warmup: usize) -> Self {
+        self.warmup = warmup;
+        self
+    }
+}
+
+impl Default for BenchSettings {
+    fn default() -> Self {
+        BenchSettings {
+            sprites: 1000,
+            meshes: 100,
+            lights: 32,
+            frames: 300,
+            warmup: 30,
+        }
+    }
+}
+
+/// What a [Bench::run] measured
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// The scene that was measured
+    pub settings: BenchSettings,
+    /// The statistics of the measured frames, with a section for the meshes, lights and
+    /// sprites
+    pub stats: FrameStats,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} sprites, {} meshes, {} lights",
+            self.settings.sprites, self.settings.meshes, self.settings.lights
+        )?;
+        write!(f, "{}", self.stats)
+    }
+}
+
+/// A small scene of many sprites, meshes and lights to measure how fast the renderer is
+///
+/// Everything is placed the same way every run, so reports of different versions of the
+/// crate on the same computer can be compared
+///
+/// # Example
+/// ```
+/// let mut bench = Bench::new(BenchSettings::new(5000, 500, 128), 1280, 720)?;
+/// let report = bench.run(world.env.win.as_ref());
+/// println!("{}", report);
+/// bench.delete();
+/// ```
+pub struct Bench {
+    /// The scene that is drawn
+    pub settings: BenchSettings,
+    /// Times the frames, it waits for the gpu so the gpu time is counted too
+    pub profiler: Profiler,
+    width: i32,
+    height: i32,
+    program: ShaderProgram,
+    vao: VertexArray,
+    /// Kept so the vertices aren't deleted while the vao uses them
+    #[allow(dead_code)]
+    vbo: Buffer,
+    ebo: IndexBuffer,
+    offsets: Vec<Vec3>,
+    lights: ClusteredLights,
+    light_homes: Vec<Vec3>,
+    sprites: SpriteBatcher,
+    sprite_homes: Vec<(Sprite, Transform2D)>,
+    /// Kept so the sprites can still be drawn with it
+    #[allow(dead_code)]
+    white: Texture,
+    start: Instant,
+}
+
+impl Bench {
+    /// Creates the scene for a window of size width by height(in pixels)
+    pub fn new(settings: BenchSettings, width: i32, height: i32) -> Result<Self, LighthouseError> {
+        let program = ShaderProgram::from_vert_frag(
+            MESH_VERT,
+            &format!("#version 330 core\n{}\n{}", CLUSTERED_GLSL, MESH_FRAG),
+        )?;
+        let (vertices, indices) = cube();
+        let vao =
+            VertexArray::new().ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?;
+        let vbo = Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?;
+        vao.bind();
+        vbo.bind(BufferType::Array);
+        buffer_data(
+            BufferType::Array,
+            bytemuck::cast_slice(&vertices),
+            BufferUsage::StaticDraw,
+        );
+        let ebo = IndexBuffer::with_data(&indices, BufferUsage::StaticDraw)?;
+        unsafe {
+            let stride = size_of::<[f32; 6]>() as i32;
+            glVertexAttribPointer(0, 3, GL_FLOAT, GL_FALSE, stride, std::ptr::null());
+            glEnableVertexAttribArray(0);
+            glVertexAttribPointer(1, 3, GL_FLOAT, GL_FALSE, stride, 12 as *const _);
+            glEnableVertexAttribArray(1);
+        }
+        VertexArray::clear_binding();
+
+        // the halton sequence spreads everything evenly but the same way every run
+        let spread = |i: usize, size: Vec3| {
+            let i = i as u32 + 1;
+            vec3(
+                (halton(i, 2) - 0.5) * size.x,
+                (halton(i, 3) - 0.5) * size.y,
+                (halton(i, 5) - 0.5) * size.z,
+            )
+        };
+        let offsets = (0..settings.meshes)
+            .map(|i| spread(i, vec3(40.0, 4.0, 40.0)))
+            .collect();
+        let light_homes = (0..settings.lights)
+            .map(|i| spread(i, vec3(40.0, 6.0, 40.0)))
+            .collect();
+
+        let white = Texture::new();
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, white.id);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_NEAREST as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_NEAREST as i32);
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_RGBA8 as i32,
+                1,
+                1,
+                0,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                [255u8; 4].as_ptr().cast(),
+            );
+        }
+        let handle = TextureHandle {
+            id: white.id,
+            size: vec2(16.0, 16.0),
+        };
+        let sprite_homes = (0..settings.sprites)
+            .map(|i| {
+                let i = i as u32 + 1;
+                let sprite = Sprite::new(handle)
+                    .tint([halton(i, 2), halton(i, 3), halton(i, 5), 0.8])
+                    .layer((i % 4) as i32);
+                let pos = vec2(halton(i, 7) * width as f32, halton(i, 11) * height as f32);
+                (sprite, Transform2D::new(pos))
+            })
+            .collect();
+
+        let proj = Bench::proj(width, height);
+        Ok(Bench {
+            settings,
+            profiler: Profiler::new().sync_gpu(true),
+            width,
+            height,
+            program,
+            vao,
+            vbo,
+            ebo,
+            offsets,
+            lights: ClusteredLights::new([16, 9, 24], &proj, 0.1, 100.0)?,
+            light_homes,
+            sprites: SpriteBatcher::new()?,
+            sprite_homes,
+            white,
+            start: Instant::now(),
+        })
+    }
+
+    fn proj(width: i32, height: i32) -> Mat4 {
+        perspective(width as f32 / height.max(1) as f32, 1.0, 0.1, 100.0)
+    }
+
+    /// Draws one frame of the scene into the bound framebuffer, timed by the profiler
+    pub fn frame(&mut self) {
+        let time = self.start.elapsed().as_secs_f32();
+        let proj = Bench::proj(self.width, self.height);
+        let view = look_at(
+            &vec3(0.0, 18.0, 32.0),
+            &vec3(0.0, 0.0, 0.0),
+            &vec3(0.0, 1.0, 0.0),
+        );
+        let view_proj = proj * view;
+
+        self.profiler.begin_frame();
+        unsafe {
+            glViewport(0, 0, self.width, self.height);
+            glEnable(GL_DEPTH_TEST);
+            glClearColor(0.0, 0.0, 0.0, 1.0);
+            glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+        }
+
+        self.profiler.begin("lights");
+        self.lights.lights.clear();
+        for (i, home) in self.light_homes.iter().enumerate() {
+            let angle = time + i as f32;
+            let pos = home + vec3(angle.cos(), 0.0, angle.sin()) * 2.0;
+            let i = i as u32 + 1;
+            self.lights.lights.push(
+                PointLight::new(pos, 8.0)
+                    .color([halton(i, 2), halton(i, 3), halton(i, 5)])
+                    .intensity(4.0),
+            );
+        }
+        self.lights.update(&view);
+        let size = [self.width as f32, self.height as f32];
+        self.lights.bind(&self.program, 0, 0, size);
+        self.profiler.end("lights");
+
+        self.profiler.begin("meshes");
+        self.program.use_program();
+        Uniform::new(&self.program, "view_proj").set_uniform_matrix(false, view_proj.into());
+        let offset = Uniform::new(&self.program, "offset");
+        self.vao.bind();
+        for pos in &self.offsets {
+            offset.set_uniform_f(&[pos.x, pos.y, pos.z]);
+            draw_elements(Primitive::Triangles, &self.ebo);
+        }
+        VertexArray::clear_binding();
+        self.profiler.end("meshes");
+
+        self.profiler.begin("sprites");
+        for (i, (sprite, home)) in self.sprite_homes.iter().enumerate() {
+            let mut transform = *home;
+            transform.pos.y += (time * 2.0 + i as f32).sin() * 8.0;
+            transform.rotation = time + i as f32;
+            self.sprites.push(sprite, &transform);
+        }
+        let screen = ortho(0.0, self.width as f32, 0.0, self.height as f32, -1.0, 1.0);
+        unsafe { glDisable(GL_DEPTH_TEST) }
+        self.sprites.flush(&screen);
+        unsafe { glEnable(GL_DEPTH_TEST) }
+        self.profiler.end("sprites");
+
+        self.profiler.end_frame();
+    }
+
+    /// Draws the warmup frames and then measures [BenchSettings::frames] frames, every
+    /// frame is shown on win
+    pub fn run(&mut self, win: &dyn Window) -> BenchReport {
+        for _ in 0..self.settings.warmup {
+            self.frame();
+            win.swap_buffers();
+        }
+        self.profiler = Profiler::new().sync_gpu(true).history(self.settings.frames);
+        for _ in 0..self.settings.frames {
+            self.frame();
+            win.swap_buffers();
+        }
+        BenchReport {
+            settings: self.settings,
+            stats: self.profiler.stats(),
+        }
+    }
+
+    /// Deletes everything the scene made now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+/// A cube of size 1 around the origin with a normal for every face, as positions and normals
+fn cube() -> (Vec<[f32; 6]>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for axis in 0..3 {
+        for sign in [-1.0, 1.0] {
+            let mut normal = [0.0; 3];
+            normal[axis] = sign;
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let first = vertices.len() as u32;
+            for (a, b) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+                let mut pos = [0.0; 3];
+                pos[axis] = sign * 0.5;
+                pos[u] = a;
+                pos[v] = b * sign;
+                vertices.push([pos[0], pos[1], pos[2], normal[0], normal[1], normal[2]]);
+            }
+            indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+    (vertices, indices)
+}
@@ -0,0 +1,121 @@
+use std::{fmt, path::PathBuf};
+
+use crate::{
+    assets::AssetError,
+    graphics::{model::ModelError, shader::ShaderType, texture::TextureError},
+};
+
+/// This is the error of lighthouse, everything that can fail returns it. It contains the more
+/// specific error enums in it as well
+///
+/// # Example
+/// ```
+/// match ShaderProgram::from_vert_frag(vert, frag) {
+///     Ok(program) => program.use_program(),
+///     Err(LighthouseError::ShaderCompile { ty, log, .. }) => eprintln!("{:?}: {}", ty, log),
+///     Err(error) => panic!("{}", error),
+/// }
+/// ```
+#[derive(Debug)]
+pub enum LighthouseError {
+    /// A shader didn't compile
+    ShaderCompile {
+        /// What kind of shader it is
+        ty: ShaderType,
+        /// The file the source was read from, if it was read from one
+        file: Option<PathBuf>,
+        /// The info log of the shader
+        log: String,
+    },
+    /// A program didn't link, contains the info log of the program
+    ProgramLink(String),
+    /// Opengl couldn't create an object like a buffer, texture or shader, contains what it
+    /// was going to be
+    BufferAlloc(String),
+    /// A framebuffer isn't complete, contains what the framebuffer is for and the status
+    /// opengl gave
+    IncompleteFramebuffer(String, u32),
+    /// This is a texture error, it is used by [Texture](crate::graphics::texture::Texture)
+    Texture(TextureError),
+    /// This is an asset error, it is used by [AssetManager](crate::assets::AssetManager)
+    Asset(AssetError),
+    /// A model file couldn't be loaded, it is used by [ObjModel](crate::graphics::model::ObjModel)
+    Model(ModelError),
+    /// Reading or writing a file failed, contains the path of the file
+    Io(PathBuf, std::io::Error),
+    /// Text couldn't be parsed, like a .cube, IES or BMFont file
+    Parse(String),
+    /// The arguments don't work together, like a vertex attribute that doesn't exist
+    InvalidInput(String),
+    /// The gpu or lighthouse can't do it, like more bones than a uniform can hold
+    Unsupported(String),
+    /// Sdl failed, contains the sdl error
+    Sdl(String),
+    /// Glutin or winit failed, contains their error
+    Glutin(String),
+}
+
+impl fmt::Display for LighthouseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LighthouseError::ShaderCompile {
+                ty,
+                file: Some(file),
+                log,
+            } => write!(f, "{:?} Compile Error in {}: {}", ty, file.display(), log),
+            LighthouseError::ShaderCompile {
+                ty,
+                file: None,
+                log,
+            } => {
+                write!(f, "{:?} Compile Error: {}", ty, log)
+            }
+            LighthouseError::ProgramLink(log) => write!(f, "Program Link Error: {}", log),
+            LighthouseError::BufferAlloc(what) => write!(f, "Couldn't allocate {}", what),
+            LighthouseError::IncompleteFramebuffer(what, status) => {
+                write!(f, "{} is not complete: 0x{:X}", what, status)
+            }
+            LighthouseError::Texture(error) => write!(f, "Texture Error: {}", error),
+            LighthouseError::Asset(error) => write!(f, "Asset Error: {}", error),
+            LighthouseError::Model(error) => write!(f, "Model Error: {}", error),
+            LighthouseError::Io(path, error) => {
+                write!(f, "Couldn't access {}: {}", path.display(), error)
+            }
+            LighthouseError::Parse(message)
+            | LighthouseError::InvalidInput(message)
+            | LighthouseError::Unsupported(message) => write!(f, "{}", message),
+            LighthouseError::Sdl(error) => write!(f, "Sdl Error: {}", error),
+            LighthouseError::Glutin(error) => write!(f, "Glutin Error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for LighthouseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LighthouseError::Texture(error) => Some(error),
+            LighthouseError::Asset(error) => Some(error),
+            LighthouseError::Model(error) => Some(error),
+            LighthouseError::Io(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<TextureError> for LighthouseError {
+    fn from(error: TextureError) -> Self {
+        LighthouseError::Texture(error)
+    }
+}
+
+impl From<AssetError> for LighthouseError {
+    fn from(error: AssetError) -> Self {
+        LighthouseError::Asset(error)
+    }
+}
+
+impl From<ModelError> for LighthouseError {
+    fn from(error: ModelError) -> Self {
+        LighthouseError::Model(error)
+    }
+}
@@ -2,44 +2,175 @@
 //!
 //! It is fully documented an is currently is a WIP
 //! Although i hope to change that in the near future
-//! It can be used to make 2d graphics
+//! It can be used to make 2d and 3d graphics
 //!
 //! It has a complete Shader system so you can make any type of shader
 //!
 //! # Limitations
 //!
-//! At the moment it cannot create 3d graphics
-//! It only supports the creation of 2D textures
-//! And does not support non primitive uniforms
+//! It is built on opengl 3.3, compute shaders are optional and need opengl 4.3, see
+//! [ShaderProgram::from_compute](self::shader::ShaderProgram::from_compute) and
+//! [GpuParticles](self::particles::GpuParticles)
 
 #![deny(missing_docs)]
 
+/// Module containing all things related to [self::FrameArena]
+pub mod arena;
+/// Module containing all things related to [self::TextureAtlas]
+pub mod atlas;
+/// Module containing all things related to [self::Blend]
+pub mod blend;
 /// Module containing all things related to [self::Buffer]
 pub mod buffer;
+/// Module containing all things related to [self::Chart]
+pub mod chart;
+/// Module containing all things related to [self::ClusteredLights]
+pub mod clustered;
+/// Module containing all things related to [self::ColorGrading]
+pub mod color_grading;
+/// Module containing all things related to [self::CompressedImage]
+pub mod compressed;
+/// Module containing all things related to [self::CookieLight]
+pub mod cookie;
+/// Module containing all things related to [self::SoftwareCursor]
+pub mod cursor;
+/// Module containing all things related to [self::GlError]
+pub mod debug;
+/// Module containing all things related to [self::DepthOfField]
+pub mod depth_of_field;
+/// Module containing all things related to [self::Monitor] and [self::WindowOptions]
+#[cfg(feature = "sdl")]
+pub mod display;
+/// Module containing all things related to [self::Draw2D]
+pub mod draw2d;
+/// Module containing all things related to [self::AutoExposure]
+pub mod exposure;
+/// Module containing all things related to [self::Framebuffer]
+pub mod framebuffer;
+/// Module containing all things related to [self::FullscreenPass]
+pub mod fullscreen;
+/// Module containing all things related to [self::GltfScene]
+pub mod gltf;
+/// Module containing all things related to [self::ImagePass]
+pub mod image_pass;
+/// Module containing all things related to [self::InstanceBuffer]
+pub mod instance;
+/// Module containing all things related to [self::Lighting]
+pub mod lighting;
+/// Module containing all things related to [self::Lightmap]
+pub mod lightmap;
+/// Module containing all things related to [self::ObjModel]
+pub mod model;
+/// Module containing all things related to [self::MotionBlur]
+pub mod motion_blur;
 /// Module containing all things related to [self::MultiSingularNumber]
 pub mod number;
+/// Module containing all things related to [self::GpuParticles]
+pub mod particles;
+/// Module containing all things related to [self::Pipeline]
+pub mod pipeline;
+/// Module containing all things related to [self::PlyModel]
+pub mod ply;
+/// Module containing all things related to [self::PointCloud]
+pub mod point_cloud;
+/// Module containing all things related to [self::PostProcessStack]
+pub mod post_process;
+/// Module containing all things related to [self::ContextRecovery]
+pub mod recovery;
+/// Module containing all things related to [self::RenderState]
+pub mod render_state;
+/// Module containing all things related to [self::RenderDoc]
+#[cfg(feature = "renderdoc")]
+pub mod renderdoc;
+/// Module containing all things related to [self::Renderer]
+pub mod renderer;
+/// Module containing all things related to [self::DynamicResolution]
+pub mod resolution;
+/// Module containing all things related to [self::RetroPass]
+pub mod retro;
+/// Module containing all things related to [self::Heightfield] and [self::VolumeSlice]
+pub mod scalar_field;
 /// Module containing all things related to [self::Shader]
 pub mod shader;
+/// Module containing all things related to [self::Sky] and [self::Skybox]
+pub mod sky;
+/// Module containing all things related to [self::SpriteBatcher]
+pub mod sprite;
+/// Module containing all things related to [self::StlModel]
+pub mod stl;
+/// Module containing all things related to [self::Svg]
+pub mod svg;
+/// Module containing all things related to [self::TargetPool]
+pub mod target_pool;
+/// Module containing all things related to [self::TemporalHistory]
+pub mod temporal;
+/// Module containing all things related to [self::Font]
+pub mod text;
+/// Module containing all things related to [self::TextMesh]
+pub mod text_mesh;
 /// Module containing all things related to [self::Texture]
 pub mod texture;
+/// Module containing all things related to [self::TimeOfDay]
+pub mod time_of_day;
+/// Module containing all things related to [self::Transitions]
+pub mod transition;
+/// Module containing all things related to [self::TrueType]
+pub mod ttf;
+/// Module containing all things related to [self::UiBatch]
+pub mod ui;
 /// Module containing all things related to [self::Uniform]
 pub mod uniform;
+/// Module containing all things related to [self::UvTransform]
+pub mod uv_transform;
 /// Module containing all things related to [self::VertexArray]
 pub mod vertex;
+/// Module containing all things related to [self::Weather]
+pub mod weather;
+/// Module containing all things related to [self::Window]
+pub mod window;
 
 // imports
+pub use crate::error::LighthouseError;
+#[cfg(feature = "sdl")]
 pub use beryllium::*;
 pub use image::DynamicImage::{self, *};
 pub use ogl33::*;
-use std::ffi::CString;
-use texture::TextureError;
-
-/// This is an error enum, It contains several more specific enums in it as well as a misc error
-pub enum Error {
-    /// This is a texture error, it is used by [Texture]
-    TextureError(TextureError),
-    /// For all other error that do not fit
-    Misc(String),
+use std::ffi::{CStr, CString};
+
+/// How long a string can be for [with_cstr] to turn it into a c string without allocating
+/// (in bytes, the nul at the end included)
+const STACK_CSTR_LEN: usize = 128;
+
+/// A string with its length that can be passed to opengl functions that take arrays of
+/// strings, the pointer can't outlive the &str it was made from
+///
+/// The string is not nul terminated, so the length always has to be passed too
+#[derive(Debug, Clone, Copy)]
+pub struct GlStr<'a> {
+    src: &'a str,
+}
+
+impl<'a> GlStr<'a> {
+    /// Gets the pointer to the first character
+    pub fn ptr(&self) -> *const GLchar {
+        self.src.as_ptr().cast()
+    }
+
+    /// Gets the length of the string (in bytes)
+    ///
+    /// # Panics
+    /// When the string is longer than an i32 can hold
+    pub fn len(&self) -> GLint {
+        self.src
+            .len()
+            .try_into()
+            .expect("The string is too long for opengl")
+    }
+
+    /// Checks if the string is empty
+    pub fn is_empty(&self) -> bool {
+        self.src.is_empty()
+    }
 }
 
 /// Takes a string of type &str and turs it into something that is used by opengl
@@ -50,8 +181,14 @@ pub enum Error {
 /// and just a string. However arrays of string are never used in my lib
 ///
 /// this function is used by functions that accepts arrays of string
-pub fn to_glstr(src: &str) -> *const u8 {
-    src.as_bytes().as_ptr()
+///
+/// # Example
+/// ```
+/// let src = to_glstr(src);
+/// unsafe { glShaderSource(shader, 1, &src.ptr(), &src.len()) }
+/// ```
+pub fn to_glstr(src: &str) -> GlStr<'_> {
+    GlStr { src }
 }
 
 /// Takes a string of type &str and turs it into something that is equivalent to a c string
@@ -61,9 +198,35 @@ pub fn to_glstr(src: &str) -> *const u8 {
 /// This function is different from [to_glstr] due to opengl accepting array of strings
 /// and just a string. However arrays of string are never used in my lib
 ///
-/// this function is used by functions that accepts a string
+/// this function is used by functions that accepts a string, a c string ends at the first
+/// nul so everything after a nul in src is cut off. [with_cstr] doesn't allocate for short
+/// strings and should be used for names that are looked up often
 pub fn to_cstr(src: &str) -> CString {
-    CString::new(src).unwrap()
+    let end = src.find('\0').unwrap_or(src.len());
+    CString::new(&src[..end]).expect("The string was cut at the first nul")
+}
+
+/// Runs f with src as a c string, like [to_cstr] everything after a nul is cut off
+///
+/// Strings shorter than 128 bytes are copied to the stack, so looking up uniforms and
+/// attributes every frame doesn't allocate
+///
+/// # Example
+/// ```
+/// let location = with_cstr(name, |name| unsafe {
+///     glGetUniformLocation(program.0, name.as_ptr().cast())
+/// });
+/// ```
+pub fn with_cstr<R>(src: &str, f: impl FnOnce(&CStr) -> R) -> R {
+    let src = &src[..src.find('\0').unwrap_or(src.len())];
+    if src.len() >= STACK_CSTR_LEN {
+        return f(&to_cstr(src));
+    }
+
+    let mut buffer = [0u8; STACK_CSTR_LEN];
+    buffer[..src.len()].copy_from_slice(src.as_bytes());
+    f(CStr::from_bytes_with_nul(&buffer[..=src.len()])
+        .expect("The string was cut at the first nul"))
 }
 
 /// Takes a vector of type &\[T\] and turs it into something that is equivalent to a c array
@@ -77,7 +240,86 @@ pub fn clear_color(r: f32, g: f32, b: f32, a: f32) {
     unsafe { glClearColor(r, g, b, a) }
 }
 
+/// Checks if the gpu has an opengl extension, e.g. `GL_EXT_texture_filter_anisotropic`
+pub fn has_extension(name: &str) -> bool {
+    let mut count = 0;
+    unsafe { glGetIntegerv(GL_NUM_EXTENSIONS, &mut count) };
+    (0..count.max(0) as u32).any(|index| {
+        let extension = unsafe { glGetStringi(GL_EXTENSIONS, index) };
+        !extension.is_null()
+            && unsafe { CStr::from_ptr(extension.cast()) }.to_bytes() == name.as_bytes()
+    })
+}
+
+/// A capability of opengl that can be turned on and off, see [enable] and [disable]
+///
+/// Blending, culling and the depth and stencil tests should be set with a
+/// [RenderState](self::render_state::RenderState) instead
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Blends the colors of what is drawn with what is already there
+    Blend = GL_BLEND as isize,
+    /// Throws away the faces of triangles that point away
+    CullFace = GL_CULL_FACE as isize,
+    /// Clamps the depth instead of clipping at the near and far planes
+    DepthClamp = GL_DEPTH_CLAMP as isize,
+    /// Tests fragments against the depth buffer
+    DepthTest = GL_DEPTH_TEST as isize,
+    /// Dithers colors before they are written
+    Dither = GL_DITHER as isize,
+    /// Turns linear colors into srgb when writing to srgb framebuffers
+    FramebufferSrgb = GL_FRAMEBUFFER_SRGB as isize,
+    /// Smooths the edges of lines
+    LineSmooth = GL_LINE_SMOOTH as isize,
+    /// Uses the samples of multisampled framebuffers
+    Multisample = GL_MULTISAMPLE as isize,
+    /// Offsets the depth of filled triangles
+    PolygonOffsetFill = GL_POLYGON_OFFSET_FILL as isize,
+    /// Offsets the depth of triangles drawn as lines
+    PolygonOffsetLine = GL_POLYGON_OFFSET_LINE as isize,
+    /// Starts a new strip or fan at the restart index
+    PrimitiveRestart = GL_PRIMITIVE_RESTART as isize,
+    /// Lets vertex shaders set the size of points
+    ProgramPointSize = GL_PROGRAM_POINT_SIZE as isize,
+    /// Throws everything away after the vertex shader, for transform feedback
+    RasterizerDiscard = GL_RASTERIZER_DISCARD as isize,
+    /// Turns alpha into multisample coverage
+    SampleAlphaToCoverage = GL_SAMPLE_ALPHA_TO_COVERAGE as isize,
+    /// Cuts drawing to the scissor box
+    ScissorTest = GL_SCISSOR_TEST as isize,
+    /// Tests fragments against the stencil buffer
+    StencilTest = GL_STENCIL_TEST as isize,
+    /// Filters across the faces of cube maps
+    TextureCubeMapSeamless = GL_TEXTURE_CUBE_MAP_SEAMLESS as isize,
+}
+
+impl From<Capability> for u32 {
+    fn from(cap: Capability) -> u32 {
+        cap as u32
+    }
+}
+
 /// glEnable enable various capabilities
-pub fn enable(cap: u32) {
-    unsafe { glEnable(cap) }
+///
+/// Turning on a capability a [RenderState](self::render_state::RenderState) covers makes
+/// the next [apply](self::render_state::RenderState::apply) set everything again
+pub fn enable(cap: Capability) {
+    unsafe { glEnable(cap.into()) }
+    forget_render_state(cap);
+}
+
+/// glDisable disables various capabilities, see [enable]
+pub fn disable(cap: Capability) {
+    unsafe { glDisable(cap.into()) }
+    forget_render_state(cap);
+}
+
+/// Invalidates the [RenderState](self::render_state::RenderState) if it covers cap
+fn forget_render_state(cap: Capability) {
+    if matches!(
+        cap,
+        Capability::Blend | Capability::CullFace | Capability::DepthTest | Capability::StencilTest
+    ) {
+        self::render_state::RenderState::invalidate();
+    }
 }
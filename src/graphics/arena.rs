@@ -0,0 +1,214 @@
+use std::{
+    alloc::{alloc_zeroed, dealloc, Layout},
+    cell::{Cell, RefCell},
+    mem::{align_of, size_of},
+    ptr::NonNull,
+};
+
+use bytemuck::Pod;
+
+/// How big the chunks of the arena of every thread start(in bytes)
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Every chunk is aligned to this, nothing in the arena can need more
+const CHUNK_ALIGN: usize = 16;
+
+thread_local! {
+    static FRAME_ARENA: RefCell<FrameArena> = RefCell::new(FrameArena::new(DEFAULT_CHUNK_SIZE));
+}
+
+/// Runs f with the frame arena of this thread, what the renderer puts its scratch data in
+///
+/// Everything allocated in f is only valid inside f, the memory is reused after the next
+/// [reset_frame_arena]
+///
+/// # Example
+/// ```
+/// with_frame_arena(|arena| {
+///     let instances = arena.alloc_iter(sprites.iter().map(|sprite| sprite.instance()));
+///     buffer_data(BufferType::Array, bytemuck::cast_slice(instances), BufferUsage::StreamDraw);
+/// });
+/// ```
+pub fn with_frame_arena<R>(f: impl FnOnce(&FrameArena) -> R) -> R {
+    FRAME_ARENA.with(|arena| f(&arena.borrow()))
+}
+
+/// Frees everything in the frame arena of this thread so it can be reused, is called by the
+/// world every update
+///
+/// # Panics
+/// When it is called inside of [with_frame_arena]
+pub fn reset_frame_arena() {
+    FRAME_ARENA.with(|arena| arena.borrow_mut().reset())
+}
+
+/// A chunk of memory owned by a [FrameArena]
+struct Chunk {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+impl Chunk {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size, CHUNK_ALIGN).unwrap();
+        let ptr = unsafe { alloc_zeroed(layout) };
+        Chunk {
+            ptr: NonNull::new(ptr).expect("Couldn't allocate a chunk for the frame arena"),
+            size,
+        }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.size, CHUNK_ALIGN).unwrap();
+        unsafe { dealloc(self.ptr.as_ptr(), layout) }
+    }
+}
+
+/// A bump allocator for data that only lives for a frame, like draw lists, flattened vertices
+/// and sort keys
+///
+/// Allocating only moves an offset forward and [FrameArena::reset] frees everything at once.
+/// After a reset the chunks are merged into one as big as the most that was ever used, so
+/// once a game has warmed up it doesn't touch the heap at all
+///
+/// Only [Pod] types can be allocated, so nothing has to be dropped
+///
+/// # Example
+/// ```
+/// let mut arena = FrameArena::new(DEFAULT_CHUNK_SIZE);
+///
+/// 'main_loop: loop {
+///     let keys = arena.alloc_iter(draws.iter().map(|draw| draw.sort_key()));
+///     keys.sort_unstable();
+///     // draw in the order of the keys
+///     arena.reset();
+/// }
+/// ```
+pub struct FrameArena {
+    chunks: RefCell<Vec<Chunk>>,
+    chunk: Cell<usize>,
+    offset: Cell<usize>,
+    used: Cell<usize>,
+    peak: usize,
+    chunk_size: usize,
+}
+
+impl FrameArena {
+    /// Creates a new arena whose first chunk is chunk_size bytes
+    pub fn new(chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(CHUNK_ALIGN);
+        FrameArena {
+            chunks: RefCell::new(vec![Chunk::new(chunk_size)]),
+            chunk: Cell::new(0),
+            offset: Cell::new(0),
+            used: Cell::new(0),
+            peak: 0,
+            chunk_size,
+        }
+    }
+
+    /// Allocates len zeroed values
+    ///
+    /// # Panics
+    /// When T has to be aligned to more than 16 bytes
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: Pod>(&self, len: usize) -> &mut [T] {
+        assert!(
+            align_of::<T>() <= CHUNK_ALIGN,
+            "The frame arena can't align to more than {} bytes",
+            CHUNK_ALIGN
+        );
+        let bytes = len * size_of::<T>();
+        if bytes == 0 {
+            return unsafe { std::slice::from_raw_parts_mut(NonNull::dangling().as_ptr(), len) };
+        }
+
+        let mut chunks = self.chunks.borrow_mut();
+        loop {
+            let index = self.chunk.get();
+            if index == chunks.len() {
+                chunks.push(Chunk::new(bytes.max(self.chunk_size)));
+            }
+            let chunk = &chunks[index];
+            let start = self.offset.get().next_multiple_of(align_of::<T>());
+            if start + bytes <= chunk.size {
+                // the padding is counted too, so a merged chunk of the peak always fits
+                self.used
+                    .set(self.used.get() + start + bytes - self.offset.get());
+                self.offset.set(start + bytes);
+                // every allocation is a part of a chunk no other allocation has and the
+                // chunks don't move or get freed until the arena is reset, which needs &mut
+                let values = unsafe {
+                    let ptr = chunk.ptr.as_ptr().add(start).cast::<T>();
+                    std::slice::from_raw_parts_mut(ptr, len)
+                };
+                values.fill(T::zeroed());
+                return values;
+            }
+            self.chunk.set(index + 1);
+            self.offset.set(0);
+        }
+    }
+
+    /// Allocates a copy of values
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_copy<T: Pod>(&self, values: &[T]) -> &mut [T] {
+        let out = self.alloc(values.len());
+        out.copy_from_slice(values);
+        out
+    }
+
+    /// Allocates every value of values
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_iter<T: Pod, I>(&self, values: I) -> &mut [T]
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        let out = self.alloc(values.len());
+        for (slot, value) in out.iter_mut().zip(values) {
+            *slot = value;
+        }
+        out
+    }
+
+    /// Frees everything that was allocated
+    ///
+    /// If more than one chunk was needed they are replaced by one that fits everything
+    pub fn reset(&mut self) {
+        self.peak = self.peak.max(self.used.get());
+        let chunks = self.chunks.get_mut();
+        if chunks.len() > 1 {
+            let size = self.peak.next_multiple_of(CHUNK_ALIGN).max(self.chunk_size);
+            chunks.clear();
+            chunks.push(Chunk::new(size));
+        }
+        self.chunk.set(0);
+        self.offset.set(0);
+        self.used.set(0);
+    }
+
+    /// Gets how many bytes were allocated since the last reset
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// Gets the most bytes that were ever allocated between two resets
+    pub fn peak(&self) -> usize {
+        self.peak.max(self.used.get())
+    }
+
+    /// Gets how many bytes the chunks of the arena have
+    pub fn capacity(&self) -> usize {
+        self.chunks.borrow().iter().map(|chunk| chunk.size).sum()
+    }
+}
+
+impl Default for FrameArena {
+    fn default() -> Self {
+        FrameArena::new(DEFAULT_CHUNK_SIZE)
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use nalgebra_glm::{vec2, Vec2};
+
+use super::{
+    sprite::{Sprite, TextureHandle},
+    texture::*,
+    *,
+};
+use crate::{
+    assets::json::Json,
+    ECS::mesh::{Mesh, VertexTrait},
+};
+
+/// A texture with many sprites packed into it, like a sprite sheet
+///
+/// Every sprite is a region of the texture, x, y, width and height(in pixels from the top
+/// left of the texture) like [Sprite::region]. The regions either come from a grid of
+/// cells of the same size or from a [TexturePacker](https://www.codeandweb.com/texturepacker)
+/// JSON descriptor, which also gives them names
+///
+/// # Example
+/// ```
+/// let tiles = TextureAtlas::from_grid(assets.load("tiles.png")?, 16, 16)?;
+/// let grass = tiles.sprite(3).unwrap();
+///
+/// let ui = TextureAtlas::from_json(assets.load("ui.png")?, &assets.load::<String>("ui.json")?)?;
+/// let button = ui.sprite(ui.find("button.png").unwrap()).unwrap();
+///
+/// // or put the sprite on a quad mesh, its uvs have to go from 0 to 1
+/// tiles.assign_uvs(3, &mut quad, |vertex| &mut vertex.tex_coord);
+/// quad.upload();
+/// ```
+pub struct TextureAtlas {
+    /// The texture the sprites are packed in
+    pub texture: Texture,
+    /// Size of the texture(in pixels)
+    pub size: Vec2,
+    /// The region of every sprite, x, y, width and height(in pixels from the top left)
+    pub regions: Vec<[f32; 4]>,
+    /// The index of every sprite that has a name
+    pub names: HashMap<String, usize>,
+}
+
+impl TextureAtlas {
+    /// Creates a new atlas from a packed image and the regions of its sprites
+    ///
+    /// The texture uses nearest filtering so pixel art stays sharp
+    pub fn new(
+        img: DynamicImage,
+        regions: Vec<[f32; 4]>,
+        names: HashMap<String, usize>,
+    ) -> Result<Self, LighthouseError> {
+        let size = vec2(img.width() as f32, img.height() as f32);
+        let texture = Texture::builder(img)
+            .filter(TextureFilter::Nearest, TextureFilter::Nearest)
+            .wrap(TextureWrap::ClampToEdge)
+            .build()?;
+        Ok(TextureAtlas {
+            texture,
+            size,
+            regions,
+            names,
+        })
+    }
+
+    /// Creates an atlas of a sheet made of cells of cell_width by cell_height(in pixels),
+    /// the sprites are numbered from the top left one row at a time
+    ///
+    /// Cells that don't fit in the image at the right or bottom are left out
+    pub fn from_grid(
+        img: DynamicImage,
+        cell_width: u32,
+        cell_height: u32,
+    ) -> Result<Self, LighthouseError> {
+        if cell_width == 0 || cell_height == 0 {
+            return Err(LighthouseError::InvalidInput(
+                "The cells of an atlas can't be empty".to_string(),
+            ));
+        }
+        let (columns, rows) = (img.width() / cell_width, img.height() / cell_height);
+        let regions = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                [
+                    (column * cell_width) as f32,
+                    (row * cell_height) as f32,
+                    cell_width as f32,
+                    cell_height as f32,
+                ]
+            })
+            .collect();
+        Self::new(img, regions, HashMap::new())
+    }
+
+    /// Creates an atlas from a TexturePacker JSON descriptor, in the hash or the array
+    /// format, and its image
+    ///
+    /// The sprites are numbered in the order of the descriptor and named by their file
+    /// names, rotated sprites aren't supported
+    pub fn from_json(img: DynamicImage, descriptor: &str) -> Result<Self, LighthouseError> {
+        let json = Json::parse(descriptor)?;
+        let frames: Vec<(&str, &Json)> = match json.get("frames") {
+            Some(Json::Object(frames)) => frames
+                .iter()
+                .map(|(name, frame)| (name.as_str(), frame))
+                .collect(),
+            Some(Json::Array(frames)) => frames
+                .iter()
+                .map(|frame| {
+                    let name = frame.get("filename").and_then(Json::as_str);
+                    name.map(|name| (name, frame)).ok_or_else(|| {
+                        LighthouseError::Parse("A frame has no filename".to_string())
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            _ => {
+                return Err(LighthouseError::Parse(
+                    "The atlas has no frames".to_string(),
+                ))
+            }
+        };
+
+        let mut regions = Vec::with_capacity(frames.len());
+        let mut names = HashMap::new();
+        for (name, frame) in frames {
+            if frame.get("rotated").and_then(Json::as_bool) == Some(true) {
+                return Err(LighthouseError::Unsupported(format!(
+                    "{} is rotated, rotated sprites aren't supported",
+                    name
+                )));
+            }
+            let rect = frame.get("frame");
+            let get = |key: &str| {
+                rect.and_then(|rect| rect.get(key))
+                    .and_then(Json::as_f64)
+                    .map(|value| value as f32)
+                    .ok_or_else(|| LighthouseError::Parse(format!("{} is missing {}", name, key)))
+            };
+            names.insert(name.to_string(), regions.len());
+            regions.push([get("x")?, get("y")?, get("w")?, get("h")?]);
+        }
+        Self::new(img, regions, names)
+    }
+
+    /// Gets how many sprites there are
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Checks if there are no sprites
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Finds the index of the sprite called name
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// Gets a handle to the texture of the atlas
+    pub fn handle(&self) -> TextureHandle {
+        TextureHandle::new(&self.texture, self.size)
+    }
+
+    /// Gets the region of the sprite at index, x, y, width and height(in pixels)
+    pub fn region(&self, index: usize) -> Option<[f32; 4]> {
+        self.regions.get(index).copied()
+    }
+
+    /// Gets a [Sprite] showing the sprite at index
+    pub fn sprite(&self, index: usize) -> Option<Sprite> {
+        Some(Sprite::new(self.handle()).region(self.region(index)?))
+    }
+
+    /// Gets the uv rectangle of the sprite at index, bottom left then top right
+    pub fn uv_rect(&self, index: usize) -> Option<[f32; 4]> {
+        let [x, y, width, height] = self.region(index)?;
+        // images are flipped when they are uploaded so v goes up from the bottom
+        Some([
+            x / self.size.x,
+            1.0 - (y + height) / self.size.y,
+            (x + width) / self.size.x,
+            1.0 - y / self.size.y,
+        ])
+    }
+
+    /// Moves a uv from 0 to 1 into the sprite at index, (0, 0) is its bottom left
+    pub fn map_uv(&self, index: usize, uv: Vec2) -> Option<Vec2> {
+        let [left, bottom, right, top] = self.uv_rect(index)?;
+        Some(vec2(
+            left + (right - left) * uv.x,
+            bottom + (top - bottom) * uv.y,
+        ))
+    }
+
+    /// Puts the sprite at index on mesh, uv gives the uv of a vertex
+    ///
+    /// The uvs of the mesh have to go from 0 to 1 over the whole quad, they are moved into
+    /// the sprite. Assigning again moves them again, so keep a copy of the mesh with its
+    /// original uvs to switch sprites. The mesh has to be uploaded afterwards. Gives false if
+    /// there is no sprite at index
+    pub fn assign_uvs<Vertex: VertexTrait + 'static + Sync + Send>(
+        &self,
+        index: usize,
+        mesh: &mut Mesh<Vertex>,
+        uv: impl Fn(&mut Vertex) -> &mut Vec2,
+    ) -> bool {
+        if index >= self.regions.len() {
+            return false;
+        }
+        for vertex in &mut mesh.vertices {
+            let uv = uv(vertex);
+            *uv = self.map_uv(index, *uv).unwrap();
+        }
+        true
+    }
+
+    /// Deletes the texture of the atlas
+    pub fn delete(self) {
+        self.texture.delete();
+    }
+}
@@ -0,0 +1,223 @@
+use super::{pipeline::BlendMode, render_state::RenderState, *};
+
+/// What a color is multiplied by before it is blended, see [Blend]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    /// 0
+    Zero = GL_ZERO as isize,
+    /// 1
+    One = GL_ONE as isize,
+    /// The color being drawn
+    SrcColor = GL_SRC_COLOR as isize,
+    /// 1 - the color being drawn
+    OneMinusSrcColor = GL_ONE_MINUS_SRC_COLOR as isize,
+    /// The color already in the framebuffer
+    DstColor = GL_DST_COLOR as isize,
+    /// 1 - the color already in the framebuffer
+    OneMinusDstColor = GL_ONE_MINUS_DST_COLOR as isize,
+    /// The alpha of the color being drawn
+    SrcAlpha = GL_SRC_ALPHA as isize,
+    /// 1 - the alpha of the color being drawn
+    OneMinusSrcAlpha = GL_ONE_MINUS_SRC_ALPHA as isize,
+    /// The alpha already in the framebuffer
+    DstAlpha = GL_DST_ALPHA as isize,
+    /// 1 - the alpha already in the framebuffer
+    OneMinusDstAlpha = GL_ONE_MINUS_DST_ALPHA as isize,
+    /// The constant color of the [Blend]
+    ConstantColor = GL_CONSTANT_COLOR as isize,
+    /// 1 - the constant color of the [Blend]
+    OneMinusConstantColor = GL_ONE_MINUS_CONSTANT_COLOR as isize,
+    /// The alpha of the constant color of the [Blend]
+    ConstantAlpha = GL_CONSTANT_ALPHA as isize,
+    /// 1 - the alpha of the constant color of the [Blend]
+    OneMinusConstantAlpha = GL_ONE_MINUS_CONSTANT_ALPHA as isize,
+    /// The smallest of the alpha being drawn and 1 - the alpha already there
+    SrcAlphaSaturate = GL_SRC_ALPHA_SATURATE as isize,
+}
+
+impl BlendFactor {
+    /// Gets the factor of an opengl enum, None if it isn't a blend factor
+    pub fn from_gl(factor: u32) -> Option<Self> {
+        use BlendFactor::*;
+        [
+            Zero,
+            One,
+            SrcColor,
+            OneMinusSrcColor,
+            DstColor,
+            OneMinusDstColor,
+            SrcAlpha,
+            OneMinusSrcAlpha,
+            DstAlpha,
+            OneMinusDstAlpha,
+            ConstantColor,
+            OneMinusConstantColor,
+            ConstantAlpha,
+            OneMinusConstantAlpha,
+            SrcAlphaSaturate,
+        ]
+        .into_iter()
+        .find(|f| *f as u32 == factor)
+    }
+}
+
+/// How the two multiplied colors are put together, see [Blend]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendEquation {
+    /// The new color plus the old color
+    #[default]
+    Add = GL_FUNC_ADD as isize,
+    /// The new color minus the old color
+    Subtract = GL_FUNC_SUBTRACT as isize,
+    /// The old color minus the new color
+    ReverseSubtract = GL_FUNC_REVERSE_SUBTRACT as isize,
+    /// The smallest of the two, the factors are ignored
+    Min = GL_MIN as isize,
+    /// The biggest of the two, the factors are ignored
+    Max = GL_MAX as isize,
+}
+
+/// How the color of what is drawn is mixed with what is already in the framebuffer
+///
+/// The color and alpha can be blended differently, the new color is multiplied by src, the
+/// old one by dst and the two are put together by the equation. Unlike a [BlendMode] it
+/// can use every factor and equation opengl has
+///
+/// # Example
+/// ```
+/// Blend::alpha().apply();
+/// sprites.draw(&camera);
+///
+/// // or by hand, the same as Blend::additive()
+/// set_blend_mode(BlendFactor::SrcAlpha, BlendFactor::One);
+///
+/// // darken with a subtractive blend
+/// Blend::new(BlendFactor::One, BlendFactor::One).equation(BlendEquation::ReverseSubtract).apply();
+/// Blend::disable();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blend {
+    /// What the new color is multiplied by
+    pub src_color: BlendFactor,
+    /// What the old color is multiplied by
+    pub dst_color: BlendFactor,
+    /// What the new alpha is multiplied by
+    pub src_alpha: BlendFactor,
+    /// What the old alpha is multiplied by
+    pub dst_alpha: BlendFactor,
+    /// How the colors are put together
+    pub color_equation: BlendEquation,
+    /// How the alphas are put together
+    pub alpha_equation: BlendEquation,
+    /// The color the constant factors use
+    pub constant: [f32; 4],
+}
+
+impl Blend {
+    /// Creates a new blend that multiplies the colors and alphas by src and dst and adds
+    /// them
+    pub fn new(src: BlendFactor, dst: BlendFactor) -> Self {
+        Blend {
+            src_color: src,
+            dst_color: dst,
+            src_alpha: src,
+            dst_alpha: dst,
+            color_equation: BlendEquation::Add,
+            alpha_equation: BlendEquation::Add,
+            constant: [0.0; 4],
+        }
+    }
+
+    /// The new color is laid over the old one by its alpha, what most transparent sprites
+    /// need
+    ///
+    /// The alpha is blended so drawing into a transparent render target keeps it right
+    pub fn alpha() -> Self {
+        Blend::new(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha)
+            .alpha_factors(BlendFactor::One, BlendFactor::OneMinusSrcAlpha)
+    }
+
+    /// Like [Blend::alpha] but the new color is already multiplied by its alpha, so
+    /// filtered edges don't get dark fringes
+    pub fn premultiplied() -> Self {
+        Blend::new(BlendFactor::One, BlendFactor::OneMinusSrcAlpha)
+    }
+
+    /// The new color is added onto the old one by its alpha, for light, fire and sparks
+    pub fn additive() -> Self {
+        Blend::new(BlendFactor::SrcAlpha, BlendFactor::One)
+    }
+
+    /// The old color is multiplied by the new one, for shadows and tints
+    pub fn multiply() -> Self {
+        Blend::new(BlendFactor::DstColor, BlendFactor::Zero)
+    }
+
+    /// Gets the blend of a [BlendMode], None for [BlendMode::Opaque]
+    pub fn from_mode(mode: BlendMode) -> Option<Self> {
+        let (src, dst) = mode.factors()?;
+        Some(Blend::new(src, dst))
+    }
+
+    /// Sets the factors of the alpha only
+    pub fn alpha_factors(mut self, src: BlendFactor, dst: BlendFactor) -> Self {
+        self.src_alpha = src;
+        self.dst_alpha = dst;
+        self
+    }
+
+    /// Sets the equation of both the color and the alpha
+    pub fn equation(mut self, equation: BlendEquation) -> Self {
+        self.color_equation = equation;
+        self.alpha_equation = equation;
+        self
+    }
+
+    /// Sets the equation of the alpha only
+    pub fn alpha_equation(mut self, equation: BlendEquation) -> Self {
+        self.alpha_equation = equation;
+        self
+    }
+
+    /// Sets the constant color
+    pub fn constant(mut self, constant: [f32; 4]) -> Self {
+        self.constant = constant;
+        self
+    }
+
+    /// Turns blending on and makes this the way it blends
+    ///
+    /// The state of [RenderState] is forgotten, so the next [RenderState::apply] sets its
+    /// blending again
+    pub fn apply(&self) {
+        unsafe {
+            glEnable(GL_BLEND);
+            glBlendFuncSeparate(
+                self.src_color as u32,
+                self.dst_color as u32,
+                self.src_alpha as u32,
+                self.dst_alpha as u32,
+            );
+            glBlendEquationSeparate(self.color_equation as u32, self.alpha_equation as u32);
+            let [r, g, b, a] = self.constant;
+            glBlendColor(r, g, b, a);
+        }
+        RenderState::invalidate();
+    }
+
+    /// Turns blending off, what is drawn replaces what is already there
+    pub fn disable() {
+        unsafe { glDisable(GL_BLEND) }
+        RenderState::invalidate();
+    }
+}
+
+/// Turns blending on with the color and alpha multiplied by src and dst and added together
+///
+/// # Example
+/// ```
+/// set_blend_mode(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha);
+/// ```
+pub fn set_blend_mode(src: BlendFactor, dst: BlendFactor) {
+    Blend::new(src, dst).apply();
+}
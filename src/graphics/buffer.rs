@@ -1,4 +1,4 @@
-use super::*;
+use super::{recovery::ContextId, *};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Specifies what the type of the [Buffer] is
@@ -9,8 +9,45 @@ pub enum BufferType {
     ElementArray = GL_ELEMENT_ARRAY_BUFFER as isize,
 }
 
+impl From<BufferType> for u32 {
+    fn from(ty: BufferType) -> u32 {
+        ty as u32
+    }
+}
+
+/// How the data of a buffer is going to be used, opengl uses it to decide where to keep it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Set once and drawn many times
+    StaticDraw = GL_STATIC_DRAW as isize,
+    /// Set many times and drawn many times
+    DynamicDraw = GL_DYNAMIC_DRAW as isize,
+    /// Set once and drawn a few times, like data that changes every frame
+    StreamDraw = GL_STREAM_DRAW as isize,
+    /// Set once by opengl and read many times
+    StaticRead = GL_STATIC_READ as isize,
+    /// Set many times by opengl and read many times
+    DynamicRead = GL_DYNAMIC_READ as isize,
+    /// Set once by opengl and read a few times
+    StreamRead = GL_STREAM_READ as isize,
+    /// Set once by opengl and drawn many times
+    StaticCopy = GL_STATIC_COPY as isize,
+    /// Set many times by opengl and drawn many times
+    DynamicCopy = GL_DYNAMIC_COPY as isize,
+    /// Set once by opengl and drawn a few times
+    StreamCopy = GL_STREAM_COPY as isize,
+}
+
+impl From<BufferUsage> for u32 {
+    fn from(usage: BufferUsage) -> u32 {
+        usage as u32
+    }
+}
+
 /// Implementation of [VBO](https://www.khronos.org/opengl/wiki/Vertex_Specification#Vertex_Buffer_Object)
-pub struct Buffer(pub u32);
+///
+/// The buffer is deleted when it is dropped, see [ContextId]
+pub struct Buffer(pub u32, ContextId);
 impl Buffer {
     /// Makes a new vertex buffer
     pub fn new() -> Option<Self> {
@@ -19,7 +56,7 @@ impl Buffer {
             glGenBuffers(1, &mut vbo);
         }
         if vbo != 0 {
-            Some(Self(vbo))
+            Some(Self(vbo, ContextId::current()))
         } else {
             None
         }
@@ -34,16 +71,214 @@ impl Buffer {
     pub fn clear_binding(ty: BufferType) {
         unsafe { glBindBuffer(ty as u32, 0) }
     }
+
+    /// Deletes the buffer now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if self.1.is_current() {
+            unsafe { glDeleteBuffers(1, &self.0) }
+        }
+    }
 }
 
 /// Store the data in the buffer
-pub fn buffer_data(ty: BufferType, data: &[u8], usage: u32) {
+pub fn buffer_data(ty: BufferType, data: &[u8], usage: BufferUsage) {
     unsafe {
         glBufferData(
-            ty as u32,
+            ty.into(),
             data.len().try_into().unwrap(),
             data.as_ptr().cast(),
+            usage.into(),
+        );
+    }
+}
+
+/// What shapes the vertices of a draw call make
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Primitive {
+    /// Every vertex is a point
+    Points = GL_POINTS as isize,
+    /// Every 2 vertices are a line
+    Lines = GL_LINES as isize,
+    /// Every vertex is connected to the one before it
+    LineStrip = GL_LINE_STRIP as isize,
+    /// Like [Primitive::LineStrip] but the last vertex is connected to the first one too
+    LineLoop = GL_LINE_LOOP as isize,
+    /// Every 3 vertices are a triangle
+    #[default]
+    Triangles = GL_TRIANGLES as isize,
+    /// Every vertex makes a triangle with the 2 before it
+    TriangleStrip = GL_TRIANGLE_STRIP as isize,
+    /// Every vertex makes a triangle with the one before it and the first one
+    TriangleFan = GL_TRIANGLE_FAN as isize,
+    /// Lines with the vertices next to them, for geometry shaders
+    LinesAdjacency = GL_LINES_ADJACENCY as isize,
+    /// Triangles with the vertices next to them, for geometry shaders
+    TrianglesAdjacency = GL_TRIANGLES_ADJACENCY as isize,
+}
+
+impl From<Primitive> for u32 {
+    fn from(primitive: Primitive) -> u32 {
+        primitive as u32
+    }
+}
+
+/// The type of the indices in an [IndexBuffer]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexType {
+    /// 8 bit indices, up to 256 vertices
+    U8 = GL_UNSIGNED_BYTE as isize,
+    /// 16 bit indices, up to 65536 vertices
+    U16 = GL_UNSIGNED_SHORT as isize,
+    /// 32 bit indices
+    U32 = GL_UNSIGNED_INT as isize,
+}
+
+impl IndexType {
+    /// Gets how big an index is(in bytes)
+    pub fn size(self) -> usize {
+        match self {
+            IndexType::U8 => 1,
+            IndexType::U16 => 2,
+            IndexType::U32 => 4,
+        }
+    }
+}
+
+impl From<IndexType> for u32 {
+    fn from(ty: IndexType) -> u32 {
+        ty as u32
+    }
+}
+
+/// A type that can be put in an [IndexBuffer], it is u8, u16 and u32
+pub trait Index: bytemuck::Pod {
+    /// The [IndexType] of the type
+    const TYPE: IndexType;
+}
+
+impl Index for u8 {
+    const TYPE: IndexType = IndexType::U8;
+}
+
+impl Index for u16 {
+    const TYPE: IndexType = IndexType::U16;
+}
+
+impl Index for u32 {
+    const TYPE: IndexType = IndexType::U32;
+}
+
+/// An element array buffer that remembers how many indices it has and their type, so it can
+/// be drawn with [draw_elements]
+///
+/// The element array buffer binding is part of the vao, so it has to be bound while the vao
+/// it belongs to is bound
+///
+/// # Example
+/// ```
+/// vao.bind();
+/// let indices = IndexBuffer::with_data(&[0u16, 1, 2, 2, 3, 0], BufferUsage::StaticDraw)?;
+///
+/// 'main_loop: loop {
+///     vao.bind();
+///     draw_elements(Primitive::Triangles, &indices);
+/// }
+/// ```
+pub struct IndexBuffer {
+    buffer: Buffer,
+    count: usize,
+    index_type: IndexType,
+}
+
+impl IndexBuffer {
+    /// Makes a new index buffer without any indices
+    pub fn new() -> Result<Self, LighthouseError> {
+        let buffer = Buffer::new()
+            .ok_or_else(|| LighthouseError::BufferAlloc("an index buffer".to_string()))?;
+        Ok(IndexBuffer {
+            buffer,
+            count: 0,
+            index_type: IndexType::U32,
+        })
+    }
+
+    /// Makes a new index buffer with indices in it, it is left bound
+    pub fn with_data<I: Index>(indices: &[I], usage: BufferUsage) -> Result<Self, LighthouseError> {
+        let mut buffer = IndexBuffer::new()?;
+        buffer.set_data(indices, usage);
+        Ok(buffer)
+    }
+
+    /// Gets the id of the buffer
+    pub fn id(&self) -> u32 {
+        self.buffer.0
+    }
+
+    /// Gets how many indices there are
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Gets the type of the indices
+    pub fn index_type(&self) -> IndexType {
+        self.index_type
+    }
+
+    /// Binds the buffer as the element array buffer of the bound vao
+    pub fn bind(&self) {
+        self.buffer.bind(BufferType::ElementArray)
+    }
+
+    /// Replaces the indices, the buffer is left bound
+    pub fn set_data<I: Index>(&mut self, indices: &[I], usage: BufferUsage) {
+        self.bind();
+        buffer_data(
+            BufferType::ElementArray,
+            bytemuck::cast_slice(indices),
             usage,
         );
+        self.count = indices.len();
+        self.index_type = I::TYPE;
+    }
+
+    /// Deletes the buffer now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+/// Draws every index of index_buffer as primitive with the bound vao and program
+pub fn draw_elements(primitive: Primitive, index_buffer: &IndexBuffer) {
+    draw_elements_range(primitive, index_buffer, 0..index_buffer.count);
+}
+
+/// Draws the indices in range of index_buffer as primitive with the bound vao and program
+///
+/// # Panics
+/// When the range goes past the end of the buffer
+pub fn draw_elements_range(
+    primitive: Primitive,
+    index_buffer: &IndexBuffer,
+    range: std::ops::Range<usize>,
+) {
+    assert!(
+        range.start <= range.end && range.end <= index_buffer.count,
+        "Can't draw indices {:?} of an index buffer with {} indices",
+        range,
+        index_buffer.count
+    );
+    if range.is_empty() {
+        return;
+    }
+    index_buffer.bind();
+    unsafe {
+        glDrawElements(
+            primitive.into(),
+            range.len() as i32,
+            index_buffer.index_type.into(),
+            (range.start * index_buffer.index_type.size()) as *const _,
+        )
     }
 }
@@ -0,0 +1,462 @@
+use std::collections::VecDeque;
+
+use nalgebra_glm::{vec2, Vec2};
+
+use super::{draw2d::Draw2D, text::Font, ui::UiBatch};
+use crate::profiler::{section_depth, Profiler};
+
+/// The colors of the sections of a [ProfilerOverlay], they repeat when there are more
+const SECTION_COLORS: [[f32; 4]; 6] = [
+    [0.3, 0.7, 1.0, 0.9],
+    [1.0, 0.6, 0.2, 0.9],
+    [0.4, 0.9, 0.4, 0.9],
+    [0.9, 0.4, 0.8, 0.9],
+    [1.0, 0.9, 0.3, 0.9],
+    [0.6, 0.6, 0.6, 0.9],
+];
+
+/// Where a chart is on the screen and how its values are scaled, shared by [LineGraph],
+/// [BarGraph] and [TimeSeries]
+///
+/// Charts are drawn with a [Draw2D] in [screen space](Draw2D::set_screen_space)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    /// The top left of the chart(in pixels from the top left of the screen)
+    pub pos: Vec2,
+    /// The size of the chart(in pixels)
+    pub size: Vec2,
+    /// The values at the bottom and top, None to fit the values. A fitted range always has
+    /// 0 in it so bars start at 0
+    pub range: Option<(f32, f32)>,
+    /// The color behind the chart
+    pub background: [f32; 4],
+    /// Horizontal lines at a value, e.g. 16.6 ms on a frame time graph
+    pub guides: Vec<(f32, [f32; 4])>,
+}
+
+impl Chart {
+    /// Creates a chart at pos with size(in pixels) that fits its values
+    pub fn new(pos: Vec2, size: Vec2) -> Self {
+        Chart {
+            pos,
+            size,
+            range: None,
+            background: [0.0, 0.0, 0.0, 0.5],
+            guides: Vec::new(),
+        }
+    }
+
+    /// Sets the values at the bottom and top, an empty range is widened to min to min + 1
+    pub fn range(mut self, min: f32, max: f32) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    /// Sets the color behind the chart
+    pub fn background(mut self, background: [f32; 4]) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Adds a horizontal line at value
+    pub fn guide(mut self, value: f32, color: [f32; 4]) -> Self {
+        self.guides.push((value, color));
+        self
+    }
+
+    /// Gets the values at the bottom and top for values
+    pub fn value_range(&self, values: impl Iterator<Item = f32>) -> (f32, f32) {
+        if let Some((min, max)) = self.range {
+            // every value would be on the same line and the scale divides by 0
+            return if max == min {
+                (min, min + 1.0)
+            } else {
+                (min, max)
+            };
+        }
+        let (min, max) = values
+            .filter(|value| value.is_finite())
+            .fold((0.0f32, 0.0f32), |(min, max), value| {
+                (min.min(value), max.max(value))
+            });
+        if max - min <= f32::EPSILON {
+            (min, min + 1.0)
+        } else {
+            (min, max)
+        }
+    }
+
+    /// Gets the y of value on the screen(in pixels), values outside of range are put at the
+    /// edge
+    pub fn y(&self, value: f32, (min, max): (f32, f32)) -> f32 {
+        let t = ((value - min) / (max - min)).clamp(0.0, 1.0);
+        self.pos.y + self.size.y * (1.0 - t)
+    }
+
+    /// Draws the background and the guides that are in range
+    fn draw_frame(&self, draw: &mut Draw2D, range: (f32, f32)) {
+        draw.draw_rect(self.pos, self.size, self.background);
+        for &(value, color) in &self.guides {
+            if value >= range.0.min(range.1) && value <= range.0.max(range.1) {
+                let y = self.y(value, range);
+                draw.draw_rect(vec2(self.pos.x, y), vec2(self.size.x, 1.0), color);
+            }
+        }
+    }
+}
+
+/// A line through values from the left to the right of a [Chart]
+///
+/// # Example
+/// ```
+/// let graph = LineGraph::new(Chart::new(vec2(10.0, 10.0), vec2(300.0, 80.0)))
+///     .color([0.3, 1.0, 0.3, 1.0]);
+///
+/// 'main_loop: loop {
+///     draw.set_screen_space(world.env.win_size);
+///     graph.draw(&mut draw, &speeds);
+///     draw.flush();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineGraph {
+    /// Where the graph is and how it is scaled
+    pub chart: Chart,
+    /// The color of the line
+    pub color: [f32; 4],
+    /// How thick the line is(in pixels)
+    pub line_width: f32,
+}
+
+impl LineGraph {
+    /// Creates a white line graph in chart
+    pub fn new(chart: Chart) -> Self {
+        LineGraph {
+            chart,
+            color: [1.0; 4],
+            line_width: 1.0,
+        }
+    }
+
+    /// Sets the color of the line
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets how thick the line is(in pixels)
+    pub fn line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Draws the graph of values
+    pub fn draw(&self, draw: &mut Draw2D, values: &[f32]) {
+        let range = self.chart.value_range(values.iter().copied());
+        self.chart.draw_frame(draw, range);
+        self.draw_line(draw, values, self.chart.pos.x, self.chart.size.x, range);
+    }
+
+    /// Draws the line of values from left over width(in pixels)
+    fn draw_line(
+        &self,
+        draw: &mut Draw2D,
+        values: &[f32],
+        left: f32,
+        width: f32,
+        range: (f32, f32),
+    ) {
+        let ys: Vec<f32> = values
+            .iter()
+            .map(|&value| self.chart.y(value, range))
+            .collect();
+        if let [y] = ys[..] {
+            let a = vec2(left, y);
+            draw.draw_line(a, a + vec2(width, 0.0), self.line_width, self.color);
+            return;
+        }
+
+        let step = width / (ys.len().max(2) - 1) as f32;
+        for (i, pair) in ys.windows(2).enumerate() {
+            let x = left + i as f32 * step;
+            draw.draw_line(
+                vec2(x, pair[0]),
+                vec2(x + step, pair[1]),
+                self.line_width,
+                self.color,
+            );
+        }
+    }
+}
+
+/// A bar for every value from the left to the right of a [Chart], bars start at 0
+///
+/// # Example
+/// ```
+/// let graph = BarGraph::new(Chart::new(vec2(10.0, 100.0), vec2(200.0, 60.0)).range(0.0, 100.0))
+///     .gap(2.0);
+///
+/// draw.set_screen_space(world.env.win_size);
+/// graph.draw(&mut draw, &[rifle_ammo, pistol_ammo, grenades]);
+/// draw.flush();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarGraph {
+    /// Where the graph is and how it is scaled
+    pub chart: Chart,
+    /// The color of the bars, see [BarGraph::draw_colored] for a color for every bar
+    pub color: [f32; 4],
+    /// The space between bars(in pixels)
+    pub gap: f32,
+}
+
+impl BarGraph {
+    /// Creates a bar graph with white bars in chart
+    pub fn new(chart: Chart) -> Self {
+        BarGraph {
+            chart,
+            color: [1.0; 4],
+            gap: 1.0,
+        }
+    }
+
+    /// Sets the color of the bars
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the space between bars(in pixels)
+    pub fn gap(mut self, gap: f32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Draws bars of values
+    pub fn draw(&self, draw: &mut Draw2D, values: &[f32]) {
+        let bars: Vec<(f32, [f32; 4])> = values.iter().map(|&value| (value, self.color)).collect();
+        self.draw_colored(draw, &bars);
+    }
+
+    /// Draws bars of values with their own color
+    pub fn draw_colored(&self, draw: &mut Draw2D, bars: &[(f32, [f32; 4])]) {
+        let chart = &self.chart;
+        let range = chart.value_range(bars.iter().map(|(value, _)| *value));
+        chart.draw_frame(draw, range);
+        if bars.is_empty() {
+            return;
+        }
+
+        let slot = chart.size.x / bars.len() as f32;
+        let width = (slot - self.gap).max(1.0);
+        let zero = chart.y(0.0, range);
+        for (i, &(value, color)) in bars.iter().enumerate() {
+            let y = chart.y(value, range);
+            let x = chart.pos.x + i as f32 * slot + (slot - width) / 2.0;
+            draw.draw_rect(vec2(x, y.min(zero)), vec2(width, (y - zero).abs()), color);
+        }
+    }
+}
+
+/// A [LineGraph] of the last values pushed to it, new values come in on the right
+///
+/// # Example
+/// ```
+/// let mut fps = TimeSeries::new(
+///     LineGraph::new(Chart::new(vec2(10.0, 10.0), vec2(240.0, 60.0)).guide(60.0, [1.0, 0.0, 0.0, 1.0])),
+///     240,
+/// );
+///
+/// 'main_loop: loop {
+///     fps.push(1.0 / world.env.time.real_delta);
+///     draw.set_screen_space(world.env.win_size);
+///     fps.draw(&mut draw);
+///     draw.flush();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries {
+    /// The graph the values are drawn with
+    pub graph: LineGraph,
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl TimeSeries {
+    /// Creates a time series that remembers capacity values
+    pub fn new(graph: LineGraph, capacity: usize) -> Self {
+        TimeSeries {
+            graph,
+            samples: VecDeque::with_capacity(capacity.max(1)),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Adds a value on the right, the oldest value is forgotten when it is full
+    pub fn push(&mut self, value: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    /// Gets the values from the oldest to the newest
+    pub fn samples(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// Gets the newest value
+    pub fn last(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
+
+    /// Gets the average of the values, 0 without values
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+
+    /// Gets the biggest value, 0 without values
+    pub fn max(&self) -> f32 {
+        self.samples.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Forgets every value
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Draws the graph of the values
+    ///
+    /// The graph always has room for capacity values, so values move left at the same speed
+    /// while it is filling up
+    pub fn draw(&self, draw: &mut Draw2D) {
+        let values: Vec<f32> = self.samples.iter().copied().collect();
+        let chart = &self.graph.chart;
+        let range = chart.value_range(values.iter().copied());
+        let filled = values.len().saturating_sub(1) as f32 / (self.capacity - 1).max(1) as f32;
+        let width = chart.size.x * filled.min(1.0);
+
+        chart.draw_frame(draw, range);
+        self.graph.draw_line(
+            draw,
+            &values,
+            chart.pos.x + chart.size.x - width,
+            width,
+            range,
+        );
+    }
+}
+
+/// Shows the frame times and sections of a [Profiler] on top of the screen
+///
+/// The frame times are a [TimeSeries] with lines at 60 and 30 fps, the sections are a
+/// [BarGraph] of the top level sections of the last frame, like `update` and `render`
+///
+/// # Example
+/// ```
+/// world.profiler = Some(Profiler::new());
+/// let mut overlay = ProfilerOverlay::new(vec2(10.0, 10.0));
+///
+/// 'main_loop: loop {
+///     world.update();
+///     world.render();
+///     if let Some(profiler) = &world.profiler {
+///         overlay.update(profiler);
+///     }
+///     draw.set_screen_space(world.env.win_size);
+///     overlay.draw(&mut draw);
+///     draw.flush();
+///     overlay.draw_text(&mut ui, &font, 100);
+///     ui.draw(world.env.win_size);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfilerOverlay {
+    /// The frame times(in milliseconds)
+    pub frames: TimeSeries,
+    /// The top level sections of the last frame(in milliseconds)
+    pub sections: BarGraph,
+    /// The top level sections of the last frame and their time(in milliseconds)
+    last_sections: Vec<(String, f32)>,
+}
+
+impl ProfilerOverlay {
+    /// Creates an overlay with its top left at pos(in pixels)
+    pub fn new(pos: Vec2) -> Self {
+        let frame_chart = Chart::new(pos, vec2(240.0, 60.0))
+            .range(0.0, 50.0)
+            .guide(1000.0 / 60.0, [0.3, 1.0, 0.3, 0.6])
+            .guide(1000.0 / 30.0, [1.0, 0.3, 0.3, 0.6]);
+        let section_chart = Chart::new(pos + vec2(0.0, 64.0), vec2(240.0, 40.0));
+        ProfilerOverlay {
+            frames: TimeSeries::new(LineGraph::new(frame_chart).color([1.0, 1.0, 1.0, 0.9]), 240),
+            sections: BarGraph::new(section_chart).gap(4.0),
+            last_sections: Vec::new(),
+        }
+    }
+
+    /// Reads the last frame of profiler
+    pub fn update(&mut self, profiler: &Profiler) {
+        let Some(frame) = profiler.last_frame() else {
+            return;
+        };
+        self.frames.push(frame.total.as_secs_f32() * 1000.0);
+        self.last_sections = frame
+            .sections
+            .iter()
+            .filter(|(path, _)| section_depth(path) == 0)
+            .map(|(path, duration)| (path.clone(), duration.as_secs_f32() * 1000.0))
+            .collect();
+    }
+
+    /// Draws the graphs of the overlay
+    pub fn draw(&self, draw: &mut Draw2D) {
+        self.frames.draw(draw);
+        let bars: Vec<(f32, [f32; 4])> = self
+            .last_sections
+            .iter()
+            .enumerate()
+            .map(|(i, (_, ms))| (*ms, SECTION_COLORS[i % SECTION_COLORS.len()]))
+            .collect();
+        self.sections.draw_colored(draw, &bars);
+    }
+
+    /// Sets the text elements of ui to the last frame time and the names of the sections,
+    /// they are called `profiler_overlay/...`
+    pub fn draw_text(&self, ui: &mut UiBatch, font: &Font, layer: i32) {
+        let chart = &self.frames.graph.chart;
+        let text = format!(
+            "{:.2} ms (avg {:.2} ms)",
+            self.frames.last().unwrap_or(0.0),
+            self.frames.average()
+        );
+        ui.text(
+            "profiler_overlay/frame_time",
+            font,
+            &text,
+            chart.pos + vec2(4.0, 4.0),
+            1.0,
+            [1.0; 4],
+            layer,
+        );
+        let names = self
+            .last_sections
+            .iter()
+            .map(|(name, ms)| format!("{} {:.1}", name, ms))
+            .collect::<Vec<_>>()
+            .join("  ");
+        let chart = &self.sections.chart;
+        ui.text(
+            "profiler_overlay/section_names",
+            font,
+            &names,
+            chart.pos + vec2(0.0, chart.size.y + 4.0),
+            1.0,
+            [1.0; 4],
+            layer,
+        );
+    }
+}
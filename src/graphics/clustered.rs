@@ -0,0 +1,310 @@
+use nalgebra_glm::{vec3, vec4, Mat4, Vec3};
+
+use super::{
+    arena::with_frame_arena, buffer::Buffer, lighting::PointLight, shader::ShaderProgram,
+    texture::Texture, uniform::Uniform, *,
+};
+
+/// The most point lights a [ClusteredLights] can draw at once
+pub const MAX_POINT_LIGHTS: usize = 256;
+
+/// Glsl that declares the clustered light uniforms and
+/// `vec3 apply_point_lights(vec3 albedo, vec3 world_pos, vec3 normal)`
+///
+/// Paste it into a fragment shader to light it with a [ClusteredLights], every fragment
+/// only goes through the lights of the cluster it is in, see [ClusteredLights::bind]
+pub const CLUSTERED_GLSL: &str = "layout (std140) uniform PointLights {
+    vec4 light_pos_radius[256];
+    vec4 light_color[256];
+};
+uniform usamplerBuffer cluster_grid;
+uniform usamplerBuffer cluster_lights;
+uniform uvec3 cluster_count;
+uniform vec2 screen_size;
+uniform float cluster_near;
+uniform float cluster_far;
+
+vec3 apply_point_lights(vec3 albedo, vec3 world_pos, vec3 normal) {
+    float z = gl_FragCoord.z * 2.0 - 1.0;
+    float depth = 2.0 * cluster_near * cluster_far
+        / (cluster_far + cluster_near - z * (cluster_far - cluster_near));
+    float slice = log(depth / cluster_near) / log(cluster_far / cluster_near) * float(cluster_count.z);
+    uvec3 cell = uvec3(clamp(
+        vec3(gl_FragCoord.xy / screen_size * vec2(cluster_count.xy), slice),
+        vec3(0.0),
+        vec3(cluster_count - 1u)
+    ));
+    uint cluster = (cell.z * cluster_count.y + cell.y) * cluster_count.x + cell.x;
+    uvec2 range = texelFetch(cluster_grid, int(cluster)).xy;
+
+    vec3 n = normalize(normal);
+    vec3 light = vec3(0.0);
+    for (uint i = 0u; i < range.y; i++) {
+        uint index = texelFetch(cluster_lights, int(range.x + i)).x;
+        vec4 pos_radius = light_pos_radius[index];
+        vec3 to_light = pos_radius.xyz - world_pos;
+        float dist = length(to_light);
+        // fades smoothly to 0 at the radius
+        float falloff = clamp(1.0 - pow(dist / pos_radius.w, 4.0), 0.0, 1.0);
+        float diffuse = max(dot(n, to_light / max(dist, 0.0001)), 0.0);
+        light += light_color[index].rgb * falloff * falloff / (dist * dist + 1.0) * diffuse;
+    }
+    return albedo * light;
+}";
+
+/// Many point lights on the forward path, culled into a grid of clusters
+///
+/// The view frustum is cut into tiles on the screen and exponential slices in depth,
+/// every frame the lights are binned into the clusters they touch on the cpu and
+/// uploaded, so a fragment only has to go through the lights near it. The buffers and
+/// textures are deleted when it is dropped
+///
+/// # Example
+/// ```
+/// let mut lights = ClusteredLights::new([16, 9, 24], &proj, 0.1, 100.0)?;
+/// lights.lights.push(PointLight::new(vec3(0.0, 2.0, 0.0), 5.0).color([1.0, 0.5, 0.2]));
+///
+/// 'main_loop: loop {
+///     lights.update(&view);
+///     lights.bind(&program, 0, 1, [1280.0, 720.0]);
+///     mesh.draw();
+/// }
+/// ```
+pub struct ClusteredLights {
+    /// The lights that are drawn, only the first [MAX_POINT_LIGHTS] are used
+    pub lights: Vec<PointLight>,
+    count: [u32; 3],
+    near: f32,
+    far: f32,
+    /// The view space bounding box of every cluster
+    bounds: Vec<(Vec3, Vec3)>,
+    ubo: Buffer,
+    grid_buffer: Buffer,
+    index_buffer: Buffer,
+    grid_texture: Texture,
+    index_texture: Texture,
+}
+
+impl ClusteredLights {
+    /// Creates a new grid of count[0] by count[1] tiles on the screen and count[2] slices
+    /// of depth, for a camera with the perspective matrix proj that sees from near to far
+    pub fn new(count: [u32; 3], proj: &Mat4, near: f32, far: f32) -> Result<Self, LighthouseError> {
+        if count.contains(&0) {
+            return Err(LighthouseError::InvalidInput(
+                "A cluster grid needs at least 1 cluster on every axis".to_string(),
+            ));
+        }
+        let buffer =
+            || Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a buffer".to_string()));
+        let mut lights = ClusteredLights {
+            lights: Vec::new(),
+            count,
+            near,
+            far,
+            bounds: Vec::new(),
+            ubo: buffer()?,
+            grid_buffer: buffer()?,
+            index_buffer: buffer()?,
+            grid_texture: Texture::new(),
+            index_texture: Texture::new(),
+        };
+        lights.set_projection(proj, near, far);
+
+        unsafe {
+            // the texture buffers read whatever is in the buffers, so they are made once
+            for (texture, buffer, format) in [
+                (&lights.grid_texture, &lights.grid_buffer, GL_RG32UI),
+                (&lights.index_texture, &lights.index_buffer, GL_R32UI),
+            ] {
+                glBindBuffer(GL_TEXTURE_BUFFER, buffer.0);
+                glBufferData(
+                    GL_TEXTURE_BUFFER,
+                    8,
+                    [0u32; 2].as_ptr().cast(),
+                    GL_STREAM_DRAW,
+                );
+                glBindTexture(GL_TEXTURE_BUFFER, texture.id);
+                glTexBuffer(GL_TEXTURE_BUFFER, format, buffer.0);
+            }
+            glBindTexture(GL_TEXTURE_BUFFER, 0);
+            glBindBuffer(GL_TEXTURE_BUFFER, 0);
+        }
+        Ok(lights)
+    }
+
+    /// Changes the perspective of the camera, call it when the fov, aspect or planes change
+    pub fn set_projection(&mut self, proj: &Mat4, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+        let inverse = proj.try_inverse().unwrap_or_default();
+        let [count_x, count_y, count_z] = self.count;
+
+        // the point on the near plane a corner of the screen is at
+        let corner = |x: u32, y: u32| {
+            let ndc = vec4(
+                x as f32 / count_x as f32 * 2.0 - 1.0,
+                y as f32 / count_y as f32 * 2.0 - 1.0,
+                -1.0,
+                1.0,
+            );
+            let point = inverse * ndc;
+            let point = point.xyz() / point.w;
+            point / -point.z
+        };
+
+        self.bounds = Vec::with_capacity((count_x * count_y * count_z) as usize);
+        for z in 0..count_z {
+            let depths = [self.slice_depth(z), self.slice_depth(z + 1)];
+            for y in 0..count_y {
+                for x in 0..count_x {
+                    let mut min = vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+                    let mut max = -min;
+                    for point in [
+                        corner(x, y),
+                        corner(x + 1, y),
+                        corner(x, y + 1),
+                        corner(x + 1, y + 1),
+                    ] {
+                        for depth in depths {
+                            min = min.inf(&(point * depth));
+                            max = max.sup(&(point * depth));
+                        }
+                    }
+                    self.bounds.push((min, max));
+                }
+            }
+        }
+    }
+
+    /// Bins the lights into the clusters and uploads them, view is the view matrix of the
+    /// camera
+    pub fn update(&mut self, view: &Mat4) {
+        with_frame_arena(|arena| {
+            // every position and radius followed by every color, like the uniform block
+            let lights = arena.alloc::<[f32; 4]>(MAX_POINT_LIGHTS * 2);
+            for (i, light) in self.lights.iter().take(MAX_POINT_LIGHTS).enumerate() {
+                lights[i] = [light.pos.x, light.pos.y, light.pos.z, light.radius];
+                let color = light.color.map(|c| c * light.intensity);
+                lights[MAX_POINT_LIGHTS + i] = [color[0], color[1], color[2], 0.0];
+            }
+
+            // the lights are binned twice, first to count them and then to place them, so
+            // the lists of every cluster are packed right away without a vec per cluster
+            let grid = arena.alloc::<u32>(self.bounds.len() * 2);
+            self.bin_lights(view, |cluster, _| grid[cluster * 2 + 1] += 1);
+            let mut total = 0;
+            for cluster in grid.chunks_exact_mut(2) {
+                cluster[0] = total;
+                total += cluster[1];
+                cluster[1] = 0;
+            }
+            // an empty buffer cant be read from
+            let indices = arena.alloc::<u32>((total as usize).max(1));
+            self.bin_lights(view, |cluster, light| {
+                let [first, count] = [grid[cluster * 2], grid[cluster * 2 + 1]];
+                indices[(first + count) as usize] = light;
+                grid[cluster * 2 + 1] += 1;
+            });
+
+            unsafe {
+                glBindBuffer(GL_UNIFORM_BUFFER, self.ubo.0);
+                let data: &[u8] = bytemuck::cast_slice(lights);
+                glBufferData(
+                    GL_UNIFORM_BUFFER,
+                    data.len() as isize,
+                    data.as_ptr().cast(),
+                    GL_STREAM_DRAW,
+                );
+                glBindBuffer(GL_UNIFORM_BUFFER, 0);
+            }
+
+            for (buffer, data) in [(&self.grid_buffer, &*grid), (&self.index_buffer, &*indices)] {
+                let data: &[u8] = bytemuck::cast_slice(data);
+                unsafe {
+                    glBindBuffer(GL_TEXTURE_BUFFER, buffer.0);
+                    glBufferData(
+                        GL_TEXTURE_BUFFER,
+                        data.len() as isize,
+                        data.as_ptr().cast(),
+                        GL_STREAM_DRAW,
+                    );
+                }
+            }
+            unsafe { glBindBuffer(GL_TEXTURE_BUFFER, 0) }
+        });
+    }
+
+    /// Calls hit with the index of every cluster and every light that reaches it
+    fn bin_lights(&self, view: &Mat4, mut hit: impl FnMut(usize, u32)) {
+        let [count_x, count_y, _] = self.count;
+        for (i, light) in self.lights.iter().take(MAX_POINT_LIGHTS).enumerate() {
+            // a light without a radius reaches nothing, a negative one would make the
+            // slices go backwards
+            if light.radius.is_nan() || light.radius <= 0.0 {
+                continue;
+            }
+            let center = (view * vec4(light.pos.x, light.pos.y, light.pos.z, 1.0)).xyz();
+            let depth = -center.z;
+            if depth + light.radius < self.near || depth - light.radius > self.far {
+                continue;
+            }
+            // only the slices the light reaches are checked
+            let slice_size = (count_x * count_y) as usize;
+            let first = self.slice(depth - light.radius) as usize * slice_size;
+            let last = (self.slice(depth + light.radius) as usize + 1) * slice_size;
+            for (cluster, (min, max)) in self.bounds[first..last].iter().enumerate() {
+                let closest = center.sup(min).inf(max);
+                if (closest - center).norm_squared() <= light.radius * light.radius {
+                    hit(first + cluster, i as u32);
+                }
+            }
+        }
+    }
+
+    /// Gives the lights to a program that uses [CLUSTERED_GLSL]
+    ///
+    /// The lights are bound to the uniform buffer binding point binding, the cluster grid
+    /// to the texture unit unit and the light lists to unit + 1, screen_size is the size of
+    /// the viewport(in pixels)
+    pub fn bind(&self, program: &ShaderProgram, binding: u32, unit: u32, screen_size: [f32; 2]) {
+        program.use_program();
+        let (grid_unit, index_unit) = (unit, unit + 1);
+        unsafe {
+            let block = with_cstr("PointLights", |name| {
+                glGetUniformBlockIndex(program.0, name.as_ptr().cast())
+            });
+            if block != GL_INVALID_INDEX {
+                glUniformBlockBinding(program.0, block, binding);
+            }
+            glBindBufferBase(GL_UNIFORM_BUFFER, binding, self.ubo.0);
+
+            glActiveTexture(GL_TEXTURE0 + grid_unit);
+            glBindTexture(GL_TEXTURE_BUFFER, self.grid_texture.id);
+            glActiveTexture(GL_TEXTURE0 + index_unit);
+            glBindTexture(GL_TEXTURE_BUFFER, self.index_texture.id);
+        }
+
+        let uniform = |name| Uniform::new(program, name);
+        uniform("cluster_grid").set_uniform_i(&[grid_unit as i32]);
+        uniform("cluster_lights").set_uniform_i(&[index_unit as i32]);
+        uniform("cluster_count").set_uniform_ui(&self.count);
+        uniform("screen_size").set_uniform_f(&screen_size);
+        uniform("cluster_near").set_uniform_f(&[self.near]);
+        uniform("cluster_far").set_uniform_f(&[self.far]);
+    }
+
+    /// Deletes the buffers and textures now instead of when it is dropped
+    pub fn delete(self) {}
+
+    /// Gets the depth the slice starts at, slices get longer further away
+    fn slice_depth(&self, slice: u32) -> f32 {
+        self.near * (self.far / self.near).powf(slice as f32 / self.count[2] as f32)
+    }
+
+    /// Gets the slice depth is in
+    fn slice(&self, depth: f32) -> u32 {
+        let depth = depth.clamp(self.near, self.far);
+        let slice = (depth / self.near).ln() / (self.far / self.near).ln() * self.count[2] as f32;
+        (slice as u32).min(self.count[2] - 1)
+    }
+}
@@ -0,0 +1,380 @@
+use super::{fullscreen::FullscreenPass, recovery::ContextId, texture::Texture, *};
+use crate::assets::{Asset, AssetError};
+
+const COLOR_GRADING_FRAG: &str = "#version 330 core
+in vec2 uv;
+
+uniform sampler2D input_texture;
+uniform sampler3D lut_a;
+uniform sampler3D lut_b;
+uniform float lut_a_size;
+uniform float lut_b_size;
+uniform vec3 lut_a_min;
+uniform vec3 lut_a_max;
+uniform vec3 lut_b_min;
+uniform vec3 lut_b_max;
+uniform float blend;
+uniform float intensity;
+
+out vec4 color;
+
+vec3 grade(sampler3D lut, float size, vec3 domain_min, vec3 domain_max, vec3 rgb) {
+    vec3 coord = clamp((rgb - domain_min) / (domain_max - domain_min), 0.0, 1.0);
+    // read between the centers of the first and last texels
+    return texture(lut, coord * (size - 1.0) / size + 0.5 / size).rgb;
+}
+
+void main() {
+    vec4 scene = texture(input_texture, uv);
+    vec3 a = grade(lut_a, lut_a_size, lut_a_min, lut_a_max, scene.rgb);
+    vec3 b = grade(lut_b, lut_b_size, lut_b_min, lut_b_max, scene.rgb);
+    color = vec4(mix(scene.rgb, mix(a, b, blend), intensity), scene.a);
+}";
+
+/// A 3d lookup table that maps every color to a graded color
+///
+/// It can be read from `.cube` files or from strip images, where the blue slices are next
+/// to each other from left to right, red goes right in every slice and green goes up
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    /// How many colors the table has on every axis
+    pub size: usize,
+    /// The colors, red changes fastest and blue slowest
+    pub data: Vec<[f32; 3]>,
+    /// The color the first entry of the table is for
+    pub domain_min: [f32; 3],
+    /// The color the last entry of the table is for
+    pub domain_max: [f32; 3],
+}
+
+impl Lut {
+    /// Creates a table that doesn't change any color
+    pub fn identity(size: usize) -> Self {
+        let size = size.max(2);
+        let step = 1.0 / (size - 1) as f32;
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f32 * step, g as f32 * step, b as f32 * step]);
+                }
+            }
+        }
+        Lut {
+            size,
+            data,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+        }
+    }
+
+    /// Reads a table out of the text of a `.cube` file
+    pub fn from_cube(text: &str) -> Result<Self, LighthouseError> {
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut data = Vec::new();
+
+        let parse = |words: &[&str]| -> Result<[f32; 3], LighthouseError> {
+            match words {
+                [r, g, b] => {
+                    let number = |word: &&str| {
+                        word.parse::<f32>().map_err(|_| {
+                            LighthouseError::Parse(format!(
+                                "{} in the .cube file is not a number",
+                                word
+                            ))
+                        })
+                    };
+                    Ok([number(r)?, number(g)?, number(b)?])
+                }
+                _ => Err(LighthouseError::Parse(format!(
+                    "Expected 3 numbers in the .cube file but got {}",
+                    words.join(" ")
+                ))),
+            }
+        };
+
+        for line in text.lines() {
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words.first() {
+                None => continue,
+                Some(word) if word.starts_with('#') => continue,
+                Some(&"TITLE") | Some(&"LUT_1D_INPUT_RANGE") => continue,
+                Some(&"LUT_1D_SIZE") => {
+                    return Err(LighthouseError::Unsupported(
+                        "1d .cube files are not supported, only 3d ones".to_string(),
+                    ))
+                }
+                Some(&"LUT_3D_SIZE") => {
+                    size = Some(
+                        words
+                            .get(1)
+                            .and_then(|size| size.parse::<usize>().ok())
+                            .ok_or_else(|| {
+                                LighthouseError::Parse(format!(
+                                    "Invalid LUT_3D_SIZE line: {}",
+                                    line
+                                ))
+                            })?,
+                    )
+                }
+                Some(&"DOMAIN_MIN") => domain_min = parse(&words[1..])?,
+                Some(&"DOMAIN_MAX") => domain_max = parse(&words[1..])?,
+                Some(&"LUT_3D_INPUT_RANGE") => {
+                    let range = words[1..]
+                        .iter()
+                        .map(|word| word.parse::<f32>().ok())
+                        .collect::<Option<Vec<_>>>();
+                    match range.as_deref() {
+                        Some([min, max]) => {
+                            domain_min = [*min; 3];
+                            domain_max = [*max; 3];
+                        }
+                        _ => {
+                            return Err(LighthouseError::Parse(format!(
+                                "Invalid LUT_3D_INPUT_RANGE line: {}",
+                                line
+                            )))
+                        }
+                    }
+                }
+                Some(_) => data.push(parse(&words)?),
+            }
+        }
+
+        let size = size.ok_or_else(|| {
+            LighthouseError::Parse("The .cube file has no LUT_3D_SIZE".to_string())
+        })?;
+        if size < 2 || data.len() != size * size * size {
+            return Err(LighthouseError::Parse(format!(
+                "A {0}x{0}x{0} .cube file needs {1} colors but has {2}",
+                size,
+                size * size * size,
+                data.len()
+            )));
+        }
+        Ok(Lut {
+            size,
+            data,
+            domain_min,
+            domain_max,
+        })
+    }
+
+    /// Reads a table out of a strip image that is size * size wide and size high
+    pub fn from_strip(image: &DynamicImage) -> Result<Self, LighthouseError> {
+        let image = image.to_rgb32f();
+        let (width, height) = image.dimensions();
+        let size = height as usize;
+        if size < 2 || width as usize != size * size {
+            return Err(LighthouseError::InvalidInput(format!(
+                "A lut strip must be height * height wide, but it is {}x{}",
+                width, height
+            )));
+        }
+
+        let mut data = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    // green goes up, images go from the top row down
+                    let pixel = image.get_pixel((b * size + r) as u32, (size - 1 - g) as u32);
+                    data.push(pixel.0);
+                }
+            }
+        }
+        Ok(Lut {
+            size,
+            data,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+        })
+    }
+
+    /// Turns the table into a strip image, e.g. to grade it in an image editor
+    pub fn to_strip(&self) -> DynamicImage {
+        let size = self.size;
+        let mut image = image::RgbImage::new((size * size) as u32, size as u32);
+        for (i, color) in self.data.iter().enumerate() {
+            let (r, g, b) = (i % size, i / size % size, i / (size * size));
+            image.put_pixel(
+                (b * size + r) as u32,
+                (size - 1 - g) as u32,
+                image::Rgb(color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)),
+            );
+        }
+        ImageRgb8(image)
+    }
+
+    /// Gets the graded color of rgb on the cpu
+    pub fn sample(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let coord: [f32; 3] = [0, 1, 2].map(|i| {
+            let range = self.domain_max[i] - self.domain_min[i];
+            ((rgb[i] - self.domain_min[i]) / range).clamp(0.0, 1.0) * last
+        });
+        let base = coord.map(|c| (c as usize).min(self.size - 2));
+        let t = [0, 1, 2].map(|i| coord[i] - base[i] as f32);
+        let at = |r: usize, g: usize, b: usize| {
+            self.data[((base[2] + b) * self.size + base[1] + g) * self.size + base[0] + r]
+        };
+        let mix = |x: [f32; 3], y: [f32; 3], t: f32| [0, 1, 2].map(|i| x[i] + (y[i] - x[i]) * t);
+
+        let mut out = [0.0; 3];
+        for (b, out_b) in [(0, 1.0 - t[2]), (1, t[2])] {
+            for (g, out_g) in [(0, 1.0 - t[1]), (1, t[1])] {
+                let color = mix(at(0, g, b), at(1, g, b), t[0]);
+                for i in 0..3 {
+                    out[i] += color[i] * out_b * out_g;
+                }
+            }
+        }
+        out
+    }
+
+    /// Uploads the table into a 3d texture
+    pub fn upload(&self) -> Result<LutTexture, LighthouseError> {
+        let mut id = 0;
+        unsafe { glGenTextures(1, &mut id) };
+        if id == 0 {
+            return Err(LighthouseError::BufferAlloc("the lut texture".to_string()));
+        }
+        unsafe {
+            glBindTexture(GL_TEXTURE_3D, id);
+            glTexImage3D(
+                GL_TEXTURE_3D,
+                0,
+                GL_RGB16F as i32,
+                self.size as i32,
+                self.size as i32,
+                self.size as i32,
+                0,
+                GL_RGB,
+                GL_FLOAT,
+                self.data.as_ptr().cast(),
+            );
+            glTexParameteri(GL_TEXTURE_3D, GL_TEXTURE_MIN_FILTER, GL_LINEAR as i32);
+            glTexParameteri(GL_TEXTURE_3D, GL_TEXTURE_MAG_FILTER, GL_LINEAR as i32);
+            for wrap in [GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T, GL_TEXTURE_WRAP_R] {
+                glTexParameteri(GL_TEXTURE_3D, wrap, GL_CLAMP_TO_EDGE as i32);
+            }
+            glBindTexture(GL_TEXTURE_3D, 0);
+        }
+        Ok(LutTexture {
+            id,
+            size: self.size,
+            domain_min: self.domain_min,
+            domain_max: self.domain_max,
+            context: ContextId::current(),
+        })
+    }
+}
+
+impl Asset for Lut {
+    /// Reads `.cube` files and strip images
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        match image::load_from_memory(&bytes) {
+            Ok(image) => Lut::from_strip(&image),
+            Err(_) => {
+                let text =
+                    String::from_utf8(bytes).map_err(|e| AssetError::Decode(e.to_string()))?;
+                Lut::from_cube(&text)
+            }
+        }
+        .map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+/// A [Lut] on the gpu, the texture is deleted when it is dropped, see [ContextId]
+#[derive(Debug, PartialEq)]
+pub struct LutTexture {
+    /// The texture id
+    pub id: u32,
+    /// How many colors the table has on every axis
+    pub size: usize,
+    /// The color the first entry of the table is for
+    pub domain_min: [f32; 3],
+    /// The color the last entry of the table is for
+    pub domain_max: [f32; 3],
+    context: ContextId,
+}
+
+impl LutTexture {
+    /// Deletes the texture now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for LutTexture {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe { glDeleteTextures(1, &self.id) };
+        }
+    }
+}
+
+/// A post pass that grades the colors of the scene with one lut or a blend of two
+///
+/// Blending lets the look change smoothly, e.g. between areas of a level or with the time
+/// of day
+///
+/// # Example
+/// ```
+/// let day = assets.load::<Lut>("luts/day.cube")?.upload()?;
+/// let night = assets.load::<Lut>("luts/night.png")?.upload()?;
+/// let mut grading = ColorGrading::new()?;
+///
+/// 'main_loop: loop {
+///     grading.blend = if time_of_day.is_day() { 0.0 } else { 1.0 };
+///     grading.draw(&target.color, &day, Some(&night));
+/// }
+/// ```
+pub struct ColorGrading {
+    /// How far the blend is from the first lut(0) to the second one(1)
+    pub blend: f32,
+    /// How much of the grading is used, 0 draws the scene as is
+    pub intensity: f32,
+    pass: FullscreenPass,
+}
+
+impl ColorGrading {
+    /// Creates a new color grading pass
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(ColorGrading {
+            blend: 0.0,
+            intensity: 1.0,
+            pass: FullscreenPass::new(COLOR_GRADING_FRAG)?,
+        })
+    }
+
+    /// Draws scene graded by a, blended towards b, into the bound framebuffer
+    ///
+    /// The luts are bound to texture units 1 and 2
+    pub fn draw(&self, scene: &Texture, a: &LutTexture, b: Option<&LutTexture>) {
+        let b = b.unwrap_or(a);
+        let pass = &self.pass;
+        for (unit, name, lut) in [(1, "lut_a", a), (2, "lut_b", b)] {
+            unsafe {
+                glActiveTexture(GL_TEXTURE0 + unit);
+                glBindTexture(GL_TEXTURE_3D, lut.id);
+            }
+            pass.uniform(name).set_uniform_i(&[unit as i32]);
+            pass.uniform(&format!("{}_size", name))
+                .set_uniform_f(&[lut.size as f32]);
+            pass.uniform(&format!("{}_min", name))
+                .set_uniform_f(&lut.domain_min);
+            pass.uniform(&format!("{}_max", name))
+                .set_uniform_f(&lut.domain_max);
+        }
+        pass.uniform("blend")
+            .set_uniform_f(&[self.blend.clamp(0.0, 1.0)]);
+        pass.uniform("intensity")
+            .set_uniform_f(&[self.intensity.clamp(0.0, 1.0)]);
+        pass.draw(scene);
+    }
+
+    /// Deletes the pass
+    pub fn delete(self) {
+        self.pass.delete();
+    }
+}
@@ -0,0 +1,634 @@
+use image::RgbaImage;
+
+use super::{
+    texture::{Texture, TextureError, TextureFilter, TextureSettings, TextureTarget, TextureUnit},
+    *,
+};
+use crate::assets::{Asset, AssetError};
+
+// the compressed formats aren't in opengl 3.3, they come from extensions
+const GL_COMPRESSED_RGB_S3TC_DXT1: u32 = 0x83F0;
+const GL_COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83F1;
+const GL_COMPRESSED_RGBA_S3TC_DXT3: u32 = 0x83F2;
+const GL_COMPRESSED_RGBA_S3TC_DXT5: u32 = 0x83F3;
+const GL_ETC1_RGB8: u32 = 0x8D64;
+const GL_COMPRESSED_RGB8_ETC2: u32 = 0x9274;
+const GL_COMPRESSED_RGBA8_ETC2_EAC: u32 = 0x9278;
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const KTX_MAGIC: &[u8] = b"\xABKTX 11\xBB\r\n\x1A\n";
+
+/// The ETC1 modifiers, the first 2 are added and the last 2 taken away
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+/// The distances of the T and H modes of ETC2
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/// The alpha modifiers of EAC
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// A block compressed format, every format stores blocks of 4x4 pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// DXT1, rgb with 1 bit alpha in 8 bytes a block
+    Bc1,
+    /// DXT3, rgb with 4 bit alpha in 16 bytes a block
+    Bc2,
+    /// DXT5, rgb with smooth alpha in 16 bytes a block
+    Bc3,
+    /// ETC2 rgb in 8 bytes a block, ETC1 textures are read as it too
+    Etc2Rgb,
+    /// ETC2 rgb with EAC alpha in 16 bytes a block
+    Etc2Rgba,
+}
+
+impl CompressedFormat {
+    /// Gets the opengl internal format
+    pub fn gl(self) -> u32 {
+        match self {
+            CompressedFormat::Bc1 => GL_COMPRESSED_RGBA_S3TC_DXT1,
+            CompressedFormat::Bc2 => GL_COMPRESSED_RGBA_S3TC_DXT3,
+            CompressedFormat::Bc3 => GL_COMPRESSED_RGBA_S3TC_DXT5,
+            CompressedFormat::Etc2Rgb => GL_COMPRESSED_RGB8_ETC2,
+            CompressedFormat::Etc2Rgba => GL_COMPRESSED_RGBA8_ETC2_EAC,
+        }
+    }
+
+    /// Gets the format of an opengl internal format, None if it isn't one of them
+    pub fn from_gl(format: u32) -> Option<Self> {
+        match format {
+            GL_COMPRESSED_RGB_S3TC_DXT1 | GL_COMPRESSED_RGBA_S3TC_DXT1 => Some(Self::Bc1),
+            GL_COMPRESSED_RGBA_S3TC_DXT3 => Some(Self::Bc2),
+            GL_COMPRESSED_RGBA_S3TC_DXT5 => Some(Self::Bc3),
+            GL_ETC1_RGB8 | GL_COMPRESSED_RGB8_ETC2 => Some(Self::Etc2Rgb),
+            GL_COMPRESSED_RGBA8_ETC2_EAC => Some(Self::Etc2Rgba),
+            _ => None,
+        }
+    }
+
+    /// Gets how many bytes a block of 4x4 pixels takes
+    pub fn block_size(self) -> usize {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Etc2Rgb => 8,
+            CompressedFormat::Bc2 | CompressedFormat::Bc3 | CompressedFormat::Etc2Rgba => 16,
+        }
+    }
+
+    /// Gets how many bytes an image of width by height takes(in pixels), None if it is too
+    /// big to count
+    pub fn data_size(self, width: u32, height: u32) -> Option<usize> {
+        (width.div_ceil(4) as usize)
+            .checked_mul(height.div_ceil(4) as usize)?
+            .checked_mul(self.block_size())
+    }
+
+    /// Checks if the gpu can read the format without it being decoded first
+    pub fn is_supported(self) -> bool {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc2 | CompressedFormat::Bc3 => {
+                has_extension("GL_EXT_texture_compression_s3tc")
+            }
+            CompressedFormat::Etc2Rgb | CompressedFormat::Etc2Rgba => {
+                has_extension("GL_ARB_ES3_compatibility")
+            }
+        }
+    }
+
+    /// Decodes a block into its 16 pixels, row by row
+    fn decode_block(self, block: &[u8]) -> [[u8; 4]; 16] {
+        match self {
+            CompressedFormat::Bc1 => decode_bc1(block, true),
+            CompressedFormat::Bc2 => {
+                let mut pixels = decode_bc1(&block[8..], false);
+                let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+                for (i, pixel) in pixels.iter_mut().enumerate() {
+                    pixel[3] = ((alpha >> (i * 4)) & 0xF) as u8 * 17;
+                }
+                pixels
+            }
+            CompressedFormat::Bc3 => {
+                let mut pixels = decode_bc1(&block[8..], false);
+                for (pixel, alpha) in pixels.iter_mut().zip(decode_bc3_alpha(&block[..8])) {
+                    pixel[3] = alpha;
+                }
+                pixels
+            }
+            CompressedFormat::Etc2Rgb => decode_etc2(block),
+            CompressedFormat::Etc2Rgba => {
+                let mut pixels = decode_etc2(&block[8..]);
+                for (pixel, alpha) in pixels.iter_mut().zip(decode_eac(&block[..8])) {
+                    pixel[3] = alpha;
+                }
+                pixels
+            }
+        }
+    }
+}
+
+/// A block compressed image read from a DDS or KTX file, with its mipmaps
+///
+/// The rows are stored top row first and aren't flipped like [Texture::builder] flips
+/// images, so the top of the image is at a v of 0. Most tools that make DDS and KTX files
+/// can flip the image when it is saved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedImage {
+    /// The format of the blocks
+    pub format: CompressedFormat,
+    /// The width of the biggest level(in pixels)
+    pub width: u32,
+    /// The height of the biggest level(in pixels)
+    pub height: u32,
+    /// The blocks of every mipmap level, the biggest first
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// Reads a DDS or KTX 1 file, which one it is is worked out from the start of the file
+    pub fn parse(bytes: &[u8]) -> Result<Self, TextureError> {
+        if bytes.starts_with(DDS_MAGIC) {
+            Self::parse_dds(bytes)
+        } else if bytes.starts_with(KTX_MAGIC) {
+            Self::parse_ktx(bytes)
+        } else {
+            Err(invalid("The file isn't a DDS or KTX file"))
+        }
+    }
+
+    /// Gets the size of a mipmap level(in pixels)
+    pub fn level_size(&self, level: usize) -> (u32, u32) {
+        let shift = |size: u32| {
+            u32::try_from(level)
+                .ok()
+                .and_then(|level| size.checked_shr(level))
+                .unwrap_or(0)
+                .max(1)
+        };
+        (shift(self.width), shift(self.height))
+    }
+
+    /// Decodes a mipmap level into rgba pixels, for gpus that can't read the format
+    pub fn decode(&self, level: usize) -> RgbaImage {
+        let (width, height) = self.level_size(level);
+        let mut img = RgbaImage::new(width, height);
+        let blocks = self.levels[level].chunks_exact(self.format.block_size());
+        let blocks_wide = width.div_ceil(4);
+        for (i, block) in blocks.enumerate() {
+            let (block_x, block_y) = (i as u32 % blocks_wide * 4, i as u32 / blocks_wide * 4);
+            for (j, pixel) in self.format.decode_block(block).into_iter().enumerate() {
+                let (x, y) = (block_x + j as u32 % 4, block_y + j as u32 / 4);
+                // the blocks at the edges can go past the image
+                if x < width && y < height {
+                    img.put_pixel(x, y, image::Rgba(pixel));
+                }
+            }
+        }
+        img
+    }
+
+    fn parse_dds(bytes: &[u8]) -> Result<Self, TextureError> {
+        if bytes.len() < 128 {
+            return Err(invalid("The DDS header is cut off"));
+        }
+        let flags = read_u32(bytes, 8, false);
+        let height = read_u32(bytes, 12, false);
+        let width = read_u32(bytes, 16, false);
+        // DDSD_MIPMAPCOUNT
+        let level_count = if flags & 0x20000 != 0 {
+            read_u32(bytes, 28, false).max(1)
+        } else {
+            1
+        };
+        check_level_count(width, height, level_count)?;
+
+        let (format, data_start) = match &bytes[84..88] {
+            b"DXT1" => (CompressedFormat::Bc1, 128),
+            b"DXT3" => (CompressedFormat::Bc2, 128),
+            b"DXT5" => (CompressedFormat::Bc3, 128),
+            b"DX10" => {
+                if bytes.len() < 148 {
+                    return Err(invalid("The DX10 header is cut off"));
+                }
+                let format = match read_u32(bytes, 128, false) {
+                    70..=72 => CompressedFormat::Bc1,
+                    73..=75 => CompressedFormat::Bc2,
+                    76..=78 => CompressedFormat::Bc3,
+                    dxgi => return Err(invalid(&format!("DXGI format {} isn't supported", dxgi))),
+                };
+                (format, 148)
+            }
+            four_cc => {
+                return Err(invalid(&format!(
+                    "DDS files with {:?} aren't supported, only DXT1, DXT3 and DXT5",
+                    String::from_utf8_lossy(four_cc)
+                )))
+            }
+        };
+
+        let mut image = CompressedImage {
+            format,
+            width,
+            height,
+            levels: Vec::new(),
+        };
+        let mut offset: usize = data_start;
+        for level in 0..level_count as usize {
+            let (width, height) = image.level_size(level);
+            let data = format
+                .data_size(width, height)
+                .and_then(|size| bytes.get(offset..offset.checked_add(size)?))
+                .ok_or_else(|| invalid(&format!("Mipmap level {} is cut off", level)))?;
+            image.levels.push(data.to_vec());
+            offset += data.len();
+        }
+        image.check()
+    }
+
+    fn parse_ktx(bytes: &[u8]) -> Result<Self, TextureError> {
+        if bytes.len() < 64 {
+            return Err(invalid("The KTX header is cut off"));
+        }
+        // the file says which way round its numbers are
+        let swap = read_u32(bytes, 12, false) != 0x04030201;
+        let internal_format = read_u32(bytes, 28, swap);
+        let width = read_u32(bytes, 36, swap);
+        let height = read_u32(bytes, 40, swap);
+        if read_u32(bytes, 44, swap) > 0
+            || read_u32(bytes, 48, swap) > 0
+            || read_u32(bytes, 52, swap) > 1
+        {
+            return Err(invalid(
+                "Only 2d KTX files are supported, not 3d, array or cubemap ones",
+            ));
+        }
+        let level_count = read_u32(bytes, 56, swap).max(1);
+        check_level_count(width, height, level_count)?;
+        let key_value_size = read_u32(bytes, 60, swap) as usize;
+
+        let format = CompressedFormat::from_gl(internal_format).ok_or_else(|| {
+            invalid(&format!(
+                "KTX files with the format {:#X} aren't supported",
+                internal_format
+            ))
+        })?;
+
+        let mut image = CompressedImage {
+            format,
+            width,
+            height,
+            levels: Vec::new(),
+        };
+        let mut offset = 64 + key_value_size;
+        for level in 0..level_count as usize {
+            let size = bytes
+                .get(offset..offset + 4)
+                .map(|_| read_u32(bytes, offset, swap) as usize)
+                .ok_or_else(|| invalid(&format!("Mipmap level {} is cut off", level)))?;
+            let data = (offset + 4)
+                .checked_add(size)
+                .and_then(|end| bytes.get(offset + 4..end))
+                .ok_or_else(|| invalid(&format!("Mipmap level {} is cut off", level)))?;
+            image.levels.push(data.to_vec());
+            // every level is padded to 4 bytes
+            offset += 4 + size.next_multiple_of(4);
+        }
+        image.check()
+    }
+
+    /// Checks that every level has as many bytes as its size needs
+    fn check(self) -> Result<Self, TextureError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(TextureError::EmptyImage);
+        }
+        for (level, data) in self.levels.iter().enumerate() {
+            let (width, height) = self.level_size(level);
+            let size = self
+                .format
+                .data_size(width, height)
+                .ok_or_else(|| invalid(&format!("Mipmap level {} is too big", level)))?;
+            if data.len() != size {
+                return Err(invalid(&format!(
+                    "Mipmap level {} has {} bytes but a {}x{} level needs {}",
+                    level,
+                    data.len(),
+                    width,
+                    height,
+                    size
+                )));
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// Checks that an image of width by height can have level_count mipmap levels, the last
+/// level is 1 by 1
+fn check_level_count(width: u32, height: u32, level_count: u32) -> Result<(), TextureError> {
+    let max_levels = 32 - width.max(height).max(1).leading_zeros();
+    if level_count > max_levels {
+        return Err(invalid(&format!(
+            "A {}x{} image can't have {} mipmap levels, only {}",
+            width, height, level_count, max_levels
+        )));
+    }
+    Ok(())
+}
+
+impl Asset for CompressedImage {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        CompressedImage::parse(&bytes).map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+impl Texture {
+    /// Creates a [Texture] from a DDS or KTX file, see [CompressedImage]
+    ///
+    /// The blocks are uploaded as they are when the gpu supports the format, so the texture
+    /// takes 4 to 8 times less memory than an rgba one. When it doesn't every level is
+    /// decoded to rgba first, so the texture still works everywhere
+    ///
+    /// # Example
+    /// ```
+    /// let texture = Texture::from_compressed(&fs::read("data/tiles.dds")?)?;
+    /// ```
+    pub fn from_compressed(bytes: &[u8]) -> Result<Texture, TextureError> {
+        Texture::from_compressed_image(&CompressedImage::parse(bytes)?)
+    }
+
+    /// Creates a [Texture] from an already read compressed image, see
+    /// [Texture::from_compressed]
+    ///
+    /// The texture is filtered linearly, with its mipmaps if it has more than one level, and
+    /// is left bound to unit 0
+    pub fn from_compressed_image(image: &CompressedImage) -> Result<Texture, TextureError> {
+        Texture::set_tex_unit(TextureUnit::Texture0);
+        let mut texture = Texture::new();
+        texture.bind(TextureTarget::Texture2D);
+        let min_filter = if image.levels.len() > 1 {
+            TextureFilter::LinearMipmapLinear
+        } else {
+            TextureFilter::Linear
+        };
+        TextureSettings::default()
+            .filter(min_filter, TextureFilter::Linear)
+            .levels(0, image.levels.len() as i32 - 1)
+            .apply(&mut texture);
+
+        let supported = image.format.is_supported();
+        for (level, data) in image.levels.iter().enumerate() {
+            let (width, height) = image.level_size(level);
+            unsafe {
+                if supported {
+                    glCompressedTexImage2D(
+                        GL_TEXTURE_2D,
+                        level as i32,
+                        image.format.gl(),
+                        width as i32,
+                        height as i32,
+                        0,
+                        data.len() as i32,
+                        data.as_ptr().cast(),
+                    );
+                } else {
+                    let pixels = image.decode(level);
+                    glTexImage2D(
+                        GL_TEXTURE_2D,
+                        level as i32,
+                        GL_RGBA8 as i32,
+                        width as i32,
+                        height as i32,
+                        0,
+                        GL_RGBA,
+                        GL_UNSIGNED_BYTE,
+                        pixels.as_ptr().cast(),
+                    );
+                }
+            }
+        }
+
+        Ok(texture)
+    }
+}
+
+fn invalid(message: &str) -> TextureError {
+    TextureError::InvalidData(message.to_string())
+}
+
+/// Reads the u32 at offset, swap is true for big endian
+fn read_u32(bytes: &[u8], offset: usize, swap: bool) -> u32 {
+    let value = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    if swap {
+        value.swap_bytes()
+    } else {
+        value
+    }
+}
+
+/// Turns a 565 color into rgb
+fn rgb565(color: u16) -> [i32; 3] {
+    let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+    [
+        (r << 3 | r >> 2) as i32,
+        (g << 2 | g >> 4) as i32,
+        (b << 3 | b >> 2) as i32,
+    ]
+}
+
+/// Decodes the color of a BC block, BC2 and BC3 always use 4 colors
+fn decode_bc1(block: &[u8], can_be_transparent: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+    let mix = |wa: i32, wb: i32, d: i32| {
+        let [r, g, b] = [0, 1, 2].map(|i| ((a[i] * wa + b[i] * wb) / d) as u8);
+        [r, g, b, 255]
+    };
+    let colors = if c0 > c1 || !can_be_transparent {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2), [0, 0, 0, 0]]
+    };
+
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+    std::array::from_fn(|i| colors[(indices >> (i * 2)) as usize & 3])
+}
+
+/// Decodes the alpha of a BC3 block
+fn decode_bc3_alpha(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let alphas: [u8; 8] = if a0 > a1 {
+        std::array::from_fn(|i| match i {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            i => ((a0 * (8 - i as u32) + a1 * (i as u32 - 1)) / 7) as u8,
+        })
+    } else {
+        std::array::from_fn(|i| match i {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            6 => 0,
+            7 => 255,
+            i => ((a0 * (6 - i as u32) + a1 * (i as u32 - 1)) / 5) as u8,
+        })
+    };
+
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+    std::array::from_fn(|i| alphas[(indices >> (i * 3)) as usize & 7])
+}
+
+/// Extends a color of bits bits to 8 bits
+fn extend(value: i32, bits: u32) -> i32 {
+    (value << (8 - bits)) | (value >> (2 * bits - 8))
+}
+
+/// Decodes an ETC2 rgb block, ETC1 blocks are ETC2 blocks too
+fn decode_etc2(block: &[u8]) -> [[u8; 4]; 16] {
+    let clamp = |value: i32| value.clamp(0, 255);
+    let indices = u32::from_be_bytes(block[4..8].try_into().unwrap());
+    // the pixels are stored column by column
+    let index = |x: usize, y: usize| {
+        let bit = x * 4 + y;
+        (((indices >> (bit + 16)) & 1) << 1 | ((indices >> bit) & 1)) as usize
+    };
+    let pixels = |color: &dyn Fn(usize, usize) -> [i32; 3]| {
+        std::array::from_fn(|i| {
+            let [r, g, b] = color(i % 4, i / 4).map(clamp);
+            [r as u8, g as u8, b as u8, 255]
+        })
+    };
+
+    let differential = block[3] & 2 != 0;
+    let delta = |byte: u8| (((byte & 7) as i8) << 5 >> 5) as i32;
+    let base = |byte: u8| (byte >> 3) as i32;
+    let overflows = |byte: u8| !(0..32).contains(&(base(byte) + delta(byte)));
+
+    if differential && overflows(block[0]) {
+        // T mode
+        let c1 = [
+            ((block[0] >> 1) & 12 | block[0] & 3) as i32,
+            (block[1] >> 4) as i32,
+            (block[1] & 15) as i32,
+        ]
+        .map(|c| extend(c, 4));
+        let c2 = [
+            (block[2] >> 4) as i32,
+            (block[2] & 15) as i32,
+            (block[3] >> 4) as i32,
+        ]
+        .map(|c| extend(c, 4));
+        let d = ETC_DISTANCES[((block[3] >> 1) & 6 | block[3] & 1) as usize];
+        let paint = [c1, c2.map(|c| c + d), c2, c2.map(|c| c - d)];
+        pixels(&|x, y| paint[index(x, y)])
+    } else if differential && overflows(block[1]) {
+        // H mode
+        let c1 = [
+            ((block[0] >> 3) & 15) as i32,
+            ((block[0] & 7) << 1 | (block[1] >> 4) & 1) as i32,
+            (block[1] & 8 | (block[1] & 3) << 1 | block[2] >> 7) as i32,
+        ];
+        let c2 = [
+            ((block[2] >> 3) & 15) as i32,
+            ((block[2] & 7) << 1 | block[3] >> 7) as i32,
+            ((block[3] >> 3) & 15) as i32,
+        ];
+        let value = |c: [i32; 3]| c[0] << 8 | c[1] << 4 | c[2];
+        let order = (value(c1) >= value(c2)) as u8;
+        let d = ETC_DISTANCES[(block[3] & 4 | (block[3] & 1) << 1 | order) as usize];
+        let (c1, c2) = (c1.map(|c| extend(c, 4)), c2.map(|c| extend(c, 4)));
+        let paint = [
+            c1.map(|c| c + d),
+            c1.map(|c| c - d),
+            c2.map(|c| c + d),
+            c2.map(|c| c - d),
+        ];
+        pixels(&|x, y| paint[index(x, y)])
+    } else if differential && overflows(block[2]) {
+        // planar mode
+        let b = |i: usize| block[i] as i32;
+        let o = [
+            extend((b(0) >> 1) & 63, 6),
+            extend((b(0) & 1) << 6 | (b(1) >> 1) & 63, 7),
+            extend((b(1) & 1) << 5 | b(2) & 24 | (b(2) & 3) << 1 | b(3) >> 7, 6),
+        ];
+        let h = [
+            extend(((b(3) >> 2) & 31) << 1 | b(3) & 1, 6),
+            extend((b(4) >> 1) & 127, 7),
+            extend((b(4) & 1) << 5 | (b(5) >> 3) & 31, 6),
+        ];
+        let v = [
+            extend((b(5) & 7) << 3 | (b(6) >> 5) & 7, 6),
+            extend((b(6) & 31) << 2 | (b(7) >> 6) & 3, 7),
+            extend(b(7) & 63, 6),
+        ];
+        pixels(&|x, y| {
+            std::array::from_fn(|i| {
+                (x as i32 * (h[i] - o[i]) + y as i32 * (v[i] - o[i]) + 4 * o[i] + 2) >> 2
+            })
+        })
+    } else {
+        // the block is 2 halves, each with its own color and modifiers
+        let colors = if differential {
+            let first = [0, 1, 2].map(|i| base(block[i]));
+            [
+                first.map(|c| extend(c, 5)),
+                [0, 1, 2].map(|i| extend(first[i] + delta(block[i]), 5)),
+            ]
+        } else {
+            [
+                [0, 1, 2].map(|i| extend((block[i] >> 4) as i32, 4)),
+                [0, 1, 2].map(|i| extend((block[i] & 15) as i32, 4)),
+            ]
+        };
+        let tables = [(block[3] >> 5) as usize, ((block[3] >> 2) & 7) as usize];
+        let flipped = block[3] & 1 != 0;
+        pixels(&|x, y| {
+            let half = if flipped { y / 2 } else { x / 2 };
+            let [small, big] = ETC_MODIFIERS[tables[half]];
+            let modifier = [small, big, -small, -big][index(x, y)];
+            colors[half].map(|c| c + modifier)
+        })
+    }
+}
+
+/// Decodes the EAC alpha of an ETC2 rgba block
+fn decode_eac(block: &[u8]) -> [u8; 16] {
+    let base = block[0] as i32;
+    let multiplier = (block[1] >> 4) as i32;
+    let table = EAC_MODIFIERS[(block[1] & 15) as usize];
+    let mut indices = [0; 8];
+    indices[2..].copy_from_slice(&block[2..8]);
+    let indices = u64::from_be_bytes(indices);
+    std::array::from_fn(|i| {
+        // the pixels are stored column by column, the first one in the highest bits
+        let bit = 45 - (i % 4 * 4 + i / 4) * 3;
+        let modifier = table[(indices >> bit) as usize & 7];
+        (base + modifier * multiplier).clamp(0, 255) as u8
+    })
+}
@@ -0,0 +1,437 @@
+use nalgebra_glm::{look_at, normalize, perspective, vec3, Mat4, Vec3};
+
+use super::{recovery::ContextId, shader::ShaderProgram, uniform::Uniform, *};
+
+/// Glsl that declares the uniforms of a [CookieLight] and
+/// `vec3 apply_cookie_light(vec3 albedo, vec3 world_pos, vec3 normal)`
+///
+/// Paste it into a fragment shader to light it with a [CookieLight], for more than one
+/// light draw once per light with additive blending, see [CookieLight::set_uniforms]
+pub const COOKIE_GLSL: &str = "uniform vec3 cookie_light_pos;
+uniform vec3 cookie_light_dir;
+uniform vec3 cookie_light_color;
+uniform float cookie_light_radius;
+uniform bool cookie_light_spot;
+uniform float spot_cos_inner;
+uniform float spot_cos_outer;
+uniform mat4 cookie_matrix;
+uniform bool use_cookie;
+uniform sampler2D cookie;
+uniform bool use_light_profile;
+uniform sampler2D light_profile;
+
+vec3 apply_cookie_light(vec3 albedo, vec3 world_pos, vec3 normal) {
+    vec3 to_light = cookie_light_pos - world_pos;
+    float dist = length(to_light);
+    vec3 l = to_light / max(dist, 0.0001);
+    float falloff = clamp(1.0 - pow(dist / cookie_light_radius, 4.0), 0.0, 1.0);
+    vec3 light = cookie_light_color * falloff * falloff / (dist * dist + 1.0)
+        * max(dot(normalize(normal), l), 0.0);
+
+    float cos_angle = dot(-l, cookie_light_dir);
+    if (cookie_light_spot) {
+        light *= smoothstep(spot_cos_outer, spot_cos_inner, cos_angle);
+    }
+    if (use_light_profile) {
+        float angle = acos(clamp(cos_angle, -1.0, 1.0)) / 3.14159265;
+        light *= texture(light_profile, vec2(angle, 0.5)).r;
+    }
+    if (use_cookie) {
+        vec2 uv;
+        if (cookie_light_spot) {
+            vec4 projected = cookie_matrix * vec4(world_pos, 1.0);
+            if (projected.w <= 0.0) {
+                return vec3(0.0);
+            }
+            uv = projected.xy / projected.w * 0.5 + 0.5;
+        } else {
+            // point light cookies are wrapped around the light like a map of the world
+            vec3 dir = normalize(mat3(cookie_matrix) * -l);
+            uv = vec2(atan(dir.x, -dir.z) / 6.28318531 + 0.5, 1.0 - acos(dir.y) / 3.14159265);
+        }
+        light *= texture(cookie, uv).rgb;
+    }
+    return albedo * light;
+}";
+
+/// How bright a light is at every angle away from the direction it points in, like the
+/// photometric profiles of real lamps
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightProfile {
+    /// The brightness from 0 to 1, evenly spaced from straight ahead(0 degrees) to straight
+    /// behind(180 degrees)
+    pub intensities: Vec<f32>,
+}
+
+impl LightProfile {
+    /// Creates a new profile out of evenly spaced intensities, they are scaled so the
+    /// brightest is 1
+    pub fn new(intensities: Vec<f32>) -> Result<Self, LighthouseError> {
+        if intensities.len() < 2 {
+            return Err(LighthouseError::InvalidInput(
+                "A light profile needs at least 2 intensities".to_string(),
+            ));
+        }
+        let max = intensities.iter().cloned().fold(0.0, f32::max);
+        if max <= 0.0 {
+            return Err(LighthouseError::InvalidInput(
+                "A light profile needs an intensity above 0".to_string(),
+            ));
+        }
+        Ok(LightProfile {
+            intensities: intensities.into_iter().map(|i| i.max(0.0) / max).collect(),
+        })
+    }
+
+    /// Creates a new profile of samples intensities out of f, which gets the angle(in
+    /// radians) away from the direction of the light
+    ///
+    /// # Example
+    /// ```
+    /// // a lamp that shines mostly forward with a dim ring at 40 degrees
+    /// let profile = LightProfile::from_fn(64, |angle| {
+    ///     angle.cos().max(0.0) + 0.3 * (-(angle - 0.7).powi(2) * 50.0).exp()
+    /// })?;
+    /// ```
+    pub fn from_fn(samples: usize, f: impl Fn(f32) -> f32) -> Result<Self, LighthouseError> {
+        LightProfile::new(
+            (0..samples)
+                .map(|i| f(i as f32 / (samples - 1).max(1) as f32 * std::f32::consts::PI))
+                .collect(),
+        )
+    }
+
+    /// Reads a profile out of an IES(LM-63) photometric file
+    ///
+    /// The candela values are averaged over the horizontal angles, so only the falloff
+    /// away from the direction of the light is kept. Angles the file doesn't have are dark
+    pub fn from_ies(text: &str) -> Result<Self, LighthouseError> {
+        let mut lines = text.lines();
+        let tilt = lines
+            .by_ref()
+            .find(|line| line.trim_start().starts_with("TILT="))
+            .ok_or_else(|| LighthouseError::Parse("The IES file has no TILT= line".to_string()))?;
+
+        let mut numbers = lines
+            .flat_map(|line| {
+                line.split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|word| !word.is_empty())
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+        let next = |numbers: &mut std::vec::IntoIter<&str>| -> Result<f32, LighthouseError> {
+            let word = numbers
+                .next()
+                .ok_or_else(|| LighthouseError::Parse("The IES file ends too early".to_string()))?;
+            word.parse().map_err(|_| {
+                LighthouseError::Parse(format!("{} in the IES file is not a number", word))
+            })
+        };
+
+        if tilt.trim() == "TILT=INCLUDE" {
+            // lamp to luminaire geometry, then the tilt angles and their factors
+            next(&mut numbers)?;
+            let count = next(&mut numbers)? as usize;
+            for _ in 0..count.saturating_mul(2) {
+                next(&mut numbers)?;
+            }
+        }
+
+        // lamps, lumens per lamp, multiplier
+        for _ in 0..3 {
+            next(&mut numbers)?;
+        }
+        let vertical = next(&mut numbers)? as usize;
+        let horizontal = next(&mut numbers)? as usize;
+        // photometric type, units, width, length, height, ballast factor, future use, watts
+        for _ in 0..8 {
+            next(&mut numbers)?;
+        }
+
+        // the angles and then a candela value for every pair of them, checked before anything
+        // is allocated for them
+        let needed = vertical
+            .checked_mul(horizontal)
+            .and_then(|values| values.checked_add(vertical))
+            .and_then(|values| values.checked_add(horizontal));
+        if needed.is_none_or(|needed| needed > numbers.len()) {
+            return Err(LighthouseError::Parse(format!(
+                "The IES file has {} by {} angles but not enough numbers for them",
+                vertical, horizontal
+            )));
+        }
+
+        let vertical_angles = (0..vertical)
+            .map(|_| next(&mut numbers))
+            .collect::<Result<Vec<_>, _>>()?;
+        for _ in 0..horizontal {
+            next(&mut numbers)?;
+        }
+        let mut candela = vec![0.0; vertical];
+        for _ in 0..horizontal {
+            for value in candela.iter_mut() {
+                *value += next(&mut numbers)? / horizontal as f32;
+            }
+        }
+
+        LightProfile::from_fn(64, |angle| {
+            let degrees = angle.to_degrees();
+            match vertical_angles.iter().position(|a| *a >= degrees) {
+                Some(0) => candela[0],
+                Some(i) => {
+                    let (a, b) = (vertical_angles[i - 1], vertical_angles[i]);
+                    let t = (degrees - a) / (b - a).max(f32::EPSILON);
+                    candela[i - 1] + (candela[i] - candela[i - 1]) * t
+                }
+                None => 0.0,
+            }
+        })
+    }
+
+    /// Gets the brightness at angle(in radians) away from the direction of the light
+    pub fn sample(&self, angle: f32) -> f32 {
+        let last = self.intensities.len() - 1;
+        let x = (angle.abs() / std::f32::consts::PI).min(1.0) * last as f32;
+        let i = (x as usize).min(last - 1);
+        let t = x - i as f32;
+        self.intensities[i] + (self.intensities[i + 1] - self.intensities[i]) * t
+    }
+
+    /// Uploads the profile into a texture, see [CookieLight::profile]
+    pub fn upload(&self) -> Result<ProfileTexture, LighthouseError> {
+        let mut id = 0;
+        unsafe { glGenTextures(1, &mut id) };
+        if id == 0 {
+            return Err(LighthouseError::BufferAlloc(
+                "the light profile texture".to_string(),
+            ));
+        }
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, id);
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_R32F as i32,
+                self.intensities.len() as i32,
+                1,
+                0,
+                GL_RED,
+                GL_FLOAT,
+                self.intensities.as_ptr().cast(),
+            );
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
+            glBindTexture(GL_TEXTURE_2D, 0);
+        }
+        Ok(ProfileTexture {
+            id,
+            context: ContextId::current(),
+        })
+    }
+}
+
+/// A [LightProfile] on the gpu, the texture is deleted when it is dropped, see [ContextId]
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProfileTexture {
+    /// The texture id
+    pub id: u32,
+    context: ContextId,
+}
+
+impl ProfileTexture {
+    /// Deletes the texture now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for ProfileTexture {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe { glDeleteTextures(1, &self.id) };
+        }
+    }
+}
+
+/// The shape of the light of a [CookieLight]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightShape {
+    /// A cone of light, fully lit inside inner_angle and fading out until outer_angle(in
+    /// radians, from the middle to the edge)
+    Spot {
+        /// Where the light starts fading out
+        inner_angle: f32,
+        /// Where the light is gone
+        outer_angle: f32,
+    },
+    /// Light in every direction
+    Point,
+}
+
+/// A spot or point light that projects a texture(a cookie) and can follow a
+/// [LightProfile], e.g. flashlights, stained glass windows and stage lights
+///
+/// Spot lights project the cookie like a slide projector, it should have a black border
+/// or be clamped to a black border color. Point lights wrap the cookie around themselves,
+/// the top row of the image is above the light and the middle is in front of it
+///
+/// # Example
+/// ```
+/// let cookie = Texture::builder(window_image)
+///     .wrap(TextureWrap::ClampToBorder)
+///     .border_color([0.0, 0.0, 0.0, 1.0])
+///     .build()?;
+/// let light = CookieLight::spot(vec3(0.0, 5.0, 0.0), vec3(0.0, -1.0, 0.3), 0.6, 20.0)
+///     .color([1.0, 0.9, 0.7])
+///     .cookie(cookie.id);
+///
+/// 'main_loop: loop {
+///     light.set_uniforms(&program, 1);
+///     mesh.draw();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CookieLight {
+    /// The position of the light
+    pub pos: Vec3,
+    /// The direction the light points in
+    pub direction: Vec3,
+    /// Which way is up for the cookie
+    pub up: Vec3,
+    /// The color of the light
+    pub color: [f32; 3],
+    /// How bright the light is, multiplied with the color
+    pub intensity: f32,
+    /// How far the light reaches, nothing past it is lit
+    pub radius: f32,
+    /// The shape of the light
+    pub shape: LightShape,
+    /// The id of the texture projected by the light
+    pub cookie: Option<u32>,
+    /// The id of the texture of the [LightProfile] of the light
+    pub profile: Option<u32>,
+}
+
+impl CookieLight {
+    /// Creates a new white spot light at pos pointing in direction, angle is the angle(in
+    /// radians) from the middle of the cone to its edge
+    pub fn spot(pos: Vec3, direction: Vec3, angle: f32, radius: f32) -> Self {
+        CookieLight {
+            pos,
+            direction: normalize(&direction),
+            up: vec3(0.0, 1.0, 0.0),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            radius,
+            shape: LightShape::Spot {
+                inner_angle: angle * 0.8,
+                outer_angle: angle,
+            },
+            cookie: None,
+            profile: None,
+        }
+    }
+
+    /// Creates a new white point light at pos, the cookie faces down -z
+    pub fn point(pos: Vec3, radius: f32) -> Self {
+        CookieLight {
+            pos,
+            direction: vec3(0.0, 0.0, -1.0),
+            up: vec3(0.0, 1.0, 0.0),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            radius,
+            shape: LightShape::Point,
+            cookie: None,
+            profile: None,
+        }
+    }
+
+    /// Sets the color of the light
+    pub fn color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets how bright the light is
+    pub fn intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Sets which way is up for the cookie
+    pub fn up(mut self, up: Vec3) -> Self {
+        self.up = up;
+        self
+    }
+
+    /// Sets the texture the light projects
+    pub fn cookie(mut self, texture: u32) -> Self {
+        self.cookie = Some(texture);
+        self
+    }
+
+    /// Sets the profile the light follows
+    pub fn profile(mut self, profile: &ProfileTexture) -> Self {
+        self.profile = Some(profile.id);
+        self
+    }
+
+    /// Gets the matrix the cookie is projected with, a perspective projection for spot
+    /// lights and only the rotation of the light for point lights
+    pub fn matrix(&self) -> Mat4 {
+        let direction = normalize(&self.direction);
+        // look_at breaks if up is the direction
+        let up = if normalize(&self.up).dot(&direction).abs() > 0.999 {
+            vec3(direction.y, direction.z, direction.x)
+        } else {
+            self.up
+        };
+        match self.shape {
+            LightShape::Spot { outer_angle, .. } => {
+                perspective(1.0, outer_angle * 2.0, 0.05, self.radius)
+                    * look_at(&self.pos, &(self.pos + direction), &up)
+            }
+            LightShape::Point => look_at(&vec3(0.0, 0.0, 0.0), &direction, &up),
+        }
+    }
+
+    /// Gives the light to a program that uses [COOKIE_GLSL]
+    ///
+    /// The cookie is bound to the texture unit unit and the profile to unit + 1
+    pub fn set_uniforms(&self, program: &ShaderProgram, unit: u32) {
+        program.use_program();
+        let uniform = |name| Uniform::new(program, name);
+        let direction = normalize(&self.direction);
+        let (spot, inner, outer) = match self.shape {
+            LightShape::Spot {
+                inner_angle,
+                outer_angle,
+            } => (true, inner_angle, outer_angle),
+            LightShape::Point => (false, 0.0, 0.0),
+        };
+
+        uniform("cookie_light_pos").set_uniform_f(&[self.pos.x, self.pos.y, self.pos.z]);
+        uniform("cookie_light_dir").set_uniform_f(&[direction.x, direction.y, direction.z]);
+        uniform("cookie_light_color").set_uniform_f(&self.color.map(|c| c * self.intensity));
+        uniform("cookie_light_radius").set_uniform_f(&[self.radius]);
+        uniform("cookie_light_spot").set_uniform_i(&[spot as i32]);
+        uniform("spot_cos_inner").set_uniform_f(&[inner.cos()]);
+        uniform("spot_cos_outer").set_uniform_f(&[outer.cos()]);
+        uniform("cookie_matrix").set_uniform_matrix(false, self.matrix().into());
+
+        for (texture, sampler, toggle, unit) in [
+            (self.cookie, "cookie", "use_cookie", unit),
+            (self.profile, "light_profile", "use_light_profile", unit + 1),
+        ] {
+            uniform(toggle).set_uniform_i(&[texture.is_some() as i32]);
+            if let Some(texture) = texture {
+                unsafe {
+                    glActiveTexture(GL_TEXTURE0 + unit);
+                    glBindTexture(GL_TEXTURE_2D, texture);
+                }
+                uniform(sampler).set_uniform_i(&[unit as i32]);
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use nalgebra_glm::{vec2, Vec2};
+
+use super::{
+    texture::Texture,
+    ui::{QuadInstance, UiBatch},
+};
+use crate::ECS::mouse::{Mouse, StateOfMouse};
+
+/// The name of the [UiBatch] element the cursor is drawn as
+const CURSOR_ELEMENT: &str = "__cursor";
+
+/// A cursor drawn by the ui instead of the os, so it is scaled and post processed with the
+/// rest of the ui
+///
+/// The cursor of the os has to be hidden with [Mouse::set_software_cursor], the cursor is
+/// only drawn while it is on and the mouse isn't locked
+///
+/// # Example
+/// ```
+/// let cursor = SoftwareCursor::new(&cursor_texture, vec2(32.0, 32.0)).hotspot(vec2(4.0, 2.0));
+/// world.env.mouse.set_software_cursor(true, world.env.win.as_ref());
+///
+/// 'main_loop: loop {
+///     cursor.draw(&mut ui, &world.env.mouse);
+///     ui.draw(world.env.win_size);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoftwareCursor {
+    /// The id of the texture of the cursor, it doesn't keep the texture alive so the [Texture]
+    /// has to live as long as the cursor is drawn
+    pub texture: u32,
+    /// The size of the cursor(in pixels)
+    pub size: Vec2,
+    /// The point of the cursor that is at the mouse position, from the top left of the
+    /// cursor(in pixels)
+    pub hotspot: Vec2,
+    /// The uv rectangle, top left then bottom right, for cursors in an atlas
+    pub uv: [f32; 4],
+    /// The color the texture is multiplied by
+    pub color: [f32; 4],
+    /// How much bigger the cursor is drawn, the hotspot is scaled too
+    pub scale: f32,
+    /// The ui layer of the cursor, it should be above everything else
+    pub layer: i32,
+}
+
+impl SoftwareCursor {
+    /// Creates a cursor of the whole texture with its hotspot at the top left
+    ///
+    /// The texture isn't kept alive by the cursor, it has to outlive it
+    pub fn new(texture: &Texture, size: Vec2) -> Self {
+        SoftwareCursor {
+            texture: texture.id,
+            size,
+            hotspot: vec2(0.0, 0.0),
+            uv: [0.0, 1.0, 1.0, 0.0],
+            color: [1.0; 4],
+            scale: 1.0,
+            layer: i32::MAX,
+        }
+    }
+
+    /// Sets the hotspot(in pixels from the top left of the cursor)
+    pub fn hotspot(mut self, hotspot: Vec2) -> Self {
+        self.hotspot = hotspot;
+        self
+    }
+
+    /// Sets the uv rectangle
+    pub fn uv(mut self, uv: [f32; 4]) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    /// Sets the color
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the scale
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the layer
+    pub fn layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Puts the cursor in ui at the mouse position, it is removed while the mouse is locked
+    /// or the os draws the cursor
+    pub fn draw(&self, ui: &mut UiBatch, mouse: &Mouse) {
+        if mouse.software_cursor() && mouse.state == StateOfMouse::Free {
+            self.draw_at(ui, mouse.position);
+        } else {
+            Self::hide(ui);
+        }
+    }
+
+    /// Puts the cursor in ui at pos(in pixels from the top left of the screen)
+    ///
+    /// Use it when the ui isn't the size of the window, pos is the mouse position scaled to
+    /// the ui
+    pub fn draw_at(&self, ui: &mut UiBatch, pos: Vec2) {
+        let top_left = pos - self.hotspot * self.scale;
+        let size = self.size * self.scale;
+        let quad = QuadInstance {
+            rect: [top_left.x, top_left.y, size.x, size.y],
+            uv: self.uv,
+            color: self.color,
+        };
+        ui.set(CURSOR_ELEMENT, self.texture, &[quad], self.layer);
+    }
+
+    /// Removes the cursor from ui
+    pub fn hide(ui: &mut UiBatch) {
+        ui.remove(CURSOR_ELEMENT);
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    ffi::{c_char, c_void, CStr},
+    fmt,
+};
+
+use super::{window::Window, *};
+
+// GL_KHR_debug isn't in opengl 3.3, so ogl33 is missing all of it
+const GL_DEBUG_OUTPUT: u32 = 0x92E0;
+const GL_DEBUG_OUTPUT_SYNCHRONOUS: u32 = 0x8242;
+const GL_DEBUG_SOURCE_API: u32 = 0x8246;
+const GL_DEBUG_SOURCE_WINDOW_SYSTEM: u32 = 0x8247;
+const GL_DEBUG_SOURCE_SHADER_COMPILER: u32 = 0x8248;
+const GL_DEBUG_SOURCE_THIRD_PARTY: u32 = 0x8249;
+const GL_DEBUG_SOURCE_APPLICATION: u32 = 0x824A;
+const GL_DEBUG_TYPE_ERROR: u32 = 0x824C;
+const GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR: u32 = 0x824D;
+const GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR: u32 = 0x824E;
+const GL_DEBUG_TYPE_PORTABILITY: u32 = 0x824F;
+const GL_DEBUG_TYPE_PERFORMANCE: u32 = 0x8250;
+const GL_DEBUG_SEVERITY_HIGH: u32 = 0x9146;
+const GL_DEBUG_SEVERITY_MEDIUM: u32 = 0x9147;
+const GL_DEBUG_SEVERITY_LOW: u32 = 0x9148;
+const GL_DEBUG_SEVERITY_NOTIFICATION: u32 = 0x826B;
+const GL_STACK_OVERFLOW: u32 = 0x0503;
+const GL_STACK_UNDERFLOW: u32 = 0x0504;
+
+type DebugCallback = extern "system" fn(u32, u32, u32, u32, i32, *const c_char, *mut c_void);
+type DebugMessageCallbackFn = unsafe extern "system" fn(DebugCallback, *const c_void);
+type DebugMessageControlFn = unsafe extern "system" fn(u32, u32, u32, i32, *const u32, u8);
+
+/// An error opengl gives from glGetError, see [check_errors] and [gl_check](crate::gl_check)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlError {
+    /// An enum argument isn't one the function takes
+    InvalidEnum,
+    /// A number argument is out of range
+    InvalidValue,
+    /// The function can't be called in the state opengl is in
+    InvalidOperation,
+    /// The bound framebuffer isn't complete
+    InvalidFramebufferOperation,
+    /// There isn't enough memory left to do what the function does
+    OutOfMemory,
+    /// A debug group was pushed onto a full stack
+    StackOverflow,
+    /// A debug group was popped from an empty stack
+    StackUnderflow,
+    /// An error lighthouse doesn't know, contains the error code
+    Unknown(u32),
+}
+
+impl GlError {
+    /// Gets the error of a glGetError code, None for `GL_NO_ERROR`
+    pub fn from_gl(code: u32) -> Option<Self> {
+        Some(match code {
+            GL_NO_ERROR => return None,
+            GL_INVALID_ENUM => GlError::InvalidEnum,
+            GL_INVALID_VALUE => GlError::InvalidValue,
+            GL_INVALID_OPERATION => GlError::InvalidOperation,
+            GL_INVALID_FRAMEBUFFER_OPERATION => GlError::InvalidFramebufferOperation,
+            GL_OUT_OF_MEMORY => GlError::OutOfMemory,
+            GL_STACK_OVERFLOW => GlError::StackOverflow,
+            GL_STACK_UNDERFLOW => GlError::StackUnderflow,
+            code => GlError::Unknown(code),
+        })
+    }
+}
+
+impl fmt::Display for GlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlError::InvalidEnum => write!(f, "GL_INVALID_ENUM"),
+            GlError::InvalidValue => write!(f, "GL_INVALID_VALUE"),
+            GlError::InvalidOperation => write!(f, "GL_INVALID_OPERATION"),
+            GlError::InvalidFramebufferOperation => write!(f, "GL_INVALID_FRAMEBUFFER_OPERATION"),
+            GlError::OutOfMemory => write!(f, "GL_OUT_OF_MEMORY"),
+            GlError::StackOverflow => write!(f, "GL_STACK_OVERFLOW"),
+            GlError::StackUnderflow => write!(f, "GL_STACK_UNDERFLOW"),
+            GlError::Unknown(code) => write!(f, "unknown gl error {:#X}", code),
+        }
+    }
+}
+
+impl std::error::Error for GlError {}
+
+/// Gets every error opengl has stored since the last time they were read, opengl can
+/// store more than one so it is read until it gives `GL_NO_ERROR`
+pub fn check_errors() -> Vec<GlError> {
+    // a lost context keeps giving GL_CONTEXT_LOST, so it stops at some point
+    (0..16)
+        .map_while(|_| GlError::from_gl(unsafe { glGetError() }))
+        .collect()
+}
+
+/// Logs every error opengl has stored with where it happened, used by
+/// [gl_check](crate::gl_check)
+pub fn log_errors(call: &str, file: &str, line: u32) {
+    for error in check_errors() {
+        log::error!("{} after `{}` at {}:{}", error, call, file, line);
+    }
+}
+
+/// Calls an opengl function and logs the errors it made through [log], only in debug
+/// builds. In release builds it is only the call
+///
+/// # Example
+/// ```
+/// unsafe {
+///     gl_check!(glBindTexture(GL_TEXTURE_2D, texture.id));
+///     let location = gl_check!(glGetUniformLocation(program.0, name.as_ptr().cast()));
+/// }
+/// ```
+#[macro_export]
+macro_rules! gl_check {
+    ($call:expr) => {{
+        let value = $call;
+        #[cfg(debug_assertions)]
+        $crate::graphics::debug::log_errors(stringify!($call), file!(), line!());
+        value
+    }};
+}
+
+/// Asks sdl for a debug context, which gives more and better messages to
+/// [enable_debug_output]. It has to be called before the [SdlWindow](super::window::SdlWindow)
+/// is created
+#[cfg(feature = "sdl")]
+pub fn request_debug_context() -> Result<(), LighthouseError> {
+    unsafe {
+        let mut flags = 0;
+        fermium::SDL_GL_GetAttribute(fermium::SDL_GL_CONTEXT_FLAGS, &mut flags);
+        let flags = flags | fermium::SDL_GL_CONTEXT_DEBUG_FLAG as i32;
+        if fermium::SDL_GL_SetAttribute(fermium::SDL_GL_CONTEXT_FLAGS, flags) != 0 {
+            let error = CStr::from_ptr(fermium::SDL_GetError());
+            return Err(LighthouseError::Sdl(error.to_string_lossy().into_owned()));
+        }
+    }
+    Ok(())
+}
+
+/// Loads a function of GL_KHR_debug from the context of win, it can be in the core or have
+/// a suffix
+fn load<F>(win: &dyn Window, name: &str) -> Option<F> {
+    ["", "KHR", "ARB"].iter().find_map(|suffix| {
+        let function = with_cstr(&format!("{}{}", name, suffix), |name| unsafe {
+            win.get_proc_address(name.as_ptr().cast())
+        });
+        (!function.is_null()).then(|| unsafe { std::mem::transmute_copy(&function) })
+    })
+}
+
+/// Makes the driver of the context of win send its messages to [log] as they happen, gives
+/// false if it doesn't have `GL_KHR_debug` or `GL_ARB_debug_output`
+///
+/// Errors are logged as errors, undefined and deprecated behavior as warnings, performance
+/// and portability hints as info and everything else as debug. Notifications are turned off
+/// unless the logger shows debug messages, some drivers send them for almost every call.
+/// It has to be called after the logger is set up. With synchronous the message is sent
+/// from inside the call that caused it, so a breakpoint in the logger shows where it came
+/// from, but everything is slower
+///
+/// # Example
+/// ```
+/// request_debug_context()?;
+/// let win = SdlWindow::new("My game", 800, 600)?;
+/// win.load_gl();
+///
+/// if cfg!(debug_assertions) && !enable_debug_output(&win, true) {
+///     log::warn!("The driver can't send debug messages");
+/// }
+/// ```
+pub fn enable_debug_output(win: &dyn Window, synchronous: bool) -> bool {
+    if !has_extension("GL_KHR_debug") && !has_extension("GL_ARB_debug_output") {
+        return false;
+    }
+    let Some(callback) = load::<DebugMessageCallbackFn>(win, "glDebugMessageCallback") else {
+        return false;
+    };
+    unsafe {
+        glEnable(GL_DEBUG_OUTPUT);
+        if synchronous {
+            glEnable(GL_DEBUG_OUTPUT_SYNCHRONOUS);
+        } else {
+            glDisable(GL_DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+        callback(debug_callback, std::ptr::null());
+        if let Some(control) = load::<DebugMessageControlFn>(win, "glDebugMessageControl") {
+            control(
+                GL_DONT_CARE,
+                GL_DONT_CARE,
+                GL_DEBUG_SEVERITY_NOTIFICATION,
+                0,
+                std::ptr::null(),
+                (log::max_level() >= log::LevelFilter::Debug) as u8,
+            );
+        }
+    }
+    true
+}
+
+/// Stops the driver from sending messages
+pub fn disable_debug_output() {
+    unsafe { glDisable(GL_DEBUG_OUTPUT) }
+}
+
+/// Gets the name of the source of a debug message
+fn source_name(source: u32) -> &'static str {
+    match source {
+        GL_DEBUG_SOURCE_API => "api",
+        GL_DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        GL_DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        GL_DEBUG_SOURCE_THIRD_PARTY => "third party",
+        GL_DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+extern "system" fn debug_callback(
+    source: u32,
+    ty: u32,
+    id: u32,
+    severity: u32,
+    length: i32,
+    message: *const c_char,
+    _user_param: *mut c_void,
+) {
+    let level = match (ty, severity) {
+        (GL_DEBUG_TYPE_ERROR, _) | (_, GL_DEBUG_SEVERITY_HIGH) => log::Level::Error,
+        (GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR | GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR, _)
+        | (_, GL_DEBUG_SEVERITY_MEDIUM) => log::Level::Warn,
+        (GL_DEBUG_TYPE_PERFORMANCE | GL_DEBUG_TYPE_PORTABILITY, _) | (_, GL_DEBUG_SEVERITY_LOW) => {
+            log::Level::Info
+        }
+        _ => log::Level::Debug,
+    };
+    if message.is_null() {
+        return;
+    }
+    let message = if length >= 0 {
+        let bytes = unsafe { std::slice::from_raw_parts(message.cast::<u8>(), length as usize) };
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    log::log!(
+        target: "lighthouse::gl",
+        level,
+        "{} {}: {}",
+        source_name(source),
+        id,
+        message.trim_end()
+    );
+}
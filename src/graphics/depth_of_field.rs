@@ -0,0 +1,180 @@
+use super::{fullscreen::FullscreenPass, texture::Texture, *};
+use crate::ECS::camera::CameraSettings;
+
+const DEPTH_OF_FIELD_FRAG: &str = "#version 330 core
+in vec2 uv;
+
+uniform sampler2D input_texture;
+uniform sampler2D depth_texture;
+uniform float near_plane;
+uniform float far_plane;
+uniform float focus_distance;
+uniform float aperture;
+uniform float max_blur;
+uniform float near_blur;
+uniform float far_blur;
+uniform float step_size;
+
+out vec4 color;
+
+const float GOLDEN_ANGLE = 2.39996323;
+
+float linear_depth(vec2 at) {
+    float z = texture(depth_texture, at).r * 2.0 - 1.0;
+    return 2.0 * near_plane * far_plane / (far_plane + near_plane - z * (far_plane - near_plane));
+}
+
+// the radius of the circle of confusion(in pixels)
+float blur_size(float depth) {
+    float coc = clamp((1.0 / focus_distance - 1.0 / depth) * aperture, -1.0, 1.0);
+    return abs(coc) * max_blur * (coc < 0.0 ? near_blur : far_blur);
+}
+
+void main() {
+    vec2 pixel = 1.0 / vec2(textureSize(input_texture, 0));
+    float center_depth = linear_depth(uv);
+    float center_size = blur_size(center_depth);
+
+    vec3 sum = texture(input_texture, uv).rgb;
+    float total = 1.0;
+    float radius = step_size;
+    for (float angle = 0.0; radius < max_blur; angle += GOLDEN_ANGLE) {
+        vec2 at = uv + vec2(cos(angle), sin(angle)) * pixel * radius;
+        vec3 sample_color = texture(input_texture, at).rgb;
+        float sample_depth = linear_depth(at);
+        float sample_size = blur_size(sample_depth);
+        // things behind the pixel can't blur over it more than it is blurred itself
+        if (sample_depth > center_depth) {
+            sample_size = clamp(sample_size, 0.0, center_size * 2.0);
+        }
+        float amount = smoothstep(radius - 0.5, radius + 0.5, sample_size);
+        sum += mix(sum / total, sample_color, amount);
+        total += 1.0;
+        radius += step_size / radius;
+    }
+    color = vec4(sum / total, texture(input_texture, uv).a);
+}";
+
+/// How a [DepthOfField] blurs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthOfFieldSettings {
+    /// How far away things are in focus
+    pub focus_distance: f32,
+    /// How blurry things out of focus get, 0 turns the blur off
+    pub aperture: f32,
+    /// The biggest radius of the blur(in pixels)
+    pub max_blur: f32,
+    /// How much things in front of the focus are blurred, from 0 to 1
+    pub near_blur: f32,
+    /// How much things behind the focus are blurred, from 0 to 1
+    pub far_blur: f32,
+    /// How far apart the samples are(in pixels), lower is smoother but slower
+    pub step_size: f32,
+    /// The near plane of the camera
+    pub near_plane: f32,
+    /// The far plane of the camera
+    pub far_plane: f32,
+}
+
+impl DepthOfFieldSettings {
+    /// Takes the focus, aperture and planes of a camera
+    pub fn from_camera(camera: &CameraSettings) -> Self {
+        DepthOfFieldSettings {
+            focus_distance: camera.focus_distance,
+            aperture: camera.aperture,
+            near_plane: camera.near_plane,
+            far_plane: camera.far_plane,
+            ..DepthOfFieldSettings::default()
+        }
+    }
+}
+
+impl Default for DepthOfFieldSettings {
+    fn default() -> Self {
+        DepthOfFieldSettings {
+            focus_distance: 10.0,
+            aperture: 5.0,
+            max_blur: 16.0,
+            near_blur: 1.0,
+            far_blur: 1.0,
+            step_size: 0.5,
+            near_plane: 0.1,
+            far_plane: 100.0,
+        }
+    }
+}
+
+/// A post pass that blurs what is in front of and behind the focus, like a real camera
+///
+/// The blur of every pixel is worked out from the depth of the scene, so it has to be drawn
+/// into a [crate::graphics::framebuffer::Framebuffer::with_depth_texture]
+///
+/// # Example
+/// ```
+/// let mut dof = DepthOfField::new()?;
+///
+/// 'main_loop: loop {
+///     dof.settings = DepthOfFieldSettings::from_camera(&camera.get_camera_settings());
+///     // draw the scene into target
+///     dof.draw(&target.color, target.depth);
+/// }
+/// ```
+pub struct DepthOfField {
+    /// How the pass blurs
+    pub settings: DepthOfFieldSettings,
+    pass: FullscreenPass,
+}
+
+impl DepthOfField {
+    /// Creates a new depth of field pass with the default settings
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(DepthOfField {
+            settings: DepthOfFieldSettings::default(),
+            pass: FullscreenPass::new(DEPTH_OF_FIELD_FRAG)?,
+        })
+    }
+
+    /// Sets how the pass blurs
+    pub fn settings(mut self, settings: DepthOfFieldSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Draws scene blurred by the depth texture depth into the bound framebuffer
+    ///
+    /// The depth is bound to texture unit 1, with an aperture of 0 the scene is drawn as is
+    pub fn draw(&self, scene: &Texture, depth: u32) {
+        let settings = &self.settings;
+        let pass = &self.pass;
+        unsafe {
+            glActiveTexture(GL_TEXTURE1);
+            glBindTexture(GL_TEXTURE_2D, depth);
+        }
+        let max_blur = if settings.aperture > 0.0 {
+            settings.max_blur
+        } else {
+            0.0
+        };
+
+        pass.uniform("depth_texture").set_uniform_i(&[1]);
+        pass.uniform("near_plane")
+            .set_uniform_f(&[settings.near_plane]);
+        pass.uniform("far_plane")
+            .set_uniform_f(&[settings.far_plane]);
+        pass.uniform("focus_distance")
+            .set_uniform_f(&[settings.focus_distance.max(settings.near_plane)]);
+        pass.uniform("aperture").set_uniform_f(&[settings.aperture]);
+        pass.uniform("max_blur").set_uniform_f(&[max_blur]);
+        pass.uniform("near_blur")
+            .set_uniform_f(&[settings.near_blur]);
+        pass.uniform("far_blur").set_uniform_f(&[settings.far_blur]);
+        pass.uniform("step_size")
+            .set_uniform_f(&[settings.step_size.max(0.1)]);
+        pass.draw(scene);
+    }
+
+    /// Deletes the pass
+    pub fn delete(self) {
+        self.pass.delete();
+    }
+}
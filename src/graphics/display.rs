@@ -0,0 +1,356 @@
+use std::ffi::CStr;
+
+use super::*;
+
+/// A display mode of a [Monitor]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayMode {
+    /// Width of the mode(in pixels)
+    pub width: i32,
+    /// Height of the mode(in pixels)
+    pub height: i32,
+    /// Refresh rate of the mode(in hz), 0 if it is unknown
+    pub refresh_rate: i32,
+    /// The SDL pixel format of the mode
+    pub format: u32,
+}
+
+impl DisplayMode {
+    fn from_sdl(mode: fermium::SDL_DisplayMode) -> Self {
+        DisplayMode {
+            width: mode.w,
+            height: mode.h,
+            refresh_rate: mode.refresh_rate,
+            format: mode.format,
+        }
+    }
+
+    fn to_sdl(self) -> fermium::SDL_DisplayMode {
+        let mut mode: fermium::SDL_DisplayMode = unsafe { std::mem::zeroed() };
+        mode.w = self.width;
+        mode.h = self.height;
+        mode.refresh_rate = self.refresh_rate;
+        mode.format = self.format;
+        mode
+    }
+}
+
+/// A monitor connected to the computer
+///
+/// # Example
+/// List every monitor for a settings menu
+/// ```
+/// for monitor in monitors()? {
+///     println!("{}: {}x{}", monitor.name, monitor.width, monitor.height);
+///     for mode in &monitor.modes {
+///         println!("    {}x{} @ {}hz", mode.width, mode.height, mode.refresh_rate);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    /// The index SDL uses for the monitor
+    pub index: i32,
+    /// The name of the monitor
+    pub name: String,
+    /// The x position of the monitor on the desktop
+    pub x: i32,
+    /// The y position of the monitor on the desktop
+    pub y: i32,
+    /// Width of the monitor(in pixels)
+    pub width: i32,
+    /// Height of the monitor(in pixels)
+    pub height: i32,
+    /// The mode the desktop is using
+    pub desktop_mode: DisplayMode,
+    /// Every mode the monitor supports, from largest to smallest
+    pub modes: Vec<DisplayMode>,
+}
+
+impl Monitor {
+    /// Gets the position that centers a window of size width by height on the monitor
+    ///
+    /// It is used to create the window on this monitor
+    /// ```
+    /// let win = sdl.create_gl_window(title, monitor.centered(800, 600), 800, 600, flags)
+    /// ```
+    pub fn centered(&self, width: i32, height: i32) -> WindowPosition {
+        WindowPosition::XY(
+            self.x + (self.width - width) / 2,
+            self.y + (self.height - height) / 2,
+        )
+    }
+
+    /// Gets the mode that is the closest to the given size and refresh rate
+    pub fn closest_mode(&self, width: i32, height: i32, refresh_rate: i32) -> Option<DisplayMode> {
+        self.modes.iter().copied().min_by_key(|mode| {
+            (
+                (mode.width - width).abs() + (mode.height - height).abs(),
+                (mode.refresh_rate - refresh_rate).abs(),
+            )
+        })
+    }
+}
+
+/// How the window covers the monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fullscreen {
+    /// A normal window
+    Windowed,
+    /// A window covering the whole monitor, the monitor keeps its desktop mode
+    Borderless,
+    /// The window takes over the monitor and switches it to this mode
+    Exclusive(DisplayMode),
+}
+
+/// Options of the window that have to be set when it is created, for overlays and desktop
+/// toys
+///
+/// With a transparent window the alpha of the clear color and of everything drawn is how
+/// see through the window is. Most compositors expect the colors to be multiplied by the
+/// alpha already, so clear to `[r * a, g * a, b * a, a]`. Whether it works depends on the os
+/// and compositor, when it doesn't the window is opaque
+///
+/// # Example
+/// A clock that floats over the desktop
+/// ```
+/// let win = WindowOptions::default()
+///     .transparent(true)
+///     .borderless(true)
+///     .always_on_top(true)
+///     .create(&sdl, "clock", WindowPosition::Centered, 200, 200)?;
+/// world.renderer.clear_color = [0.0, 0.0, 0.0, 0.0];
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowOptions {
+    /// Whether the window has an alpha channel the desktop shows through
+    pub transparent: bool,
+    /// Whether the window has no title bar and border
+    pub borderless: bool,
+    /// Whether the window stays above the other windows
+    pub always_on_top: bool,
+    /// Whether the window can be resized
+    pub resizable: bool,
+}
+
+impl WindowOptions {
+    /// Sets whether the window is transparent
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Sets whether the window is borderless
+    pub fn borderless(mut self, borderless: bool) -> Self {
+        self.borderless = borderless;
+        self
+    }
+
+    /// Sets whether the window is always on top
+    pub fn always_on_top(mut self, always_on_top: bool) -> Self {
+        self.always_on_top = always_on_top;
+        self
+    }
+
+    /// Sets whether the window can be resized
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Gets the flags the window is created with
+    pub fn flags(&self) -> WindowFlags {
+        let mut flags = fermium::SDL_WINDOW_SHOWN;
+        if self.borderless {
+            flags |= fermium::SDL_WINDOW_BORDERLESS;
+        }
+        if self.always_on_top {
+            flags |= fermium::SDL_WINDOW_ALWAYS_ON_TOP;
+        }
+        if self.resizable {
+            flags |= fermium::SDL_WINDOW_RESIZABLE;
+        }
+        // beryllium only has constants for a few of the flags, WindowFlags is a transparent
+        // wrapper of the SDL flags so the rest are made from their bits
+        unsafe { std::mem::transmute::<fermium::SDL_WindowFlags, WindowFlags>(flags) }
+    }
+
+    /// Creates the window and its opengl context with these options
+    ///
+    /// The opengl version and profile have to be set on sdl before, like for
+    /// `create_gl_window`
+    pub fn create(
+        &self,
+        sdl: &SDL,
+        title: &str,
+        pos: WindowPosition,
+        width: u32,
+        height: u32,
+    ) -> Result<GlWindow, LighthouseError> {
+        if self.transparent {
+            sdl.gl_set_attribute(SdlGlAttr::AlphaSize, 8)
+                .map_err(LighthouseError::Sdl)?;
+            // X11 turns the compositor off for some windows, without it nothing shows through
+            unsafe {
+                fermium::SDL_SetHint(
+                    c"SDL_VIDEO_X11_NET_WM_BYPASS_COMPOSITOR".as_ptr(),
+                    c"0".as_ptr(),
+                );
+            }
+        }
+        sdl.create_gl_window(title, pos, width, height, self.flags())
+            .map_err(LighthouseError::Sdl)
+    }
+}
+
+impl Default for WindowOptions {
+    /// An opaque window with a border that can't be resized, like `WindowFlags::Shown`
+    fn default() -> Self {
+        WindowOptions {
+            transparent: false,
+            borderless: false,
+            always_on_top: false,
+            resizable: false,
+        }
+    }
+}
+
+/// Adds or removes the title bar and border of the window
+///
+/// The window has to be [Fullscreen::Windowed] for this to do anything
+pub fn set_borderless(win: &GlWindow, borderless: bool) {
+    unsafe {
+        fermium::SDL_SetWindowBordered(
+            raw_window(win),
+            if borderless {
+                fermium::SDL_FALSE
+            } else {
+                fermium::SDL_TRUE
+            },
+        )
+    }
+}
+
+/// Gets every monitor connected to the computer
+pub fn monitors() -> Result<Vec<Monitor>, LighthouseError> {
+    let count = unsafe { fermium::SDL_GetNumVideoDisplays() };
+    if count < 0 {
+        return Err(sdl_error());
+    }
+
+    (0..count).map(monitor).collect()
+}
+
+/// Gets the monitor with the given index
+pub fn monitor(index: i32) -> Result<Monitor, LighthouseError> {
+    unsafe {
+        let name = fermium::SDL_GetDisplayName(index);
+        if name.is_null() {
+            return Err(sdl_error());
+        }
+        let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+
+        let mut bounds: fermium::SDL_Rect = std::mem::zeroed();
+        if fermium::SDL_GetDisplayBounds(index, &mut bounds) != 0 {
+            return Err(sdl_error());
+        }
+
+        let mut desktop_mode: fermium::SDL_DisplayMode = std::mem::zeroed();
+        if fermium::SDL_GetDesktopDisplayMode(index, &mut desktop_mode) != 0 {
+            return Err(sdl_error());
+        }
+
+        let mut modes = Vec::new();
+        for mode_index in 0..fermium::SDL_GetNumDisplayModes(index) {
+            let mut mode: fermium::SDL_DisplayMode = std::mem::zeroed();
+            if fermium::SDL_GetDisplayMode(index, mode_index, &mut mode) == 0 {
+                modes.push(DisplayMode::from_sdl(mode));
+            }
+        }
+
+        Ok(Monitor {
+            index,
+            name,
+            x: bounds.x,
+            y: bounds.y,
+            width: bounds.w,
+            height: bounds.h,
+            desktop_mode: DisplayMode::from_sdl(desktop_mode),
+            modes,
+        })
+    }
+}
+
+/// Gets the SDL window of win
+fn raw_window(win: &GlWindow) -> *mut fermium::SDL_Window {
+    let window: &beryllium::Window = win;
+    // beryllium's Window is a transparent wrapper of the SDL window but doesn't give it out
+    unsafe { *(window as *const beryllium::Window).cast::<*mut fermium::SDL_Window>() }
+}
+
+/// Gets the monitor the window is on
+pub fn current_monitor(win: &GlWindow) -> Result<Monitor, LighthouseError> {
+    let index = unsafe { fermium::SDL_GetWindowDisplayIndex(raw_window(win)) };
+    if index < 0 {
+        return Err(sdl_error());
+    }
+    monitor(index)
+}
+
+/// Moves the window to the center of the monitor
+///
+/// The window has to be [Fullscreen::Windowed] for this to work,
+/// use [set_fullscreen] to move fullscreen windows
+pub fn move_to_monitor(win: &GlWindow, monitor: &Monitor) {
+    let window = raw_window(win);
+    // the monitor bounds are in points like the window size, the drawable size isn't
+    let (mut width, mut height) = (0, 0);
+    unsafe { fermium::SDL_GetWindowSize(window, &mut width, &mut height) };
+    if let WindowPosition::XY(x, y) = monitor.centered(width, height) {
+        unsafe { fermium::SDL_SetWindowPosition(window, x, y) }
+    }
+}
+
+/// Makes the window cover the monitor in the given way
+///
+/// # Example
+/// ```
+/// let monitor = &monitors()?[1];
+/// let mode = monitor.closest_mode(1920, 1080, 144).unwrap();
+/// set_fullscreen(&win, monitor, Fullscreen::Exclusive(mode))?;
+/// ```
+pub fn set_fullscreen(
+    win: &GlWindow,
+    monitor: &Monitor,
+    fullscreen: Fullscreen,
+) -> Result<(), LighthouseError> {
+    let window = raw_window(win);
+
+    // SDL uses the monitor the window is on, so leave fullscreen first to be able to move it
+    if unsafe { fermium::SDL_SetWindowFullscreen(window, 0) } != 0 {
+        return Err(sdl_error());
+    }
+    move_to_monitor(win, monitor);
+
+    let flags = match fullscreen {
+        Fullscreen::Windowed => return Ok(()),
+        Fullscreen::Borderless => fermium::SDL_WINDOW_FULLSCREEN_DESKTOP,
+        Fullscreen::Exclusive(mode) => {
+            if unsafe { fermium::SDL_SetWindowDisplayMode(window, &mode.to_sdl()) } != 0 {
+                return Err(sdl_error());
+            }
+            fermium::SDL_WINDOW_FULLSCREEN
+        }
+    };
+
+    if unsafe { fermium::SDL_SetWindowFullscreen(window, flags as u32) } != 0 {
+        return Err(sdl_error());
+    }
+    Ok(())
+}
+
+/// Gets the last SDL error
+fn sdl_error() -> LighthouseError {
+    let error = unsafe { CStr::from_ptr(fermium::SDL_GetError()) };
+    LighthouseError::Sdl(error.to_string_lossy().into_owned())
+}
@@ -0,0 +1,381 @@
+use std::{f32::consts::TAU, mem::size_of};
+
+use nalgebra_glm::{identity, ortho, vec2, Mat4, Vec2};
+
+use super::{
+    buffer::*, render_state::RenderState, shader::ShaderProgram, sprite::TextureHandle, svg::Svg,
+    texture::*, uniform::Uniform, vertex::VertexArray, *,
+};
+use crate::ECS::camera2d::Camera2D;
+
+const DRAW_2D_VERT: &str = "#version 330 core
+layout (location = 0) in vec2 pos;
+layout (location = 1) in vec2 in_uv;
+layout (location = 2) in vec4 in_color;
+
+uniform mat4 view_proj;
+
+out vec2 uv;
+out vec4 color;
+
+void main() {
+    uv = in_uv;
+    color = in_color;
+    gl_Position = view_proj * vec4(pos, 0.0, 1.0);
+}";
+
+const DRAW_2D_FRAG: &str = "#version 330 core
+in vec2 uv;
+in vec4 color;
+
+uniform sampler2D tex;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = texture(tex, uv) * color;
+}";
+
+/// How many segments a circle is made of
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// A vertex of [Draw2D]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Vertex2D {
+    pos: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for Vertex2D {}
+unsafe impl bytemuck::Pod for Vertex2D {}
+
+/// Immediate mode 2d drawing, for prototypes and debug drawing
+///
+/// Every shape is added to a batch that is drawn when the camera changes or on
+/// [Draw2D::flush]. Positions are in world units of the [Camera2D] that was set last, or in
+/// pixels from the top left when [Draw2D::set_screen_space] was called
+///
+/// # Example
+/// ```
+/// let mut draw = Draw2D::new()?;
+///
+/// 'main_loop: loop {
+///     draw.set_camera(&world.ecs.read_resource::<Camera2D>());
+///     draw.draw_rect(vec2(0.0, 0.0), vec2(100.0, 10.0), [0.2, 0.8, 0.2, 1.0]);
+///     draw.draw_circle(ball_pos, 8.0, [1.0; 4]);
+///     draw.draw_texture_region(sheet, [16.0, 0.0, 16.0, 16.0], player_pos, vec2(16.0, 16.0), [1.0; 4]);
+///
+///     // the hud is in screen space
+///     draw.set_screen_space(world.env.win_size);
+///     draw.draw_rect(vec2(10.0, 10.0), vec2(200.0 * health, 20.0), [1.0, 0.0, 0.0, 1.0]);
+///     draw.flush();
+/// }
+/// ```
+pub struct Draw2D {
+    /// The shader program used to draw
+    pub program: ShaderProgram,
+    /// The matrix the batch is drawn with
+    view_proj: Mat4,
+    /// Whether y goes down, then textures have to be flipped to stay upright
+    y_down: bool,
+    /// The vertices of the batch, every 3 vertices are a triangle
+    vertices: Vec<Vertex2D>,
+    /// Every texture change in the batch, the first vertex that uses the texture and its id
+    textures: Vec<(usize, u32)>,
+    /// 1x1 white texture used for shapes without a texture
+    white: Texture,
+    vao: VertexArray,
+    vbo: Buffer,
+}
+
+impl Draw2D {
+    /// Creates a new drawer that draws in clip space until a camera is set
+    pub fn new() -> Result<Self, LighthouseError> {
+        let white = Texture::builder(ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([255, 255, 255, 255]),
+        )))
+        .filter(TextureFilter::Nearest, TextureFilter::Nearest)
+        .build()?;
+
+        let draw = Draw2D {
+            program: ShaderProgram::from_vert_frag(DRAW_2D_VERT, DRAW_2D_FRAG)?,
+            view_proj: identity(),
+            y_down: false,
+            vertices: Vec::new(),
+            textures: Vec::new(),
+            white,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+            vbo: Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+        };
+
+        draw.vao.bind();
+        draw.vbo.bind(BufferType::Array);
+        let stride = size_of::<Vertex2D>() as i32;
+        unsafe {
+            glVertexAttribPointer(0, 2, GL_FLOAT, GL_FALSE, stride, std::ptr::null());
+            glVertexAttribPointer(1, 2, GL_FLOAT, GL_FALSE, stride, 8 as *const _);
+            glVertexAttribPointer(2, 4, GL_FLOAT, GL_FALSE, stride, 16 as *const _);
+            for location in 0..3 {
+                glEnableVertexAttribArray(location);
+            }
+        }
+        VertexArray::clear_binding();
+
+        Ok(draw)
+    }
+
+    /// Draws what was batched and makes the next shapes use the transform of camera
+    pub fn set_camera(&mut self, camera: &Camera2D) {
+        self.set_view_proj(camera.view_proj());
+    }
+
+    /// Draws what was batched and makes the next shapes use pixels from the top left of
+    /// the screen
+    pub fn set_screen_space(&mut self, screen_size: Vec2) {
+        self.set_view_proj(ortho(0.0, screen_size.x, screen_size.y, 0.0, -1.0, 1.0));
+        self.y_down = true;
+    }
+
+    /// Draws what was batched and makes the next shapes use view_proj, y is expected to
+    /// go up
+    pub fn set_view_proj(&mut self, view_proj: Mat4) {
+        self.flush();
+        self.view_proj = view_proj;
+        self.y_down = false;
+    }
+
+    /// Draws a filled rectangle, pos is the corner with the lowest coordinates
+    pub fn draw_rect(&mut self, pos: Vec2, size: Vec2, color: [f32; 4]) {
+        self.quad(self.white.id, pos, size, [0.0, 0.0, 1.0, 1.0], [color; 4]);
+    }
+
+    /// Draws a rectangle with a color in every corner that blend into each other
+    ///
+    /// colors are counter clockwise from the corner at pos, so when y goes up they are the
+    /// bottom left, bottom right, top right and top left
+    pub fn draw_rect_gradient(&mut self, pos: Vec2, size: Vec2, colors: [[f32; 4]; 4]) {
+        self.quad(self.white.id, pos, size, [0.0, 0.0, 1.0, 1.0], colors);
+    }
+
+    /// Draws a triangle with a color for every point that blend into each other
+    pub fn draw_triangle(&mut self, points: [Vec2; 3], colors: [[f32; 4]; 3]) {
+        self.triangle(self.white.id, points, colors);
+    }
+
+    /// Draws the outline of a rectangle, thickness goes inwards
+    pub fn draw_rect_lines(&mut self, pos: Vec2, size: Vec2, thickness: f32, color: [f32; 4]) {
+        let t = thickness.min(size.x / 2.0).min(size.y / 2.0);
+        self.draw_rect(pos, vec2(size.x, t), color);
+        self.draw_rect(pos + vec2(0.0, size.y - t), vec2(size.x, t), color);
+        self.draw_rect(pos + vec2(0.0, t), vec2(t, size.y - 2.0 * t), color);
+        self.draw_rect(pos + vec2(size.x - t, t), vec2(t, size.y - 2.0 * t), color);
+    }
+
+    /// Draws a line from a to b
+    pub fn draw_line(&mut self, a: Vec2, b: Vec2, thickness: f32, color: [f32; 4]) {
+        let dir = b - a;
+        if dir.norm_squared() == 0.0 {
+            return;
+        }
+        let side = vec2(-dir.y, dir.x).normalize() * thickness / 2.0;
+        self.triangle(self.white.id, [a - side, a + side, b + side], [color; 3]);
+        self.triangle(self.white.id, [a - side, b + side, b - side], [color; 3]);
+    }
+
+    /// Draws a filled circle
+    pub fn draw_circle(&mut self, center: Vec2, radius: f32, color: [f32; 4]) {
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            center + vec2(angle.cos(), angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.triangle(self.white.id, [center, point(i), point(i + 1)], [color; 3]);
+        }
+    }
+
+    /// Draws the outline of a circle, thickness goes inwards
+    pub fn draw_circle_lines(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        thickness: f32,
+        color: [f32; 4],
+    ) {
+        let point = |i: usize, radius: f32| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            center + vec2(angle.cos(), angle.sin()) * radius
+        };
+        let inner = (radius - thickness).max(0.0);
+        for i in 0..CIRCLE_SEGMENTS {
+            let quad = [
+                point(i, inner),
+                point(i, radius),
+                point(i + 1, radius),
+                point(i + 1, inner),
+            ];
+            self.triangle(self.white.id, [quad[0], quad[1], quad[2]], [color; 3]);
+            self.triangle(self.white.id, [quad[0], quad[2], quad[3]], [color; 3]);
+        }
+    }
+
+    /// Draws all of texture stretched over a rectangle
+    pub fn draw_texture(&mut self, texture: TextureHandle, pos: Vec2, size: Vec2, tint: [f32; 4]) {
+        self.draw_texture_region(
+            texture,
+            [0.0, 0.0, texture.size.x, texture.size.y],
+            pos,
+            size,
+            tint,
+        );
+    }
+
+    /// Draws a region of texture stretched over a rectangle
+    ///
+    /// region is x, y, width and height(in pixels from the top left of the texture)
+    pub fn draw_texture_region(
+        &mut self,
+        texture: TextureHandle,
+        region: [f32; 4],
+        pos: Vec2,
+        size: Vec2,
+        tint: [f32; 4],
+    ) {
+        let [x, y, width, height] = region;
+        // images are flipped when they are uploaded so v goes up from the bottom
+        let uv = [
+            x / texture.size.x,
+            1.0 - (y + height) / texture.size.y,
+            (x + width) / texture.size.x,
+            1.0 - y / texture.size.y,
+        ];
+        self.quad(texture.id, pos, size, uv, [tint; 4]);
+    }
+
+    /// Draws an [Svg] stretched over a rectangle, pos is the corner with the lowest
+    /// coordinates
+    ///
+    /// The image stays upright whether y goes up or down
+    pub fn draw_svg(&mut self, svg: &Svg, pos: Vec2, size: Vec2) {
+        let [x, y, width, height] = svg.view_box;
+        let scale = vec2(size.x / width, size.y / height);
+        let y_down = self.y_down;
+        let map = |point: &Vec2| {
+            let local = vec2(point.x - x, point.y - y).component_mul(&scale);
+            match y_down {
+                true => pos + local,
+                false => vec2(pos.x + local.x, pos.y + size.y - local.y),
+            }
+        };
+        for shape in &svg.shapes {
+            for triangle in &shape.triangles {
+                let points = triangle.map(|i| map(&shape.points[i as usize]));
+                self.triangle(self.white.id, points, [shape.color; 3]);
+            }
+        }
+    }
+
+    /// Draws every batched shape and empties the batch
+    pub fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        self.program.use_program();
+        Uniform::new(&self.program, "view_proj").set_uniform_matrix(false, self.view_proj.into());
+        Uniform::new(&self.program, "tex").set_uniform_i(&[0]);
+
+        self.vao.bind();
+        self.vbo.bind(BufferType::Array);
+        buffer_data(
+            BufferType::Array,
+            bytemuck::cast_slice(&self.vertices),
+            BufferUsage::StreamDraw,
+        );
+
+        unsafe {
+            let depth_test = glIsEnabled(GL_DEPTH_TEST) == GL_TRUE;
+            let blend = glIsEnabled(GL_BLEND) == GL_TRUE;
+            glDisable(GL_DEPTH_TEST);
+            glEnable(GL_BLEND);
+            glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+            glActiveTexture(GL_TEXTURE0);
+
+            for (i, (first, texture)) in self.textures.iter().enumerate() {
+                let end = self
+                    .textures
+                    .get(i + 1)
+                    .map_or(self.vertices.len(), |(next, _)| *next);
+                glBindTexture(GL_TEXTURE_2D, *texture);
+                glDrawArrays(GL_TRIANGLES, *first as i32, (end - first) as i32);
+            }
+
+            if depth_test {
+                glEnable(GL_DEPTH_TEST);
+            }
+            if !blend {
+                glDisable(GL_BLEND);
+            }
+        }
+        // the blend function isn't put back how it was
+        RenderState::invalidate();
+        VertexArray::clear_binding();
+
+        self.vertices.clear();
+        self.textures.clear();
+    }
+
+    /// Adds a triangle with a plain color to the batch
+    fn triangle(&mut self, texture: u32, points: [Vec2; 3], colors: [[f32; 4]; 3]) {
+        self.use_texture(texture);
+        for (point, color) in points.into_iter().zip(colors) {
+            self.vertices.push(Vertex2D {
+                pos: [point.x, point.y],
+                uv: [0.0, 0.0],
+                color,
+            });
+        }
+    }
+
+    /// Adds a textured quad to the batch, uv is the bottom left then the top right
+    ///
+    /// colors are the colors of the corners, counter clockwise from the corner at pos
+    fn quad(&mut self, texture: u32, pos: Vec2, size: Vec2, uv: [f32; 4], colors: [[f32; 4]; 4]) {
+        self.use_texture(texture);
+        let [u0, mut v0, u1, mut v1] = uv;
+        if self.y_down {
+            std::mem::swap(&mut v0, &mut v1);
+        }
+        let corners = [
+            ([pos.x, pos.y], [u0, v0]),
+            ([pos.x + size.x, pos.y], [u1, v0]),
+            ([pos.x + size.x, pos.y + size.y], [u1, v1]),
+            ([pos.x, pos.y + size.y], [u0, v1]),
+        ];
+        for i in [0, 1, 2, 0, 2, 3] {
+            let (pos, uv) = corners[i];
+            self.vertices.push(Vertex2D {
+                pos,
+                uv,
+                color: colors[i],
+            });
+        }
+    }
+
+    /// Starts a new draw if texture is not the texture of the last shape
+    fn use_texture(&mut self, texture: u32) {
+        if self.textures.last().map(|(_, last)| *last) != Some(texture) {
+            self.textures.push((self.vertices.len(), texture));
+        }
+    }
+
+    /// Deletes the shader program and texture of the drawer
+    pub fn delete(self) {
+        self.white.delete();
+        self.program.delete();
+    }
+}
@@ -0,0 +1,234 @@
+use super::{
+    framebuffer::ColorFormat,
+    image_pass::{read_texel, render_texture, Histogram, ImagePass},
+    post_process::PostPass,
+    texture::Texture,
+    *,
+};
+
+/// Width and height of the texture the luminance of the scene is measured in(in pixels)
+const LUMINANCE_SIZE: i32 = 256;
+
+/// How many bins [MeteringMode::Histogram] sorts the luminance into
+const HISTOGRAM_BINS: i32 = 64;
+
+const LOG_LUMINANCE_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+out vec4 color;
+
+void main() {
+    float luminance = dot(texture(input_texture, uv).rgb, vec3(0.2126, 0.7152, 0.0722));
+    color = vec4(log2(max(luminance, 0.0001)));
+}";
+
+/// How [AutoExposure] works out how bright the scene is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MeteringMode {
+    /// The average of the whole screen, reduced on the gpu with [ImagePass::reduce]
+    Average,
+    /// The average of a [Histogram] without the darkest low and the brightest high part of
+    /// the pixels, from 0 to 1, so a small bright light or a dark corner doesn't change the
+    /// exposure much
+    Histogram {
+        /// The part of the darkest pixels that is left out
+        low: f32,
+        /// The part of the brightest pixels that is left out
+        high: f32,
+    },
+}
+
+/// Changes the exposure of an hdr scene to how bright it is, like an eye getting used to
+/// the dark
+///
+/// Every update the average log luminance of the scene is measured, it is clamped to
+/// [AutoExposure::min_luminance] and [AutoExposure::max_luminance] and the eye slowly
+/// adapts to it. The exposure is [AutoExposure::key] divided by the adapted luminance and
+/// is given to a [PostPass::tone_map] with [AutoExposure::apply]
+///
+/// Measuring reads a few pixels back to the cpu, which waits for the gpu to finish the
+/// scene
+///
+/// # Example
+/// ```
+/// let mut post = PostProcessStack::with_format(800, 600, ColorFormat::Rgba16F)?;
+/// post.push(PostPass::tone_map(1.0)?);
+/// let mut exposure = AutoExposure::new()?
+///     .metering(MeteringMode::Histogram { low: 0.5, high: 0.05 })
+///     .clamp(0.05, 8.0);
+///
+/// 'main_loop: loop {
+///     post.begin();
+///     // draw the scene
+///     exposure.update(&post.scene().color, world.env.time.real_delta)?;
+///     exposure.apply(post.pass_mut("tone_map").unwrap());
+///     post.end(800, 600);
+/// }
+/// ```
+pub struct AutoExposure {
+    /// How the brightness of the scene is measured, see [MeteringMode]
+    pub metering: MeteringMode,
+    /// The luminance the adapted luminance is mapped to, 0.18 is middle gray
+    pub key: f32,
+    /// The darkest luminance the eye adapts to, darker scenes stay dark
+    pub min_luminance: f32,
+    /// The brightest luminance the eye adapts to, brighter scenes stay bright
+    pub max_luminance: f32,
+    /// How fast the eye adapts when the scene gets brighter(per second)
+    pub speed_up: f32,
+    /// How fast the eye adapts when the scene gets darker(per second)
+    pub speed_down: f32,
+    /// The luminance the eye has adapted to, None before the first update
+    adapted: Option<f32>,
+    /// Turns the scene into log luminance
+    log_pass: ImagePass,
+    /// Averages the log luminance for [MeteringMode::Average]
+    average: ImagePass,
+    /// Counts the log luminance for [MeteringMode::Histogram]
+    histogram: Histogram,
+    /// The log luminance of the scene, a LUMINANCE_SIZE by LUMINANCE_SIZE
+    /// [ColorFormat::Rgba32F] texture
+    log_luminance: Texture,
+}
+
+impl AutoExposure {
+    /// Creates an auto exposure that averages the whole screen and adapts between 0.03 and
+    /// 16
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(AutoExposure {
+            metering: MeteringMode::Average,
+            key: 0.18,
+            min_luminance: 0.03,
+            max_luminance: 16.0,
+            speed_up: 3.0,
+            speed_down: 1.0,
+            adapted: None,
+            log_pass: ImagePass::new(LOG_LUMINANCE_FRAG)?,
+            average: ImagePass::average()?,
+            histogram: Histogram::new(HISTOGRAM_BINS)?.weights([1.0, 0.0, 0.0, 0.0]),
+            log_luminance: render_texture(LUMINANCE_SIZE, LUMINANCE_SIZE, ColorFormat::Rgba32F),
+        })
+    }
+
+    /// Sets how the brightness of the scene is measured
+    pub fn metering(mut self, metering: MeteringMode) -> Self {
+        self.metering = metering;
+        self
+    }
+
+    /// Sets the luminance the adapted luminance is mapped to
+    pub fn key(mut self, key: f32) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Sets the darkest and brightest luminance the eye adapts to
+    pub fn clamp(mut self, min_luminance: f32, max_luminance: f32) -> Self {
+        self.min_luminance = min_luminance.max(0.0001);
+        self.max_luminance = max_luminance.max(self.min_luminance);
+        self
+    }
+
+    /// Sets how fast the eye adapts when the scene gets brighter and darker(per second)
+    pub fn speed(mut self, speed_up: f32, speed_down: f32) -> Self {
+        self.speed_up = speed_up;
+        self.speed_down = speed_down;
+        self
+    }
+
+    /// Measures how bright level 0 of scene is right now, without clamping or adapting
+    pub fn measure(&mut self, scene: &Texture) -> Result<f32, LighthouseError> {
+        self.log_pass.run(scene, &self.log_luminance)?;
+        let log_luminance = match self.metering {
+            MeteringMode::Average => {
+                let levels = self.average.reduce(&self.log_luminance)?;
+                read_texel(&self.log_luminance, levels - 1)[0]
+            }
+            MeteringMode::Histogram { low, high } => {
+                let range = (self.min_luminance.log2(), self.max_luminance.log2());
+                self.histogram.range = range;
+                self.histogram.run(&self.log_luminance)?;
+                histogram_average(&self.histogram.read(), range, low, high)
+            }
+        };
+        Ok(log_luminance.exp2())
+    }
+
+    /// Measures scene and adapts to it over delta(in seconds), gives the new exposure
+    ///
+    /// The first update adapts at once, so the scene doesn't fade in
+    pub fn update(&mut self, scene: &Texture, delta: f32) -> Result<f32, LighthouseError> {
+        let target = self
+            .measure(scene)?
+            .clamp(self.min_luminance, self.max_luminance);
+        let adapted = match self.adapted {
+            Some(adapted) => {
+                let speed = if target > adapted {
+                    self.speed_up
+                } else {
+                    self.speed_down
+                };
+                // adapting in log space makes getting brighter and darker feel the same
+                let blend = 1.0 - (-delta.max(0.0) * speed).exp();
+                (adapted.log2() + (target.log2() - adapted.log2()) * blend).exp2()
+            }
+            None => target,
+        };
+        self.adapted = Some(adapted);
+        Ok(self.exposure())
+    }
+
+    /// Gets the luminance the eye has adapted to, the key before the first update
+    pub fn luminance(&self) -> f32 {
+        self.adapted.unwrap_or(self.key)
+    }
+
+    /// Gets the exposure the scene is multiplied by
+    pub fn exposure(&self) -> f32 {
+        self.key / self.luminance()
+    }
+
+    /// Sets the `exposure` uniform of pass, a [PostPass::tone_map]
+    pub fn apply(&self, pass: &mut PostPass) {
+        pass.set("exposure", self.exposure());
+    }
+
+    /// Forgets what the eye adapted to, so the next update adapts at once, e.g. after a
+    /// cut to another scene
+    pub fn reset(&mut self) {
+        self.adapted = None;
+    }
+
+    /// Deletes the passes, histogram and luminance texture
+    pub fn delete(self) {
+        self.log_pass.delete();
+        self.average.delete();
+        self.histogram.delete();
+        self.log_luminance.delete();
+    }
+}
+
+/// Gets the average value of the bins of a histogram over range without the low and high
+/// part of the counts
+fn histogram_average(counts: &[u32], range: (f32, f32), low: f32, high: f32) -> f32 {
+    let total: u32 = counts.iter().sum();
+    let mut skip_low = total as f32 * low.clamp(0.0, 1.0);
+    let mut keep = (total as f32 * (1.0 - high.clamp(0.0, 1.0)) - skip_low).max(0.0);
+    let step = (range.1 - range.0) / (counts.len().max(2) - 1) as f32;
+
+    let (mut sum, mut kept) = (0.0, 0.0);
+    for (bin, &count) in counts.iter().enumerate() {
+        let mut count = count as f32;
+        let skipped = count.min(skip_low);
+        skip_low -= skipped;
+        count = (count - skipped).min(keep);
+        keep -= count;
+        sum += count * (range.0 + bin as f32 * step);
+        kept += count;
+    }
+    if kept > 0.0 {
+        sum / kept
+    } else {
+        (range.0 + range.1) / 2.0
+    }
+}
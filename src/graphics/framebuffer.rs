@@ -0,0 +1,363 @@
+use super::{
+    recovery::ContextId,
+    texture::{Texture, TextureFilter, TextureSettings, TextureTarget, TextureWrap},
+    *,
+};
+
+/// The format of the color texture of a [Framebuffer]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorFormat {
+    /// 8 bit rgba, what the window has
+    #[default]
+    Rgba8,
+    /// 16 bit float rgba, for hdr colors
+    Rgba16F,
+    /// 32 bit float rgba
+    Rgba32F,
+    /// 16 bit float red and green, e.g. for velocities
+    Rg16F,
+    /// 8 bit red, e.g. for masks
+    R8,
+}
+
+impl ColorFormat {
+    /// Gets the internal format of the texture
+    pub fn internal_format(&self) -> u32 {
+        match self {
+            ColorFormat::Rgba8 => GL_RGBA8,
+            ColorFormat::Rgba16F => GL_RGBA16F,
+            ColorFormat::Rgba32F => GL_RGBA32F,
+            ColorFormat::Rg16F => GL_RG16F,
+            ColorFormat::R8 => GL_R8,
+        }
+    }
+
+    /// Gets the format and type of the pixels the texture is made with
+    pub(crate) fn pixel_format(&self) -> (u32, u32) {
+        match self {
+            ColorFormat::Rgba8 => (GL_RGBA, GL_UNSIGNED_BYTE),
+            ColorFormat::Rgba16F | ColorFormat::Rgba32F => (GL_RGBA, GL_FLOAT),
+            ColorFormat::Rg16F => (GL_RG, GL_FLOAT),
+            ColorFormat::R8 => (GL_RED, GL_UNSIGNED_BYTE),
+        }
+    }
+}
+
+/// A [Framebuffer object](https://www.khronos.org/opengl/wiki/Framebuffer_Object) used to render
+/// into a texture instead of the window
+///
+/// It has a color texture, RGBA8 unless made with [Framebuffer::with_format], and a depth
+/// and stencil renderbuffer, or a depth texture when made with
+/// [Framebuffer::with_depth_texture]. More color textures can be added with
+/// [Framebuffer::add_color] for shaders that write to several outputs
+///
+/// The framebuffer and its attachments are deleted when it is dropped, see [ContextId]
+///
+/// # Example
+/// ```
+/// let mut target = Framebuffer::new(800, 600)?;
+/// // layout (location = 1) out vec4 normal; in the fragment shader
+/// let normals = target.add_color(ColorFormat::Rgba16F)?;
+///
+/// target.bind();
+/// // draw the scene into target.color and target.extra_colors[normals - 1]
+/// Framebuffer::unbind();
+/// target.blit_to_window(800, 600, TextureFilter::Nearest);
+/// ```
+pub struct Framebuffer {
+    /// The framebuffer id
+    pub id: u32,
+    /// The color texture the framebuffer renders into
+    pub color: Texture,
+    /// The format of the color texture
+    pub format: ColorFormat,
+    /// The depth renderbuffer id, or the depth texture id if [Framebuffer::depth_texture]
+    pub depth: u32,
+    /// Whether depth is a texture that shaders can read instead of a renderbuffer
+    pub depth_texture: bool,
+    /// The color textures after the first one with their formats, see [Framebuffer::add_color]
+    pub extra_colors: Vec<(Texture, ColorFormat)>,
+    /// Width of the framebuffer(in pixels)
+    pub width: i32,
+    /// Height of the framebuffer(in pixels)
+    pub height: i32,
+    context: ContextId,
+}
+
+impl Framebuffer {
+    /// Creates a new framebuffer of size width by height
+    pub fn new(width: i32, height: i32) -> Result<Self, LighthouseError> {
+        Framebuffer::with_format(width, height, ColorFormat::Rgba8, false)
+    }
+
+    /// Creates a new framebuffer of size width by height whose depth can be read by shaders,
+    /// e.g. for depth of field
+    pub fn with_depth_texture(width: i32, height: i32) -> Result<Self, LighthouseError> {
+        Framebuffer::with_format(width, height, ColorFormat::Rgba8, true)
+    }
+
+    /// Creates a new framebuffer of size width by height with a color texture of format,
+    /// see [Framebuffer::with_depth_texture] for depth_texture
+    pub fn with_format(
+        width: i32,
+        height: i32,
+        format: ColorFormat,
+        depth_texture: bool,
+    ) -> Result<Self, LighthouseError> {
+        let mut id = 0;
+        let mut depth = 0;
+        unsafe {
+            glGenFramebuffers(1, &mut id);
+            if depth_texture {
+                glGenTextures(1, &mut depth);
+                glBindTexture(GL_TEXTURE_2D, depth);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_NEAREST as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_NEAREST as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
+            } else {
+                glGenRenderbuffers(1, &mut depth);
+            }
+        }
+        if id == 0 || depth == 0 {
+            return Err(LighthouseError::BufferAlloc("a framebuffer".to_string()));
+        }
+
+        let mut framebuffer = Framebuffer {
+            id,
+            color: color_texture(),
+            format,
+            depth,
+            depth_texture,
+            extra_colors: Vec::new(),
+            width: 0,
+            height: 0,
+            context: ContextId::current(),
+        };
+        framebuffer.resize(width, height)?;
+        Ok(framebuffer)
+    }
+
+    /// Adds another color texture of format, gives the location the fragment shader writes
+    /// to it with, the first one added is 1
+    ///
+    /// The content of the framebuffer is lost
+    pub fn add_color(&mut self, format: ColorFormat) -> Result<usize, LighthouseError> {
+        let mut max = 0;
+        unsafe { glGetIntegerv(GL_MAX_COLOR_ATTACHMENTS, &mut max) };
+        if self.extra_colors.len() + 1 >= max as usize {
+            return Err(LighthouseError::Unsupported(format!(
+                "A framebuffer can only have {} color textures",
+                max
+            )));
+        }
+        self.extra_colors.push((color_texture(), format));
+        self.resize(self.width, self.height)?;
+        Ok(self.extra_colors.len())
+    }
+
+    /// Reallocates the attachments of the framebuffer for the new size
+    ///
+    /// The content of the framebuffer is lost
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        self.width = width;
+        self.height = height;
+
+        let colors = std::iter::once((self.color.id, self.format)).chain(
+            self.extra_colors
+                .iter()
+                .map(|(texture, format)| (texture.id, *format)),
+        );
+        for (texture, format) in colors {
+            let (pixel_format, pixel_type) = format.pixel_format();
+            unsafe {
+                glBindTexture(GL_TEXTURE_2D, texture);
+                glTexImage2D(
+                    GL_TEXTURE_2D,
+                    0,
+                    format.internal_format() as i32,
+                    width,
+                    height,
+                    0,
+                    pixel_format,
+                    pixel_type,
+                    std::ptr::null(),
+                );
+            }
+        }
+
+        unsafe {
+            if self.depth_texture {
+                glBindTexture(GL_TEXTURE_2D, self.depth);
+                glTexImage2D(
+                    GL_TEXTURE_2D,
+                    0,
+                    GL_DEPTH24_STENCIL8 as i32,
+                    width,
+                    height,
+                    0,
+                    GL_DEPTH_STENCIL,
+                    GL_UNSIGNED_INT_24_8,
+                    std::ptr::null(),
+                );
+                glBindTexture(GL_TEXTURE_2D, 0);
+            } else {
+                glBindRenderbuffer(GL_RENDERBUFFER, self.depth);
+                glRenderbufferStorage(GL_RENDERBUFFER, GL_DEPTH24_STENCIL8, width, height);
+                glBindRenderbuffer(GL_RENDERBUFFER, 0);
+            }
+
+            glBindFramebuffer(GL_FRAMEBUFFER, self.id);
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_COLOR_ATTACHMENT0,
+                GL_TEXTURE_2D,
+                self.color.id,
+                0,
+            );
+            let mut draw_buffers = vec![GL_COLOR_ATTACHMENT0];
+            for (i, (texture, _)) in self.extra_colors.iter().enumerate() {
+                let attachment = GL_COLOR_ATTACHMENT0 + i as u32 + 1;
+                glFramebufferTexture2D(GL_FRAMEBUFFER, attachment, GL_TEXTURE_2D, texture.id, 0);
+                draw_buffers.push(attachment);
+            }
+            glDrawBuffers(draw_buffers.len() as i32, draw_buffers.as_ptr());
+            if self.depth_texture {
+                glFramebufferTexture2D(
+                    GL_FRAMEBUFFER,
+                    GL_DEPTH_STENCIL_ATTACHMENT,
+                    GL_TEXTURE_2D,
+                    self.depth,
+                    0,
+                );
+            } else {
+                glFramebufferRenderbuffer(
+                    GL_FRAMEBUFFER,
+                    GL_DEPTH_STENCIL_ATTACHMENT,
+                    GL_RENDERBUFFER,
+                    self.depth,
+                );
+            }
+            let status = glCheckFramebufferStatus(GL_FRAMEBUFFER);
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+
+            if status != GL_FRAMEBUFFER_COMPLETE {
+                return Err(LighthouseError::IncompleteFramebuffer(
+                    "Framebuffer".to_string(),
+                    status,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the framebuffer and its textures again with the same size and formats, e.g.
+    /// after the context was lost and they are gone
+    ///
+    /// The color textures get the default settings again
+    pub fn recreate(&mut self) -> Result<(), LighthouseError> {
+        let mut framebuffer =
+            Framebuffer::with_format(self.width, self.height, self.format, self.depth_texture)?;
+        for (_, format) in &self.extra_colors {
+            framebuffer.add_color(*format)?;
+        }
+        // the old one only deletes its objects if its context is still current
+        *self = framebuffer;
+        Ok(())
+    }
+
+    /// Binds the framebuffer and sets the viewport to cover all of it
+    pub fn bind(&self) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, self.id);
+            glViewport(0, 0, self.width, self.height);
+        }
+    }
+
+    /// Binds the window as the framebuffer again
+    pub fn unbind() {
+        unsafe { glBindFramebuffer(GL_FRAMEBUFFER, 0) }
+    }
+
+    /// Copies the first color texture into target, it is stretched if they aren't the same
+    /// size
+    pub fn blit(&self, target: &Framebuffer, filter: TextureFilter) {
+        self.blit_into(
+            target.id,
+            target.width,
+            target.height,
+            GL_COLOR_BUFFER_BIT,
+            filter,
+        );
+    }
+
+    /// Copies the depth and stencil into target, they have to be the same size
+    ///
+    /// Useful to draw more on top of a scene that was rendered somewhere else
+    pub fn blit_depth(&self, target: &Framebuffer) {
+        self.blit_into(
+            target.id,
+            target.width,
+            target.height,
+            GL_DEPTH_BUFFER_BIT | GL_STENCIL_BUFFER_BIT,
+            TextureFilter::Nearest,
+        );
+    }
+
+    /// Copies the first color texture to the window of size width by height(in pixels),
+    /// it is stretched over all of it
+    pub fn blit_to_window(&self, width: i32, height: i32, filter: TextureFilter) {
+        self.blit_into(0, width, height, GL_COLOR_BUFFER_BIT, filter);
+    }
+
+    fn blit_into(&self, target: u32, width: i32, height: i32, mask: u32, filter: TextureFilter) {
+        unsafe {
+            glBindFramebuffer(GL_READ_FRAMEBUFFER, self.id);
+            glReadBuffer(GL_COLOR_ATTACHMENT0);
+            glBindFramebuffer(GL_DRAW_FRAMEBUFFER, target);
+            glBlitFramebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                width,
+                height,
+                mask,
+                filter as u32,
+            );
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Deletes the framebuffer and its attachments now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe {
+                if self.depth_texture {
+                    glDeleteTextures(1, &self.depth);
+                } else {
+                    glDeleteRenderbuffers(1, &self.depth);
+                }
+                glDeleteFramebuffers(1, &self.id);
+            }
+        }
+    }
+}
+
+/// Creates a linearly filtered color texture that is clamped to its edges
+fn color_texture() -> Texture {
+    let mut color = Texture::new();
+    color.bind(TextureTarget::Texture2D);
+    TextureSettings::default()
+        .filter(TextureFilter::Linear, TextureFilter::Linear)
+        .wrap_s(TextureWrap::ClampToEdge)
+        .wrap_t(TextureWrap::ClampToEdge)
+        .apply(&mut color);
+    color
+}
@@ -0,0 +1,83 @@
+use super::{shader::*, texture::Texture, uniform::Uniform, vertex::VertexArray, *};
+
+/// The vertex shader used by every [FullscreenPass]
+///
+/// It makes a triangle that covers the whole screen without any vertex buffer and gives
+/// the fragment shader `uv`, the position on the screen from 0 to 1
+pub const FULLSCREEN_VERT: &str = "#version 330 core
+out vec2 uv;
+
+void main() {
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    uv = pos;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}";
+
+/// Runs a fragment shader over every pixel of the bound framebuffer
+///
+/// The fragment shader gets `in vec2 uv` and the input texture as `uniform sampler2D input_texture`
+///
+/// # Example
+/// ```
+/// let invert = FullscreenPass::new("#version 330 core
+/// in vec2 uv;
+/// uniform sampler2D input_texture;
+/// out vec4 color;
+///
+/// void main() {
+///     color = vec4(1.0 - texture(input_texture, uv).rgb, 1.0);
+/// }")?;
+///
+/// invert.draw(&target.color);
+/// ```
+pub struct FullscreenPass {
+    /// The shader program of the pass
+    pub program: ShaderProgram,
+    /// Empty VAO, opengl needs one bound to draw
+    vao: VertexArray,
+}
+
+impl FullscreenPass {
+    /// Creates a new pass from the source of a fragment shader
+    pub fn new(frag: &str) -> Result<Self, LighthouseError> {
+        Ok(FullscreenPass {
+            program: ShaderProgram::from_vert_frag(FULLSCREEN_VERT, frag)?,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+        })
+    }
+
+    /// Gets a uniform of the pass, so it can be set before drawing
+    pub fn uniform(&self, name: &str) -> Uniform {
+        self.program.use_program();
+        Uniform::new(&self.program, name)
+    }
+
+    /// Draws the pass into the bound framebuffer, input is bound to texture unit 0
+    ///
+    /// Depth testing is turned off while drawing
+    pub fn draw(&self, input: &Texture) {
+        self.program.use_program();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+            glBindTexture(GL_TEXTURE_2D, input.id);
+        }
+        Uniform::new(&self.program, "input_texture").set_uniform_i(&[0]);
+
+        self.vao.bind();
+        unsafe {
+            let depth_test = glIsEnabled(GL_DEPTH_TEST) == GL_TRUE;
+            glDisable(GL_DEPTH_TEST);
+            glDrawArrays(GL_TRIANGLES, 0, 3);
+            if depth_test {
+                glEnable(GL_DEPTH_TEST);
+            }
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Deletes the shader program of the pass
+    pub fn delete(self) {
+        self.program.delete();
+    }
+}
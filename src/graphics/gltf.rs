@@ -0,0 +1,744 @@
+use std::{fs, path::Path};
+
+use image::DynamicImage;
+use nalgebra_glm::{
+    mat3_to_quat, quat, quat_angle, quat_axis, quat_to_mat4, scaling, translation, vec2, vec3,
+    vec4, Mat3, Mat4, Quat, Vec3, Vec4,
+};
+use specs::{Builder, Entity, WorldExt};
+
+use super::{
+    model::{calculate_normals, ModelError, ModelVertex},
+    LighthouseError,
+};
+use crate::{
+    assets::json::Json,
+    ECS::mesh::{Mesh, Position, Rotation, SubMesh},
+};
+
+/// The magic at the start of a .glb file
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+/// The type of the JSON chunk of a .glb file
+const GLB_JSON: u32 = 0x4E4F534A;
+/// The type of the binary chunk of a .glb file
+const GLB_BIN: u32 = 0x004E4942;
+/// The mode of a primitive made of triangles, the only one that is supported
+const MODE_TRIANGLES: usize = 4;
+
+/// A material of a glTF scene, only the metallic roughness values lighthouse can use
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    /// The name of the material, if it has one
+    pub name: Option<String>,
+    /// The color the base color image is multiplied with(rgba)
+    pub base_color: Vec4,
+    /// The index of the base color image in [GltfScene::images]
+    pub base_color_image: Option<usize>,
+    /// How metallic the material is, from 0 to 1
+    pub metallic: f32,
+    /// How rough the material is, from 0 to 1
+    pub roughness: f32,
+    /// The index of the normal map in [GltfScene::images]
+    pub normal_image: Option<usize>,
+    /// The color the material glows with
+    pub emissive: Vec3,
+}
+
+impl Default for GltfMaterial {
+    /// The material glTF uses for primitives without one
+    fn default() -> Self {
+        GltfMaterial {
+            name: None,
+            base_color: vec4(1.0, 1.0, 1.0, 1.0),
+            base_color_image: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            normal_image: None,
+            emissive: vec3(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// A mesh of a glTF scene, every primitive of it is a [SubMesh] with its own material
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMesh {
+    /// The name of the mesh, if it has one
+    pub name: Option<String>,
+    /// The vertices of every primitive
+    pub vertices: Vec<ModelVertex>,
+    /// The triangles of every primitive
+    pub indices: Vec<[u32; 3]>,
+    /// One sub mesh per primitive, the material is an index into [GltfScene::materials]
+    pub sub_meshes: Vec<SubMesh>,
+}
+
+impl GltfMesh {
+    /// Makes a mesh out of it with the vertices scaled by scale, since the [Position] and
+    /// [Rotation] components can't scale
+    pub fn to_mesh(&self, scale: Vec3) -> Result<Mesh<ModelVertex>, LighthouseError> {
+        let vertices = self
+            .vertices
+            .iter()
+            .map(|vertex| {
+                // normals are scaled the other way so they stay at a right angle to the surface
+                let normal = vertex.normal.component_div(&scale);
+                ModelVertex {
+                    pos: vertex.pos.component_mul(&scale),
+                    uv: vertex.uv,
+                    normal: if normal.norm_squared() > 0.0 {
+                        normal.normalize()
+                    } else {
+                        normal
+                    },
+                }
+            })
+            .collect();
+        Mesh::from_vertices(vertices, self.indices.clone()).with_sub_meshes(self.sub_meshes.clone())
+    }
+}
+
+/// A node of a glTF scene, its transform is relative to its parent
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfNode {
+    /// The name of the node, if it has one
+    pub name: Option<String>,
+    /// The index of its mesh in [GltfScene::meshes]
+    pub mesh: Option<usize>,
+    /// The indices of its children in [GltfScene::nodes]
+    pub children: Vec<usize>,
+    /// Where the node is
+    pub translation: Vec3,
+    /// How the node is rotated
+    pub rotation: Quat,
+    /// How the node is scaled
+    pub scale: Vec3,
+}
+
+impl GltfNode {
+    /// Gets the matrix of the node relative to its parent, it scales, then rotates and
+    /// then moves
+    pub fn local_matrix(&self) -> Mat4 {
+        translation(&self.translation) * quat_to_mat4(&self.rotation) * scaling(&self.scale)
+    }
+}
+
+/// A scene loaded from a glTF 2.0 file, either a `.gltf` with its buffers and images next
+/// to it or in data uris, or a `.glb`
+///
+/// The meshes, materials, images and the node tree are read, animations, skins and
+/// cameras are skipped. The uvs are flipped so the images can be made into
+/// [Texture](super::texture::Texture)s like any other image
+///
+/// [GltfScene::spawn] makes every node an entity of the ecs, so the scene can be updated by
+/// the mesh systems, see [impl_update_mesh](crate::impl_update_mesh)
+///
+/// # Example
+/// ```
+/// let scene = GltfScene::load("models/house.glb")?;
+/// let textures = scene
+///     .images
+///     .iter()
+///     .map(|image| Texture::builder(image.clone()).build())
+///     .collect::<Result<Vec<_>, _>>()?;
+/// let entities = scene.spawn(&mut world.ecs)?;
+///
+/// // draw a mesh of the scene with the base color of its materials
+/// mesh.draw_with_materials(|material| {
+///     if let Some(image) = scene.materials[material].base_color_image {
+///         textures[image].bind(TextureTarget::Texture2D);
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct GltfScene {
+    /// Every mesh of the file
+    pub meshes: Vec<GltfMesh>,
+    /// Every material of the file, if a primitive has no material the last one is the
+    /// [default](GltfMaterial::default) one
+    pub materials: Vec<GltfMaterial>,
+    /// Every image of the file
+    pub images: Vec<DynamicImage>,
+    /// Every node of the file
+    pub nodes: Vec<GltfNode>,
+    /// The nodes at the top of the scene
+    pub roots: Vec<usize>,
+}
+
+/// Makes the error of a malformed glTF file
+fn invalid(message: impl Into<String>) -> LighthouseError {
+    ModelError::Invalid(message.into()).into()
+}
+
+/// Gets the elements of the array at key, an empty slice if there is none
+fn array<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+    json.get(key).and_then(Json::as_array).unwrap_or(&[])
+}
+
+/// Gets the index at key
+fn get_index(json: &Json, key: &str) -> Option<usize> {
+    json.get(key).and_then(Json::as_usize)
+}
+
+/// Gets the numbers of the array at key, there have to be N of them
+fn floats<const N: usize>(json: &Json, key: &str) -> Result<Option<[f32; N]>, LighthouseError> {
+    let Some(values) = json.get(key) else {
+        return Ok(None);
+    };
+    let values = values
+        .as_array()
+        .filter(|values| values.len() == N)
+        .ok_or_else(|| invalid(format!("{} has to be {} numbers", key, N)))?;
+    let mut out = [0.0; N];
+    for (out, value) in out.iter_mut().zip(values) {
+        *out = value
+            .as_f64()
+            .ok_or_else(|| invalid(format!("{} has to be {} numbers", key, N)))?
+            as f32;
+    }
+    Ok(Some(out))
+}
+
+/// Decodes base64 text, the padding is optional
+fn decode_base64(text: &str) -> Result<Vec<u8>, LighthouseError> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in text.bytes().filter(|c| *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(invalid("Invalid base64 in a data uri")),
+        };
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Reads what uri points to, a data uri or a file relative to base
+fn read_uri(uri: &str, base: Option<&Path>) -> Result<Vec<u8>, LighthouseError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, data) = data
+            .split_once(";base64,")
+            .ok_or_else(|| invalid("Only base64 data uris are supported"))?;
+        return decode_base64(data);
+    }
+    let path = base.map_or_else(|| Path::new(uri).to_path_buf(), |base| base.join(uri));
+    fs::read(&path).map_err(|e| LighthouseError::Io(path, e))
+}
+
+/// Splits a .glb file into its JSON and binary chunk
+fn split_glb(bytes: &[u8]) -> Result<(&str, Option<&[u8]>), LighthouseError> {
+    let read_u32 = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or_else(|| invalid("The .glb file is cut off"))
+    };
+    if read_u32(4)? != 2 {
+        return Err(LighthouseError::Unsupported(
+            "Only glTF 2.0 is supported".to_string(),
+        ));
+    }
+    let (mut json, mut bin) = (None, None);
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let len = read_u32(offset)? as usize;
+        let ty = read_u32(offset + 4)?;
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + len)
+            .ok_or_else(|| invalid("A chunk of the .glb file is cut off"))?;
+        match ty {
+            GLB_JSON => {
+                json =
+                    Some(std::str::from_utf8(chunk).map_err(|_| invalid("The JSON isn't utf-8"))?)
+            }
+            GLB_BIN => bin = Some(chunk),
+            _ => {}
+        }
+        // chunks are padded to 4 bytes
+        offset += 8 + len.div_ceil(4) * 4;
+    }
+    Ok((
+        json.ok_or_else(|| invalid("The .glb file has no JSON"))?,
+        bin,
+    ))
+}
+
+/// The parsed JSON of a file with its buffers, for reading the accessors
+struct Document<'a> {
+    json: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Document<'_> {
+    /// Gets the bytes of a buffer view and its stride, if it has one
+    fn view(&self, index: usize) -> Result<(&[u8], Option<usize>), LighthouseError> {
+        let view = self
+            .json
+            .get("bufferViews")
+            .and_then(|views| views.at(index))
+            .ok_or_else(|| invalid(format!("There is no buffer view {}", index)))?;
+        let buffer = get_index(view, "buffer")
+            .and_then(|buffer| self.buffers.get(buffer))
+            .ok_or_else(|| invalid(format!("Buffer view {} has no buffer", index)))?;
+        let offset = get_index(view, "byteOffset").unwrap_or(0);
+        let len = get_index(view, "byteLength")
+            .ok_or_else(|| invalid(format!("Buffer view {} has no length", index)))?;
+        let bytes = offset
+            .checked_add(len)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| {
+                LighthouseError::Parse(format!("Buffer view {} is out of its buffer", index))
+            })?;
+        Ok((bytes, get_index(view, "byteStride")))
+    }
+
+    /// Reads every component of an accessor, gives them with how many components an
+    /// element has
+    ///
+    /// Normalized integers are turned into 0 to 1 or -1 to 1
+    fn read(&self, index: usize) -> Result<(Vec<f64>, usize), LighthouseError> {
+        let accessor = self
+            .json
+            .get("accessors")
+            .and_then(|accessors| accessors.at(index))
+            .ok_or_else(|| invalid(format!("There is no accessor {}", index)))?;
+        if accessor.get("sparse").is_some() {
+            return Err(LighthouseError::Unsupported(
+                "Sparse accessors aren't supported".to_string(),
+            ));
+        }
+        let count = get_index(accessor, "count")
+            .ok_or_else(|| invalid(format!("Accessor {} has no count", index)))?;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            _ => return Err(invalid(format!("Accessor {} has an unknown type", index))),
+        };
+        let component_type = get_index(accessor, "componentType").unwrap_or(0);
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => {
+                return Err(invalid(format!(
+                    "Accessor {} has an unknown component type",
+                    index
+                )))
+            }
+        };
+        let too_big = || LighthouseError::Parse(format!("Accessor {} is too big", index));
+        let total = count.checked_mul(components).ok_or_else(too_big)?;
+        // an accessor without a view is all zeros
+        let Some(view) = get_index(accessor, "bufferView") else {
+            return Ok((vec![0.0; total], components));
+        };
+        let (bytes, stride) = self.view(view)?;
+        let offset = get_index(accessor, "byteOffset").unwrap_or(0);
+        let stride = stride.unwrap_or(components * size);
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        // the last element has to be in the view before anything is allocated, then the
+        // elements before it are too
+        if count > 0 {
+            let end = (count - 1)
+                .checked_mul(stride)
+                .and_then(|last| last.checked_add(offset))
+                .and_then(|last| last.checked_add(components * size))
+                .ok_or_else(too_big)?;
+            if end > bytes.len() {
+                return Err(LighthouseError::Parse(format!(
+                    "Accessor {} is out of its view",
+                    index
+                )));
+            }
+        }
+
+        let mut out = Vec::with_capacity(total);
+        for element in 0..count {
+            for component in 0..components {
+                let start = offset + element * stride + component * size;
+                let b = bytes
+                    .get(start..start + size)
+                    .ok_or_else(|| invalid(format!("Accessor {} is out of its view", index)))?;
+                let value = match component_type {
+                    5120 => (b[0] as i8 as f64, 127.0),
+                    5121 => (b[0] as f64, 255.0),
+                    5122 => (i16::from_le_bytes([b[0], b[1]]) as f64, 32767.0),
+                    5123 => (u16::from_le_bytes([b[0], b[1]]) as f64, 65535.0),
+                    5125 => (u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64, 1.0),
+                    _ => (f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64, 1.0),
+                };
+                out.push(match normalized {
+                    true => (value.0 / value.1).max(-1.0),
+                    false => value.0,
+                });
+            }
+        }
+        Ok((out, components))
+    }
+
+    /// Reads an accessor that has to have components components per element
+    fn read_vecs(
+        &self,
+        index: usize,
+        components: usize,
+        what: &str,
+    ) -> Result<Vec<f64>, LighthouseError> {
+        let (values, found) = self.read(index)?;
+        if found != components {
+            return Err(invalid(format!(
+                "{} has to have {} components",
+                what, components
+            )));
+        }
+        Ok(values)
+    }
+}
+
+/// Splits a matrix into its translation, rotation and scale, shearing is lost
+fn decompose(matrix: &Mat4) -> (Vec3, Quat, Vec3) {
+    let column = |i: usize| matrix.column(i).xyz();
+    let scale = vec3(column(0).norm(), column(1).norm(), column(2).norm());
+    let axis = |i: usize| match scale[i] {
+        s if s > 0.0 => column(i) / s,
+        _ => column(i),
+    };
+    let rotation = Mat3::from_columns(&[axis(0), axis(1), axis(2)]);
+    (column(3), mat3_to_quat(&rotation), scale)
+}
+
+/// Turns a rotation into the axis and angle of a [Rotation]
+fn axis_angle(rotation: &Quat) -> Vec4 {
+    let angle = quat_angle(rotation);
+    if angle.abs() < 1e-6 {
+        return vec4(0.0, 1.0, 0.0, 0.0);
+    }
+    let axis = quat_axis(rotation);
+    vec4(axis.x, axis.y, axis.z, angle)
+}
+
+impl GltfScene {
+    /// Loads the `.gltf` or `.glb` file at path, the files it points to are read relative
+    /// to it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LighthouseError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| LighthouseError::Io(path.to_path_buf(), e))?;
+        GltfScene::from_bytes(&bytes, path.parent())
+    }
+
+    /// Parses the bytes of a `.gltf` or `.glb` file, the files it points to are read
+    /// relative to base
+    pub fn from_bytes(bytes: &[u8], base: Option<&Path>) -> Result<Self, LighthouseError> {
+        let (text, bin) = match bytes.starts_with(GLB_MAGIC) {
+            true => split_glb(bytes)?,
+            false => (
+                std::str::from_utf8(bytes).map_err(|_| invalid("The file isn't utf-8"))?,
+                None,
+            ),
+        };
+        let json = Json::parse(text)?;
+        if let Some(version) = json
+            .get("asset")
+            .and_then(|asset| asset.get("version"))
+            .and_then(Json::as_str)
+        {
+            if !version.starts_with("2.") {
+                return Err(LighthouseError::Unsupported(format!(
+                    "glTF {} isn't supported, only 2.0 is",
+                    version
+                )));
+            }
+        }
+
+        let mut buffers = Vec::new();
+        for (i, buffer) in array(&json, "buffers").iter().enumerate() {
+            let data = match (buffer.get("uri").and_then(Json::as_str), bin) {
+                (Some(uri), _) => read_uri(uri, base)?,
+                (None, Some(bin)) if i == 0 => bin.to_vec(),
+                _ => return Err(invalid(format!("Buffer {} has no data", i))),
+            };
+            buffers.push(data);
+        }
+        let document = Document {
+            json: &json,
+            buffers,
+        };
+
+        let images = array(&json, "images")
+            .iter()
+            .enumerate()
+            .map(|(i, image)| {
+                let bytes = match (
+                    image.get("uri").and_then(Json::as_str),
+                    get_index(image, "bufferView"),
+                ) {
+                    (Some(uri), _) => read_uri(uri, base)?,
+                    (None, Some(view)) => document.view(view)?.0.to_vec(),
+                    _ => return Err(invalid(format!("Image {} has no data", i))),
+                };
+                image::load_from_memory(&bytes)
+                    .map_err(|e| invalid(format!("Couldn't decode image {}: {}", i, e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // materials point to textures, which point to the images
+        let textures = array(&json, "textures");
+        let image_of = |info: Option<&Json>| {
+            info.and_then(|info| get_index(info, "index"))
+                .and_then(|texture| textures.get(texture))
+                .and_then(|texture| get_index(texture, "source"))
+        };
+        let mut materials = array(&json, "materials")
+            .iter()
+            .map(|material| {
+                let default = GltfMaterial::default();
+                let pbr = material.get("pbrMetallicRoughness");
+                let factor = |key: &str, default: f32| {
+                    pbr.and_then(|pbr| pbr.get(key))
+                        .and_then(Json::as_f64)
+                        .map_or(default, |value| value as f32)
+                };
+                Ok(GltfMaterial {
+                    name: material
+                        .get("name")
+                        .and_then(Json::as_str)
+                        .map(String::from),
+                    base_color: match pbr {
+                        Some(pbr) => floats::<4>(pbr, "baseColorFactor")?
+                            .map_or(default.base_color, Vec4::from),
+                        None => default.base_color,
+                    },
+                    base_color_image: image_of(pbr.and_then(|pbr| pbr.get("baseColorTexture"))),
+                    metallic: factor("metallicFactor", default.metallic),
+                    roughness: factor("roughnessFactor", default.roughness),
+                    normal_image: image_of(material.get("normalTexture")),
+                    emissive: floats::<3>(material, "emissiveFactor")?
+                        .map_or(default.emissive, Vec3::from),
+                })
+            })
+            .collect::<Result<Vec<_>, LighthouseError>>()?;
+
+        let default_material = materials.len();
+        let mut uses_default = false;
+        let mut meshes = Vec::new();
+        for (i, mesh) in array(&json, "meshes").iter().enumerate() {
+            let mut out = GltfMesh {
+                name: mesh.get("name").and_then(Json::as_str).map(String::from),
+                vertices: Vec::new(),
+                indices: Vec::new(),
+                sub_meshes: Vec::new(),
+            };
+            for primitive in array(mesh, "primitives") {
+                if get_index(primitive, "mode").unwrap_or(MODE_TRIANGLES) != MODE_TRIANGLES {
+                    return Err(LighthouseError::Unsupported(format!(
+                        "Mesh {} has a primitive that isn't made of triangles",
+                        i
+                    )));
+                }
+                let attributes = primitive.get("attributes").ok_or_else(|| {
+                    invalid(format!("A primitive of mesh {} has no attributes", i))
+                })?;
+                let positions = get_index(attributes, "POSITION").ok_or_else(|| {
+                    invalid(format!("A primitive of mesh {} has no positions", i))
+                })?;
+                let positions = document.read_vecs(positions, 3, "POSITION")?;
+                let count = positions.len() / 3;
+                let normals = match get_index(attributes, "NORMAL") {
+                    Some(normals) => Some(document.read_vecs(normals, 3, "NORMAL")?),
+                    None => None,
+                };
+                let uvs = match get_index(attributes, "TEXCOORD_0") {
+                    Some(uvs) => Some(document.read_vecs(uvs, 2, "TEXCOORD_0")?),
+                    None => None,
+                };
+
+                let first_vertex = out.vertices.len() as u32;
+                let mut vertices = (0..count)
+                    .map(|v| ModelVertex {
+                        pos: vec3(
+                            positions[v * 3] as f32,
+                            positions[v * 3 + 1] as f32,
+                            positions[v * 3 + 2] as f32,
+                        ),
+                        // glTF uvs start at the top of the image, lighthouse ones at the bottom
+                        uv: uvs
+                            .as_ref()
+                            .and_then(|uvs| uvs.get(v * 2..v * 2 + 2))
+                            .map_or(vec2(0.0, 0.0), |uv| vec2(uv[0] as f32, 1.0 - uv[1] as f32)),
+                        normal: normals
+                            .as_ref()
+                            .and_then(|normals| normals.get(v * 3..v * 3 + 3))
+                            .map_or(vec3(0.0, 0.0, 0.0), |n| {
+                                vec3(n[0] as f32, n[1] as f32, n[2] as f32)
+                            }),
+                    })
+                    .collect::<Vec<_>>();
+                let indices = match get_index(primitive, "indices") {
+                    Some(indices) => document.read_vecs(indices, 1, "indices")?,
+                    None => (0..count).map(|v| v as f64).collect(),
+                };
+                if indices.iter().any(|v| *v as usize >= count) {
+                    return Err(invalid(format!(
+                        "A primitive of mesh {} has an index out of range",
+                        i
+                    )));
+                }
+                let triangles = indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0] as u32, t[1] as u32, t[2] as u32])
+                    .collect::<Vec<_>>();
+                if normals.is_none() {
+                    calculate_normals(&mut vertices, &triangles);
+                }
+
+                let material = get_index(primitive, "material").unwrap_or_else(|| {
+                    uses_default = true;
+                    default_material
+                });
+                out.sub_meshes.push(SubMesh {
+                    first: out.indices.len() * 3,
+                    count: triangles.len() * 3,
+                    base_vertex: 0,
+                    material,
+                });
+                out.vertices.extend(vertices);
+                out.indices.extend(
+                    triangles
+                        .iter()
+                        .map(|triangle| triangle.map(|v| v + first_vertex)),
+                );
+            }
+            if out.vertices.is_empty() {
+                return Err(invalid(format!("Mesh {} has no vertices", i)));
+            }
+            meshes.push(out);
+        }
+        if uses_default {
+            materials.push(GltfMaterial::default());
+        }
+
+        let nodes = array(&json, "nodes")
+            .iter()
+            .map(|node| {
+                let children = array(node, "children")
+                    .iter()
+                    .map(|child| {
+                        child
+                            .as_usize()
+                            .filter(|child| *child < array(&json, "nodes").len())
+                            .ok_or_else(|| invalid("A node has a child that doesn't exist"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mesh = get_index(node, "mesh");
+                if mesh.is_some_and(|mesh| mesh >= meshes.len()) {
+                    return Err(invalid("A node has a mesh that doesn't exist"));
+                }
+                let (translation, rotation, scale) = match floats::<16>(node, "matrix")? {
+                    Some(matrix) => decompose(&Mat4::from_column_slice(&matrix)),
+                    None => (
+                        floats::<3>(node, "translation")?.map_or(vec3(0.0, 0.0, 0.0), Vec3::from),
+                        floats::<4>(node, "rotation")?
+                            .map_or(quat(0.0, 0.0, 0.0, 1.0), |[x, y, z, w]| quat(x, y, z, w)),
+                        floats::<3>(node, "scale")?.map_or(vec3(1.0, 1.0, 1.0), Vec3::from),
+                    ),
+                };
+                Ok(GltfNode {
+                    name: node.get("name").and_then(Json::as_str).map(String::from),
+                    mesh,
+                    children,
+                    translation,
+                    rotation,
+                    scale,
+                })
+            })
+            .collect::<Result<Vec<_>, LighthouseError>>()?;
+
+        let scene = get_index(&json, "scene")
+            .unwrap_or(0)
+            .min(array(&json, "scenes").len().saturating_sub(1));
+        let roots = match array(&json, "scenes").get(scene) {
+            Some(scene) => array(scene, "nodes")
+                .iter()
+                .filter_map(Json::as_usize)
+                .filter(|root| *root < nodes.len())
+                .collect(),
+            // without a scene every node that isn't a child is a root
+            None => (0..nodes.len())
+                .filter(|i| !nodes.iter().any(|node| node.children.contains(i)))
+                .collect(),
+        };
+
+        Ok(GltfScene {
+            meshes,
+            materials,
+            images,
+            nodes,
+            roots,
+        })
+    }
+
+    /// Finds the first node called name
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.name.as_deref() == Some(name))
+    }
+
+    /// Makes every node of the scene an entity of ecs, with a [Position] and [Rotation] of
+    /// its transform in the world and a [Mesh] if it has one
+    ///
+    /// Gives the entity of every node, nodes that aren't in the scene have none. The scale
+    /// of a node is baked into its mesh, the meshes still have to be set up and uploaded,
+    /// see [impl_setup_mesh](crate::impl_setup_mesh)
+    pub fn spawn(&self, ecs: &mut specs::World) -> Result<Vec<Option<Entity>>, LighthouseError> {
+        ecs.register::<Position>();
+        ecs.register::<Rotation>();
+        ecs.register::<Mesh<ModelVertex>>();
+
+        let mut entities = vec![None; self.nodes.len()];
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|root| (*root, Mat4::identity()))
+            .collect::<Vec<_>>();
+        while let Some((index, parent)) = stack.pop() {
+            // a node can only be in the tree once
+            if entities[index].is_some() {
+                continue;
+            }
+            let node = &self.nodes[index];
+            let world = parent * node.local_matrix();
+            let (pos, rot, scale) = decompose(&world);
+            let mesh = match node.mesh {
+                Some(mesh) => Some(self.meshes[mesh].to_mesh(scale)?),
+                None => None,
+            };
+
+            let mut entity = ecs
+                .create_entity()
+                .with(Position(pos))
+                .with(Rotation(axis_angle(&rot)));
+            if let Some(mesh) = mesh {
+                entity = entity.with(mesh);
+            }
+            entities[index] = Some(entity.build());
+            stack.extend(node.children.iter().rev().map(|child| (*child, world)));
+        }
+        Ok(entities)
+    }
+}
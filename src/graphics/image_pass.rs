@@ -0,0 +1,585 @@
+use super::{
+    framebuffer::ColorFormat,
+    fullscreen::FULLSCREEN_VERT,
+    recovery::ContextId,
+    render_state::RenderState,
+    shader::ShaderProgram,
+    texture::{Texture, TextureFilter, TextureSettings, TextureTarget, TextureWrap},
+    uniform::{Uniform, UniformValue},
+    vertex::VertexArray,
+    *,
+};
+
+const AVERAGE_FRAG: &str = "#version 330 core
+uniform sampler2D input_texture;
+out vec4 color;
+
+void main() {
+    ivec2 size = textureSize(input_texture, 0);
+    ivec2 pos = ivec2(gl_FragCoord.xy) * 2;
+    vec4 sum = vec4(0.0);
+    for (int i = 0; i < 4; i++) {
+        sum += texelFetch(input_texture, min(pos + ivec2(i & 1, i >> 1), size - 1), 0);
+    }
+    color = sum / 4.0;
+}";
+
+const MIN_FRAG: &str = "#version 330 core
+uniform sampler2D input_texture;
+out vec4 color;
+
+void main() {
+    ivec2 size = textureSize(input_texture, 0);
+    ivec2 pos = ivec2(gl_FragCoord.xy) * 2;
+    color = texelFetch(input_texture, pos, 0);
+    for (int i = 1; i < 4; i++) {
+        color = min(color, texelFetch(input_texture, min(pos + ivec2(i & 1, i >> 1), size - 1), 0));
+    }
+}";
+
+const MAX_FRAG: &str = "#version 330 core
+uniform sampler2D input_texture;
+out vec4 color;
+
+void main() {
+    ivec2 size = textureSize(input_texture, 0);
+    ivec2 pos = ivec2(gl_FragCoord.xy) * 2;
+    color = texelFetch(input_texture, pos, 0);
+    for (int i = 1; i < 4; i++) {
+        color = max(color, texelFetch(input_texture, min(pos + ivec2(i & 1, i >> 1), size - 1), 0));
+    }
+}";
+
+const HISTOGRAM_VERT: &str = "#version 330 core
+uniform sampler2D input_texture;
+uniform vec4 weights;
+uniform vec2 range;
+uniform int bins;
+
+void main() {
+    ivec2 size = textureSize(input_texture, 0);
+    vec4 texel = texelFetch(input_texture, ivec2(gl_VertexID % size.x, gl_VertexID / size.x), 0);
+    float value = (dot(texel, weights) - range.x) / (range.y - range.x);
+    float bin = floor(clamp(value, 0.0, 1.0) * float(bins - 1) + 0.5);
+    gl_Position = vec4((bin + 0.5) / float(bins) * 2.0 - 1.0, 0.0, 0.0, 1.0);
+}";
+
+const HISTOGRAM_FRAG: &str = "#version 330 core
+out vec4 color;
+
+void main() {
+    color = vec4(1.0);
+}";
+
+/// Runs a fragment shader over an input texture into an output texture, to process images
+/// on the gpu without compute shaders
+///
+/// The fragment shader gets the same inputs as a [FullscreenPass](super::fullscreen::FullscreenPass),
+/// `in vec2 uv` and `uniform sampler2D input_texture`, and `uniform vec2 texel_size`, the
+/// size of a pixel of the input in uv. Chains of passes are run with a [PingPong], e.g. a
+/// blur that is run many times. The framebuffer is deleted when it is dropped, see
+/// [ContextId]
+///
+/// # Example
+/// ```
+/// let mut blur = ImagePass::new("#version 330 core
+/// in vec2 uv;
+/// uniform sampler2D input_texture;
+/// uniform vec2 texel_size;
+/// uniform vec2 direction;
+/// out vec4 color;
+///
+/// void main() {
+///     color = (texture(input_texture, uv - direction * texel_size)
+///         + texture(input_texture, uv) * 2.0
+///         + texture(input_texture, uv + direction * texel_size)) / 4.0;
+/// }")?
+/// .uniform("direction", vec2(1.0, 0.0));
+///
+/// let mut targets = PingPong::new(512, 512, ColorFormat::Rgba8)?;
+/// targets.apply_from(&blur, &image)?;
+/// for _ in 0..4 {
+///     blur.set("direction", vec2(0.0, 1.0));
+///     targets.apply(&blur)?;
+///     blur.set("direction", vec2(1.0, 0.0));
+///     targets.apply(&blur)?;
+/// }
+/// let blurred = targets.read();
+/// ```
+pub struct ImagePass {
+    /// The shader program of the pass
+    pub program: ShaderProgram,
+    /// The uniforms set every time the pass is run
+    pub uniforms: Vec<(String, UniformValue)>,
+    /// The framebuffer the output is attached to while it is drawn
+    fbo: u32,
+    /// Empty VAO, opengl needs one bound to draw
+    vao: VertexArray,
+    context: ContextId,
+}
+
+impl ImagePass {
+    /// Creates a new pass from the source of a fragment shader
+    pub fn new(frag: &str) -> Result<Self, LighthouseError> {
+        let mut fbo = 0;
+        unsafe { glGenFramebuffers(1, &mut fbo) };
+        if fbo == 0 {
+            return Err(LighthouseError::BufferAlloc("a framebuffer".to_string()));
+        }
+        Ok(ImagePass {
+            program: ShaderProgram::from_vert_frag(FULLSCREEN_VERT, frag)?,
+            uniforms: Vec::new(),
+            fbo,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+            context: ContextId::current(),
+        })
+    }
+
+    /// Creates a pass that halves an image by averaging every 2 by 2 pixels, see
+    /// [ImagePass::reduce]
+    pub fn average() -> Result<Self, LighthouseError> {
+        ImagePass::new(AVERAGE_FRAG)
+    }
+
+    /// Creates a pass that halves an image by keeping the smallest of every 2 by 2 pixels
+    pub fn min() -> Result<Self, LighthouseError> {
+        ImagePass::new(MIN_FRAG)
+    }
+
+    /// Creates a pass that halves an image by keeping the biggest of every 2 by 2 pixels
+    pub fn max() -> Result<Self, LighthouseError> {
+        ImagePass::new(MAX_FRAG)
+    }
+
+    /// Sets the uniform called name to value whenever the pass is run
+    pub fn uniform(mut self, name: &str, value: impl Into<UniformValue>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Changes the uniform called name to value, or adds it if the pass doesn't have it
+    pub fn set(&mut self, name: &str, value: impl Into<UniformValue>) {
+        let value = value.into();
+        match self.uniforms.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value,
+            None => self.uniforms.push((name.to_string(), value)),
+        }
+    }
+
+    /// Runs the pass over input into level 0 of output, see [ImagePass::run_with]
+    pub fn run(&self, input: &Texture, output: &Texture) -> Result<(), LighthouseError> {
+        self.run_with(Some(input), &[], output, 0)
+    }
+
+    /// Runs the pass without an input into a new texture of width by height, e.g. to bake
+    /// noise or a lookup table once instead of working it out every frame
+    pub fn bake(
+        &self,
+        width: i32,
+        height: i32,
+        format: ColorFormat,
+    ) -> Result<Texture, LighthouseError> {
+        let output = render_texture(width, height, format);
+        self.run_with(None, &[], &output, 0)?;
+        Ok(output)
+    }
+
+    /// Runs the pass over input into level of output, output is drawn over completely
+    ///
+    /// input is bound to unit 0 as `input_texture`, the textures of extra are bound to the
+    /// units after it as the sampler they are named with. Output can't be one of the inputs
+    pub fn run_with(
+        &self,
+        input: Option<&Texture>,
+        extra: &[(&str, &Texture)],
+        output: &Texture,
+        level: i32,
+    ) -> Result<(), LighthouseError> {
+        let (width, height) = level_size(output, level);
+        if width == 0 || height == 0 {
+            return Err(LighthouseError::InvalidInput(format!(
+                "Level {} of the output texture has no storage",
+                level
+            )));
+        }
+
+        self.program.use_program();
+        let uniform = |name| Uniform::new(&self.program, name);
+        for (name, value) in &self.uniforms {
+            uniform(name).set(value.clone());
+        }
+        unsafe { glActiveTexture(GL_TEXTURE0) };
+        if let Some(input) = input {
+            let (input_width, input_height) = level_size(input, 0);
+            uniform("texel_size").set_uniform_f(&[
+                1.0 / input_width.max(1) as f32,
+                1.0 / input_height.max(1) as f32,
+            ]);
+            unsafe { glBindTexture(GL_TEXTURE_2D, input.id) };
+        } else {
+            // so output isn't left bound while it is drawn into
+            Texture::unbind(TextureTarget::Texture2D);
+        }
+        uniform("input_texture").set_uniform_i(&[0]);
+        for (unit, (name, texture)) in extra.iter().enumerate() {
+            let unit = unit as u32 + 1;
+            unsafe {
+                glActiveTexture(GL_TEXTURE0 + unit);
+                glBindTexture(GL_TEXTURE_2D, texture.id);
+            }
+            uniform(name).set_uniform_i(&[unit as i32]);
+        }
+        unsafe { glActiveTexture(GL_TEXTURE0) };
+
+        self.draw_into(output, level, width, height, || unsafe {
+            glDrawArrays(GL_TRIANGLES, 0, 3)
+        })
+    }
+
+    /// Makes the mipmaps of texture with the pass, every level is the pass run over the
+    /// level before it, gives how many levels there are
+    ///
+    /// With [ImagePass::average], [ImagePass::min] or [ImagePass::max] the last level is a
+    /// single pixel with the average, smallest or biggest color of the whole texture, e.g.
+    /// for auto exposure. It can be read with [read_texel] or sampled by other shaders with
+    /// `textureLod`, so it doesn't have to go through the cpu
+    ///
+    /// The pass reads the level before with `texelFetch(input_texture, pos, 0)`, the levels
+    /// the pass doesn't write are hidden from it
+    pub fn reduce(&self, texture: &Texture) -> Result<i32, LighthouseError> {
+        let (width, height) = level_size(texture, 0);
+        let levels = 32 - (width.max(height).max(1) as u32).leading_zeros() as i32;
+        let mut internal_format = 0;
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, texture.id);
+            glGetTexLevelParameteriv(
+                GL_TEXTURE_2D,
+                0,
+                GL_TEXTURE_INTERNAL_FORMAT,
+                &mut internal_format,
+            );
+            for level in 1..levels {
+                glTexImage2D(
+                    GL_TEXTURE_2D,
+                    level,
+                    internal_format,
+                    (width >> level).max(1),
+                    (height >> level).max(1),
+                    0,
+                    GL_RGBA,
+                    GL_FLOAT,
+                    std::ptr::null(),
+                );
+            }
+        }
+
+        let mut result = Ok(());
+        for level in 1..levels {
+            // only the level before can be read, so the one that is drawn isn't
+            set_levels(texture, level - 1, level - 1);
+            result = self.run_with(Some(texture), &[], texture, level);
+            if result.is_err() {
+                break;
+            }
+        }
+        set_levels(texture, 0, levels - 1);
+        result.map(|_| levels)
+    }
+
+    /// Attaches level of output and draws into it with draw
+    fn draw_into(
+        &self,
+        output: &Texture,
+        level: i32,
+        width: i32,
+        height: i32,
+        draw: impl FnOnce(),
+    ) -> Result<(), LighthouseError> {
+        let mut viewport = [0; 4];
+        let mut framebuffer = 0;
+        unsafe {
+            glGetIntegerv(GL_VIEWPORT, viewport.as_mut_ptr());
+            glGetIntegerv(GL_FRAMEBUFFER_BINDING, &mut framebuffer);
+            glBindFramebuffer(GL_FRAMEBUFFER, self.fbo);
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_COLOR_ATTACHMENT0,
+                GL_TEXTURE_2D,
+                output.id,
+                level,
+            );
+            let status = glCheckFramebufferStatus(GL_FRAMEBUFFER);
+            if status == GL_FRAMEBUFFER_COMPLETE {
+                glViewport(0, 0, width, height);
+                let depth_test = glIsEnabled(GL_DEPTH_TEST) == GL_TRUE;
+                let blend = glIsEnabled(GL_BLEND) == GL_TRUE;
+                glDisable(GL_DEPTH_TEST);
+                glDisable(GL_BLEND);
+                self.vao.bind();
+                draw();
+                VertexArray::clear_binding();
+                if depth_test {
+                    glEnable(GL_DEPTH_TEST);
+                }
+                if blend {
+                    glEnable(GL_BLEND);
+                }
+            }
+            glFramebufferTexture2D(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT0, GL_TEXTURE_2D, 0, 0);
+            glBindFramebuffer(GL_FRAMEBUFFER, framebuffer as u32);
+            glViewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            if status != GL_FRAMEBUFFER_COMPLETE {
+                return Err(LighthouseError::IncompleteFramebuffer(
+                    "ImagePass".to_string(),
+                    status,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the shader program and framebuffer of the pass now instead of when it is
+    /// dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for ImagePass {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe { glDeleteFramebuffers(1, &self.fbo) };
+        }
+    }
+}
+
+/// Two textures of the same size that [ImagePass]es are run back and forth between, every
+/// pass reads the texture the last one wrote
+pub struct PingPong {
+    /// The two textures
+    pub textures: [Texture; 2],
+    /// The format of the textures
+    pub format: ColorFormat,
+    /// Width of the textures(in pixels)
+    pub width: i32,
+    /// Height of the textures(in pixels)
+    pub height: i32,
+    /// The texture the last pass wrote
+    current: usize,
+}
+
+impl PingPong {
+    /// Creates two textures of width by height with format
+    pub fn new(width: i32, height: i32, format: ColorFormat) -> Result<Self, LighthouseError> {
+        if width <= 0 || height <= 0 {
+            return Err(LighthouseError::InvalidInput(format!(
+                "A ping pong of {}x{} has no pixels",
+                width, height
+            )));
+        }
+        Ok(PingPong {
+            textures: [
+                render_texture(width, height, format),
+                render_texture(width, height, format),
+            ],
+            format,
+            width,
+            height,
+            current: 0,
+        })
+    }
+
+    /// Gets the texture the last pass wrote
+    pub fn read(&self) -> &Texture {
+        &self.textures[self.current]
+    }
+
+    /// Runs pass over input into the texture that isn't read, which is read afterwards. It
+    /// starts a chain
+    pub fn apply_from(&mut self, pass: &ImagePass, input: &Texture) -> Result<(), LighthouseError> {
+        pass.run(input, &self.textures[1 - self.current])?;
+        self.current = 1 - self.current;
+        Ok(())
+    }
+
+    /// Runs pass over the texture that is read into the other one, which is read afterwards
+    pub fn apply(&mut self, pass: &ImagePass) -> Result<(), LighthouseError> {
+        let [first, second] = &self.textures;
+        let (read, write) = if self.current == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        pass.run(read, write)?;
+        self.current = 1 - self.current;
+        Ok(())
+    }
+}
+
+/// Counts how many pixels of a texture fall in every bin of a range, on the gpu
+///
+/// Every pixel is drawn as a point into the bin its value falls in with additive blending,
+/// so it only needs opengl 3.3. The value of a pixel is its color dotted with
+/// [Histogram::weights], the luminance by default
+///
+/// # Example
+/// ```
+/// let mut histogram = Histogram::new(64)?.range(0.0, 4.0);
+/// histogram.run(&hdr_scene.color)?;
+/// // histogram.output is 64 by 1, every pixel has the count of a bin in red
+/// let counts = histogram.read();
+/// ```
+pub struct Histogram {
+    /// The shader program that puts the pixels into bins
+    pub program: ShaderProgram,
+    /// The bins, a bins by 1 [ColorFormat::Rgba32F] texture with the count in every channel
+    pub output: Texture,
+    /// How many bins there are
+    pub bins: i32,
+    /// The values of the first and the last bin, values outside of it go in the edge bins
+    pub range: (f32, f32),
+    /// What the channels of a pixel are multiplied by and added up to get its value
+    pub weights: [f32; 4],
+    pass: ImagePass,
+}
+
+impl Histogram {
+    /// Creates a histogram with bins bins over 0 to 1
+    pub fn new(bins: i32) -> Result<Self, LighthouseError> {
+        let bins = bins.max(1);
+        Ok(Histogram {
+            program: ShaderProgram::from_vert_frag(HISTOGRAM_VERT, HISTOGRAM_FRAG)?,
+            output: render_texture(bins, 1, ColorFormat::Rgba32F),
+            bins,
+            range: (0.0, 1.0),
+            weights: [0.2126, 0.7152, 0.0722, 0.0],
+            // only used for its framebuffer
+            pass: ImagePass::new(HISTOGRAM_FRAG)?,
+        })
+    }
+
+    /// Sets the values of the first and the last bin
+    pub fn range(mut self, min: f32, max: f32) -> Self {
+        self.range = (min, max);
+        self
+    }
+
+    /// Sets what the channels of a pixel are multiplied by
+    pub fn weights(mut self, weights: [f32; 4]) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Counts the pixels of level 0 of input into [Histogram::output]
+    pub fn run(&self, input: &Texture) -> Result<(), LighthouseError> {
+        let (width, height) = level_size(input, 0);
+        self.program.use_program();
+        let uniform = |name| Uniform::new(&self.program, name);
+        uniform("input_texture").set_uniform_i(&[0]);
+        uniform("weights").set_uniform_f(&self.weights);
+        uniform("range").set_uniform_f(&[self.range.0, self.range.1]);
+        uniform("bins").set_uniform_i(&[self.bins]);
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+            glBindTexture(GL_TEXTURE_2D, input.id);
+        }
+
+        self.pass
+            .draw_into(&self.output, 0, self.bins, 1, || unsafe {
+                glClearColor(0.0, 0.0, 0.0, 0.0);
+                glClear(GL_COLOR_BUFFER_BIT);
+                glEnable(GL_BLEND);
+                glBlendFunc(GL_ONE, GL_ONE);
+                glDrawArrays(GL_POINTS, 0, width * height);
+                glDisable(GL_BLEND);
+            })?;
+        // the clear color and blend function aren't put back how they were
+        RenderState::invalidate();
+        Ok(())
+    }
+
+    /// Reads the counts back to the cpu, this waits for the gpu to finish
+    pub fn read(&self) -> Vec<u32> {
+        let mut texels = vec![[0.0f32; 4]; self.bins as usize];
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, self.output.id);
+            glGetTexImage(
+                GL_TEXTURE_2D,
+                0,
+                GL_RGBA,
+                GL_FLOAT,
+                texels.as_mut_ptr().cast(),
+            );
+        }
+        texels.iter().map(|texel| texel[0] as u32).collect()
+    }
+
+    /// Deletes the shader programs, framebuffer and bins
+    pub fn delete(self) {
+        self.program.delete();
+        self.pass.delete();
+    }
+}
+
+/// Reads a single texel of level of texture back to the cpu, e.g. the last level after
+/// [ImagePass::reduce]. This waits for the gpu to finish
+pub fn read_texel(texture: &Texture, level: i32) -> [f32; 4] {
+    let (width, height) = level_size(texture, level);
+    let mut texels = vec![[0.0f32; 4]; (width.max(1) * height.max(1)) as usize];
+    unsafe {
+        glBindTexture(GL_TEXTURE_2D, texture.id);
+        glGetTexImage(
+            GL_TEXTURE_2D,
+            level,
+            GL_RGBA,
+            GL_FLOAT,
+            texels.as_mut_ptr().cast(),
+        );
+    }
+    texels[0]
+}
+
+/// Creates a linearly filtered texture of width by height with format, that passes can
+/// draw into
+pub fn render_texture(width: i32, height: i32, format: ColorFormat) -> Texture {
+    let mut texture = Texture::new();
+    texture.bind(TextureTarget::Texture2D);
+    TextureSettings::default()
+        .filter(TextureFilter::Linear, TextureFilter::Linear)
+        .wrap(TextureWrap::ClampToEdge)
+        .apply(&mut texture);
+    let (pixel_format, pixel_type) = format.pixel_format();
+    unsafe {
+        glTexImage2D(
+            GL_TEXTURE_2D,
+            0,
+            format.internal_format() as i32,
+            width,
+            height,
+            0,
+            pixel_format,
+            pixel_type,
+            std::ptr::null(),
+        );
+    }
+    texture
+}
+
+/// Gets the size of level of a 2d texture, 0 by 0 if it has no storage
+fn level_size(texture: &Texture, level: i32) -> (i32, i32) {
+    let (mut width, mut height) = (0, 0);
+    unsafe {
+        glBindTexture(GL_TEXTURE_2D, texture.id);
+        glGetTexLevelParameteriv(GL_TEXTURE_2D, level, GL_TEXTURE_WIDTH, &mut width);
+        glGetTexLevelParameteriv(GL_TEXTURE_2D, level, GL_TEXTURE_HEIGHT, &mut height);
+    }
+    (width, height)
+}
+
+/// Sets the levels of texture that can be sampled
+fn set_levels(texture: &Texture, base: i32, max: i32) {
+    unsafe {
+        glBindTexture(GL_TEXTURE_2D, texture.id);
+        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_BASE_LEVEL, base);
+        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAX_LEVEL, max);
+    }
+}
@@ -0,0 +1,207 @@
+use nalgebra_glm::Mat4;
+
+use super::{
+    buffer::*,
+    pipeline::VertexLayout,
+    vertex::{AttrFormat, VertexArray},
+    *,
+};
+use crate::ECS::mesh::{Mesh, VertexTrait};
+
+/// The first location of the attributes of an [Instance], the model matrix takes 5 to 8,
+/// the tint 9, the uv rectangle 10 and the custom data 11
+pub const INSTANCE_LOCATION: u32 = 5;
+
+/// Glsl that declares the attributes of an [Instance]
+///
+/// Paste it into a vertex shader of meshes drawn with an [InstanceBuffer::standard] buffer
+pub const INSTANCE_GLSL: &str = "layout (location = 5) in mat4 instance_model;
+layout (location = 9) in vec4 instance_tint;
+layout (location = 10) in vec4 instance_uv_rect;
+layout (location = 11) in vec4 instance_custom;
+
+// moves a uv from 0 to 1 into the uv rectangle of the instance
+vec2 instance_uv(vec2 uv) {
+    return mix(instance_uv_rect.xy, instance_uv_rect.zw, uv);
+}";
+
+/// One instance of a mesh with the attributes of [InstanceBuffer::standard]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    /// Where the instance is
+    pub model: Mat4,
+    /// The color the instance is multiplied by
+    pub tint: [f32; 4],
+    /// The part of the texture the instance shows, bottom left then top right
+    pub uv_rect: [f32; 4],
+    /// Anything the shader wants, it isn't used by lighthouse
+    pub custom: [f32; 4],
+}
+
+impl Instance {
+    /// Creates a new untinted instance at model showing the whole texture
+    pub fn new(model: Mat4) -> Self {
+        Instance {
+            model,
+            tint: [1.0; 4],
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            custom: [0.0; 4],
+        }
+    }
+
+    /// Sets the tint
+    pub fn tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    /// Sets the uv rectangle, e.g. from [TextureAtlas::uv_rect](super::atlas::TextureAtlas::uv_rect)
+    pub fn uv_rect(mut self, uv_rect: [f32; 4]) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+
+    /// Sets the custom data
+    pub fn custom(mut self, custom: [f32; 4]) -> Self {
+        self.custom = custom;
+        self
+    }
+}
+
+/// Per instance attributes for drawing many copies of a mesh with one draw call
+///
+/// What every instance has is declared with a [VertexLayout], the attributes are read once
+/// per instance instead of once per vertex. [InstanceBuffer::standard] has a model matrix, a
+/// tint, a uv rectangle and a custom vec4, see [Instance] and [INSTANCE_GLSL]. The locations
+/// of the layout can't be used by the vertices of the mesh
+///
+/// # Example
+/// ```
+/// let mut trees = InstanceBuffer::standard()?;
+/// trees.attach(&tree);
+///
+/// 'main_loop: loop {
+///     for pos in &forest {
+///         trees.push_instance(&Instance::new(translation(pos)).tint(autumn));
+///     }
+///     trees.draw(&tree);
+/// }
+///
+/// // or with your own attributes, a float for how far a flag has blown
+/// let mut flags = InstanceBuffer::new(VertexLayout::default().attribute(5, 3, AttrFormat::Float).attribute(6, 1, AttrFormat::HalfFloat))?;
+/// flags.push(&[pos.x, pos.y, pos.z, wind]);
+/// ```
+pub struct InstanceBuffer {
+    /// The attributes of every instance
+    pub layout: VertexLayout,
+    /// The packed instances pushed since the last draw
+    data: Vec<u8>,
+    count: usize,
+    vbo: Buffer,
+}
+
+impl InstanceBuffer {
+    /// Creates a new empty buffer of instances with the attributes of layout
+    pub fn new(layout: VertexLayout) -> Result<Self, LighthouseError> {
+        layout.validate()?;
+        Ok(InstanceBuffer {
+            layout,
+            data: Vec::new(),
+            count: 0,
+            vbo: Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+        })
+    }
+
+    /// Creates a new empty buffer of [Instance]s
+    pub fn standard() -> Result<Self, LighthouseError> {
+        let layout = (INSTANCE_LOCATION..INSTANCE_LOCATION + 7)
+            .fold(VertexLayout::default(), |layout, location| {
+                layout.attribute(location, 4, AttrFormat::Float)
+            });
+        Self::new(layout)
+    }
+
+    /// Adds the per instance attributes to the vertex array of mesh, call it once after
+    /// the mesh is set up
+    pub fn attach<Vertex: VertexTrait + 'static + Sync + Send>(&self, mesh: &Mesh<Vertex>) {
+        mesh.bind();
+        self.vbo.bind(BufferType::Array);
+        let stride = self.layout.stride();
+        let mut pointer = 0;
+        for attr in &self.layout.attributes {
+            unsafe {
+                glVertexAttribPointer(
+                    attr.location,
+                    attr.format.gl_size(attr.size),
+                    attr.format.gl_type(),
+                    attr.format.normalized() as u8,
+                    stride as i32,
+                    pointer as *const _,
+                );
+                glEnableVertexAttribArray(attr.location);
+                glVertexAttribDivisor(attr.location, 1);
+            }
+            pointer += attr.format.byte_size(attr.size);
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Adds an instance to be drawn on the next draw, values are the elements of every
+    /// attribute one after the other
+    ///
+    /// Missing elements are 0 and extra ones are ignored
+    pub fn push(&mut self, values: &[f32]) {
+        let mut start = 0;
+        for attr in &self.layout.attributes {
+            let end = (start + attr.size as usize).min(values.len());
+            let mut elements = [0.0; 4];
+            elements[..end.saturating_sub(start)].copy_from_slice(&values[start.min(end)..end]);
+            attr.format
+                .pack(&elements[..attr.size as usize], &mut self.data);
+            start += attr.size as usize;
+        }
+        self.count += 1;
+    }
+
+    /// Adds an [Instance] to be drawn on the next draw, the buffer has to be made with
+    /// [InstanceBuffer::standard]
+    pub fn push_instance(&mut self, instance: &Instance) {
+        let mut values = [0.0; 28];
+        values[..16].copy_from_slice(instance.model.as_slice());
+        values[16..20].copy_from_slice(&instance.tint);
+        values[20..24].copy_from_slice(&instance.uv_rect);
+        values[24..].copy_from_slice(&instance.custom);
+        self.push(&values);
+    }
+
+    /// Gets how many instances were pushed since the last draw
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Checks if no instances were pushed since the last draw
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Throws away every pushed instance
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.count = 0;
+    }
+
+    /// Draws every pushed instance of mesh and empties the buffer
+    ///
+    /// The mesh must have been given to [InstanceBuffer::attach] and the program it is
+    /// drawn with has to be in use
+    pub fn draw<Vertex: VertexTrait + 'static + Sync + Send>(&mut self, mesh: &Mesh<Vertex>) {
+        if self.is_empty() {
+            return;
+        }
+        self.vbo.bind(BufferType::Array);
+        buffer_data(BufferType::Array, &self.data, BufferUsage::StreamDraw);
+        mesh.draw_instanced(self.count);
+        VertexArray::clear_binding();
+        self.clear();
+    }
+}
@@ -0,0 +1,133 @@
+use nalgebra_glm::{normalize, vec3, Vec3};
+
+use super::{shader::ShaderProgram, uniform::Uniform};
+
+/// Glsl that declares the lighting uniforms and `vec3 apply_lighting(vec3 albedo, vec3 normal)`
+///
+/// Paste it into a fragment shader to light it with a [Lighting], see [Lighting::set_uniforms]
+pub const LIGHTING_GLSL: &str = "uniform vec3 sun_direction;
+uniform vec3 sun_color;
+uniform vec3 ambient_color;
+
+vec3 apply_lighting(vec3 albedo, vec3 normal) {
+    float diffuse = max(dot(normalize(normal), -sun_direction), 0.0);
+    return albedo * (ambient_color + sun_color * diffuse);
+}";
+
+/// A light infinitely far away that lights everything from the same direction, e.g. the sun
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    /// The direction the light shines in
+    pub direction: Vec3,
+    /// The color of the light
+    pub color: [f32; 3],
+    /// How bright the light is, multiplied with the color
+    pub intensity: f32,
+}
+
+impl DirectionalLight {
+    /// Creates a new white light shining in direction
+    pub fn new(direction: Vec3) -> Self {
+        DirectionalLight {
+            direction: normalize(&direction),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+        }
+    }
+
+    /// Sets the color of the light
+    pub fn color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets how bright the light is
+    pub fn intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+/// A light that shines in every direction from a point and fades out at its radius
+///
+/// Many of them can be drawn at once with [crate::graphics::clustered::ClusteredLights]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    /// The position of the light
+    pub pos: Vec3,
+    /// The color of the light
+    pub color: [f32; 3],
+    /// How bright the light is, multiplied with the color
+    pub intensity: f32,
+    /// How far the light reaches, nothing past it is lit
+    pub radius: f32,
+}
+
+impl PointLight {
+    /// Creates a new white light at pos that reaches radius far
+    pub fn new(pos: Vec3, radius: f32) -> Self {
+        PointLight {
+            pos,
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            radius,
+        }
+    }
+
+    /// Sets the color of the light
+    pub fn color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets how bright the light is
+    pub fn intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+/// The lights of a scene
+///
+/// # Example
+/// ```
+/// let mut lighting = Lighting::default();
+/// lighting.sun = DirectionalLight::new(vec3(-0.3, -1.0, -0.2)).color([1.0, 0.95, 0.8]);
+///
+/// 'main_loop: loop {
+///     lighting.set_uniforms(&program);
+///     mesh.draw();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lighting {
+    /// The directional light of the scene
+    pub sun: DirectionalLight,
+    /// The light that reaches every surface from every direction
+    pub ambient: [f32; 3],
+}
+
+impl Lighting {
+    /// Gives the lights to a program that uses [LIGHTING_GLSL]
+    pub fn set_uniforms(&self, program: &ShaderProgram) {
+        program.use_program();
+        let sun = self.sun.color.map(|c| c * self.sun.intensity);
+        let direction = normalize(&self.sun.direction);
+        Uniform::new(program, "sun_direction").set_uniform_f(&[
+            direction.x,
+            direction.y,
+            direction.z,
+        ]);
+        Uniform::new(program, "sun_color").set_uniform_f(&sun);
+        Uniform::new(program, "ambient_color").set_uniform_f(&self.ambient);
+    }
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting {
+            sun: DirectionalLight::new(vec3(-0.3, -1.0, -0.2)),
+            ambient: [0.2, 0.2, 0.2],
+        }
+    }
+}
@@ -0,0 +1,599 @@
+use nalgebra_glm::{cross, dot, normalize, vec2, vec3, Vec2, Vec3, Vec4};
+
+use super::{
+    lighting::DirectionalLight,
+    shader::ShaderProgram,
+    texture::{Texture, TextureFilter, TextureWrap},
+    uniform::Uniform,
+    *,
+};
+use crate::ECS::mesh::{Mesh, VertexTrait};
+
+/// The triangles of a static scene in world space, what [Lightmap::bake] lights
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BakeMesh {
+    /// Position of every vertex
+    pub positions: Vec<Vec3>,
+    /// Normal of every vertex
+    pub normals: Vec<Vec3>,
+    /// The triangles
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl BakeMesh {
+    /// Creates a new bake mesh, without normals every vertex gets the normal of the last
+    /// triangle it is in
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        indices: Vec<[u32; 3]>,
+    ) -> Result<Self, LighthouseError> {
+        if let Some(index) = indices
+            .iter()
+            .flatten()
+            .find(|i| **i as usize >= positions.len())
+        {
+            return Err(LighthouseError::InvalidInput(format!(
+                "Index {} is out of bounds, there are {} vertices",
+                index,
+                positions.len()
+            )));
+        }
+        let normals = if normals.is_empty() {
+            let mut normals = vec![vec3(0.0, 1.0, 0.0); positions.len()];
+            for [a, b, c] in &indices {
+                let (a, b, c) = (*a as usize, *b as usize, *c as usize);
+                let normal = safe_normalize(&cross(
+                    &(positions[b] - positions[a]),
+                    &(positions[c] - positions[a]),
+                ));
+                normals[a] = normal;
+                normals[b] = normal;
+                normals[c] = normal;
+            }
+            normals
+        } else if normals.len() != positions.len() {
+            return Err(LighthouseError::InvalidInput(format!(
+                "There are {} normals but {} vertices",
+                normals.len(),
+                positions.len()
+            )));
+        } else {
+            normals
+        };
+
+        Ok(BakeMesh {
+            positions,
+            normals,
+            indices,
+        })
+    }
+
+    /// Takes the triangles of mesh placed at pos with rotation rot
+    ///
+    /// The first vertex attribute must be the position, normal_attr is the index of the
+    /// normal attribute if the mesh has one
+    pub fn from_mesh<Vertex: VertexTrait + 'static + Sync + Send>(
+        mesh: &Mesh<Vertex>,
+        pos: Vec3,
+        rot: Vec4,
+        normal_attr: Option<usize>,
+    ) -> Result<Self, LighthouseError> {
+        if mesh.vert_attr.first() != Some(&3) {
+            return Err(LighthouseError::InvalidInput(
+                "The first attribute of a baked mesh must be a 3d position".to_string(),
+            ));
+        }
+        let normal_offset = match normal_attr {
+            Some(attr) if mesh.vert_attr.get(attr) == Some(&3) => {
+                Some(mesh.vert_attr[..attr].iter().sum::<u32>() as usize)
+            }
+            Some(attr) => {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "Attribute {} is not a 3d normal",
+                    attr
+                )))
+            }
+            None => None,
+        };
+
+        let mut positions = Vec::with_capacity(mesh.vertices.len());
+        let mut normals = Vec::new();
+        for vertex in &mesh.vertices {
+            let list = vertex.get_vertex(pos, rot).as_list();
+            positions.push(vec3(list[0], list[1], list[2]));
+            if let Some(offset) = normal_offset {
+                normals.push(vec3(list[offset], list[offset + 1], list[offset + 2]));
+            }
+        }
+        BakeMesh::new(positions, normals, mesh.indicies.clone())
+    }
+
+    /// Adds the triangles of other, so a whole scene can be baked into one lightmap
+    pub fn append(&mut self, other: &BakeMesh) {
+        let offset = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.indices.extend(
+            other
+                .indices
+                .iter()
+                .map(|triangle| triangle.map(|index| index + offset)),
+        );
+    }
+}
+
+/// How a [Lightmap] is baked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightmapSettings {
+    /// The width and height of the lightmap(in texels)
+    pub resolution: u32,
+    /// Empty texels around every triangle, so filtering doesnt bleed between triangles
+    pub padding: u32,
+    /// How many ambient occlusion rays every texel casts
+    pub samples: u32,
+    /// How far away geometry still occludes(in world units)
+    pub max_distance: f32,
+    /// The light that reaches unoccluded surfaces from every direction
+    pub ambient: [f32; 3],
+    /// A light that casts hard shadows into the lightmap
+    pub sun: Option<DirectionalLight>,
+    /// How far rays start away from the surface, so surfaces dont shadow themselves
+    pub bias: f32,
+}
+
+impl Default for LightmapSettings {
+    fn default() -> Self {
+        LightmapSettings {
+            resolution: 512,
+            padding: 2,
+            samples: 64,
+            max_distance: 5.0,
+            ambient: [1.0, 1.0, 1.0],
+            sun: None,
+            bias: 0.001,
+        }
+    }
+}
+
+/// Gives every triangle its own spot in a lightmap, the secondary uv set lightmaps are
+/// read with
+///
+/// Every square cell of the lightmap holds two triangles, one in each half. The uvs are
+/// per corner, so vertices shared between triangles have to be split
+pub fn unwrap_lightmap_uvs(
+    triangles: usize,
+    resolution: u32,
+    padding: u32,
+) -> Result<Vec<[[f32; 2]; 3]>, LighthouseError> {
+    let cells = triangles.div_ceil(2).max(1);
+    let per_row = (cells as f32).sqrt().ceil() as u32;
+    let cell = resolution / per_row;
+    if cell < padding * 2 + 3 {
+        return Err(LighthouseError::InvalidInput(format!(
+            "{} triangles don't fit in a {}x{} lightmap, use a bigger resolution",
+            triangles, resolution, resolution
+        )));
+    }
+
+    let size = resolution as f32;
+    let inner = (cell - padding * 2) as f32;
+    // gap between the two triangles of a cell, along the diagonal
+    let gap = padding as f32 / inner;
+    Ok((0..triangles)
+        .map(|triangle| {
+            let index = triangle as u32 / 2;
+            let origin = vec2(
+                ((index % per_row) * cell + padding) as f32,
+                ((index / per_row) * cell + padding) as f32,
+            );
+            let corners = if triangle % 2 == 0 {
+                [vec2(0.0, 0.0), vec2(1.0 - gap, 0.0), vec2(0.0, 1.0 - gap)]
+            } else {
+                [vec2(1.0, 1.0), vec2(gap, 1.0), vec2(1.0, gap)]
+            };
+            corners.map(|corner: Vec2| {
+                let texel = origin + corner * inner;
+                [texel.x / size, texel.y / size]
+            })
+        })
+        .collect())
+}
+
+/// Light baked ahead of time into a texture, for static scenes on weak gpus
+///
+/// The ambient occlusion and sun shadows are ray traced on the cpu, the built in shaders
+/// multiply the texture with the color when a lightmap is bound, see [Lightmap::bind]
+///
+/// # Example
+/// ```
+/// let mut scene = BakeMesh::from_mesh(&level, pos, rot, Some(2))?;
+/// scene.append(&BakeMesh::from_mesh(&house, house_pos, house_rot, Some(2))?);
+///
+/// let lightmap = Lightmap::bake(&scene, &LightmapSettings::default())?;
+/// lightmap.to_image().save("data/level_lightmap.png")?;
+/// // give every corner of every triangle its lightmap.uvs as the lightmap uv attribute
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lightmap {
+    /// Width of the lightmap(in texels)
+    pub width: u32,
+    /// Height of the lightmap(in texels)
+    pub height: u32,
+    /// The lightmap uvs of every corner of every triangle, see [unwrap_lightmap_uvs]
+    pub uvs: Vec<[[f32; 2]; 3]>,
+    /// The light of every texel, from the bottom row up
+    pub texels: Vec<[f32; 3]>,
+}
+
+impl Lightmap {
+    /// Bakes the light of every triangle of mesh
+    pub fn bake(mesh: &BakeMesh, settings: &LightmapSettings) -> Result<Self, LighthouseError> {
+        let size = settings.resolution;
+        let uvs = unwrap_lightmap_uvs(mesh.indices.len(), size, settings.padding)?;
+        let bvh = Bvh::new(
+            mesh.indices
+                .iter()
+                .map(|triangle| triangle.map(|index| mesh.positions[index as usize]))
+                .collect(),
+        );
+
+        let mut texels = vec![[0.0; 3]; (size * size) as usize];
+        let mut filled = vec![false; texels.len()];
+        for (triangle, corners) in mesh.indices.iter().zip(&uvs) {
+            let corners = corners.map(|[u, v]| vec2(u, v) * size as f32);
+            let positions = triangle.map(|index| mesh.positions[index as usize]);
+            let normals = triangle.map(|index| mesh.normals[index as usize]);
+
+            let min = corners[0].inf(&corners[1]).inf(&corners[2]);
+            let max = corners[0].sup(&corners[1]).sup(&corners[2]);
+            for y in min.y.floor() as u32..(max.y.ceil() as u32).min(size) {
+                for x in min.x.floor() as u32..(max.x.ceil() as u32).min(size) {
+                    let center = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                    let bary = match barycentric(&corners, &center) {
+                        Some(bary) => bary,
+                        None => continue,
+                    };
+                    let pos = positions[0] * bary.x + positions[1] * bary.y + positions[2] * bary.z;
+                    let normal = safe_normalize(
+                        &(normals[0] * bary.x + normals[1] * bary.y + normals[2] * bary.z),
+                    );
+
+                    let index = (y * size + x) as usize;
+                    texels[index] = light_texel(&bvh, settings, pos, normal, index as u32);
+                    filled[index] = true;
+                }
+            }
+        }
+
+        // spread the edges into the padding so filtering doesnt read black
+        for _ in 0..settings.padding.max(1) {
+            dilate(&mut texels, &mut filled, size);
+        }
+
+        Ok(Lightmap {
+            width: size,
+            height: size,
+            uvs,
+            texels,
+        })
+    }
+
+    /// Turns the lightmap into an image, e.g. to save it with the level
+    pub fn to_image(&self) -> DynamicImage {
+        let mut image = image::RgbImage::new(self.width, self.height);
+        for (i, texel) in self.texels.iter().enumerate() {
+            let x = i as u32 % self.width;
+            let y = i as u32 / self.width;
+            // images go from the top row down
+            image.put_pixel(
+                x,
+                self.height - 1 - y,
+                image::Rgb(texel.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)),
+            );
+        }
+        ImageRgb8(image)
+    }
+
+    /// Uploads the lightmap into a linearly filtered texture on texture unit 0
+    pub fn upload(&self) -> Result<Texture, LighthouseError> {
+        Texture::builder(self.to_image())
+            .filter(TextureFilter::Linear, TextureFilter::Linear)
+            .wrap(TextureWrap::ClampToEdge)
+            .build()
+            .map_err(LighthouseError::from)
+    }
+
+    /// Makes the built in shaders multiply their color with texture, bound to unit
+    pub fn bind(program: &ShaderProgram, texture: &Texture, unit: u32) {
+        program.use_program();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0 + unit);
+            glBindTexture(GL_TEXTURE_2D, texture.id);
+        }
+        Uniform::new(program, "lightmap").set_uniform_i(&[unit as i32]);
+        Uniform::new(program, "use_lightmap").set_uniform_i(&[1]);
+    }
+
+    /// Stops the built in shaders from using a lightmap
+    pub fn unbind(program: &ShaderProgram) {
+        program.use_program();
+        Uniform::new(program, "use_lightmap").set_uniform_i(&[0]);
+    }
+}
+
+/// Gets the baked light at pos
+fn light_texel(
+    bvh: &Bvh,
+    settings: &LightmapSettings,
+    pos: Vec3,
+    normal: Vec3,
+    seed: u32,
+) -> [f32; 3] {
+    let origin = pos + normal * settings.bias;
+    let (tangent, bitangent) = basis(&normal);
+    // every texel turns the sample pattern a bit differently, so the noise doesnt band
+    let rotation = radical_inverse(seed.wrapping_mul(2654435761)) * std::f32::consts::TAU;
+
+    let samples = settings.samples.max(1);
+    let mut hits = 0;
+    for i in 0..samples {
+        // cosine weighted hemisphere from the hammersley sequence
+        let u = (i as f32 + 0.5) / samples as f32;
+        let angle = radical_inverse(i) * std::f32::consts::TAU + rotation;
+        let r = u.sqrt();
+        let dir =
+            tangent * (r * angle.cos()) + bitangent * (r * angle.sin()) + normal * (1.0 - u).sqrt();
+        if bvh.any_hit(&origin, &dir, settings.max_distance) {
+            hits += 1;
+        }
+    }
+    let occlusion = 1.0 - hits as f32 / samples as f32;
+    let mut light = settings.ambient.map(|c| c * occlusion);
+
+    if let Some(sun) = &settings.sun {
+        let to_sun = -normalize(&sun.direction);
+        let lambert = dot(&normal, &to_sun);
+        if lambert > 0.0 && !bvh.any_hit(&origin, &to_sun, f32::INFINITY) {
+            for (channel, color) in light.iter_mut().zip(sun.color) {
+                *channel += color * sun.intensity * lambert;
+            }
+        }
+    }
+    light
+}
+
+/// Fills every empty texel next to a filled one with the average of its filled neighbours
+fn dilate(texels: &mut [[f32; 3]], filled: &mut [bool], size: u32) {
+    let before = filled.to_vec();
+    for y in 0..size as i32 {
+        for x in 0..size as i32 {
+            let index = (y as u32 * size + x as u32) as usize;
+            if before[index] {
+                continue;
+            }
+            let mut sum = [0.0; 3];
+            let mut count = 0;
+            for (dx, dy) in [
+                (-1, 0),
+                (1, 0),
+                (0, -1),
+                (0, 1),
+                (-1, -1),
+                (1, 1),
+                (-1, 1),
+                (1, -1),
+            ] {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= size as i32 || ny >= size as i32 {
+                    continue;
+                }
+                let neighbour = (ny as u32 * size + nx as u32) as usize;
+                if before[neighbour] {
+                    for c in 0..3 {
+                        sum[c] += texels[neighbour][c];
+                    }
+                    count += 1;
+                }
+            }
+            if count > 0 {
+                texels[index] = sum.map(|c| c / count as f32);
+                filled[index] = true;
+            }
+        }
+    }
+}
+
+/// Gets the barycentric coordinates of point in triangle, None if it is outside
+fn barycentric(triangle: &[Vec2; 3], point: &Vec2) -> Option<Vec3> {
+    let [a, b, c] = triangle;
+    let area = (b - a).perp(&(c - a));
+    if area.abs() < f32::EPSILON {
+        return None;
+    }
+    let u = (c - b).perp(&(point - b)) / area;
+    let v = (a - c).perp(&(point - c)) / area;
+    let w = 1.0 - u - v;
+    // a bit of slack so texels on the edges are still lit
+    let slack = -0.01;
+    (u >= slack && v >= slack && w >= slack).then(|| vec3(u, v, w))
+}
+
+/// Gets two directions at right angles to normal and to each other
+fn basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let up = if normal.y.abs() < 0.99 {
+        vec3(0.0, 1.0, 0.0)
+    } else {
+        vec3(1.0, 0.0, 0.0)
+    };
+    let tangent = normalize(&cross(&up, normal));
+    (tangent, cross(normal, &tangent))
+}
+
+/// The van der corput sequence, evenly spread numbers from 0 to 1
+fn radical_inverse(i: u32) -> f32 {
+    i.reverse_bits() as f32 / 4294967296.0
+}
+
+fn safe_normalize(v: &Vec3) -> Vec3 {
+    if v.norm_squared() > 0.0 {
+        normalize(v)
+    } else {
+        vec3(0.0, 1.0, 0.0)
+    }
+}
+
+/// A node of a [Bvh], leaves have triangles and the others have 2 children
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    /// The first triangle of a leaf or the first child of an inner node
+    first: usize,
+    /// How many triangles the leaf has, 0 for inner nodes
+    count: usize,
+}
+
+impl BvhNode {
+    fn empty() -> Self {
+        BvhNode {
+            min: vec3(0.0, 0.0, 0.0),
+            max: vec3(0.0, 0.0, 0.0),
+            first: 0,
+            count: 0,
+        }
+    }
+}
+
+/// A bounding volume hierarchy, so a ray only has to be tested against the triangles near it
+struct Bvh {
+    nodes: Vec<BvhNode>,
+    triangles: Vec<[Vec3; 3]>,
+}
+
+impl Bvh {
+    fn new(mut triangles: Vec<[Vec3; 3]>) -> Self {
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            let count = triangles.len();
+            nodes.push(BvhNode::empty());
+            Bvh::build(&mut nodes, &mut triangles, 0, 0, count);
+        }
+        Bvh { nodes, triangles }
+    }
+
+    /// Builds node out of the triangles first..first + count
+    fn build(
+        nodes: &mut Vec<BvhNode>,
+        triangles: &mut [[Vec3; 3]],
+        node: usize,
+        first: usize,
+        count: usize,
+    ) {
+        let slice = &mut triangles[first..first + count];
+        let mut min = vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = -min;
+        for point in slice.iter().flatten() {
+            min = min.inf(point);
+            max = max.sup(point);
+        }
+        nodes[node] = BvhNode {
+            min,
+            max,
+            first,
+            count,
+        };
+        if count <= 4 {
+            return;
+        }
+
+        // split at the middle triangle along the longest axis
+        let extent = max - min;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+        slice.sort_by(|a, b| {
+            let a = a[0][axis] + a[1][axis] + a[2][axis];
+            let b = b[0][axis] + b[1][axis] + b[2][axis];
+            a.total_cmp(&b)
+        });
+        let half = count / 2;
+
+        // the children are next to each other, so only the first is stored
+        let left = nodes.len();
+        nodes.push(BvhNode::empty());
+        nodes.push(BvhNode::empty());
+        nodes[node].first = left;
+        nodes[node].count = 0;
+        Bvh::build(nodes, triangles, left, first, half);
+        Bvh::build(nodes, triangles, left + 1, first + half, count - half);
+    }
+
+    /// Checks if the ray from origin in dir hits any triangle closer than max_distance
+    fn any_hit(&self, origin: &Vec3, dir: &Vec3, max_distance: f32) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let inverse = vec3(1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z);
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if !ray_box(origin, &inverse, &node.min, &node.max, max_distance) {
+                continue;
+            }
+            if node.count > 0 {
+                let triangles = &self.triangles[node.first..node.first + node.count];
+                if triangles
+                    .iter()
+                    .any(|triangle| ray_triangle(origin, dir, triangle, max_distance))
+                {
+                    return true;
+                }
+            } else {
+                stack.push(node.first);
+                stack.push(node.first + 1);
+            }
+        }
+        false
+    }
+}
+
+/// Checks if a ray hits a box closer than max_distance, inverse is 1 / the direction
+fn ray_box(origin: &Vec3, inverse: &Vec3, min: &Vec3, max: &Vec3, max_distance: f32) -> bool {
+    let mut near = 0.0_f32;
+    let mut far = max_distance;
+    for axis in 0..3 {
+        let a = (min[axis] - origin[axis]) * inverse[axis];
+        let b = (max[axis] - origin[axis]) * inverse[axis];
+        near = near.max(a.min(b));
+        far = far.min(a.max(b));
+    }
+    near <= far
+}
+
+/// Checks if a ray hits a triangle closer than max_distance, the Möller Trumbore way
+fn ray_triangle(origin: &Vec3, dir: &Vec3, triangle: &[Vec3; 3], max_distance: f32) -> bool {
+    let edge1 = triangle[1] - triangle[0];
+    let edge2 = triangle[2] - triangle[0];
+    let p = cross(dir, &edge2);
+    let det = dot(&edge1, &p);
+    if det.abs() < 1e-8 {
+        return false;
+    }
+    let inverse = 1.0 / det;
+    let to_origin = origin - triangle[0];
+    let u = dot(&to_origin, &p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return false;
+    }
+    let q = cross(&to_origin, &edge1);
+    let v = dot(dir, &q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return false;
+    }
+    let t = dot(&edge2, &q) * inverse;
+    t > 1e-5 && t < max_distance
+}
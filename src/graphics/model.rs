@@ -0,0 +1,424 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use nalgebra_glm::{rotate_vec3, vec2, vec3, Vec2, Vec3, Vec4};
+
+use super::*;
+use crate::{
+    assets::{Asset, AssetError},
+    ECS::mesh::{Mesh, VertexLayout, VertexTrait},
+};
+
+/// An error from parsing a model file, like a Wavefront OBJ, STL or PLY file
+#[derive(Debug)]
+pub enum ModelError {
+    /// Reading the file failed
+    Io(std::io::Error),
+    /// A line couldn't be parsed, contains the line number(starting at 1) and what is wrong
+    /// with it
+    Syntax(usize, String),
+    /// A face points to a position, uv or normal that doesn't exist, contains the line
+    /// number and the index as it is written in the file
+    IndexOutOfRange(usize, i64),
+    /// The file doesn't have a single face
+    Empty,
+    /// The model has more vertices than a u32 index can point to
+    TooManyVertices,
+    /// The file isn't a valid model, contains what is wrong with it
+    Invalid(String),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Io(err) => write!(f, "{}", err),
+            ModelError::Syntax(line, message) => write!(f, "Line {}: {}", line, message),
+            ModelError::IndexOutOfRange(line, index) => {
+                write!(f, "Line {}: index {} is out of range", line, index)
+            }
+            ModelError::Empty => write!(f, "The model has no faces"),
+            ModelError::TooManyVertices => write!(f, "The model has too many vertices"),
+            ModelError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModelError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ModelError {
+    fn from(err: std::io::Error) -> Self {
+        ModelError::Io(err)
+    }
+}
+
+/// A vertex of a loaded model, with everything an OBJ or glTF file can give a vertex
+///
+/// As a [VertexTrait] it has 3 attributes, the position, the uv and the normal, so
+/// shaders should declare
+/// ```
+/// layout (location = 0) in vec3 pos;
+/// layout (location = 1) in vec2 uv;
+/// layout (location = 2) in vec3 normal;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelVertex {
+    /// The position of the vertex
+    pub pos: Vec3,
+    /// The texture coordinate, (0, 0) if the file has none
+    pub uv: Vec2,
+    /// The normal, if the file has none it is the average of the normals of the faces
+    /// around the vertex
+    pub normal: Vec3,
+}
+
+impl ModelVertex {
+    /// The sizes of the vertex attributes of a ModelVertex, for [Mesh::new]
+    pub const ATTRIBUTES: [u32; 3] = [3, 2, 3];
+}
+
+impl VertexLayout for ModelVertex {
+    const ATTRIBUTES: &'static [u32] = &ModelVertex::ATTRIBUTES;
+}
+
+impl VertexTrait for ModelVertex {
+    const SIZE: u32 = 8;
+
+    fn as_list(&self) -> Vec<f32> {
+        vec![
+            self.pos.x,
+            self.pos.y,
+            self.pos.z,
+            self.uv.x,
+            self.uv.y,
+            self.normal.x,
+            self.normal.y,
+            self.normal.z,
+        ]
+    }
+
+    fn get_vertex(&self, pos: Vec3, rot: Vec4) -> Self {
+        ModelVertex {
+            pos: rotate_vec3(&self.pos, rot.w, &rot.xyz()) + pos,
+            uv: self.uv,
+            normal: rotate_vec3(&self.normal, rot.w, &rot.xyz()),
+        }
+    }
+}
+
+/// A model loaded from a Wavefront OBJ file
+///
+/// Only the geometry is read: positions, uvs, normals and faces. Faces with more than 3
+/// vertices are turned into triangles as a fan, so they have to be convex. Every different
+/// combination of position, uv and normal is one vertex, so vertices shared by faces are
+/// only stored once
+///
+/// # Example
+/// ```
+/// let mut pyramid = Mesh::from_obj("models/pyramid.obj")?;
+/// pyramid.upload();
+///
+/// // or with your own vertex
+/// let model: ObjModel = assets.load("models/pyramid.obj")?;
+/// let pyramid = model.into_mesh(vec![3, 2], |v| Vertex::new(v.pos, v.uv))?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjModel {
+    /// The vertices of the model
+    pub vertices: Vec<ModelVertex>,
+    /// The triangles of the model, as indices into [ObjModel::vertices]
+    pub indices: Vec<[u32; 3]>,
+    /// Whether the file had uvs
+    pub has_uvs: bool,
+    /// Whether the file had normals, if it didn't they were calculated
+    pub has_normals: bool,
+}
+
+/// Parses the floats after the keyword of a line, there have to be at least N of them
+fn parse_floats<const N: usize>(
+    line: usize,
+    keyword: &str,
+    parts: &[&str],
+) -> Result<[f32; N], ModelError> {
+    if parts.len() < N {
+        return Err(ModelError::Syntax(
+            line,
+            format!("{} needs {} numbers", keyword, N),
+        ));
+    }
+    let mut out = [0.0; N];
+    for (out, part) in out.iter_mut().zip(parts) {
+        *out = part
+            .parse()
+            .map_err(|_| ModelError::Syntax(line, format!("{} is not a number", part)))?;
+    }
+    Ok(out)
+}
+
+/// Turns an index as it is written in the file into an index into a list of len, positive
+/// indices start at 1 and negative indices count back from the end
+fn resolve_index(line: usize, index: &str, len: usize) -> Result<usize, ModelError> {
+    let written: i64 = index
+        .parse()
+        .map_err(|_| ModelError::Syntax(line, format!("{} is not an index", index)))?;
+    let resolved = match written {
+        0 => None,
+        1.. => Some(written - 1),
+        _ => Some(len as i64 + written),
+    };
+    resolved
+        .filter(|&index| index >= 0 && index < len as i64)
+        .map(|index| index as usize)
+        .ok_or(ModelError::IndexOutOfRange(line, written))
+}
+
+/// Gives every vertex without a normal the average of the normals of the triangles
+/// around it, weighted by their area
+pub(crate) fn calculate_normals(vertices: &mut [ModelVertex], indices: &[[u32; 3]]) {
+    let missing = vertices
+        .iter()
+        .map(|vertex| vertex.normal == vec3(0.0, 0.0, 0.0))
+        .collect::<Vec<_>>();
+    for [a, b, c] in indices {
+        let [a, b, c] = [*a as usize, *b as usize, *c as usize];
+        let (pa, pb, pc) = (vertices[a].pos, vertices[b].pos, vertices[c].pos);
+        let normal = (pb - pa).cross(&(pc - pa));
+        for index in [a, b, c] {
+            if missing[index] {
+                vertices[index].normal += normal;
+            }
+        }
+    }
+    for (vertex, missing) in vertices.iter_mut().zip(missing) {
+        if missing && vertex.normal.norm_squared() > 0.0 {
+            vertex.normal = vertex.normal.normalize();
+        }
+    }
+}
+
+/// Checks if two vertices are the same, the positions can be up to epsilon apart and the
+/// uvs and normals up to epsilon different
+fn near(a: &ModelVertex, b: &ModelVertex, epsilon: f32) -> bool {
+    (a.pos - b.pos).norm() <= epsilon
+        && (a.uv - b.uv).abs().max() <= epsilon
+        && (a.normal - b.normal).abs().max() <= epsilon
+}
+
+/// Merges the vertices that are the same as an earlier one, see [near]
+///
+/// Returns the vertices that are left and the new index of every old vertex. The vertices
+/// are put in a grid of cells epsilon wide so only the vertices in the cells around a
+/// vertex are checked
+fn weld_vertices(vertices: &[ModelVertex], epsilon: f32) -> (Vec<ModelVertex>, Vec<u32>) {
+    let cell = |pos: Vec3| -> [i64; 3] {
+        if epsilon > 0.0 {
+            [pos.x, pos.y, pos.z].map(|c| (c / epsilon).floor() as i64)
+        } else {
+            [pos.x, pos.y, pos.z].map(|c| c.to_bits() as i64)
+        }
+    };
+    let neighbours: &[i64] = if epsilon > 0.0 { &[-1, 0, 1] } else { &[0] };
+
+    let mut grid = HashMap::<[i64; 3], Vec<u32>>::new();
+    let mut welded = Vec::new();
+    let mut remap = Vec::with_capacity(vertices.len());
+    for vertex in vertices {
+        let [x, y, z] = cell(vertex.pos);
+        let found = neighbours
+            .iter()
+            .flat_map(|dx| neighbours.iter().map(move |dy| (dx, dy)))
+            .flat_map(|(dx, dy)| neighbours.iter().map(move |dz| [x + dx, y + dy, z + dz]))
+            .filter_map(|key| grid.get(&key))
+            .flatten()
+            .copied()
+            .find(|&index| near(&welded[index as usize], vertex, epsilon));
+        let index = found.unwrap_or_else(|| {
+            let index = welded.len() as u32;
+            welded.push(*vertex);
+            grid.entry([x, y, z]).or_default().push(index);
+            index
+        });
+        remap.push(index);
+    }
+    (welded, remap)
+}
+
+impl ObjModel {
+    /// Creates a model from a triangle soup, a list of triangles that don't share vertices
+    /// like STL files and most generated meshes have, the vertices are welded with
+    /// [ObjModel::weld]
+    ///
+    /// # Example
+    /// ```
+    /// let model = ObjModel::from_triangles(&triangles, 0.0001);
+    /// let mut mesh = model.into_mesh(vec![3, 2, 3], |v| *v)?;
+    /// ```
+    pub fn from_triangles(triangles: &[[ModelVertex; 3]], epsilon: f32) -> Self {
+        let mut model = ObjModel {
+            vertices: triangles.iter().flatten().copied().collect(),
+            indices: (0..triangles.len() as u32)
+                .map(|i| [i * 3, i * 3 + 1, i * 3 + 2])
+                .collect(),
+            has_uvs: true,
+            has_normals: true,
+        };
+        model.weld(epsilon);
+        model
+    }
+
+    /// Merges vertices that are the same or nearly the same, so they are only stored once
+    ///
+    /// Positions up to epsilon apart(in units) and uvs and normals up to epsilon different
+    /// count as the same, the first of them is kept. Triangles that end up with 2 of the
+    /// same vertex are removed. An epsilon of 0 only merges vertices that are exactly the
+    /// same
+    pub fn weld(&mut self, epsilon: f32) {
+        let (vertices, remap) = weld_vertices(&self.vertices, epsilon);
+        self.vertices = vertices;
+        self.indices = self
+            .indices
+            .iter()
+            .map(|triangle| triangle.map(|index| remap[index as usize]))
+            .filter(|[a, b, c]| a != b && b != c && a != c)
+            .collect();
+    }
+
+    /// Throws away the normals and calculates smooth ones, every vertex gets the average of
+    /// the normals of the triangles around it
+    ///
+    /// The vertices are welded first without their normals, so faces that only had split
+    /// vertices because of their normals are smoothed together. Vertices with different uvs
+    /// stay split, so uv seams can still show
+    pub fn smooth_normals(&mut self, epsilon: f32) {
+        for vertex in &mut self.vertices {
+            vertex.normal = vec3(0.0, 0.0, 0.0);
+        }
+        self.weld(epsilon);
+        calculate_normals(&mut self.vertices, &self.indices);
+        self.has_normals = false;
+    }
+
+    /// Parses the text of an OBJ file
+    ///
+    /// Lines that aren't geometry, like materials, groups and smoothing, are skipped
+    pub fn parse(src: &str) -> Result<Self, ModelError> {
+        let mut positions = Vec::<Vec3>::new();
+        let mut uvs = Vec::<Vec2>::new();
+        let mut normals = Vec::<Vec3>::new();
+        let mut model = ObjModel {
+            has_uvs: true,
+            has_normals: true,
+            ..Default::default()
+        };
+        // the vertex every (position, uv, normal) combination was turned into
+        let mut seen = HashMap::<(usize, Option<usize>, Option<usize>), u32>::new();
+
+        for (line, text) in src.lines().enumerate() {
+            let line = line + 1;
+            let text = text.split('#').next().unwrap_or("");
+            let mut parts = text.split_whitespace();
+            let Some(keyword) = parts.next() else {
+                continue;
+            };
+            let parts = parts.collect::<Vec<_>>();
+
+            match keyword {
+                "v" => positions.push(parse_floats::<3>(line, keyword, &parts)?.into()),
+                "vt" => {
+                    // the w of 3d texture coordinates is ignored
+                    let [u, v] = parse_floats::<2>(line, keyword, &parts)?;
+                    uvs.push(vec2(u, v));
+                }
+                "vn" => normals.push(parse_floats::<3>(line, keyword, &parts)?.into()),
+                "f" => {
+                    if parts.len() < 3 {
+                        return Err(ModelError::Syntax(
+                            line,
+                            "A face needs at least 3 vertices".to_string(),
+                        ));
+                    }
+                    let mut face = Vec::with_capacity(parts.len());
+                    for part in &parts {
+                        let mut indices = part.split('/');
+                        let pos =
+                            resolve_index(line, indices.next().unwrap_or(""), positions.len())?;
+                        let uv = match indices.next() {
+                            None | Some("") => None,
+                            Some(uv) => Some(resolve_index(line, uv, uvs.len())?),
+                        };
+                        let normal = match indices.next() {
+                            None | Some("") => None,
+                            Some(normal) => Some(resolve_index(line, normal, normals.len())?),
+                        };
+                        model.has_uvs &= uv.is_some();
+                        model.has_normals &= normal.is_some();
+
+                        let key = (pos, uv, normal);
+                        let index = match seen.get(&key) {
+                            Some(&index) => index,
+                            None => {
+                                let index = u32::try_from(model.vertices.len())
+                                    .map_err(|_| ModelError::TooManyVertices)?;
+                                model.vertices.push(ModelVertex {
+                                    pos: positions[pos],
+                                    uv: uv.map_or(vec2(0.0, 0.0), |uv| uvs[uv]),
+                                    normal: normal.map_or(vec3(0.0, 0.0, 0.0), |n| normals[n]),
+                                });
+                                seen.insert(key, index);
+                                index
+                            }
+                        };
+                        face.push(index);
+                    }
+                    for i in 1..face.len() - 1 {
+                        model.indices.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if model.indices.is_empty() {
+            return Err(ModelError::Empty);
+        }
+        if !model.has_normals {
+            calculate_normals(&mut model.vertices, &model.indices);
+        }
+        Ok(model)
+    }
+
+    /// Reads and parses the OBJ file at path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        ObjModel::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Turns the model into a mesh of your own vertex, vert_attr is the size of every
+    /// attribute of the vertex like in [Mesh::new]
+    pub fn into_mesh<Vertex: VertexTrait + 'static + Sync + Send>(
+        self,
+        vert_attr: Vec<u32>,
+        to_vertex: impl Fn(&ModelVertex) -> Vertex,
+    ) -> Result<Mesh<Vertex>, LighthouseError> {
+        let vertices = self.vertices.iter().map(to_vertex).collect();
+        Mesh::new(vertices, vert_attr, self.indices)
+    }
+}
+
+impl Asset for ObjModel {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        ObjModel::parse(&String::from_bytes(bytes)?).map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+impl Mesh<ModelVertex> {
+    /// Loads a mesh from the Wavefront OBJ file at path, see [ObjModel]
+    pub fn from_obj(path: impl AsRef<Path>) -> Result<Self, LighthouseError> {
+        let model = ObjModel::load(path)?;
+        Ok(Mesh::from_vertices(model.vertices, model.indices))
+    }
+}
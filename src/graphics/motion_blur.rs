@@ -0,0 +1,370 @@
+use nalgebra_glm::Mat4;
+
+use super::{
+    fullscreen::FullscreenPass, recovery::ContextId, shader::ShaderProgram,
+    target_pool::WindowTargets, texture::Texture, uniform::Uniform, *,
+};
+
+const VELOCITY_VERT: &str = "#version 330 core
+layout (location = 0) in vec3 pos;
+
+uniform mat4 model_view_proj;
+uniform mat4 prev_model_view_proj;
+
+out vec4 current_clip;
+out vec4 previous_clip;
+
+void main() {
+    current_clip = model_view_proj * vec4(pos, 1.0);
+    previous_clip = prev_model_view_proj * vec4(pos, 1.0);
+    gl_Position = current_clip;
+}";
+
+const VELOCITY_FRAG: &str = "#version 330 core
+in vec4 current_clip;
+in vec4 previous_clip;
+
+out vec2 velocity;
+
+void main() {
+    // how far the pixel moved since the last frame, in uvs
+    velocity = (current_clip.xy / current_clip.w - previous_clip.xy / previous_clip.w) * 0.5;
+}";
+
+const MOTION_BLUR_FRAG: &str = "#version 330 core
+in vec2 uv;
+
+uniform sampler2D input_texture;
+uniform sampler2D velocity_texture;
+uniform sampler2D velocity_depth;
+uniform mat4 inverse_view_proj;
+uniform mat4 prev_view_proj;
+uniform float intensity;
+uniform float max_length;
+uniform int samples;
+
+out vec4 color;
+
+void main() {
+    vec2 velocity = texture(velocity_texture, uv).rg;
+    if (texture(velocity_depth, uv).r >= 1.0) {
+        // nothing was drawn here, so only the camera moved
+        vec4 world = inverse_view_proj * vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+        vec4 previous = prev_view_proj * vec4(world.xyz / world.w, 1.0);
+        velocity = (uv * 2.0 - 1.0 - previous.xy / previous.w) * 0.5;
+    }
+    velocity *= intensity;
+    float len = length(velocity);
+    if (len > max_length) {
+        velocity *= max_length / len;
+    }
+
+    vec4 sum = vec4(0.0);
+    for (int i = 0; i < samples; i++) {
+        float t = samples > 1 ? float(i) / float(samples - 1) - 0.5 : 0.0;
+        sum += texture(input_texture, uv + velocity * t);
+    }
+    color = sum / float(max(samples, 1));
+}";
+
+/// How strong a [MotionBlur] is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionBlurSettings {
+    /// How much of the movement of a frame is blurred, like the shutter of a camera
+    pub intensity: f32,
+    /// How many times every pixel reads the scene, more is smoother but slower
+    pub samples: u32,
+    /// The longest a blur can be(in uvs), so fast objects dont smear across the screen
+    pub max_length: f32,
+}
+
+impl Default for MotionBlurSettings {
+    fn default() -> Self {
+        MotionBlurSettings {
+            intensity: 0.5,
+            samples: 8,
+            max_length: 0.05,
+        }
+    }
+}
+
+/// How far every pixel moved since the last frame, what [MotionBlur] blurs along
+///
+/// Every object is drawn into it a second time with the matrices of this frame and the
+/// last one. Pixels no object was drawn to get the movement of the camera. Only the
+/// position attribute at location 0 is read, so skinned and morphed meshes move as if
+/// they were still
+///
+/// The framebuffer and its textures are deleted when it is dropped, see [ContextId]
+///
+/// # Example
+/// ```
+/// let mut velocity = VelocityBuffer::new(800, 600)?;
+///
+/// 'main_loop: loop {
+///     velocity.begin(&view_proj);
+///     for (mesh, model, prev_model) in &objects {
+///         velocity.object(model, prev_model);
+///         mesh.draw();
+///     }
+///     velocity.end();
+/// }
+/// ```
+pub struct VelocityBuffer {
+    /// The framebuffer id
+    pub id: u32,
+    /// The RG16F texture the velocity is drawn into
+    pub velocity: u32,
+    /// The depth texture, so objects hide the velocity of the objects behind them
+    pub depth: u32,
+    /// Width of the buffer(in pixels)
+    pub width: i32,
+    /// Height of the buffer(in pixels)
+    pub height: i32,
+    program: ShaderProgram,
+    view_proj: Option<Mat4>,
+    prev_view_proj: Option<Mat4>,
+    /// The viewport before begin, put back by end
+    viewport: [i32; 4],
+    context: ContextId,
+}
+
+impl VelocityBuffer {
+    /// Creates a new velocity buffer of size width by height
+    pub fn new(width: i32, height: i32) -> Result<Self, LighthouseError> {
+        let mut id = 0;
+        let mut textures = [0; 2];
+        unsafe {
+            glGenFramebuffers(1, &mut id);
+            glGenTextures(2, textures.as_mut_ptr());
+        }
+        if id == 0 || textures.contains(&0) {
+            return Err(LighthouseError::BufferAlloc(
+                "a velocity buffer".to_string(),
+            ));
+        }
+        for texture in textures {
+            unsafe {
+                glBindTexture(GL_TEXTURE_2D, texture);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_NEAREST as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_NEAREST as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
+            }
+        }
+
+        let mut buffer = VelocityBuffer {
+            id,
+            velocity: textures[0],
+            depth: textures[1],
+            width: 0,
+            height: 0,
+            program: ShaderProgram::from_vert_frag(VELOCITY_VERT, VELOCITY_FRAG)?,
+            view_proj: None,
+            prev_view_proj: None,
+            viewport: [0; 4],
+            context: ContextId::current(),
+        };
+        buffer.resize(width, height)?;
+        Ok(buffer)
+    }
+
+    /// Reallocates the textures of the buffer for the new size
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        self.width = width;
+        self.height = height;
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, self.velocity);
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_RG16F as i32,
+                width,
+                height,
+                0,
+                GL_RG,
+                GL_FLOAT,
+                std::ptr::null(),
+            );
+            glBindTexture(GL_TEXTURE_2D, self.depth);
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_DEPTH_COMPONENT24 as i32,
+                width,
+                height,
+                0,
+                GL_DEPTH_COMPONENT,
+                GL_FLOAT,
+                std::ptr::null(),
+            );
+            glBindTexture(GL_TEXTURE_2D, 0);
+
+            glBindFramebuffer(GL_FRAMEBUFFER, self.id);
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_COLOR_ATTACHMENT0,
+                GL_TEXTURE_2D,
+                self.velocity,
+                0,
+            );
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_DEPTH_ATTACHMENT,
+                GL_TEXTURE_2D,
+                self.depth,
+                0,
+            );
+            let status = glCheckFramebufferStatus(GL_FRAMEBUFFER);
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+
+            if status != GL_FRAMEBUFFER_COMPLETE {
+                return Err(LighthouseError::IncompleteFramebuffer(
+                    "Velocity buffer".to_string(),
+                    status,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds and clears the buffer for a new frame, view_proj is the matrix of the camera
+    ///
+    /// The matrix of the last frame is remembered, on the first frame nothing moves
+    pub fn begin(&mut self, view_proj: &Mat4) {
+        self.prev_view_proj = self.view_proj.or(Some(*view_proj));
+        self.view_proj = Some(*view_proj);
+
+        unsafe {
+            let mut clear = [0.0; 4];
+            glGetFloatv(GL_COLOR_CLEAR_VALUE, clear.as_mut_ptr());
+            glGetIntegerv(GL_VIEWPORT, self.viewport.as_mut_ptr());
+            glBindFramebuffer(GL_FRAMEBUFFER, self.id);
+            glViewport(0, 0, self.width, self.height);
+            glClearColor(0.0, 0.0, 0.0, 0.0);
+            glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+            glClearColor(clear[0], clear[1], clear[2], clear[3]);
+        }
+        self.program.use_program();
+    }
+
+    /// Sets the model matrix of the next object drawn and the one it had the last frame
+    pub fn object(&self, model: &Mat4, prev_model: &Mat4) {
+        let view_proj = self.view_proj.unwrap_or_default();
+        let prev_view_proj = self.prev_view_proj.unwrap_or_default();
+        Uniform::new(&self.program, "model_view_proj")
+            .set_uniform_matrix(false, (view_proj * model).into());
+        Uniform::new(&self.program, "prev_model_view_proj")
+            .set_uniform_matrix(false, (prev_view_proj * prev_model).into());
+    }
+
+    /// Binds the window as the framebuffer again and puts back the viewport it had before
+    /// [VelocityBuffer::begin]
+    pub fn end(&self) {
+        let [x, y, width, height] = self.viewport;
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+            glViewport(x, y, width, height);
+        }
+    }
+
+    /// Forgets the last frame, e.g. after the camera cuts to somewhere else
+    pub fn reset(&mut self) {
+        self.view_proj = None;
+        self.prev_view_proj = None;
+    }
+
+    /// Deletes the framebuffer, its textures and the shader program now instead of when it
+    /// is dropped
+    pub fn delete(self) {}
+}
+
+impl WindowTargets for VelocityBuffer {
+    fn window_resized(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        self.resize(width, height)
+    }
+
+    fn recreate_targets(&mut self) -> Result<(), LighthouseError> {
+        // the shader program was gone with the context too
+        *self = VelocityBuffer::new(self.width, self.height)?;
+        Ok(())
+    }
+}
+
+impl Drop for VelocityBuffer {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe {
+                glDeleteTextures(2, [self.velocity, self.depth].as_ptr());
+                glDeleteFramebuffers(1, &self.id);
+            }
+        }
+    }
+}
+
+/// A post pass that blurs the scene along the movement of the camera and objects
+///
+/// # Example
+/// ```
+/// let blur = MotionBlur::new()?;
+///
+/// 'main_loop: loop {
+///     // draw the scene into target and the objects into velocity
+///     blur.draw(&target.color, &velocity);
+/// }
+/// ```
+pub struct MotionBlur {
+    /// How strong the blur is
+    pub settings: MotionBlurSettings,
+    pass: FullscreenPass,
+}
+
+impl MotionBlur {
+    /// Creates a new motion blur with the default settings
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(MotionBlur {
+            settings: MotionBlurSettings::default(),
+            pass: FullscreenPass::new(MOTION_BLUR_FRAG)?,
+        })
+    }
+
+    /// Sets how strong the blur is
+    pub fn settings(mut self, settings: MotionBlurSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Draws scene blurred along velocity into the bound framebuffer
+    ///
+    /// The velocity and its depth are bound to texture units 1 and 2
+    pub fn draw(&self, scene: &Texture, velocity: &VelocityBuffer) {
+        let view_proj = velocity.view_proj.unwrap_or_default();
+        let prev_view_proj = velocity.prev_view_proj.unwrap_or(view_proj);
+        unsafe {
+            glActiveTexture(GL_TEXTURE1);
+            glBindTexture(GL_TEXTURE_2D, velocity.velocity);
+            glActiveTexture(GL_TEXTURE2);
+            glBindTexture(GL_TEXTURE_2D, velocity.depth);
+        }
+
+        let pass = &self.pass;
+        pass.uniform("velocity_texture").set_uniform_i(&[1]);
+        pass.uniform("velocity_depth").set_uniform_i(&[2]);
+        pass.uniform("inverse_view_proj")
+            .set_uniform_matrix(false, view_proj.try_inverse().unwrap_or_default().into());
+        pass.uniform("prev_view_proj")
+            .set_uniform_matrix(false, prev_view_proj.into());
+        pass.uniform("intensity")
+            .set_uniform_f(&[self.settings.intensity]);
+        pass.uniform("max_length")
+            .set_uniform_f(&[self.settings.max_length]);
+        pass.uniform("samples")
+            .set_uniform_i(&[self.settings.samples.max(1) as i32]);
+        pass.draw(scene);
+    }
+
+    /// Deletes the pass
+    pub fn delete(self) {
+        self.pass.delete();
+    }
+}
@@ -0,0 +1,570 @@
+use std::ffi::{c_char, c_void};
+
+use nalgebra_glm::{vec3, Mat4, Vec3};
+
+use super::{buffer::*, shader::ShaderProgram, uniform::Uniform, vertex::VertexArray, *};
+
+/// The most attractors a [GpuParticles] system can have
+pub const MAX_ATTRACTORS: usize = 8;
+
+/// How many particles one compute work group simulates
+const WORK_GROUP_SIZE: usize = 256;
+
+/// Shader storage buffers, missing from ogl33 like the rest of opengl 4.3
+const GL_SHADER_STORAGE_BUFFER: u32 = 0x90D2;
+/// Makes vertex attribute reads wait for shader storage writes
+const GL_VERTEX_ATTRIB_ARRAY_BARRIER_BIT: u32 = 0x1;
+/// Makes shader storage reads wait for shader storage writes
+const GL_SHADER_STORAGE_BARRIER_BIT: u32 = 0x2000;
+
+/// The simulation shared by the compute shader and the transform feedback shader
+///
+/// Every particle is a `vec4 pos_life`, the position and how long it has left to live(in
+/// seconds), and a `vec4 vel_seed`, the velocity and a random seed. Particles with no life
+/// left count up to 0 and are respawned at the emitter
+const SIMULATE_GLSL: &str = "uniform float delta;
+uniform float time;
+uniform vec3 gravity;
+uniform float drag;
+uniform vec3 emitter_pos;
+uniform float emitter_radius;
+uniform vec3 emitter_box;
+uniform float spawn_fraction;
+uniform vec3 emitter_dir;
+uniform float emitter_spread;
+uniform vec2 speed_range;
+uniform vec2 life_range;
+uniform vec4 attractors[8];
+uniform int attractor_count;
+
+uniform bool collide;
+uniform sampler2D depth_texture;
+uniform mat4 view_proj;
+uniform mat4 inverse_view_proj;
+uniform float bounce;
+
+float hash(float n) {
+    return fract(sin(n) * 43758.5453123);
+}
+
+vec3 random_dir(float seed) {
+    float z = hash(seed) * 2.0 - 1.0;
+    float a = hash(seed + 1.7) * 6.2831853;
+    float r = sqrt(1.0 - z * z);
+    return vec3(r * cos(a), r * sin(a), z);
+}
+
+vec3 depth_world(vec2 uv) {
+    float depth = textureLod(depth_texture, uv, 0.0).r;
+    vec4 world = inverse_view_proj * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+    return world.xyz / world.w;
+}
+
+void simulate(inout vec4 pos_life, inout vec4 vel_seed, float id) {
+    if (pos_life.w <= 0.0) {
+        pos_life.w += delta;
+        if (pos_life.w > 0.0) {
+            float seed = hash(id * 0.1234 + time) * 1000.0;
+            if (hash(seed + 11.3) >= spawn_fraction) {
+                // try again after the shortest life
+                pos_life.w = -life_range.x;
+                return;
+            }
+            vec3 dir = normalize(mix(emitter_dir, random_dir(seed), emitter_spread) + 1e-5);
+            float speed = mix(speed_range.x, speed_range.y, hash(seed + 3.1));
+            vec3 in_box = vec3(hash(seed + 13.1), hash(seed + 15.7), hash(seed + 17.9)) * 2.0 - 1.0;
+            pos_life.xyz = emitter_pos + random_dir(seed + 5.3) * emitter_radius * hash(seed + 7.9)
+                + in_box * emitter_box;
+            pos_life.w = mix(life_range.x, life_range.y, hash(seed + 9.4));
+            vel_seed = vec4(dir * speed, seed);
+        }
+        return;
+    }
+
+    vec3 accel = gravity;
+    for (int i = 0; i < attractor_count; i++) {
+        vec3 to = attractors[i].xyz - pos_life.xyz;
+        accel += normalize(to + 1e-5) * attractors[i].w / (dot(to, to) + 1.0);
+    }
+    vel_seed.xyz += accel * delta;
+    vel_seed.xyz *= max(1.0 - drag * delta, 0.0);
+    vec3 next = pos_life.xyz + vel_seed.xyz * delta;
+
+    if (collide) {
+        vec4 clip = view_proj * vec4(next, 1.0);
+        vec3 ndc = clip.xyz / clip.w;
+        vec2 uv = ndc.xy * 0.5 + 0.5;
+        if (clip.w > 0.0 && all(greaterThan(uv, vec2(0.0))) && all(lessThan(uv, vec2(1.0)))) {
+            float scene = textureLod(depth_texture, uv, 0.0).r;
+            // behind the visible surface but close to it, so it hit the surface
+            if (ndc.z * 0.5 + 0.5 > scene && ndc.z * 0.5 + 0.5 < scene + 0.002) {
+                vec2 texel = 1.0 / vec2(textureSize(depth_texture, 0));
+                vec3 p = depth_world(uv);
+                vec3 normal = normalize(cross(depth_world(uv + vec2(texel.x, 0.0)) - p, depth_world(uv + vec2(0.0, texel.y)) - p));
+                if (dot(normal, vel_seed.xyz) > 0.0) {
+                    normal = -normal;
+                }
+                vel_seed.xyz = reflect(vel_seed.xyz, normal) * bounce;
+                next = pos_life.xyz;
+            }
+        }
+    }
+
+    pos_life.xyz = next;
+    pos_life.w -= delta;
+}";
+
+/// Simulates with transform feedback, every particle is a point whose outputs are captured
+const FEEDBACK_VERT: &str = "#version 330 core
+layout (location = 0) in vec4 pos_life;
+layout (location = 1) in vec4 vel_seed;
+
+out vec4 out_pos_life;
+out vec4 out_vel_seed;
+
+{simulate}
+
+void main() {
+    out_pos_life = pos_life;
+    out_vel_seed = vel_seed;
+    simulate(out_pos_life, out_vel_seed, float(gl_VertexID));
+}";
+
+/// Simulates with a compute shader, the particles are updated in place
+const COMPUTE_SRC: &str = "#version 430 core
+layout (local_size_x = 256) in;
+
+layout (std430, binding = 0) buffer Particles {
+    vec4 particles[];
+};
+
+uniform uint particle_count;
+
+{simulate}
+
+void main() {
+    uint id = gl_GlobalInvocationID.x;
+    if (id >= particle_count) {
+        return;
+    }
+    vec4 pos_life = particles[id * 2u];
+    vec4 vel_seed = particles[id * 2u + 1u];
+    simulate(pos_life, vel_seed, float(id));
+    particles[id * 2u] = pos_life;
+    particles[id * 2u + 1u] = vel_seed;
+}";
+
+const RENDER_VERT: &str = "#version 330 core
+layout (location = 0) in vec4 pos_life;
+layout (location = 1) in vec4 vel_seed;
+
+uniform mat4 view_proj;
+uniform float point_size;
+uniform vec2 life_range;
+
+out float life;
+
+void main() {
+    life = clamp(pos_life.w / life_range.y, 0.0, 1.0);
+    gl_Position = view_proj * vec4(pos_life.xyz, 1.0);
+    gl_PointSize = pos_life.w > 0.0 ? point_size / max(gl_Position.w, 0.001) : 0.0;
+}";
+
+const RENDER_FRAG: &str = "#version 330 core
+in float life;
+
+uniform vec4 start_color;
+uniform vec4 end_color;
+
+out vec4 frag_color;
+
+void main() {
+    vec2 p = gl_PointCoord * 2.0 - 1.0;
+    float falloff = 1.0 - dot(p, p);
+    if (falloff <= 0.0) {
+        discard;
+    }
+    frag_color = mix(end_color, start_color, life) * vec4(1.0, 1.0, 1.0, falloff);
+}";
+
+/// The opengl 4.3 functions compute particles need, ogl33 only loads opengl 3.3
+#[derive(Clone, Copy)]
+pub struct ComputeFns {
+    dispatch_compute: extern "system" fn(u32, u32, u32),
+    memory_barrier: extern "system" fn(u32),
+}
+
+impl ComputeFns {
+    /// Loads the functions with the same loader given to `load_gl_with`, None if the
+    /// context is older than opengl 4.3
+    ///
+    /// # Example
+    /// ```
+    /// let compute = ComputeFns::load(|name| win.get_proc_address(name));
+    /// ```
+    pub fn load(mut loader: impl FnMut(*const c_char) -> *const c_void) -> Option<Self> {
+        let (mut major, mut minor) = (0, 0);
+        unsafe {
+            glGetIntegerv(GL_MAJOR_VERSION, &mut major);
+            glGetIntegerv(GL_MINOR_VERSION, &mut minor);
+        }
+        if (major, minor) < (4, 3) {
+            return None;
+        }
+
+        let dispatch_compute = loader(to_cstr("glDispatchCompute").as_ptr());
+        let memory_barrier = loader(to_cstr("glMemoryBarrier").as_ptr());
+        if dispatch_compute.is_null() || memory_barrier.is_null() {
+            return None;
+        }
+        // the pointers come from the driver and have the signatures of the gl functions
+        unsafe {
+            Some(ComputeFns {
+                dispatch_compute: std::mem::transmute::<
+                    *const c_void,
+                    extern "system" fn(u32, u32, u32),
+                >(dispatch_compute),
+                memory_barrier: std::mem::transmute::<*const c_void, extern "system" fn(u32)>(
+                    memory_barrier,
+                ),
+            })
+        }
+    }
+}
+
+/// How the particles are simulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleBackend {
+    /// A compute shader updates the particles in place, needs opengl 4.3
+    Compute,
+    /// A vertex shader updates the particles into a second buffer, works on opengl 3.3
+    TransformFeedback,
+}
+
+/// A point that pulls particles towards it, or pushes them away with a negative strength
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attractor {
+    /// Where the attractor is
+    pub pos: Vec3,
+    /// How hard it pulls, the pull gets weaker with the square of the distance
+    pub strength: f32,
+}
+
+/// Makes particles bounce off whatever was drawn into a depth texture, e.g. the depth of
+/// the scene from the camera
+///
+/// Only surfaces the camera sees can be hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthCollision {
+    /// The id of the depth texture
+    pub depth_texture: u32,
+    /// The matrix the depth texture was drawn with
+    pub view_proj: Mat4,
+    /// How much speed a particle keeps when it bounces, from 0 to 1
+    pub bounce: f32,
+}
+
+/// How particles are spawned and moved
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleSettings {
+    /// Where particles spawn
+    pub emitter_pos: Vec3,
+    /// Particles spawn up to this far from emitter_pos
+    pub emitter_radius: f32,
+    /// Particles also spawn anywhere in a box of these half sizes around emitter_pos
+    pub emitter_box: Vec3,
+    /// The direction particles are shot in
+    pub emitter_dir: Vec3,
+    /// From 0(every particle goes in emitter_dir) to 1(every direction)
+    pub emitter_spread: f32,
+    /// How many of the particles respawn when they die, from 0(none) to 1(all)
+    pub spawn_fraction: f32,
+    /// The slowest and fastest speed a particle spawns with
+    pub speed_range: (f32, f32),
+    /// The shortest and longest a particle lives(in seconds)
+    pub life_range: (f32, f32),
+    /// Added to the velocity of every particle every second
+    pub gravity: Vec3,
+    /// How much velocity is lost every second
+    pub drag: f32,
+    /// Points that pull on the particles, at most [MAX_ATTRACTORS]
+    pub attractors: Vec<Attractor>,
+    /// What particles bounce off, if anything
+    pub collision: Option<DepthCollision>,
+    /// The color of new particles
+    pub start_color: [f32; 4],
+    /// The color of particles about to die
+    pub end_color: [f32; 4],
+    /// The size of a particle 1 unit away from the camera(in pixels)
+    pub point_size: f32,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        ParticleSettings {
+            emitter_pos: vec3(0.0, 0.0, 0.0),
+            emitter_radius: 0.0,
+            emitter_box: vec3(0.0, 0.0, 0.0),
+            emitter_dir: vec3(0.0, 1.0, 0.0),
+            emitter_spread: 0.3,
+            spawn_fraction: 1.0,
+            speed_range: (1.0, 3.0),
+            life_range: (1.0, 3.0),
+            gravity: vec3(0.0, -9.81, 0.0),
+            drag: 0.0,
+            attractors: Vec::new(),
+            collision: None,
+            start_color: [1.0, 1.0, 1.0, 1.0],
+            end_color: [1.0, 1.0, 1.0, 0.0],
+            point_size: 20.0,
+        }
+    }
+}
+
+/// A particle system simulated and drawn entirely on the gpu, made for hundreds of
+/// thousands of particles
+///
+/// # Example
+/// ```
+/// let compute = ComputeFns::load(|name| win.get_proc_address(name));
+/// let mut sparks = GpuParticles::new(200_000, ParticleSettings::default(), compute)?;
+/// sparks.settings.attractors.push(Attractor { pos: vec3(0.0, 2.0, 0.0), strength: 20.0 });
+///
+/// 'main_loop: loop {
+///     sparks.update(world.env.time.delta);
+///     sparks.draw(&view_proj);
+/// }
+/// ```
+pub struct GpuParticles {
+    /// How the particles are simulated
+    pub backend: ParticleBackend,
+    /// How many particles there are
+    pub count: usize,
+    /// How particles are spawned, moved and drawn
+    pub settings: ParticleSettings,
+    /// The program that moves the particles
+    update: ShaderProgram,
+    /// The program that draws the particles
+    render: ShaderProgram,
+    compute: Option<ComputeFns>,
+    /// Transform feedback reads one buffer and writes the other, compute only uses the first
+    buffers: [Buffer; 2],
+    vaos: [VertexArray; 2],
+    current: usize,
+    time: f32,
+}
+
+impl GpuParticles {
+    /// Creates count particles, they are simulated with a compute shader when compute is
+    /// given and with transform feedback otherwise
+    pub fn new(
+        count: usize,
+        settings: ParticleSettings,
+        compute: Option<ComputeFns>,
+    ) -> Result<Self, LighthouseError> {
+        let (backend, update) = match compute {
+            Some(_) => (
+                ParticleBackend::Compute,
+                ShaderProgram::from_compute(&COMPUTE_SRC.replace("{simulate}", SIMULATE_GLSL))?,
+            ),
+            None => (
+                ParticleBackend::TransformFeedback,
+                ShaderProgram::from_vert_feedback(
+                    &FEEDBACK_VERT.replace("{simulate}", SIMULATE_GLSL),
+                    &["out_pos_life", "out_vel_seed"],
+                )?,
+            ),
+        };
+        let render = ShaderProgram::from_vert_frag(RENDER_VERT, RENDER_FRAG)?;
+
+        // every particle starts dead with a different wait, so they dont all spawn at once
+        let mut data: Vec<f32> = Vec::with_capacity(count * 8);
+        for i in 0..count {
+            let wait = (i as f32 / count as f32) * settings.life_range.1;
+            data.extend_from_slice(&[0.0, 0.0, 0.0, -wait, 0.0, 0.0, 0.0, i as f32]);
+        }
+
+        let buffers = [
+            Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+            Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+        ];
+        let vaos = [
+            VertexArray::new().ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+            VertexArray::new().ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+        ];
+        for (buffer, vao) in buffers.iter().zip(&vaos) {
+            vao.bind();
+            buffer.bind(BufferType::Array);
+            buffer_data(
+                BufferType::Array,
+                bytemuck::cast_slice(&data),
+                BufferUsage::DynamicCopy,
+            );
+            for location in 0..2 {
+                unsafe {
+                    glVertexAttribPointer(
+                        location,
+                        4,
+                        GL_FLOAT,
+                        GL_FALSE,
+                        (8 * std::mem::size_of::<f32>()) as i32,
+                        (location as usize * 4 * std::mem::size_of::<f32>()) as *const _,
+                    );
+                    glEnableVertexAttribArray(location);
+                }
+            }
+        }
+        VertexArray::clear_binding();
+
+        Ok(GpuParticles {
+            backend,
+            count,
+            settings,
+            update,
+            render,
+            compute,
+            buffers,
+            vaos,
+            current: 0,
+            time: 0.0,
+        })
+    }
+
+    /// Moves the particles delta seconds forward
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+        self.set_simulation_uniforms(delta);
+
+        match (self.backend, self.compute) {
+            (ParticleBackend::Compute, Some(compute)) => unsafe {
+                Uniform::new(&self.update, "particle_count").set_uniform_ui(&[self.count as u32]);
+                glBindBufferBase(GL_SHADER_STORAGE_BUFFER, 0, self.buffers[0].0);
+                (compute.dispatch_compute)(self.count.div_ceil(WORK_GROUP_SIZE) as u32, 1, 1);
+                (compute.memory_barrier)(
+                    GL_VERTEX_ATTRIB_ARRAY_BARRIER_BIT | GL_SHADER_STORAGE_BARRIER_BIT,
+                );
+            },
+            _ => {
+                let next = 1 - self.current;
+                self.vaos[self.current].bind();
+                unsafe {
+                    glEnable(GL_RASTERIZER_DISCARD);
+                    glBindBufferBase(GL_TRANSFORM_FEEDBACK_BUFFER, 0, self.buffers[next].0);
+                    glBeginTransformFeedback(GL_POINTS);
+                    glDrawArrays(GL_POINTS, 0, self.count as i32);
+                    glEndTransformFeedback();
+                    glBindBufferBase(GL_TRANSFORM_FEEDBACK_BUFFER, 0, 0);
+                    glDisable(GL_RASTERIZER_DISCARD);
+                }
+                VertexArray::clear_binding();
+                self.current = next;
+            }
+        }
+    }
+
+    /// Draws the particles as points with additive blending, view_proj turns world positions
+    /// into clip space
+    pub fn draw(&self, view_proj: &Mat4) {
+        self.render.use_program();
+        let uniform = |name| Uniform::new(&self.render, name);
+        uniform("view_proj").set_uniform_matrix(false, (*view_proj).into());
+        uniform("point_size").set_uniform_f(&[self.settings.point_size]);
+        uniform("life_range")
+            .set_uniform_f(&[self.settings.life_range.0, self.settings.life_range.1]);
+        uniform("start_color").set_uniform_f(&self.settings.start_color);
+        uniform("end_color").set_uniform_f(&self.settings.end_color);
+
+        self.vaos[self.current].bind();
+        unsafe {
+            let blend = glIsEnabled(GL_BLEND) == GL_TRUE;
+            let depth_mask = {
+                let mut mask = 0;
+                glGetIntegerv(GL_DEPTH_WRITEMASK, &mut mask);
+                mask
+            };
+            let mut blend_func = [0; 4];
+            for (value, name) in blend_func.iter_mut().zip([
+                GL_BLEND_SRC_RGB,
+                GL_BLEND_DST_RGB,
+                GL_BLEND_SRC_ALPHA,
+                GL_BLEND_DST_ALPHA,
+            ]) {
+                glGetIntegerv(name, value);
+            }
+            glEnable(GL_BLEND);
+            glBlendFunc(GL_SRC_ALPHA, GL_ONE);
+            glDepthMask(GL_FALSE);
+            glEnable(GL_PROGRAM_POINT_SIZE);
+
+            glDrawArrays(GL_POINTS, 0, self.count as i32);
+
+            glDisable(GL_PROGRAM_POINT_SIZE);
+            glDepthMask(depth_mask as u8);
+            let [src_rgb, dst_rgb, src_alpha, dst_alpha] = blend_func.map(|factor| factor as u32);
+            glBlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+            if !blend {
+                glDisable(GL_BLEND);
+            }
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Deletes the programs and buffers of the particles
+    pub fn delete(self) {
+        self.update.delete();
+        self.render.delete();
+        // the buffers and vaos delete themselves when they are dropped
+    }
+
+    /// Gives the settings to the update program
+    fn set_simulation_uniforms(&self, delta: f32) {
+        let settings = &self.settings;
+        self.update.use_program();
+        let uniform = |name| Uniform::new(&self.update, name);
+        let xyz = |v: &Vec3| [v.x, v.y, v.z];
+
+        uniform("delta").set_uniform_f(&[delta]);
+        uniform("time").set_uniform_f(&[self.time]);
+        uniform("gravity").set_uniform_f(&xyz(&settings.gravity));
+        uniform("drag").set_uniform_f(&[settings.drag]);
+        uniform("emitter_pos").set_uniform_f(&xyz(&settings.emitter_pos));
+        uniform("emitter_radius").set_uniform_f(&[settings.emitter_radius]);
+        uniform("emitter_box").set_uniform_f(&xyz(&settings.emitter_box));
+        uniform("spawn_fraction").set_uniform_f(&[settings.spawn_fraction]);
+        uniform("emitter_dir").set_uniform_f(&xyz(&settings.emitter_dir));
+        uniform("emitter_spread").set_uniform_f(&[settings.emitter_spread]);
+        uniform("speed_range").set_uniform_f(&[settings.speed_range.0, settings.speed_range.1]);
+        uniform("life_range").set_uniform_f(&[settings.life_range.0, settings.life_range.1]);
+
+        let attractors: Vec<f32> = settings
+            .attractors
+            .iter()
+            .take(MAX_ATTRACTORS)
+            .flat_map(|a| [a.pos.x, a.pos.y, a.pos.z, a.strength])
+            .collect();
+        unsafe {
+            glUniform4fv(
+                uniform("attractors").0,
+                (attractors.len() / 4) as i32,
+                attractors.as_ptr(),
+            );
+        }
+        uniform("attractor_count").set_uniform_i(&[(attractors.len() / 4) as i32]);
+
+        match &settings.collision {
+            Some(collision) => {
+                uniform("collide").set_uniform_i(&[1]);
+                uniform("bounce").set_uniform_f(&[collision.bounce]);
+                uniform("view_proj").set_uniform_matrix(false, collision.view_proj.into());
+                let inverse = collision.view_proj.try_inverse().unwrap_or_default();
+                uniform("inverse_view_proj").set_uniform_matrix(false, inverse.into());
+                uniform("depth_texture").set_uniform_i(&[0]);
+                unsafe {
+                    glActiveTexture(GL_TEXTURE0);
+                    glBindTexture(GL_TEXTURE_2D, collision.depth_texture);
+                }
+            }
+            None => uniform("collide").set_uniform_i(&[0]),
+        }
+    }
+}
@@ -0,0 +1,678 @@
+use super::{
+    blend::BlendFactor, render_state::RenderState, shader::ShaderProgram, vertex::AttrFormat, *,
+};
+use crate::ECS::mesh::{Mesh, VertexTrait, COLOR_LOCATION, LIGHTMAP_UV_LOCATION};
+
+/// How the color of what is drawn is mixed with what is already in the framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// The new color replaces the old one
+    #[default]
+    Opaque,
+    /// The new color is laid over the old one by its alpha
+    Alpha,
+    /// Like [BlendMode::Alpha] but the new color is already multiplied by its alpha
+    Premultiplied,
+    /// The new color is added onto the old one, for light and fire
+    Additive,
+    /// The old color is multiplied by the new one, for shadows and tints
+    Multiply,
+    /// Any blend factors, a [Blend](super::blend::Blend) can blend the alpha differently too
+    Custom {
+        /// What the new color is multiplied by
+        src: BlendFactor,
+        /// What the old color is multiplied by
+        dst: BlendFactor,
+    },
+}
+
+impl BlendMode {
+    /// Gets the source and destination factors of the mode, None if blending is off
+    pub fn factors(self) -> Option<(BlendFactor, BlendFactor)> {
+        use BlendFactor::*;
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some((SrcAlpha, OneMinusSrcAlpha)),
+            BlendMode::Premultiplied => Some((One, OneMinusSrcAlpha)),
+            BlendMode::Additive => Some((SrcAlpha, One)),
+            BlendMode::Multiply => Some((DstColor, Zero)),
+            BlendMode::Custom { src, dst } => Some((src, dst)),
+        }
+    }
+}
+
+/// How a depth or stencil value is compared with the one in the framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompareFunc {
+    /// Never passes
+    Never,
+    /// Passes if the new value is less
+    #[default]
+    Less,
+    /// Passes if the values are the same
+    Equal,
+    /// Passes if the new value is less or the same
+    LessEqual,
+    /// Passes if the new value is greater
+    Greater,
+    /// Passes if the values aren't the same
+    NotEqual,
+    /// Passes if the new value is greater or the same
+    GreaterEqual,
+    /// Always passes
+    Always,
+}
+
+impl CompareFunc {
+    /// Gets the opengl enum of the function
+    pub fn gl(self) -> u32 {
+        match self {
+            CompareFunc::Never => GL_NEVER,
+            CompareFunc::Less => GL_LESS,
+            CompareFunc::Equal => GL_EQUAL,
+            CompareFunc::LessEqual => GL_LEQUAL,
+            CompareFunc::Greater => GL_GREATER,
+            CompareFunc::NotEqual => GL_NOTEQUAL,
+            CompareFunc::GreaterEqual => GL_GEQUAL,
+            CompareFunc::Always => GL_ALWAYS,
+        }
+    }
+}
+
+/// How the depth buffer is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthState {
+    /// Whether fragments are tested against the depth buffer
+    pub test: bool,
+    /// Whether fragments that pass write their depth
+    pub write: bool,
+    /// How the depth of a fragment is compared
+    pub func: CompareFunc,
+}
+
+impl DepthState {
+    /// No depth testing or writing, for 2d, ui and post passes
+    pub fn disabled() -> Self {
+        DepthState {
+            test: false,
+            write: false,
+            func: CompareFunc::Always,
+        }
+    }
+
+    /// Tests against the depth but doesn't write it, for transparent things
+    pub fn read_only() -> Self {
+        DepthState {
+            write: false,
+            ..DepthState::default()
+        }
+    }
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        DepthState {
+            test: true,
+            write: true,
+            func: CompareFunc::Less,
+        }
+    }
+}
+
+/// What happens to the stencil value of a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StencilOp {
+    /// The value stays the same
+    #[default]
+    Keep,
+    /// The value becomes 0
+    Zero,
+    /// The value becomes the reference
+    Replace,
+    /// The value goes up by 1, stopping at the max
+    Increment,
+    /// The value goes down by 1, stopping at 0
+    Decrement,
+    /// The bits of the value are flipped
+    Invert,
+}
+
+impl StencilOp {
+    /// Gets the opengl enum of the operation
+    pub fn gl(self) -> u32 {
+        match self {
+            StencilOp::Keep => GL_KEEP,
+            StencilOp::Zero => GL_ZERO,
+            StencilOp::Replace => GL_REPLACE,
+            StencilOp::Increment => GL_INCR,
+            StencilOp::Decrement => GL_DECR,
+            StencilOp::Invert => GL_INVERT,
+        }
+    }
+}
+
+/// How the stencil buffer is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    /// How the reference is compared with the stencil value
+    pub func: CompareFunc,
+    /// The value compared with and written by [StencilOp::Replace]
+    pub reference: i32,
+    /// The bits that are compared
+    pub read_mask: u32,
+    /// The bits that can be written
+    pub write_mask: u32,
+    /// What happens when the stencil test fails
+    pub fail: StencilOp,
+    /// What happens when the stencil test passes but the depth test fails
+    pub depth_fail: StencilOp,
+    /// What happens when both tests pass
+    pub pass: StencilOp,
+}
+
+impl StencilState {
+    /// Writes reference everywhere that is drawn, e.g. to mark a mask
+    pub fn write(reference: i32) -> Self {
+        StencilState {
+            func: CompareFunc::Always,
+            reference,
+            pass: StencilOp::Replace,
+            ..StencilState::default()
+        }
+    }
+
+    /// Only draws where the stencil value is reference
+    pub fn equal(reference: i32) -> Self {
+        StencilState {
+            func: CompareFunc::Equal,
+            reference,
+            write_mask: 0,
+            ..StencilState::default()
+        }
+    }
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        StencilState {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: 0xff,
+            write_mask: 0xff,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+/// Which faces of triangles are thrown away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullMode {
+    /// Both faces are drawn
+    #[default]
+    None,
+    /// The faces pointing away from the camera are thrown away
+    Back,
+    /// The faces pointing towards the camera are thrown away
+    Front,
+}
+
+/// How triangles are turned into pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RasterizerState {
+    /// Which faces are thrown away
+    pub cull: CullMode,
+    /// Whether triangles whose vertices go counter clockwise are the front
+    pub front_ccw: bool,
+    /// Whether only the edges of triangles are drawn
+    pub wireframe: bool,
+    /// Whether drawing is cut to the scissor box
+    pub scissor: bool,
+    /// Which of red, green, blue and alpha are written
+    pub color_mask: [bool; 4],
+}
+
+impl Default for RasterizerState {
+    fn default() -> Self {
+        RasterizerState {
+            cull: CullMode::None,
+            front_ccw: true,
+            wireframe: false,
+            scissor: false,
+            color_mask: [true; 4],
+        }
+    }
+}
+
+/// An attribute of a [VertexLayout]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    /// The location the attribute is bound to
+    pub location: u32,
+    /// How many elements the attribute has
+    pub size: u32,
+    /// The format the attribute is stored in
+    pub format: AttrFormat,
+}
+
+/// The attributes a vertex buffer gives the vertex shader
+///
+/// # Example
+/// ```
+/// // position and uv
+/// let layout = VertexLayout::new(vec![3, 2]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VertexLayout {
+    /// The attributes read from the buffer
+    pub attributes: Vec<VertexAttribute>,
+    /// Locations that aren't read from the buffer but get one value for every vertex, like the
+    /// white color of a [Mesh] without [Mesh::with_color_attr]
+    pub constants: Vec<u32>,
+}
+
+impl VertexLayout {
+    /// Creates a new layout of float attributes with sizes, bound to the locations 0, 1, 2...
+    pub fn new(sizes: Vec<u32>) -> Self {
+        VertexLayout {
+            attributes: sizes
+                .into_iter()
+                .enumerate()
+                .map(|(location, size)| VertexAttribute {
+                    location: location as u32,
+                    size,
+                    format: AttrFormat::Float,
+                })
+                .collect(),
+            constants: Vec::new(),
+        }
+    }
+
+    /// Creates the layout a mesh is set up with in [Mesh::setup]
+    pub fn from_mesh<Vertex: VertexTrait + 'static + Sync + Send>(mesh: &Mesh<Vertex>) -> Self {
+        let attributes = mesh
+            .vert_attr
+            .iter()
+            .zip(&mesh.attr_formats)
+            .enumerate()
+            .map(|(i, (size, format))| VertexAttribute {
+                location: if mesh.color_attr == Some(i) {
+                    COLOR_LOCATION
+                } else if mesh.lightmap_uv_attr == Some(i) {
+                    LIGHTMAP_UV_LOCATION
+                } else {
+                    i as u32
+                },
+                size: *size,
+                format: *format,
+            })
+            .collect();
+
+        let mut constants = Vec::new();
+        if mesh.color_attr.is_none() {
+            constants.push(COLOR_LOCATION);
+        }
+        if mesh.lightmap_uv_attr.is_none() {
+            constants.push(LIGHTMAP_UV_LOCATION);
+        }
+        VertexLayout {
+            attributes,
+            constants,
+        }
+    }
+
+    /// Adds an attribute with size elements stored in format at location
+    pub fn attribute(mut self, location: u32, size: u32, format: AttrFormat) -> Self {
+        self.attributes.push(VertexAttribute {
+            location,
+            size,
+            format,
+        });
+        self
+    }
+
+    /// Adds a location that gets one value for every vertex
+    pub fn constant(mut self, location: u32) -> Self {
+        self.constants.push(location);
+        self
+    }
+
+    /// Gets the size of a vertex(in bytes)
+    pub fn stride(&self) -> usize {
+        self.attributes
+            .iter()
+            .map(|attr| attr.format.byte_size(attr.size))
+            .sum()
+    }
+
+    /// Checks that every location is used once and every attribute has 1 to 4 elements
+    pub fn validate(&self) -> Result<(), LighthouseError> {
+        let mut used = Vec::new();
+        for attr in &self.attributes {
+            if attr.size == 0 || attr.size > 4 {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "The attribute at location {} has {} elements, it must have 1 to 4",
+                    attr.location, attr.size
+                )));
+            }
+            if attr.format == AttrFormat::Int2101010Rev && attr.size < 3 {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "The attribute at location {} is packed as 2_10_10_10 but only has {} elements",
+                    attr.location, attr.size
+                )));
+            }
+            used.push(attr.location);
+        }
+        used.extend(&self.constants);
+        used.sort_unstable();
+        if let Some(pair) = used.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(LighthouseError::InvalidInput(format!(
+                "The location {} is used more than once",
+                pair[0]
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// An input of a vertex shader
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderInput {
+    /// The name of the input
+    pub name: String,
+    /// The location of the input
+    pub location: u32,
+    /// The glsl type of the input, e.g. `GL_FLOAT_VEC3`
+    pub ty: u32,
+}
+
+impl ShaderInput {
+    /// Gets how many elements the input has, 0 for types a [VertexLayout] can't feed
+    pub fn size(&self) -> u32 {
+        match self.ty {
+            GL_FLOAT => 1,
+            GL_FLOAT_VEC2 => 2,
+            GL_FLOAT_VEC3 => 3,
+            GL_FLOAT_VEC4 => 4,
+            _ => 0,
+        }
+    }
+}
+
+/// Gets every input of the vertex shader of program, without the built in `gl_` ones
+pub fn shader_inputs(program: &ShaderProgram) -> Vec<ShaderInput> {
+    let mut count = 0;
+    let mut max_length = 0;
+    unsafe {
+        glGetProgramiv(program.0, GL_ACTIVE_ATTRIBUTES, &mut count);
+        glGetProgramiv(program.0, GL_ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+    }
+
+    let mut inputs = Vec::new();
+    for index in 0..count.max(0) as u32 {
+        let mut name = vec![0u8; max_length.max(1) as usize];
+        let mut length = 0;
+        let mut size = 0;
+        let mut ty = 0;
+        unsafe {
+            glGetActiveAttrib(
+                program.0,
+                index,
+                name.len() as i32,
+                &mut length,
+                &mut size,
+                &mut ty,
+                name.as_mut_ptr().cast(),
+            )
+        };
+        name.truncate(length.max(0) as usize);
+        let name = String::from_utf8_lossy(&name).into_owned();
+        if name.starts_with("gl_") {
+            continue;
+        }
+
+        let location = with_cstr(&name, |name| unsafe {
+            glGetAttribLocation(program.0, name.as_ptr().cast())
+        });
+        if location >= 0 {
+            inputs.push(ShaderInput {
+                name,
+                location: location as u32,
+                ty,
+            });
+        }
+    }
+    inputs
+}
+
+/// Everything opengl needs to know to draw, bundled so it can be switched all at once
+///
+/// The pipeline checks when it is built that its vertex layout feeds every input of the vertex
+/// shader, so a mesh that doesn't fit a shader is found before anything is drawn. A pipeline
+/// can't be changed after it is built, make another one instead
+///
+/// # Example
+/// ```
+/// let transparent = Pipeline::builder(program)
+///     .blend(BlendMode::Alpha)
+///     .depth(DepthState::read_only())
+///     .layout(VertexLayout::from_mesh(&mesh))
+///     .build()?;
+///
+/// transparent.bind();
+/// mesh.draw();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    program: ShaderProgram,
+    blend: BlendMode,
+    depth: DepthState,
+    stencil: Option<StencilState>,
+    rasterizer: RasterizerState,
+    layout: VertexLayout,
+}
+
+impl Pipeline {
+    /// Starts building a pipeline that draws with program, with opaque, depth tested and
+    /// not culled defaults
+    pub fn builder(program: ShaderProgram) -> PipelineBuilder {
+        PipelineBuilder {
+            program,
+            blend: BlendMode::default(),
+            depth: DepthState::default(),
+            stencil: None,
+            rasterizer: RasterizerState::default(),
+            layout: None,
+        }
+    }
+
+    /// Makes the program and every state of the pipeline the current ones
+    ///
+    /// The depth, stencil, culling and blending are set with [RenderState::apply], so only
+    /// what is different from the last state is changed
+    pub fn bind(&self) {
+        self.program.use_program();
+        self.render_state().apply();
+
+        let rasterizer = &self.rasterizer;
+        unsafe {
+            glPolygonMode(
+                GL_FRONT_AND_BACK,
+                if rasterizer.wireframe {
+                    GL_LINE
+                } else {
+                    GL_FILL
+                },
+            );
+            if rasterizer.scissor {
+                glEnable(GL_SCISSOR_TEST);
+            } else {
+                glDisable(GL_SCISSOR_TEST);
+            }
+            let [r, g, b, a] = rasterizer.color_mask;
+            glColorMask(r as u8, g as u8, b as u8, a as u8);
+        }
+    }
+
+    /// Gets the program of the pipeline
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    /// Gets how colors are blended
+    pub fn blend(&self) -> BlendMode {
+        self.blend
+    }
+
+    /// Gets how the depth buffer is used
+    pub fn depth(&self) -> DepthState {
+        self.depth
+    }
+
+    /// Gets how the stencil buffer is used, None if the stencil test is off
+    pub fn stencil(&self) -> Option<StencilState> {
+        self.stencil
+    }
+
+    /// Gets how triangles are turned into pixels
+    pub fn rasterizer(&self) -> RasterizerState {
+        self.rasterizer
+    }
+
+    /// Gets the depth, stencil, culling and blending of the pipeline as a [RenderState]
+    pub fn render_state(&self) -> RenderState {
+        RenderState {
+            blend: self.blend,
+            depth: self.depth,
+            stencil: self.stencil,
+            cull: self.rasterizer.cull,
+            front_ccw: self.rasterizer.front_ccw,
+        }
+    }
+
+    /// Gets the vertex layout the pipeline was checked against
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    /// Checks if a mesh with layout can be drawn with the pipeline
+    pub fn accepts(&self, layout: &VertexLayout) -> Result<(), LighthouseError> {
+        check_layout(&self.program, layout)
+    }
+}
+
+/// Builds a [Pipeline], see [Pipeline::builder]
+pub struct PipelineBuilder {
+    program: ShaderProgram,
+    blend: BlendMode,
+    depth: DepthState,
+    stencil: Option<StencilState>,
+    rasterizer: RasterizerState,
+    layout: Option<VertexLayout>,
+}
+
+impl PipelineBuilder {
+    /// Sets how colors are blended
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Sets how the depth buffer is used
+    pub fn depth(mut self, depth: DepthState) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Turns the stencil test on
+    pub fn stencil(mut self, stencil: StencilState) -> Self {
+        self.stencil = Some(stencil);
+        self
+    }
+
+    /// Sets how triangles are turned into pixels
+    pub fn rasterizer(mut self, rasterizer: RasterizerState) -> Self {
+        self.rasterizer = rasterizer;
+        self
+    }
+
+    /// Sets the vertex layout the shader is fed with, without one the shader is expected to
+    /// have no inputs, like a [crate::graphics::fullscreen::FullscreenPass]
+    pub fn layout(mut self, layout: VertexLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Checks the states and layout against the program and builds the pipeline
+    pub fn build(self) -> Result<Pipeline, LighthouseError> {
+        if !self.program.link_success() {
+            return Err(LighthouseError::ProgramLink(self.program.info_log()));
+        }
+
+        let layout = self.layout.unwrap_or_default();
+        check_layout(&self.program, &layout)?;
+
+        if let Some(stencil) = self.stencil {
+            if stencil.reference < 0 {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "The stencil reference {} can't be negative",
+                    stencil.reference
+                )));
+            }
+        }
+        if self.depth.write && !self.depth.test {
+            // opengl doesn't write depth at all with the depth test off
+            return Err(LighthouseError::InvalidInput(
+                "A pipeline can't write depth without testing it, use CompareFunc::Always instead"
+                    .to_string(),
+            ));
+        }
+
+        Ok(Pipeline {
+            program: self.program,
+            blend: self.blend,
+            depth: self.depth,
+            stencil: self.stencil,
+            rasterizer: self.rasterizer,
+            layout,
+        })
+    }
+}
+
+fn check_layout(program: &ShaderProgram, layout: &VertexLayout) -> Result<(), LighthouseError> {
+    layout.validate()?;
+    for input in shader_inputs(program) {
+        if layout.constants.contains(&input.location) {
+            continue;
+        }
+        let attr = layout
+            .attributes
+            .iter()
+            .find(|attr| attr.location == input.location)
+            .ok_or_else(|| {
+                LighthouseError::InvalidInput(format!(
+                    "The shader input {} at location {} isn't in the vertex layout",
+                    input.name, input.location
+                ))
+            })?;
+        if input.size() == 0 {
+            return Err(LighthouseError::InvalidInput(format!(
+                "The shader input {} must be a float or vec, the vertex layout only has floats",
+                input.name
+            )));
+        }
+        if attr.size < input.size() && attr.format != AttrFormat::Int2101010Rev {
+            // missing elements are filled with 0 and 1 by opengl, which is fine for w
+            if !(input.size() == 4 && attr.size == 3) {
+                return Err(LighthouseError::InvalidInput(format!(
+                    "The shader input {} has {} elements but the vertex layout only gives it {}",
+                    input.name,
+                    input.size(),
+                    attr.size
+                )));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,389 @@
+use std::{path::Path, str::SplitAsciiWhitespace};
+
+use nalgebra_glm::{vec2, vec3, vec4, Vec4};
+
+use super::model::{calculate_normals, ModelError, ModelVertex, ObjModel};
+use crate::assets::{Asset, AssetError};
+
+/// The type of a property of a PLY element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => PlyType::I8,
+            "uchar" | "uint8" => PlyType::U8,
+            "short" | "int16" => PlyType::I16,
+            "ushort" | "uint16" => PlyType::U16,
+            "int" | "int32" => PlyType::I32,
+            "uint" | "uint32" => PlyType::U32,
+            "float" | "float32" => PlyType::F32,
+            "double" | "float64" => PlyType::F64,
+            _ => return None,
+        })
+    }
+
+    /// Gets the size of the type in a binary file(in bytes)
+    fn size(self) -> usize {
+        match self {
+            PlyType::I8 | PlyType::U8 => 1,
+            PlyType::I16 | PlyType::U16 => 2,
+            PlyType::I32 | PlyType::U32 | PlyType::F32 => 4,
+            PlyType::F64 => 8,
+        }
+    }
+
+    /// Gets the biggest value of integer types, colors are stored from 0 to it
+    fn max(self) -> f64 {
+        match self {
+            PlyType::I8 => i8::MAX as f64,
+            PlyType::U8 => u8::MAX as f64,
+            PlyType::I16 => i16::MAX as f64,
+            PlyType::U16 => u16::MAX as f64,
+            PlyType::I32 => i32::MAX as f64,
+            PlyType::U32 => u32::MAX as f64,
+            PlyType::F32 | PlyType::F64 => 1.0,
+        }
+    }
+}
+
+/// A property of a PLY element, lists have the type of their length and of their items
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    ty: PlyType,
+    list: Option<PlyType>,
+}
+
+/// An element of a PLY file, like vertex or face
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads the values of the body of a PLY file
+enum Reader<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary {
+        bytes: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl Reader<'_> {
+    fn read(&mut self, ty: PlyType) -> Result<f64, ModelError> {
+        let cut_off =
+            || ModelError::Invalid("The file ends before all of its elements".to_string());
+        match self {
+            Reader::Ascii(words) => {
+                let word = words.next().ok_or_else(cut_off)?;
+                word.parse()
+                    .map_err(|_| ModelError::Invalid(format!("{} is not a number", word)))
+            }
+            Reader::Binary {
+                bytes,
+                offset,
+                big_endian,
+            } => {
+                let data = bytes
+                    .get(*offset..*offset + ty.size())
+                    .ok_or_else(cut_off)?;
+                *offset += ty.size();
+                let mut buf = [0; 8];
+                buf[..data.len()].copy_from_slice(data);
+                if *big_endian {
+                    buf[..data.len()].reverse();
+                }
+                Ok(match ty {
+                    PlyType::I8 => buf[0] as i8 as f64,
+                    PlyType::U8 => buf[0] as f64,
+                    PlyType::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyType::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyType::I32 => i32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    PlyType::U32 => u32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    PlyType::F32 => f32::from_le_bytes(buf[..4].try_into().unwrap()) as f64,
+                    PlyType::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+}
+
+/// A model or point cloud loaded from a PLY file, ascii or binary
+///
+/// PLY files come from 3d scanners and scientific tools. Only the vertex and face elements
+/// are read, from the vertices the position, normal, uv and color are kept. Faces with
+/// more than 3 vertices are turned into triangles as a fan like [ObjModel] does
+///
+/// # Example
+/// ```
+/// let scan = PlyModel::load("scans/statue.ply")?;
+/// if scan.is_point_cloud() {
+///     // draw the vertices as points with the colors
+/// } else {
+///     let mut statue = scan.into_model().into_mesh(vec![3, 2, 3], |v| *v)?;
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlyModel {
+    /// The vertices of the model
+    pub vertices: Vec<ModelVertex>,
+    /// The color of every vertex(rgba), empty if the file has none
+    pub colors: Vec<Vec4>,
+    /// The triangles of the model, empty if the file is a point cloud
+    pub indices: Vec<[u32; 3]>,
+    /// Whether the file had uvs
+    pub has_uvs: bool,
+    /// Whether the file had normals, if it didn't and has faces they were calculated
+    pub has_normals: bool,
+}
+
+impl PlyModel {
+    /// Parses a PLY file
+    pub fn parse(bytes: &[u8]) -> Result<Self, ModelError> {
+        let no_end = || ModelError::Invalid("The PLY header has no end_header".to_string());
+        let header_end = bytes
+            .windows(10)
+            .position(|window| window == b"end_header")
+            .ok_or_else(no_end)?;
+        // the body starts after the new line, which can be \r\n
+        let body_start = bytes[header_end..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(no_end)?
+            + header_end
+            + 1;
+        let header = String::from_utf8_lossy(&bytes[..header_end]);
+        let body = &bytes[body_start..];
+
+        let mut lines = header
+            .lines()
+            .enumerate()
+            .map(|(line, text)| (line + 1, text));
+        if lines.next().map(|(_, text)| text.trim()) != Some("ply") {
+            return Err(ModelError::Invalid("The file isn't a PLY file".to_string()));
+        }
+        let mut format = None;
+        let mut elements = Vec::<Element>::new();
+        for (line, text) in lines {
+            let parts = text.split_whitespace().collect::<Vec<_>>();
+            let syntax = |message: &str| ModelError::Syntax(line, message.to_string());
+            match parts[..] {
+                ["format", name, _] => format = Some(name.to_string()),
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| syntax("The count isn't a number"))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count_ty, ty, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| syntax("A property has to be after an element"))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        ty: PlyType::parse(ty).ok_or_else(|| syntax("Unknown type"))?,
+                        list: Some(PlyType::parse(count_ty).ok_or_else(|| syntax("Unknown type"))?),
+                    }),
+                ["property", ty, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| syntax("A property has to be after an element"))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        ty: PlyType::parse(ty).ok_or_else(|| syntax("Unknown type"))?,
+                        list: None,
+                    }),
+                ["comment", ..] | ["obj_info", ..] | [] => {}
+                _ => return Err(syntax("Unknown header line")),
+            }
+        }
+
+        let mut reader = match format.as_deref() {
+            Some("ascii") => Reader::Ascii(
+                std::str::from_utf8(body)
+                    .map_err(|_| ModelError::Invalid("The ascii body isn't utf-8".to_string()))?
+                    .split_ascii_whitespace(),
+            ),
+            Some("binary_little_endian") => Reader::Binary {
+                bytes: body,
+                offset: 0,
+                big_endian: false,
+            },
+            Some("binary_big_endian") => Reader::Binary {
+                bytes: body,
+                offset: 0,
+                big_endian: true,
+            },
+            _ => {
+                return Err(ModelError::Invalid(
+                    "The PLY format isn't known".to_string(),
+                ))
+            }
+        };
+
+        let mut model = PlyModel::default();
+        for element in &elements {
+            match element.name.as_str() {
+                "vertex" => model.read_vertices(element, &mut reader)?,
+                "face" => model.read_faces(element, &mut reader)?,
+                _ => {
+                    let total = element
+                        .count
+                        .checked_mul(element.properties.len())
+                        .ok_or_else(|| {
+                            ModelError::Invalid(format!("There are too many {}", element.name))
+                        })?;
+                    for property in element.properties.iter().cycle().take(total) {
+                        read_property(property, &mut reader)?;
+                    }
+                }
+            }
+        }
+
+        if model.vertices.is_empty() {
+            return Err(ModelError::Empty);
+        }
+        if !model.has_normals && !model.indices.is_empty() {
+            calculate_normals(&mut model.vertices, &model.indices);
+        }
+        Ok(model)
+    }
+
+    /// Reads and parses the PLY file at path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        PlyModel::parse(&std::fs::read(path)?)
+    }
+
+    /// Checks if the file only has vertices and no faces
+    pub fn is_point_cloud(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Turns it into an [ObjModel], the colors are thrown away
+    pub fn into_model(self) -> ObjModel {
+        ObjModel {
+            vertices: self.vertices,
+            indices: self.indices,
+            has_uvs: self.has_uvs,
+            has_normals: self.has_normals,
+        }
+    }
+
+    fn read_vertices(&mut self, element: &Element, reader: &mut Reader) -> Result<(), ModelError> {
+        let has = |names: &[&str]| {
+            names
+                .iter()
+                .all(|name| element.properties.iter().any(|p| p.name == *name))
+        };
+        self.has_normals = has(&["nx", "ny", "nz"]);
+        self.has_uvs = ["u", "s", "texture_u"].iter().any(|name| has(&[name]));
+        let has_colors = has(&["red", "green", "blue"]);
+
+        for _ in 0..element.count {
+            let mut vertex = ModelVertex {
+                pos: vec3(0.0, 0.0, 0.0),
+                uv: vec2(0.0, 0.0),
+                normal: vec3(0.0, 0.0, 0.0),
+            };
+            let mut color = vec4(1.0, 1.0, 1.0, 1.0);
+            for property in &element.properties {
+                let value = read_property(property, reader)?;
+                let Some(value) = value else {
+                    continue;
+                };
+                let channel = (value / property.ty.max()) as f32;
+                let value = value as f32;
+                match property.name.as_str() {
+                    "x" => vertex.pos.x = value,
+                    "y" => vertex.pos.y = value,
+                    "z" => vertex.pos.z = value,
+                    "nx" => vertex.normal.x = value,
+                    "ny" => vertex.normal.y = value,
+                    "nz" => vertex.normal.z = value,
+                    "u" | "s" | "texture_u" => vertex.uv.x = value,
+                    "v" | "t" | "texture_v" => vertex.uv.y = value,
+                    "red" => color.x = channel,
+                    "green" => color.y = channel,
+                    "blue" => color.z = channel,
+                    "alpha" => color.w = channel,
+                    _ => {}
+                }
+            }
+            self.vertices.push(vertex);
+            if has_colors {
+                self.colors.push(color);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_faces(&mut self, element: &Element, reader: &mut Reader) -> Result<(), ModelError> {
+        for _ in 0..element.count {
+            for property in &element.properties {
+                let Some(count) = property.list else {
+                    read_property(property, reader)?;
+                    continue;
+                };
+                // the length comes from the file, so the face only grows as it is read
+                let len = reader.read(count)? as usize;
+                let mut face = Vec::new();
+                for _ in 0..len {
+                    face.push(reader.read(property.ty)? as i64);
+                }
+                if !matches!(property.name.as_str(), "vertex_indices" | "vertex_index") {
+                    continue;
+                }
+                if let Some(&index) = face
+                    .iter()
+                    .find(|&&index| index < 0 || index >= self.vertices.len() as i64)
+                {
+                    return Err(ModelError::Invalid(format!(
+                        "A face points to vertex {} but there are only {}",
+                        index,
+                        self.vertices.len()
+                    )));
+                }
+                for i in 1..face.len().saturating_sub(1) {
+                    self.indices
+                        .push([face[0], face[i], face[i + 1]].map(|index| index as u32));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Asset for PlyModel {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        PlyModel::parse(&bytes).map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+/// Reads a property, lists are skipped and give None
+fn read_property(property: &Property, reader: &mut Reader) -> Result<Option<f64>, ModelError> {
+    match property.list {
+        Some(count) => {
+            let len = reader.read(count)? as usize;
+            for _ in 0..len {
+                reader.read(property.ty)?;
+            }
+            Ok(None)
+        }
+        None => reader.read(property.ty).map(Some),
+    }
+}
@@ -0,0 +1,248 @@
+use std::mem::size_of;
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use super::{
+    buffer::*, ply::PlyModel, shader::ShaderProgram, uniform::Uniform, vertex::VertexArray, *,
+};
+
+const POINT_VERT: &str = "#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec4 color;
+
+uniform mat4 view_proj;
+uniform float point_size;
+uniform float size_scale;
+uniform vec2 size_range;
+
+out vec4 point_color;
+
+void main() {
+    gl_Position = view_proj * vec4(pos, 1.0);
+    float size = size_scale > 0.0 ? point_size * size_scale / max(gl_Position.w, 0.001) : point_size;
+    gl_PointSize = clamp(size, size_range.x, size_range.y);
+    point_color = color;
+}";
+
+const POINT_FRAG: &str = "#version 330 core
+in vec4 point_color;
+
+uniform bool round_points;
+
+out vec4 frag_color;
+
+void main() {
+    vec2 p = gl_PointCoord * 2.0 - 1.0;
+    if (round_points && dot(p, p) > 1.0) {
+        discard;
+    }
+    frag_color = point_color;
+}";
+
+/// A point of a [PointCloud]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CloudPoint {
+    /// The position of the point
+    pub pos: [f32; 3],
+    /// The color of the point(rgba)
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for CloudPoint {}
+unsafe impl bytemuck::Pod for CloudPoint {}
+
+/// How big the points of a [PointCloud] are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointSize {
+    /// Every point is the same size on the screen(in pixels)
+    Pixels(f32),
+    /// Points get smaller the further away they are, like a ball of this size(in units)
+    World(f32),
+}
+
+/// Draws a lot of points, like a LiDAR scan or a [PlyModel] without faces
+///
+/// The points are uploaded once and drawn with a single draw call, so millions of them can
+/// be drawn every frame. They are drawn as squares, or as circles with
+/// [PointCloud::round_points]
+///
+/// # Example
+/// ```
+/// let scan = PlyModel::load("scans/street.ply")?;
+/// let cloud = PointCloud::from_ply(&scan)?
+///     .size(PointSize::World(0.05))
+///     .round_points(true);
+///
+/// 'main_loop: loop {
+///     cloud.draw(&(proj * view), &proj, world.env.win_size.y);
+/// }
+/// ```
+pub struct PointCloud {
+    /// The shader program of the points
+    pub program: ShaderProgram,
+    /// How big the points are
+    pub size: PointSize,
+    /// The smallest and biggest size a point can get(in pixels), so far away points don't
+    /// disappear and close ones don't cover the screen
+    pub size_range: (f32, f32),
+    /// Whether the points are circles instead of squares
+    pub round: bool,
+    vao: VertexArray,
+    vbo: Buffer,
+    count: usize,
+}
+
+impl PointCloud {
+    /// Creates a new point cloud without points
+    pub fn new() -> Result<Self, LighthouseError> {
+        let cloud = PointCloud {
+            program: ShaderProgram::from_vert_frag(POINT_VERT, POINT_FRAG)?,
+            size: PointSize::Pixels(2.0),
+            size_range: (1.0, 64.0),
+            round: false,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+            vbo: Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+            count: 0,
+        };
+
+        cloud.vao.bind();
+        cloud.vbo.bind(BufferType::Array);
+        unsafe {
+            let stride = size_of::<CloudPoint>() as i32;
+            glVertexAttribPointer(0, 3, GL_FLOAT, GL_FALSE, stride, std::ptr::null());
+            glEnableVertexAttribArray(0);
+            glVertexAttribPointer(
+                1,
+                4,
+                GL_FLOAT,
+                GL_FALSE,
+                stride,
+                size_of::<[f32; 3]>() as *const _,
+            );
+            glEnableVertexAttribArray(1);
+        }
+        VertexArray::clear_binding();
+
+        Ok(cloud)
+    }
+
+    /// Creates a point cloud of the vertices of a PLY file, with their colors if it has
+    /// them and white if it doesn't
+    pub fn from_ply(model: &PlyModel) -> Result<Self, LighthouseError> {
+        let positions: Vec<Vec3> = model.vertices.iter().map(|vertex| vertex.pos).collect();
+        let mut cloud = PointCloud::new()?;
+        cloud.set_positions(
+            &positions,
+            (!model.colors.is_empty()).then_some(&model.colors[..]),
+        )?;
+        Ok(cloud)
+    }
+
+    /// Sets how big the points are
+    pub fn size(mut self, size: PointSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets the smallest and biggest size a point can get(in pixels)
+    pub fn size_range(mut self, min: f32, max: f32) -> Self {
+        self.size_range = (min, max);
+        self
+    }
+
+    /// Sets whether the points are circles instead of squares
+    pub fn round_points(mut self, round: bool) -> Self {
+        self.round = round;
+        self
+    }
+
+    /// Replaces the points
+    pub fn set_points(&mut self, points: &[CloudPoint]) {
+        self.vbo.bind(BufferType::Array);
+        buffer_data(
+            BufferType::Array,
+            bytemuck::cast_slice(points),
+            BufferUsage::StaticDraw,
+        );
+        Buffer::clear_binding(BufferType::Array);
+        self.count = points.len();
+    }
+
+    /// Replaces the points with points at positions, colors has to be as long as positions
+    /// and without it every point is white
+    pub fn set_positions(
+        &mut self,
+        positions: &[Vec3],
+        colors: Option<&[Vec4]>,
+    ) -> Result<(), LighthouseError> {
+        if let Some(colors) = colors.filter(|colors| colors.len() != positions.len()) {
+            return Err(LighthouseError::InvalidInput(format!(
+                "There are {} positions but {} colors",
+                positions.len(),
+                colors.len()
+            )));
+        }
+        let points: Vec<CloudPoint> = positions
+            .iter()
+            .enumerate()
+            .map(|(i, pos)| CloudPoint {
+                pos: [pos.x, pos.y, pos.z],
+                color: colors.map_or([1.0; 4], |colors| colors[i].into()),
+            })
+            .collect();
+        self.set_points(&points);
+        Ok(())
+    }
+
+    /// Gets how many points there are
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Checks if there are no points
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Draws the points, view_proj is the projection times the view of the camera
+    ///
+    /// proj and screen_height(in pixels) are only used by [PointSize::World], to work out
+    /// how many pixels a unit is. The points are depth tested like the rest of the scene
+    pub fn draw(&self, view_proj: &Mat4, proj: &Mat4, screen_height: f32) {
+        if self.count == 0 {
+            return;
+        }
+        let (point_size, size_scale) = match self.size {
+            PointSize::Pixels(size) => (size, 0.0),
+            PointSize::World(size) => (size, proj[(1, 1)] * screen_height / 2.0),
+        };
+
+        self.program.use_program();
+        let uniform = |name| Uniform::new(&self.program, name);
+        uniform("view_proj").set_uniform_matrix(false, (*view_proj).into());
+        uniform("point_size").set_uniform_f(&[point_size]);
+        uniform("size_scale").set_uniform_f(&[size_scale]);
+        uniform("size_range").set_uniform_f(&[self.size_range.0, self.size_range.1]);
+        uniform("round_points").set_uniform_i(&[self.round as i32]);
+
+        self.vao.bind();
+        unsafe {
+            let point_size = glIsEnabled(GL_PROGRAM_POINT_SIZE) == GL_TRUE;
+            glEnable(GL_PROGRAM_POINT_SIZE);
+            glDrawArrays(GL_POINTS, 0, self.count as i32);
+            if !point_size {
+                glDisable(GL_PROGRAM_POINT_SIZE);
+            }
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Deletes the shader program and points
+    pub fn delete(self) {
+        self.program.delete();
+        self.vao.delete();
+        self.vbo.delete();
+    }
+}
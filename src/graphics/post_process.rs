@@ -0,0 +1,548 @@
+use nalgebra_glm::{vec3, Vec3};
+use specs::{Component, VecStorage};
+
+use super::{
+    exposure::AutoExposure,
+    framebuffer::{ColorFormat, Framebuffer},
+    fullscreen::FullscreenPass,
+    target_pool::WindowTargets,
+    texture::{Texture, TextureFilter},
+    uniform::UniformValue,
+    *,
+};
+
+const GRAYSCALE_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform float amount;
+out vec4 color;
+
+void main() {
+    vec4 scene = texture(input_texture, uv);
+    float luma = dot(scene.rgb, vec3(0.2126, 0.7152, 0.0722));
+    color = vec4(mix(scene.rgb, vec3(luma), amount), scene.a);
+}";
+
+const VIGNETTE_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform float strength;
+uniform float radius;
+uniform float softness;
+out vec4 color;
+
+void main() {
+    vec4 scene = texture(input_texture, uv);
+    float dist = length(uv - 0.5) * 1.41421356;
+    float dark = smoothstep(radius, radius + softness, dist) * strength;
+    color = vec4(scene.rgb * (1.0 - dark), scene.a);
+}";
+
+const BLOOM_THRESHOLD_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform float threshold;
+uniform float knee;
+out vec4 color;
+
+void main() {
+    vec3 scene = texture(input_texture, uv).rgb;
+    float brightness = max(scene.r, max(scene.g, scene.b));
+    // a soft curve around the threshold so bright parts don't pop in
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.0001);
+    float keep = max(soft, brightness - threshold) / max(brightness, 0.0001);
+    color = vec4(scene * keep, 1.0);
+}";
+
+const BLOOM_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform float threshold;
+uniform float knee;
+uniform float intensity;
+uniform float radius;
+out vec4 color;
+
+vec3 bright(vec2 at) {
+    vec3 scene = texture(input_texture, at).rgb;
+    float brightness = max(scene.r, max(scene.g, scene.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.0001);
+    return scene * max(soft, brightness - threshold) / max(brightness, 0.0001);
+}
+
+void main() {
+    vec4 scene = texture(input_texture, uv);
+    vec2 step = radius / vec2(textureSize(input_texture, 0));
+    vec3 glow = vec3(0.0);
+    float total = 0.0;
+    // a gaussian over 9 by 9 samples of the bright parts, added on top of the scene
+    for (int x = -4; x <= 4; x++) {
+        for (int y = -4; y <= 4; y++) {
+            float weight = exp(-float(x * x + y * y) / 8.0);
+            glow += bright(uv + vec2(x, y) * step) * weight;
+            total += weight;
+        }
+    }
+    color = vec4(scene.rgb + glow / total * intensity, scene.a);
+}";
+
+const TONE_MAP_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform float exposure;
+uniform vec3 white_balance;
+uniform float gamma;
+out vec4 color;
+
+// the aces filmic curve fitted by Krzysztof Narkowicz
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec4 scene = texture(input_texture, uv);
+    vec3 mapped = aces(scene.rgb * white_balance * exposure);
+    color = vec4(pow(mapped, vec3(1.0 / gamma)), scene.a);
+}";
+
+/// One pass of a [PostProcessStack], a fragment shader with the uniforms it is drawn with
+///
+/// The fragment shader gets the same inputs as a [FullscreenPass], `in vec2 uv` and
+/// `uniform sampler2D input_texture` with the result of the pass before it
+///
+/// # Example
+/// ```
+/// let sepia = PostPass::new("sepia", "#version 330 core
+/// in vec2 uv;
+/// uniform sampler2D input_texture;
+/// uniform vec3 tone;
+/// out vec4 color;
+///
+/// void main() {
+///     float luma = dot(texture(input_texture, uv).rgb, vec3(0.299, 0.587, 0.114));
+///     color = vec4(luma * tone, 1.0);
+/// }")?
+/// .uniform("tone", vec3(1.0, 0.9, 0.7));
+/// ```
+pub struct PostPass {
+    /// The name the pass is found by in a [PostProcessStack]
+    pub name: String,
+    /// Whether the pass is drawn, disabled passes are skipped
+    pub enabled: bool,
+    /// The uniforms set every time the pass is drawn
+    pub uniforms: Vec<(String, UniformValue)>,
+    pass: FullscreenPass,
+}
+
+impl PostPass {
+    /// Creates a new pass called name from the source of a fragment shader
+    pub fn new(name: &str, frag: &str) -> Result<Self, LighthouseError> {
+        Ok(PostPass {
+            name: name.to_string(),
+            enabled: true,
+            uniforms: Vec::new(),
+            pass: FullscreenPass::new(frag)?,
+        })
+    }
+
+    /// Creates a pass that takes the color out of the screen, amount is from 0(no change)
+    /// to 1(fully gray)
+    pub fn grayscale(amount: f32) -> Result<Self, LighthouseError> {
+        Ok(PostPass::new("grayscale", GRAYSCALE_FRAG)?.uniform("amount", amount.clamp(0.0, 1.0)))
+    }
+
+    /// Creates a pass that darkens the corners of the screen, strength is from 0 to 1
+    ///
+    /// The darkening starts at radius and is full at radius + softness, where 1 is the
+    /// distance from the middle to a corner
+    pub fn vignette(strength: f32, radius: f32, softness: f32) -> Result<Self, LighthouseError> {
+        Ok(PostPass::new("vignette", VIGNETTE_FRAG)?
+            .uniform("strength", strength.clamp(0.0, 1.0))
+            .uniform("radius", radius)
+            .uniform("softness", softness.max(0.0001)))
+    }
+
+    /// Creates a pass that keeps only the parts of the screen brighter than threshold,
+    /// the first step of bloom
+    ///
+    /// Colors fade out over knee below the threshold instead of being cut off. It is most
+    /// useful with a [ColorFormat::Rgba16F] stack, so colors can be brighter than 1
+    pub fn bloom_threshold(threshold: f32, knee: f32) -> Result<Self, LighthouseError> {
+        Ok(PostPass::new("bloom_threshold", BLOOM_THRESHOLD_FRAG)?
+            .uniform("threshold", threshold)
+            .uniform("knee", knee.max(0.0)))
+    }
+
+    /// Creates a pass called `bloom` that blurs the parts of the screen brighter than
+    /// threshold and adds them on top of the screen times intensity
+    ///
+    /// The blur is 9 by 9 samples that are radius pixels apart, it can be changed with the
+    /// `radius` uniform. Unlike [PostPass::bloom_threshold] it keeps the scene, so it can be
+    /// the only bloom pass of a stack
+    pub fn bloom(threshold: f32, intensity: f32) -> Result<Self, LighthouseError> {
+        Ok(PostPass::new("bloom", BLOOM_FRAG)?
+            .uniform("threshold", threshold)
+            .uniform("knee", 0.5f32)
+            .uniform("intensity", intensity.max(0.0))
+            .uniform("radius", 2.0f32))
+    }
+
+    /// Creates a pass that maps the colors of an hdr scene to 0 to 1 with the aces curve,
+    /// after multiplying them by exposure, and gamma corrects them with 2.2
+    ///
+    /// The exposure can be changed with the `exposure` uniform, e.g. by an [AutoExposure],
+    /// and the colors are multiplied by the `white_balance` uniform first, see
+    /// [PostProcessSettings]
+    pub fn tone_map(exposure: f32) -> Result<Self, LighthouseError> {
+        Ok(PostPass::new("tone_map", TONE_MAP_FRAG)?
+            .uniform("exposure", exposure.max(0.0))
+            .uniform("white_balance", vec3(1.0, 1.0, 1.0))
+            .uniform("gamma", 2.2f32))
+    }
+
+    /// Sets the uniform called name to value whenever the pass is drawn
+    pub fn uniform(mut self, name: &str, value: impl Into<UniformValue>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Changes the uniform called name to value, or adds it if the pass doesn't have it
+    pub fn set(&mut self, name: &str, value: impl Into<UniformValue>) {
+        let value = value.into();
+        match self.uniforms.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value,
+            None => self.uniforms.push((name.to_string(), value)),
+        }
+    }
+
+    /// Turns the pass on or off
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Draws the pass with input into the bound framebuffer
+    pub fn draw(&self, input: &Texture) {
+        for (name, value) in &self.uniforms {
+            self.pass.uniform(name).set(value.clone());
+        }
+        self.pass.draw(input);
+    }
+
+    /// Deletes the pass
+    pub fn delete(self) {
+        self.pass.delete();
+    }
+}
+
+/// How a camera looks, the settings of the post passes of a [PostProcessStack]
+///
+/// Every camera can have its own, e.g. gameplay with auto exposure and bloom, a ui camera
+/// without tone mapping and a warm cutscene camera. They are kept in
+/// [CameraSettings::post](crate::ECS::camera::CameraSettings) or as a component of a camera
+/// entity and put on the stack with [PostProcessSettings::apply] before the camera is drawn.
+/// The engine doesn't draw the stack itself, so the game calls it
+///
+/// The settings are given to the passes by name, the exposure, white balance and gamma to
+/// `tone_map`, see [PostPass::tone_map], and the bloom to `bloom`, see [PostPass::bloom].
+/// Passes the stack doesn't have are skipped and other passes are left alone
+///
+/// # Example
+/// ```
+/// post.push(PostPass::bloom(1.0, 0.0)?);
+/// post.push(PostPass::tone_map(1.0)?);
+/// let gameplay = PostProcessSettings::default().bloom(0.8, 1.0);
+/// let cutscene = PostProcessSettings::default()
+///     .auto_exposure(false)
+///     .exposure(1.4)
+///     .white_balance(0.3, 0.0);
+///
+/// 'main_loop: loop {
+///     post.begin();
+///     // draw the scene
+///     exposure.update(&post.scene().color, world.env.time.real_delta)?;
+///     camera.get_camera_settings().post.apply(&mut post, Some(&mut exposure));
+///     post.end(800, 600);
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[storage(VecStorage)]
+pub struct PostProcessSettings {
+    /// Whether the `tone_map` pass is drawn, off for cameras that don't draw hdr like the ui
+    pub tone_map: bool,
+    /// The exposure the scene is multiplied by, with auto exposure it is multiplied by the
+    /// exposure of the [AutoExposure] too
+    pub exposure: f32,
+    /// Whether the exposure adapts to the scene with an [AutoExposure]
+    pub auto_exposure: bool,
+    /// The darkest luminance auto exposure adapts to
+    pub min_luminance: f32,
+    /// The brightest luminance auto exposure adapts to
+    pub max_luminance: f32,
+    /// How warm the colors are, from -1(blue) to 1(orange)
+    pub temperature: f32,
+    /// How the colors are tinted, from -1(green) to 1(magenta)
+    pub tint: f32,
+    /// How bright a color has to be to bloom
+    pub bloom_threshold: f32,
+    /// How strong the bloom is, 0 turns the `bloom` pass off
+    pub bloom_intensity: f32,
+    /// The gamma the tone mapped colors are corrected with
+    pub gamma: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        PostProcessSettings {
+            tone_map: true,
+            exposure: 1.0,
+            auto_exposure: true,
+            min_luminance: 0.03,
+            max_luminance: 16.0,
+            temperature: 0.0,
+            tint: 0.0,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.0,
+            gamma: 2.2,
+        }
+    }
+}
+
+impl PostProcessSettings {
+    /// Creates settings for a ui camera, without tone mapping, auto exposure or bloom
+    pub fn ui() -> Self {
+        PostProcessSettings {
+            tone_map: false,
+            auto_exposure: false,
+            ..PostProcessSettings::default()
+        }
+    }
+
+    /// Sets whether the `tone_map` pass is drawn
+    pub fn tone_map(mut self, tone_map: bool) -> Self {
+        self.tone_map = tone_map;
+        self
+    }
+
+    /// Sets the exposure the scene is multiplied by
+    pub fn exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure.max(0.0);
+        self
+    }
+
+    /// Sets whether the exposure adapts to the scene
+    pub fn auto_exposure(mut self, auto_exposure: bool) -> Self {
+        self.auto_exposure = auto_exposure;
+        self
+    }
+
+    /// Sets the darkest and brightest luminance auto exposure adapts to
+    pub fn luminance_range(mut self, min_luminance: f32, max_luminance: f32) -> Self {
+        self.min_luminance = min_luminance;
+        self.max_luminance = max_luminance;
+        self
+    }
+
+    /// Sets the temperature and tint, both from -1 to 1
+    pub fn white_balance(mut self, temperature: f32, tint: f32) -> Self {
+        self.temperature = temperature.clamp(-1.0, 1.0);
+        self.tint = tint.clamp(-1.0, 1.0);
+        self
+    }
+
+    /// Sets how bright a color has to be to bloom and how strong the bloom is
+    pub fn bloom(mut self, threshold: f32, intensity: f32) -> Self {
+        self.bloom_threshold = threshold;
+        self.bloom_intensity = intensity.max(0.0);
+        self
+    }
+
+    /// Gets what the colors are multiplied by for the white balance, it keeps the
+    /// luminance the same
+    pub fn white_balance_scale(&self) -> Vec3 {
+        let scale = vec3(
+            1.0 + self.temperature * 0.2 + self.tint * 0.1,
+            1.0 - self.tint * 0.2,
+            1.0 - self.temperature * 0.2 + self.tint * 0.1,
+        );
+        scale / scale.dot(&vec3(0.2126, 0.7152, 0.0722))
+    }
+
+    /// Gets the exposure the scene is multiplied by, with the exposure of auto_exposure if
+    /// it is on
+    pub fn current_exposure(&self, auto_exposure: Option<&AutoExposure>) -> f32 {
+        match auto_exposure {
+            Some(auto_exposure) if self.auto_exposure => self.exposure * auto_exposure.exposure(),
+            _ => self.exposure,
+        }
+    }
+
+    /// Puts the settings on the passes of stack and the luminance range on auto_exposure
+    ///
+    /// It is called after [AutoExposure::update], the luminance range is used from the
+    /// next update
+    pub fn apply(&self, stack: &mut PostProcessStack, auto_exposure: Option<&mut AutoExposure>) {
+        let auto_exposure = auto_exposure.map(|auto_exposure| {
+            auto_exposure.min_luminance = self.min_luminance.max(0.0001);
+            auto_exposure.max_luminance = self.max_luminance.max(auto_exposure.min_luminance);
+            &*auto_exposure
+        });
+        if let Some(pass) = stack.pass_mut("tone_map") {
+            pass.enabled = self.tone_map;
+            pass.set("exposure", self.current_exposure(auto_exposure));
+            pass.set("white_balance", self.white_balance_scale());
+            pass.set("gamma", self.gamma);
+        }
+        if let Some(pass) = stack.pass_mut("bloom") {
+            pass.enabled = self.bloom_intensity > 0.0;
+            pass.set("threshold", self.bloom_threshold);
+            pass.set("intensity", self.bloom_intensity);
+        }
+    }
+}
+
+/// Post processing made of [PostPass]es that are drawn one after the other
+///
+/// The scene is drawn into one of two render targets, then every enabled pass reads the
+/// target the last one wrote and writes into the other one. The last pass draws straight
+/// into the window
+///
+/// # Example
+/// ```
+/// let mut post = PostProcessStack::new(800, 600)?;
+/// post.push(PostPass::grayscale(0.8)?);
+/// post.push(PostPass::vignette(0.5, 0.4, 0.6)?);
+///
+/// 'main_loop: loop {
+///     post.begin();
+///     // draw the scene
+///     post.end(800, 600);
+///     world.env.win.swap_buffers();
+///
+///     // passes can be changed at any time
+///     post.pass_mut("grayscale").unwrap().set("amount", fade);
+/// }
+/// ```
+pub struct PostProcessStack {
+    /// The passes in the order they are drawn
+    pub passes: Vec<PostPass>,
+    targets: [Framebuffer; 2],
+}
+
+impl PostProcessStack {
+    /// Creates a new stack without passes that draws into targets of size width by height
+    pub fn new(width: i32, height: i32) -> Result<Self, LighthouseError> {
+        PostProcessStack::with_format(width, height, ColorFormat::Rgba8)
+    }
+
+    /// Creates a new stack whose targets have a color texture of format, e.g.
+    /// [ColorFormat::Rgba16F] for hdr scenes
+    pub fn with_format(
+        width: i32,
+        height: i32,
+        format: ColorFormat,
+    ) -> Result<Self, LighthouseError> {
+        Ok(PostProcessStack {
+            passes: Vec::new(),
+            targets: [
+                Framebuffer::with_format(width, height, format, false)?,
+                Framebuffer::with_format(width, height, format, false)?,
+            ],
+        })
+    }
+
+    /// Adds a pass after every other pass, gives its index
+    pub fn push(&mut self, pass: PostPass) -> usize {
+        self.passes.push(pass);
+        self.passes.len() - 1
+    }
+
+    /// Adds a pass at index, the passes after it move one back
+    pub fn insert(&mut self, index: usize, pass: PostPass) {
+        self.passes.insert(index, pass);
+    }
+
+    /// Takes the first pass called name out of the stack
+    pub fn remove(&mut self, name: &str) -> Option<PostPass> {
+        let index = self.passes.iter().position(|pass| pass.name == name)?;
+        Some(self.passes.remove(index))
+    }
+
+    /// Gets the first pass called name
+    pub fn pass(&self, name: &str) -> Option<&PostPass> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+
+    /// Gets the first pass called name to change it
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostPass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// Gets the target the scene is drawn into, e.g. to blit its depth somewhere
+    pub fn scene(&self) -> &Framebuffer {
+        &self.targets[0]
+    }
+
+    /// Resizes both targets to width by height, call it when the window is resized
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        for target in &mut self.targets {
+            target.resize(width, height)?;
+        }
+        Ok(())
+    }
+
+    /// Binds the scene target and clears it, everything drawn after this is post processed
+    pub fn begin(&self) {
+        self.targets[0].bind();
+        unsafe { glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT | GL_STENCIL_BUFFER_BIT) }
+    }
+
+    /// Draws every enabled pass and the result into the window of size width by
+    /// height(in pixels)
+    ///
+    /// Without enabled passes the scene is copied into the window as it is
+    pub fn end(&self, width: i32, height: i32) {
+        let mut enabled = self.passes.iter().filter(|pass| pass.enabled).peekable();
+        if enabled.peek().is_none() {
+            self.targets[0].blit_to_window(width, height, TextureFilter::Linear);
+            return;
+        }
+
+        let mut read = 0;
+        while let Some(pass) = enabled.next() {
+            if enabled.peek().is_some() {
+                self.targets[1 - read].bind();
+            } else {
+                Framebuffer::unbind();
+                unsafe { glViewport(0, 0, width, height) }
+            }
+            pass.draw(&self.targets[read].color);
+            read = 1 - read;
+        }
+    }
+
+    /// Deletes the targets and every pass
+    pub fn delete(self) {
+        for target in self.targets {
+            target.delete();
+        }
+        for pass in self.passes {
+            pass.delete();
+        }
+    }
+}
+
+impl WindowTargets for PostProcessStack {
+    fn window_resized(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        self.resize(width, height)
+    }
+
+    fn recreate_targets(&mut self) -> Result<(), LighthouseError> {
+        for target in &mut self.targets {
+            target.recreate()?;
+        }
+        Ok(())
+    }
+}
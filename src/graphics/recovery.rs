@@ -0,0 +1,289 @@
+use std::{
+    cell::Cell,
+    ffi::{c_char, c_void},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{render_state::RenderState, window::Window, *};
+use crate::assets::AssetManager;
+
+/// `GL_GUILTY_CONTEXT_RESET`, ogl33 only knows gl 3.3 so it is missing from it
+const GL_GUILTY_CONTEXT_RESET: u32 = 0x8253;
+/// `GL_INNOCENT_CONTEXT_RESET`
+const GL_INNOCENT_CONTEXT_RESET: u32 = 0x8254;
+/// `GL_UNKNOWN_CONTEXT_RESET`
+const GL_UNKNOWN_CONTEXT_RESET: u32 = 0x8255;
+
+type GetResetStatusFn = unsafe extern "system" fn() -> u32;
+
+/// Why the opengl context was lost, see [reset_status]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetStatus {
+    /// The context is fine
+    None,
+    /// The game caused the reset, e.g. a shader that ran for too long
+    Guilty,
+    /// Something else caused the reset, like another program or a driver update
+    Innocent,
+    /// The driver doesn't know what caused the reset
+    Unknown,
+}
+
+impl ResetStatus {
+    /// Checks if the context was lost
+    pub fn is_lost(self) -> bool {
+        self != ResetStatus::None
+    }
+}
+
+/// Gets the last sdl error
+#[cfg(feature = "sdl")]
+pub(crate) fn sdl_error() -> LighthouseError {
+    let error = unsafe { std::ffi::CStr::from_ptr(fermium::SDL_GetError()) };
+    LighthouseError::Sdl(error.to_string_lossy().into_owned())
+}
+
+/// Asks sdl for a context that tells the game when it is lost instead of crashing, has to
+/// be called before the [SdlWindow](super::window::SdlWindow) is created
+///
+/// Without it [reset_status] always gives [ResetStatus::None] on most drivers
+#[cfg(feature = "sdl")]
+pub fn request_robust_context() -> Result<(), LighthouseError> {
+    unsafe {
+        let mut flags = 0;
+        fermium::SDL_GL_GetAttribute(fermium::SDL_GL_CONTEXT_FLAGS, &mut flags);
+        let flags = flags | fermium::SDL_GL_CONTEXT_ROBUST_ACCESS_FLAG as i32;
+        if fermium::SDL_GL_SetAttribute(fermium::SDL_GL_CONTEXT_FLAGS, flags) != 0
+            || fermium::SDL_GL_SetAttribute(
+                fermium::SDL_GL_CONTEXT_RESET_NOTIFICATION,
+                fermium::SDL_GL_CONTEXT_RESET_LOSE_CONTEXT as i32,
+            ) != 0
+        {
+            return Err(sdl_error());
+        }
+    }
+    Ok(())
+}
+
+/// Loads glGetGraphicsResetStatus, which is gl 4.5 or `GL_ARB_robustness`, for
+/// [reset_status] with the loader of the context that is current, is called by
+/// [Window::load_gl]
+pub fn load_reset_status(get_proc_address: impl Fn(*const c_char) -> *const c_void) {
+    let function = ["glGetGraphicsResetStatus", "glGetGraphicsResetStatusARB"]
+        .iter()
+        .find_map(|name| {
+            let function = with_cstr(name, |name| get_proc_address(name.as_ptr().cast()));
+            (!function.is_null()).then(|| unsafe {
+                std::mem::transmute::<*const c_void, GetResetStatusFn>(function)
+            })
+        });
+    GET_RESET_STATUS.with(|get| get.set(function));
+}
+
+/// The last id given to a context, ids are never given twice, not even on other threads
+static LAST_CONTEXT: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The id of the context that is current on this thread, 0 when there is none
+    static CURRENT_CONTEXT: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The opengl context an object was made in
+///
+/// Opengl objects delete themselves when they are dropped, but only if the context they
+/// were made in is still current on the thread. Once the window is gone, the context was
+/// made again or the object is dropped on another thread the id may belong to something
+/// else, so it is left alone
+///
+/// The id doesn't come from the backend, a context gets a new one every time its functions
+/// are loaded with [Window::load_gl](super::window::Window::load_gl) or it is made again by
+/// [ContextRecovery]. A backend that deletes its context should call [ContextId::forget]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextId(u64);
+
+impl ContextId {
+    /// Gets the context that is current on this thread
+    pub fn current() -> Self {
+        ContextId(CURRENT_CONTEXT.with(Cell::get))
+    }
+
+    /// Checks if this context is still alive and current, so its objects can be deleted
+    pub fn is_current(self) -> bool {
+        self.0 != 0 && self == ContextId::current()
+    }
+
+    /// Gives the context that was just made current on this thread a new id, objects of
+    /// the context before are left alone from now on
+    pub fn new_context() -> Self {
+        let context = ContextId(LAST_CONTEXT.fetch_add(1, Ordering::Relaxed) + 1);
+        CURRENT_CONTEXT.with(|current| current.set(context.0));
+        context
+    }
+
+    /// Forgets the context of this thread, e.g. when the window is dropped, so its objects
+    /// are left alone from now on
+    pub fn forget() {
+        CURRENT_CONTEXT.with(|current| current.set(0));
+    }
+}
+
+thread_local! {
+    /// glGetGraphicsResetStatus of the context that is current, see [load_reset_status]
+    static GET_RESET_STATUS: Cell<Option<GetResetStatusFn>> = const { Cell::new(None) };
+}
+
+/// Checks if the opengl context of this thread was lost, is cheap enough to call every
+/// frame
+///
+/// Drivers that can't tell always give [ResetStatus::None], with SDL see
+/// `request_robust_context`
+pub fn reset_status() -> ResetStatus {
+    let status = GET_RESET_STATUS.with(|get| get.get().map_or(GL_NO_ERROR, |get| unsafe { get() }));
+    match status {
+        GL_GUILTY_CONTEXT_RESET => ResetStatus::Guilty,
+        GL_INNOCENT_CONTEXT_RESET => ResetStatus::Innocent,
+        GL_UNKNOWN_CONTEXT_RESET => ResetStatus::Unknown,
+        _ => ResetStatus::None,
+    }
+}
+
+/// What happened to the opengl context, see [UpdateCtx::context_events](crate::ECS::context::UpdateCtx::context_events)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextEvent {
+    /// The context was lost and every opengl object is gone, until
+    /// [World::restore_context](crate::ECS::world::World::restore_context) is called
+    /// nothing should be drawn
+    Lost(ResetStatus),
+    /// There is a new context and the restorers ran, anything that isn't restored by a
+    /// restorer has to be uploaded again now
+    Restored,
+}
+
+/// Something that makes opengl objects again after the context was lost
+pub type Restorer = Box<dyn FnMut(&AssetManager) -> Result<(), LighthouseError>>;
+
+/// Keeps track of whether the opengl context was lost and what has to be made again when
+/// there is a new one
+///
+/// Opengl objects are gone with the context, but the assets they were made from are still
+/// in the [AssetManager]. A restorer loads them again and uploads them, so the game only
+/// has to handle [ContextEvent::Restored] for what it made by hand
+///
+/// The window stays, only the context is made again with [Window::recreate_context]
+///
+/// # Example
+/// ```
+/// request_robust_context()?;
+/// let win = sdl.create_gl_window(...)?;
+///
+/// let stone = Rc::new(RefCell::new(None));
+/// let handle = stone.clone();
+/// world.env.recovery.add("stone", move |assets| {
+///     // the old texture was made in the lost context, dropping it doesn't delete anything
+///     *handle.borrow_mut() = Some(Texture::builder(assets.load("stone.png")?).build()?);
+///     Ok(())
+/// });
+///
+/// 'main_loop: loop {
+///     world.update();
+///     if world.env.recovery.is_lost() {
+///         for (name, error) in world.restore_context(&assets)? {
+///             eprintln!("Couldn't restore {}: {}", name, error);
+///         }
+///     }
+/// }
+/// ```
+pub struct ContextRecovery {
+    restorers: Vec<(String, Restorer)>,
+    lost: Option<ResetStatus>,
+}
+
+impl ContextRecovery {
+    /// Creates a new context recovery without restorers
+    pub fn new() -> Self {
+        ContextRecovery {
+            restorers: Vec::new(),
+            lost: None,
+        }
+    }
+
+    /// Adds a restorer called name, a restorer that already has the name is replaced
+    ///
+    /// The restorers run in the order they were added
+    pub fn add(
+        &mut self,
+        name: &str,
+        restorer: impl FnMut(&AssetManager) -> Result<(), LighthouseError> + 'static,
+    ) {
+        let restorer: Restorer = Box::new(restorer);
+        match self.restorers.iter_mut().find(|(other, _)| other == name) {
+            Some((_, old)) => *old = restorer,
+            None => self.restorers.push((name.to_string(), restorer)),
+        }
+    }
+
+    /// Removes the restorer called name
+    pub fn remove(&mut self, name: &str) {
+        self.restorers.retain(|(other, _)| other != name);
+    }
+
+    /// Checks the context, gives the reset status the first time it is seen lost
+    pub fn check(&mut self) -> Option<ResetStatus> {
+        if self.lost.is_some() {
+            return None;
+        }
+        let status = reset_status();
+        if !status.is_lost() {
+            return None;
+        }
+        self.lost = Some(status);
+        Some(status)
+    }
+
+    /// Marks the context as lost by hand, e.g. when the driver reported it some other way
+    pub fn mark_lost(&mut self, status: ResetStatus) {
+        self.lost = Some(status);
+    }
+
+    /// Checks if the context was lost and isn't restored yet
+    pub fn is_lost(&self) -> bool {
+        self.lost.is_some()
+    }
+
+    /// Gets why the context was lost if it is
+    pub fn lost_status(&self) -> Option<ResetStatus> {
+        self.lost
+    }
+
+    /// Makes a new context for win, makes it current and loads the opengl functions again
+    pub fn recreate_context(&mut self, win: &dyn Window) -> Result<(), LighthouseError> {
+        win.recreate_context()?;
+        win.load_gl();
+        Ok(())
+    }
+
+    /// Makes a new context with [ContextRecovery::recreate_context] and runs every restorer,
+    /// gives the name and error of every restorer that failed
+    ///
+    /// The context counts as restored even if a restorer failed
+    pub fn restore(
+        &mut self,
+        win: &dyn Window,
+        assets: &AssetManager,
+    ) -> Result<Vec<(String, LighthouseError)>, LighthouseError> {
+        self.recreate_context(win)?;
+        self.lost = None;
+        RenderState::invalidate();
+        Ok(self
+            .restorers
+            .iter_mut()
+            .filter_map(|(name, restorer)| restorer(assets).err().map(|e| (name.clone(), e)))
+            .collect())
+    }
+}
+
+impl Default for ContextRecovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,213 @@
+use std::cell::Cell;
+
+use super::{
+    pipeline::{BlendMode, CullMode, DepthState, StencilState},
+    *,
+};
+
+thread_local! {
+    /// The state opengl is in on this thread, None when it isn't known
+    static CURRENT: Cell<Option<RenderState>> = const { Cell::new(None) };
+}
+
+/// The depth, stencil, culling and blending state of opengl, set all at once with
+/// [RenderState::apply]
+///
+/// The last applied state is remembered, so applying a state only makes the gl calls for
+/// what is different. Anything that changes the same state with raw gl calls has to call
+/// [RenderState::invalidate] after, or the next apply may skip something it needs
+///
+/// # Example
+/// ```
+/// let opaque = RenderState::default().cull(CullMode::Back);
+/// let glass = RenderState::transparent();
+///
+/// 'main_loop: loop {
+///     opaque.apply();
+///     // draw the level
+///     glass.apply(); // only the blending and depth writing change
+///     // draw the windows
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderState {
+    /// How colors are blended
+    pub blend: BlendMode,
+    /// How the depth buffer is used
+    pub depth: DepthState,
+    /// How the stencil buffer is used, None turns the stencil test off
+    pub stencil: Option<StencilState>,
+    /// Which faces are thrown away
+    pub cull: CullMode,
+    /// Whether triangles whose vertices go counter clockwise are the front
+    pub front_ccw: bool,
+}
+
+impl RenderState {
+    /// Blends by alpha and tests against the depth without writing it, for transparent
+    /// things
+    pub fn transparent() -> Self {
+        RenderState {
+            blend: BlendMode::Alpha,
+            depth: DepthState::read_only(),
+            ..RenderState::default()
+        }
+    }
+
+    /// Blends by alpha without any depth, for 2d and ui drawn on top of everything
+    pub fn overlay() -> Self {
+        RenderState {
+            blend: BlendMode::Alpha,
+            depth: DepthState::disabled(),
+            ..RenderState::default()
+        }
+    }
+
+    /// Sets the blend mode
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Sets the depth state
+    pub fn depth(mut self, depth: DepthState) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the stencil state
+    pub fn stencil(mut self, stencil: Option<StencilState>) -> Self {
+        self.stencil = stencil;
+        self
+    }
+
+    /// Sets the cull mode
+    pub fn cull(mut self, cull: CullMode) -> Self {
+        self.cull = cull;
+        self
+    }
+
+    /// Sets which faces are the front
+    pub fn front_ccw(mut self, front_ccw: bool) -> Self {
+        self.front_ccw = front_ccw;
+        self
+    }
+
+    /// Makes this the state of opengl, skipping what is already set
+    pub fn apply(&self) {
+        let old = CURRENT.with(Cell::get);
+        let old = old.as_ref();
+
+        unsafe {
+            // a [Blend](super::blend::Blend) may have changed the equation
+            if old.is_none() {
+                glBlendEquation(GL_FUNC_ADD);
+            }
+            if changed(old, self, |s| s.blend.factors().is_some()) {
+                set_capability(GL_BLEND, self.blend.factors().is_some());
+            }
+            // with blending off the factors are whatever was set before, so they are set
+            // again when it is turned back on
+            if let Some((src, dst)) = self.blend.factors() {
+                if changed(old, self, |s| s.blend.factors()) {
+                    glBlendFunc(src as u32, dst as u32);
+                }
+            }
+
+            if changed(old, self, |s| s.depth.test) {
+                set_capability(GL_DEPTH_TEST, self.depth.test);
+            }
+            if changed(old, self, |s| s.depth.func) {
+                glDepthFunc(self.depth.func.gl());
+            }
+            if changed(old, self, |s| s.depth.write) {
+                glDepthMask(self.depth.write as u8);
+            }
+
+            if changed(old, self, |s| s.stencil.is_some()) {
+                set_capability(GL_STENCIL_TEST, self.stencil.is_some());
+            }
+            if let Some(stencil) = self.stencil {
+                if changed(old, self, |s| {
+                    s.stencil.map(|s| (s.func, s.reference, s.read_mask))
+                }) {
+                    glStencilFunc(stencil.func.gl(), stencil.reference, stencil.read_mask);
+                }
+                if changed(old, self, |s| s.stencil.map(|s| s.write_mask)) {
+                    glStencilMask(stencil.write_mask);
+                }
+                if changed(old, self, |s| {
+                    s.stencil.map(|s| (s.fail, s.depth_fail, s.pass))
+                }) {
+                    glStencilOp(
+                        stencil.fail.gl(),
+                        stencil.depth_fail.gl(),
+                        stencil.pass.gl(),
+                    );
+                }
+            }
+
+            if changed(old, self, |s| s.cull != CullMode::None) {
+                set_capability(GL_CULL_FACE, self.cull != CullMode::None);
+            }
+            if changed(old, self, |s| s.cull) {
+                match self.cull {
+                    CullMode::None => (),
+                    CullMode::Back => glCullFace(GL_BACK),
+                    CullMode::Front => glCullFace(GL_FRONT),
+                }
+            }
+            if changed(old, self, |s| s.front_ccw) {
+                glFrontFace(if self.front_ccw { GL_CCW } else { GL_CW });
+            }
+        }
+
+        CURRENT.with(|current| current.set(Some(*self)));
+    }
+
+    /// Gets the state that was applied last on this thread, None after
+    /// [RenderState::invalidate]
+    pub fn current() -> Option<RenderState> {
+        CURRENT.with(Cell::get)
+    }
+
+    /// Forgets what state opengl is in, so the next [RenderState::apply] sets everything
+    ///
+    /// Call it after changing depth, stencil, culling or blending with raw gl calls and
+    /// after the context is made again
+    pub fn invalidate() {
+        CURRENT.with(|current| current.set(None));
+    }
+}
+
+impl Default for RenderState {
+    /// Opaque and depth tested without culling, what most 3d meshes are drawn with
+    fn default() -> Self {
+        RenderState {
+            blend: BlendMode::Opaque,
+            depth: DepthState::default(),
+            stencil: None,
+            cull: CullMode::None,
+            front_ccw: true,
+        }
+    }
+}
+
+/// Checks if a part of the state is different from the old state, everything is different
+/// when the old state isn't known
+fn changed<T: PartialEq>(
+    old: Option<&RenderState>,
+    new: &RenderState,
+    get: impl Fn(&RenderState) -> T,
+) -> bool {
+    old.is_none_or(|old| get(old) != get(new))
+}
+
+/// Turns a capability on or off
+unsafe fn set_capability(cap: u32, on: bool) {
+    if on {
+        glEnable(cap);
+    } else {
+        glDisable(cap);
+    }
+}
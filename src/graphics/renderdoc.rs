@@ -0,0 +1,213 @@
+use std::{
+    ffi::{c_char, c_int, c_void, CStr},
+    path::PathBuf,
+};
+
+use device_query::Keycode;
+
+use super::*;
+
+/// The version of the in-application api that is asked for, 1.1.2 has everything used here
+const API_VERSION_1_1_2: c_int = 10102;
+
+type GetApiFn = unsafe extern "C" fn(version: c_int, api: *mut *mut c_void) -> c_int;
+
+/// The start of the function table of the RenderDoc in-application api, the functions that
+/// aren't used are only there to keep the order
+#[repr(C)]
+struct Api {
+    get_api_version: unsafe extern "C" fn(*mut c_int, *mut c_int, *mut c_int),
+    set_capture_option_u32: *const c_void,
+    set_capture_option_f32: *const c_void,
+    get_capture_option_u32: *const c_void,
+    get_capture_option_f32: *const c_void,
+    set_focus_toggle_keys: *const c_void,
+    set_capture_keys: *const c_void,
+    get_overlay_bits: *const c_void,
+    mask_overlay_bits: *const c_void,
+    remove_hooks: *const c_void,
+    unload_crash_handler: *const c_void,
+    set_capture_file_path_template: unsafe extern "C" fn(*const c_char),
+    get_capture_file_path_template: *const c_void,
+    get_num_captures: unsafe extern "C" fn() -> u32,
+    get_capture: unsafe extern "C" fn(u32, *mut c_char, *mut u32, *mut u64) -> u32,
+    trigger_capture: unsafe extern "C" fn(),
+    is_target_control_connected: unsafe extern "C" fn() -> u32,
+    launch_replay_ui: unsafe extern "C" fn(u32, *const c_char) -> u32,
+    set_active_window: *const c_void,
+    start_frame_capture: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    is_frame_capturing: unsafe extern "C" fn() -> u32,
+    end_frame_capture: unsafe extern "C" fn(*mut c_void, *mut c_void) -> u32,
+    trigger_multi_frame_capture: unsafe extern "C" fn(u32),
+}
+
+#[cfg(windows)]
+extern "system" {
+    fn GetModuleHandleA(name: *const c_char) -> *mut c_void;
+    fn GetProcAddress(module: *mut c_void, name: *const c_char) -> *mut c_void;
+}
+
+/// Finds RENDERDOC_GetAPI in the RenderDoc library, only if RenderDoc already injected it
+#[cfg(windows)]
+fn find_get_api() -> Option<GetApiFn> {
+    unsafe {
+        let module = GetModuleHandleA(c"renderdoc.dll".as_ptr());
+        if module.is_null() {
+            return None;
+        }
+        let get_api = GetProcAddress(module, c"RENDERDOC_GetAPI".as_ptr());
+        (!get_api.is_null()).then(|| std::mem::transmute::<*mut c_void, GetApiFn>(get_api))
+    }
+}
+
+/// Finds RENDERDOC_GetAPI in the RenderDoc library, only if RenderDoc already injected it
+#[cfg(all(unix, not(target_os = "macos")))]
+fn find_get_api() -> Option<GetApiFn> {
+    unsafe {
+        let library = libc::dlopen(
+            c"librenderdoc.so".as_ptr(),
+            libc::RTLD_NOW | libc::RTLD_NOLOAD,
+        );
+        if library.is_null() {
+            return None;
+        }
+        let get_api = libc::dlsym(library, c"RENDERDOC_GetAPI".as_ptr());
+        (!get_api.is_null()).then(|| std::mem::transmute::<*mut c_void, GetApiFn>(get_api))
+    }
+}
+
+/// RenderDoc doesn't run on macos
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+fn find_get_api() -> Option<GetApiFn> {
+    None
+}
+
+/// A connection to [RenderDoc](https://renderdoc.org) through its in-application api, so
+/// captures can be made from code or with a key instead of from the RenderDoc ui
+///
+/// It only works when the game was started from RenderDoc, otherwise [RenderDoc::load] gives
+/// None. Needs the `renderdoc` feature
+///
+/// A capture is everything between two swaps of the window, so [RenderDoc::trigger_capture]
+/// captures the next whole frame. The [Renderer](super::renderer::Renderer) loads it by
+/// itself, see [Renderer::capture_next_frame](super::renderer::Renderer::capture_next_frame)
+///
+/// # Example
+/// ```
+/// world.renderer.renderdoc = RenderDoc::load().map(|renderdoc| renderdoc.capture_key(Keycode::F11));
+///
+/// // or when something looks wrong
+/// if glitch_happened {
+///     world.renderer.capture_next_frame();
+/// }
+/// ```
+pub struct RenderDoc {
+    api: &'static Api,
+    /// The key that captures the next frame
+    pub capture_key: Option<Keycode>,
+    /// The keys that were down last update, so holding a key only captures once
+    last_keys: Vec<Keycode>,
+}
+
+impl RenderDoc {
+    /// Connects to RenderDoc, gives None if the game wasn't started from RenderDoc
+    pub fn load() -> Option<Self> {
+        let get_api = find_get_api()?;
+        let mut api = std::ptr::null_mut();
+        if unsafe { get_api(API_VERSION_1_1_2, &mut api) } != 1 || api.is_null() {
+            return None;
+        }
+        Some(RenderDoc {
+            // the table lives as long as the RenderDoc library, which is never unloaded
+            api: unsafe { &*(api as *const Api) },
+            capture_key: None,
+            last_keys: Vec::new(),
+        })
+    }
+
+    /// Sets the key that captures the next frame
+    pub fn capture_key(mut self, key: Keycode) -> Self {
+        self.capture_key = Some(key);
+        self
+    }
+
+    /// Gets the version of the api RenderDoc gave as (major, minor, patch)
+    pub fn version(&self) -> (i32, i32, i32) {
+        let (mut major, mut minor, mut patch) = (0, 0, 0);
+        unsafe { (self.api.get_api_version)(&mut major, &mut minor, &mut patch) };
+        (major, minor, patch)
+    }
+
+    /// Captures the next frame
+    pub fn trigger_capture(&self) {
+        unsafe { (self.api.trigger_capture)() }
+    }
+
+    /// Captures the next frames, every frame is a capture of its own
+    pub fn trigger_captures(&self, frames: u32) {
+        unsafe { (self.api.trigger_multi_frame_capture)(frames) }
+    }
+
+    /// Starts capturing right away instead of at the next swap, for captures that aren't
+    /// a whole frame, like a compute dispatch
+    pub fn start_capture(&self) {
+        unsafe { (self.api.start_frame_capture)(std::ptr::null_mut(), std::ptr::null_mut()) }
+    }
+
+    /// Ends the capture started with [RenderDoc::start_capture], gives false if it failed
+    pub fn end_capture(&self) -> bool {
+        unsafe { (self.api.end_frame_capture)(std::ptr::null_mut(), std::ptr::null_mut()) == 1 }
+    }
+
+    /// Checks if a capture is being made right now
+    pub fn is_capturing(&self) -> bool {
+        unsafe { (self.api.is_frame_capturing)() == 1 }
+    }
+
+    /// Sets where the captures are saved, RenderDoc adds the frame number and `.rdc` to it
+    pub fn set_capture_path(&self, template: &str) {
+        with_cstr(template, |template| unsafe {
+            (self.api.set_capture_file_path_template)(template.as_ptr())
+        });
+    }
+
+    /// Gets how many captures were made
+    pub fn capture_count(&self) -> u32 {
+        unsafe { (self.api.get_num_captures)() }
+    }
+
+    /// Gets the path of the capture at index, the first capture is 0
+    pub fn capture(&self, index: u32) -> Option<PathBuf> {
+        let mut len = 0;
+        let found = unsafe {
+            (self.api.get_capture)(index, std::ptr::null_mut(), &mut len, std::ptr::null_mut())
+        };
+        if found != 1 || len == 0 {
+            return None;
+        }
+        let mut path = vec![0 as c_char; len as usize];
+        unsafe { (self.api.get_capture)(index, path.as_mut_ptr(), &mut len, std::ptr::null_mut()) };
+        let path = unsafe { CStr::from_ptr(path.as_ptr()) };
+        Some(PathBuf::from(path.to_string_lossy().into_owned()))
+    }
+
+    /// Opens the RenderDoc ui connected to the game, gives false if it couldn't
+    pub fn launch_replay_ui(&self) -> bool {
+        if unsafe { (self.api.is_target_control_connected)() } == 1 {
+            return true;
+        }
+        unsafe { (self.api.launch_replay_ui)(1, std::ptr::null()) != 0 }
+    }
+
+    /// Handles the keys that are down this update, a key counts when it is pressed
+    pub fn handle_keys(&mut self, keys: &[Keycode]) {
+        if self
+            .capture_key
+            .is_some_and(|key| keys.contains(&key) && !self.last_keys.contains(&key))
+        {
+            self.trigger_capture();
+        }
+        self.last_keys.clear();
+        self.last_keys.extend_from_slice(keys);
+    }
+}
@@ -0,0 +1,305 @@
+use nalgebra_glm::Mat4;
+
+#[cfg(feature = "renderdoc")]
+use super::renderdoc::RenderDoc;
+use super::{
+    shader::ShaderProgram,
+    target_pool::TargetPool,
+    texture::{Texture, TextureTarget},
+    uniform::Uniform,
+    *,
+};
+use crate::ECS::mesh::{Mesh, VertexTrait};
+
+/// Glsl that declares `uniform float alpha_cutoff` and `void alpha_test(float alpha)`, which
+/// discards the pixel if alpha is below the cutoff
+///
+/// Paste it into the fragment shader of a material and call `alpha_test` with the alpha of
+/// its texture, the [Renderer] sets the cutoff of every [DrawCommand::alpha_cutoff]
+pub const ALPHA_CUTOFF_GLSL: &str = "uniform float alpha_cutoff;
+
+void alpha_test(float alpha) {
+    if (alpha < alpha_cutoff) {
+        discard;
+    }
+}";
+
+/// Something the [Renderer] can draw, every [Mesh] is one
+pub trait Renderable {
+    /// Gets the model matrix it is drawn with
+    fn model_matrix(&self) -> Mat4;
+
+    /// Draws it with the vao bound, calling bind_material before every part that has
+    /// its own material
+    fn draw_with_materials(&self, bind_material: &mut dyn FnMut(usize));
+}
+
+impl<Vertex: VertexTrait + 'static + Sync + Send> Renderable for Mesh<Vertex> {
+    fn model_matrix(&self) -> Mat4 {
+        Mesh::model_matrix(self)
+    }
+
+    fn draw_with_materials(&self, bind_material: &mut dyn FnMut(usize)) {
+        Mesh::draw_with_materials(self, bind_material)
+    }
+}
+
+/// A mesh that is going to be drawn by [Renderer::render], with the program and texture it
+/// is drawn with
+///
+/// # Example
+/// ```
+/// fn get_draw_commands(&self) -> Vec<DrawCommand<'_>> {
+///     vec![
+///         DrawCommand::new(&self.pyramid.mesh).texture(&self.stone),
+///         DrawCommand::new(&self.water.mesh).program(&self.water_program),
+///         // leaves are seen from both sides and have holes in their texture
+///         DrawCommand::new(&self.tree.leaves)
+///             .texture(&self.leaf)
+///             .double_sided(true)
+///             .alpha_cutoff(0.5),
+///     ]
+/// }
+/// ```
+pub struct DrawCommand<'a> {
+    /// What is drawn
+    pub mesh: &'a dyn Renderable,
+    /// The program it is drawn with, the program of the [Enviroment](crate::ECS::world::Enviroment)
+    /// if it is None
+    pub program: Option<&'a ShaderProgram>,
+    /// The texture bound to unit 0 while it is drawn, the texture that is already bound
+    /// stays if it is None
+    pub texture: Option<&'a Texture>,
+    /// Whether both faces of the triangles are drawn, culling is turned off while it is
+    /// drawn
+    pub double_sided: bool,
+    /// Pixels with an alpha below it are thrown away, the program needs [ALPHA_CUTOFF_GLSL].
+    /// None sets the cutoff to 0 so nothing is thrown away
+    pub alpha_cutoff: Option<f32>,
+}
+
+impl<'a> DrawCommand<'a> {
+    /// Creates a new draw command for mesh without its own program or texture
+    pub fn new(mesh: &'a dyn Renderable) -> Self {
+        DrawCommand {
+            mesh,
+            program: None,
+            texture: None,
+            double_sided: false,
+            alpha_cutoff: None,
+        }
+    }
+
+    /// Sets the program it is drawn with
+    pub fn program(mut self, program: &'a ShaderProgram) -> Self {
+        self.program = Some(program);
+        self
+    }
+
+    /// Sets the texture it is drawn with
+    pub fn texture(mut self, texture: &'a Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Sets whether both faces of the triangles are drawn
+    pub fn double_sided(mut self, double_sided: bool) -> Self {
+        self.double_sided = double_sided;
+        self
+    }
+
+    /// Sets the alpha below which pixels are thrown away
+    pub fn alpha_cutoff(mut self, alpha_cutoff: f32) -> Self {
+        self.alpha_cutoff = Some(alpha_cutoff);
+        self
+    }
+
+    /// Gets what the commands are sorted by, first the program then the texture, so the
+    /// state changes as little as possible
+    fn sort_key(&self, default_program: &ShaderProgram) -> (u32, u32) {
+        (
+            self.program.unwrap_or(default_program).0,
+            self.texture.map_or(0, |texture| texture.id),
+        )
+    }
+}
+
+/// Draws 3d meshes with their model matrices, so the draw calls don't have to be written
+/// by hand
+///
+/// Usually the renderer of the world is used with [World::render](crate::ECS::world::World::render),
+/// which draws the [DrawCommand]s of every game object. Meshes can also be drawn one by one,
+/// every mesh is drawn with the program it is given and the model matrix of the mesh is
+/// uploaded to the uniform [Renderer::model_uniform] first
+///
+/// # Example
+/// ```
+/// let mut renderer = Renderer::new();
+/// pyramid.upload();
+///
+/// 'main_loop: loop {
+///     world.update();
+///     renderer.begin_frame([0.2, 0.3, 0.3, 1.0]);
+///     renderer.draw_mesh(&program, &pyramid);
+///     world.env.win.swap_buffers();
+/// }
+/// ```
+pub struct Renderer {
+    /// The name of the model matrix uniform in the programs meshes are drawn with
+    pub model_uniform: String,
+    /// The color [Renderer::render] clears the screen to
+    pub clear_color: [f32; 4],
+    /// The render targets of the post processing, the [World](crate::ECS::world::World)
+    /// resizes the ones that scale with or follow the window when it is resized and makes
+    /// them again when the context is restored
+    pub targets: TargetPool,
+    /// The connection to RenderDoc, None if the game wasn't started from RenderDoc
+    #[cfg(feature = "renderdoc")]
+    pub renderdoc: Option<RenderDoc>,
+    draw_calls: usize,
+}
+
+impl Renderer {
+    /// Creates a new renderer that uploads the model matrix to `uniform mat4 model`
+    pub fn new() -> Self {
+        Renderer {
+            model_uniform: "model".to_string(),
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            targets: TargetPool::new(),
+            #[cfg(feature = "renderdoc")]
+            renderdoc: RenderDoc::load(),
+            draw_calls: 0,
+        }
+    }
+
+    /// Sets the name of the model matrix uniform
+    pub fn model_uniform(mut self, name: &str) -> Self {
+        self.model_uniform = name.to_string();
+        self
+    }
+
+    /// Sets the color [Renderer::render] clears the screen to
+    pub fn clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// Clears the bound framebuffer to color and the depth to the far plane and starts
+    /// counting the draw calls again
+    pub fn begin_frame(&mut self, color: [f32; 4]) {
+        self.draw_calls = 0;
+        unsafe {
+            glClearColor(color[0], color[1], color[2], color[3]);
+            glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+        }
+    }
+
+    /// Draws mesh with program at its [Mesh::transform]
+    ///
+    /// The mesh has to be uploaded with [Mesh::upload] first, a mesh with sub meshes is
+    /// drawn with one draw call per sub mesh
+    pub fn draw_mesh<Vertex: VertexTrait + 'static + Sync + Send>(
+        &mut self,
+        program: &ShaderProgram,
+        mesh: &Mesh<Vertex>,
+    ) {
+        program.use_program();
+        mesh.set_model_uniform(program, &self.model_uniform);
+        mesh.draw_with_materials(|_| self.draw_calls += 1);
+    }
+
+    /// Draws mesh with program, calling bind_material with the material of every sub mesh
+    /// before it is drawn, see [Mesh::draw_with_materials]
+    pub fn draw_mesh_with_materials<Vertex: VertexTrait + 'static + Sync + Send>(
+        &mut self,
+        program: &ShaderProgram,
+        mesh: &Mesh<Vertex>,
+        mut bind_material: impl FnMut(usize),
+    ) {
+        program.use_program();
+        mesh.set_model_uniform(program, &self.model_uniform);
+        mesh.draw_with_materials(|material| {
+            bind_material(material);
+            self.draw_calls += 1;
+        });
+    }
+
+    /// Clears the screen to [Renderer::clear_color] and draws every command
+    ///
+    /// The commands are sorted by program and then texture, a program is only used and a
+    /// texture only bound when it is different from the last command. Commands without a
+    /// program are drawn with default_program
+    ///
+    /// Culling is turned off for [double sided](DrawCommand::double_sided) commands and back
+    /// on after them if it was on, the `alpha_cutoff` uniform is set for every command
+    pub fn render(&mut self, default_program: &ShaderProgram, mut commands: Vec<DrawCommand>) {
+        self.begin_frame(self.clear_color);
+        commands.sort_by_key(|command| command.sort_key(default_program));
+
+        let cull = unsafe { glIsEnabled(GL_CULL_FACE) == GL_TRUE };
+        let mut culling = cull;
+        let mut program_id = None;
+        let mut texture_id = None;
+        for command in &commands {
+            let program = command.program.unwrap_or(default_program);
+            if program_id != Some(program.0) {
+                program.use_program();
+                program_id = Some(program.0);
+            }
+            if let Some(texture) = command.texture {
+                if texture_id != Some(texture.id) {
+                    let target = texture.texture_type.unwrap_or(TextureTarget::Texture2D);
+                    unsafe {
+                        glActiveTexture(GL_TEXTURE0);
+                        glBindTexture(target.into(), texture.id);
+                    }
+                    texture_id = Some(texture.id);
+                }
+            }
+
+            if cull && culling == command.double_sided {
+                culling = !command.double_sided;
+                unsafe {
+                    match culling {
+                        true => glEnable(GL_CULL_FACE),
+                        false => glDisable(GL_CULL_FACE),
+                    }
+                }
+            }
+
+            Uniform::new(program, &self.model_uniform).set_mat4(&command.mesh.model_matrix());
+            Uniform::new(program, "alpha_cutoff")
+                .set_uniform_f(&[command.alpha_cutoff.unwrap_or(0.0)]);
+            command
+                .mesh
+                .draw_with_materials(&mut |_| self.draw_calls += 1);
+        }
+        if cull && !culling {
+            unsafe { glEnable(GL_CULL_FACE) };
+        }
+    }
+
+    /// Makes RenderDoc capture the next frame, gives false if the game wasn't started from
+    /// RenderDoc. Needs the `renderdoc` feature
+    #[cfg(feature = "renderdoc")]
+    pub fn capture_next_frame(&self) -> bool {
+        match &self.renderdoc {
+            Some(renderdoc) => {
+                renderdoc.trigger_capture();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gets how many draw calls were made since [Renderer::begin_frame]
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer::new()
+    }
+}
@@ -0,0 +1,196 @@
+use super::{framebuffer::Framebuffer, fullscreen::FullscreenPass, target_pool::WindowTargets, *};
+
+/// Bilinear upscale, uv_scale is the part of the target the scene was rendered into
+const BILINEAR_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform vec2 uv_scale;
+out vec4 color;
+
+void main() {
+    color = texture(input_texture, uv * uv_scale);
+}";
+
+/// Bilinear upscale followed by contrast adaptive sharpening, like the RCAS pass of FSR1
+const SHARPEN_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform vec2 uv_scale;
+uniform float sharpness;
+out vec4 color;
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(input_texture, 0));
+    vec2 pos = uv * uv_scale;
+    vec3 c = texture(input_texture, pos).rgb;
+    vec3 n = texture(input_texture, pos + vec2(0.0, texel.y)).rgb;
+    vec3 s = texture(input_texture, pos - vec2(0.0, texel.y)).rgb;
+    vec3 e = texture(input_texture, pos + vec2(texel.x, 0.0)).rgb;
+    vec3 w = texture(input_texture, pos - vec2(texel.x, 0.0)).rgb;
+
+    vec3 min_c = min(c, min(min(n, s), min(e, w)));
+    vec3 max_c = max(c, max(max(n, s), max(e, w)));
+    // sharpen less where there is already a lot of contrast so edges dont ring
+    vec3 amount = sqrt(clamp(min(min_c, 1.0 - max_c) / max(max_c, 0.0001), 0.0, 1.0));
+    vec3 weight = -amount * mix(0.125, 0.2, sharpness);
+
+    color = vec4((c + (n + s + e + w) * weight) / (1.0 + 4.0 * weight), 1.0);
+}";
+
+/// How the scaled down scene is stretched back to the size of the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Upscale {
+    /// Plain bilinear filtering
+    Bilinear,
+    /// Bilinear filtering followed by a sharpening pass, the value is from 0 to 1
+    Sharpen(f32),
+}
+
+/// Renders the scene at a lower resolution when the frame takes longer than the budget
+///
+/// The target is always the size of the window, the scene is only rendered into the top
+/// left part of it, so changing the scale never reallocates anything
+///
+/// # Example
+/// ```
+/// let mut resolution = DynamicResolution::new(800, 600, 1.0 / 60.0, Upscale::Sharpen(0.5))?;
+///
+/// 'main_loop: loop {
+///     resolution.begin();
+///     // draw the 3d scene
+///     resolution.end(world.env.time.real_delta);
+///     // draw the ui at full resolution
+///     world.env.win.swap_buffers();
+/// }
+/// ```
+pub struct DynamicResolution {
+    /// How long a frame should take(in seconds)
+    pub frame_budget: f32,
+    /// The lowest the scale can go
+    pub min_scale: f32,
+    /// The highest the scale can go
+    pub max_scale: f32,
+    /// How much the scale changes at once
+    pub step: f32,
+    /// The current scale, the scene is rendered at scale * window size
+    pub scale: f32,
+    /// How the scene is upscaled
+    pub upscale: Upscale,
+    /// The frame time averaged over the last few frames
+    smoothed_frame_time: f32,
+    /// The target the scene is rendered into
+    target: Framebuffer,
+    /// Pass used for [Upscale::Bilinear]
+    bilinear: FullscreenPass,
+    /// Pass used for [Upscale::Sharpen]
+    sharpen: FullscreenPass,
+}
+
+impl DynamicResolution {
+    /// Creates a new dynamic resolution for a window of size width by height
+    ///
+    /// frame_budget is how long a frame should take(in seconds)
+    pub fn new(
+        width: i32,
+        height: i32,
+        frame_budget: f32,
+        upscale: Upscale,
+    ) -> Result<Self, LighthouseError> {
+        Ok(DynamicResolution {
+            frame_budget,
+            min_scale: 0.5,
+            max_scale: 1.0,
+            step: 0.05,
+            scale: 1.0,
+            upscale,
+            smoothed_frame_time: frame_budget,
+            target: Framebuffer::new(width, height)?,
+            bilinear: FullscreenPass::new(BILINEAR_FRAG)?,
+            sharpen: FullscreenPass::new(SHARPEN_FRAG)?,
+        })
+    }
+
+    /// Resizes the target, should be called when the window is resized
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        self.target.resize(width, height)
+    }
+
+    /// Gets the size the scene is rendered at
+    pub fn render_size(&self) -> (i32, i32) {
+        (
+            ((self.target.width as f32 * self.scale) as i32).max(1),
+            ((self.target.height as f32 * self.scale) as i32).max(1),
+        )
+    }
+
+    /// Binds the scaled down target and clears it, everything drawn after this is scaled
+    pub fn begin(&self) {
+        let (width, height) = self.render_size();
+        self.target.bind();
+        unsafe {
+            glViewport(0, 0, width, height);
+            glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+        }
+    }
+
+    /// Upscales the scene to the window and changes the scale for the next frame
+    ///
+    /// frame_time is how long the last frame took(in seconds)
+    pub fn end(&mut self, frame_time: f32) {
+        Framebuffer::unbind();
+        unsafe { glViewport(0, 0, self.target.width, self.target.height) }
+
+        let (width, height) = self.render_size();
+        let uv_scale = [
+            width as f32 / self.target.width as f32,
+            height as f32 / self.target.height as f32,
+        ];
+        let pass = match self.upscale {
+            Upscale::Bilinear => &self.bilinear,
+            Upscale::Sharpen(sharpness) => {
+                self.sharpen
+                    .uniform("sharpness")
+                    .set_uniform_f(&[sharpness]);
+                &self.sharpen
+            }
+        };
+        pass.uniform("uv_scale").set_uniform_f(&uv_scale);
+        pass.draw(&self.target.color);
+
+        self.adapt(frame_time);
+    }
+
+    /// Changes the scale so the frame time gets closer to the budget
+    fn adapt(&mut self, frame_time: f32) {
+        self.smoothed_frame_time += (frame_time - self.smoothed_frame_time) * 0.1;
+
+        // only change the scale when far from the budget so it doesnt flicker
+        let step = if self.smoothed_frame_time > self.frame_budget * 1.05 {
+            -self.step
+        } else if self.smoothed_frame_time < self.frame_budget * 0.85 {
+            self.step
+        } else {
+            return;
+        };
+        self.scale = (self.scale + step).clamp(self.min_scale, self.max_scale);
+        // give the new scale a few frames to show up in the frame time
+        self.smoothed_frame_time = self.frame_budget;
+    }
+
+    /// Deletes the target and passes
+    pub fn delete(self) {
+        self.target.delete();
+        self.bilinear.delete();
+        self.sharpen.delete();
+    }
+}
+
+impl WindowTargets for DynamicResolution {
+    fn window_resized(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        self.resize(width, height)
+    }
+
+    fn recreate_targets(&mut self) -> Result<(), LighthouseError> {
+        self.target.recreate()
+    }
+}
@@ -0,0 +1,306 @@
+use super::{
+    framebuffer::Framebuffer,
+    fullscreen::FullscreenPass,
+    target_pool::WindowTargets,
+    texture::{Texture, TextureFilter, TextureSettings, TextureTarget},
+    *,
+};
+
+const RETRO_FRAG: &str = "#version 330 core
+in vec2 uv;
+
+uniform sampler2D input_texture;
+uniform float pixelate;
+uniform float curvature;
+uniform float scanlines;
+uniform float scanline_count;
+uniform float aberration;
+uniform float vignette;
+uniform float dither_levels;
+uniform float dither_strength;
+
+out vec4 color;
+
+const float BAYER[16] = float[](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0
+);
+
+void main() {
+    vec2 size = vec2(textureSize(input_texture, 0));
+    vec2 pos = uv;
+
+    // bend the screen like the glass of a crt
+    vec2 centered = pos * 2.0 - 1.0;
+    centered *= 1.0 + curvature * dot(centered, centered) * 0.25;
+    pos = centered * 0.5 + 0.5;
+    if (pos.x < 0.0 || pos.x > 1.0 || pos.y < 0.0 || pos.y > 1.0) {
+        color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    // read the middle of the big pixel this one is in
+    vec2 cells = size / max(pixelate, 1.0);
+    vec2 snapped = (floor(pos * cells) + 0.5) / cells;
+
+    // split the colors apart towards the edges
+    vec2 shift = (snapped - 0.5) * aberration;
+    vec3 rgb = vec3(
+        texture(input_texture, snapped + shift).r,
+        texture(input_texture, snapped).g,
+        texture(input_texture, snapped - shift).b
+    );
+
+    float line = sin(pos.y * scanline_count * 3.14159265);
+    rgb *= 1.0 - scanlines * (1.0 - line * line);
+    rgb *= 1.0 - vignette * dot(centered, centered) * 0.5;
+
+    if (dither_levels > 1.0) {
+        ivec2 cell = ivec2(mod(floor(pos * cells), 4.0));
+        float threshold = BAYER[cell.y * 4 + cell.x] / 16.0 - 0.5;
+        float steps = dither_levels - 1.0;
+        rgb = floor(rgb * steps + 0.5 + threshold * dither_strength) / steps;
+    }
+    color = vec4(clamp(rgb, 0.0, 1.0), 1.0);
+}";
+
+const NEAREST_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+out vec4 color;
+
+void main() {
+    color = texture(input_texture, uv);
+}";
+
+/// The effects of a [RetroPass], 0 turns an effect off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetroSettings {
+    /// The size of the big pixels the screen is cut into(in pixels)
+    pub pixelate: f32,
+    /// How much the screen bulges like the glass of a crt
+    pub curvature: f32,
+    /// How dark the gaps between the scanlines are, from 0 to 1
+    pub scanlines: f32,
+    /// How many scanlines there are from the bottom to the top of the screen
+    pub scanline_count: f32,
+    /// How far the red and blue are split from the green at the edges(in uvs)
+    pub chromatic_aberration: f32,
+    /// How dark the corners get, from 0 to 1
+    pub vignette: f32,
+    /// How many shades every color channel is cut down to, 0 or 1 turns dithering off
+    pub dither_levels: u32,
+    /// How much the bayer pattern moves colors between shades, from 0 to 1
+    pub dither_strength: f32,
+}
+
+impl RetroSettings {
+    /// The look of an old crt tv
+    pub fn crt() -> Self {
+        RetroSettings {
+            curvature: 0.15,
+            scanlines: 0.35,
+            scanline_count: 240.0,
+            chromatic_aberration: 0.004,
+            vignette: 0.4,
+            ..RetroSettings::default()
+        }
+    }
+
+    /// Big pixels of size pixels with a few dithered colors
+    pub fn pixel_art(size: f32) -> Self {
+        RetroSettings {
+            pixelate: size,
+            dither_levels: 8,
+            dither_strength: 1.0,
+            ..RetroSettings::default()
+        }
+    }
+}
+
+impl Default for RetroSettings {
+    fn default() -> Self {
+        RetroSettings {
+            pixelate: 0.0,
+            curvature: 0.0,
+            scanlines: 0.0,
+            scanline_count: 240.0,
+            chromatic_aberration: 0.0,
+            vignette: 0.0,
+            dither_levels: 0,
+            dither_strength: 1.0,
+        }
+    }
+}
+
+/// A post pass with retro effects, scanlines, crt curvature, chromatic aberration,
+/// pixelation and dithering
+///
+/// Every effect is in the one pass, so turning more on doesn't cost another pass
+///
+/// # Example
+/// ```
+/// let retro = RetroPass::new()?.settings(RetroSettings {
+///     dither_levels: 4,
+///     ..RetroSettings::crt()
+/// });
+///
+/// retro.draw(&target.color);
+/// ```
+pub struct RetroPass {
+    /// The effects of the pass
+    pub settings: RetroSettings,
+    pass: FullscreenPass,
+}
+
+impl RetroPass {
+    /// Creates a new retro pass with every effect off
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(RetroPass {
+            settings: RetroSettings::default(),
+            pass: FullscreenPass::new(RETRO_FRAG)?,
+        })
+    }
+
+    /// Sets the effects of the pass
+    pub fn settings(mut self, settings: RetroSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Draws scene with the effects into the bound framebuffer
+    pub fn draw(&self, scene: &Texture) {
+        let settings = &self.settings;
+        let pass = &self.pass;
+        pass.uniform("pixelate").set_uniform_f(&[settings.pixelate]);
+        pass.uniform("curvature")
+            .set_uniform_f(&[settings.curvature]);
+        pass.uniform("scanlines")
+            .set_uniform_f(&[settings.scanlines.clamp(0.0, 1.0)]);
+        pass.uniform("scanline_count")
+            .set_uniform_f(&[settings.scanline_count]);
+        pass.uniform("aberration")
+            .set_uniform_f(&[settings.chromatic_aberration]);
+        pass.uniform("vignette")
+            .set_uniform_f(&[settings.vignette.clamp(0.0, 1.0)]);
+        pass.uniform("dither_levels")
+            .set_uniform_f(&[settings.dither_levels as f32]);
+        pass.uniform("dither_strength")
+            .set_uniform_f(&[settings.dither_strength]);
+        pass.draw(scene);
+    }
+
+    /// Deletes the pass
+    pub fn delete(self) {
+        self.pass.delete();
+    }
+}
+
+/// A small render target that is scaled up to the window with nearest filtering, so pixel
+/// art stays sharp
+///
+/// The scene is drawn at the low resolution and scaled up by the biggest whole number that
+/// fits the window, the rest of the window is left as a border
+///
+/// # Example
+/// ```
+/// let low_res = LowResTarget::new(320, 180)?;
+///
+/// 'main_loop: loop {
+///     low_res.begin();
+///     // draw the game
+///     low_res.end(window_width, window_height);
+///     world.env.win.swap_buffers();
+/// }
+/// ```
+pub struct LowResTarget {
+    /// The target the scene is drawn into
+    pub target: Framebuffer,
+    /// Whether the scale is rounded down to a whole number, so every pixel is the same size
+    pub integer_scale: bool,
+    pass: FullscreenPass,
+}
+
+impl LowResTarget {
+    /// Creates a new target of size width by height
+    pub fn new(width: i32, height: i32) -> Result<Self, LighthouseError> {
+        let mut target = Framebuffer::new(width, height)?;
+        nearest(&mut target);
+
+        Ok(LowResTarget {
+            target,
+            integer_scale: true,
+            pass: FullscreenPass::new(NEAREST_FRAG)?,
+        })
+    }
+
+    /// Binds the target and clears it, everything drawn after this is low resolution
+    pub fn begin(&self) {
+        self.target.bind();
+        unsafe { glClear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT) }
+    }
+
+    /// Clears the window and draws the target scaled up into the middle of it, width and
+    /// height are the size of the window(in pixels)
+    pub fn end(&self, width: i32, height: i32) {
+        Framebuffer::unbind();
+        let (x, y, scaled_width, scaled_height) = self.viewport(width, height);
+        unsafe {
+            glViewport(0, 0, width, height);
+            glClear(GL_COLOR_BUFFER_BIT);
+            glViewport(x, y, scaled_width, scaled_height);
+        }
+        self.pass.draw(&self.target.color);
+        unsafe { glViewport(0, 0, width, height) }
+    }
+
+    /// Gets where the target is drawn in a window of size width by height, as x, y, width
+    /// and height(in pixels), e.g. to turn mouse positions into positions on the target
+    pub fn viewport(&self, width: i32, height: i32) -> (i32, i32, i32, i32) {
+        let scale = (width as f32 / self.target.width as f32)
+            .min(height as f32 / self.target.height as f32);
+        let scale = if self.integer_scale && scale >= 1.0 {
+            scale.floor()
+        } else {
+            scale
+        };
+        let scaled_width = (self.target.width as f32 * scale) as i32;
+        let scaled_height = (self.target.height as f32 * scale) as i32;
+        (
+            (width - scaled_width) / 2,
+            (height - scaled_height) / 2,
+            scaled_width,
+            scaled_height,
+        )
+    }
+
+    /// Deletes the target and pass
+    pub fn delete(self) {
+        self.target.delete();
+        self.pass.delete();
+    }
+}
+
+impl WindowTargets for LowResTarget {
+    /// The target stays at its low resolution, [LowResTarget::end] is given the size of
+    /// the window every frame
+    fn window_resized(&mut self, _width: i32, _height: i32) -> Result<(), LighthouseError> {
+        Ok(())
+    }
+
+    fn recreate_targets(&mut self) -> Result<(), LighthouseError> {
+        self.target.recreate()?;
+        nearest(&mut self.target);
+        Ok(())
+    }
+}
+
+/// Makes the color of target use nearest filtering, so its pixels stay sharp
+fn nearest(target: &mut Framebuffer) {
+    target.color.bind(TextureTarget::Texture2D);
+    TextureSettings::default()
+        .filter(TextureFilter::Nearest, TextureFilter::Nearest)
+        .apply(&mut target.color);
+}
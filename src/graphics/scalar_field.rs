@@ -0,0 +1,538 @@
+use std::mem::size_of;
+
+use nalgebra_glm::{Mat4, Vec3};
+
+use super::{
+    buffer::*,
+    shader::ShaderProgram,
+    texture::{Texture, TextureError, TextureFilter, TextureSettings, TextureTarget, TextureWrap},
+    uniform::Uniform,
+    vertex::VertexArray,
+    *,
+};
+
+const HEIGHTFIELD_VERT: &str = "#version 330 core
+layout (location = 0) in vec2 uv;
+
+uniform mat4 model;
+uniform mat4 view_proj;
+uniform sampler2D field;
+uniform vec2 range;
+uniform float height;
+
+out float value;
+out vec3 world_pos;
+
+void main() {
+    value = (texture(field, uv).r - range.x) / max(range.y - range.x, 1e-6);
+    vec3 pos = vec3(uv.x - 0.5, clamp(value, 0.0, 1.0) * height, uv.y - 0.5);
+    world_pos = (model * vec4(pos, 1.0)).xyz;
+    gl_Position = view_proj * vec4(world_pos, 1.0);
+}";
+
+const HEIGHTFIELD_FRAG: &str = "#version 330 core
+in float value;
+in vec3 world_pos;
+
+uniform sampler2D transfer;
+uniform vec3 light_dir;
+uniform bool shaded;
+
+out vec4 frag_color;
+
+void main() {
+    vec4 color = texture(transfer, vec2(clamp(value, 0.0, 1.0), 0.5));
+    if (shaded) {
+        vec3 normal = normalize(cross(dFdx(world_pos), dFdy(world_pos)));
+        color.rgb *= 0.3 + 0.7 * abs(dot(normal, -light_dir));
+    }
+    frag_color = color;
+}";
+
+const SLICE_VERT: &str = "#version 330 core
+uniform mat4 model;
+uniform mat4 view_proj;
+uniform int axis;
+uniform float depth;
+
+out vec3 uvw;
+
+void main() {
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    if (axis == 0) {
+        uvw = vec3(depth, corner);
+    } else if (axis == 1) {
+        uvw = vec3(corner.x, depth, corner.y);
+    } else {
+        uvw = vec3(corner, depth);
+    }
+    gl_Position = view_proj * model * vec4(uvw - 0.5, 1.0);
+}";
+
+const SLICE_FRAG: &str = "#version 330 core
+in vec3 uvw;
+
+uniform sampler3D volume;
+uniform sampler2D transfer;
+uniform vec2 range;
+
+out vec4 frag_color;
+
+void main() {
+    float value = (texture(volume, uvw).r - range.x) / max(range.y - range.x, 1e-6);
+    vec4 color = texture(transfer, vec2(clamp(value, 0.0, 1.0), 0.5));
+    if (color.a <= 0.0) {
+        discard;
+    }
+    frag_color = color;
+}";
+
+/// How many colors a [TransferFunction] texture has
+const TRANSFER_SIZE: usize = 256;
+
+/// Turns a value from 0 to 1 into a color, used to color scalar data
+///
+/// It is a list of stops, the value of a stop and the color it has. Colors between stops
+/// are mixed linearly and the alpha can be used to hide values, e.g. the air of a CT scan
+///
+/// # Example
+/// ```
+/// let transfer = TransferFunction::new(vec![
+///     (0.0, [0.0, 0.0, 0.0, 0.0]), // below 0.3 is invisible
+///     (0.3, [0.8, 0.2, 0.1, 0.0]),
+///     (0.5, [0.9, 0.6, 0.4, 1.0]),
+///     (1.0, [1.0, 1.0, 1.0, 1.0]),
+/// ]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferFunction {
+    stops: Vec<(f32, [f32; 4])>,
+}
+
+impl TransferFunction {
+    /// Creates a transfer function from its stops, they are sorted by their value
+    pub fn new(mut stops: Vec<(f32, [f32; 4])>) -> Self {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        TransferFunction { stops }
+    }
+
+    /// Black at 0 to white at 1
+    pub fn grayscale() -> Self {
+        TransferFunction::new(vec![(0.0, [0.0, 0.0, 0.0, 1.0]), (1.0, [1.0; 4])])
+    }
+
+    /// Dark purple through blue and green to yellow, a map where every step looks as
+    /// big and that can be read by color blind people
+    pub fn viridis() -> Self {
+        TransferFunction::new(vec![
+            (0.0, [0.267, 0.005, 0.329, 1.0]),
+            (0.25, [0.229, 0.322, 0.546, 1.0]),
+            (0.5, [0.128, 0.567, 0.551, 1.0]),
+            (0.75, [0.369, 0.789, 0.383, 1.0]),
+            (1.0, [0.993, 0.906, 0.144, 1.0]),
+        ])
+    }
+
+    /// Blue through white to red, for values that go both ways from the middle
+    pub fn diverging() -> Self {
+        TransferFunction::new(vec![
+            (0.0, [0.23, 0.3, 0.75, 1.0]),
+            (0.5, [0.87, 0.87, 0.87, 1.0]),
+            (1.0, [0.71, 0.02, 0.15, 1.0]),
+        ])
+    }
+
+    /// Green lowlands through brown hills to white peaks, for terrain
+    pub fn terrain() -> Self {
+        TransferFunction::new(vec![
+            (0.0, [0.1, 0.3, 0.6, 1.0]),
+            (0.1, [0.2, 0.6, 0.3, 1.0]),
+            (0.5, [0.5, 0.4, 0.25, 1.0]),
+            (0.8, [0.5, 0.5, 0.5, 1.0]),
+            (1.0, [1.0; 4]),
+        ])
+    }
+
+    /// Gets the color of value, values outside of the stops get the color of the nearest
+    /// stop
+    pub fn sample(&self, value: f32) -> [f32; 4] {
+        let (Some(first), Some(last)) = (self.stops.first(), self.stops.last()) else {
+            return [0.0; 4];
+        };
+        if value <= first.0 {
+            return first.1;
+        }
+        if value >= last.0 {
+            return last.1;
+        }
+        // a NaN isn't between any of the stops
+        let Some(next) = self.stops.iter().position(|stop| stop.0 > value) else {
+            return last.1;
+        };
+        let ((a, from), (b, to)) = (self.stops[next - 1], self.stops[next]);
+        let t = (value - a) / (b - a);
+        std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t)
+    }
+
+    /// Uploads the colors into a 256 by 1 texture that shaders can read, it is left bound
+    pub fn to_texture(&self) -> Texture {
+        let colors: Vec<[f32; 4]> = (0..TRANSFER_SIZE)
+            .map(|i| self.sample(i as f32 / (TRANSFER_SIZE - 1) as f32))
+            .collect();
+        let mut texture = Texture::new();
+        texture.bind(TextureTarget::Texture2D);
+        clamped_linear().apply(&mut texture);
+        unsafe {
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_RGBA32F as i32,
+                TRANSFER_SIZE as i32,
+                1,
+                0,
+                GL_RGBA,
+                GL_FLOAT,
+                colors.as_ptr().cast(),
+            );
+        }
+        texture
+    }
+}
+
+/// Linear filtering without mipmaps that doesn't wrap, what scalar data is read with
+fn clamped_linear() -> TextureSettings {
+    TextureSettings::default()
+        .filter(TextureFilter::Linear, TextureFilter::Linear)
+        .wrap(TextureWrap::ClampToEdge)
+}
+
+/// Checks that there is one value for every cell of size
+fn check_len(len: usize, size: &[u32]) -> Result<(), TextureError> {
+    let cells = size
+        .iter()
+        .try_fold(1usize, |cells, &size| cells.checked_mul(size as usize))
+        .ok_or_else(|| TextureError::InvalidOptions(format!("A {:?} field is too big", size)))?;
+    if cells == 0 {
+        return Err(TextureError::EmptyImage);
+    }
+    if len != cells {
+        return Err(TextureError::InvalidOptions(format!(
+            "There are {} values but a {:?} field needs {}",
+            len, size, cells
+        )));
+    }
+    Ok(())
+}
+
+impl Texture {
+    /// Creates a single channel float texture from a 2d array of values, row by row with
+    /// the first row at a v of 0
+    ///
+    /// The values are kept as they are, a shader maps them to colors, see [Heightfield].
+    /// It is filtered linearly and left bound
+    pub fn from_scalars_2d(width: u32, height: u32, values: &[f32]) -> Result<Self, TextureError> {
+        check_len(values.len(), &[width, height])?;
+        let mut texture = Texture::new();
+        texture.bind(TextureTarget::Texture2D);
+        clamped_linear().apply(&mut texture);
+        unsafe {
+            // rows of 1 float aren't always 4 byte aligned
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                GL_R32F as i32,
+                width as i32,
+                height as i32,
+                0,
+                GL_RED,
+                GL_FLOAT,
+                values.as_ptr().cast(),
+            );
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 4);
+        }
+        Ok(texture)
+    }
+
+    /// Creates a single channel float 3d texture from a 3d array of values, x first then y
+    /// then z, see [Texture::from_scalars_2d] and [VolumeSlice]
+    pub fn from_scalars_3d(size: [u32; 3], values: &[f32]) -> Result<Self, TextureError> {
+        check_len(values.len(), &size)?;
+        let mut max = 0;
+        unsafe { glGetIntegerv(GL_MAX_3D_TEXTURE_SIZE, &mut max) };
+        if let Some(&too_big) = size.iter().find(|&&size| max > 0 && size > max as u32) {
+            return Err(TextureError::TooBig((too_big, too_big), max as u32));
+        }
+        let mut texture = Texture::new();
+        texture.bind(TextureTarget::Texture3D);
+        clamped_linear()
+            .wrap_r(TextureWrap::ClampToEdge)
+            .apply(&mut texture);
+        let [width, height, depth] = size.map(|size| size as i32);
+        unsafe {
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
+            glTexImage3D(
+                GL_TEXTURE_3D,
+                0,
+                GL_R32F as i32,
+                width,
+                height,
+                depth,
+                0,
+                GL_RED,
+                GL_FLOAT,
+                values.as_ptr().cast(),
+            );
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 4);
+        }
+        Ok(texture)
+    }
+}
+
+/// Draws a 2d scalar field as a color mapped surface, the height and color of every point
+/// is its value
+///
+/// The surface is a grid of vertices that read the field texture, it is 1 by 1 around the
+/// origin on the x and z axes and `height` tall, the model matrix moves and scales it
+///
+/// # Example
+/// ```
+/// let field = Texture::from_scalars_2d(width, height, &elevation)?;
+/// let transfer = TransferFunction::terrain().to_texture();
+/// let mut surface = Heightfield::new(256, 256)?.range(0.0, 4000.0).height(0.2);
+///
+/// 'main_loop: loop {
+///     surface.draw(&field, &transfer, &scaling(&vec3(10.0, 10.0, 10.0)), &(proj * view));
+/// }
+/// ```
+pub struct Heightfield {
+    /// The shader program of the surface
+    pub program: ShaderProgram,
+    /// The values that are mapped to 0 and 1 of the transfer function and height
+    pub range: (f32, f32),
+    /// How tall the surface is(in units, before the model matrix)
+    pub height: f32,
+    /// Whether the surface is lit by a light from light_dir, so its shape can be seen
+    pub shaded: bool,
+    /// The direction the light shines in
+    pub light_dir: Vec3,
+    vao: VertexArray,
+    vbo: Buffer,
+    indices: IndexBuffer,
+}
+
+impl Heightfield {
+    /// Creates a surface of columns by rows vertices, it doesn't have to be as big as the
+    /// field since the field is filtered
+    pub fn new(columns: u32, rows: u32) -> Result<Self, LighthouseError> {
+        let (columns, rows) = (columns.max(2), rows.max(2));
+        let uvs: Vec<[f32; 2]> = (0..rows)
+            .flat_map(|y| {
+                (0..columns).map(move |x| {
+                    [
+                        x as f32 / (columns - 1) as f32,
+                        y as f32 / (rows - 1) as f32,
+                    ]
+                })
+            })
+            .collect();
+        let indices: Vec<u32> = (0..rows - 1)
+            .flat_map(|y| (0..columns - 1).map(move |x| y * columns + x))
+            .flat_map(|i| [i, i + columns, i + 1, i + 1, i + columns, i + columns + 1])
+            .collect();
+
+        let vao =
+            VertexArray::new().ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?;
+        let vbo = Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?;
+        vao.bind();
+        vbo.bind(BufferType::Array);
+        buffer_data(
+            BufferType::Array,
+            bytemuck::cast_slice(&uvs),
+            BufferUsage::StaticDraw,
+        );
+        let indices = IndexBuffer::with_data(&indices, BufferUsage::StaticDraw)?;
+        unsafe {
+            let stride = size_of::<[f32; 2]>() as i32;
+            glVertexAttribPointer(0, 2, GL_FLOAT, GL_FALSE, stride, std::ptr::null());
+            glEnableVertexAttribArray(0);
+        }
+        VertexArray::clear_binding();
+
+        Ok(Heightfield {
+            program: ShaderProgram::from_vert_frag(HEIGHTFIELD_VERT, HEIGHTFIELD_FRAG)?,
+            range: (0.0, 1.0),
+            height: 0.25,
+            shaded: true,
+            light_dir: Vec3::new(-0.3, -1.0, -0.5).normalize(),
+            vao,
+            vbo,
+            indices,
+        })
+    }
+
+    /// Sets the values that are mapped to 0 and 1
+    pub fn range(mut self, min: f32, max: f32) -> Self {
+        self.range = (min, max);
+        self
+    }
+
+    /// Sets how tall the surface is
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets whether the surface is lit
+    pub fn shaded(mut self, shaded: bool) -> Self {
+        self.shaded = shaded;
+        self
+    }
+
+    /// Draws the field, see [Texture::from_scalars_2d] and [TransferFunction::to_texture]
+    ///
+    /// The field is bound to unit 0 and the transfer function to unit 1
+    pub fn draw(&self, field: &Texture, transfer: &Texture, model: &Mat4, view_proj: &Mat4) {
+        self.program.use_program();
+        let uniform = |name| Uniform::new(&self.program, name);
+        uniform("model").set_uniform_matrix(false, (*model).into());
+        uniform("view_proj").set_uniform_matrix(false, (*view_proj).into());
+        uniform("field").set_uniform_i(&[0]);
+        uniform("transfer").set_uniform_i(&[1]);
+        uniform("range").set_uniform_f(&[self.range.0, self.range.1]);
+        uniform("height").set_uniform_f(&[self.height]);
+        uniform("shaded").set_uniform_i(&[self.shaded as i32]);
+        uniform("light_dir").set_uniform_f(&[self.light_dir.x, self.light_dir.y, self.light_dir.z]);
+
+        self.vao.bind();
+        self.indices.bind();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+            glBindTexture(GL_TEXTURE_2D, field.id);
+            glActiveTexture(GL_TEXTURE1);
+            glBindTexture(GL_TEXTURE_2D, transfer.id);
+            glActiveTexture(GL_TEXTURE0);
+        }
+        draw_elements(Primitive::Triangles, &self.indices);
+        VertexArray::clear_binding();
+    }
+
+    /// Deletes the shader program and grid
+    pub fn delete(self) {
+        self.program.delete();
+        self.vao.delete();
+        self.vbo.delete();
+        self.indices.delete();
+    }
+}
+
+/// An axis of a volume, see [VolumeSlice]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceAxis {
+    /// The slice faces the x axis, it shows y and z
+    X,
+    /// The slice faces the y axis, it shows x and z
+    Y,
+    /// The slice faces the z axis, it shows x and y
+    Z,
+}
+
+/// Draws a slice through a 3d scalar field, like one picture of a CT or MRI scan
+///
+/// The volume is a 1 by 1 by 1 cube around the origin, the model matrix moves and scales
+/// it. The slice is a square through it facing axis at depth, from 0 at the negative side
+/// to 1 at the positive side. Values the transfer function gives no alpha are not drawn
+///
+/// # Example
+/// ```
+/// let volume = Texture::from_scalars_3d([128, 128, 64], &scan)?;
+/// let transfer = TransferFunction::grayscale().to_texture();
+/// let mut slice = VolumeSlice::new()?.range(0.0, 1000.0);
+///
+/// 'main_loop: loop {
+///     slice.depth = mouse_wheel;
+///     slice.draw(&volume, &transfer, &model, &(proj * view));
+/// }
+/// ```
+pub struct VolumeSlice {
+    /// The shader program of the slice
+    pub program: ShaderProgram,
+    /// The axis the slice faces
+    pub axis: SliceAxis,
+    /// How far along the axis the slice is, from 0 to 1
+    pub depth: f32,
+    /// The values that are mapped to 0 and 1 of the transfer function
+    pub range: (f32, f32),
+    vao: VertexArray,
+}
+
+impl VolumeSlice {
+    /// Creates a slice through the middle of the volume facing the z axis
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(VolumeSlice {
+            program: ShaderProgram::from_vert_frag(SLICE_VERT, SLICE_FRAG)?,
+            axis: SliceAxis::Z,
+            depth: 0.5,
+            range: (0.0, 1.0),
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+        })
+    }
+
+    /// Sets the axis the slice faces
+    pub fn axis(mut self, axis: SliceAxis) -> Self {
+        self.axis = axis;
+        self
+    }
+
+    /// Sets how far along the axis the slice is
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the values that are mapped to 0 and 1
+    pub fn range(mut self, min: f32, max: f32) -> Self {
+        self.range = (min, max);
+        self
+    }
+
+    /// Draws the slice, see [Texture::from_scalars_3d] and [TransferFunction::to_texture]
+    ///
+    /// The volume is bound to unit 0 and the transfer function to unit 1. Both sides of the
+    /// slice are drawn
+    pub fn draw(&self, volume: &Texture, transfer: &Texture, model: &Mat4, view_proj: &Mat4) {
+        self.program.use_program();
+        let uniform = |name| Uniform::new(&self.program, name);
+        uniform("model").set_uniform_matrix(false, (*model).into());
+        uniform("view_proj").set_uniform_matrix(false, (*view_proj).into());
+        uniform("volume").set_uniform_i(&[0]);
+        uniform("transfer").set_uniform_i(&[1]);
+        uniform("range").set_uniform_f(&[self.range.0, self.range.1]);
+        uniform("axis").set_uniform_i(&[self.axis as i32]);
+        uniform("depth").set_uniform_f(&[self.depth.clamp(0.0, 1.0)]);
+
+        self.vao.bind();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+            glBindTexture(GL_TEXTURE_3D, volume.id);
+            glActiveTexture(GL_TEXTURE1);
+            glBindTexture(GL_TEXTURE_2D, transfer.id);
+            glActiveTexture(GL_TEXTURE0);
+
+            let cull_face = glIsEnabled(GL_CULL_FACE) == GL_TRUE;
+            glDisable(GL_CULL_FACE);
+            glDrawArrays(GL_TRIANGLE_STRIP, 0, 4);
+            if cull_face {
+                glEnable(GL_CULL_FACE);
+            }
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Deletes the shader program
+    pub fn delete(self) {
+        self.program.delete();
+        self.vao.delete();
+    }
+}
@@ -1,5 +1,17 @@
-use super::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use super::{recovery::ContextId, *};
+
+/// The type of compute shaders, ogl33 only knows gl 3.3 so it is missing from it
+pub const GL_COMPUTE_SHADER: u32 = 0x91B9;
+
 /// Specifies the type of [Shader]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShaderType {
     /// Vertex shaders determine the position of geometry within the screen.
     Vertex = GL_VERTEX_SHADER as isize,
@@ -7,10 +19,17 @@ pub enum ShaderType {
     ///
     /// Also other values, but mostly color.
     Fragment = GL_FRAGMENT_SHADER as isize,
+    /// Geometry shaders run between the vertex and fragment shaders on whole primitives and
+    /// can emit new ones, e.g. for billboards or showing normals
+    Geometry = GL_GEOMETRY_SHADER as isize,
+    /// Compute shaders run outside of drawing, they need opengl 4.3
+    Compute = GL_COMPUTE_SHADER as isize,
 }
 
 /// An opengl [shader](https://www.khronos.org/opengl/wiki/Shader) That is of type [ShaderType]
-pub struct Shader(pub u32);
+///
+/// The shader is deleted when it is dropped, see [ContextId]
+pub struct Shader(pub u32, ContextId);
 impl Shader {
     /// Creates a new shader
     ///
@@ -18,7 +37,7 @@ impl Shader {
     pub fn new(ty: ShaderType) -> Option<Self> {
         let shader = unsafe { glCreateShader(ty as u32) };
         if shader != 0 {
-            Some(Self(shader))
+            Some(Self(shader, ContextId::current()))
         } else {
             None
         }
@@ -26,14 +45,8 @@ impl Shader {
 
     /// Sets the source code for the shader
     pub fn set_source(&self, src: &str) {
-        unsafe {
-            glShaderSource(
-                self.0,
-                1,
-                &to_glstr(src).cast(),
-                &(src.len().try_into().unwrap()),
-            )
-        }
+        let src = to_glstr(src);
+        unsafe { glShaderSource(self.0, 1, &src.ptr(), &src.len()) }
     }
 
     /// Compiles the shader
@@ -66,30 +79,40 @@ impl Shader {
         String::from_utf8_lossy(&v).into_owned()
     }
 
-    /// Marks the program for deletion
-    pub fn delete(&self) {
-        unsafe { glDeleteShader(self.0) }
-    }
+    /// Marks the shader for deletion now instead of when it is dropped
+    pub fn delete(self) {}
 
     /// Creates a new shader program from a string
-    pub fn from_source(ty: ShaderType, source: &str) -> Result<Self, String> {
-        let id = Self::new(ty).ok_or_else(|| "Unable to allocate new shader".to_string())?;
+    pub fn from_source(ty: ShaderType, source: &str) -> Result<Self, LighthouseError> {
+        let id = Self::new(ty)
+            .ok_or_else(|| LighthouseError::BufferAlloc(format!("a {:?} shader", ty)))?;
         id.set_source(source);
         id.compile();
         if id.compile_success() {
             Ok(id)
         } else {
-            let out = id.info_log();
+            let log = id.info_log();
             id.delete();
-            Err(out)
+            Err(LighthouseError::ShaderCompile {
+                ty,
+                file: None,
+                log,
+            })
         }
     }
 }
 
 /// A handle to a [Program
 /// Object](https://www.khronos.org/opengl/wiki/GLSL_Object#Program_objects)
-#[derive(Copy, Clone)]
-pub struct ShaderProgram(pub u32);
+///
+/// Cloning a program shares it, it is deleted when the last clone is dropped, see
+/// [ContextId]
+#[derive(Debug, Clone)]
+pub struct ShaderProgram(pub u32, #[allow(dead_code)] Arc<ProgramGuard>);
+
+/// Deletes a program when the last [ShaderProgram] that shares it is dropped
+#[derive(Debug)]
+struct ProgramGuard(u32, ContextId);
 impl ShaderProgram {
     /// Allocates a new program object.
     ///
@@ -99,7 +122,10 @@ impl ShaderProgram {
     pub fn new() -> Option<Self> {
         let prog = unsafe { glCreateProgram() };
         if prog != 0 {
-            Some(Self(prog))
+            Some(Self(
+                prog,
+                Arc::new(ProgramGuard(prog, ContextId::current())),
+            ))
         } else {
             None
         }
@@ -147,37 +173,304 @@ impl ShaderProgram {
         unsafe { glUseProgram(self.0) };
     }
 
-    /// Marks the program for deletion.
+    /// Marks the program for deletion now instead of when it is dropped.
     ///
     /// Note: This _does not_ immediately delete the program. If the program is
     /// currently in use it won't be deleted until it's not the active program.
     /// When a program is finally deleted and attached shaders are unattached.
-    pub fn delete(self) {
-        unsafe { glDeleteProgram(self.0) };
-    }
+    /// If it has clones it is only deleted when the last one is.
+    pub fn delete(self) {}
 
     /// Takes a vertex shader source string and a fragment shader source string
-    /// and either gets you a working program object or gets you an error message.
+    /// and either gets you a working program object or gets you a [LighthouseError].
     ///
     /// This is the preferred way to create a simple shader program in the common
     /// case. It's just less error prone than doing all the steps yourself.
-    pub fn from_vert_frag(vert: &str, frag: &str) -> Result<Self, String> {
-        let p = Self::new().ok_or_else(|| "Couldn't allocate a program".to_string())?;
-        let v = Shader::from_source(ShaderType::Vertex, vert)
-            .map_err(|e| format!("Vertex Compile Error: {}", e))?;
-        let f = Shader::from_source(ShaderType::Fragment, frag)
-            .map_err(|e| format!("Fragment Compile Error: {}", e))?;
+    pub fn from_vert_frag(vert: &str, frag: &str) -> Result<Self, LighthouseError> {
+        let p = Self::new().ok_or_else(|| LighthouseError::BufferAlloc("a program".to_string()))?;
+        let v = Shader::from_source(ShaderType::Vertex, vert)?;
+        let f = Shader::from_source(ShaderType::Fragment, frag)?;
+        p.attach_shader(&v);
+        p.attach_shader(&f);
+        p.link_program();
+        v.delete();
+        f.delete();
+        if p.link_success() {
+            Ok(p)
+        } else {
+            let log = p.info_log();
+            p.delete();
+            Err(LighthouseError::ProgramLink(log))
+        }
+    }
+
+    /// Like [ShaderProgram::from_vert_frag] but with a geometry shader between the vertex
+    /// and fragment shader
+    ///
+    /// # Example
+    /// ```
+    /// // turns every point into a quad that faces the camera
+    /// let billboards = ShaderProgram::from_vert_geom_frag(POINT_VERT, BILLBOARD_GEOM, SPRITE_FRAG)?;
+    /// ```
+    pub fn from_vert_geom_frag(
+        vert: &str,
+        geom: &str,
+        frag: &str,
+    ) -> Result<Self, LighthouseError> {
+        let p = Self::new().ok_or_else(|| LighthouseError::BufferAlloc("a program".to_string()))?;
+        let v = Shader::from_source(ShaderType::Vertex, vert)?;
+        let g = Shader::from_source(ShaderType::Geometry, geom)?;
+        let f = Shader::from_source(ShaderType::Fragment, frag)?;
         p.attach_shader(&v);
+        p.attach_shader(&g);
         p.attach_shader(&f);
         p.link_program();
         v.delete();
+        g.delete();
         f.delete();
         if p.link_success() {
             Ok(p)
         } else {
-            let out = format!("Program Link Error: {}", p.info_log());
+            let log = p.info_log();
             p.delete();
-            Err(out)
+            Err(LighthouseError::ProgramLink(log))
         }
     }
+
+    /// Makes a program out of just a vertex shader whose outputs named varyings are
+    /// captured with transform feedback, interleaved in the order they are given
+    pub fn from_vert_feedback(vert: &str, varyings: &[&str]) -> Result<Self, LighthouseError> {
+        let p = Self::new().ok_or_else(|| LighthouseError::BufferAlloc("a program".to_string()))?;
+        let v = Shader::from_source(ShaderType::Vertex, vert)?;
+        p.attach_shader(&v);
+
+        let names: Vec<CString> = varyings.iter().map(|name| to_cstr(name)).collect();
+        let pointers: Vec<*const i8> = names.iter().map(|name| name.as_ptr().cast()).collect();
+        unsafe {
+            glTransformFeedbackVaryings(
+                p.0,
+                pointers.len().try_into().unwrap(),
+                pointers.as_ptr(),
+                GL_INTERLEAVED_ATTRIBS,
+            )
+        };
+        p.link_program();
+        v.delete();
+        if p.link_success() {
+            Ok(p)
+        } else {
+            let log = p.info_log();
+            p.delete();
+            Err(LighthouseError::ProgramLink(log))
+        }
+    }
+
+    /// Makes a program out of a compute shader, needs opengl 4.3
+    pub fn from_compute(compute: &str) -> Result<Self, LighthouseError> {
+        let p = Self::new().ok_or_else(|| LighthouseError::BufferAlloc("a program".to_string()))?;
+        let c = Shader::from_source(ShaderType::Compute, compute)?;
+        p.attach_shader(&c);
+        p.link_program();
+        c.delete();
+        if p.link_success() {
+            Ok(p)
+        } else {
+            let log = p.info_log();
+            p.delete();
+            Err(LighthouseError::ProgramLink(log))
+        }
+    }
+}
+
+/// A shader file a [ShaderWatcher] watches
+struct WatchedSource {
+    ty: ShaderType,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        if self.1.is_current() {
+            unsafe { glDeleteShader(self.0) }
+        }
+    }
+}
+
+impl Drop for ProgramGuard {
+    fn drop(&mut self) {
+        if self.1.is_current() {
+            unsafe { glDeleteProgram(self.0) }
+        }
+    }
+}
+
+/// Reloads a [ShaderProgram] from its source files when they change on disk, so glsl can be
+/// changed while the game is running
+///
+/// The program is relinked in place, so its id stays the same and every copy of it gets the
+/// new shaders. When the new sources don't compile or link the old program keeps working and
+/// the error is given back instead
+///
+/// # Example
+/// ```
+/// let mut watcher = ShaderWatcher::from_files("shaders/vert.glsl", "shaders/frag.glsl")?;
+/// let program = watcher.program();
+///
+/// 'main_loop: loop {
+///     if let Some(Err(error)) = watcher.poll() {
+///         eprintln!("{}", error);
+///     }
+///     // draw with program
+/// }
+/// ```
+pub struct ShaderWatcher {
+    program: ShaderProgram,
+    sources: Vec<WatchedSource>,
+    error: Option<String>,
+}
+
+impl ShaderWatcher {
+    /// Creates a new watcher for program without any files, add them with
+    /// [ShaderWatcher::watch]
+    pub fn new(program: ShaderProgram) -> Self {
+        ShaderWatcher {
+            program,
+            sources: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Makes a program from a vertex and fragment shader file and watches both of them
+    pub fn from_files(
+        vert: impl AsRef<Path>,
+        frag: impl AsRef<Path>,
+    ) -> Result<Self, LighthouseError> {
+        let program = ShaderProgram::new()
+            .ok_or_else(|| LighthouseError::BufferAlloc("a program".to_string()))?;
+        let mut watcher = ShaderWatcher::new(program)
+            .watch(ShaderType::Vertex, vert)
+            .watch(ShaderType::Fragment, frag);
+        watcher.reload()?;
+        Ok(watcher)
+    }
+
+    /// Watches the file at path as the shader of type ty, it is only read on the next reload
+    pub fn watch(mut self, ty: ShaderType, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        self.sources.push(WatchedSource {
+            ty,
+            modified: modified(&path),
+            path,
+        });
+        self
+    }
+
+    /// Gets the program, it stays the same program after every reload
+    pub fn program(&self) -> ShaderProgram {
+        self.program.clone()
+    }
+
+    /// Gets the error of the last reload if it failed
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Reloads the program if a file changed since it was last checked, should be called
+    /// every frame
+    ///
+    /// Gives None if nothing changed, otherwise the result of [ShaderWatcher::reload]
+    pub fn poll(&mut self) -> Option<Result<(), LighthouseError>> {
+        let mut changed = false;
+        for source in &mut self.sources {
+            let modified = modified(&source.path);
+            if modified != source.modified {
+                source.modified = modified;
+                changed = true;
+            }
+        }
+        changed.then(|| self.reload())
+    }
+
+    /// Reads every file, compiles the shaders and relinks the program with them
+    ///
+    /// Nothing about the program changes if a file can't be read or a shader doesn't
+    /// compile or link
+    pub fn reload(&mut self) -> Result<(), LighthouseError> {
+        let result = self.try_reload();
+        self.error = result.as_ref().err().map(|error| error.to_string());
+        result
+    }
+
+    /// Compiles every file and relinks the program, the shaders are deleted either way
+    fn try_reload(&mut self) -> Result<(), LighthouseError> {
+        let mut shaders = Vec::new();
+        let compiled = self.sources.iter().try_for_each(|source| {
+            let src = fs::read_to_string(&source.path)
+                .map_err(|e| LighthouseError::Io(source.path.clone(), e))?;
+            let shader = Shader::from_source(source.ty, &src).map_err(|e| match e {
+                LighthouseError::ShaderCompile { ty, log, .. } => LighthouseError::ShaderCompile {
+                    ty,
+                    file: Some(source.path.clone()),
+                    log,
+                },
+                e => e,
+            })?;
+            shaders.push(shader);
+            Ok(())
+        });
+        compiled.and_then(|()| self.relink(&shaders))
+    }
+
+    /// Links the shaders into a scratch program first, so a link error never breaks the
+    /// program, then relinks the program with them
+    fn relink(&self, shaders: &[Shader]) -> Result<(), LighthouseError> {
+        let test = ShaderProgram::new()
+            .ok_or_else(|| LighthouseError::BufferAlloc("a program".to_string()))?;
+        for shader in shaders {
+            test.attach_shader(shader);
+        }
+        test.link_program();
+        let linked = test.link_success();
+        let log = test.info_log();
+        test.delete();
+        if !linked {
+            return Err(LighthouseError::ProgramLink(log));
+        }
+
+        self.detach_all();
+        for shader in shaders {
+            self.program.attach_shader(shader);
+        }
+        self.program.link_program();
+        self.detach_all();
+        if self.program.link_success() {
+            Ok(())
+        } else {
+            Err(LighthouseError::ProgramLink(self.program.info_log()))
+        }
+    }
+
+    /// Detaches every shader from the program, so only the new ones are linked
+    fn detach_all(&self) {
+        let mut count = 0;
+        unsafe { glGetProgramiv(self.program.0, GL_ATTACHED_SHADERS, &mut count) };
+        let mut attached = vec![0u32; count.max(0) as usize];
+        unsafe {
+            glGetAttachedShaders(
+                self.program.0,
+                count,
+                std::ptr::null_mut(),
+                attached.as_mut_ptr(),
+            );
+        }
+        for shader in attached {
+            unsafe { glDetachShader(self.program.0, shader) };
+        }
+    }
+}
+
+/// Gets when the file at path was last changed, None if it can't be read
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
 }
@@ -0,0 +1,279 @@
+use std::mem::size_of;
+
+use nalgebra_glm::{mat3_to_mat4, mat4_to_mat3, vec3, Mat4, Vec3};
+
+use super::{
+    buffer::*, shader::ShaderProgram, texture::Texture, uniform::Uniform, vertex::VertexArray, *,
+};
+
+const SKY_VERT: &str = "#version 330 core
+uniform mat4 inverse_view_proj;
+
+out vec3 view_dir;
+
+void main() {
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    vec4 far = inverse_view_proj * vec4(pos, 1.0, 1.0);
+    vec4 near = inverse_view_proj * vec4(pos, -1.0, 1.0);
+    view_dir = far.xyz / far.w - near.xyz / near.w;
+    // on the far plane, so the sky is behind everything
+    gl_Position = vec4(pos, 1.0, 1.0);
+}";
+
+const SKYBOX_VERT: &str = "#version 330 core
+layout (location = 0) in vec3 pos;
+
+uniform mat4 view_proj;
+
+out vec3 dir;
+
+void main() {
+    dir = pos;
+    // w as z puts it on the far plane, so the skybox is behind everything
+    gl_Position = (view_proj * vec4(pos, 1.0)).xyww;
+}";
+
+const SKYBOX_FRAG: &str = "#version 330 core
+in vec3 dir;
+
+uniform samplerCube cubemap;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = texture(cubemap, dir);
+}";
+
+const SKY_FRAG: &str = "#version 330 core
+in vec3 view_dir;
+
+uniform vec3 zenith_color;
+uniform vec3 horizon_color;
+uniform vec3 ground_color;
+uniform vec3 sun_direction;
+uniform vec3 sun_color;
+uniform float sun_size;
+
+out vec4 frag_color;
+
+void main() {
+    vec3 dir = normalize(view_dir);
+    vec3 color = dir.y > 0.0
+        ? mix(horizon_color, zenith_color, pow(dir.y, 0.5))
+        : mix(horizon_color, ground_color, pow(-dir.y, 0.3));
+
+    float sun = dot(dir, -sun_direction);
+    color += sun_color * smoothstep(cos(sun_size), cos(sun_size * 0.8), sun);
+    // glow around the sun
+    color += sun_color * pow(max(sun, 0.0), 64.0) * 0.3;
+    frag_color = vec4(color, 1.0);
+}";
+
+/// The colors of a [Sky]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkyParams {
+    /// The color straight up
+    pub zenith: [f32; 3],
+    /// The color at the horizon
+    pub horizon: [f32; 3],
+    /// The color below the horizon
+    pub ground: [f32; 3],
+    /// The direction the sun shines in, the sun is drawn in the opposite direction
+    pub sun_direction: Vec3,
+    /// The color of the sun disc, black hides it
+    pub sun_color: [f32; 3],
+    /// The radius of the sun disc(in radians)
+    pub sun_size: f32,
+}
+
+impl Default for SkyParams {
+    fn default() -> Self {
+        SkyParams {
+            zenith: [0.25, 0.45, 0.85],
+            horizon: [0.7, 0.8, 0.95],
+            ground: [0.3, 0.3, 0.3],
+            sun_direction: vec3(-0.3, -1.0, -0.2),
+            sun_color: [1.0, 0.95, 0.8],
+            sun_size: 0.03,
+        }
+    }
+}
+
+/// A procedural gradient sky with a sun, drawn behind everything
+///
+/// # Example
+/// ```
+/// let sky = Sky::new()?;
+///
+/// 'main_loop: loop {
+///     // draw the scene first so the sky is only drawn where nothing else is
+///     sky.draw(&SkyParams::default(), &view_proj);
+/// }
+/// ```
+pub struct Sky {
+    /// The shader program of the sky
+    pub program: ShaderProgram,
+    /// Empty VAO, opengl needs one bound to draw
+    vao: VertexArray,
+}
+
+impl Sky {
+    /// Creates a new sky
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(Sky {
+            program: ShaderProgram::from_vert_frag(SKY_VERT, SKY_FRAG)?,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+        })
+    }
+
+    /// Draws the sky with params, view_proj is the matrix of the camera
+    ///
+    /// The sky is on the far plane and doesnt write depth, so it can be drawn before or
+    /// after the scene
+    pub fn draw(&self, params: &SkyParams, view_proj: &Mat4) {
+        self.program.use_program();
+        let uniform = |name| Uniform::new(&self.program, name);
+        let inverse = view_proj.try_inverse().unwrap_or_default();
+        let sun = params.sun_direction.normalize();
+        uniform("inverse_view_proj").set_uniform_matrix(false, inverse.into());
+        uniform("zenith_color").set_uniform_f(&params.zenith);
+        uniform("horizon_color").set_uniform_f(&params.horizon);
+        uniform("ground_color").set_uniform_f(&params.ground);
+        uniform("sun_direction").set_uniform_f(&[sun.x, sun.y, sun.z]);
+        uniform("sun_color").set_uniform_f(&params.sun_color);
+        uniform("sun_size").set_uniform_f(&[params.sun_size]);
+
+        self.vao.bind();
+        unsafe {
+            let mut depth_func = 0;
+            let mut depth_mask = 0;
+            glGetIntegerv(GL_DEPTH_FUNC, &mut depth_func);
+            glGetIntegerv(GL_DEPTH_WRITEMASK, &mut depth_mask);
+            glDepthFunc(GL_LEQUAL);
+            glDepthMask(GL_FALSE);
+
+            glDrawArrays(GL_TRIANGLES, 0, 3);
+
+            glDepthFunc(depth_func as u32);
+            glDepthMask(depth_mask as u8);
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Deletes the shader program of the sky
+    pub fn delete(self) {
+        self.program.delete();
+    }
+}
+
+/// The corners of the 12 triangles of a cube from -1 to 1
+#[rustfmt::skip]
+const CUBE: [f32; 108] = [
+    -1.0,  1.0, -1.0,  -1.0, -1.0, -1.0,   1.0, -1.0, -1.0,
+     1.0, -1.0, -1.0,   1.0,  1.0, -1.0,  -1.0,  1.0, -1.0,
+    -1.0, -1.0,  1.0,  -1.0, -1.0, -1.0,  -1.0,  1.0, -1.0,
+    -1.0,  1.0, -1.0,  -1.0,  1.0,  1.0,  -1.0, -1.0,  1.0,
+     1.0, -1.0, -1.0,   1.0, -1.0,  1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,   1.0,  1.0, -1.0,   1.0, -1.0, -1.0,
+    -1.0, -1.0,  1.0,  -1.0,  1.0,  1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,   1.0, -1.0,  1.0,  -1.0, -1.0,  1.0,
+    -1.0,  1.0, -1.0,   1.0,  1.0, -1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,  -1.0,  1.0,  1.0,  -1.0,  1.0, -1.0,
+    -1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0, -1.0,
+     1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0,  1.0,
+];
+
+/// A sky made of a cubemap, see [Texture::cubemap_from_images], drawn behind everything
+///
+/// Only the rotation of the camera is used, so the sky never gets closer however far the
+/// camera moves
+///
+/// # Example
+/// ```
+/// let skybox = Skybox::new()?;
+/// let cubemap = Texture::cubemap_from_images(faces)?;
+///
+/// 'main_loop: loop {
+///     // draw the scene first so the skybox is only drawn where nothing else is
+///     skybox.draw(&cubemap, &view, &proj);
+/// }
+/// ```
+pub struct Skybox {
+    /// The shader program of the skybox
+    pub program: ShaderProgram,
+    vao: VertexArray,
+    vbo: Buffer,
+}
+
+impl Skybox {
+    /// Creates a new skybox
+    pub fn new() -> Result<Self, LighthouseError> {
+        let skybox = Skybox {
+            program: ShaderProgram::from_vert_frag(SKYBOX_VERT, SKYBOX_FRAG)?,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+            vbo: Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+        };
+
+        skybox.vao.bind();
+        skybox.vbo.bind(BufferType::Array);
+        buffer_data(
+            BufferType::Array,
+            bytemuck::cast_slice(&CUBE),
+            BufferUsage::StaticDraw,
+        );
+        unsafe {
+            let stride = size_of::<[f32; 3]>() as i32;
+            glVertexAttribPointer(0, 3, GL_FLOAT, GL_FALSE, stride, std::ptr::null());
+            glEnableVertexAttribArray(0);
+        }
+        VertexArray::clear_binding();
+
+        Ok(skybox)
+    }
+
+    /// Draws the cubemap around the camera, view and proj are the matrices of the camera
+    ///
+    /// The position is taken out of view. Like [Sky::draw] the skybox is on the far plane
+    /// and doesnt write depth, so it can be drawn before or after the scene
+    pub fn draw(&self, cubemap: &Texture, view: &Mat4, proj: &Mat4) {
+        let rotation = mat3_to_mat4(&mat4_to_mat3(view));
+        self.program.use_program();
+        Uniform::new(&self.program, "view_proj")
+            .set_uniform_matrix(false, (proj * rotation).into());
+        Uniform::new(&self.program, "cubemap").set_uniform_i(&[0]);
+
+        self.vao.bind();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+            glBindTexture(GL_TEXTURE_CUBE_MAP, cubemap.id);
+
+            let mut depth_func = 0;
+            let mut depth_mask = 0;
+            glGetIntegerv(GL_DEPTH_FUNC, &mut depth_func);
+            glGetIntegerv(GL_DEPTH_WRITEMASK, &mut depth_mask);
+            // the inside of the cube is seen, which may be the back faces
+            let cull_face = glIsEnabled(GL_CULL_FACE) == GL_TRUE;
+            glDepthFunc(GL_LEQUAL);
+            glDepthMask(GL_FALSE);
+            glDisable(GL_CULL_FACE);
+
+            glDrawArrays(GL_TRIANGLES, 0, 36);
+
+            glDepthFunc(depth_func as u32);
+            glDepthMask(depth_mask as u8);
+            if cull_face {
+                glEnable(GL_CULL_FACE);
+            }
+        }
+        VertexArray::clear_binding();
+    }
+
+    /// Deletes the shader program and cube of the skybox
+    pub fn delete(self) {
+        self.program.delete();
+        self.vao.delete();
+        self.vbo.delete();
+    }
+}
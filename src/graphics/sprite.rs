@@ -0,0 +1,444 @@
+use std::mem::size_of;
+
+use nalgebra_glm::{vec2, Mat4, Vec2};
+use specs::{Component, Join, VecStorage, WorldExt};
+
+use super::{
+    arena::with_frame_arena, buffer::*, recovery::ContextId, render_state::RenderState,
+    shader::ShaderProgram, texture::Texture, uniform::Uniform, vertex::VertexArray, *,
+};
+
+const SPRITE_VERT: &str = "#version 330 core
+layout (location = 0) in vec4 pos_size;
+layout (location = 1) in vec4 pivot_rotation;
+layout (location = 2) in vec4 uv_rect;
+layout (location = 3) in vec4 color;
+
+uniform mat4 view_proj;
+
+out vec2 uv;
+out vec4 tint;
+
+void main() {
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    vec2 local = (corner - pivot_rotation.xy) * pos_size.zw;
+    float s = sin(pivot_rotation.z);
+    float c = cos(pivot_rotation.z);
+    vec2 world = pos_size.xy + vec2(local.x * c - local.y * s, local.x * s + local.y * c);
+
+    uv = mix(uv_rect.xy, uv_rect.zw, corner);
+    tint = color;
+    gl_Position = view_proj * vec4(world, 0.0, 1.0);
+}";
+
+const SPRITE_FRAG: &str = "#version 330 core
+in vec2 uv;
+in vec4 tint;
+
+uniform sampler2D tex;
+uniform float alpha_cutoff;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = texture(tex, uv) * tint;
+    if (frag_color.a < alpha_cutoff) {
+        discard;
+    }
+}";
+
+/// A cheap handle to a [Texture] that also knows its size, so sprites can use pixel regions
+///
+/// It doesn't keep the texture alive, the [Texture] has to live as long as the handle is used,
+/// after it is dropped the id is deleted and may be reused by another texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureHandle {
+    /// The texture id
+    pub id: u32,
+    /// Size of the texture(in pixels)
+    pub size: Vec2,
+}
+
+impl TextureHandle {
+    /// Creates a new handle to texture, size is the size of its image(in pixels)
+    pub fn new(texture: &Texture, size: Vec2) -> Self {
+        TextureHandle {
+            id: texture.id,
+            size,
+        }
+    }
+}
+
+/// Position, rotation and scale of a 2d entity
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[storage(VecStorage)]
+pub struct Transform2D {
+    /// Position of the entity(in world units)
+    pub pos: Vec2,
+    /// Counter clockwise rotation around the pivot(in radians)
+    pub rotation: f32,
+    /// Scale of the entity, a negative scale also flips it
+    pub scale: Vec2,
+}
+
+impl Transform2D {
+    /// Creates a new transform at pos with no rotation and a scale of 1
+    pub fn new(pos: Vec2) -> Self {
+        Transform2D {
+            pos,
+            rotation: 0.0,
+            scale: vec2(1.0, 1.0),
+        }
+    }
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::new(vec2(0.0, 0.0))
+    }
+}
+
+/// A 2d image drawn by the [SpriteBatcher]
+///
+/// One pixel of the region is one world unit before the [Transform2D] scale is applied
+///
+/// # Example
+/// ```
+/// let player = world
+///     .ecs
+///     .create_entity()
+///     .with(Transform2D::new(vec2(100.0, 50.0)))
+///     .with(
+///         Sprite::new(sheet)
+///             .region([32.0, 0.0, 16.0, 16.0])
+///             .pivot(vec2(0.5, 0.0))
+///             .flip_x(facing_left),
+///     )
+///     .build();
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[storage(VecStorage)]
+pub struct Sprite {
+    /// The texture the sprite is cut out of
+    pub texture: TextureHandle,
+    /// The part of the texture that is drawn, x, y, width and height(in pixels from the top
+    /// left of the texture). None means the whole texture
+    pub region: Option<[f32; 4]>,
+    /// The point the sprite is placed and rotated around, (0, 0) is the bottom left and
+    /// (1, 1) is the top right of the sprite
+    pub pivot: Vec2,
+    /// The color the texture is multiplied by
+    pub tint: [f32; 4],
+    /// Whether the sprite is mirrored horizontally
+    pub flip_x: bool,
+    /// Whether the sprite is mirrored vertically
+    pub flip_y: bool,
+    /// Sprites with a higher layer are drawn on top
+    pub layer: i32,
+}
+
+impl Sprite {
+    /// Creates a new sprite showing all of texture, centered and untinted
+    pub fn new(texture: TextureHandle) -> Self {
+        Sprite {
+            texture,
+            region: None,
+            pivot: vec2(0.5, 0.5),
+            tint: [1.0; 4],
+            flip_x: false,
+            flip_y: false,
+            layer: 0,
+        }
+    }
+
+    /// Sets the region of the texture that is drawn
+    pub fn region(mut self, region: [f32; 4]) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Sets the pivot
+    pub fn pivot(mut self, pivot: Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    /// Sets the tint
+    pub fn tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    /// Sets whether the sprite is mirrored horizontally
+    pub fn flip_x(mut self, flip_x: bool) -> Self {
+        self.flip_x = flip_x;
+        self
+    }
+
+    /// Sets whether the sprite is mirrored vertically
+    pub fn flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Sets the layer
+    pub fn layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Gets the size of the drawn region(in pixels)
+    pub fn size(&self) -> Vec2 {
+        match self.region {
+            Some([_, _, width, height]) => vec2(width, height),
+            None => self.texture.size,
+        }
+    }
+
+    /// Gets the uv rectangle of the region, bottom left then top right, with the flips applied
+    pub fn uv_rect(&self) -> [f32; 4] {
+        let [x, y, width, height] =
+            self.region
+                .unwrap_or([0.0, 0.0, self.texture.size.x, self.texture.size.y]);
+        let size = self.texture.size;
+        // images are flipped when they are uploaded so v goes up from the bottom
+        let (mut left, mut right) = (x / size.x, (x + width) / size.x);
+        let (mut bottom, mut top) = (1.0 - (y + height) / size.y, 1.0 - y / size.y);
+        if self.flip_x {
+            std::mem::swap(&mut left, &mut right);
+        }
+        if self.flip_y {
+            std::mem::swap(&mut bottom, &mut top);
+        }
+        [left, bottom, right, top]
+    }
+}
+
+/// One sprite as it is uploaded for the instanced draw
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SpriteInstance {
+    /// Position then size
+    pos_size: [f32; 4],
+    /// Pivot, rotation and padding
+    pivot_rotation: [f32; 4],
+    uv: [f32; 4],
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for SpriteInstance {}
+unsafe impl bytemuck::Pod for SpriteInstance {}
+
+/// Draws sprites with as few instanced draw calls as possible
+///
+/// Sprites are collected every frame and sorted by layer and texture when flushed, every
+/// run of sprites that share a texture is a single draw call
+///
+/// With a [SpriteBatcher::pixel_grid] the corners of sprites are snapped to the grid and
+/// every texture is read with nearest filtering, for pixel art with a
+/// [Camera2D](crate::ECS::camera2d::Camera2D) that snaps to the same grid
+///
+/// # Example
+/// ```
+/// let mut sprites = SpriteBatcher::new()?;
+///
+/// 'main_loop: loop {
+///     sprites.push_world(&world.ecs);
+///     sprites.push(&cursor_sprite, &cursor_transform);
+///     sprites.flush(&view_proj);
+/// }
+/// ```
+pub struct SpriteBatcher {
+    /// The sprite shader program
+    pub program: ShaderProgram,
+    /// Pixels with an alpha below it are thrown away, e.g. so a fence doesn't have to be
+    /// sorted with the sprites behind it
+    pub alpha_cutoff: f32,
+    /// The size of a virtual pixel the sprites snap to(in world units), None doesn't snap
+    /// and uses the filtering of the textures
+    pub pixel_grid: Option<f32>,
+    /// The sprites pushed since the last flush, with their layer and texture
+    sprites: Vec<(i32, u32, SpriteInstance)>,
+    /// How many draw calls the last flush made
+    draw_calls: usize,
+    vao: VertexArray,
+    vbo: Buffer,
+    /// Sampler object with nearest filtering used while there is a pixel grid
+    nearest: u32,
+    context: ContextId,
+}
+
+impl SpriteBatcher {
+    /// Creates a new empty sprite batcher
+    pub fn new() -> Result<Self, LighthouseError> {
+        let program = ShaderProgram::from_vert_frag(SPRITE_VERT, SPRITE_FRAG)?;
+        let vao =
+            VertexArray::new().ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?;
+        let vbo = Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?;
+        // the sampler is made last, it would leak if something after it failed
+        let mut nearest = 0;
+        unsafe {
+            glGenSamplers(1, &mut nearest);
+            glSamplerParameteri(nearest, GL_TEXTURE_MIN_FILTER, GL_NEAREST as i32);
+            glSamplerParameteri(nearest, GL_TEXTURE_MAG_FILTER, GL_NEAREST as i32);
+        }
+        if nearest == 0 {
+            return Err(LighthouseError::BufferAlloc("a sampler".to_string()));
+        }
+
+        let batcher = SpriteBatcher {
+            program,
+            alpha_cutoff: 0.0,
+            pixel_grid: None,
+            sprites: Vec::new(),
+            draw_calls: 0,
+            vao,
+            vbo,
+            nearest,
+            context: ContextId::current(),
+        };
+
+        batcher.vao.bind();
+        batcher.vbo.bind(BufferType::Array);
+        for location in 0..4 {
+            unsafe {
+                glEnableVertexAttribArray(location);
+                glVertexAttribDivisor(location, 1);
+            }
+        }
+        VertexArray::clear_binding();
+
+        Ok(batcher)
+    }
+
+    /// Adds a sprite to be drawn on the next flush
+    pub fn push(&mut self, sprite: &Sprite, transform: &Transform2D) {
+        let size = sprite.size().component_mul(&transform.scale);
+        let mut pos = transform.pos;
+        if let Some(grid) = self.pixel_grid.filter(|grid| *grid > 0.0) {
+            // the bottom left corner is snapped, snapping the pivot would leave the corners
+            // between pixels
+            let corner = pos - sprite.pivot.component_mul(&size);
+            pos += (corner / grid).map(f32::round) * grid - corner;
+        }
+        self.sprites.push((
+            sprite.layer,
+            sprite.texture.id,
+            SpriteInstance {
+                pos_size: [pos.x, pos.y, size.x, size.y],
+                pivot_rotation: [sprite.pivot.x, sprite.pivot.y, transform.rotation, 0.0],
+                uv: sprite.uv_rect(),
+                color: sprite.tint,
+            },
+        ));
+    }
+
+    /// Adds every entity of ecs that has a [Sprite] and a [Transform2D]
+    pub fn push_world(&mut self, ecs: &specs::World) {
+        let sprites = ecs.read_storage::<Sprite>();
+        let transforms = ecs.read_storage::<Transform2D>();
+        for (sprite, transform) in (&sprites, &transforms).join() {
+            self.push(sprite, transform);
+        }
+    }
+
+    /// Gets how many draw calls the last [SpriteBatcher::flush] made
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    /// Draws every pushed sprite and empties the batcher
+    ///
+    /// view_proj turns world positions into clip space, e.g. the matrix of a 2d camera
+    pub fn flush(&mut self, view_proj: &Mat4) {
+        self.draw_calls = 0;
+        if self.sprites.is_empty() {
+            return;
+        }
+
+        // the sort is stable so sprites on the same layer and texture keep their order
+        self.sprites
+            .sort_by_key(|(layer, texture, _)| (*layer, *texture));
+
+        self.program.use_program();
+        Uniform::new(&self.program, "view_proj").set_uniform_matrix(false, (*view_proj).into());
+        Uniform::new(&self.program, "tex").set_uniform_i(&[0]);
+        Uniform::new(&self.program, "alpha_cutoff").set_uniform_f(&[self.alpha_cutoff]);
+
+        self.vao.bind();
+        self.vbo.bind(BufferType::Array);
+        with_frame_arena(|arena| {
+            let instances = arena.alloc_iter(self.sprites.iter().map(|(_, _, instance)| *instance));
+            buffer_data(
+                BufferType::Array,
+                bytemuck::cast_slice(instances),
+                BufferUsage::StreamDraw,
+            )
+        });
+
+        unsafe {
+            let blend = glIsEnabled(GL_BLEND) == GL_TRUE;
+            glEnable(GL_BLEND);
+            glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+            glActiveTexture(GL_TEXTURE0);
+            if self.pixel_grid.is_some() {
+                glBindSampler(0, self.nearest);
+            }
+
+            let stride = size_of::<SpriteInstance>();
+            let mut first = 0;
+            while first < self.sprites.len() {
+                let (layer, texture, _) = self.sprites[first];
+                let count = self.sprites[first..]
+                    .iter()
+                    .take_while(|(l, t, _)| *l == layer && *t == texture)
+                    .count();
+
+                for location in 0..4 {
+                    glVertexAttribPointer(
+                        location,
+                        4,
+                        GL_FLOAT,
+                        GL_FALSE,
+                        stride as i32,
+                        (first * stride + location as usize * size_of::<[f32; 4]>()) as *const _,
+                    );
+                }
+                glBindTexture(GL_TEXTURE_2D, texture);
+                if self.pixel_grid.is_some() {
+                    // the sampler replaces the wrapping of the texture too, so it is copied
+                    for wrap in [GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T] {
+                        let mut mode = 0;
+                        glGetTexParameteriv(GL_TEXTURE_2D, wrap, &mut mode);
+                        glSamplerParameteri(self.nearest, wrap, mode);
+                    }
+                }
+                glDrawArraysInstanced(GL_TRIANGLE_STRIP, 0, 4, count as i32);
+
+                self.draw_calls += 1;
+                first += count;
+            }
+
+            glBindSampler(0, 0);
+            if !blend {
+                glDisable(GL_BLEND);
+            }
+        }
+        // the blend function isn't put back how it was
+        RenderState::invalidate();
+        VertexArray::clear_binding();
+        self.sprites.clear();
+    }
+
+    /// Deletes the shader program and sampler of the batcher now instead of when it is
+    /// dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for SpriteBatcher {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe { glDeleteSamplers(1, &self.nearest) }
+        }
+    }
+}
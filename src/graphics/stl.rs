@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use nalgebra_glm::{vec2, vec3, Vec3};
+
+use super::model::{ModelError, ModelVertex, ObjModel};
+use crate::assets::{Asset, AssetError};
+
+/// The size of the header of a binary STL file(in bytes)
+const HEADER_SIZE: usize = 80;
+/// The size of a triangle in a binary STL file, 12 floats and a u16(in bytes)
+const TRIANGLE_SIZE: usize = 50;
+
+/// A model loaded from an STL file, ascii or binary
+///
+/// STL files are what most CAD programs and 3d scanners export. They are a list of
+/// triangles that don't share vertices, with a normal for every triangle and no uvs, so
+/// the vertices have to be welded with [StlModel::to_model] before the model is drawn
+///
+/// # Example
+/// ```
+/// let scan = StlModel::load("scans/bunny.stl")?;
+/// let mut model = scan.to_model(0.0001);
+/// model.smooth_normals(0.0001);
+/// let mut bunny = model.into_mesh(vec![3, 2, 3], |v| *v)?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StlModel {
+    /// The name after `solid` in an ascii file, binary files don't have one
+    pub name: Option<String>,
+    /// The triangles, every vertex has the normal of its triangle
+    pub triangles: Vec<[ModelVertex; 3]>,
+}
+
+impl StlModel {
+    /// Parses an STL file, whether it is ascii or binary is worked out from its size and
+    /// start
+    pub fn parse(bytes: &[u8]) -> Result<Self, ModelError> {
+        // binary files can start with solid too, but their size always fits the triangle
+        // count
+        let binary_size = bytes.get(HEADER_SIZE..HEADER_SIZE + 4).map(|count| {
+            HEADER_SIZE + 4 + u32::from_le_bytes(count.try_into().unwrap()) as usize * TRIANGLE_SIZE
+        });
+        let model = if binary_size == Some(bytes.len()) {
+            StlModel::parse_binary(bytes)
+        } else if bytes.starts_with(b"solid") {
+            StlModel::parse_ascii(&String::from_utf8_lossy(bytes))?
+        } else {
+            return Err(ModelError::Invalid(
+                "The file isn't an ascii STL file and its size doesn't fit a binary one"
+                    .to_string(),
+            ));
+        };
+        if model.triangles.is_empty() {
+            return Err(ModelError::Empty);
+        }
+        Ok(model)
+    }
+
+    /// Reads and parses the STL file at path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        StlModel::parse(&std::fs::read(path)?)
+    }
+
+    /// Welds the triangles into a model, see [ObjModel::weld]
+    ///
+    /// The normals are still the normals of the triangles, so only vertices of triangles
+    /// facing the same way are welded. Use [ObjModel::smooth_normals] to weld the rest and
+    /// smooth them
+    pub fn to_model(&self, epsilon: f32) -> ObjModel {
+        let mut model = ObjModel::from_triangles(&self.triangles, epsilon);
+        model.has_uvs = false;
+        model
+    }
+
+    fn parse_binary(bytes: &[u8]) -> Self {
+        let triangles = bytes[HEADER_SIZE + 4..]
+            .chunks_exact(TRIANGLE_SIZE)
+            .map(|data| {
+                // the normal and the 3 vertices, the u16 at the end is ignored
+                let float =
+                    |i: usize| f32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+                let vector = |i: usize| vec3(float(i * 3), float(i * 3 + 1), float(i * 3 + 2));
+                triangle(vector(0), [vector(1), vector(2), vector(3)])
+            })
+            .collect();
+        StlModel {
+            name: None,
+            triangles,
+        }
+    }
+
+    fn parse_ascii(src: &str) -> Result<Self, ModelError> {
+        let mut model = StlModel::default();
+        let mut normal = None;
+        let mut positions = Vec::with_capacity(3);
+
+        for (line, text) in src.lines().enumerate() {
+            let line = line + 1;
+            let mut parts = text.split_whitespace();
+            let Some(keyword) = parts.next() else {
+                continue;
+            };
+            match keyword {
+                "solid" => {
+                    let name = parts.collect::<Vec<_>>().join(" ");
+                    model.name = (!name.is_empty()).then_some(name);
+                }
+                "facet" => {
+                    if parts.next() != Some("normal") {
+                        return Err(ModelError::Syntax(
+                            line,
+                            "facet has to be followed by normal".to_string(),
+                        ));
+                    }
+                    normal = Some(parse_vector(line, parts)?);
+                    positions.clear();
+                }
+                "vertex" => positions.push(parse_vector(line, parts)?),
+                "endfacet" => {
+                    let (Some(normal), [a, b, c]) = (normal.take(), &positions[..]) else {
+                        return Err(ModelError::Syntax(
+                            line,
+                            "A facet needs a normal and 3 vertices".to_string(),
+                        ));
+                    };
+                    model.triangles.push(triangle(normal, [*a, *b, *c]));
+                }
+                // outer loop, endloop and endsolid don't hold anything
+                _ => {}
+            }
+        }
+        Ok(model)
+    }
+}
+
+impl Asset for StlModel {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        StlModel::parse(&bytes).map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+/// Parses the 3 numbers of a normal or vertex line
+fn parse_vector<'a>(line: usize, parts: impl Iterator<Item = &'a str>) -> Result<Vec3, ModelError> {
+    let numbers = parts
+        .map(|part| {
+            part.parse::<f32>()
+                .map_err(|_| ModelError::Syntax(line, format!("{} is not a number", part)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match numbers[..] {
+        [x, y, z] => Ok(vec3(x, y, z)),
+        _ => Err(ModelError::Syntax(
+            line,
+            "A vector needs 3 numbers".to_string(),
+        )),
+    }
+}
+
+/// Makes a triangle with the normal on every vertex, many exporters write a normal of 0
+/// so then it is worked out from the positions
+fn triangle(normal: Vec3, [a, b, c]: [Vec3; 3]) -> [ModelVertex; 3] {
+    let normal = if normal.norm_squared() > 0.0 {
+        normal.normalize()
+    } else {
+        let cross = (b - a).cross(&(c - a));
+        if cross.norm_squared() > 0.0 {
+            cross.normalize()
+        } else {
+            cross
+        }
+    };
+    [a, b, c].map(|pos| ModelVertex {
+        pos,
+        uv: vec2(0.0, 0.0),
+        normal,
+    })
+}
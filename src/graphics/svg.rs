@@ -0,0 +1,785 @@
+use std::f32::consts::TAU;
+
+use nalgebra_glm::{vec2, vec3, Mat3, Vec2};
+
+use super::{text_mesh::triangulate, *};
+use crate::assets::{xml::XmlElement, Asset, AssetError};
+
+/// How far the lines of curves can be from the curves when an [Svg] is loaded as an asset
+/// (in the units of the image)
+pub const DEFAULT_TOLERANCE: f32 = 0.1;
+
+/// A filled or stroked part of an [Svg], made of triangles with one color
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorShape {
+    /// The points of the triangles(in the units of the image, y goes down)
+    pub points: Vec<Vec2>,
+    /// The triangles, they point to points
+    pub triangles: Vec<[u32; 3]>,
+    /// The color of the shape
+    pub color: [f32; 4],
+}
+
+/// An SVG image turned into triangles, for icons and 2d art that stay sharp at any size
+///
+/// Paths, rects, circles, ellipses, lines, polylines and polygons are supported with their
+/// fill, stroke, stroke width, opacity and transform, also from the `style` attribute and
+/// inherited from groups. Gradients, patterns, text, `<use>`, clipping and masks aren't,
+/// shapes that use them are left out. Holes are made by the even odd rule even when the
+/// fill rule is nonzero, so contours that overlap come out wrong. Strokes have bevel joins
+/// and butt caps
+///
+/// # Example
+/// ```
+/// let icon: Svg = assets.load("ui/settings.svg")?;
+///
+/// 'main_loop: loop {
+///     draw.set_screen_space(world.env.win_size);
+///     draw.draw_svg(&icon, vec2(10.0, 10.0), vec2(32.0, 32.0));
+///     draw.flush();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Svg {
+    /// The part of the image that is shown, x, y, width and height(in the units of the image)
+    pub view_box: [f32; 4],
+    /// The size the image wants to be shown at(usually in pixels)
+    pub size: Vec2,
+    /// The shapes from back to front
+    pub shapes: Vec<VectorShape>,
+}
+
+impl Svg {
+    /// Parses the text of an SVG file, curves are split into lines that are at most
+    /// tolerance away from them(in the units of the image)
+    pub fn parse(src: &str, tolerance: f32) -> Result<Self, LighthouseError> {
+        let root = XmlElement::parse(src)?;
+        if root.name != "svg" {
+            return Err(LighthouseError::Parse(
+                "The root of an SVG file has to be <svg>".to_string(),
+            ));
+        }
+
+        let width = root.attr("width").and_then(parse_length);
+        let height = root.attr("height").and_then(parse_length);
+        let view_box = match root.attr("viewBox").map(parse_numbers) {
+            Some(numbers) if numbers.len() == 4 => [numbers[0], numbers[1], numbers[2], numbers[3]],
+            _ => [0.0, 0.0, width.unwrap_or(100.0), height.unwrap_or(100.0)],
+        };
+
+        let mut svg = Svg {
+            view_box,
+            size: vec2(width.unwrap_or(view_box[2]), height.unwrap_or(view_box[3])),
+            shapes: Vec::new(),
+        };
+        svg.add_element(&root, &Style::default(), tolerance.max(f32::EPSILON));
+        Ok(svg)
+    }
+
+    /// Adds the shapes of element and its children
+    fn add_element(&mut self, element: &XmlElement, parent: &Style, tolerance: f32) {
+        let style = parent.child(element);
+        let m = &style.transform;
+        let scale = (m[(0, 0)] * m[(1, 1)] - m[(0, 1)] * m[(1, 0)]).abs().sqrt();
+        // the shapes are flattened before they are transformed
+        let local_tolerance = tolerance / scale.max(f32::EPSILON);
+        let mut path = PathBuilder::new(local_tolerance);
+        let number = |name: &str| element.attr(name).and_then(parse_length).unwrap_or(0.0);
+
+        match element.name.as_str() {
+            "svg" | "g" => {
+                for child in &element.children {
+                    self.add_element(child, &style, tolerance);
+                }
+                return;
+            }
+            "path" => path.parse(element.attr("d").unwrap_or("")),
+            "rect" => {
+                let (x, y, width, height) =
+                    (number("x"), number("y"), number("width"), number("height"));
+                let (mut rx, mut ry) = (element.attr("rx"), element.attr("ry"));
+                // a missing radius is the same as the other one
+                rx = rx.or(ry);
+                ry = ry.or(rx);
+                let rx = rx.and_then(parse_length).unwrap_or(0.0).min(width / 2.0);
+                let ry = ry.and_then(parse_length).unwrap_or(0.0).min(height / 2.0);
+                path.move_to(vec2(x + rx, y));
+                path.line_to(vec2(x + width - rx, y));
+                path.arc(rx, ry, 0.0, false, true, vec2(x + width, y + ry));
+                path.line_to(vec2(x + width, y + height - ry));
+                path.arc(rx, ry, 0.0, false, true, vec2(x + width - rx, y + height));
+                path.line_to(vec2(x + rx, y + height));
+                path.arc(rx, ry, 0.0, false, true, vec2(x, y + height - ry));
+                path.line_to(vec2(x, y + ry));
+                path.arc(rx, ry, 0.0, false, true, vec2(x + rx, y));
+                path.close();
+            }
+            "circle" | "ellipse" => {
+                let (cx, cy) = (number("cx"), number("cy"));
+                let (rx, ry) = match element.name.as_str() {
+                    "circle" => (number("r"), number("r")),
+                    _ => (number("rx"), number("ry")),
+                };
+                path.move_to(vec2(cx + rx, cy));
+                path.arc(rx, ry, 0.0, false, true, vec2(cx - rx, cy));
+                path.arc(rx, ry, 0.0, false, true, vec2(cx + rx, cy));
+                path.close();
+            }
+            "line" => {
+                path.move_to(vec2(number("x1"), number("y1")));
+                path.line_to(vec2(number("x2"), number("y2")));
+            }
+            "polyline" | "polygon" => {
+                let numbers = parse_numbers(element.attr("points").unwrap_or(""));
+                for (i, point) in numbers.chunks_exact(2).enumerate() {
+                    match i {
+                        0 => path.move_to(vec2(point[0], point[1])),
+                        _ => path.line_to(vec2(point[0], point[1])),
+                    }
+                }
+                if element.name == "polygon" {
+                    path.close();
+                }
+            }
+            _ => return,
+        }
+
+        let transform = |point: &Vec2| (style.transform * vec3(point.x, point.y, 1.0)).xy();
+        let subpaths: Vec<(Vec<Vec2>, bool)> = path
+            .finish()
+            .into_iter()
+            .map(|(points, closed)| (points.iter().map(transform).collect(), closed))
+            .collect();
+
+        if let Some(color) = style.fill_color() {
+            let contours: Vec<Vec<Vec2>> = subpaths
+                .iter()
+                .map(|(points, _)| points.clone())
+                .filter(|points| points.len() > 2)
+                .collect();
+            let triangles = triangulate(&contours);
+            if !triangles.is_empty() {
+                self.shapes.push(VectorShape {
+                    points: contours.into_iter().flatten().collect(),
+                    triangles,
+                    color,
+                });
+            }
+        }
+        if let Some(color) = style.stroke_color() {
+            let mut shape = VectorShape {
+                points: Vec::new(),
+                triangles: Vec::new(),
+                color,
+            };
+            for (points, closed) in &subpaths {
+                stroke(
+                    points,
+                    *closed,
+                    style.stroke_width * scale / 2.0,
+                    &mut shape,
+                );
+            }
+            if !shape.triangles.is_empty() {
+                self.shapes.push(shape);
+            }
+        }
+    }
+}
+
+impl Asset for Svg {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        Svg::parse(&String::from_bytes(bytes)?, DEFAULT_TOLERANCE)
+            .map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+/// Adds a stroke of half_width along points to shape, as a quad for every segment and a
+/// triangle on both sides of every corner
+fn stroke(points: &[Vec2], closed: bool, half_width: f32, shape: &mut VectorShape) {
+    let count = points.len();
+    let segments = if closed {
+        count
+    } else {
+        count.saturating_sub(1)
+    };
+    let side = |i: usize| {
+        let dir = points[(i + 1) % count] - points[i];
+        vec2(-dir.y, dir.x)
+            .try_normalize(f32::EPSILON)
+            .map(|side| side * half_width)
+    };
+
+    let mut last_side: Option<Vec2> = None;
+    for i in 0..segments {
+        let (a, b) = (points[i], points[(i + 1) % count]);
+        let side = match side(i) {
+            Some(side) => side,
+            None => continue,
+        };
+        let first = shape.points.len() as u32;
+        shape
+            .points
+            .extend([a - side, a + side, b + side, b - side]);
+        shape.triangles.push([first, first + 1, first + 2]);
+        shape.triangles.push([first, first + 2, first + 3]);
+
+        // fill the gap on the outside of the corner, the inside is already covered
+        if let Some(last_side) = last_side {
+            let first = shape.points.len() as u32;
+            shape
+                .points
+                .extend([a, a + last_side, a + side, a - last_side, a - side]);
+            shape.triangles.push([first, first + 1, first + 2]);
+            shape.triangles.push([first, first + 3, first + 4]);
+        }
+        last_side = Some(side);
+    }
+
+    // join the end of a closed stroke to its start
+    if let (true, Some(last_side), Some(first_side)) = (closed, last_side, side(0)) {
+        let a = points[0];
+        let first = shape.points.len() as u32;
+        shape.points.extend([
+            a,
+            a + last_side,
+            a + first_side,
+            a - last_side,
+            a - first_side,
+        ]);
+        shape.triangles.push([first, first + 1, first + 2]);
+        shape.triangles.push([first, first + 3, first + 4]);
+    }
+}
+
+/// The style of an element, inherited by its children
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Style {
+    /// The fill color, None for `none` and paints that aren't supported
+    fill: Option<[f32; 3]>,
+    /// The stroke color, None for `none` and paints that aren't supported
+    stroke: Option<[f32; 3]>,
+    stroke_width: f32,
+    opacity: f32,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    /// From the units of the element to the units of the image
+    transform: Mat3,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fill: Some([0.0; 3]),
+            stroke: None,
+            stroke_width: 1.0,
+            opacity: 1.0,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            transform: Mat3::identity(),
+        }
+    }
+}
+
+impl Style {
+    /// Gets the style of element inside of an element with this style
+    fn child(&self, element: &XmlElement) -> Style {
+        let mut style = *self;
+        style.opacity = 1.0;
+        let declarations = element.attr("style").into_iter().flat_map(|css| {
+            css.split(';')
+                .filter_map(|declaration| declaration.split_once(':'))
+                .map(|(key, value)| (key.trim(), value.trim()))
+        });
+        // the style attribute wins over the presentation attributes
+        let attributes = element
+            .attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()));
+        for (key, value) in attributes.chain(declarations) {
+            match key {
+                "fill" => style.fill = parse_color(value).unwrap_or(style.fill),
+                "stroke" => style.stroke = parse_color(value).unwrap_or(style.stroke),
+                "stroke-width" => {
+                    style.stroke_width = parse_length(value).unwrap_or(style.stroke_width)
+                }
+                "opacity" => style.opacity = parse_length(value).unwrap_or(1.0),
+                "fill-opacity" => style.fill_opacity = parse_length(value).unwrap_or(1.0),
+                "stroke-opacity" => style.stroke_opacity = parse_length(value).unwrap_or(1.0),
+                "transform" => style.transform *= parse_transform(value),
+                _ => (),
+            }
+        }
+        // opacity isn't inherited but a group fades everything in it
+        style.opacity *= self.opacity;
+        style
+    }
+
+    fn fill_color(&self) -> Option<[f32; 4]> {
+        let [r, g, b] = self.fill?;
+        Some([r, g, b, self.fill_opacity * self.opacity])
+    }
+
+    fn stroke_color(&self) -> Option<[f32; 4]> {
+        let [r, g, b] = self.stroke?;
+        (self.stroke_width > 0.0).then_some([r, g, b, self.stroke_opacity * self.opacity])
+    }
+}
+
+/// Parses a paint, Some(None) is no paint and None isn't a paint that is understood
+fn parse_color(value: &str) -> Option<Option<[f32; 3]>> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |i: usize, digits: usize| {
+            let channel = u8::from_str_radix(hex.get(i * digits..(i + 1) * digits)?, 16).ok()?;
+            Some(match digits {
+                1 => channel as f32 / 15.0,
+                _ => channel as f32 / 255.0,
+            })
+        };
+        let digits = match hex.len() {
+            3 => 1,
+            6 => 2,
+            _ => return None,
+        };
+        return Some(Some([
+            channel(0, digits)?,
+            channel(1, digits)?,
+            channel(2, digits)?,
+        ]));
+    }
+    if let Some(channels) = value
+        .strip_prefix("rgb(")
+        .and_then(|rgb| rgb.strip_suffix(')'))
+    {
+        let channels: Vec<f32> = channels
+            .split(',')
+            .map(|channel| match channel.trim().strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f32>().map(|percent| percent / 100.0),
+                None => channel.trim().parse::<f32>().map(|channel| channel / 255.0),
+            })
+            .collect::<Result<_, _>>()
+            .ok()?;
+        return match channels[..] {
+            [r, g, b] => Some(Some([r, g, b].map(|channel| channel.clamp(0.0, 1.0)))),
+            _ => None,
+        };
+    }
+
+    let color = match value {
+        "none" | "transparent" => return Some(None),
+        // gradients and patterns aren't supported
+        _ if value.starts_with("url(") => return Some(None),
+        "black" | "currentColor" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "silver" => [192, 192, 192],
+        "maroon" => [128, 0, 0],
+        "olive" => [128, 128, 0],
+        "navy" => [0, 0, 128],
+        "purple" => [128, 0, 128],
+        "teal" => [0, 128, 128],
+        "orange" => [255, 165, 0],
+        _ => return None,
+    };
+    Some(Some(color.map(|channel| channel as f32 / 255.0)))
+}
+
+/// Parses a length like `12`, `12px` or `1.5e2`, other units are read as if they were px
+fn parse_length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+/// Parses a list of numbers separated by spaces or commas
+fn parse_numbers(value: &str) -> Vec<f32> {
+    let mut data = PathData::new(value);
+    std::iter::from_fn(|| data.number()).collect()
+}
+
+/// Parses a transform list like `translate(10 20) rotate(45)`
+fn parse_transform(value: &str) -> Mat3 {
+    let mut transform = Mat3::identity();
+    for function in value.split(')') {
+        let (name, args) = match function.split_once('(') {
+            Some((name, args)) => (
+                name.trim().trim_start_matches(',').trim(),
+                parse_numbers(args),
+            ),
+            None => continue,
+        };
+        let arg = |i: usize| args.get(i).copied();
+        let matrix = |a, b, c, d, e, f| Mat3::new(a, c, e, b, d, f, 0.0, 0.0, 1.0);
+        transform *= match name {
+            "matrix" if args.len() == 6 => {
+                matrix(args[0], args[1], args[2], args[3], args[4], args[5])
+            }
+            "translate" => matrix(
+                1.0,
+                0.0,
+                0.0,
+                1.0,
+                arg(0).unwrap_or(0.0),
+                arg(1).unwrap_or(0.0),
+            ),
+            "scale" => {
+                let x = arg(0).unwrap_or(1.0);
+                matrix(x, 0.0, 0.0, arg(1).unwrap_or(x), 0.0, 0.0)
+            }
+            "rotate" => {
+                let (sin, cos) = arg(0).unwrap_or(0.0).to_radians().sin_cos();
+                let (cx, cy) = (arg(1).unwrap_or(0.0), arg(2).unwrap_or(0.0));
+                matrix(1.0, 0.0, 0.0, 1.0, cx, cy)
+                    * matrix(cos, sin, -sin, cos, 0.0, 0.0)
+                    * matrix(1.0, 0.0, 0.0, 1.0, -cx, -cy)
+            }
+            "skewX" => matrix(
+                1.0,
+                0.0,
+                arg(0).unwrap_or(0.0).to_radians().tan(),
+                1.0,
+                0.0,
+                0.0,
+            ),
+            "skewY" => matrix(
+                1.0,
+                arg(0).unwrap_or(0.0).to_radians().tan(),
+                0.0,
+                1.0,
+                0.0,
+                0.0,
+            ),
+            _ => Mat3::identity(),
+        };
+    }
+    transform
+}
+
+/// Reads the numbers, flags and commands of path data
+struct PathData<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PathData<'a> {
+    fn new(data: &'a str) -> Self {
+        PathData {
+            bytes: data.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace() || *b == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Takes the next command letter, if the next thing is one
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let command = self
+            .bytes
+            .get(self.pos)
+            .filter(|b| b.is_ascii_alphabetic())?;
+        self.pos += 1;
+        Some(*command)
+    }
+
+    /// Checks if the next thing is a number
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+        self.bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.".contains(b))
+    }
+
+    /// Takes the next number, `1.5.5` is 1.5 and 0.5 and `1-2` is 1 and -2
+    fn number(&mut self) -> Option<f32> {
+        if !self.has_number() {
+            return None;
+        }
+        let start = self.pos;
+        let digits = |data: &mut Self| {
+            while data.bytes.get(data.pos).is_some_and(u8::is_ascii_digit) {
+                data.pos += 1;
+            }
+        };
+        if matches!(self.bytes[self.pos], b'+' | b'-') {
+            self.pos += 1;
+        }
+        digits(self);
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            digits(self);
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.bytes.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            digits(self);
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Takes the next flag of an arc, they can be written without separators
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.bytes.get(self.pos)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+
+    fn point(&mut self) -> Option<Vec2> {
+        Some(vec2(self.number()?, self.number()?))
+    }
+}
+
+/// Turns the commands of a path into lines
+struct PathBuilder {
+    tolerance: f32,
+    /// The finished subpaths and whether they are closed
+    subpaths: Vec<(Vec<Vec2>, bool)>,
+    /// The points of the current subpath
+    current: Vec<Vec2>,
+    pos: Vec2,
+    /// The last control point of a cubic or quadratic curve, for the smooth curves after
+    /// them
+    last_cubic: Option<Vec2>,
+    last_quadratic: Option<Vec2>,
+}
+
+impl PathBuilder {
+    fn new(tolerance: f32) -> Self {
+        PathBuilder {
+            tolerance,
+            subpaths: Vec::new(),
+            current: Vec::new(),
+            pos: vec2(0.0, 0.0),
+            last_cubic: None,
+            last_quadratic: None,
+        }
+    }
+
+    /// Follows the commands of path data, stops at the first error like browsers do
+    fn parse(&mut self, d: &str) {
+        let mut data = PathData::new(d);
+        let mut command = match data.command() {
+            Some(command) => command,
+            None => return,
+        };
+        loop {
+            let relative = command.is_ascii_lowercase();
+            let origin = if relative { self.pos } else { vec2(0.0, 0.0) };
+            let (last_cubic, last_quadratic) = (self.last_cubic.take(), self.last_quadratic.take());
+
+            let done = match command.to_ascii_uppercase() {
+                b'M' => data.point().map(|point| {
+                    self.move_to(origin + point);
+                    // more points after a move are lines
+                    command = if relative { b'l' } else { b'L' };
+                }),
+                b'L' => data.point().map(|point| self.line_to(origin + point)),
+                b'H' => data.number().map(|x| {
+                    let x = if relative { self.pos.x + x } else { x };
+                    self.line_to(vec2(x, self.pos.y))
+                }),
+                b'V' => data.number().map(|y| {
+                    let y = if relative { self.pos.y + y } else { y };
+                    self.line_to(vec2(self.pos.x, y))
+                }),
+                b'C' => (|| Some((data.point()?, data.point()?, data.point()?)))()
+                    .map(|(c1, c2, end)| self.cubic(origin + c1, origin + c2, origin + end)),
+                b'S' => (|| Some((data.point()?, data.point()?)))().map(|(c2, end)| {
+                    // the first control point is the last one mirrored
+                    let c1 = last_cubic.map_or(self.pos, |last| self.pos * 2.0 - last);
+                    self.cubic(c1, origin + c2, origin + end)
+                }),
+                b'Q' => (|| Some((data.point()?, data.point()?)))()
+                    .map(|(control, end)| self.quadratic(origin + control, origin + end)),
+                b'T' => data.point().map(|end| {
+                    let control = last_quadratic.map_or(self.pos, |last| self.pos * 2.0 - last);
+                    self.quadratic(control, origin + end)
+                }),
+                b'A' => (|| {
+                    Some((
+                        data.number()?,
+                        data.number()?,
+                        data.number()?,
+                        data.flag()?,
+                        data.flag()?,
+                        data.point()?,
+                    ))
+                })()
+                .map(|(rx, ry, angle, large, sweep, end)| {
+                    self.arc(rx, ry, angle, large, sweep, origin + end)
+                }),
+                b'Z' => {
+                    self.close();
+                    Some(())
+                }
+                _ => None,
+            };
+            if done.is_none() {
+                return;
+            }
+
+            // a command repeats while it is followed by numbers
+            if command.eq_ignore_ascii_case(&b'Z') || !data.has_number() {
+                command = match data.command() {
+                    Some(command) => command,
+                    None => return,
+                };
+            }
+        }
+    }
+
+    fn move_to(&mut self, point: Vec2) {
+        self.end_subpath(false);
+        self.current.push(point);
+        self.pos = point;
+    }
+
+    fn line_to(&mut self, point: Vec2) {
+        if self.current.is_empty() {
+            self.current.push(self.pos);
+        }
+        if self.current.last() != Some(&point) {
+            self.current.push(point);
+        }
+        self.pos = point;
+    }
+
+    fn quadratic(&mut self, control: Vec2, end: Vec2) {
+        let start = self.pos;
+        let bend = (start - control * 2.0 + end).norm();
+        let steps = ((bend / (4.0 * self.tolerance)).sqrt().ceil() as usize).clamp(1, 100);
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            self.line_to(
+                start * (1.0 - t) * (1.0 - t) + control * 2.0 * t * (1.0 - t) + end * t * t,
+            );
+        }
+        self.last_quadratic = Some(control);
+    }
+
+    fn cubic(&mut self, c1: Vec2, c2: Vec2, end: Vec2) {
+        let start = self.pos;
+        let bend = (start - c1 * 2.0 + c2)
+            .norm()
+            .max((c1 - c2 * 2.0 + end).norm());
+        let steps = ((0.75 * bend / self.tolerance).sqrt().ceil() as usize).clamp(1, 100);
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let u = 1.0 - t;
+            self.line_to(
+                start * u * u * u + c1 * 3.0 * u * u * t + c2 * 3.0 * u * t * t + end * t * t * t,
+            );
+        }
+        self.last_cubic = Some(c2);
+    }
+
+    /// Adds an elliptical arc, see the
+    /// [SVG spec](https://www.w3.org/TR/SVG11/implnote.html#ArcImplementationNotes)
+    fn arc(&mut self, rx: f32, ry: f32, angle: f32, large: bool, sweep: bool, end: Vec2) {
+        let start = self.pos;
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if start == end {
+            return;
+        }
+        if rx == 0.0 || ry == 0.0 {
+            return self.line_to(end);
+        }
+
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let half = (start - end) / 2.0;
+        let p = vec2(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+        // radii that are too small are scaled up until the arc fits
+        let lambda = (p.x * p.x) / (rx * rx) + (p.y * p.y) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let numerator = rx * rx * ry * ry - rx * rx * p.y * p.y - ry * ry * p.x * p.x;
+        let denominator = rx * rx * p.y * p.y + ry * ry * p.x * p.x;
+        let sign = if large == sweep { -1.0 } else { 1.0 };
+        let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+        let center_p = vec2(coefficient * rx * p.y / ry, -coefficient * ry * p.x / rx);
+        let middle = (start + end) / 2.0;
+        let center = vec2(
+            cos * center_p.x - sin * center_p.y + middle.x,
+            sin * center_p.x + cos * center_p.y + middle.y,
+        );
+
+        let angle_between = |u: Vec2, v: Vec2| (u.x * v.y - u.y * v.x).atan2(u.dot(&v));
+        let u = vec2((p.x - center_p.x) / rx, (p.y - center_p.y) / ry);
+        let v = vec2((-p.x - center_p.x) / rx, (-p.y - center_p.y) / ry);
+        let first = angle_between(vec2(1.0, 0.0), u);
+        let mut sweep_angle = angle_between(u, v);
+        if !sweep && sweep_angle > 0.0 {
+            sweep_angle -= TAU;
+        } else if sweep && sweep_angle < 0.0 {
+            sweep_angle += TAU;
+        }
+
+        // how big a step can be so the chord stays within the tolerance
+        let radius = rx.max(ry);
+        let step = 2.0 * (1.0 - (self.tolerance / radius).min(1.0)).acos();
+        let steps = ((sweep_angle.abs() / step.max(0.01)).ceil() as usize).clamp(1, 256);
+        for i in 1..steps {
+            let (sin_t, cos_t) = (first + sweep_angle * i as f32 / steps as f32).sin_cos();
+            self.line_to(vec2(
+                center.x + cos * rx * cos_t - sin * ry * sin_t,
+                center.y + sin * rx * cos_t + cos * ry * sin_t,
+            ));
+        }
+        // the end is exact so the next command starts at the right place
+        self.line_to(end);
+    }
+
+    fn close(&mut self) {
+        let start = self.current.first().copied();
+        self.end_subpath(true);
+        if let Some(start) = start {
+            self.pos = start;
+        }
+    }
+
+    fn end_subpath(&mut self, closed: bool) {
+        let mut points = std::mem::take(&mut self.current);
+        // a closed subpath that ends where it starts doesn't need the point twice
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() > 1 {
+            self.subpaths.push((points, closed));
+        }
+    }
+
+    fn finish(mut self) -> Vec<(Vec<Vec2>, bool)> {
+        self.end_subpath(false);
+        self.subpaths
+    }
+}
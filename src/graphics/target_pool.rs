@@ -0,0 +1,359 @@
+use std::{cell::RefCell, collections::HashMap, ops::Deref, rc::Rc};
+
+use super::{
+    framebuffer::{ColorFormat, Framebuffer},
+    LighthouseError,
+};
+
+/// What a pooled render target looks like, targets are only reused for the same description
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetDesc {
+    /// Width of the target(in pixels)
+    pub width: i32,
+    /// Height of the target(in pixels)
+    pub height: i32,
+    /// The format of the color texture
+    pub format: ColorFormat,
+    /// Whether the depth can be read by shaders, see [Framebuffer::with_depth_texture]
+    pub depth_texture: bool,
+    /// Makes the target the size of the window divided by this, it follows the window when
+    /// it is resized and width and height are ignored
+    pub scale_with_window: Option<u32>,
+}
+
+impl TargetDesc {
+    /// Creates a new description of an RGBA8 target of size width by height
+    pub fn new(width: i32, height: i32) -> Self {
+        TargetDesc {
+            width,
+            height,
+            format: ColorFormat::Rgba8,
+            depth_texture: false,
+            scale_with_window: None,
+        }
+    }
+
+    /// Creates a new description of an RGBA8 target that is the size of the window divided
+    /// by divisor, e.g. 2 for half of the window
+    pub fn window(divisor: u32) -> Self {
+        TargetDesc::new(0, 0).scale_with_window(divisor)
+    }
+
+    /// Sets the format of the color texture
+    pub fn format(mut self, format: ColorFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets whether the depth can be read by shaders
+    pub fn depth_texture(mut self, depth_texture: bool) -> Self {
+        self.depth_texture = depth_texture;
+        self
+    }
+
+    /// Makes the target the size of the window divided by divisor
+    pub fn scale_with_window(mut self, divisor: u32) -> Self {
+        self.scale_with_window = Some(divisor);
+        self
+    }
+
+    /// Gets the description with the size it has for a window of window_size, targets that
+    /// don't scale with the window stay the same
+    pub fn resolve(mut self, window_size: (i32, i32)) -> Self {
+        if let Some(divisor) = self.scale_with_window {
+            let divisor = divisor.max(1) as i32;
+            self.width = (window_size.0 / divisor).max(1);
+            self.height = (window_size.1 / divisor).max(1);
+        }
+        self
+    }
+}
+
+/// Render targets that are kept outside of a [TargetPool] but still follow the window and
+/// the context, see [TargetPool::follow]
+pub trait WindowTargets {
+    /// The window was resized to width by height(in pixels), targets that are the size of
+    /// the window are resized and the others stay as they are
+    fn window_resized(&mut self, width: i32, height: i32) -> Result<(), LighthouseError>;
+
+    /// The context was lost and the framebuffers are gone with it, they are made again
+    /// with the sizes they had
+    fn recreate_targets(&mut self) -> Result<(), LighthouseError>;
+}
+
+/// A render target borrowed from a [TargetPool], give it back with [TargetPool::release]
+pub struct PooledTarget {
+    desc: TargetDesc,
+    target: Framebuffer,
+}
+
+impl PooledTarget {
+    /// Gets what the target looks like
+    pub fn desc(&self) -> TargetDesc {
+        self.desc
+    }
+}
+
+impl Deref for PooledTarget {
+    type Target = Framebuffer;
+
+    fn deref(&self) -> &Framebuffer {
+        &self.target
+    }
+}
+
+/// Reuses render targets for temporary passes, so post processing chains don't make new
+/// framebuffers every frame
+///
+/// Targets that are given back stay in the pool, one that isn't used for
+/// [TargetPool::max_unused_frames] frames is deleted
+///
+/// Targets that [scale with the window](TargetDesc::scale_with_window) are resized by the
+/// pool when the window is, the [Renderer](super::renderer::Renderer) of the
+/// [World](crate::ECS::world::World) has a pool that is told about every resize. Targets
+/// that live longer than a frame, like a shadow map or the history of an effect, can be
+/// kept in the pool by name with [TargetPool::add_target]
+///
+/// Targets the game keeps itself, like a [PostProcessStack](super::post_process::PostProcessStack)
+/// or a [TemporalHistory](super::temporal::TemporalHistory), follow the window when they are
+/// given to [TargetPool::follow]. When the context is lost [TargetPool::recreate] makes
+/// every target again, the world does it in
+/// [World::restore_context](crate::ECS::world::World::restore_context)
+///
+/// # Example
+/// ```
+/// let mut pool = TargetPool::new();
+/// pool.resize(800, 600);
+/// pool.add_target("scene", TargetDesc::window(1).format(ColorFormat::Rgba16F))?;
+///
+/// 'main_loop: loop {
+///     pool.target("scene")?.bind();
+///     // draw the scene
+///
+///     let half = pool.acquire(TargetDesc::window(2))?;
+///     half.bind();
+///     bloom_down.draw(&pool.target("scene")?.color);
+///     // use half.color
+///     pool.release(half);
+///
+///     pool.end_frame();
+/// }
+///
+/// let post = Rc::new(RefCell::new(PostProcessStack::new(800, 600)?));
+/// pool.follow("post", post.clone())?;
+/// ```
+pub struct TargetPool {
+    /// How many frames a target can go unused before it is deleted
+    pub max_unused_frames: u64,
+    free: HashMap<TargetDesc, Vec<(Framebuffer, u64)>>,
+    /// The targets added with [TargetPool::add_target], with the description they were
+    /// added with
+    named: HashMap<String, (TargetDesc, Framebuffer)>,
+    /// The targets given to [TargetPool::follow]
+    followers: Vec<(String, Rc<RefCell<dyn WindowTargets>>)>,
+    window_size: (i32, i32),
+    frame: u64,
+    in_use: usize,
+}
+
+impl TargetPool {
+    /// Creates a new empty pool
+    pub fn new() -> Self {
+        TargetPool {
+            max_unused_frames: 3,
+            free: HashMap::new(),
+            named: HashMap::new(),
+            followers: Vec::new(),
+            window_size: (1, 1),
+            frame: 0,
+            in_use: 0,
+        }
+    }
+
+    /// Gets a target that looks like desc, reusing a free one if there is one
+    ///
+    /// The content of a reused target is whatever was drawn into it last
+    pub fn acquire(&mut self, desc: TargetDesc) -> Result<PooledTarget, LighthouseError> {
+        let desc = desc.resolve(self.window_size);
+        let target = match self.free.get_mut(&desc).and_then(|free| free.pop()) {
+            Some((target, _)) => target,
+            None => {
+                Framebuffer::with_format(desc.width, desc.height, desc.format, desc.depth_texture)?
+            }
+        };
+        self.in_use += 1;
+        Ok(PooledTarget { desc, target })
+    }
+
+    /// Gives a target back so it can be reused
+    ///
+    /// A target that scales with the window and was borrowed before the window was resized
+    /// is deleted instead
+    pub fn release(&mut self, target: PooledTarget) {
+        self.in_use = self.in_use.saturating_sub(1);
+        if target.desc.resolve(self.window_size) != target.desc {
+            target.target.delete();
+            return;
+        }
+        self.free
+            .entry(target.desc)
+            .or_default()
+            .push((target.target, self.frame));
+    }
+
+    /// Ends the frame and deletes the targets that weren't used for too long
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+        let oldest = self.frame.saturating_sub(self.max_unused_frames);
+        for free in self.free.values_mut() {
+            let (keep, old) = std::mem::take(free)
+                .into_iter()
+                .partition(|(_, last_used)| *last_used >= oldest);
+            *free = keep;
+            for (target, _) in old {
+                target.delete();
+            }
+        }
+        self.free.retain(|_, free| !free.is_empty());
+    }
+
+    /// Gets how many targets are borrowed right now
+    pub fn in_use(&self) -> usize {
+        self.in_use
+    }
+
+    /// Gets how many targets are waiting in the pool
+    pub fn free(&self) -> usize {
+        self.free.values().map(Vec::len).sum()
+    }
+
+    /// Tells the pool the window has a new size(in pixels), gives the name and error of
+    /// every followed target that couldn't be resized
+    ///
+    /// The free targets that scale with the window are deleted, the named ones are resized
+    /// the next time they are gotten with [TargetPool::target] and the followed ones are
+    /// resized now
+    pub fn resize(&mut self, width: i32, height: i32) -> Vec<(String, LighthouseError)> {
+        if self.window_size == (width, height) {
+            return Vec::new();
+        }
+        self.window_size = (width, height);
+        self.free.retain(|desc, free| {
+            let keep = desc.scale_with_window.is_none();
+            if !keep {
+                free.drain(..).for_each(|(target, _)| target.delete());
+            }
+            keep
+        });
+        self.followers
+            .iter()
+            .filter_map(|(name, targets)| {
+                let result = targets.borrow_mut().window_resized(width, height);
+                result.err().map(|error| (name.clone(), error))
+            })
+            .collect()
+    }
+
+    /// Makes every target again after the context was lost, gives the name and error of
+    /// every named or followed target that couldn't be made
+    ///
+    /// The free targets are forgotten, their framebuffers were gone with the context
+    pub fn recreate(&mut self) -> Vec<(String, LighthouseError)> {
+        self.free.clear();
+        let named = self.named.iter_mut().filter_map(|(name, (_, target))| {
+            target.recreate().err().map(|error| (name.clone(), error))
+        });
+        let followers = self.followers.iter().filter_map(|(name, targets)| {
+            let result = targets.borrow_mut().recreate_targets();
+            result.err().map(|error| (name.clone(), error))
+        });
+        named.chain(followers).collect()
+    }
+
+    /// Makes targets called name follow the window, they are resized to the size the pool
+    /// knows about now and when the window is resized, see [WindowTargets]
+    ///
+    /// Targets that already have the name stop following
+    pub fn follow(
+        &mut self,
+        name: &str,
+        targets: Rc<RefCell<dyn WindowTargets>>,
+    ) -> Result<(), LighthouseError> {
+        let (width, height) = self.window_size;
+        targets.borrow_mut().window_resized(width, height)?;
+        self.unfollow(name);
+        self.followers.push((name.to_string(), targets));
+        Ok(())
+    }
+
+    /// Makes the targets called name stop following the window
+    pub fn unfollow(&mut self, name: &str) {
+        self.followers.retain(|(other, _)| other != name);
+    }
+
+    /// Gets the size of the window the pool knows about(in pixels)
+    pub fn window_size(&self) -> (i32, i32) {
+        self.window_size
+    }
+
+    /// Adds a target that stays in the pool until it is removed, a target that already has
+    /// the name is replaced
+    pub fn add_target(&mut self, name: &str, desc: TargetDesc) -> Result<(), LighthouseError> {
+        let resolved = desc.resolve(self.window_size);
+        let target = Framebuffer::with_format(
+            resolved.width,
+            resolved.height,
+            resolved.format,
+            resolved.depth_texture,
+        )?;
+        if let Some((_, old)) = self.named.insert(name.to_string(), (desc, target)) {
+            old.delete();
+        }
+        Ok(())
+    }
+
+    /// Gets the target called name, resizing it first if the window was resized since it
+    /// was last gotten
+    ///
+    /// The content of a resized target is lost
+    pub fn target(&mut self, name: &str) -> Result<&Framebuffer, LighthouseError> {
+        let window_size = self.window_size;
+        let (desc, target) = self.named.get_mut(name).ok_or_else(|| {
+            LighthouseError::InvalidInput(format!("The pool has no target called {}", name))
+        })?;
+        let resolved = desc.resolve(window_size);
+        if (target.width, target.height) != (resolved.width, resolved.height) {
+            target.resize(resolved.width, resolved.height)?;
+        }
+        Ok(target)
+    }
+
+    /// Deletes the target called name
+    pub fn remove_target(&mut self, name: &str) {
+        if let Some((_, target)) = self.named.remove(name) {
+            target.delete();
+        }
+    }
+
+    /// Deletes every free target
+    pub fn clear(&mut self) {
+        for (target, _) in self.free.drain().flat_map(|(_, free)| free) {
+            target.delete();
+        }
+    }
+
+    /// Deletes every free and named target, borrowed targets have to be deleted by whoever
+    /// has them
+    pub fn delete(mut self) {
+        self.clear();
+        for (_, (_, target)) in self.named.drain() {
+            target.delete();
+        }
+    }
+}
+
+impl Default for TargetPool {
+    fn default() -> Self {
+        TargetPool::new()
+    }
+}
@@ -0,0 +1,235 @@
+use nalgebra_glm::{vec2, Mat4, Vec2};
+
+use super::{framebuffer::Framebuffer, target_pool::WindowTargets, LighthouseError};
+
+/// Gets the index-th number of the halton sequence of base, evenly spread numbers from 0 to 1
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Moves the projection a bit less than a pixel every frame, so effects that look at
+/// several frames see between the pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jitter {
+    /// Whether the projection is moved at all
+    pub enabled: bool,
+    /// How many frames before the offsets repeat
+    pub sequence_length: u32,
+    /// How far the projection is moved, 1 moves it up to half a pixel every way
+    pub scale: f32,
+    index: u32,
+    offset: Vec2,
+}
+
+impl Jitter {
+    /// Creates a new jitter that repeats every 8 frames
+    pub fn new() -> Self {
+        Jitter {
+            enabled: true,
+            sequence_length: 8,
+            scale: 1.0,
+            index: 0,
+            offset: vec2(0.0, 0.0),
+        }
+    }
+
+    /// Sets how many frames before the offsets repeat
+    pub fn sequence_length(mut self, sequence_length: u32) -> Self {
+        self.sequence_length = sequence_length.max(1);
+        self
+    }
+
+    /// Sets how far the projection is moved
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Moves on to the offset of the next frame
+    pub fn advance(&mut self) {
+        if !self.enabled {
+            self.offset = vec2(0.0, 0.0);
+            return;
+        }
+        self.index = self.index % self.sequence_length.max(1) + 1;
+        self.offset = vec2(halton(self.index, 2) - 0.5, halton(self.index, 3) - 0.5) * self.scale;
+    }
+
+    /// Gets the offset of this frame(in pixels), from -0.5 to 0.5
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+
+    /// Moves proj by the offset of this frame, width and height are the size of the
+    /// target(in pixels)
+    pub fn apply(&self, proj: &Mat4, width: i32, height: i32) -> Mat4 {
+        let ndc = vec2(
+            self.offset.x * 2.0 / width.max(1) as f32,
+            self.offset.y * 2.0 / height.max(1) as f32,
+        );
+        // moves clip space x and y by the offset times w, so it works for any projection
+        let mut jittered = *proj;
+        for col in 0..4 {
+            jittered[(0, col)] += ndc.x * proj[(3, col)];
+            jittered[(1, col)] += ndc.y * proj[(3, col)];
+        }
+        jittered
+    }
+}
+
+impl Default for Jitter {
+    fn default() -> Self {
+        Jitter::new()
+    }
+}
+
+/// The render targets and matrices of the last frames of a camera, what temporal effects
+/// like anti aliasing and upscaling are built on
+///
+/// Every camera that uses temporal effects keeps its own history. Each frame the scene is
+/// drawn with the jittered matrix from [TemporalHistory::begin], the effect reads
+/// [TemporalHistory::history] and writes into [TemporalHistory::current], which becomes
+/// the history of the next frame
+///
+/// # Example
+/// ```
+/// let mut history = TemporalHistory::new(800, 600)?;
+///
+/// 'main_loop: loop {
+///     let view_proj = history.begin(&proj, &view);
+///     // draw the scene into scene_target with view_proj
+///     history.current().bind();
+///     match history.history() {
+///         Some(previous) => taa.draw(&scene_target.color, &previous.color),
+///         None => copy.draw(&scene_target.color),
+///     }
+///     Framebuffer::unbind();
+/// }
+/// ```
+pub struct TemporalHistory {
+    /// The jitter of the projection
+    pub jitter: Jitter,
+    targets: [Framebuffer; 2],
+    current: usize,
+    valid: bool,
+    started: bool,
+    view_proj: Mat4,
+    prev_view_proj: Mat4,
+    frame: u64,
+}
+
+impl TemporalHistory {
+    /// Creates a new history with targets of size width by height
+    pub fn new(width: i32, height: i32) -> Result<Self, LighthouseError> {
+        Ok(TemporalHistory {
+            jitter: Jitter::new(),
+            targets: [
+                Framebuffer::new(width, height)?,
+                Framebuffer::new(width, height)?,
+            ],
+            current: 0,
+            valid: false,
+            started: false,
+            view_proj: Mat4::identity(),
+            prev_view_proj: Mat4::identity(),
+            frame: 0,
+        })
+    }
+
+    /// Starts a new frame and gets the jittered matrix to draw the scene with
+    ///
+    /// The targets are swapped, so what was written last frame is now the history
+    pub fn begin(&mut self, proj: &Mat4, view: &Mat4) -> Mat4 {
+        if self.started {
+            self.current = 1 - self.current;
+            self.valid = true;
+            self.frame += 1;
+        }
+        self.started = true;
+
+        self.jitter.advance();
+        self.prev_view_proj = if self.valid {
+            self.view_proj
+        } else {
+            proj * view
+        };
+        self.view_proj = proj * view;
+        let (width, height) = self.size();
+        self.jitter.apply(proj, width, height) * view
+    }
+
+    /// Gets the target this frame is written into
+    pub fn current(&self) -> &Framebuffer {
+        &self.targets[self.current]
+    }
+
+    /// Gets what was written last frame, None on the first frame and after a reset
+    pub fn history(&self) -> Option<&Framebuffer> {
+        self.valid.then(|| &self.targets[1 - self.current])
+    }
+
+    /// Gets the matrix of this frame without the jitter
+    pub fn view_proj(&self) -> Mat4 {
+        self.view_proj
+    }
+
+    /// Gets the matrix of the last frame without the jitter, e.g. to reproject the history
+    pub fn prev_view_proj(&self) -> Mat4 {
+        self.prev_view_proj
+    }
+
+    /// Gets how many frames the history has, it goes back to 0 on a reset
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Gets the size of the targets(in pixels)
+    pub fn size(&self) -> (i32, i32) {
+        let target = &self.targets[0];
+        (target.width, target.height)
+    }
+
+    /// Throws the history away, e.g. when the camera cuts to somewhere else
+    pub fn reset(&mut self) {
+        self.valid = false;
+        self.started = false;
+        self.frame = 0;
+    }
+
+    /// Resizes the targets, the history is thrown away
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        for target in &mut self.targets {
+            target.resize(width, height)?;
+        }
+        self.reset();
+        Ok(())
+    }
+
+    /// Deletes the targets
+    pub fn delete(self) {
+        for target in self.targets {
+            target.delete();
+        }
+    }
+}
+
+impl WindowTargets for TemporalHistory {
+    fn window_resized(&mut self, width: i32, height: i32) -> Result<(), LighthouseError> {
+        self.resize(width, height)
+    }
+
+    fn recreate_targets(&mut self) -> Result<(), LighthouseError> {
+        for target in &mut self.targets {
+            target.recreate()?;
+        }
+        self.reset();
+        Ok(())
+    }
+}
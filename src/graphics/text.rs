@@ -0,0 +1,679 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use image::{Rgba, RgbaImage};
+use nalgebra_glm::{vec2, Vec2};
+
+use super::{
+    texture::*,
+    ttf::{rasterize, TrueType},
+    ui::QuadInstance,
+    *,
+};
+
+/// The empty pixels around every glyph baked by [Font::from_ttf], so linear filtering
+/// doesn't bleed neighbours into each other
+const GLYPH_PADDING: u32 = 1;
+
+/// A glyph in the atlas of a [Font]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    /// The uv rectangle of the glyph in the atlas, top left then bottom right
+    pub uv: [f32; 4],
+    /// The size of the glyph(in pixels)
+    pub size: Vec2,
+    /// Where the top left of the glyph is relative to the pen(in pixels)
+    pub offset: Vec2,
+    /// How much the pen moves after the glyph(in pixels)
+    pub advance: f32,
+}
+
+/// A string that has been turned into glyph quads, see [Font::shape]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphRun {
+    /// The quads of the glyphs, relative to the top left of the run
+    pub quads: Vec<QuadInstance>,
+    /// The size of the whole run(in pixels)
+    pub size: Vec2,
+}
+
+impl GlyphRun {
+    /// Gets the quads moved to pos and tinted with color
+    pub fn placed(&self, pos: Vec2, color: [f32; 4]) -> impl Iterator<Item = QuadInstance> + '_ {
+        self.quads.iter().map(move |quad| QuadInstance {
+            rect: [
+                quad.rect[0] + pos.x,
+                quad.rect[1] + pos.y,
+                quad.rect[2],
+                quad.rect[3],
+            ],
+            uv: quad.uv,
+            color: [
+                quad.color[0] * color[0],
+                quad.color[1] * color[1],
+                quad.color[2] * color[2],
+                quad.color[3] * color[3],
+            ],
+        })
+    }
+}
+
+/// Shaped strings keyed by string and layout, the bool is whether it was used since the
+/// last [Font::trim_cache]
+type ShapeCache = RefCell<HashMap<(String, [u32; 5]), (Rc<GlyphRun>, Cell<bool>)>>;
+
+/// A bitmap font, the glyphs are stored in an atlas [Texture]
+///
+/// The atlas is either drawn beforehand(a grid or a BMFont) or baked from a [TrueType]
+/// font at one size with [Font::from_ttf]. Shaped strings are cached, so drawing the same
+/// string every frame only shapes it once
+///
+/// # Example
+/// ```
+/// let font = Font::from_bmfont(&fs::read_to_string("data/font.fnt")?, atlas_image)?;
+/// // or every printable ascii char of a ttf at 24 pixels
+/// let font = Font::from_ttf(&assets.load("fonts/roboto.ttf")?, 24.0, ' '..='~')?;
+/// let run = font.shape("Hello world", 1.0);
+/// ui.glyph_run("hello", &font, &run, vec2(10.0, 10.0), [1.0, 1.0, 1.0, 1.0]);
+/// ```
+pub struct Font {
+    /// The atlas containing every glyph
+    pub texture: Texture,
+    /// The glyphs of the font
+    pub glyphs: HashMap<char, Glyph>,
+    /// Kerning between pairs of glyphs(in pixels)
+    pub kerning: HashMap<(char, char), f32>,
+    /// The distance between two lines(in pixels)
+    pub line_height: f32,
+    /// Shaped strings, see [Font::shape]
+    cache: ShapeCache,
+}
+
+impl Font {
+    /// Creates a new font from an atlas image and its glyphs
+    pub fn new(
+        img: DynamicImage,
+        glyphs: HashMap<char, Glyph>,
+        kerning: HashMap<(char, char), f32>,
+        line_height: f32,
+    ) -> Result<Self, LighthouseError> {
+        let texture = Texture::builder(img)
+            .filter(TextureFilter::Linear, TextureFilter::Linear)
+            .wrap(TextureWrap::ClampToEdge)
+            .build()?;
+
+        Ok(Font {
+            texture,
+            glyphs,
+            kerning,
+            line_height,
+            cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Creates a monospace font from an image where every glyph is a cell of a grid
+    ///
+    /// The cells are read left to right, top to bottom starting with first_char
+    pub fn from_grid(
+        img: DynamicImage,
+        cell_width: u32,
+        cell_height: u32,
+        first_char: char,
+    ) -> Result<Self, LighthouseError> {
+        let (width, height) = (img.width(), img.height());
+        let columns = width / cell_width;
+        let rows = height / cell_height;
+        if columns == 0 || rows == 0 {
+            return Err(LighthouseError::InvalidInput(
+                "The cells are bigger than the image".to_string(),
+            ));
+        }
+
+        let mut glyphs = HashMap::new();
+        for i in 0..columns * rows {
+            let c = match char::from_u32(first_char as u32 + i) {
+                Some(c) => c,
+                None => continue,
+            };
+            let (x, y) = ((i % columns) * cell_width, (i / columns) * cell_height);
+            glyphs.insert(
+                c,
+                Glyph {
+                    uv: pixel_uv(x, y, cell_width, cell_height, width, height),
+                    size: vec2(cell_width as f32, cell_height as f32),
+                    offset: vec2(0.0, 0.0),
+                    advance: cell_width as f32,
+                },
+            );
+        }
+
+        Self::new(img, glyphs, HashMap::new(), cell_height as f32)
+    }
+
+    /// Creates a font from an [AngelCode BMFont](https://www.angelcode.com/products/bmfont/)
+    /// text descriptor and its atlas image, only single page fonts are supported
+    pub fn from_bmfont(descriptor: &str, img: DynamicImage) -> Result<Self, LighthouseError> {
+        let (width, height) = (img.width(), img.height());
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+        let mut line_height = 0.0;
+
+        for line in descriptor.lines() {
+            let mut words = line.split_whitespace();
+            let tag = words.next().unwrap_or("");
+            let values: HashMap<&str, i64> = words
+                .filter_map(|word| word.split_once('='))
+                .filter_map(|(key, value)| Some((key, value.parse().ok()?)))
+                .collect();
+            let get = |key: &str| {
+                values
+                    .get(key)
+                    .copied()
+                    .ok_or_else(|| LighthouseError::Parse(format!("{} is missing {}", tag, key)))
+            };
+
+            match tag {
+                "common" => {
+                    line_height = get("lineHeight")? as f32;
+                    if get("pages").unwrap_or(1) > 1 {
+                        return Err(LighthouseError::Parse(
+                            "Only single page fonts are supported".to_string(),
+                        ));
+                    }
+                }
+                "char" => {
+                    let c = char::from_u32(get("id")? as u32)
+                        .ok_or_else(|| LighthouseError::Parse("Invalid char id".to_string()))?;
+                    let (x, y) = (get("x")? as u32, get("y")? as u32);
+                    let (w, h) = (get("width")? as u32, get("height")? as u32);
+                    glyphs.insert(
+                        c,
+                        Glyph {
+                            uv: pixel_uv(x, y, w, h, width, height),
+                            size: vec2(w as f32, h as f32),
+                            offset: vec2(get("xoffset")? as f32, get("yoffset")? as f32),
+                            advance: get("xadvance")? as f32,
+                        },
+                    );
+                }
+                "kerning" => {
+                    let first = char::from_u32(get("first")? as u32);
+                    let second = char::from_u32(get("second")? as u32);
+                    if let (Some(first), Some(second)) = (first, second) {
+                        kerning.insert((first, second), get("amount")? as f32);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Self::new(img, glyphs, kerning, line_height)
+    }
+
+    /// Creates a font by rasterizing chars of ttf at size(in pixels per em) into an atlas
+    ///
+    /// Chars the font has no glyph for are left out, include '?' so they are drawn as it.
+    /// Draw the text with a scale of 1 for it to be sharp, other sizes are stretched
+    pub fn from_ttf(
+        ttf: &TrueType,
+        size: f32,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<Self, LighthouseError> {
+        let scale = size / ttf.units_per_em;
+        let mut baked = Vec::new();
+        let mut glyph_chars = HashMap::new();
+        for c in chars {
+            let index = match ttf.glyph_index(c) {
+                Some(index) => index,
+                None => continue,
+            };
+            // a quarter of a pixel is close enough to the curves
+            let bitmap = rasterize(&ttf.outline(index, 0.25 / scale)?, scale);
+            glyph_chars.entry(index).or_insert_with(Vec::new).push(c);
+            baked.push((c, index, bitmap));
+        }
+
+        // pack the glyphs on shelves, tallest first so less space is wasted
+        let mut order: Vec<usize> = (0..baked.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(baked[*i].2.height));
+        let area: u32 = baked
+            .iter()
+            .map(|(_, _, bitmap)| (bitmap.width + GLYPH_PADDING) * (bitmap.height + GLYPH_PADDING))
+            .sum();
+        let widest = baked
+            .iter()
+            .map(|(_, _, bitmap)| bitmap.width)
+            .max()
+            .unwrap_or(0);
+        let width = ((area as f32).sqrt().ceil() as u32)
+            .max(widest + GLYPH_PADDING * 2)
+            .next_power_of_two();
+        let mut positions = vec![(0, 0); baked.len()];
+        let (mut x, mut y, mut shelf) = (GLYPH_PADDING, GLYPH_PADDING, 0);
+        for i in order {
+            let bitmap = &baked[i].2;
+            if x + bitmap.width + GLYPH_PADDING > width {
+                x = GLYPH_PADDING;
+                y += shelf + GLYPH_PADDING;
+                shelf = 0;
+            }
+            positions[i] = (x, y);
+            x += bitmap.width + GLYPH_PADDING;
+            shelf = shelf.max(bitmap.height);
+        }
+        let height = (y + shelf + GLYPH_PADDING).next_power_of_two();
+
+        let mut img = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 0]));
+        let mut glyphs = HashMap::new();
+        for ((c, index, bitmap), (x, y)) in baked.iter().zip(positions) {
+            for (i, coverage) in bitmap.coverage.iter().enumerate() {
+                let (column, row) = (i as u32 % bitmap.width, i as u32 / bitmap.width);
+                img.get_pixel_mut(x + column, y + row)[3] = *coverage;
+            }
+            glyphs.insert(
+                *c,
+                Glyph {
+                    uv: pixel_uv(x, y, bitmap.width, bitmap.height, width, height),
+                    size: vec2(bitmap.width as f32, bitmap.height as f32),
+                    // the pen is at the top of the line
+                    offset: vec2(bitmap.left as f32, ttf.ascent * scale - bitmap.top as f32),
+                    advance: ttf.advance(*index) * scale,
+                },
+            );
+        }
+
+        let mut kerning = HashMap::new();
+        for ((left, right), amount) in ttf.kerning_pairs() {
+            if let (Some(lefts), Some(rights)) = (glyph_chars.get(&left), glyph_chars.get(&right)) {
+                for first in lefts {
+                    for second in rights {
+                        kerning.insert((*first, *second), amount * scale);
+                    }
+                }
+            }
+        }
+
+        Self::new(ImageRgba8(img), glyphs, kerning, ttf.line_height() * scale)
+    }
+
+    /// Turns a string into glyph quads, scale is the size of the font relative to the atlas
+    ///
+    /// The result is cached, calling this again with the same string and scale is cheap
+    pub fn shape(&self, text: &str, scale: f32) -> Rc<GlyphRun> {
+        self.layout(text, &TextLayout::new(scale))
+    }
+
+    /// Turns a string into glyph quads that are laid out following layout, see [TextLayout]
+    ///
+    /// The result is cached, calling this again with the same string and layout is cheap
+    pub fn layout(&self, text: &str, layout: &TextLayout) -> Rc<GlyphRun> {
+        let key = (text.to_string(), layout.key());
+        if let Some((run, used)) = self.cache.borrow().get(&key) {
+            used.set(true);
+            return run.clone();
+        }
+
+        let run = Rc::new(self.layout_uncached(text, layout));
+        self.cache
+            .borrow_mut()
+            .insert(key, (run.clone(), Cell::new(true)));
+        run
+    }
+
+    /// Removes every cached string that wasnt shaped since the last call to this
+    ///
+    /// Call it once in a while(e.g. every frame) so strings that are not drawn anymore
+    /// dont stay in memory
+    pub fn trim_cache(&self) {
+        self.cache
+            .borrow_mut()
+            .retain(|_, (_, used)| used.replace(false));
+    }
+
+    /// Gets every char of text the font has no glyph for, markup tags and new lines are
+    /// skipped
+    ///
+    /// Useful to check that a font covers every language a game is translated to
+    ///
+    /// # Example
+    /// ```
+    /// locale.set_language("ja");
+    /// assert!(font.missing_glyphs(locale.tr("menu.play")).is_empty());
+    /// ```
+    pub fn missing_glyphs(&self, text: &str) -> Vec<char> {
+        let mut missing: Vec<char> = parse_markup(text)
+            .into_iter()
+            .map(|styled| styled.c)
+            .filter(|c| *c != '\n' && !self.glyphs.contains_key(c))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
+    /// Gets the glyph of c, unknown chars use the glyph of '?'
+    fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    /// Gets the width of a word(in pixels)
+    fn word_width(&self, word: &[StyledChar], scale: f32) -> f32 {
+        let mut width = 0.0;
+        let mut last = None;
+        for styled in word {
+            if let Some(kerning) = last.and_then(|last| self.kerning.get(&(last, styled.c))) {
+                width += kerning * scale;
+            }
+            width += self.glyph(styled.c).map_or(0.0, |glyph| glyph.advance) * scale;
+            last = Some(styled.c);
+        }
+        width
+    }
+
+    fn layout_uncached(&self, text: &str, layout: &TextLayout) -> GlyphRun {
+        let scale = layout.scale;
+        let chars = if layout.markup {
+            parse_markup(text)
+        } else {
+            text.chars()
+                .map(|c| StyledChar {
+                    c,
+                    color: [1.0; 4],
+                    bold: false,
+                })
+                .collect()
+        };
+        let space = self
+            .glyphs
+            .get(&' ')
+            .map_or(self.line_height * 0.25, |glyph| glyph.advance)
+            * scale;
+
+        // break the text into lines of words
+        let mut lines: Vec<Line> = Vec::new();
+        for paragraph in chars.split(|styled| styled.c == '\n') {
+            let mut line = Line::default();
+            for word in paragraph.split(|styled| styled.c == ' ') {
+                let width = self.word_width(word, scale);
+                let new_width = if line.words.is_empty() {
+                    width
+                } else {
+                    line.width + space + width
+                };
+
+                if let Some(max_width) = layout.max_width {
+                    if new_width > max_width && !line.words.is_empty() {
+                        lines.push(std::mem::take(&mut line));
+                        line.words.push(word);
+                        line.width = width;
+                        continue;
+                    }
+                }
+                line.words.push(word);
+                line.width = new_width;
+            }
+            line.paragraph_end = true;
+            lines.push(line);
+        }
+
+        let box_width = layout
+            .max_width
+            .unwrap_or_else(|| lines.iter().map(|line| line.width).fold(0.0, f32::max));
+        let line_advance = self.line_height * scale * layout.line_spacing;
+        let bold_offset = scale.max(1.0);
+
+        let mut quads = Vec::with_capacity(chars.len());
+        for (i, line) in lines.iter().enumerate() {
+            let free = (box_width - line.width).max(0.0);
+            let gaps = line.words.len().saturating_sub(1) as f32;
+            let (mut x, gap) = match layout.align {
+                Align::Left => (0.0, space),
+                Align::Center => (free / 2.0, space),
+                Align::Right => (free, space),
+                // the last line of a paragraph is not stretched
+                Align::Justify if line.paragraph_end || gaps == 0.0 => (0.0, space),
+                Align::Justify => (0.0, space + free / gaps),
+            };
+            let y = i as f32 * line_advance;
+
+            for word in &line.words {
+                let mut last = None;
+                for styled in *word {
+                    let glyph = match self.glyph(styled.c) {
+                        Some(glyph) => glyph,
+                        None => continue,
+                    };
+                    if let Some(kerning) = last.and_then(|last| self.kerning.get(&(last, styled.c)))
+                    {
+                        x += kerning * scale;
+                    }
+
+                    if glyph.size.x > 0.0 && glyph.size.y > 0.0 {
+                        let quad = QuadInstance {
+                            rect: [
+                                x + glyph.offset.x * scale,
+                                y + glyph.offset.y * scale,
+                                glyph.size.x * scale,
+                                glyph.size.y * scale,
+                            ],
+                            uv: glyph.uv,
+                            color: styled.color,
+                        };
+                        quads.push(quad);
+                        // fake bold by drawing the glyph again a bit to the right
+                        if styled.bold {
+                            let mut quad = quad;
+                            quad.rect[0] += bold_offset;
+                            quads.push(quad);
+                        }
+                    }
+                    x += glyph.advance * scale;
+                    last = Some(styled.c);
+                }
+                x += gap;
+            }
+        }
+
+        let height = lines.len().saturating_sub(1) as f32 * line_advance + self.line_height * scale;
+        GlyphRun {
+            quads,
+            size: vec2(box_width, height),
+        }
+    }
+}
+
+/// How the lines of a text are aligned, see [TextLayout]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Align {
+    /// Lines start at the left
+    Left,
+    /// Lines are centered
+    Center,
+    /// Lines end at the right
+    Right,
+    /// The spaces are stretched so lines go from the left to the right,
+    /// the last line of a paragraph is aligned to the left
+    Justify,
+}
+
+/// How a text is laid out, see [Font::layout]
+///
+/// # Markup
+/// When markup is on the text can change its style inline, use `[[` for a literal `[`
+/// ```text
+/// [color=#ff0000]red[/color] and [b]bold[/b] text
+/// ```
+///
+/// # Example
+/// ```
+/// let run = font.layout(
+///     "[b]Guard:[/b] Halt! Who goes there?",
+///     TextLayout::new(1.0)
+///         .max_width(300.0)
+///         .align(Align::Justify)
+///         .line_spacing(1.2)
+///         .markup(true),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLayout {
+    /// The size of the font relative to the atlas
+    pub scale: f32,
+    /// Lines are wrapped at word boundaries to fit in this width(in pixels)
+    pub max_width: Option<f32>,
+    /// How the lines are aligned
+    pub align: Align,
+    /// The distance between lines relative to the line height of the font
+    pub line_spacing: f32,
+    /// Whether the text has inline markup
+    pub markup: bool,
+}
+
+impl TextLayout {
+    /// Creates a new left aligned layout without wrapping and markup
+    pub fn new(scale: f32) -> Self {
+        TextLayout {
+            scale,
+            max_width: None,
+            align: Align::Left,
+            line_spacing: 1.0,
+            markup: false,
+        }
+    }
+
+    /// This function is supposed to set the max_width. It is optional
+    pub fn max_width(&mut self, max_width: f32) -> &mut Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// This function is supposed to set the align. It is optional
+    pub fn align(&mut self, align: Align) -> &mut Self {
+        self.align = align;
+        self
+    }
+
+    /// This function is supposed to set the line_spacing. It is optional
+    pub fn line_spacing(&mut self, line_spacing: f32) -> &mut Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    /// This function is supposed to set whether the text has markup. It is optional
+    pub fn markup(&mut self, markup: bool) -> &mut Self {
+        self.markup = markup;
+        self
+    }
+
+    /// Turns the layout into something that can be hashed for the cache of [Font]
+    fn key(&self) -> [u32; 5] {
+        [
+            self.scale.to_bits(),
+            self.max_width.map_or(u32::MAX, f32::to_bits),
+            self.align as u32,
+            self.line_spacing.to_bits(),
+            self.markup as u32,
+        ]
+    }
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+/// A char and the style it was given by the markup
+#[derive(Debug, Clone, Copy)]
+struct StyledChar {
+    c: char,
+    color: [f32; 4],
+    bold: bool,
+}
+
+/// A line of a laid out text
+#[derive(Default)]
+struct Line<'a> {
+    words: Vec<&'a [StyledChar]>,
+    width: f32,
+    /// Whether the line is the last line of a paragraph
+    paragraph_end: bool,
+}
+
+/// Turns text with markup into styled chars, unknown tags are kept as text
+fn parse_markup(text: &str) -> Vec<StyledChar> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut colors = vec![[1.0; 4]];
+    let mut bold = 0_u32;
+    let mut chars = text.chars().peekable();
+
+    let push = |out: &mut Vec<StyledChar>, c, colors: &[[f32; 4]], bold| {
+        out.push(StyledChar {
+            c,
+            color: *colors.last().unwrap(),
+            bold: bold > 0,
+        })
+    };
+
+    while let Some(c) = chars.next() {
+        if c != '[' {
+            push(&mut out, c, &colors, bold);
+            continue;
+        }
+        if chars.peek() == Some(&'[') {
+            chars.next();
+            push(&mut out, '[', &colors, bold);
+            continue;
+        }
+
+        let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+        match tag.as_str() {
+            "b" => bold += 1,
+            "/b" => bold = bold.saturating_sub(1),
+            "/color" => {
+                if colors.len() > 1 {
+                    colors.pop();
+                }
+            }
+            tag => match tag.strip_prefix("color=").and_then(parse_hex_color) {
+                Some(color) => colors.push(color),
+                None => {
+                    for c in format!("[{}]", tag).chars() {
+                        push(&mut out, c, &colors, bold);
+                    }
+                }
+            },
+        }
+    }
+
+    out
+}
+
+/// Parses a #rrggbb or #rrggbbaa color
+fn parse_hex_color(hex: &str) -> Option<[f32; 4]> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+
+    let mut color = [1.0; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()? as f32 / 255.0;
+    }
+    Some(color)
+}
+
+/// Turns a rectangle in pixels into a uv rectangle, textures are flipped when they are
+/// uploaded so v goes from the bottom of the image
+fn pixel_uv(x: u32, y: u32, width: u32, height: u32, img_width: u32, img_height: u32) -> [f32; 4] {
+    let (img_width, img_height) = (img_width as f32, img_height as f32);
+    [
+        x as f32 / img_width,
+        1.0 - y as f32 / img_height,
+        (x + width) as f32 / img_width,
+        1.0 - (y + height) as f32 / img_height,
+    ]
+}
@@ -0,0 +1,458 @@
+use nalgebra_glm::{vec2, vec3, Vec2, Vec3};
+
+use super::{model::ModelVertex, text::Align, ttf::TrueType, *};
+use crate::ECS::mesh::Mesh;
+
+/// The sides of two edges that bend less than this(in radians) are shaded smoothly
+const SMOOTH_ANGLE: f32 = 0.6;
+
+/// Turns text into real geometry with a [TrueType] font, for 3D titles and labels that
+/// are lit like the rest of the world
+///
+/// The front of the text faces +z with the baseline of the first line at y = 0, it is
+/// extruded by [TextMesh::depth] towards -z. Lines are split at `\n`. The uvs of the front
+/// and the back go from 0 to 1 over the whole text, the uvs of the sides go along the
+/// outline(in world units) and from the front to the back
+///
+/// # Example
+/// ```
+/// let ttf: TrueType = assets.load("fonts/roboto.ttf")?;
+/// let title = TextMesh::new(1.0).depth(0.2).align(Align::Center).mesh(&ttf, "Lighthouse")?;
+/// title.setup();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextMesh {
+    /// The size of the font, the height of an em(in world units)
+    pub size: f32,
+    /// How far the text is extruded, 0 only makes the front(in world units)
+    pub depth: f32,
+    /// How far the lines can be from the curves of the glyphs(in world units)
+    pub tolerance: f32,
+    /// How the lines are aligned around x = 0, justify is the same as left
+    pub align: Align,
+    /// The distance between lines relative to the line height of the font
+    pub line_spacing: f32,
+}
+
+impl TextMesh {
+    /// Creates a new flat left aligned text mesh builder with a font of size(in world units)
+    pub fn new(size: f32) -> Self {
+        TextMesh {
+            size,
+            depth: 0.0,
+            tolerance: size / 200.0,
+            align: Align::Left,
+            line_spacing: 1.0,
+        }
+    }
+
+    /// Sets how far the text is extruded
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets how far the lines can be from the curves of the glyphs
+    pub fn tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets how the lines are aligned
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Sets the distance between lines
+    pub fn line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    /// Gets the contours of every glyph of text laid out(in world units), chars the font
+    /// doesn't have are drawn as '?' or left out
+    pub fn outlines(
+        &self,
+        ttf: &TrueType,
+        text: &str,
+    ) -> Result<Vec<Vec<Vec<Vec2>>>, LighthouseError> {
+        let scale = self.size / ttf.units_per_em;
+        let mut glyphs = Vec::new();
+        for (line, chars) in text.split('\n').enumerate() {
+            let indices: Vec<u16> = chars
+                .chars()
+                .filter_map(|c| ttf.glyph_index(c).or_else(|| ttf.glyph_index('?')))
+                .collect();
+            let width = indices
+                .iter()
+                .zip(indices.iter().skip(1).map(Some).chain([None]))
+                .map(|(index, next)| {
+                    ttf.advance(*index) + next.map_or(0.0, |next| ttf.kerning(*index, *next))
+                })
+                .sum::<f32>();
+            let mut pen = vec2(
+                match self.align {
+                    Align::Left | Align::Justify => 0.0,
+                    Align::Center => -width / 2.0,
+                    Align::Right => -width,
+                },
+                -(line as f32) * ttf.line_height() * self.line_spacing,
+            );
+
+            let mut last = None;
+            for index in indices {
+                if let Some(last) = last {
+                    pen.x += ttf.kerning(last, index);
+                }
+                let contours = ttf.outline(index, self.tolerance / scale)?;
+                if !contours.is_empty() {
+                    glyphs.push(
+                        contours
+                            .into_iter()
+                            .map(|contour| contour.iter().map(|p| (p + pen) * scale).collect())
+                            .collect(),
+                    );
+                }
+                pen.x += ttf.advance(index);
+                last = Some(index);
+            }
+        }
+        Ok(glyphs)
+    }
+
+    /// Builds the vertices and triangles of text
+    pub fn build(
+        &self,
+        ttf: &TrueType,
+        text: &str,
+    ) -> Result<(Vec<ModelVertex>, Vec<[u32; 3]>), LighthouseError> {
+        let glyphs = self.outlines(ttf, text)?;
+        let (min, max) = glyphs.iter().flatten().flatten().fold(
+            (vec2(f32::MAX, f32::MAX), vec2(f32::MIN, f32::MIN)),
+            |(min, max), point| (min.inf(point), max.sup(point)),
+        );
+        let extent = (max - min).map(|extent| extent.max(f32::EPSILON));
+        let uv = |point: &Vec2| (point - min).component_div(&extent);
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for contours in &glyphs {
+            let triangles = triangulate(contours);
+            let points = contours.iter().flatten();
+
+            let front = vertices.len() as u32;
+            vertices.extend(points.clone().map(|point| ModelVertex {
+                pos: vec3(point.x, point.y, 0.0),
+                uv: uv(point),
+                normal: vec3(0.0, 0.0, 1.0),
+            }));
+            indices.extend(triangles.iter().map(|t| t.map(|i| i + front)));
+            if self.depth <= 0.0 {
+                continue;
+            }
+
+            // the back faces the other way so its triangles are flipped
+            let back = vertices.len() as u32;
+            vertices.extend(points.map(|point| ModelVertex {
+                pos: vec3(point.x, point.y, -self.depth),
+                uv: vec2(1.0 - uv(point).x, uv(point).y),
+                normal: vec3(0.0, 0.0, -1.0),
+            }));
+            indices.extend(
+                triangles
+                    .iter()
+                    .map(|t| [t[0] + back, t[2] + back, t[1] + back]),
+            );
+
+            for (contour, depth) in contours.iter().zip(nesting(contours)) {
+                self.add_sides(contour, depth % 2 == 1, &mut vertices, &mut indices);
+            }
+        }
+
+        if vertices.is_empty() {
+            return Err(LighthouseError::InvalidInput(
+                "The text has nothing to draw".to_string(),
+            ));
+        }
+        Ok((vertices, indices))
+    }
+
+    /// Builds a mesh of text, see [TextMesh::build]
+    pub fn mesh(&self, ttf: &TrueType, text: &str) -> Result<Mesh<ModelVertex>, LighthouseError> {
+        let (vertices, indices) = self.build(ttf, text)?;
+        Ok(Mesh::from_vertices(vertices, indices))
+    }
+
+    /// Adds the quads between the front and the back along contour
+    fn add_sides(
+        &self,
+        contour: &[Vec2],
+        hole: bool,
+        vertices: &mut Vec<ModelVertex>,
+        indices: &mut Vec<[u32; 3]>,
+    ) {
+        // walk outer contours counter clockwise and holes clockwise so the normals point out
+        let mut contour = contour.to_vec();
+        if (area(&contour) < 0.0) != hole {
+            contour.reverse();
+        }
+        let count = contour.len();
+        let edge_normal = |i: usize| {
+            let edge = contour[(i + 1) % count] - contour[i];
+            vec3(edge.y, -edge.x, 0.0).try_normalize(f32::EPSILON)
+        };
+        let normals: Vec<Option<Vec3>> = (0..count).map(edge_normal).collect();
+        // the normal at the start of edge i, smoothed with the edge before it if they
+        // bend a little
+        let corner = |i: usize, normal: Vec3| match normals[(i + count - 1) % count] {
+            Some(other) if normal.angle(&other) < SMOOTH_ANGLE => (normal + other).normalize(),
+            _ => normal,
+        };
+
+        let mut distance = 0.0;
+        for i in 0..count {
+            let next = (i + 1) % count;
+            let normal = match normals[i] {
+                Some(normal) => normal,
+                None => continue,
+            };
+            let end_normal = match normals[next] {
+                Some(other) if normal.angle(&other) < SMOOTH_ANGLE => (normal + other).normalize(),
+                _ => normal,
+            };
+            let (start, end) = (contour[i], contour[next]);
+            let length = (end - start).norm();
+
+            let first = vertices.len() as u32;
+            for (point, normal, u) in [
+                (start, corner(i, normal), distance),
+                (end, end_normal, distance + length),
+            ] {
+                for (z, v) in [(0.0, 0.0), (-self.depth, 1.0)] {
+                    vertices.push(ModelVertex {
+                        pos: vec3(point.x, point.y, z),
+                        uv: vec2(u, v),
+                        normal,
+                    });
+                }
+            }
+            indices.push([first, first + 1, first + 3]);
+            indices.push([first, first + 3, first + 2]);
+            distance += length;
+        }
+    }
+}
+
+impl Default for TextMesh {
+    fn default() -> Self {
+        TextMesh::new(1.0)
+    }
+}
+
+/// Triangulates a shape made of closed contours, like the outline of a glyph
+///
+/// Contours inside an odd number of other contours are holes, the direction they go in
+/// doesn't matter. The indices point to the points of the contours one contour after the
+/// other, the triangles go counter clockwise
+///
+/// Contours that overlap each other aren't merged, so the few glyphs whose accent overlaps
+/// the letter(like Ǫ in some fonts) come out wrong
+pub fn triangulate(contours: &[Vec<Vec2>]) -> Vec<[u32; 3]> {
+    let points: Vec<Vec2> = contours.iter().flatten().copied().collect();
+    let mut polygons = Vec::with_capacity(contours.len());
+    let mut start = 0;
+    for contour in contours {
+        polygons.push((start..start + contour.len() as u32).collect::<Vec<u32>>());
+        start += contour.len() as u32;
+    }
+
+    let depths = nesting(contours);
+    let mut triangles = Vec::new();
+    for (outer, depth) in depths.iter().enumerate() {
+        if depth % 2 == 1 {
+            continue;
+        }
+        let mut polygon = polygons[outer].clone();
+        if area(&contours[outer]) < 0.0 {
+            polygon.reverse();
+        }
+
+        // the holes directly inside of this contour, the rightmost one first so the bridges
+        // don't cross
+        let mut holes: Vec<usize> = (0..contours.len())
+            .filter(|hole| {
+                depths[*hole] == depth + 1
+                    && inside(contours[*hole][0], &contours[outer])
+                    && !(0..contours.len()).any(|other| {
+                        other != outer
+                            && depths[other] == *depth
+                            && inside(contours[*hole][0], &contours[other])
+                            && inside(contours[other][0], &contours[outer])
+                    })
+            })
+            .collect();
+        let rightmost = |contour: &[Vec2]| contour.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        holes.sort_by(|a, b| rightmost(&contours[*b]).total_cmp(&rightmost(&contours[*a])));
+        for hole in holes {
+            let mut hole_polygon = polygons[hole].clone();
+            if area(&contours[hole]) > 0.0 {
+                hole_polygon.reverse();
+            }
+            polygon = bridge(polygon, &hole_polygon, &points);
+        }
+
+        clip_ears(polygon, &points, &mut triangles);
+    }
+    triangles
+}
+
+/// Gets how many other contours every contour is inside of
+fn nesting(contours: &[Vec<Vec2>]) -> Vec<usize> {
+    (0..contours.len())
+        .map(|i| {
+            (0..contours.len())
+                .filter(|other| *other != i && inside(contours[i][0], &contours[*other]))
+                .count()
+        })
+        .collect()
+}
+
+/// Gets the area of a contour, it is positive if the contour goes counter clockwise
+fn area(contour: &[Vec2]) -> f32 {
+    (0..contour.len())
+        .map(|i| cross(contour[i], contour[(i + 1) % contour.len()]))
+        .sum::<f32>()
+        / 2.0
+}
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Checks if point is inside of contour with the even odd rule
+fn inside(point: Vec2, contour: &[Vec2]) -> bool {
+    let mut inside = false;
+    for i in 0..contour.len() {
+        let (a, b) = (contour[i], contour[(i + 1) % contour.len()]);
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Checks if point is inside of or on the edge of the counter clockwise triangle a, b, c
+fn in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    cross(b - a, point - a) >= 0.0
+        && cross(c - b, point - b) >= 0.0
+        && cross(a - c, point - c) >= 0.0
+}
+
+/// Joins a clockwise hole into a counter clockwise polygon by cutting a bridge from the
+/// rightmost point of the hole to a point of the polygon it can see
+fn bridge(polygon: Vec<u32>, hole: &[u32], points: &[Vec2]) -> Vec<u32> {
+    let start = (0..hole.len())
+        .max_by(|a, b| {
+            points[hole[*a] as usize]
+                .x
+                .total_cmp(&points[hole[*b] as usize].x)
+        })
+        .unwrap();
+    let m = points[hole[start] as usize];
+
+    // the closest edge to the right of m, the end of it that is further right can be seen
+    let count = polygon.len();
+    let mut hit: Option<(Vec2, usize)> = None;
+    for i in 0..count {
+        let (a, b) = (
+            points[polygon[i] as usize],
+            points[polygon[(i + 1) % count] as usize],
+        );
+        if (a.y > m.y) == (b.y > m.y) || a.y == b.y {
+            continue;
+        }
+        let x = a.x + (m.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x >= m.x && hit.is_none_or(|(closest, _)| x < closest.x) {
+            let end = if a.x > b.x { i } else { (i + 1) % count };
+            hit = Some((vec2(x, m.y), end));
+        }
+    }
+    let target = match hit {
+        Some((intersection, end)) => {
+            let p = points[polygon[end] as usize];
+            // a point of the polygon in the way is closer to the ray, take the one with the
+            // smallest angle to it
+            (0..count)
+                .filter(|i| {
+                    let v = points[polygon[*i] as usize];
+                    *i != end
+                        && v.x > m.x
+                        && v != m
+                        && (in_triangle(v, m, intersection, p)
+                            || in_triangle(v, m, p, intersection))
+                })
+                .min_by(|a, b| {
+                    let slope = |i: &usize| {
+                        let v = points[polygon[*i] as usize];
+                        ((v.y - m.y).abs() / (v.x - m.x), (v - m).norm())
+                    };
+                    slope(a)
+                        .partial_cmp(&slope(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap_or(end)
+        }
+        // the hole isn't really inside, join it to the closest point
+        None => (0..count)
+            .min_by(|a, b| {
+                let distance = |i: &usize| (points[polygon[*i] as usize] - m).norm();
+                distance(a).total_cmp(&distance(b))
+            })
+            .unwrap(),
+    };
+
+    let mut merged = Vec::with_capacity(count + hole.len() + 2);
+    merged.extend_from_slice(&polygon[..=target]);
+    merged.extend((0..=hole.len()).map(|i| hole[(start + i) % hole.len()]));
+    merged.extend_from_slice(&polygon[target..]);
+    merged
+}
+
+/// Triangulates a counter clockwise polygon by cutting off its ears, corners that no other
+/// point is in, one at a time
+fn clip_ears(mut polygon: Vec<u32>, points: &[Vec2], triangles: &mut Vec<[u32; 3]>) {
+    let mut i = 0;
+    let mut stalled = 0;
+    while polygon.len() >= 3 {
+        let count = polygon.len();
+        i %= count;
+        let (a, b, c) = (
+            polygon[(i + count - 1) % count],
+            polygon[i],
+            polygon[(i + 1) % count],
+        );
+        let (pa, pb, pc) = (points[a as usize], points[b as usize], points[c as usize]);
+        let turn = cross(pb - pa, pc - pb);
+        let ear = turn > 0.0
+            && !polygon.iter().any(|v| {
+                let p = points[*v as usize];
+                p != pa && p != pb && p != pc && in_triangle(p, pa, pb, pc)
+            });
+
+        // a corner that doesn't turn is cut off without a triangle, a polygon that crosses
+        // itself can run out of ears so then any corner is cut off
+        if ear || turn == 0.0 || stalled > count {
+            if turn != 0.0 {
+                triangles.push([a, b, c]);
+            }
+            polygon.remove(i);
+            stalled = 0;
+        } else {
+            i += 1;
+            stalled += 1;
+        }
+    }
+}
@@ -1,61 +1,834 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
-use super::{number::*, *};
+use super::{number::*, recovery::ContextId, *};
 
 /// This is a texture error, it is used by [Texture]
 #[derive(Debug)]
 pub enum TextureError {
-    /// This error happens when the name of the texture parameter dosen't exist
-    UnknownTextureParameter(String),
+    /// This error happens when the image has no pixels
+    EmptyImage,
+    /// This error happens when the image is bigger than the gpu allows, contains the size of
+    /// the image and the biggest size allowed(in pixels)
+    TooBig((u32, u32), u32),
+    /// This error happens when the options of a [TextureBuilder] don't work together
+    InvalidOptions(String),
+    /// This error happens when a compressed texture file can't be read, contains why
+    InvalidData(String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::EmptyImage => write!(f, "The image has no pixels"),
+            TextureError::TooBig((width, height), max) => write!(
+                f,
+                "The image is {}x{} but the gpu allows at most {}x{}",
+                width, height, max, max
+            ),
+            TextureError::InvalidOptions(message) => write!(f, "{}", message),
+            TextureError::InvalidData(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+/// A parameter of a texture, see
+/// [glTexParameter](https://www.khronos.org/registry/OpenGL-Refpages/gl4/html/glTexParameter.xhtml)
+///
+/// [TextureSettings] sets the common ones with typed values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureParameter {
+    /// The lowest mipmap level that is used
+    BaseLevel = GL_TEXTURE_BASE_LEVEL as isize,
+    /// The highest mipmap level that is used
+    MaxLevel = GL_TEXTURE_MAX_LEVEL as isize,
+    /// How a depth texture is compared when [TextureParameter::CompareMode] compares it
+    CompareFunc = GL_TEXTURE_COMPARE_FUNC as isize,
+    /// Whether a depth texture is compared to the uv when it is read, for shadow samplers
+    CompareMode = GL_TEXTURE_COMPARE_MODE as isize,
+    /// Added to the mipmap level that is picked
+    LodBias = GL_TEXTURE_LOD_BIAS as isize,
+    /// The filter used when the texture is smaller on the screen than it is
+    MinFilter = GL_TEXTURE_MIN_FILTER as isize,
+    /// The filter used when the texture is bigger on the screen than it is
+    MagFilter = GL_TEXTURE_MAG_FILTER as isize,
+    /// The lowest mipmap level that can be picked
+    MinLod = GL_TEXTURE_MIN_LOD as isize,
+    /// The highest mipmap level that can be picked
+    MaxLod = GL_TEXTURE_MAX_LOD as isize,
+    /// Which channel shaders read as red
+    SwizzleR = GL_TEXTURE_SWIZZLE_R as isize,
+    /// Which channel shaders read as green
+    SwizzleG = GL_TEXTURE_SWIZZLE_G as isize,
+    /// Which channel shaders read as blue
+    SwizzleB = GL_TEXTURE_SWIZZLE_B as isize,
+    /// Which channel shaders read as alpha
+    SwizzleA = GL_TEXTURE_SWIZZLE_A as isize,
+    /// All 4 swizzles at once
+    SwizzleRgba = GL_TEXTURE_SWIZZLE_RGBA as isize,
+    /// How the x uv is wrapped
+    WrapS = GL_TEXTURE_WRAP_S as isize,
+    /// How the y uv is wrapped
+    WrapT = GL_TEXTURE_WRAP_T as isize,
+    /// How the z uv is wrapped, for 3d textures and cubemaps
+    WrapR = GL_TEXTURE_WRAP_R as isize,
+    /// The color used outside of the texture with [TextureWrap::ClampToBorder]
+    BorderColor = GL_TEXTURE_BORDER_COLOR as isize,
+    /// How many samples anisotropic filtering takes at most, it only exists when
+    /// [max_anisotropy] is Some
+    MaxAnisotropy = GL_TEXTURE_MAX_ANISOTROPY as isize,
+}
+
+impl From<TextureParameter> for u32 {
+    fn from(param: TextureParameter) -> u32 {
+        param as u32
+    }
+}
+
+/// `GL_TEXTURE_MAX_ANISOTROPY` of the anisotropic filtering extension, it isn't in opengl 3.3
+const GL_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+/// `GL_MAX_TEXTURE_MAX_ANISOTROPY` of the anisotropic filtering extension
+const GL_MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+/// Gets the most anisotropy the gpu can filter with, None when it doesn't have the
+/// anisotropic filtering extension
+///
+/// Anisotropic filtering keeps textures seen at steep angles, like the floor, sharp
+pub fn max_anisotropy() -> Option<f32> {
+    if !has_extension("GL_EXT_texture_filter_anisotropic")
+        && !has_extension("GL_ARB_texture_filter_anisotropic")
+    {
+        return None;
+    }
+    let mut max = 1.0;
+    unsafe { glGetFloatv(GL_MAX_TEXTURE_MAX_ANISOTROPY, &mut max) };
+    Some(max)
 }
 
 /// A type used by [Texture] to store the texture params and it's values
-pub type TextureParam = HashMap<&'static str, MultiSingularNumber>;
+pub type TextureParam = HashMap<TextureParameter, MultiSingularNumber>;
+
+/// What kind of texture a [Texture] is bound as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureTarget {
+    /// A 1d texture
+    Texture1D = GL_TEXTURE_1D as isize,
+    /// A 2d texture
+    Texture2D = GL_TEXTURE_2D as isize,
+    /// A 3d texture
+    Texture3D = GL_TEXTURE_3D as isize,
+    /// An array of 1d textures
+    Texture1DArray = GL_TEXTURE_1D_ARRAY as isize,
+    /// An array of 2d textures
+    Texture2DArray = GL_TEXTURE_2D_ARRAY as isize,
+    /// A 2d texture read with pixel coordinates and without mipmaps
+    Rectangle = GL_TEXTURE_RECTANGLE as isize,
+    /// 6 2d textures that make the faces of a cube
+    CubeMap = GL_TEXTURE_CUBE_MAP as isize,
+    /// A 1d texture that reads a buffer
+    Buffer = GL_TEXTURE_BUFFER as isize,
+    /// A 2d texture with several samples per pixel
+    Texture2DMultisample = GL_TEXTURE_2D_MULTISAMPLE as isize,
+    /// An array of multisampled 2d textures
+    Texture2DMultisampleArray = GL_TEXTURE_2D_MULTISAMPLE_ARRAY as isize,
+}
+
+impl From<TextureTarget> for u32 {
+    fn from(target: TextureTarget) -> u32 {
+        target as u32
+    }
+}
+
+/// One of the 32 texture units textures are bound to, opengl 3.3 only promises 16 of them
+/// to every shader stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureUnit {
+    /// Texture unit 0
+    Texture0 = GL_TEXTURE0 as isize,
+    /// Texture unit 1
+    Texture1 = GL_TEXTURE1 as isize,
+    /// Texture unit 2
+    Texture2 = GL_TEXTURE2 as isize,
+    /// Texture unit 3
+    Texture3 = GL_TEXTURE3 as isize,
+    /// Texture unit 4
+    Texture4 = GL_TEXTURE4 as isize,
+    /// Texture unit 5
+    Texture5 = GL_TEXTURE5 as isize,
+    /// Texture unit 6
+    Texture6 = GL_TEXTURE6 as isize,
+    /// Texture unit 7
+    Texture7 = GL_TEXTURE7 as isize,
+    /// Texture unit 8
+    Texture8 = GL_TEXTURE8 as isize,
+    /// Texture unit 9
+    Texture9 = GL_TEXTURE9 as isize,
+    /// Texture unit 10
+    Texture10 = GL_TEXTURE10 as isize,
+    /// Texture unit 11
+    Texture11 = GL_TEXTURE11 as isize,
+    /// Texture unit 12
+    Texture12 = GL_TEXTURE12 as isize,
+    /// Texture unit 13
+    Texture13 = GL_TEXTURE13 as isize,
+    /// Texture unit 14
+    Texture14 = GL_TEXTURE14 as isize,
+    /// Texture unit 15
+    Texture15 = GL_TEXTURE15 as isize,
+    /// Texture unit 16
+    Texture16 = GL_TEXTURE16 as isize,
+    /// Texture unit 17
+    Texture17 = GL_TEXTURE17 as isize,
+    /// Texture unit 18
+    Texture18 = GL_TEXTURE18 as isize,
+    /// Texture unit 19
+    Texture19 = GL_TEXTURE19 as isize,
+    /// Texture unit 20
+    Texture20 = GL_TEXTURE20 as isize,
+    /// Texture unit 21
+    Texture21 = GL_TEXTURE21 as isize,
+    /// Texture unit 22
+    Texture22 = GL_TEXTURE22 as isize,
+    /// Texture unit 23
+    Texture23 = GL_TEXTURE23 as isize,
+    /// Texture unit 24
+    Texture24 = GL_TEXTURE24 as isize,
+    /// Texture unit 25
+    Texture25 = GL_TEXTURE25 as isize,
+    /// Texture unit 26
+    Texture26 = GL_TEXTURE26 as isize,
+    /// Texture unit 27
+    Texture27 = GL_TEXTURE27 as isize,
+    /// Texture unit 28
+    Texture28 = GL_TEXTURE28 as isize,
+    /// Texture unit 29
+    Texture29 = GL_TEXTURE29 as isize,
+    /// Texture unit 30
+    Texture30 = GL_TEXTURE30 as isize,
+    /// Texture unit 31
+    Texture31 = GL_TEXTURE31 as isize,
+}
+
+impl TextureUnit {
+    /// Gets the unit at index, None if it is 32 or more
+    pub fn from_index(index: u32) -> Option<Self> {
+        use TextureUnit::*;
+        const UNITS: [TextureUnit; 32] = [
+            Texture0, Texture1, Texture2, Texture3, Texture4, Texture5, Texture6, Texture7,
+            Texture8, Texture9, Texture10, Texture11, Texture12, Texture13, Texture14, Texture15,
+            Texture16, Texture17, Texture18, Texture19, Texture20, Texture21, Texture22, Texture23,
+            Texture24, Texture25, Texture26, Texture27, Texture28, Texture29, Texture30, Texture31,
+        ];
+        UNITS.get(index as usize).copied()
+    }
+
+    /// Gets the index of the unit, what samplers are set to
+    pub fn index(self) -> u32 {
+        self as u32 - GL_TEXTURE0
+    }
+}
+
+impl From<TextureUnit> for u32 {
+    fn from(unit: TextureUnit) -> u32 {
+        unit as u32
+    }
+}
+
+/// How a texture is sampled between and across texels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// Takes the closest texel
+    Nearest = GL_NEAREST as isize,
+    /// Blends the 4 closest texels
+    Linear = GL_LINEAR as isize,
+    /// Takes the closest texel of the closest mipmap
+    NearestMipmapNearest = GL_NEAREST_MIPMAP_NEAREST as isize,
+    /// Blends the 4 closest texels of the closest mipmap
+    LinearMipmapNearest = GL_LINEAR_MIPMAP_NEAREST as isize,
+    /// Takes the closest texel of the 2 closest mipmaps and blends them
+    NearestMipmapLinear = GL_NEAREST_MIPMAP_LINEAR as isize,
+    /// Blends the 4 closest texels of the 2 closest mipmaps, also called trilinear
+    LinearMipmapLinear = GL_LINEAR_MIPMAP_LINEAR as isize,
+}
+
+impl TextureFilter {
+    /// Gets the filter of an opengl enum, None if it isn't a filter
+    pub fn from_gl(filter: u32) -> Option<Self> {
+        match filter {
+            GL_NEAREST => Some(TextureFilter::Nearest),
+            GL_LINEAR => Some(TextureFilter::Linear),
+            GL_NEAREST_MIPMAP_NEAREST => Some(TextureFilter::NearestMipmapNearest),
+            GL_LINEAR_MIPMAP_NEAREST => Some(TextureFilter::LinearMipmapNearest),
+            GL_NEAREST_MIPMAP_LINEAR => Some(TextureFilter::NearestMipmapLinear),
+            GL_LINEAR_MIPMAP_LINEAR => Some(TextureFilter::LinearMipmapLinear),
+            _ => None,
+        }
+    }
+
+    /// Checks if the filter reads mipmaps, only min filters can
+    pub fn uses_mipmaps(self) -> bool {
+        !matches!(self, TextureFilter::Nearest | TextureFilter::Linear)
+    }
+
+    /// Gets the filter that samples the same way inside of a mipmap without reading any
+    /// other mipmap
+    pub fn without_mipmaps(self) -> Self {
+        match self {
+            TextureFilter::Nearest
+            | TextureFilter::NearestMipmapNearest
+            | TextureFilter::NearestMipmapLinear => TextureFilter::Nearest,
+            TextureFilter::Linear
+            | TextureFilter::LinearMipmapNearest
+            | TextureFilter::LinearMipmapLinear => TextureFilter::Linear,
+        }
+    }
+}
+
+/// Whether mipmaps are generated for a texture
+///
+/// A texture whose min filter reads mipmaps but has none is incomplete and samples as black,
+/// so the options are checked against the min filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MipmapMode {
+    /// Mipmaps are generated when the min filter reads them
+    #[default]
+    Auto,
+    /// Mipmaps are always generated, e.g. when the filter is changed later
+    Generate,
+    /// Mipmaps are never generated, a min filter that reads them is changed to
+    /// [TextureFilter::without_mipmaps]
+    None,
+    /// Mipmaps are uploaded by hand with [TextureBuilder::mip_levels] or
+    /// [Texture::set_mip_levels], e.g. when they are stored in the file or sharpened
+    Manual,
+}
+
+impl MipmapMode {
+    /// Works out whether mipmaps are generated with min_filter and the min filter that is
+    /// actually used
+    pub fn resolve(self, min_filter: TextureFilter) -> (bool, TextureFilter) {
+        match self {
+            MipmapMode::Auto => (min_filter.uses_mipmaps(), min_filter),
+            MipmapMode::Generate => (true, min_filter),
+            MipmapMode::None => (false, min_filter.without_mipmaps()),
+            MipmapMode::Manual => (false, min_filter),
+        }
+    }
+}
+
+impl From<TextureFilter> for u32 {
+    fn from(filter: TextureFilter) -> u32 {
+        filter as u32
+    }
+}
+
+/// What a texture does with uvs outside of 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureWrap {
+    /// Repeats the texture
+    Repeat = GL_REPEAT as isize,
+    /// Repeats the texture, every other one flipped
+    MirroredRepeat = GL_MIRRORED_REPEAT as isize,
+    /// Stretches the texels at the edge
+    ClampToEdge = GL_CLAMP_TO_EDGE as isize,
+    /// Uses the border color, see [TextureBuilder::border_color]
+    ClampToBorder = GL_CLAMP_TO_BORDER as isize,
+}
+
+impl From<TextureWrap> for u32 {
+    fn from(wrap: TextureWrap) -> u32 {
+        wrap as u32
+    }
+}
+
+/// The format the pixels of a texture are stored in on the gpu, the image is converted to it
+/// before it is uploaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFormat {
+    /// 8 bit red, green, blue and alpha
+    #[default]
+    Rgba8,
+    /// 8 bit red, green, blue and alpha where the colors are srgb, they are made linear when
+    /// the texture is read
+    Srgba8,
+    /// 8 bit red, green and blue
+    Rgb8,
+    /// 8 bit red and green, the image is converted to luma and alpha
+    Rg8,
+    /// 8 bit red, the image is converted to luma
+    R8,
+}
+
+impl TextureFormat {
+    /// Gets the internal format, pixel format and how many bytes a pixel has
+    fn gl_formats(self) -> (u32, u32, usize) {
+        match self {
+            TextureFormat::Rgba8 => (GL_RGBA8, GL_RGBA, 4),
+            TextureFormat::Srgba8 => (GL_SRGB8_ALPHA8, GL_RGBA, 4),
+            TextureFormat::Rgb8 => (GL_RGB8, GL_RGB, 3),
+            TextureFormat::Rg8 => (GL_RG8, GL_RG, 2),
+            TextureFormat::R8 => (GL_R8, GL_RED, 1),
+        }
+    }
+
+    /// Converts img to the pixels of this format, the bottom row first like opengl wants
+    fn pixels(self, img: DynamicImage) -> Vec<u8> {
+        let img = img.flipv();
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Srgba8 => img.into_rgba8().into_raw(),
+            TextureFormat::Rgb8 => img.into_rgb8().into_raw(),
+            TextureFormat::Rg8 => img.into_luma_alpha8().into_raw(),
+            TextureFormat::R8 => img.into_luma8().into_raw(),
+        }
+    }
+}
+
+/// The filtering, wrapping and mipmap levels of a texture, only what is set is changed and
+/// the rest keeps the opengl defaults
+///
+/// # Example
+/// ```
+/// let mut target = Framebuffer::new(320, 180)?;
+/// target.color.bind(TextureTarget::Texture2D);
+/// TextureSettings::default()
+///     .min_filter(TextureFilter::Nearest)
+///     .mag_filter(TextureFilter::Nearest)
+///     .apply(&mut target.color);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextureSettings {
+    /// The filter used when the texture is smaller on the screen than it is
+    pub min_filter: Option<TextureFilter>,
+    /// The filter used when the texture is bigger on the screen than it is
+    pub mag_filter: Option<TextureFilter>,
+    /// How the x uv is wrapped
+    pub wrap_s: Option<TextureWrap>,
+    /// How the y uv is wrapped
+    pub wrap_t: Option<TextureWrap>,
+    /// How the z uv is wrapped
+    pub wrap_r: Option<TextureWrap>,
+    /// The color used outside of the texture with [TextureWrap::ClampToBorder]
+    pub border_color: Option<[f32; 4]>,
+    /// Added to the mipmap level that is picked
+    pub lod_bias: Option<f32>,
+    /// The lowest and highest mipmap level that can be picked
+    pub lod_range: Option<(f32, f32)>,
+    /// The lowest and highest mipmap level that is used
+    pub levels: Option<(i32, i32)>,
+    /// How many samples anisotropic filtering takes at most, 1 turns it off
+    ///
+    /// It is ignored when the gpu doesn't support it and clamped to [max_anisotropy]
+    pub anisotropy: Option<f32>,
+}
+
+impl TextureSettings {
+    /// Sets the min filter
+    pub fn min_filter(mut self, filter: TextureFilter) -> Self {
+        self.min_filter = Some(filter);
+        self
+    }
+
+    /// Sets the mag filter
+    pub fn mag_filter(mut self, filter: TextureFilter) -> Self {
+        self.mag_filter = Some(filter);
+        self
+    }
+
+    /// Sets the min and mag filter
+    pub fn filter(self, min: TextureFilter, mag: TextureFilter) -> Self {
+        self.min_filter(min).mag_filter(mag)
+    }
+
+    /// Sets how uvs outside of 0 to 1 are wrapped on every axis
+    pub fn wrap(mut self, wrap: TextureWrap) -> Self {
+        self.wrap_s = Some(wrap);
+        self.wrap_t = Some(wrap);
+        self.wrap_r = Some(wrap);
+        self
+    }
+
+    /// Sets how the x uv is wrapped
+    pub fn wrap_s(mut self, wrap: TextureWrap) -> Self {
+        self.wrap_s = Some(wrap);
+        self
+    }
+
+    /// Sets how the y uv is wrapped
+    pub fn wrap_t(mut self, wrap: TextureWrap) -> Self {
+        self.wrap_t = Some(wrap);
+        self
+    }
+
+    /// Sets how the z uv is wrapped
+    pub fn wrap_r(mut self, wrap: TextureWrap) -> Self {
+        self.wrap_r = Some(wrap);
+        self
+    }
+
+    /// Sets the border color
+    pub fn border_color(mut self, color: [f32; 4]) -> Self {
+        self.border_color = Some(color);
+        self
+    }
+
+    /// Sets the lod bias
+    pub fn lod_bias(mut self, bias: f32) -> Self {
+        self.lod_bias = Some(bias);
+        self
+    }
+
+    /// Sets the lowest and highest mipmap level that can be picked
+    pub fn lod_range(mut self, min: f32, max: f32) -> Self {
+        self.lod_range = Some((min, max));
+        self
+    }
+
+    /// Sets the lowest and highest mipmap level that is used
+    pub fn levels(mut self, base: i32, max: i32) -> Self {
+        self.levels = Some((base, max));
+        self
+    }
+
+    /// Sets the anisotropy
+    pub fn anisotropy(mut self, anisotropy: f32) -> Self {
+        self.anisotropy = Some(anisotropy);
+        self
+    }
+
+    /// Gets the settings in the format of [Texture::params]
+    ///
+    /// The border color isn't one of them because [Array] can only hold static arrays,
+    /// [TextureSettings::apply] sets it on its own
+    pub fn params(&self) -> TextureParam {
+        let int = |value: i32| MultiSingularNumber::Number(Number::Integer(value));
+        let float = |value: f32| MultiSingularNumber::Number(Number::Float(value));
+        let mut params = TextureParam::new();
+        let mut set = |param, value: Option<MultiSingularNumber>| {
+            if let Some(value) = value {
+                params.insert(param, value);
+            }
+        };
+        set(
+            TextureParameter::MinFilter,
+            self.min_filter.map(|filter| int(filter as i32)),
+        );
+        set(
+            TextureParameter::MagFilter,
+            self.mag_filter.map(|filter| int(filter as i32)),
+        );
+        set(
+            TextureParameter::WrapS,
+            self.wrap_s.map(|wrap| int(wrap as i32)),
+        );
+        set(
+            TextureParameter::WrapT,
+            self.wrap_t.map(|wrap| int(wrap as i32)),
+        );
+        set(
+            TextureParameter::WrapR,
+            self.wrap_r.map(|wrap| int(wrap as i32)),
+        );
+        set(TextureParameter::LodBias, self.lod_bias.map(float));
+        set(
+            TextureParameter::MinLod,
+            self.lod_range.map(|(min, _)| float(min)),
+        );
+        set(
+            TextureParameter::MaxLod,
+            self.lod_range.map(|(_, max)| float(max)),
+        );
+        set(
+            TextureParameter::BaseLevel,
+            self.levels.map(|(base, _)| int(base)),
+        );
+        set(
+            TextureParameter::MaxLevel,
+            self.levels.map(|(_, max)| int(max)),
+        );
+        if let Some(anisotropy) = self.anisotropy {
+            set(
+                TextureParameter::MaxAnisotropy,
+                max_anisotropy().map(|max| float(anisotropy.clamp(1.0, max))),
+            );
+        }
+        params
+    }
+
+    /// Sets the settings on texture, it has to be bound
+    pub fn apply(&self, texture: &mut Texture) {
+        texture.params.extend(self.params());
+        texture.set_params();
+        if let Some(color) = self.border_color {
+            unsafe { glTexParameterfv(texture.target(), GL_TEXTURE_BORDER_COLOR, color.as_ptr()) }
+        }
+    }
+}
+
+/// Builds a 2d [Texture] from an image with typed options, the options are checked once in
+/// [TextureBuilder::build] instead of when every parameter is set
+///
+/// By default the texture is on unit 0, is [TextureFormat::Rgba8], repeats, is filtered
+/// trilinearly and has mipmaps when the min filter reads them, see [MipmapMode]
+///
+/// # Example
+/// ```
+/// let texture = Texture::builder(image::open("data/image.jpg")?)
+///     .filter(TextureFilter::Nearest, TextureFilter::Nearest)
+///     .wrap(TextureWrap::ClampToEdge)
+///     .build()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct TextureBuilder {
+    img: DynamicImage,
+    unit: TextureUnit,
+    format: TextureFormat,
+    min_filter: TextureFilter,
+    mag_filter: TextureFilter,
+    wrap_s: TextureWrap,
+    wrap_t: TextureWrap,
+    border_color: Option<[f32; 4]>,
+    mipmaps: MipmapMode,
+    mip_levels: Vec<DynamicImage>,
+    anisotropy: Option<f32>,
+}
+
+impl TextureBuilder {
+    /// Creates a new builder for a texture of img
+    pub fn new(img: DynamicImage) -> Self {
+        TextureBuilder {
+            img,
+            unit: TextureUnit::Texture0,
+            format: TextureFormat::Rgba8,
+            min_filter: TextureFilter::LinearMipmapLinear,
+            mag_filter: TextureFilter::Linear,
+            wrap_s: TextureWrap::Repeat,
+            wrap_t: TextureWrap::Repeat,
+            border_color: None,
+            mipmaps: MipmapMode::Auto,
+            mip_levels: Vec::new(),
+            anisotropy: None,
+        }
+    }
+
+    /// Sets the unit the texture is bound to while it is built
+    pub fn unit(mut self, unit: TextureUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Sets the format the texture is stored in
+    pub fn format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the filter used when the texture is smaller(min) and bigger(mag) on the screen
+    /// than it is
+    pub fn filter(mut self, min: TextureFilter, mag: TextureFilter) -> Self {
+        self.min_filter = min;
+        self.mag_filter = mag;
+        self
+    }
+
+    /// Sets how uvs outside of 0 to 1 are wrapped on both axes
+    pub fn wrap(mut self, wrap: TextureWrap) -> Self {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self
+    }
+
+    /// Sets how uvs outside of 0 to 1 are wrapped on the x(s) and y(t) axis
+    pub fn wrap_st(mut self, s: TextureWrap, t: TextureWrap) -> Self {
+        self.wrap_s = s;
+        self.wrap_t = t;
+        self
+    }
+
+    /// Sets the color used outside of the texture with [TextureWrap::ClampToBorder]
+    pub fn border_color(mut self, color: [f32; 4]) -> Self {
+        self.border_color = Some(color);
+        self
+    }
+
+    /// Sets whether mipmaps are generated
+    pub fn mipmaps(mut self, mipmaps: MipmapMode) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Sets the mipmaps from level 1 on, each half the size of the one before, and sets
+    /// the mipmap mode to [MipmapMode::Manual]
+    ///
+    /// The chain doesn't have to go down to 1x1, the levels after the last one aren't used
+    pub fn mip_levels(mut self, levels: Vec<DynamicImage>) -> Self {
+        self.mip_levels = levels;
+        self.mipmaps = MipmapMode::Manual;
+        self
+    }
+
+    /// Sets how many samples anisotropic filtering takes at most, see
+    /// [TextureSettings::anisotropy]
+    pub fn anisotropy(mut self, anisotropy: f32) -> Self {
+        self.anisotropy = Some(anisotropy);
+        self
+    }
+
+    /// Gets the settings the texture is made with
+    pub fn settings(&self) -> TextureSettings {
+        let (_, min_filter) = self.mipmaps.resolve(self.min_filter);
+        let mut settings = TextureSettings::default()
+            .filter(min_filter, self.mag_filter)
+            .wrap_s(self.wrap_s)
+            .wrap_t(self.wrap_t);
+        settings.anisotropy = self.anisotropy;
+        if self.mipmaps == MipmapMode::Manual {
+            settings = settings.levels(0, self.mip_levels.len() as i32);
+        }
+        match self.border_color {
+            Some(color) => settings.border_color(color),
+            None => settings,
+        }
+    }
+
+    /// Gets the parameters the texture is made with, in the format of [Texture::params]
+    ///
+    /// The border color isn't one of them because [Array] can only hold static arrays
+    pub fn params(&self) -> TextureParam {
+        self.settings().params()
+    }
+
+    /// Checks that the options work together
+    ///
+    /// The image must have pixels and fit in `GL_MAX_TEXTURE_SIZE`, the mag filter can't use
+    /// mipmaps and a border color is only used with [TextureWrap::ClampToBorder]
+    ///
+    /// The levels given to [TextureBuilder::mip_levels] have to halve in size every level.
+    /// A min filter that reads mipmaps with [MipmapMode::None] isn't an error, it is fixed up
+    /// by [MipmapMode::resolve]
+    pub fn validate(&self) -> Result<(), TextureError> {
+        let (width, height) = (self.img.width(), self.img.height());
+        if width == 0 || height == 0 {
+            return Err(TextureError::EmptyImage);
+        }
+        let mut max_size = 0;
+        unsafe { glGetIntegerv(GL_MAX_TEXTURE_SIZE, &mut max_size) };
+        if max_size > 0 && width.max(height) > max_size as u32 {
+            return Err(TextureError::TooBig((width, height), max_size as u32));
+        }
+        check_mip_levels(width, height, &self.mip_levels)?;
+        if self.mag_filter.uses_mipmaps() {
+            return Err(TextureError::InvalidOptions(format!(
+                "The mag filter can't be {:?}, only min filters use mipmaps",
+                self.mag_filter
+            )));
+        }
+        if self.border_color.is_some()
+            && self.wrap_s != TextureWrap::ClampToBorder
+            && self.wrap_t != TextureWrap::ClampToBorder
+        {
+            return Err(TextureError::InvalidOptions(
+                "A border color is set but neither axis is clamped to the border".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validates the options, then creates and uploads the texture
+    ///
+    /// The texture is left bound to the unit
+    pub fn build(self) -> Result<Texture, TextureError> {
+        self.validate()?;
+
+        Texture::set_tex_unit(self.unit);
+        let mut texture = Texture::new();
+        texture.bind(TextureTarget::Texture2D);
+        self.settings().apply(&mut texture);
+
+        upload_2d(GL_TEXTURE_2D, 0, self.format, self.img);
+        for (level, img) in self.mip_levels.into_iter().enumerate() {
+            upload_2d(GL_TEXTURE_2D, level as i32 + 1, self.format, img);
+        }
+        if self.mipmaps.resolve(self.min_filter).0 {
+            texture.generate_mipmaps();
+        }
+
+        Ok(texture)
+    }
+}
+
+/// Uploads img as the mipmap level lod of the bound texture target in format
+fn upload_2d(target: u32, lod: i32, format: TextureFormat, img: DynamicImage) {
+    let (width, height) = (img.width(), img.height());
+    let (internal_format, pixel_format, pixel_size) = format.gl_formats();
+    let pixels = format.pixels(img);
+    unsafe {
+        // rows of 1 and 3 byte pixels aren't always 4 byte aligned
+        if pixel_size % 4 != 0 {
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
+        }
+        glTexImage2D(
+            target,
+            lod,
+            internal_format as i32,
+            width as i32,
+            height as i32,
+            0,
+            pixel_format,
+            GL_UNSIGNED_BYTE,
+            pixels.as_ptr().cast(),
+        );
+        if pixel_size % 4 != 0 {
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 4);
+        }
+    }
+}
+
+/// Checks that every mipmap level from level 1 on is half the size of the one before, like
+/// opengl needs for the texture to be complete
+fn check_mip_levels(width: u32, height: u32, levels: &[DynamicImage]) -> Result<(), TextureError> {
+    for (level, img) in levels.iter().enumerate() {
+        let shift = level as u32 + 1;
+        let expected = ((width >> shift).max(1), (height >> shift).max(1));
+        if (img.width(), img.height()) != expected {
+            return Err(TextureError::InvalidOptions(format!(
+                "Mipmap level {} is {}x{} but it has to be {}x{}",
+                shift,
+                img.width(),
+                img.height(),
+                expected.0,
+                expected.1
+            )));
+        }
+    }
+    Ok(())
+}
 
 /// [Texture](https://www.khronos.org/opengl/wiki/Texture) is a wrapper for opengl textures
+///
+/// The texture is deleted when it is dropped, see [ContextId]
 pub struct Texture {
     /// The texture id
     pub id: u32,
     /// The texture parameters
     pub params: TextureParam,
     /// The texture type, it can exist and not exist
-    pub texture_type: Option<u32>,
+    pub texture_type: Option<TextureTarget>,
+    context: ContextId,
 }
 impl Texture {
     /// Creates a new blank texture
     ///
-    /// Prefer [Texture::from_image] over this
+    /// Prefer [Texture::builder] over this
     pub fn new() -> Self {
         let mut texture: u32 = 0;
         unsafe {
             glGenTextures(1, &mut texture);
             Self {
                 id: texture,
-                params: {
-                    let mut params = TextureParam::new();
-                    params.insert("GL_DEPTH_COMPONENT", MultiSingularNumber::None);
-                    params.insert("GL_STENCIL_INDEX", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_BASE_LEVEL", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_COMPARE_FUNC", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_COMPARE_MODE", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_LOD_BIAS", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_MIN_FILTER", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_MAG_FILTER", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_MIN_LOD", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_MAX_LOD", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_MAX_LOD", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_SWIZZLE_R", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_SWIZZLE_G", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_SWIZZLE_B", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_SWIZZLE_A", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_WRAP_S", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_WRAP_T", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_WRAP_R", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_BORDER_COLOR", MultiSingularNumber::None);
-                    params.insert("GL_TEXTURE_SWIZZLE_RGBA", MultiSingularNumber::None);
-                    params
-                },
+                params: TextureParam::new(),
                 texture_type: None,
+                context: ContextId::current(),
             }
         }
     }
@@ -64,73 +837,33 @@ impl Texture {
     ///
     /// # Arguments
     ///
-    /// * 'texture unit' - Can be anything of GL_TEXTUREi + the texture's location
+    /// * 'texture unit' - The unit the next textures are bound to
     ///
-    pub fn set_tex_unit(texture_unit: u32) {
-        unsafe { glActiveTexture(texture_unit) }
+    pub fn set_tex_unit(texture_unit: TextureUnit) {
+        unsafe { glActiveTexture(texture_unit.into()) }
     }
 
     /// Binds the texture to a certain type
     ///
     /// This function takes 1 argument which is texture_type
-    pub fn bind(&mut self, texture_type: u32) {
+    pub fn bind(&mut self, texture_type: TextureTarget) {
         self.texture_type = Some(texture_type);
-        unsafe { glBindTexture(texture_type, self.id) }
+        unsafe { glBindTexture(texture_type.into(), self.id) }
     }
 
     /// Unbinds the texture
-    pub fn unbind(texture_type: u32) {
-        unsafe { glBindTexture(texture_type, 0) }
-    }
-
-    /// Turns a string into the actual opengl parameter
-    ///
-    /// If the parameter is not found it will give you a [TextureError]
-    pub fn texture_param_to_u32(in_str: &str) -> Result<u32, TextureError> {
-        if in_str == "GL_DEPTH_COMPONENT" {
-            Ok(GL_DEPTH_COMPONENT)
-        } else if in_str == "GL_STENCIL_INDEX" {
-            Ok(GL_STENCIL_INDEX)
-        } else if in_str == "GL_TEXTURE_BASE_LEVEL" {
-            Ok(GL_TEXTURE_BASE_LEVEL)
-        } else if in_str == "GL_TEXTURE_COMPARE_FUNC" {
-            Ok(GL_TEXTURE_COMPARE_FUNC)
-        } else if in_str == "GL_TEXTURE_COMPARE_MODE" {
-            Ok(GL_TEXTURE_COMPARE_MODE)
-        } else if in_str == "GL_TEXTURE_LOD_BIAS" {
-            Ok(GL_TEXTURE_LOD_BIAS)
-        } else if in_str == "GL_TEXTURE_MIN_FILTER" {
-            Ok(GL_TEXTURE_MIN_FILTER)
-        } else if in_str == "GL_TEXTURE_MAG_FILTER" {
-            Ok(GL_TEXTURE_MAG_FILTER)
-        } else if in_str == "GL_TEXTURE_MIN_LOD" {
-            Ok(GL_TEXTURE_MIN_LOD)
-        } else if in_str == "GL_TEXTURE_MAX_LOD" {
-            Ok(GL_TEXTURE_MAX_LOD)
-        } else if in_str == "GL_TEXTURE_SWIZZLE_R" {
-            Ok(GL_TEXTURE_SWIZZLE_R)
-        } else if in_str == "GL_TEXTURE_SWIZZLE_G" {
-            Ok(GL_TEXTURE_SWIZZLE_G)
-        } else if in_str == "GL_TEXTURE_SWIZZLE_B" {
-            Ok(GL_TEXTURE_SWIZZLE_B)
-        } else if in_str == "GL_TEXTURE_SWIZZLE_A" {
-            Ok(GL_TEXTURE_SWIZZLE_A)
-        } else if in_str == "GL_TEXTURE_WRAP_S" {
-            Ok(GL_TEXTURE_WRAP_S)
-        } else if in_str == "GL_TEXTURE_WRAP_T" {
-            Ok(GL_TEXTURE_WRAP_T)
-        } else if in_str == "GL_TEXTURE_WRAP_R" {
-            Ok(GL_TEXTURE_WRAP_R)
-        } else if in_str == "GL_TEXTURE_BORDER_COLOR" {
-            Ok(GL_TEXTURE_BORDER_COLOR)
-        } else if in_str == "GL_TEXTURE_SWIZZLE_RGBA" {
-            Ok(GL_TEXTURE_SWIZZLE_RGBA)
-        } else {
-            Err(TextureError::UnknownTextureParameter(format!(
-                "{} not found",
-                in_str
-            )))
-        }
+    pub fn unbind(texture_type: TextureTarget) {
+        unsafe { glBindTexture(texture_type.into(), 0) }
+    }
+
+    /// Gets the opengl enum of the type the texture was bound as
+    ///
+    /// # Panics
+    /// When the texture was never bound
+    fn target(&self) -> u32 {
+        self.texture_type
+            .expect("The texture has to be bound before it is used")
+            .into()
     }
 
     /// Sets the parameters to the texture object
@@ -140,14 +873,14 @@ impl Texture {
             unsafe {
                 match value {
                 MultiSingularNumber::Number(number) => match number {
-                    Number::Integer(int) => glTexParameteri(self.texture_type.unwrap(), Texture::texture_param_to_u32(name).unwrap(), *int),
-                    Number::Float(float) => glTexParameterf(self.texture_type.unwrap(), Texture::texture_param_to_u32(name).unwrap(), *float),
+                    Number::Integer(int) => glTexParameteri(self.target(), u32::from(*name), *int),
+                    Number::Float(float) => glTexParameterf(self.target(), u32::from(*name), *float),
                     Number::UsInteger(_) => panic!("For reasons beyond my understanding texture parameters for u8 only exist in vector commands"),
                 },
                 MultiSingularNumber::Array(array) => match array {
-                    Array::Integer(int) => glTexParameterIiv(self.texture_type.unwrap(), Texture::texture_param_to_u32(name).unwrap(), to_carray(int)),
-                    Array::Float(float) => glTexParameterfv(self.texture_type.unwrap(), Texture::texture_param_to_u32(name).unwrap(), to_carray(float)),
-                    Array::UsInteger(uint) => glTexParameterIuiv(self.texture_type.unwrap(), Texture::texture_param_to_u32(name).unwrap(), to_carray(uint)),
+                    Array::Integer(int) => glTexParameterIiv(self.target(), u32::from(*name), to_carray(int)),
+                    Array::Float(float) => glTexParameterfv(self.target(), u32::from(*name), to_carray(float)),
+                    Array::UsInteger(uint) => glTexParameterIuiv(self.target(), u32::from(*name), to_carray(uint)),
                 }
                 MultiSingularNumber::None => (),
             }
@@ -163,7 +896,7 @@ impl Texture {
         };
         unsafe {
             glTexImage2D(
-                self.texture_type.unwrap(),
+                self.target(),
                 lod,
                 GL_RGBA as i32,
                 img.width() as i32,
@@ -176,17 +909,179 @@ impl Texture {
         }
     }
 
+    /// Sets the slices of a 3d texture, the first image is the slice at the front
+    ///
+    /// The texture has to be bound as [TextureTarget::Texture3D], wrapping along the depth
+    /// is set with `GL_TEXTURE_WRAP_R` in [Texture::params]
+    ///
+    /// # Example
+    /// ```
+    /// let mut volume = Texture::new();
+    /// volume.bind(TextureTarget::Texture3D);
+    /// volume.tex_3d(0, &slices)?;
+    /// ```
+    pub fn tex_3d(&self, lod: i32, slices: &[DynamicImage]) -> Result<(), TextureError> {
+        self.tex_layers(lod, slices, GL_MAX_3D_TEXTURE_SIZE)
+    }
+
+    /// Sets the layers of a 2d texture array, every layer has to be the same size
+    ///
+    /// The texture has to be bound as [TextureTarget::Texture2DArray], shaders read a layer
+    /// with a `sampler2DArray` and the layer as the third uv coordinate, which is handy for
+    /// the frames of a sprite animation
+    ///
+    /// # Example
+    /// ```
+    /// let mut frames = Texture::new();
+    /// frames.bind(TextureTarget::Texture2DArray);
+    /// frames.tex_2d_array(0, &[walk_0, walk_1, walk_2])?;
+    /// frames.generate_mipmaps();
+    /// ```
+    pub fn tex_2d_array(&self, lod: i32, layers: &[DynamicImage]) -> Result<(), TextureError> {
+        self.tex_layers(lod, layers, GL_MAX_ARRAY_TEXTURE_LAYERS)
+    }
+
+    /// Replaces one layer of a 2d texture array or slice of a 3d texture that was already
+    /// set with [Texture::tex_2d_array] or [Texture::tex_3d]
+    ///
+    /// img has to be the size of the other layers
+    pub fn tex_layer(&self, lod: i32, layer: i32, img: DynamicImage) {
+        let img = img.flipv().into_rgba8();
+        unsafe {
+            glTexSubImage3D(
+                self.target(),
+                lod,
+                0,
+                0,
+                layer,
+                img.width() as i32,
+                img.height() as i32,
+                1,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                img.as_ptr().cast(),
+            )
+        }
+    }
+
+    /// Uploads images as the layers of a texture with depth, max_depth is the opengl limit
+    /// of how many layers there can be
+    fn tex_layers(
+        &self,
+        lod: i32,
+        layers: &[DynamicImage],
+        max_depth: u32,
+    ) -> Result<(), TextureError> {
+        let first = layers.first().ok_or(TextureError::EmptyImage)?;
+        let (width, height) = (first.width(), first.height());
+        if width == 0 || height == 0 {
+            return Err(TextureError::EmptyImage);
+        }
+        if let Some((layer, img)) = layers
+            .iter()
+            .enumerate()
+            .find(|(_, img)| img.width() != width || img.height() != height)
+        {
+            return Err(TextureError::InvalidOptions(format!(
+                "Layer {} is {}x{} but every layer has to be {}x{}",
+                layer,
+                img.width(),
+                img.height(),
+                width,
+                height
+            )));
+        }
+        let mut max = 0;
+        unsafe { glGetIntegerv(max_depth, &mut max) };
+        if max > 0 && layers.len() > max as usize {
+            return Err(TextureError::InvalidOptions(format!(
+                "There are {} layers but the gpu allows at most {}",
+                layers.len(),
+                max
+            )));
+        }
+
+        // the layers are put one after the other, each bottom row first
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4 * layers.len());
+        for img in layers {
+            pixels.extend_from_slice(&img.flipv().into_rgba8());
+        }
+        unsafe {
+            glTexImage3D(
+                self.target(),
+                lod,
+                GL_RGBA8 as i32,
+                width as i32,
+                height as i32,
+                layers.len() as i32,
+                0,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                pixels.as_ptr().cast(),
+            )
+        }
+        Ok(())
+    }
+
+    /// Gets the min filter in [Texture::params], opengl's default
+    /// [TextureFilter::NearestMipmapLinear] if it isn't set
+    pub fn min_filter(&self) -> TextureFilter {
+        match self.params.get(&TextureParameter::MinFilter) {
+            Some(MultiSingularNumber::Number(Number::Integer(filter))) => {
+                TextureFilter::from_gl(*filter as u32)
+            }
+            _ => None,
+        }
+        .unwrap_or(TextureFilter::NearestMipmapLinear)
+    }
+
     /// Generate the mipmaps required by the texture
     pub fn generate_mipmaps(&self) {
         unsafe {
-            glGenerateMipmap(self.texture_type.unwrap());
+            glGenerateMipmap(self.target());
         }
     }
 
+    /// Uploads the mipmaps from level 1 on, each half the size of the one before, like
+    /// [Texture::tex_2d] they are stored as rgba
+    ///
+    /// Level 0 has to be set first, the levels after the last one given aren't used so the
+    /// chain doesn't have to go down to 1x1
+    ///
+    /// # Example
+    /// ```
+    /// let mut texture = Texture::from_image_mipmapped(unit, TextureTarget::Texture2D, params, img, MipmapMode::Manual)?;
+    /// texture.set_mip_levels(vec![half, quarter, eighth])?;
+    /// ```
+    pub fn set_mip_levels(&mut self, levels: Vec<DynamicImage>) -> Result<(), TextureError> {
+        let (mut width, mut height) = (0, 0);
+        unsafe {
+            glGetTexLevelParameteriv(self.target(), 0, GL_TEXTURE_WIDTH, &mut width);
+            glGetTexLevelParameteriv(self.target(), 0, GL_TEXTURE_HEIGHT, &mut height);
+        }
+        check_mip_levels(width as u32, height as u32, &levels)?;
+
+        let count = levels.len() as i32;
+        for (level, img) in levels.into_iter().enumerate() {
+            self.tex_2d(level as i32 + 1, img);
+        }
+        TextureSettings::default().levels(0, count).apply(self);
+        Ok(())
+    }
+
+    /// Creates a [TextureBuilder] for a texture of img
+    pub fn builder(img: DynamicImage) -> TextureBuilder {
+        TextureBuilder::new(img)
+    }
+
     /// Creates a [Texture] object from an image
+    ///
+    /// This sets every parameter by its opengl enum, [Texture::builder] is easier to use and
+    /// checks the options. Mipmaps are only generated when the min filter reads them, like
+    /// [MipmapMode::Auto], use [Texture::from_image_mipmapped] to choose
     pub fn from_image(
-        texture_unit: u32,
-        texture_type: u32,
+        texture_unit: TextureUnit,
+        texture_type: TextureTarget,
         params: TextureParam,
         lod: i32,
         img: DynamicImage,
@@ -195,28 +1090,121 @@ impl Texture {
         let mut texture = Texture::new();
         texture.bind(texture_type);
 
-        for (param, value) in &params {
-            if params.contains_key(*param) {
-                *(texture.params).get_mut(param).unwrap() = *value;
-            } else {
-                return Err(TextureError::UnknownTextureParameter(format!(
-                    "Error: Unknown parameter {}",
-                    param
+        texture.params.extend(params);
+        texture.set_params();
+
+        texture.tex_2d(lod, img);
+        if texture.min_filter().uses_mipmaps() {
+            texture.generate_mipmaps();
+        }
+
+        Ok(texture)
+    }
+
+    /// Creates a cubemap from the images of its 6 faces, in the order +x, -x, +y, -y, +z
+    /// and -z
+    ///
+    /// Every face has to be the same square size. The faces aren't flipped like 2d textures
+    /// are because cubemaps are read with the first row at the top. The cubemap is linearly
+    /// filtered, clamped to the edges and left bound to unit 0
+    ///
+    /// # Example
+    /// ```
+    /// let sky = Texture::cubemap_from_images(
+    ///     ["right", "left", "top", "bottom", "front", "back"]
+    ///         .map(|face| image::open(format!("data/sky/{}.png", face)).unwrap()),
+    /// )?;
+    /// ```
+    pub fn cubemap_from_images(images: [DynamicImage; 6]) -> Result<Texture, TextureError> {
+        let size = images[0].width();
+        if size == 0 || images[0].height() == 0 {
+            return Err(TextureError::EmptyImage);
+        }
+        for (face, img) in images.iter().enumerate() {
+            if img.width() != size || img.height() != size {
+                return Err(TextureError::InvalidOptions(format!(
+                    "Face {} of the cubemap is {}x{} but every face has to be {}x{}",
+                    face,
+                    img.width(),
+                    img.height(),
+                    size,
+                    size
                 )));
             }
         }
+        let mut max_size = 0;
+        unsafe { glGetIntegerv(GL_MAX_CUBE_MAP_TEXTURE_SIZE, &mut max_size) };
+        if max_size > 0 && size > max_size as u32 {
+            return Err(TextureError::TooBig((size, size), max_size as u32));
+        }
+
+        Texture::set_tex_unit(TextureUnit::Texture0);
+        let mut texture = Texture::new();
+        texture.bind(TextureTarget::CubeMap);
+        TextureSettings::default()
+            .filter(TextureFilter::Linear, TextureFilter::Linear)
+            .wrap(TextureWrap::ClampToEdge)
+            .apply(&mut texture);
+
+        for (face, img) in images.into_iter().enumerate() {
+            let pixels = img.into_rgba8();
+            unsafe {
+                glTexImage2D(
+                    GL_TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                    0,
+                    GL_RGBA8 as i32,
+                    size as i32,
+                    size as i32,
+                    0,
+                    GL_RGBA,
+                    GL_UNSIGNED_BYTE,
+                    pixels.as_ptr().cast(),
+                )
+            }
+        }
 
+        Ok(texture)
+    }
+
+    /// Creates a [Texture] object from an image like [Texture::from_image] but chooses how
+    /// the mipmaps are made
+    ///
+    /// With [MipmapMode::Manual] no mipmaps are made, they are uploaded after with
+    /// [Texture::set_mip_levels]
+    pub fn from_image_mipmapped(
+        texture_unit: TextureUnit,
+        texture_type: TextureTarget,
+        params: TextureParam,
+        img: DynamicImage,
+        mipmaps: MipmapMode,
+    ) -> Result<Texture, TextureError> {
+        Texture::set_tex_unit(texture_unit);
+        let mut texture = Texture::new();
+        texture.bind(texture_type);
+        texture.params.extend(params);
+        let (generate, min_filter) = mipmaps.resolve(texture.min_filter());
+        texture
+            .params
+            .extend(TextureSettings::default().min_filter(min_filter).params());
         texture.set_params();
 
-        texture.tex_2d(lod, img);
-        texture.generate_mipmaps();
+        texture.tex_2d(0, img);
+        if generate {
+            texture.generate_mipmaps();
+        }
 
         Ok(texture)
     }
 
-    /// Deletes the texture
-    pub fn delete(&self) {
-        unsafe { glDeleteTextures(1, &self.id) }
+    /// Deletes the texture now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe { glDeleteTextures(1, &self.id) }
+        }
     }
 }
 
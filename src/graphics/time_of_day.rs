@@ -0,0 +1,201 @@
+use nalgebra_glm::{vec3, Vec3};
+
+use super::{lighting::Lighting, sky::SkyParams};
+
+/// The look of the scene at one hour of the day, [TimeOfDay] blends between them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkyKey {
+    /// The hour of the key, from 0 to 24
+    pub hour: f32,
+    /// The color of the sun light
+    pub sun_color: [f32; 3],
+    /// How bright the sun light is
+    pub sun_intensity: f32,
+    /// The ambient light
+    pub ambient: [f32; 3],
+    /// The color of the sky straight up
+    pub zenith: [f32; 3],
+    /// The color of the sky at the horizon
+    pub horizon: [f32; 3],
+}
+
+/// The default keys, a dark blue night, orange sunrise and sunset and a bright noon
+pub fn default_sky_keys() -> Vec<SkyKey> {
+    let night = SkyKey {
+        hour: 0.0,
+        sun_color: [0.3, 0.35, 0.5],
+        sun_intensity: 0.1,
+        ambient: [0.03, 0.04, 0.08],
+        zenith: [0.01, 0.01, 0.04],
+        horizon: [0.04, 0.05, 0.1],
+    };
+    vec![
+        night,
+        SkyKey { hour: 5.0, ..night },
+        SkyKey {
+            hour: 6.5,
+            sun_color: [1.0, 0.55, 0.3],
+            sun_intensity: 0.6,
+            ambient: [0.15, 0.12, 0.12],
+            zenith: [0.25, 0.3, 0.55],
+            horizon: [0.95, 0.55, 0.35],
+        },
+        SkyKey {
+            hour: 12.0,
+            sun_color: [1.0, 0.97, 0.9],
+            sun_intensity: 1.0,
+            ambient: [0.25, 0.27, 0.3],
+            zenith: [0.25, 0.45, 0.85],
+            horizon: [0.7, 0.8, 0.95],
+        },
+        SkyKey {
+            hour: 17.5,
+            sun_color: [1.0, 0.5, 0.25],
+            sun_intensity: 0.6,
+            ambient: [0.15, 0.1, 0.1],
+            zenith: [0.2, 0.25, 0.5],
+            horizon: [0.95, 0.45, 0.25],
+        },
+        SkyKey {
+            hour: 19.0,
+            ..night
+        },
+    ]
+}
+
+/// A day and night cycle that moves the sun and colors the light and sky
+///
+/// # Example
+/// ```
+/// let mut day = TimeOfDay::new(600.0).hour(8.0);
+///
+/// 'main_loop: loop {
+///     day.update(world.env.time.delta);
+///     day.apply(&mut lighting, &mut sky_params);
+///     lighting.set_uniforms(&program);
+///     sky.draw(&sky_params, &view_proj);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeOfDay {
+    /// The time of day(in hours), from 0 to 24
+    pub hour: f32,
+    /// How long a whole day takes(in seconds)
+    pub day_length: f32,
+    /// Whether the time stands still
+    pub paused: bool,
+    /// How far the path of the sun is tilted from straight overhead(in radians)
+    pub tilt: f32,
+    /// The direction the sun rises in, the sun sets in the opposite direction
+    pub east: Vec3,
+    /// The looks blended between, sorted by hour
+    pub keys: Vec<SkyKey>,
+}
+
+impl TimeOfDay {
+    /// Creates a new cycle at noon where a day takes day_length seconds
+    pub fn new(day_length: f32) -> Self {
+        TimeOfDay {
+            hour: 12.0,
+            day_length,
+            paused: false,
+            tilt: 0.4,
+            east: vec3(1.0, 0.0, 0.0),
+            keys: default_sky_keys(),
+        }
+    }
+
+    /// Sets the time of day
+    pub fn hour(mut self, hour: f32) -> Self {
+        self.hour = hour.rem_euclid(24.0);
+        self
+    }
+
+    /// Sets the looks blended between, they are sorted by hour
+    pub fn keys(mut self, mut keys: Vec<SkyKey>) -> Self {
+        keys.sort_by(|a, b| a.hour.total_cmp(&b.hour));
+        self.keys = keys;
+        self
+    }
+
+    /// Moves the time forward by delta seconds
+    pub fn update(&mut self, delta: f32) {
+        if !self.paused && self.day_length > 0.0 {
+            self.hour = (self.hour + delta / self.day_length * 24.0).rem_euclid(24.0);
+        }
+    }
+
+    /// Checks if the sun is above the horizon
+    pub fn is_day(&self) -> bool {
+        self.sun_direction().y < 0.0
+    }
+
+    /// Gets the direction the sun shines in, straight down at noon if there is no tilt
+    pub fn sun_direction(&self) -> Vec3 {
+        // 6 is sunrise in the east and 18 is sunset in the west
+        let angle = (self.hour - 6.0) / 24.0 * std::f32::consts::TAU;
+        let east = self.east.normalize();
+        let north = vec3(0.0, 1.0, 0.0).cross(&east).normalize();
+        let up = vec3(0.0, 1.0, 0.0) * self.tilt.cos() + north * self.tilt.sin();
+        -(east * angle.cos() + up * angle.sin()).normalize()
+    }
+
+    /// Gets the blended look at the current hour
+    pub fn current(&self) -> Option<SkyKey> {
+        let first = self.keys.first()?;
+        let last = self.keys.last()?;
+        let next = self
+            .keys
+            .iter()
+            .position(|key| key.hour > self.hour)
+            .unwrap_or(0);
+        let (a, b) = if next == 0 {
+            (last, first)
+        } else {
+            (&self.keys[next - 1], &self.keys[next])
+        };
+
+        // the keys wrap around midnight
+        let span = (b.hour - a.hour).rem_euclid(24.0);
+        let t = if span > 0.0 {
+            (self.hour - a.hour).rem_euclid(24.0) / span
+        } else {
+            0.0
+        };
+        let mix = |x: [f32; 3], y: [f32; 3]| [0, 1, 2].map(|i| x[i] + (y[i] - x[i]) * t);
+        Some(SkyKey {
+            hour: self.hour,
+            sun_color: mix(a.sun_color, b.sun_color),
+            sun_intensity: a.sun_intensity + (b.sun_intensity - a.sun_intensity) * t,
+            ambient: mix(a.ambient, b.ambient),
+            zenith: mix(a.zenith, b.zenith),
+            horizon: mix(a.horizon, b.horizon),
+        })
+    }
+
+    /// Moves and colors the sun of lighting and sky to the current hour
+    ///
+    /// At night the light comes from the moon, opposite the sun
+    pub fn apply(&self, lighting: &mut Lighting, sky: &mut SkyParams) {
+        let key = match self.current() {
+            Some(key) => key,
+            None => return,
+        };
+        let sun_direction = self.sun_direction();
+        let light_direction = if self.is_day() {
+            sun_direction
+        } else {
+            -sun_direction
+        };
+
+        lighting.sun.direction = light_direction;
+        lighting.sun.color = key.sun_color;
+        lighting.sun.intensity = key.sun_intensity;
+        lighting.ambient = key.ambient;
+
+        sky.zenith = key.zenith;
+        sky.horizon = key.horizon;
+        sky.sun_direction = sun_direction;
+        sky.sun_color = key.sun_color.map(|c| c * key.sun_intensity);
+    }
+}
@@ -0,0 +1,278 @@
+use super::{fullscreen::FullscreenPass, texture::Texture, *};
+
+/// Mixes the scene with a color, amount 1 is only the color
+const FADE_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform vec4 fade_color;
+uniform float amount;
+out vec4 color;
+
+void main() {
+    color = mix(texture(input_texture, uv), fade_color, amount * fade_color.a);
+}";
+
+/// Mixes the previous scene into the scene, amount 1 is only the scene
+const CROSSFADE_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform sampler2D previous_texture;
+uniform float amount;
+out vec4 color;
+
+void main() {
+    color = mix(texture(previous_texture, uv), texture(input_texture, uv), amount);
+}";
+
+/// Shows the scene on one side of a moving soft edge and the previous scene or the color on
+/// the other side
+const WIPE_FRAG: &str = "#version 330 core
+in vec2 uv;
+uniform sampler2D input_texture;
+uniform sampler2D previous_texture;
+uniform bool has_previous;
+uniform vec4 fade_color;
+uniform vec2 direction;
+uniform float amount;
+uniform float softness;
+out vec4 color;
+
+void main() {
+    // 0 where the wipe starts and 1 where it ends
+    float along = dot(uv - 0.5, direction) + 0.5;
+    float edge = amount * (1.0 + softness);
+    float shown = 1.0 - smoothstep(edge - softness, edge, along);
+    vec4 hidden = has_previous ? texture(previous_texture, uv) : fade_color;
+    color = mix(hidden, texture(input_texture, uv), shown);
+}";
+
+/// Which way a [Transition::Wipe] moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    /// From the left to the right of the screen
+    LeftToRight,
+    /// From the right to the left of the screen
+    RightToLeft,
+    /// From the top to the bottom of the screen
+    TopToBottom,
+    /// From the bottom to the top of the screen
+    BottomToTop,
+}
+
+impl WipeDirection {
+    /// Gets the direction as a vector in uv space
+    fn vector(self) -> [f32; 2] {
+        match self {
+            WipeDirection::LeftToRight => [1.0, 0.0],
+            WipeDirection::RightToLeft => [-1.0, 0.0],
+            WipeDirection::TopToBottom => [0.0, -1.0],
+            WipeDirection::BottomToTop => [0.0, 1.0],
+        }
+    }
+}
+
+/// A full screen transition, every duration is in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// Fades the scene into [Transitions::color], the screen stays that color once it
+    /// finishes until another transition starts
+    FadeOut(f32),
+    /// Fades from [Transitions::color] into the scene
+    FadeIn(f32),
+    /// Fades from the previous scene into the scene
+    Crossfade(f32),
+    /// Uncovers the scene with a moving edge, what is covered is the previous scene if there
+    /// is one and [Transitions::color] otherwise
+    Wipe(f32, WipeDirection),
+}
+
+impl Transition {
+    /// Gets how long the transition takes(in seconds)
+    pub fn duration(&self) -> f32 {
+        match *self {
+            Transition::FadeOut(duration)
+            | Transition::FadeIn(duration)
+            | Transition::Crossfade(duration)
+            | Transition::Wipe(duration, _) => duration,
+        }
+    }
+}
+
+/// Plays [Transition]s on top of the scene
+///
+/// The scene has to be rendered into a texture(e.g. the color of a
+/// [Framebuffer](super::framebuffer::Framebuffer)), it is then drawn with the transition into
+/// the bound framebuffer. Crossfades and wipes also need a texture with the previous scene
+///
+/// # Example
+/// ```
+/// let mut transitions = Transitions::new()?;
+///
+/// 'main_loop: loop {
+///     if level_done {
+///         transitions.start(Transition::FadeOut(0.5));
+///     }
+///     if transitions.just_finished() {
+///         load_next_level();
+///         transitions.start(Transition::FadeIn(0.5));
+///     }
+///
+///     scene.bind();
+///     // draw the scene
+///     Framebuffer::unbind();
+///
+///     transitions.update(world.env.time.delta);
+///     transitions.draw(&scene.color, None);
+/// }
+/// ```
+pub struct Transitions {
+    /// The color faded to and from
+    pub color: [f32; 4],
+    /// How soft the edge of wipes is, relative to the size of the screen
+    pub softness: f32,
+    /// The transition that is playing and how long it has been playing(in seconds)
+    current: Option<(Transition, f32)>,
+    /// Whether the current transition finished during the last update
+    just_finished: bool,
+    /// Whether the current transition was updated since it started, so one that takes no
+    /// time finishes on its first update
+    updated: bool,
+    fade: FullscreenPass,
+    crossfade: FullscreenPass,
+    wipe: FullscreenPass,
+}
+
+impl Transitions {
+    /// Creates new transitions that fade to black
+    pub fn new() -> Result<Self, LighthouseError> {
+        Ok(Transitions {
+            color: [0.0, 0.0, 0.0, 1.0],
+            softness: 0.05,
+            current: None,
+            just_finished: false,
+            updated: false,
+            fade: FullscreenPass::new(FADE_FRAG)?,
+            crossfade: FullscreenPass::new(CROSSFADE_FRAG)?,
+            wipe: FullscreenPass::new(WIPE_FRAG)?,
+        })
+    }
+
+    /// Starts a transition, the transition that was playing is stopped
+    pub fn start(&mut self, transition: Transition) {
+        self.current = Some((transition, 0.0));
+        self.just_finished = false;
+        self.updated = false;
+    }
+
+    /// Stops the transition that is playing, the scene is drawn as is again
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.just_finished = false;
+    }
+
+    /// Advances the transition, delta is the time since the last update(in seconds)
+    pub fn update(&mut self, delta: f32) {
+        self.just_finished = false;
+        if let Some((transition, elapsed)) = &mut self.current {
+            let was_running = !self.updated || *elapsed < transition.duration();
+            self.updated = true;
+            *elapsed += delta;
+            self.just_finished = was_running && *elapsed >= transition.duration();
+        }
+    }
+
+    /// Gets the transition that is playing
+    pub fn current(&self) -> Option<Transition> {
+        self.current.map(|(transition, _)| transition)
+    }
+
+    /// Gets how far the transition is from 0 to 1, eased so it starts and ends slowly
+    pub fn progress(&self) -> f32 {
+        match self.current {
+            Some((transition, elapsed)) => {
+                let t = if transition.duration() > 0.0 {
+                    (elapsed / transition.duration()).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                t * t * (3.0 - 2.0 * t)
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Checks if a transition is still playing
+    pub fn is_running(&self) -> bool {
+        matches!(self.current, Some((transition, elapsed)) if elapsed < transition.duration())
+    }
+
+    /// Checks if the transition finished during the last update, e.g. to load the next
+    /// level when a fade out is done
+    pub fn just_finished(&self) -> bool {
+        self.just_finished
+    }
+
+    /// Draws scene with the transition into the bound framebuffer
+    ///
+    /// previous is the scene that is transitioned from, it is only used by crossfades and wipes
+    pub fn draw(&self, scene: &Texture, previous: Option<&Texture>) {
+        let progress = self.progress();
+        let transition = match self.current {
+            Some((transition, _)) => transition,
+            None => {
+                self.fade.uniform("amount").set_uniform_f(&[0.0]);
+                self.fade.draw(scene);
+                return;
+            }
+        };
+
+        if let Some(previous) = previous {
+            unsafe {
+                glActiveTexture(GL_TEXTURE1);
+                glBindTexture(GL_TEXTURE_2D, previous.id);
+            }
+        }
+
+        match transition {
+            Transition::FadeOut(_) | Transition::FadeIn(_) => {
+                let amount = match transition {
+                    Transition::FadeOut(_) => progress,
+                    _ => 1.0 - progress,
+                };
+                self.fade.uniform("fade_color").set_uniform_f(&self.color);
+                self.fade.uniform("amount").set_uniform_f(&[amount]);
+                self.fade.draw(scene);
+            }
+            Transition::Crossfade(_) => {
+                let amount = if previous.is_some() { progress } else { 1.0 };
+                self.crossfade
+                    .uniform("previous_texture")
+                    .set_uniform_i(&[1]);
+                self.crossfade.uniform("amount").set_uniform_f(&[amount]);
+                self.crossfade.draw(scene);
+            }
+            Transition::Wipe(_, direction) => {
+                self.wipe.uniform("previous_texture").set_uniform_i(&[1]);
+                self.wipe
+                    .uniform("has_previous")
+                    .set_uniform_i(&[previous.is_some() as i32]);
+                self.wipe.uniform("fade_color").set_uniform_f(&self.color);
+                self.wipe
+                    .uniform("direction")
+                    .set_uniform_f(&direction.vector());
+                self.wipe.uniform("amount").set_uniform_f(&[progress]);
+                self.wipe
+                    .uniform("softness")
+                    .set_uniform_f(&[self.softness]);
+                self.wipe.draw(scene);
+            }
+        }
+    }
+
+    /// Deletes the passes of the transitions
+    pub fn delete(self) {
+        self.fade.delete();
+        self.crossfade.delete();
+        self.wipe.delete();
+    }
+}
@@ -0,0 +1,640 @@
+use std::collections::HashMap;
+
+use nalgebra_glm::{vec2, Vec2};
+
+use super::*;
+use crate::assets::{Asset, AssetError};
+
+/// How many components of a composite glyph can be nested
+const MAX_COMPOSITE_DEPTH: u32 = 8;
+
+/// A TrueType(.ttf) font, its glyph outlines and metrics
+///
+/// Only fonts with quadratic outlines(a `glyf` table) are supported, OpenType fonts with
+/// CFF outlines aren't. Kerning is read from the `kern` table, GPOS kerning is ignored.
+/// To draw text with it bake it into a [Font](super::text::Font) with
+/// [Font::from_ttf](super::text::Font::from_ttf)
+///
+/// # Example
+/// ```
+/// let ttf: TrueType = assets.load("fonts/roboto.ttf")?;
+/// let a = ttf.glyph_index('a').unwrap();
+/// let bitmap = rasterize(&ttf.outline(a, 1.0)?, 32.0 / ttf.units_per_em);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrueType {
+    /// The whole file, glyphs are read from it when they are needed
+    data: Vec<u8>,
+    /// How many font units one em is, the size of the font
+    pub units_per_em: f32,
+    /// How far the font goes above the baseline(in font units)
+    pub ascent: f32,
+    /// How far the font goes below the baseline, it is negative(in font units)
+    pub descent: f32,
+    /// The extra space between two lines(in font units)
+    pub line_gap: f32,
+    /// The glyph of every char
+    cmap: HashMap<char, u16>,
+    /// Kerning between pairs of glyphs(in font units)
+    kerning: HashMap<(u16, u16), f32>,
+    /// How much the pen moves after every glyph(in font units)
+    advances: Vec<f32>,
+    /// Where every glyph starts in the glyf table, with the end of the last one at the end
+    loca: Vec<usize>,
+    /// Where the glyf table starts in the file
+    glyf: usize,
+}
+
+impl TrueType {
+    /// Parses the bytes of a .ttf file
+    pub fn parse(data: Vec<u8>) -> Result<Self, LighthouseError> {
+        match read::<4>(&data, 0)? {
+            [0, 1, 0, 0] | [b't', b'r', b'u', b'e'] => (),
+            [b'O', b'T', b'T', b'O'] => {
+                return Err(LighthouseError::Unsupported(
+                    "Fonts with CFF outlines aren't supported".to_string(),
+                ))
+            }
+            [b't', b't', b'c', b'f'] => {
+                return Err(LighthouseError::Unsupported(
+                    "Font collections aren't supported".to_string(),
+                ))
+            }
+            _ => return Err(invalid("The file isn't a TrueType font")),
+        }
+
+        let mut tables = HashMap::new();
+        for i in 0..u16_at(&data, 4)? as usize {
+            let record = 12 + i * 16;
+            tables.insert(
+                read::<4>(&data, record)?,
+                u32_at(&data, record + 8)? as usize,
+            );
+        }
+        let table = |tag: &[u8; 4]| {
+            tables.get(tag).copied().ok_or_else(|| {
+                invalid(format!(
+                    "The font has no {} table",
+                    String::from_utf8_lossy(tag)
+                ))
+            })
+        };
+
+        let head = table(b"head")?;
+        let units_per_em = u16_at(&data, head + 18)? as f32;
+        // every size is divided by it
+        if units_per_em == 0.0 {
+            return Err(invalid("The font has 0 units per em"));
+        }
+        let long_loca = i16_at(&data, head + 50)? != 0;
+        let glyph_count = u16_at(&data, table(b"maxp")? + 4)? as usize;
+
+        let hhea = table(b"hhea")?;
+        let metric_count = (u16_at(&data, hhea + 34)? as usize).clamp(1, glyph_count.max(1));
+        let hmtx = table(b"hmtx")?;
+        let mut advances = Vec::with_capacity(glyph_count);
+        for i in 0..metric_count {
+            advances.push(u16_at(&data, hmtx + i * 4)? as f32);
+        }
+        // the glyphs after the last metric have the same advance as it
+        advances.resize(glyph_count, *advances.last().unwrap());
+
+        let loca_table = table(b"loca")?;
+        let loca = (0..=glyph_count)
+            .map(|i| match long_loca {
+                true => u32_at(&data, loca_table + i * 4).map(|offset| offset as usize),
+                false => u16_at(&data, loca_table + i * 2).map(|offset| offset as usize * 2),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(TrueType {
+            units_per_em,
+            ascent: i16_at(&data, hhea + 4)? as f32,
+            descent: i16_at(&data, hhea + 6)? as f32,
+            line_gap: i16_at(&data, hhea + 8)? as f32,
+            cmap: parse_cmap(&data, table(b"cmap")?, glyph_count)?,
+            kerning: match tables.get(b"kern") {
+                Some(kern) => parse_kern(&data, *kern)?,
+                None => HashMap::new(),
+            },
+            advances,
+            loca,
+            glyf: table(b"glyf")?,
+            data,
+        })
+    }
+
+    /// Gets the glyph of c, if the font has one
+    pub fn glyph_index(&self, c: char) -> Option<u16> {
+        self.cmap.get(&c).copied()
+    }
+
+    /// Gets every char the font has a glyph for, in no particular order
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.cmap.keys().copied()
+    }
+
+    /// Gets how many glyphs there are
+    pub fn glyph_count(&self) -> usize {
+        self.advances.len()
+    }
+
+    /// Gets how much the pen moves after glyph(in font units)
+    pub fn advance(&self, glyph: u16) -> f32 {
+        self.advances.get(glyph as usize).copied().unwrap_or(0.0)
+    }
+
+    /// Gets the kerning between the glyphs left and right(in font units)
+    pub fn kerning(&self, left: u16, right: u16) -> f32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0.0)
+    }
+
+    /// Gets every pair of glyphs that has kerning with its kerning(in font units)
+    pub fn kerning_pairs(&self) -> impl Iterator<Item = ((u16, u16), f32)> + '_ {
+        self.kerning.iter().map(|(pair, kerning)| (*pair, *kerning))
+    }
+
+    /// Gets the distance between two lines(in font units)
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+
+    /// Gets the outline of glyph as closed contours(in font units, y goes up)
+    ///
+    /// The curves are split into lines that are at most tolerance away from them, the last
+    /// point of a contour connects back to the first one. Outer contours go clockwise and
+    /// holes go counter clockwise
+    pub fn outline(&self, glyph: u16, tolerance: f32) -> Result<Vec<Vec<Vec2>>, LighthouseError> {
+        let mut contours = Vec::new();
+        self.glyph_points(glyph, 0, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], &mut contours)?;
+        Ok(contours
+            .iter()
+            .map(|contour| flatten(contour, tolerance.max(f32::EPSILON)))
+            .filter(|contour| contour.len() > 2)
+            .collect())
+    }
+
+    /// Reads the points of glyph transformed by transform, whether they are on the curve
+    /// and adds them to contours
+    ///
+    /// transform is a 2x2 matrix and an offset, x' = a * x + c * y + e, y' = b * x + d * y + f
+    fn glyph_points(
+        &self,
+        glyph: u16,
+        depth: u32,
+        transform: [f32; 6],
+        contours: &mut Vec<Vec<(Vec2, bool)>>,
+    ) -> Result<(), LighthouseError> {
+        let glyph = glyph as usize;
+        if glyph + 1 >= self.loca.len() {
+            return Err(invalid(format!("The glyph {} doesn't exist", glyph)));
+        }
+        let (start, end) = (
+            self.glyf + self.loca[glyph],
+            self.glyf + self.loca[glyph + 1],
+        );
+        // glyphs like space have no outline
+        if end <= start {
+            return Ok(());
+        }
+        let data = &self.data;
+        let [a, b, c, d, e, f] = transform;
+        let apply = |x: f32, y: f32| vec2(a * x + c * y + e, b * x + d * y + f);
+
+        let contour_count = i16_at(data, start)?;
+        if contour_count >= 0 {
+            let contour_count = contour_count as usize;
+            let ends = (0..contour_count)
+                .map(|i| u16_at(data, start + 10 + i * 2).map(usize::from))
+                .collect::<Result<Vec<_>, _>>()?;
+            // every point is looked up by the ends, so they have to go up
+            if ends.windows(2).any(|pair| pair[1] <= pair[0]) {
+                return Err(invalid("A glyph has contours out of order"));
+            }
+            let point_count = ends.last().map_or(0, |end| end + 1);
+            let instructions = u16_at(data, start + 10 + contour_count * 2)? as usize;
+            let mut pos = start + 12 + contour_count * 2 + instructions;
+
+            let mut flags = Vec::with_capacity(point_count);
+            while flags.len() < point_count {
+                let [flag] = read::<1>(data, pos)?;
+                pos += 1;
+                flags.push(flag);
+                if flag & 0x08 != 0 {
+                    let [repeat] = read::<1>(data, pos)?;
+                    pos += 1;
+                    flags.extend(std::iter::repeat_n(flag, repeat as usize));
+                }
+            }
+            flags.truncate(point_count);
+            let xs = read_coords(data, &mut pos, &flags, 0x02, 0x10)?;
+            let ys = read_coords(data, &mut pos, &flags, 0x04, 0x20)?;
+
+            let mut first = 0;
+            for end in ends {
+                contours.push(
+                    (first..=end)
+                        .map(|i| (apply(xs[i], ys[i]), flags[i] & 0x01 != 0))
+                        .collect(),
+                );
+                first = end + 1;
+            }
+            return Ok(());
+        }
+
+        if depth >= MAX_COMPOSITE_DEPTH {
+            return Err(invalid("A composite glyph is nested too deep"));
+        }
+        let mut pos = start + 10;
+        loop {
+            let flags = u16_at(data, pos)?;
+            let component = u16_at(data, pos + 2)?;
+            pos += 4;
+            let (dx, dy) = if flags & 0x0001 != 0 {
+                pos += 4;
+                (i16_at(data, pos - 4)? as f32, i16_at(data, pos - 2)? as f32)
+            } else {
+                pos += 2;
+                let [dx, dy] = read::<2>(data, pos - 2)?;
+                (dx as i8 as f32, dy as i8 as f32)
+            };
+            // components placed by matching points aren't supported, they aren't moved
+            let (dx, dy) = match flags & 0x0002 != 0 {
+                true => (dx, dy),
+                false => (0.0, 0.0),
+            };
+            let [ma, mb, mc, md] = if flags & 0x0008 != 0 {
+                pos += 2;
+                let scale = f2dot14_at(data, pos - 2)?;
+                [scale, 0.0, 0.0, scale]
+            } else if flags & 0x0040 != 0 {
+                pos += 4;
+                [
+                    f2dot14_at(data, pos - 4)?,
+                    0.0,
+                    0.0,
+                    f2dot14_at(data, pos - 2)?,
+                ]
+            } else if flags & 0x0080 != 0 {
+                pos += 8;
+                [
+                    f2dot14_at(data, pos - 8)?,
+                    f2dot14_at(data, pos - 6)?,
+                    f2dot14_at(data, pos - 4)?,
+                    f2dot14_at(data, pos - 2)?,
+                ]
+            } else {
+                [1.0, 0.0, 0.0, 1.0]
+            };
+
+            let combined = [
+                a * ma + c * mb,
+                b * ma + d * mb,
+                a * mc + c * md,
+                b * mc + d * md,
+                a * dx + c * dy + e,
+                b * dx + d * dy + f,
+            ];
+            self.glyph_points(component, depth + 1, combined, contours)?;
+            if flags & 0x0020 == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Asset for TrueType {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self, AssetError> {
+        TrueType::parse(bytes).map_err(|e| AssetError::Decode(e.to_string()))
+    }
+}
+
+/// A glyph rasterized by [rasterize]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphBitmap {
+    /// The width of the bitmap(in pixels)
+    pub width: u32,
+    /// The height of the bitmap(in pixels)
+    pub height: u32,
+    /// Where the left of the bitmap is relative to the pen(in pixels)
+    pub left: i32,
+    /// How far the top of the bitmap is above the baseline(in pixels)
+    pub top: i32,
+    /// How much of every pixel is covered by the glyph, row by row from the top
+    pub coverage: Vec<u8>,
+}
+
+/// Rasterizes the contours of a glyph from [TrueType::outline] scaled by scale, usually
+/// the size in pixels divided by [TrueType::units_per_em]
+///
+/// The edges are anti aliased by how much of every pixel the glyph covers
+pub fn rasterize(contours: &[Vec<Vec2>], scale: f32) -> GlyphBitmap {
+    let points = contours.iter().flatten().map(|point| point * scale);
+    let (min, max) = points.fold(
+        (vec2(f32::MAX, f32::MAX), vec2(f32::MIN, f32::MIN)),
+        |(min, max), point| (min.inf(&point), max.sup(&point)),
+    );
+    if min.x > max.x {
+        return GlyphBitmap {
+            width: 0,
+            height: 0,
+            left: 0,
+            top: 0,
+            coverage: Vec::new(),
+        };
+    }
+    let (left, top) = (min.x.floor() as i32, max.y.ceil() as i32);
+    let width = (max.x.ceil() as i32 - left).max(1) as usize;
+    let height = (top - min.y.floor() as i32).max(1) as usize;
+
+    // the bitmap goes down from the top left, the outline goes up from the baseline
+    let to_pixel = |point: &Vec2| {
+        vec2(
+            (point.x * scale - left as f32).clamp(0.0, width as f32),
+            (top as f32 - point.y * scale).clamp(0.0, height as f32),
+        )
+    };
+    // a bit extra at the end because lines on the right edge write one past the row
+    let mut accumulation = vec![0.0; width * height + 4];
+    for contour in contours {
+        for (i, point) in contour.iter().enumerate() {
+            let next = &contour[(i + 1) % contour.len()];
+            draw_line(
+                &mut accumulation,
+                width,
+                height,
+                to_pixel(point),
+                to_pixel(next),
+            );
+        }
+    }
+
+    let mut sum = 0.0;
+    let coverage = accumulation[..width * height]
+        .iter()
+        .map(|area| {
+            sum += area;
+            (sum.abs().min(1.0) * 255.0).round() as u8
+        })
+        .collect();
+    GlyphBitmap {
+        width: width as u32,
+        height: height as u32,
+        left,
+        top,
+        coverage,
+    }
+}
+
+/// Adds how much of every pixel is covered to the right of the line from p0 to p1, the
+/// running sum of a row is then the coverage
+fn draw_line(accumulation: &mut [f32], width: usize, height: usize, p0: Vec2, p1: Vec2) {
+    if (p0.y - p1.y).abs() <= f32::EPSILON {
+        return;
+    }
+    let (dir, p0, p1) = match p0.y < p1.y {
+        true => (1.0, p0, p1),
+        false => (-1.0, p1, p0),
+    };
+    let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+    let mut x = p0.x;
+    for y in p0.y as usize..height.min(p1.y.ceil() as usize) {
+        let row = &mut accumulation[y * width..];
+        let dy = ((y + 1) as f32).min(p1.y) - (y as f32).max(p0.y);
+        let x_next = x + dxdy * dy;
+        let d = dy * dir;
+        let (x0, x1) = match x < x_next {
+            true => (x, x_next),
+            false => (x_next, x),
+        };
+        let (x0_floor, x1_ceil) = (x0.floor(), x1.ceil());
+        let (x0i, x1i) = (x0_floor as usize, x1_ceil as usize);
+        if x1i <= x0i + 1 {
+            // the line stays in one pixel of the row
+            let middle = 0.5 * (x + x_next) - x0_floor;
+            row[x0i] += d - d * middle;
+            row[x0i + 1] += d * middle;
+        } else {
+            let s = (x1 - x0).recip();
+            let x0f = x0 - x0_floor;
+            let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+            let x1f = x1 - x1_ceil + 1.0;
+            let am = 0.5 * s * x1f * x1f;
+            row[x0i] += d * a0;
+            if x1i == x0i + 2 {
+                row[x0i + 1] += d * (1.0 - a0 - am);
+            } else {
+                let a1 = s * (1.5 - x0f);
+                row[x0i + 1] += d * (a1 - a0);
+                for area in &mut row[x0i + 2..x1i - 1] {
+                    *area += d * s;
+                }
+                let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                row[x1i - 1] += d * (1.0 - a2 - am);
+            }
+            row[x1i] += d * am;
+        }
+        x = x_next;
+    }
+}
+
+/// Turns the points of a contour into lines, off curve points are the control points of
+/// quadratic curves and two of them in a row have an on curve point between them
+fn flatten(points: &[(Vec2, bool)], tolerance: f32) -> Vec<Vec2> {
+    let mut expanded = Vec::with_capacity(points.len() * 2);
+    for (i, (point, on)) in points.iter().enumerate() {
+        expanded.push((*point, *on));
+        let (next, next_on) = points[(i + 1) % points.len()];
+        if !on && !next_on {
+            expanded.push(((point + next) / 2.0, true));
+        }
+    }
+    let first = match expanded.iter().position(|(_, on)| *on) {
+        Some(first) => first,
+        None => return Vec::new(),
+    };
+    expanded.rotate_left(first);
+
+    let mut out = vec![expanded[0].0];
+    let mut i = 1;
+    while i <= expanded.len() {
+        let (point, on) = expanded[i % expanded.len()];
+        if on {
+            out.push(point);
+            i += 1;
+            continue;
+        }
+        let (start, end) = (*out.last().unwrap(), expanded[(i + 1) % expanded.len()].0);
+        // how far the curve is from a line is a quarter of this
+        let bend = (start - point * 2.0 + end).norm();
+        let steps = ((bend / (8.0 * tolerance)).sqrt().ceil() as usize).clamp(1, 64);
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            out.push(start * (1.0 - t) * (1.0 - t) + point * 2.0 * t * (1.0 - t) + end * t * t);
+        }
+        i += 2;
+    }
+    // the contour ended where it started
+    out.pop();
+    out
+}
+
+/// Reads the x or y coordinates of the points of a simple glyph, they are stored as
+/// differences from the previous point
+fn read_coords(
+    data: &[u8],
+    pos: &mut usize,
+    flags: &[u8],
+    short: u8,
+    same: u8,
+) -> Result<Vec<f32>, LighthouseError> {
+    let mut value = 0_i32;
+    let mut out = Vec::with_capacity(flags.len());
+    for flag in flags {
+        if flag & short != 0 {
+            let [delta] = read::<1>(data, *pos)?;
+            *pos += 1;
+            // for short coordinates the same bit is the sign
+            value += match flag & same != 0 {
+                true => delta as i32,
+                false => -(delta as i32),
+            };
+        } else if flag & same == 0 {
+            value += i16_at(data, *pos)? as i32;
+            *pos += 2;
+        }
+        out.push(value as f32);
+    }
+    Ok(out)
+}
+
+/// Reads the best subtable of the cmap table, the one mapping the most chars
+fn parse_cmap(
+    data: &[u8],
+    cmap: usize,
+    glyph_count: usize,
+) -> Result<HashMap<char, u16>, LighthouseError> {
+    let mut best = None;
+    for i in 0..u16_at(data, cmap + 2)? as usize {
+        let record = cmap + 4 + i * 8;
+        let platform = u16_at(data, record)?;
+        let encoding = u16_at(data, record + 2)?;
+        let subtable = cmap + u32_at(data, record + 4)? as usize;
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        let rank = match u16_at(data, subtable)? {
+            12 if unicode => 2,
+            4 if unicode => 1,
+            _ => continue,
+        };
+        if best.is_none_or(|(best_rank, _)| rank > best_rank) {
+            best = Some((rank, subtable));
+        }
+    }
+    let (_, subtable) = best
+        .ok_or_else(|| LighthouseError::Unsupported("The font has no unicode cmap".to_string()))?;
+
+    let mut chars = HashMap::new();
+    let mut insert = |code: u32, glyph: u32| {
+        if glyph != 0 && (glyph as usize) < glyph_count {
+            if let Some(c) = char::from_u32(code) {
+                chars.insert(c, glyph as u16);
+            }
+        }
+    };
+
+    if u16_at(data, subtable)? == 12 {
+        for i in 0..u32_at(data, subtable + 12)? as usize {
+            let group = subtable + 16 + i * 12;
+            let start = u32_at(data, group)?;
+            let end = u32_at(data, group + 4)?.min(char::MAX as u32);
+            let first_glyph = u32_at(data, group + 8)?;
+            for code in start..=end {
+                // the glyphs after the last one aren't inserted anyway, and adding up to them
+                // could overflow
+                match first_glyph.checked_add(code - start) {
+                    Some(glyph) if (glyph as usize) < glyph_count => insert(code, glyph),
+                    _ => break,
+                }
+            }
+        }
+        return Ok(chars);
+    }
+
+    let segments = u16_at(data, subtable + 6)? as usize / 2;
+    let ends = subtable + 14;
+    let starts = ends + segments * 2 + 2;
+    let deltas = starts + segments * 2;
+    let ranges = deltas + segments * 2;
+    for i in 0..segments {
+        let end = u16_at(data, ends + i * 2)?;
+        let start = u16_at(data, starts + i * 2)?;
+        let delta = u16_at(data, deltas + i * 2)?;
+        let range = u16_at(data, ranges + i * 2)? as usize;
+        for code in start..=end {
+            let glyph = match range {
+                0 => code.wrapping_add(delta),
+                // the offset is relative to where it is stored
+                _ => match u16_at(data, ranges + i * 2 + range + (code - start) as usize * 2)? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(delta),
+                },
+            };
+            insert(code as u32, glyph as u32);
+        }
+    }
+    Ok(chars)
+}
+
+/// Reads the horizontal pairs of the kern table
+fn parse_kern(data: &[u8], kern: usize) -> Result<HashMap<(u16, u16), f32>, LighthouseError> {
+    let mut kerning = HashMap::new();
+    // apple fonts have a different version of the table
+    if u16_at(data, kern)? != 0 {
+        return Ok(kerning);
+    }
+    let mut subtable = kern + 4;
+    for _ in 0..u16_at(data, kern + 2)? {
+        let length = u16_at(data, subtable + 2)? as usize;
+        let coverage = u16_at(data, subtable + 4)?;
+        // format 0, horizontal and not cross stream
+        if coverage >> 8 == 0 && coverage & 0x0005 == 0x0001 {
+            for i in 0..u16_at(data, subtable + 6)? as usize {
+                let pair = subtable + 14 + i * 6;
+                kerning.insert(
+                    (u16_at(data, pair)?, u16_at(data, pair + 2)?),
+                    i16_at(data, pair + 4)? as f32,
+                );
+            }
+        }
+        subtable += length.max(6);
+    }
+    Ok(kerning)
+}
+
+/// Makes the error of a malformed font
+fn invalid(message: impl Into<String>) -> LighthouseError {
+    LighthouseError::Parse(format!("Invalid TrueType font: {}", message.into()))
+}
+
+fn read<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N], LighthouseError> {
+    data.get(offset..offset + N)
+        .map(|bytes| bytes.try_into().unwrap())
+        .ok_or_else(|| invalid("The file ends early"))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, LighthouseError> {
+    read(data, offset).map(u16::from_be_bytes)
+}
+
+fn i16_at(data: &[u8], offset: usize) -> Result<i16, LighthouseError> {
+    read(data, offset).map(i16::from_be_bytes)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, LighthouseError> {
+    read(data, offset).map(u32::from_be_bytes)
+}
+
+/// Reads a 2.14 fixed point number
+fn f2dot14_at(data: &[u8], offset: usize) -> Result<f32, LighthouseError> {
+    i16_at(data, offset).map(|value| value as f32 / 16384.0)
+}
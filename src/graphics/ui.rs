@@ -0,0 +1,343 @@
+use std::{collections::HashMap, mem::size_of};
+
+use nalgebra_glm::Vec2;
+
+use super::{
+    buffer::*,
+    render_state::RenderState,
+    shader::ShaderProgram,
+    text::{Font, GlyphRun},
+    texture::*,
+    uniform::Uniform,
+    vertex::VertexArray,
+    *,
+};
+
+const UI_VERT: &str = "#version 330 core
+layout (location = 0) in vec4 rect;
+layout (location = 1) in vec4 uv_rect;
+layout (location = 2) in vec4 color;
+
+uniform vec2 screen_size;
+
+out vec2 uv;
+out vec4 tint;
+
+void main() {
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    vec2 pos = rect.xy + corner * rect.zw;
+    uv = mix(uv_rect.xy, uv_rect.zw, corner);
+    tint = color;
+    gl_Position = vec4(pos / screen_size * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+}";
+
+const UI_FRAG: &str = "#version 330 core
+in vec2 uv;
+in vec4 tint;
+
+uniform sampler2D tex;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = texture(tex, uv) * tint;
+}";
+
+/// A textured and tinted quad, it is one instance of the instanced UI draw
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuadInstance {
+    /// x, y, width and height of the quad(in pixels from the top left of the screen)
+    pub rect: [f32; 4],
+    /// The uv rectangle, top left then bottom right
+    pub uv: [f32; 4],
+    /// The color the texture is multiplied by
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for QuadInstance {}
+unsafe impl bytemuck::Pod for QuadInstance {}
+
+/// An element of the [UiBatch]
+struct UiElement {
+    /// The texture the quads use
+    texture: u32,
+    /// The quads of the element
+    quads: Vec<QuadInstance>,
+    /// When elements overlap the one with the higher layer is drawn on top
+    layer: i32,
+    /// When the element was added, elements of a layer with the same texture are drawn in
+    /// the order they were added
+    order: u64,
+}
+
+/// A group of quads drawn with a single instanced draw call
+struct UiDraw {
+    texture: u32,
+    first: usize,
+    count: usize,
+}
+
+/// Retained UI renderer, every UI element and string is batched into as few instanced
+/// draw calls as possible
+///
+/// Elements are kept between frames and identified by a name. Setting an element to what
+/// it already is does nothing, so when nothing changed a frame only costs the draw calls
+/// and nothing is uploaded
+///
+/// Elements of the same layer and texture are drawn in the order they were first set, so
+/// overlapping elements don't swap between frames. To be sure one element is drawn over
+/// another give it a higher layer
+///
+/// # Example
+/// ```
+/// let mut ui = UiBatch::new()?;
+///
+/// 'main_loop: loop {
+///     ui.rect("health_bar", vec2(10.0, 10.0), vec2(200.0 * health, 20.0), [1.0, 0.0, 0.0, 1.0], 0);
+///     ui.text("score", &font, &format!("Score: {}", score), vec2(10.0, 40.0), 1.0, [1.0; 4], 1);
+///     ui.draw(world.env.win_size);
+/// }
+/// ```
+pub struct UiBatch {
+    /// The UI shader program
+    pub program: ShaderProgram,
+    elements: HashMap<String, UiElement>,
+    /// The order of the next element that is added
+    next_order: u64,
+    /// Whether the elements changed since the last upload
+    dirty: bool,
+    /// The draw calls made from the last upload
+    draws: Vec<UiDraw>,
+    /// 1x1 white texture used for plain colored quads
+    white: Texture,
+    vao: VertexArray,
+    vbo: Buffer,
+}
+
+impl UiBatch {
+    /// Creates a new empty UI batch
+    pub fn new() -> Result<Self, LighthouseError> {
+        let white = Texture::builder(ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([255, 255, 255, 255]),
+        )))
+        .filter(TextureFilter::Nearest, TextureFilter::Nearest)
+        .build()?;
+
+        let batch = UiBatch {
+            program: ShaderProgram::from_vert_frag(UI_VERT, UI_FRAG)?,
+            elements: HashMap::new(),
+            next_order: 0,
+            dirty: false,
+            draws: Vec::new(),
+            white,
+            vao: VertexArray::new()
+                .ok_or_else(|| LighthouseError::BufferAlloc("a VAO".to_string()))?,
+            vbo: Buffer::new().ok_or_else(|| LighthouseError::BufferAlloc("a VBO".to_string()))?,
+        };
+
+        batch.vao.bind();
+        batch.vbo.bind(BufferType::Array);
+        for location in 0..3 {
+            unsafe {
+                glEnableVertexAttribArray(location);
+                glVertexAttribDivisor(location, 1);
+            }
+        }
+        VertexArray::clear_binding();
+
+        Ok(batch)
+    }
+
+    /// Sets the quads of an element, the element is created if it doesnt exist
+    ///
+    /// texture is the id of the texture the quads use
+    pub fn set(&mut self, name: &str, texture: u32, quads: &[QuadInstance], layer: i32) {
+        if let Some(element) = self.elements.get_mut(name) {
+            if element.texture == texture && element.layer == layer && element.quads == quads {
+                return;
+            }
+            element.texture = texture;
+            element.layer = layer;
+            element.quads.clear();
+            element.quads.extend_from_slice(quads);
+        } else {
+            self.elements.insert(
+                name.to_string(),
+                UiElement {
+                    texture,
+                    quads: quads.to_vec(),
+                    layer,
+                    order: self.next_order,
+                },
+            );
+            self.next_order += 1;
+        }
+        self.dirty = true;
+    }
+
+    /// Sets an element to a plain colored rectangle
+    pub fn rect(&mut self, name: &str, pos: Vec2, size: Vec2, color: [f32; 4], layer: i32) {
+        let quad = QuadInstance {
+            rect: [pos.x, pos.y, size.x, size.y],
+            uv: [0.0, 1.0, 1.0, 0.0],
+            color,
+        };
+        self.set(name, self.white.id, &[quad], layer);
+    }
+
+    /// Sets an element to plain colored rectangles as pos, size and color, the later ones
+    /// are drawn on top of the earlier ones
+    pub fn rects(&mut self, name: &str, rects: &[(Vec2, Vec2, [f32; 4])], layer: i32) {
+        let quads: Vec<QuadInstance> = rects
+            .iter()
+            .map(|(pos, size, color)| QuadInstance {
+                rect: [pos.x, pos.y, size.x, size.y],
+                uv: [0.0, 1.0, 1.0, 0.0],
+                color: *color,
+            })
+            .collect();
+        self.set(name, self.white.id, &quads, layer);
+    }
+
+    /// Sets an element to an already shaped string
+    pub fn glyph_run(
+        &mut self,
+        name: &str,
+        font: &Font,
+        run: &GlyphRun,
+        pos: Vec2,
+        color: [f32; 4],
+        layer: i32,
+    ) {
+        let quads: Vec<QuadInstance> = run.placed(pos, color).collect();
+        self.set(name, font.texture.id, &quads, layer);
+    }
+
+    /// Sets an element to a string, the string is shaped with [Font::shape] so it is cached
+    #[allow(clippy::too_many_arguments)]
+    pub fn text(
+        &mut self,
+        name: &str,
+        font: &Font,
+        text: &str,
+        pos: Vec2,
+        scale: f32,
+        color: [f32; 4],
+        layer: i32,
+    ) {
+        let run = font.shape(text, scale);
+        self.glyph_run(name, font, &run, pos, color, layer);
+    }
+
+    /// Removes an element
+    pub fn remove(&mut self, name: &str) {
+        if self.elements.remove(name).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Removes every element
+    pub fn clear(&mut self) {
+        if !self.elements.is_empty() {
+            self.elements.clear();
+            self.dirty = true;
+        }
+    }
+
+    /// Gets how many draw calls the last [UiBatch::draw] made
+    pub fn draw_calls(&self) -> usize {
+        self.draws.len()
+    }
+
+    /// Draws every element on top of the screen, the quads are only uploaded again if an
+    /// element changed
+    pub fn draw(&mut self, screen_size: Vec2) {
+        if self.dirty {
+            self.upload();
+        }
+        if self.draws.is_empty() {
+            return;
+        }
+
+        self.program.use_program();
+        Uniform::new(&self.program, "screen_size").set_uniform_f(&[screen_size.x, screen_size.y]);
+        Uniform::new(&self.program, "tex").set_uniform_i(&[0]);
+
+        self.vao.bind();
+        self.vbo.bind(BufferType::Array);
+        unsafe {
+            let depth_test = glIsEnabled(GL_DEPTH_TEST) == GL_TRUE;
+            let blend = glIsEnabled(GL_BLEND) == GL_TRUE;
+            glDisable(GL_DEPTH_TEST);
+            glEnable(GL_BLEND);
+            glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+            glActiveTexture(GL_TEXTURE0);
+
+            let stride = size_of::<QuadInstance>();
+            for draw in &self.draws {
+                let offset = draw.first * stride;
+                for location in 0..3 {
+                    glVertexAttribPointer(
+                        location,
+                        4,
+                        GL_FLOAT,
+                        GL_FALSE,
+                        stride as i32,
+                        (offset + location as usize * size_of::<[f32; 4]>()) as *const _,
+                    );
+                }
+                glBindTexture(GL_TEXTURE_2D, draw.texture);
+                glDrawArraysInstanced(GL_TRIANGLE_STRIP, 0, 4, draw.count as i32);
+            }
+
+            if depth_test {
+                glEnable(GL_DEPTH_TEST);
+            }
+            if !blend {
+                glDisable(GL_BLEND);
+            }
+        }
+        // the blend function isn't put back how it was
+        RenderState::invalidate();
+        VertexArray::clear_binding();
+    }
+
+    /// Sorts the elements into draw calls and uploads their quads
+    fn upload(&mut self) {
+        let mut elements: Vec<&UiElement> = self.elements.values().collect();
+        // layers have to be drawn in order, inside a layer the elements are grouped by
+        // texture and the order they were added keeps the order the same every frame
+        elements.sort_by_key(|element| (element.layer, element.texture, element.order));
+
+        let mut quads = Vec::new();
+        self.draws.clear();
+        for element in elements {
+            match self.draws.last_mut() {
+                Some(draw) if draw.texture == element.texture => draw.count += element.quads.len(),
+                _ => self.draws.push(UiDraw {
+                    texture: element.texture,
+                    first: quads.len(),
+                    count: element.quads.len(),
+                }),
+            }
+            quads.extend_from_slice(&element.quads);
+        }
+
+        self.vbo.bind(BufferType::Array);
+        buffer_data(
+            BufferType::Array,
+            bytemuck::cast_slice(&quads),
+            BufferUsage::DynamicDraw,
+        );
+        self.dirty = false;
+    }
+
+    /// Deletes the shader program and textures of the batch
+    pub fn delete(self) {
+        self.white.delete();
+        self.program.delete();
+    }
+}
@@ -1,15 +1,261 @@
-use super::{shader::*, *};
+use nalgebra_glm::{IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3, UVec4, Vec2, Vec3, Vec4};
+
+use super::{buffer::BufferUsage, recovery::ContextId, shader::*, *};
+
+/// A value a [Uniform] can be set to, so the right `glUniform*` function is picked for you
+///
+/// Most glm types, numbers and vecs of them turn into one with [Into]
+///
+/// # Example
+/// ```
+/// Uniform::new(&program, "light_pos").set(vec3(0.0, 4.0, 0.0));
+/// Uniform::new(&program, "bones").set(bone_matrices);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniformValue {
+    /// A `float`
+    Float(f32),
+    /// A `vec2`
+    Vec2(Vec2),
+    /// A `vec3`
+    Vec3(Vec3),
+    /// A `vec4`
+    Vec4(Vec4),
+    /// An `int`, also what samplers are set with
+    Int(i32),
+    /// An `ivec2`
+    IVec2(IVec2),
+    /// An `ivec3`
+    IVec3(IVec3),
+    /// An `ivec4`
+    IVec4(IVec4),
+    /// A `uint`
+    UInt(u32),
+    /// A `uvec2`
+    UVec2(UVec2),
+    /// A `uvec3`
+    UVec3(UVec3),
+    /// A `uvec4`
+    UVec4(UVec4),
+    /// A `bool`
+    Bool(bool),
+    /// A `mat2`
+    Mat2(Mat2),
+    /// A `mat3`
+    Mat3(Mat3),
+    /// A `mat4`
+    Mat4(Mat4),
+    /// A `float[]`
+    FloatArray(Vec<f32>),
+    /// A `vec2[]`
+    Vec2Array(Vec<Vec2>),
+    /// A `vec3[]`
+    Vec3Array(Vec<Vec3>),
+    /// A `vec4[]`
+    Vec4Array(Vec<Vec4>),
+    /// An `int[]`
+    IntArray(Vec<i32>),
+    /// A `uint[]`
+    UIntArray(Vec<u32>),
+    /// A `mat2[]`
+    Mat2Array(Vec<Mat2>),
+    /// A `mat3[]`
+    Mat3Array(Vec<Mat3>),
+    /// A `mat4[]`
+    Mat4Array(Vec<Mat4>),
+}
+
+/// Implements From for [UniformValue] for every type and variant given
+macro_rules! impl_uniform_value {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for UniformValue {
+                fn from(value: $ty) -> Self {
+                    UniformValue::$variant(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_uniform_value!(
+    f32 => Float,
+    Vec2 => Vec2,
+    Vec3 => Vec3,
+    Vec4 => Vec4,
+    i32 => Int,
+    IVec2 => IVec2,
+    IVec3 => IVec3,
+    IVec4 => IVec4,
+    u32 => UInt,
+    UVec2 => UVec2,
+    UVec3 => UVec3,
+    UVec4 => UVec4,
+    bool => Bool,
+    Mat2 => Mat2,
+    Mat3 => Mat3,
+    Mat4 => Mat4,
+    Vec<f32> => FloatArray,
+    Vec<Vec2> => Vec2Array,
+    Vec<Vec3> => Vec3Array,
+    Vec<Vec4> => Vec4Array,
+    Vec<i32> => IntArray,
+    Vec<u32> => UIntArray,
+    Vec<Mat2> => Mat2Array,
+    Vec<Mat3> => Mat3Array,
+    Vec<Mat4> => Mat4Array,
+);
+
+/// Something that is set as a glsl struct, with one uniform per field
+///
+/// # Example
+/// ```
+/// struct PointLight {
+///     pos: Vec3,
+///     color: Vec3,
+/// }
+///
+/// impl UniformStruct for PointLight {
+///     fn fields(&self) -> Vec<(&'static str, UniformValue)> {
+///         vec![("pos", self.pos.into()), ("color", self.color.into())]
+///     }
+/// }
+///
+/// // sets `lights[0].pos`, `lights[0].color`, `lights[1].pos`...
+/// Uniform::set_struct_array(&program, "lights", &lights);
+/// ```
+pub trait UniformStruct {
+    /// Gets the names of the fields in glsl and their values
+    fn fields(&self) -> Vec<(&'static str, UniformValue)>;
+}
 
 /// A [Uniform object](https://www.khronos.org/opengl/wiki/Uniform_(GLSL))
 pub struct Uniform(pub i32);
 impl Uniform {
     /// Creates a new uniform
+    ///
+    /// Fields of structs and elements of arrays are found with their glsl names, like
+    /// `light.color` and `lights[2].color`
     pub fn new(program: &ShaderProgram, name: &str) -> Self {
+        with_cstr(name, |name| unsafe {
+            Self(glGetUniformLocation(program.0, name.as_ptr().cast()))
+        })
+    }
+
+    /// Finds the field called field of the struct uniform called name
+    pub fn field(program: &ShaderProgram, name: &str, field: &str) -> Self {
+        Uniform::new(program, &format!("{}.{}", name, field))
+    }
+
+    /// Finds the element at index of the array uniform called name
+    pub fn element(program: &ShaderProgram, name: &str, index: usize) -> Self {
+        Uniform::new(program, &format!("{}[{}]", name, index))
+    }
+
+    /// Checks if the uniform was found, uniforms the compiler removed because they aren't
+    /// used aren't found either. Setting a uniform that wasn't found does nothing
+    pub fn exists(&self) -> bool {
+        self.0 != -1
+    }
+
+    /// Sets the uniform to value, the program has to be in use
+    ///
+    /// Arrays are set from the element the uniform points at
+    pub fn set(&self, value: impl Into<UniformValue>) {
+        let location = self.0;
+        let count = |len: usize| len.try_into().unwrap();
         unsafe {
-            Self(glGetUniformLocation(
-                program.0,
-                to_cstr(name).as_ptr().cast(),
-            ))
+            match value.into() {
+                UniformValue::Float(value) => glUniform1f(location, value),
+                UniformValue::Vec2(value) => glUniform2fv(location, 1, value.as_ptr()),
+                UniformValue::Vec3(value) => glUniform3fv(location, 1, value.as_ptr()),
+                UniformValue::Vec4(value) => glUniform4fv(location, 1, value.as_ptr()),
+                UniformValue::Int(value) => glUniform1i(location, value),
+                UniformValue::IVec2(value) => glUniform2iv(location, 1, value.as_ptr()),
+                UniformValue::IVec3(value) => glUniform3iv(location, 1, value.as_ptr()),
+                UniformValue::IVec4(value) => glUniform4iv(location, 1, value.as_ptr()),
+                UniformValue::UInt(value) => glUniform1ui(location, value),
+                UniformValue::UVec2(value) => glUniform2uiv(location, 1, value.as_ptr()),
+                UniformValue::UVec3(value) => glUniform3uiv(location, 1, value.as_ptr()),
+                UniformValue::UVec4(value) => glUniform4uiv(location, 1, value.as_ptr()),
+                UniformValue::Bool(value) => glUniform1i(location, value as i32),
+                // glm matrices are column major like opengl, so they are never transposed
+                UniformValue::Mat2(value) => glUniformMatrix2fv(location, 1, 0, value.as_ptr()),
+                UniformValue::Mat3(value) => glUniformMatrix3fv(location, 1, 0, value.as_ptr()),
+                UniformValue::Mat4(value) => glUniformMatrix4fv(location, 1, 0, value.as_ptr()),
+                UniformValue::FloatArray(values) => {
+                    glUniform1fv(location, count(values.len()), values.as_ptr())
+                }
+                UniformValue::Vec2Array(values) => {
+                    glUniform2fv(location, count(values.len()), values.as_ptr().cast())
+                }
+                UniformValue::Vec3Array(values) => {
+                    glUniform3fv(location, count(values.len()), values.as_ptr().cast())
+                }
+                UniformValue::Vec4Array(values) => {
+                    glUniform4fv(location, count(values.len()), values.as_ptr().cast())
+                }
+                UniformValue::IntArray(values) => {
+                    glUniform1iv(location, count(values.len()), values.as_ptr())
+                }
+                UniformValue::UIntArray(values) => {
+                    glUniform1uiv(location, count(values.len()), values.as_ptr())
+                }
+                UniformValue::Mat2Array(values) => {
+                    glUniformMatrix2fv(location, count(values.len()), 0, values.as_ptr().cast())
+                }
+                UniformValue::Mat3Array(values) => {
+                    glUniformMatrix3fv(location, count(values.len()), 0, values.as_ptr().cast())
+                }
+                UniformValue::Mat4Array(values) => {
+                    glUniformMatrix4fv(location, count(values.len()), 0, values.as_ptr().cast())
+                }
+            }
+        }
+    }
+
+    /// Sets the uniform as a vec2
+    pub fn set_vec2(&self, value: &Vec2) {
+        self.set(*value)
+    }
+
+    /// Sets the uniform as a vec3
+    pub fn set_vec3(&self, value: &Vec3) {
+        self.set(*value)
+    }
+
+    /// Sets the uniform as a vec4
+    pub fn set_vec4(&self, value: &Vec4) {
+        self.set(*value)
+    }
+
+    /// Sets the uniform as a mat2
+    pub fn set_mat2(&self, value: &Mat2) {
+        self.set(*value)
+    }
+
+    /// Sets the uniform as a mat3
+    pub fn set_mat3(&self, value: &Mat3) {
+        self.set(*value)
+    }
+
+    /// Sets the uniform as a mat4
+    pub fn set_mat4(&self, value: &Mat4) {
+        self.set(*value)
+    }
+
+    /// Sets every field of the struct uniform called name, the program has to be in use
+    pub fn set_struct(program: &ShaderProgram, name: &str, value: &impl UniformStruct) {
+        for (field, value) in value.fields() {
+            Uniform::field(program, name, field).set(value);
+        }
+    }
+
+    /// Sets every field of every element of the struct array uniform called name, the
+    /// program has to be in use
+    pub fn set_struct_array<T: UniformStruct>(program: &ShaderProgram, name: &str, values: &[T]) {
+        for (index, value) in values.iter().enumerate() {
+            Uniform::set_struct(program, &format!("{}[{}]", name, index), value);
         }
     }
 
@@ -171,3 +417,361 @@ impl Uniform {
         }
     }
 }
+
+/// Writes values with the [std140 layout](https://www.khronos.org/opengl/wiki/Interface_Block_(GLSL)#Memory_layout),
+/// the layout `layout (std140) uniform` blocks use, so the bytes match the block in glsl
+///
+/// Every value is padded to its std140 alignment before it is written, scalars to 4 bytes,
+/// vec2 to 8 and everything else to 16. Elements of arrays and columns of matrices are
+/// always padded to 16 bytes
+///
+/// # Example
+/// ```
+/// // layout (std140) uniform Camera {
+/// //     mat4 view_proj;
+/// //     vec3 pos;
+/// //     float time;
+/// // };
+/// let mut writer = Std140Writer::new();
+/// writer.mat4(&view_proj).vec3(&pos);
+/// let time_offset = writer.offset();
+/// writer.float(time);
+/// camera_buffer.set_data(&writer.finish());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Std140Writer {
+    bytes: Vec<u8>,
+}
+
+impl Std140Writer {
+    /// Creates a new empty writer
+    pub fn new() -> Self {
+        Std140Writer { bytes: Vec::new() }
+    }
+
+    /// Gets where the next value is written if it only needs to be aligned to 4 bytes(in
+    /// bytes), use it to remember where a value is for [UniformBuffer::update]
+    pub fn offset(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Pads the bytes so the next value starts at a multiple of align
+    pub fn align(&mut self, align: usize) -> &mut Self {
+        let len = self.bytes.len().next_multiple_of(align);
+        self.bytes.resize(len, 0);
+        self
+    }
+
+    /// Aligns to align and writes values
+    fn write(&mut self, align: usize, values: &[u8]) -> &mut Self {
+        self.align(align);
+        self.bytes.extend_from_slice(values);
+        self
+    }
+
+    /// Writes a float
+    pub fn float(&mut self, value: f32) -> &mut Self {
+        self.write(4, bytemuck::bytes_of(&value))
+    }
+
+    /// Writes an int
+    pub fn int(&mut self, value: i32) -> &mut Self {
+        self.write(4, bytemuck::bytes_of(&value))
+    }
+
+    /// Writes a uint
+    pub fn uint(&mut self, value: u32) -> &mut Self {
+        self.write(4, bytemuck::bytes_of(&value))
+    }
+
+    /// Writes a bool, they are 4 bytes in glsl
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.uint(value as u32)
+    }
+
+    /// Writes a vec2
+    pub fn vec2(&mut self, value: &Vec2) -> &mut Self {
+        self.write(8, bytemuck::cast_slice(value.as_slice()))
+    }
+
+    /// Writes a vec3, a float can be written right after it in the same 16 bytes
+    pub fn vec3(&mut self, value: &Vec3) -> &mut Self {
+        self.write(16, bytemuck::cast_slice(value.as_slice()))
+    }
+
+    /// Writes a vec4
+    pub fn vec4(&mut self, value: &Vec4) -> &mut Self {
+        self.write(16, bytemuck::cast_slice(value.as_slice()))
+    }
+
+    /// Writes a mat2, every column takes 16 bytes
+    pub fn mat2(&mut self, value: &Mat2) -> &mut Self {
+        for column in value.column_iter() {
+            self.write(16, bytemuck::cast_slice(column.as_slice()));
+        }
+        self.align(16)
+    }
+
+    /// Writes a mat3, every column takes 16 bytes
+    pub fn mat3(&mut self, value: &Mat3) -> &mut Self {
+        for column in value.column_iter() {
+            self.write(16, bytemuck::cast_slice(column.as_slice()));
+        }
+        self.align(16)
+    }
+
+    /// Writes a mat4
+    pub fn mat4(&mut self, value: &Mat4) -> &mut Self {
+        self.write(16, bytemuck::cast_slice(value.as_slice()))
+    }
+
+    /// Writes a float array, every element takes 16 bytes
+    pub fn float_array(&mut self, values: &[f32]) -> &mut Self {
+        for value in values {
+            self.align(16).float(*value);
+        }
+        self.align(16)
+    }
+
+    /// Writes a vec4 array
+    pub fn vec4_array(&mut self, values: &[Vec4]) -> &mut Self {
+        for value in values {
+            self.vec4(value);
+        }
+        self
+    }
+
+    /// Writes a mat4 array
+    pub fn mat4_array(&mut self, values: &[Mat4]) -> &mut Self {
+        for value in values {
+            self.mat4(value);
+        }
+        self
+    }
+
+    /// Writes a struct, structs start and end at a multiple of 16 bytes
+    ///
+    /// # Example
+    /// ```
+    /// for light in &lights {
+    ///     writer.structure(|writer| {
+    ///         writer.vec3(&light.pos).float(light.radius);
+    ///     });
+    /// }
+    /// ```
+    pub fn structure(&mut self, f: impl FnOnce(&mut Self)) -> &mut Self {
+        self.align(16);
+        f(self);
+        self.align(16)
+    }
+
+    /// Gets the bytes, padded to a multiple of 16 bytes like the size of a block
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align(16);
+        self.bytes
+    }
+}
+
+/// A [uniform buffer object](https://www.khronos.org/opengl/wiki/Uniform_Buffer_Object),
+/// a buffer that `uniform` blocks read from, so the same data can be used by many programs
+/// without setting it in every one of them
+///
+/// The buffer is bound to a binding point and the block of every program is pointed at the
+/// same binding point, see [UniformBindings]. The buffer is deleted when it is dropped,
+/// see [ContextId]
+///
+/// # Example
+/// ```
+/// let mut bindings = UniformBindings::new();
+/// let mut camera = UniformBuffer::new(80)?;
+/// camera.bind(bindings.point("Camera"));
+/// bindings.apply(&program);
+/// bindings.apply(&sky_program);
+///
+/// 'main_loop: loop {
+///     let mut writer = Std140Writer::new();
+///     writer.mat4(&view_proj).vec3(&pos);
+///     camera.update(0, &writer.finish())?;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct UniformBuffer {
+    id: u32,
+    size: usize,
+    usage: BufferUsage,
+    binding: Option<u32>,
+    context: ContextId,
+}
+
+impl UniformBuffer {
+    /// Creates a new buffer of size zeroed bytes that is changed often
+    pub fn new(size: usize) -> Result<Self, LighthouseError> {
+        UniformBuffer::with_data(&vec![0; size], BufferUsage::DynamicDraw)
+    }
+
+    /// Creates a new buffer with data
+    pub fn with_data(data: &[u8], usage: BufferUsage) -> Result<Self, LighthouseError> {
+        let mut id = 0;
+        unsafe { glGenBuffers(1, &mut id) };
+        if id == 0 {
+            return Err(LighthouseError::BufferAlloc(
+                "the uniform buffer".to_string(),
+            ));
+        }
+        let mut buffer = UniformBuffer {
+            id,
+            size: 0,
+            usage,
+            binding: None,
+            context: ContextId::current(),
+        };
+        buffer.set_data(data);
+        Ok(buffer)
+    }
+
+    /// Gets the opengl id of the buffer
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Gets the size of the buffer(in bytes)
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Gets the binding point the buffer was bound to last
+    pub fn binding(&self) -> Option<u32> {
+        self.binding
+    }
+
+    /// Replaces everything in the buffer with data, the size of the buffer becomes the size
+    /// of data
+    pub fn set_data(&mut self, data: &[u8]) {
+        unsafe {
+            glBindBuffer(GL_UNIFORM_BUFFER, self.id);
+            glBufferData(
+                GL_UNIFORM_BUFFER,
+                data.len().try_into().unwrap(),
+                data.as_ptr().cast(),
+                self.usage.into(),
+            );
+            glBindBuffer(GL_UNIFORM_BUFFER, 0);
+        }
+        self.size = data.len();
+    }
+
+    /// Writes data to the buffer starting at offset(in bytes), the rest stays the same
+    ///
+    /// Fails when data doesn't fit in the buffer
+    pub fn update(&self, offset: usize, data: &[u8]) -> Result<(), LighthouseError> {
+        if offset + data.len() > self.size {
+            return Err(LighthouseError::InvalidInput(format!(
+                "Can't write {} bytes at {} to a uniform buffer of {} bytes",
+                data.len(),
+                offset,
+                self.size
+            )));
+        }
+        unsafe {
+            glBindBuffer(GL_UNIFORM_BUFFER, self.id);
+            glBufferSubData(
+                GL_UNIFORM_BUFFER,
+                offset.try_into().unwrap(),
+                data.len().try_into().unwrap(),
+                data.as_ptr().cast(),
+            );
+            glBindBuffer(GL_UNIFORM_BUFFER, 0);
+        }
+        Ok(())
+    }
+
+    /// Binds the buffer to the binding point, every block pointed at it reads this buffer
+    pub fn bind(&mut self, binding: u32) {
+        unsafe { glBindBufferBase(GL_UNIFORM_BUFFER, binding, self.id) }
+        self.binding = Some(binding);
+    }
+
+    /// Points the block called name of program at the binding point
+    ///
+    /// Gives false if the program has no block called name
+    pub fn bind_block(program: &ShaderProgram, name: &str, binding: u32) -> bool {
+        let block = with_cstr(name, |name| unsafe {
+            glGetUniformBlockIndex(program.0, name.as_ptr().cast())
+        });
+        if block == GL_INVALID_INDEX {
+            return false;
+        }
+        unsafe { glUniformBlockBinding(program.0, block, binding) };
+        true
+    }
+
+    /// Gets how many binding points the gpu has, opengl 3.3 promises at least 36
+    pub fn max_bindings() -> u32 {
+        let mut max = 0;
+        unsafe { glGetIntegerv(GL_MAX_UNIFORM_BUFFER_BINDINGS, &mut max) };
+        max as u32
+    }
+
+    /// Deletes the buffer now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for UniformBuffer {
+    fn drop(&mut self) {
+        if self.context.is_current() {
+            unsafe { glDeleteBuffers(1, &self.id) }
+        }
+    }
+}
+
+/// Gives every uniform block name its own binding point, so blocks with the same name use
+/// the same [UniformBuffer] in every program
+///
+/// The points are handed out from first, so points below it can be used by hand, e.g. by
+/// [ClusteredLights::bind](super::clustered::ClusteredLights::bind)
+#[derive(Debug, Clone, Default)]
+pub struct UniformBindings {
+    points: Vec<(String, u32)>,
+    first: u32,
+}
+
+impl UniformBindings {
+    /// Creates a new registry that hands out points from 0
+    pub fn new() -> Self {
+        UniformBindings::default()
+    }
+
+    /// Sets the first point that is handed out
+    pub fn first(mut self, first: u32) -> Self {
+        self.first = first;
+        self
+    }
+
+    /// Gets the binding point of the block called name, it gets the next free one the first
+    /// time
+    pub fn point(&mut self, name: &str) -> u32 {
+        if let Some(point) = self.get(name) {
+            return point;
+        }
+        let point = self.first + self.points.len() as u32;
+        self.points.push((name.to_string(), point));
+        point
+    }
+
+    /// Gets the binding point of the block called name if it has one
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.points
+            .iter()
+            .find(|(block, _)| block == name)
+            .map(|(_, point)| *point)
+    }
+
+    /// Points every block of program that has a point at it, gives how many blocks were
+    /// found
+    pub fn apply(&self, program: &ShaderProgram) -> usize {
+        self.points
+            .iter()
+            .filter(|(name, point)| UniformBuffer::bind_block(program, name, *point))
+            .count()
+    }
+}
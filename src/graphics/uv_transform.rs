@@ -0,0 +1,221 @@
+use nalgebra_glm::{vec2, Mat3, Vec2};
+
+use super::{shader::ShaderProgram, uniform::Uniform};
+
+/// Glsl that declares `uniform mat3 uv_transform` and `vec2 transform_uv(vec2 uv)`
+///
+/// Paste it into the vertex or fragment shader of a material and sample its textures with
+/// `transform_uv(uv)`, see [UvTransform::set_uniform]
+pub const UV_TRANSFORM_GLSL: &str = "uniform mat3 uv_transform;
+
+vec2 transform_uv(vec2 uv) {
+    return (uv_transform * vec3(uv, 1.0)).xy;
+}";
+
+/// A texture that is a grid of animation frames, the frames are played from the top left
+/// to the bottom right one row at a time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flipbook {
+    /// How many frames there are next to each other
+    pub columns: u32,
+    /// How many rows of frames there are
+    pub rows: u32,
+    /// How many frames are used, the last row doesn't have to be full
+    pub frames: u32,
+    /// How many frames are shown every second
+    pub fps: f32,
+    /// Whether it starts over after the last frame, otherwise it stays on it
+    pub looping: bool,
+}
+
+impl Flipbook {
+    /// Creates a new looping flipbook that uses every frame of the grid
+    pub fn new(columns: u32, rows: u32, fps: f32) -> Self {
+        Flipbook {
+            columns: columns.max(1),
+            rows: rows.max(1),
+            frames: columns.max(1) * rows.max(1),
+            fps,
+            looping: true,
+        }
+    }
+
+    /// Sets how many frames are used
+    pub fn frames(mut self, frames: u32) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Sets whether it starts over after the last frame
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Gets the frame that is shown at time(in seconds)
+    pub fn frame(&self, time: f32) -> u32 {
+        let (columns, rows) = self.grid();
+        let frames = self.frames.clamp(1, columns.saturating_mul(rows));
+        let frame = (time * self.fps).max(0.0) as u32;
+        if self.looping {
+            frame % frames
+        } else {
+            frame.min(frames - 1)
+        }
+    }
+
+    /// Gets the matrix that moves the uvs of the whole texture into the frame
+    fn matrix(&self, frame: u32) -> Mat3 {
+        let (columns, rows) = self.grid();
+        let size = vec2(1.0 / columns as f32, 1.0 / rows as f32);
+        let column = frame % columns;
+        // uvs start at the bottom but the frames start at the top
+        let row = rows - 1 - (frame / columns).min(rows - 1);
+        translate(vec2(column as f32 * size.x, row as f32 * size.y)) * scale(size)
+    }
+
+    /// Gets the columns and rows, at least 1 of each since the fields can be set to 0
+    fn grid(&self) -> (u32, u32) {
+        (self.columns.max(1), self.rows.max(1))
+    }
+}
+
+/// Gets the matrix that moves uvs by offset
+fn translate(offset: Vec2) -> Mat3 {
+    Mat3::new(1.0, 0.0, offset.x, 0.0, 1.0, offset.y, 0.0, 0.0, 1.0)
+}
+
+/// Gets the matrix that scales uvs by scale
+fn scale(scale: Vec2) -> Mat3 {
+    Mat3::new(scale.x, 0.0, 0.0, 0.0, scale.y, 0.0, 0.0, 0.0, 1.0)
+}
+
+/// Gets the matrix that rotates uvs by angle(in radians) counter clockwise
+fn rotate(angle: f32) -> Mat3 {
+    let (sin, cos) = angle.sin_cos();
+    Mat3::new(cos, -sin, 0.0, sin, cos, 0.0, 0.0, 0.0, 1.0)
+}
+
+/// Moves, scales and rotates the uvs of a material, optionally animated over time
+///
+/// The uvs are scaled first, then rotated around [UvTransform::pivot] and then moved. A
+/// [Flipbook] is applied last, so the transform works inside of one frame. The texture
+/// has to repeat for scrolling to wrap around
+///
+/// Every material can have its own transform, the mesh doesn't change so it is cheap to
+/// animate flowing water or a conveyor belt
+///
+/// # Example
+/// ```
+/// // water flows slowly to the right
+/// let water = UvTransform::new().scale(vec2(4.0, 4.0)).scroll(vec2(0.1, 0.0));
+/// // an explosion with 16 frames in a 4 by 4 grid
+/// let explosion = UvTransform::new().flipbook(Flipbook::new(4, 4, 24.0).looping(false));
+///
+/// 'main_loop: loop {
+///     water.set_uniform(&water_program, world.env.time.elapsed);
+///     water_mesh.draw();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvTransform {
+    /// How far the uvs are moved
+    pub offset: Vec2,
+    /// How much the uvs are scaled, 2 repeats the texture twice
+    pub scale: Vec2,
+    /// How much the uvs are rotated(in radians)
+    pub rotation: f32,
+    /// The uv the rotation is around
+    pub pivot: Vec2,
+    /// How fast the uvs move on every axis(per second)
+    pub scroll: Vec2,
+    /// How fast the uvs rotate(in radians per second)
+    pub spin: f32,
+    /// The flipbook the texture is, if it is one
+    pub flipbook: Option<Flipbook>,
+}
+
+impl UvTransform {
+    /// Creates a new transform that doesn't change the uvs
+    pub fn new() -> Self {
+        UvTransform {
+            offset: vec2(0.0, 0.0),
+            scale: vec2(1.0, 1.0),
+            rotation: 0.0,
+            pivot: vec2(0.5, 0.5),
+            scroll: vec2(0.0, 0.0),
+            spin: 0.0,
+            flipbook: None,
+        }
+    }
+
+    /// Sets how far the uvs are moved
+    pub fn offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets how much the uvs are scaled
+    pub fn scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets how much the uvs are rotated(in radians)
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the uv the rotation is around
+    pub fn pivot(mut self, pivot: Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    /// Sets how fast the uvs move on every axis(per second)
+    pub fn scroll(mut self, scroll: Vec2) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    /// Sets how fast the uvs rotate(in radians per second)
+    pub fn spin(mut self, spin: f32) -> Self {
+        self.spin = spin;
+        self
+    }
+
+    /// Sets the flipbook the texture is
+    pub fn flipbook(mut self, flipbook: Flipbook) -> Self {
+        self.flipbook = Some(flipbook);
+        self
+    }
+
+    /// Gets the matrix that transforms the uvs at time(in seconds)
+    pub fn matrix(&self, time: f32) -> Mat3 {
+        // the scroll is wrapped so the offset doesn't lose precision after a long time
+        let scrolled = self.offset + (self.scroll * time).map(f32::fract);
+        let transform = translate(scrolled)
+            * translate(self.pivot)
+            * rotate(self.rotation + self.spin * time)
+            * translate(-self.pivot)
+            * scale(self.scale);
+        match self.flipbook {
+            Some(flipbook) => flipbook.matrix(flipbook.frame(time)) * transform,
+            None => transform,
+        }
+    }
+
+    /// Sets `uniform mat3 uv_transform` of program to the matrix at time(in seconds), see
+    /// [UV_TRANSFORM_GLSL]
+    pub fn set_uniform(&self, program: &ShaderProgram, time: f32) {
+        program.use_program();
+        Uniform::new(program, "uv_transform").set_mat3(&self.matrix(time));
+    }
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        UvTransform::new()
+    }
+}
@@ -1,15 +1,17 @@
-use super::*;
+use super::{recovery::ContextId, *};
 
 /// Creates a [VAO](https://www.khronos.org/opengl/wiki/Client-Side_Vertex_Arrays) and is used to make [VBO](https://www.khronos.org/opengl/wiki/Vertex_Specification#Vertex_Buffer_Object)
 /// using the [Buffer] struct
-pub struct VertexArray(pub u32);
+///
+/// The VAO is deleted when it is dropped, see [ContextId]
+pub struct VertexArray(pub u32, ContextId);
 impl VertexArray {
     /// Creates a new VAO
     pub fn new() -> Option<Self> {
         let mut vao = 0;
         unsafe { glGenVertexArrays(1, &mut vao) };
         if vao != 0 {
-            Some(Self(vao))
+            Some(Self(vao, ContextId::current()))
         } else {
             None
         }
@@ -24,4 +26,157 @@ impl VertexArray {
     pub fn clear_binding() {
         unsafe { glBindVertexArray(0) }
     }
+
+    /// Deletes the VAO now instead of when it is dropped
+    pub fn delete(self) {}
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        if self.1.is_current() {
+            unsafe { glDeleteVertexArrays(1, &self.0) }
+        }
+    }
+}
+
+/// The format a vertex attribute is stored in on the gpu
+///
+/// Smaller formats use less memory and bandwidth, the values are still given to the shader
+/// as floats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttrFormat {
+    /// 32 bit floats
+    #[default]
+    Float,
+    /// 16 bit floats, good enough for most positions and uvs, see [pack_half]
+    HalfFloat,
+    /// x, y and z as signed normalized 10 bit integers and w as a 2 bit one, all in 32 bits.
+    /// Made for normals and tangents, see [pack_int_2_10_10_10_rev]
+    Int2101010Rev,
+}
+
+impl AttrFormat {
+    /// Gets the opengl type of the format
+    pub fn gl_type(self) -> u32 {
+        match self {
+            AttrFormat::Float => GL_FLOAT,
+            AttrFormat::HalfFloat => GL_HALF_FLOAT,
+            AttrFormat::Int2101010Rev => GL_INT_2_10_10_10_REV,
+        }
+    }
+
+    /// Checks if opengl has to normalize the values of the format
+    pub fn normalized(self) -> bool {
+        self == AttrFormat::Int2101010Rev
+    }
+
+    /// Gets how many components opengl is told the attribute has, packed formats always
+    /// have 4
+    pub fn gl_size(self, size: u32) -> i32 {
+        match self {
+            AttrFormat::Int2101010Rev => 4,
+            _ => size as i32,
+        }
+    }
+
+    /// Gets how many bytes an attribute with size elements takes, padded to 4 bytes so every
+    /// attribute stays aligned
+    pub fn byte_size(self, size: u32) -> usize {
+        let bytes = match self {
+            AttrFormat::Float => size as usize * 4,
+            AttrFormat::HalfFloat => size as usize * 2,
+            AttrFormat::Int2101010Rev => 4,
+        };
+        bytes.div_ceil(4) * 4
+    }
+
+    /// Packs the elements of an attribute into out in this format, padding included
+    pub fn pack(self, values: &[f32], out: &mut Vec<u8>) {
+        let start = out.len();
+        out.resize(start + self.byte_size(values.len() as u32), 0);
+        self.pack_into(values, &mut out[start..]);
+    }
+
+    /// Packs the elements of an attribute into the start of out in this format and gets how
+    /// many bytes were written, padding included
+    ///
+    /// # Panics
+    /// When out is shorter than [AttrFormat::byte_size]
+    pub fn pack_into(self, values: &[f32], out: &mut [u8]) -> usize {
+        let size = self.byte_size(values.len() as u32);
+        let out = &mut out[..size];
+        out.fill(0);
+        match self {
+            AttrFormat::Float => {
+                for (bytes, value) in out.chunks_exact_mut(4).zip(values) {
+                    bytes.copy_from_slice(&value.to_ne_bytes());
+                }
+            }
+            AttrFormat::HalfFloat => {
+                for (bytes, value) in out.chunks_exact_mut(2).zip(values) {
+                    bytes.copy_from_slice(&pack_half(*value).to_ne_bytes());
+                }
+            }
+            AttrFormat::Int2101010Rev => {
+                let get = |i: usize| values.get(i).copied().unwrap_or(0.0);
+                let packed = pack_int_2_10_10_10_rev([get(0), get(1), get(2), get(3)]);
+                out.copy_from_slice(&packed.to_ne_bytes());
+            }
+        }
+        size
+    }
+}
+
+/// Turns a float into the bits of a 16 bit float, rounded to the nearest one
+///
+/// Values too big for a 16 bit float become infinity
+pub fn pack_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // infinity and nan
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // too small even for a subnormal
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let round = (mantissa >> (shift - 1)) & 1;
+        return sign | ((mantissa >> shift) + round) as u16;
+    }
+
+    // a carry from rounding correctly moves into the exponent
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let round = (mantissa >> 12) & 1;
+    sign | (half + round) as u16
+}
+
+/// Packs 4 values from -1 to 1 into a `GL_INT_2_10_10_10_REV`, x, y and z get 10 bits and w
+/// gets 2 bits
+///
+/// # Example
+/// ```
+/// let normal = normalize(&vec3(1.0, 1.0, 0.0));
+/// let packed = pack_int_2_10_10_10_rev([normal.x, normal.y, normal.z, 0.0]);
+/// ```
+pub fn pack_int_2_10_10_10_rev(values: [f32; 4]) -> u32 {
+    let pack = |value: f32, max: f32, bits: u32| {
+        let value = (value.clamp(-1.0, 1.0) * max).round() as i32;
+        (value as u32) & ((1 << bits) - 1)
+    };
+    pack(values[0], 511.0, 10)
+        | pack(values[1], 511.0, 10) << 10
+        | pack(values[2], 511.0, 10) << 20
+        | pack(values[3], 1.0, 2) << 30
 }
@@ -0,0 +1,211 @@
+use nalgebra_glm::{normalize, vec3, Mat4, Vec3};
+
+use super::{
+    particles::{ComputeFns, GpuParticles, ParticleSettings},
+    shader::ShaderProgram,
+    uniform::Uniform,
+    LighthouseError,
+};
+
+/// Glsl that declares the weather uniforms and
+/// `vec3 apply_weather(vec3 albedo, vec3 normal, inout float roughness)`
+///
+/// Paste it into the fragment shader of a material so surfaces get wet in the rain and
+/// covered in snow, see [Weather::set_uniforms]
+pub const WEATHER_GLSL: &str = "uniform float wetness;
+uniform float snow_cover;
+
+vec3 apply_weather(vec3 albedo, vec3 normal, inout float roughness) {
+    // wet surfaces are darker and shinier
+    albedo *= mix(1.0, 0.6, wetness);
+    roughness = mix(roughness, 0.1, wetness);
+    // snow settles on surfaces that face up
+    float snow = smoothstep(0.3, 0.8, normal.y) * snow_cover;
+    roughness = mix(roughness, 0.8, snow);
+    return mix(albedo, vec3(0.95), snow);
+}";
+
+/// The kind of weather
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WeatherKind {
+    /// Nothing falls and surfaces dry up
+    #[default]
+    Clear,
+    /// Fast streaks that make surfaces wet
+    Rain,
+    /// Slow flakes that pile up on surfaces
+    Snow,
+}
+
+/// The settings of rain particles, intensity is from 0 to 1
+pub fn rain_settings(intensity: f32, wind: Vec3) -> ParticleSettings {
+    ParticleSettings {
+        emitter_dir: normalize(&(vec3(0.0, -18.0, 0.0) + wind)),
+        emitter_spread: 0.01,
+        spawn_fraction: intensity,
+        speed_range: (16.0, 20.0),
+        life_range: (1.0, 1.4),
+        gravity: vec3(0.0, 0.0, 0.0),
+        start_color: [0.7, 0.75, 0.85, 0.6],
+        end_color: [0.7, 0.75, 0.85, 0.4],
+        point_size: 4.0,
+        ..ParticleSettings::default()
+    }
+}
+
+/// The settings of snow particles, intensity is from 0 to 1
+pub fn snow_settings(intensity: f32, wind: Vec3) -> ParticleSettings {
+    ParticleSettings {
+        emitter_dir: normalize(&(vec3(0.0, -1.0, 0.0) + wind * 0.2)),
+        emitter_spread: 0.4,
+        spawn_fraction: intensity,
+        speed_range: (0.8, 1.5),
+        life_range: (10.0, 14.0),
+        gravity: vec3(0.0, -0.3, 0.0) + wind * 0.3,
+        drag: 0.5,
+        start_color: [1.0, 1.0, 1.0, 0.9],
+        end_color: [1.0, 1.0, 1.0, 0.6],
+        point_size: 12.0,
+        ..ParticleSettings::default()
+    }
+}
+
+/// Ready made rain and snow that follow the camera
+///
+/// The particles spawn in a box above the camera and fall in world space, so they stay
+/// put when the camera turns. While it rains or snows the [Weather::wetness] and
+/// [Weather::snow_cover] of surfaces go up and they go back down when it clears, give them
+/// to materials with [Weather::set_uniforms] and [WEATHER_GLSL]
+///
+/// # Example
+/// ```
+/// let mut weather = Weather::new(60_000, compute)?;
+/// weather.set(WeatherKind::Rain, 0.7);
+///
+/// 'main_loop: loop {
+///     weather.update(world.env.time.delta, camera.pos);
+///     weather.set_uniforms(&ground_program);
+///     ground.draw();
+///     weather.draw(&view_proj);
+/// }
+/// ```
+pub struct Weather {
+    /// The particles of the weather, their settings can be changed after [Weather::set]
+    pub particles: GpuParticles,
+    /// Half the size of the box the particles spawn in, the height is ignored
+    pub area: Vec3,
+    /// How high above the camera the particles spawn
+    pub height: f32,
+    /// Which way and how hard the wind blows
+    pub wind: Vec3,
+    /// How fast surfaces get wet in full rain(per second)
+    pub wetting_speed: f32,
+    /// How fast surfaces dry and snow melts when it is clear(per second)
+    pub drying_speed: f32,
+    /// How fast snow piles up in full snow(per second)
+    pub snowing_speed: f32,
+    kind: WeatherKind,
+    intensity: f32,
+    wetness: f32,
+    snow_cover: f32,
+}
+
+impl Weather {
+    /// Creates clear weather with room for max_particles, see [GpuParticles::new] for compute
+    pub fn new(max_particles: usize, compute: Option<ComputeFns>) -> Result<Self, LighthouseError> {
+        let settings = ParticleSettings {
+            spawn_fraction: 0.0,
+            ..ParticleSettings::default()
+        };
+        Ok(Weather {
+            particles: GpuParticles::new(max_particles, settings, compute)?,
+            area: vec3(25.0, 0.0, 25.0),
+            height: 20.0,
+            wind: vec3(0.0, 0.0, 0.0),
+            wetting_speed: 0.1,
+            drying_speed: 0.02,
+            snowing_speed: 0.01,
+            kind: WeatherKind::Clear,
+            intensity: 0.0,
+            wetness: 0.0,
+            snow_cover: 0.0,
+        })
+    }
+
+    /// Changes the weather, intensity is from 0 to 1
+    ///
+    /// This replaces the particle settings, particles that are already falling keep falling
+    pub fn set(&mut self, kind: WeatherKind, intensity: f32) {
+        let intensity = intensity.clamp(0.0, 1.0);
+        let collision = self.particles.settings.collision;
+        self.particles.settings = match kind {
+            WeatherKind::Clear => ParticleSettings {
+                spawn_fraction: 0.0,
+                ..self.particles.settings.clone()
+            },
+            WeatherKind::Rain => rain_settings(intensity, self.wind),
+            WeatherKind::Snow => snow_settings(intensity, self.wind),
+        };
+        self.particles.settings.collision = collision;
+        self.kind = kind;
+        self.intensity = intensity;
+    }
+
+    /// Gets the kind of weather
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    /// Gets how hard it rains or snows, from 0 to 1
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Gets how wet surfaces are, from 0 to 1
+    pub fn wetness(&self) -> f32 {
+        self.wetness
+    }
+
+    /// Gets how much snow is on surfaces, from 0 to 1
+    pub fn snow_cover(&self) -> f32 {
+        self.snow_cover
+    }
+
+    /// Moves the particles and the emitter to above camera_pos and updates the wetness and
+    /// snow cover, delta is in seconds
+    pub fn update(&mut self, delta: f32, camera_pos: Vec3) {
+        let settings = &mut self.particles.settings;
+        settings.emitter_pos = camera_pos + vec3(0.0, self.height, 0.0);
+        settings.emitter_box = vec3(self.area.x, 0.0, self.area.z);
+        self.particles.update(delta);
+
+        let (wet, snow) = match self.kind {
+            WeatherKind::Clear => (-self.drying_speed, -self.drying_speed),
+            WeatherKind::Rain => (
+                self.wetting_speed * self.intensity,
+                // rain washes snow away
+                -self.drying_speed - self.wetting_speed * self.intensity,
+            ),
+            WeatherKind::Snow => (-self.drying_speed, self.snowing_speed * self.intensity),
+        };
+        self.wetness = (self.wetness + wet * delta).clamp(0.0, 1.0);
+        self.snow_cover = (self.snow_cover + snow * delta).clamp(0.0, 1.0);
+    }
+
+    /// Draws the rain or snow
+    pub fn draw(&self, view_proj: &Mat4) {
+        self.particles.draw(view_proj);
+    }
+
+    /// Gives the wetness and snow cover to a program that uses [WEATHER_GLSL]
+    pub fn set_uniforms(&self, program: &ShaderProgram) {
+        program.use_program();
+        Uniform::new(program, "wetness").set_uniform_f(&[self.wetness]);
+        Uniform::new(program, "snow_cover").set_uniform_f(&[self.snow_cover]);
+    }
+
+    /// Deletes the particles
+    pub fn delete(self) {
+        self.particles.delete();
+    }
+}
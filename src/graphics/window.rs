@@ -0,0 +1,507 @@
+#[cfg(feature = "sdl")]
+use std::cell::Cell;
+use std::{
+    any::Any,
+    ffi::{c_char, c_void},
+};
+#[cfg(feature = "glutin")]
+use std::{collections::VecDeque, ffi::CStr};
+
+use nalgebra_glm::{vec2, Vec2};
+
+#[cfg(feature = "sdl")]
+use super::{recovery::sdl_error, *};
+use super::{recovery::ContextId, LighthouseError};
+
+/// An event of a [Window], every backend turns its own events into these
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowEvent {
+    /// The window was asked to close
+    Quit,
+    /// The window got keyboard focus
+    FocusGained,
+    /// The window lost keyboard focus
+    FocusLost,
+    /// The window was minimized
+    Minimized,
+    /// The window was restored after being minimized
+    Restored,
+    /// The window was resized, contains the new width and height of what is drawn to(in
+    /// pixels), the same as [Window::size]
+    Resized(u32, u32),
+    /// The mouse moved
+    MouseMotion {
+        /// Where the mouse is in the window(in pixels)
+        pos: Vec2,
+        /// How far the mouse moved(in pixels), this is still given when the mouse is
+        /// captured
+        delta: Vec2,
+    },
+    /// The mouse wheel was scrolled, y is up and down and x is left and right
+    MouseWheel(Vec2),
+    /// An event lighthouse doesn't use
+    Other,
+}
+
+/// A window with an opengl context that lighthouse draws to
+///
+/// The [Enviroment](crate::ECS::world::Enviroment) only uses a window through this trait.
+/// [SdlWindow] is the backend behind the `sdl` feature and [GlutinWindow] the one behind
+/// the `glutin` feature. Without either the game has to bring its own window
+///
+/// Only the first five functions have to be implemented, the ones that change the window
+/// or the context give false or [LighthouseError::Unsupported] when the backend can't
+///
+/// # Example
+/// ```
+/// let win = SdlWindow::new("My game", 800, 600)?;
+/// win.load_gl();
+/// win.set_vsync(true);
+///
+/// let env = Enviroment::new(Box::new(win), shader_program, device, mouse);
+/// let (width, height) = world.env.win.size();
+/// ```
+pub trait Window: Any {
+    /// Shows what was drawn since the last swap
+    fn swap_buffers(&self);
+
+    /// Gets the size of what is drawn to(in pixels), this can be bigger than the window on
+    /// high dpi screens
+    fn size(&self) -> (u32, u32);
+
+    /// Gets the address of an opengl function, null if there isn't one
+    ///
+    /// # Safety
+    /// name has to be a valid nul terminated string
+    unsafe fn get_proc_address(&self, name: *const c_char) -> *const c_void;
+
+    /// Takes the next event that happened, None when there are no more this frame
+    fn poll_event(&mut self) -> Option<WindowEvent>;
+
+    /// Gets the window as [Any], so the backend can be got back with downcast_ref
+    fn as_any(&self) -> &dyn Any;
+
+    /// Turns waiting for the screen to refresh before swapping on or off, gives false if
+    /// the backend can't
+    fn set_vsync(&self, _vsync: bool) -> bool {
+        false
+    }
+
+    /// Captures and hides the mouse so only how far it moved is given, or lets it go
+    /// again, gives false if the backend can't
+    fn set_relative_mouse(&self, _enabled: bool) -> bool {
+        false
+    }
+
+    /// Shows or hides the cursor of the os over the window, gives false if the backend
+    /// can't
+    fn show_cursor(&self, _shown: bool) -> bool {
+        false
+    }
+
+    /// Makes a new opengl context for the window and makes it current, after the old one
+    /// was lost. The functions still have to be loaded with [Window::load_gl], see
+    /// [ContextRecovery](super::recovery::ContextRecovery)
+    ///
+    /// Gives [LighthouseError::Unsupported] if the backend can't
+    fn recreate_context(&self) -> Result<(), LighthouseError> {
+        Err(LighthouseError::Unsupported(
+            "This window can't make a new opengl context".to_string(),
+        ))
+    }
+
+    /// Loads the opengl functions of the context of the window, it has to be current
+    ///
+    /// The context gets a new [ContextId], so objects made before are left alone
+    fn load_gl(&self) {
+        unsafe { ogl33::load_gl_with(|name| self.get_proc_address(name)) }
+        super::recovery::load_reset_status(|name| unsafe { self.get_proc_address(name) });
+        ContextId::new_context();
+    }
+
+    /// Gets the size as a vector, what [Enviroment::win_size](crate::ECS::world::Enviroment)
+    /// holds
+    fn size_vec(&self) -> Vec2 {
+        let (width, height) = self.size();
+        vec2(width as f32, height as f32)
+    }
+}
+
+/// The SDL backend of [Window], it holds SDL itself too since SDL gives the events
+///
+/// The functions of [display](super::display) need the [GlWindow] inside
+///
+/// # Example
+/// ```
+/// let win = SdlWindow::new("My game", 800, 600)?;
+/// win.load_gl();
+/// win.win.set_swap_interval(SwapInterval::Vsync);
+///
+/// let env = Enviroment::new(Box::new(win), shader_program, device, mouse);
+///
+/// // later on
+/// if let Some(sdl) = world.env.win.as_any().downcast_ref::<SdlWindow>() {
+///     set_borderless(&sdl.win, true);
+/// }
+/// ```
+#[cfg(feature = "sdl")]
+pub struct SdlWindow {
+    /// The window and its context
+    pub win: GlWindow,
+    /// SDL, dropped after the window
+    pub sdl: SDL,
+    /// The context made by [Window::recreate_context], null until the first one was lost
+    recreated: Cell<fermium::SDL_GLContext>,
+}
+
+#[cfg(feature = "sdl")]
+impl SdlWindow {
+    /// Starts SDL and creates a centered window with an opengl 3.3 core context
+    pub fn new(title: &str, width: u32, height: u32) -> Result<Self, LighthouseError> {
+        let sdl = SDL::init(InitFlags::Everything)
+            .map_err(|error| LighthouseError::Sdl(error.to_string()))?;
+        sdl.gl_set_attribute(SdlGlAttr::MajorVersion, 3)
+            .map_err(LighthouseError::Sdl)?;
+        sdl.gl_set_attribute(SdlGlAttr::MinorVersion, 3)
+            .map_err(LighthouseError::Sdl)?;
+        sdl.gl_set_attribute(SdlGlAttr::Profile, GlProfile::Core)
+            .map_err(LighthouseError::Sdl)?;
+        #[cfg(target_os = "macos")]
+        sdl.gl_set_attribute(SdlGlAttr::Flags, ContextFlag::ForwardCompatible)
+            .map_err(LighthouseError::Sdl)?;
+        let win = sdl
+            .create_gl_window(
+                title,
+                WindowPosition::Centered,
+                width,
+                height,
+                WindowFlags::Shown,
+            )
+            .map_err(LighthouseError::Sdl)?;
+        Ok(SdlWindow::from_gl_window(sdl, win))
+    }
+
+    /// Uses a window that was already made, for when it needs other settings
+    pub fn from_gl_window(sdl: SDL, win: GlWindow) -> Self {
+        SdlWindow {
+            win,
+            sdl,
+            recreated: Cell::new(std::ptr::null_mut()),
+        }
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl Window for SdlWindow {
+    fn swap_buffers(&self) {
+        self.win.swap_window();
+    }
+
+    fn size(&self) -> (u32, u32) {
+        let (width, height) = self.win.get_drawable_size();
+        (width.max(0) as u32, height.max(0) as u32)
+    }
+
+    unsafe fn get_proc_address(&self, name: *const c_char) -> *const c_void {
+        self.win.get_proc_address(name)
+    }
+
+    fn poll_event(&mut self) -> Option<WindowEvent> {
+        // events beryllium can't read are skipped instead of ending the frame
+        loop {
+            match self.sdl.poll_events()? {
+                Ok(event) => {
+                    return Some(match WindowEvent::from(event) {
+                        // sdl gives the size in points, on high dpi screens it is smaller
+                        WindowEvent::Resized(..) => {
+                            let (width, height) = self.size();
+                            WindowEvent::Resized(width, height)
+                        }
+                        event => event,
+                    });
+                }
+                Err(_) => continue,
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn set_vsync(&self, vsync: bool) -> bool {
+        let interval = if vsync {
+            SwapInterval::Vsync
+        } else {
+            SwapInterval::Immediate
+        };
+        self.win.set_swap_interval(interval) == 0
+    }
+
+    fn set_relative_mouse(&self, enabled: bool) -> bool {
+        let enabled = if enabled {
+            fermium::SDL_TRUE
+        } else {
+            fermium::SDL_FALSE
+        };
+        unsafe { fermium::SDL_SetRelativeMouseMode(enabled) == 0 }
+    }
+
+    fn recreate_context(&self) -> Result<(), LighthouseError> {
+        unsafe {
+            let window = fermium::SDL_GL_GetCurrentWindow();
+            if window.is_null() {
+                return Err(sdl_error());
+            }
+            // the swap interval of the old context is kept
+            let swap_interval = fermium::SDL_GL_GetSwapInterval();
+            let context = fermium::SDL_GL_CreateContext(window);
+            if context.is_null() {
+                return Err(sdl_error());
+            }
+            if fermium::SDL_GL_MakeCurrent(window, context) != 0 {
+                fermium::SDL_GL_DeleteContext(context);
+                return Err(sdl_error());
+            }
+            let old = self.recreated.replace(context);
+            if !old.is_null() {
+                fermium::SDL_GL_DeleteContext(old);
+            }
+            fermium::SDL_GL_SetSwapInterval(swap_interval);
+        }
+        Ok(())
+    }
+
+    fn show_cursor(&self, shown: bool) -> bool {
+        let toggle = if shown {
+            fermium::SDL_ENABLE
+        } else {
+            fermium::SDL_DISABLE
+        };
+        unsafe { fermium::SDL_ShowCursor(toggle as _) >= 0 }
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl Drop for SdlWindow {
+    fn drop(&mut self) {
+        // the context goes with the window
+        let recreated = self.recreated.get();
+        if !recreated.is_null() {
+            unsafe { fermium::SDL_GL_DeleteContext(recreated) };
+        }
+        ContextId::forget();
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl From<Event> for WindowEvent {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Quit(_) => WindowEvent::Quit,
+            Event::Window(window) => match window.event {
+                WindowEventEnum::KeyboardFocusGained => WindowEvent::FocusGained,
+                WindowEventEnum::KeyboardFocusLost => WindowEvent::FocusLost,
+                WindowEventEnum::Minimized => WindowEvent::Minimized,
+                WindowEventEnum::Restored => WindowEvent::Restored,
+                WindowEventEnum::Resized { w, h } => WindowEvent::Resized(w, h),
+                _ => WindowEvent::Other,
+            },
+            Event::MouseMotion(motion) => WindowEvent::MouseMotion {
+                pos: vec2(motion.x_pos as f32, motion.y_pos as f32),
+                delta: vec2(motion.x_delta as f32, motion.y_delta as f32),
+            },
+            Event::MouseWheel(wheel) => {
+                // some mice scroll the other way, SDL says so instead of flipping it
+                let flip = if wheel.is_normal { 1.0 } else { -1.0 };
+                WindowEvent::MouseWheel(vec2(wheel.x_delta as f32, wheel.y_delta as f32) * flip)
+            }
+            _ => WindowEvent::Other,
+        }
+    }
+}
+
+/// How many pixels of a touchpad scroll count as one line of a mouse wheel
+#[cfg(feature = "glutin")]
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// The glutin backend of [Window], it holds the winit event loop too since it gives the
+/// events
+///
+/// The event loop is pumped by [Window::poll_event], so the game keeps its own main loop
+/// like with [SdlWindow]. Glutin can't change the vsync or make a new context after it was
+/// made
+///
+/// # Example
+/// ```
+/// let win = GlutinWindow::new("My game", 800, 600, true)?;
+/// win.load_gl();
+///
+/// let env = Enviroment::new(Box::new(win), shader_program, device, mouse);
+/// ```
+#[cfg(feature = "glutin")]
+pub struct GlutinWindow {
+    /// The window and its context
+    pub context: glutin::ContextWrapper<glutin::PossiblyCurrent, glutin::window::Window>,
+    /// The event loop of the window
+    pub event_loop: glutin::event_loop::EventLoop<()>,
+    /// The events that were pumped but not taken yet
+    events: VecDeque<WindowEvent>,
+    /// Whether the event loop was pumped since [Window::poll_event] last gave None
+    pumped: bool,
+    /// Where the mouse was last seen in the window(in pixels)
+    mouse_pos: Vec2,
+}
+
+#[cfg(feature = "glutin")]
+impl GlutinWindow {
+    /// Creates a window with an opengl 3.3 core context and makes it current
+    pub fn new(title: &str, width: u32, height: u32, vsync: bool) -> Result<Self, LighthouseError> {
+        use glutin::{dpi::LogicalSize, window::WindowBuilder, Api, GlProfile, GlRequest};
+
+        let event_loop = glutin::event_loop::EventLoop::new();
+        let window = WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(LogicalSize::new(width, height));
+        let context = glutin::ContextBuilder::new()
+            .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
+            .with_gl_profile(GlProfile::Core)
+            .with_vsync(vsync)
+            .build_windowed(window, &event_loop)
+            .map_err(|error| LighthouseError::Glutin(error.to_string()))?;
+        let context = unsafe { context.make_current() }
+            .map_err(|(_, error)| LighthouseError::Glutin(error.to_string()))?;
+        Ok(GlutinWindow {
+            context,
+            event_loop,
+            events: VecDeque::new(),
+            pumped: false,
+            mouse_pos: vec2(0.0, 0.0),
+        })
+    }
+
+    /// Runs the event loop until it is out of events and queues them
+    fn pump_events(&mut self) {
+        use glutin::{
+            event::{DeviceEvent, Event, MouseScrollDelta, WindowEvent as WinitEvent},
+            event_loop::ControlFlow,
+            platform::run_return::EventLoopExtRunReturn,
+        };
+
+        let GlutinWindow {
+            context,
+            event_loop,
+            events,
+            mouse_pos,
+            ..
+        } = self;
+        event_loop.run_return(|event, _, control_flow| {
+            let event = match event {
+                Event::WindowEvent { event, .. } => match event {
+                    WinitEvent::CloseRequested => WindowEvent::Quit,
+                    WinitEvent::Focused(true) => WindowEvent::FocusGained,
+                    WinitEvent::Focused(false) => WindowEvent::FocusLost,
+                    WinitEvent::Occluded(true) => WindowEvent::Minimized,
+                    WinitEvent::Occluded(false) => WindowEvent::Restored,
+                    WinitEvent::Resized(size) => {
+                        context.resize(size);
+                        WindowEvent::Resized(size.width, size.height)
+                    }
+                    WinitEvent::CursorMoved { position, .. } => {
+                        *mouse_pos = vec2(position.x as f32, position.y as f32);
+                        // the delta comes from the device, it keeps coming while captured
+                        WindowEvent::MouseMotion {
+                            pos: *mouse_pos,
+                            delta: vec2(0.0, 0.0),
+                        }
+                    }
+                    WinitEvent::MouseWheel { delta, .. } => match delta {
+                        MouseScrollDelta::LineDelta(x, y) => WindowEvent::MouseWheel(vec2(x, y)),
+                        MouseScrollDelta::PixelDelta(pixels) => WindowEvent::MouseWheel(
+                            vec2(pixels.x as f32, pixels.y as f32) / PIXELS_PER_LINE,
+                        ),
+                    },
+                    _ => return,
+                },
+                Event::DeviceEvent {
+                    event: DeviceEvent::MouseMotion { delta },
+                    ..
+                } => WindowEvent::MouseMotion {
+                    pos: *mouse_pos,
+                    delta: vec2(delta.0 as f32, delta.1 as f32),
+                },
+                Event::MainEventsCleared => {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                _ => return,
+            };
+            events.push_back(event);
+        });
+    }
+}
+
+#[cfg(feature = "glutin")]
+impl Window for GlutinWindow {
+    fn swap_buffers(&self) {
+        if let Err(error) = self.context.swap_buffers() {
+            log::error!("Couldn't swap the buffers: {}", error);
+        }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.context.window().inner_size().into()
+    }
+
+    unsafe fn get_proc_address(&self, name: *const c_char) -> *const c_void {
+        match CStr::from_ptr(name).to_str() {
+            Ok(name) => self.context.get_proc_address(name),
+            Err(_) => std::ptr::null(),
+        }
+    }
+
+    fn poll_event(&mut self) -> Option<WindowEvent> {
+        // the loop is pumped once per frame, so a mouse that keeps moving can't hold it up
+        if self.events.is_empty() && !self.pumped {
+            self.pump_events();
+            self.pumped = true;
+        }
+        let event = self.events.pop_front();
+        if event.is_none() {
+            self.pumped = false;
+        }
+        event
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn set_relative_mouse(&self, enabled: bool) -> bool {
+        use glutin::window::CursorGrabMode;
+
+        let window = self.context.window();
+        window.set_cursor_visible(!enabled);
+        if !enabled {
+            return window.set_cursor_grab(CursorGrabMode::None).is_ok();
+        }
+        // some platforms can only lock and others can only confine
+        window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+            .is_ok()
+    }
+
+    fn show_cursor(&self, shown: bool) -> bool {
+        self.context.window().set_cursor_visible(shown);
+        true
+    }
+}
+
+#[cfg(feature = "glutin")]
+impl Drop for GlutinWindow {
+    fn drop(&mut self) {
+        // the context goes with the window
+        ContextId::forget();
+    }
+}
@@ -2,18 +2,30 @@
 //!
 //! It is fully documented an is currently is a WIP
 //! Although i hope to change that in the near future
-//! It can be used to make 2d graphics
+//! It can be used to make 2d and 3d graphics
 //!
 //! It has a complete Shader system so you can make any type of shader
 //!
 //! # Limitations
 //!
-//! At the moment it cannot create 3d graphics
-//! It only supports the creation of 2D textures
-//! And does not support non primitive uniforms
+//! It is built on opengl 3.3, compute shaders are optional and need opengl 4.3, see
+//! [ShaderProgram::from_compute](crate::graphics::shader::ShaderProgram::from_compute) and
+//! [GpuParticles](crate::graphics::particles::GpuParticles)
 
 #![deny(missing_docs)]
+/// Module containing all things related to [crate::animation::skeleton::Skeleton]
+pub mod animation;
+/// Module containing all things related to [crate::app::App]
+pub mod app;
+/// Module containing all things related to [crate::assets::AssetManager]
+pub mod assets;
+/// Module containing all things related to [crate::bench::Bench]
+pub mod bench;
 /// Module containing ECS stuff
 pub mod ECS;
+/// Module containing all things related to [crate::error::LighthouseError]
+pub mod error;
 /// Module containing all things related to [crate::graphics]
 pub mod graphics;
+/// Module containing all things related to [crate::profiler::Profiler]
+pub mod profiler;
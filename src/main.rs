@@ -13,15 +13,17 @@ use core::{
 use device_query::{DeviceQuery, DeviceState, Keycode, MouseState};
 use image::DynamicImage;
 use lighthouse::{
+    app::{App, AppConfig},
     ECS::{
         camera::{CameraSettings, CameraSettingsBuilder, CameraTrait},
+        context::UpdateCtx,
+        game_loop::GameLoop,
         mouse::{MousePressed::*, StateOfMouse::*, *},
-        mesh::{
-            ControllableKey, ControllableMouse, Mesh, MeshTrait, Object, PosRot, VertexTrait,
-        },
+        mesh::{Mesh, PosRot, Transform3D, VertexLayout, VertexTrait},
+        object::{ControllableKey, ControllableMouse, Input, Object},
         world::{self, Enviroment, GameObjectTrait, World},
     },
-    graphics::{buffer::*, shader::*, texture::*, uniform::*, vertex::*, *},
+    graphics::{buffer::*, render_state::RenderState, renderer::DrawCommand, shader::*, texture::*, uniform::*, vertex::*, window::Window, *},
     impl_posrot,
 };
 use nalgebra_glm::*;
@@ -44,6 +46,10 @@ impl Vertex {
     }
 }
 
+impl VertexLayout for Vertex {
+    const ATTRIBUTES: &'static [u32] = &[3, 2];
+}
+
 impl VertexTrait for Vertex {
     const SIZE: u32 = 5;
 
@@ -84,15 +90,14 @@ impl Camera {
 impl_posrot!(Camera);
 
 impl Object<GameObject> for Camera {
-    fn update(world: &mut World<GameObject>, _: u32) {
-        Camera::matrix(&world.objects.camera);
-        Camera::on_key(world);
+    fn update(&mut self, _: &mut UpdateCtx<GameObject>) {
+        self.matrix();
     }
 }
 
 impl CameraTrait<GameObject> for Camera {
     fn get_camera_settings(&self) -> CameraSettings {
-        self.settings
+        self.settings.clone()
     }
 
     fn get_camera_uniform(&self) -> String {
@@ -100,46 +105,37 @@ impl CameraTrait<GameObject> for Camera {
     }
 }
 
-impl ControllableKey<GameObject> for Camera {
-    fn on_key(world: &mut World<GameObject>) {
-        for key in world.env.device.get_keys() {
+impl ControllableKey for Camera {
+    fn on_key(&mut self, input: &mut Input) {
+        for key in input.keys {
             match key {
-                Keycode::W => world.objects.set_camera().set_pos().z += 0.01,
-                Keycode::A => world.objects.set_camera().set_pos().x += 0.01,
-                Keycode::S => world.objects.set_camera().set_pos().z -= 0.01,
-                Keycode::D => world.objects.set_camera().set_pos().x -= 0.01,
-                Keycode::LShift | Keycode::RShift => world.objects.set_camera().set_pos().y -= 0.01,
-                Keycode::Space => world.objects.set_camera().set_pos().y += 0.01,
+                Keycode::W => self.set_pos().z += 0.01,
+                Keycode::A => self.set_pos().x += 0.01,
+                Keycode::S => self.set_pos().z -= 0.01,
+                Keycode::D => self.set_pos().x -= 0.01,
+                Keycode::LShift | Keycode::RShift => self.set_pos().y -= 0.01,
+                Keycode::Space => self.set_pos().y += 0.01,
                 _ => (),
             }
         }
     }
 }
 
-impl ControllableMouse<GameObject> for Camera {
-    fn on_mouse(world: &mut World<GameObject>) {
-        if let Some(keys) = world
-            .env
-            .mouse
-            .get_pressed_cooldown(Duration::from_millis(100))
-        {
+impl ControllableMouse for Camera {
+    fn on_mouse(&mut self, input: &mut Input) {
+        if let Some(keys) = input.mouse.get_pressed_cooldown(Duration::from_millis(100)) {
             keys.iter().for_each(|key| match key {
-                LeftMouse => world.env.mouse.state = Locked(world.env.win_size / 2.0),
-                RightMouse => world.env.mouse.state = Free,
+                LeftMouse => input.mouse.lock(input.win),
+                RightMouse => input.mouse.unlock(input.win),
                 _ => (),
             });
         }
 
-        match world.env.mouse.state {
-            Free => (),
-            Locked(vec) => {
-                let arr: [f32; 2] = vec.into();
-                let (x, y) = (arr[0], arr[1]);
-
-                world.env.win.warp_mouse_in_window(x as i32, y as i32);
-                world.env.device = DeviceState::new();
-                world.env.mouse.mouse = world.env.device.get_mouse();
-            }
+        if input.mouse.state == Locked {
+            let delta = self.look_delta(input.mouse) * 0.005;
+            let dir = rotate_vec3(&self.rot.xyz(), -delta.x, &vec3(0.0, 1.0, 0.0));
+            let dir = rotate_vec3(&dir, -delta.y, &cross(&dir, &vec3(0.0, 1.0, 0.0)));
+            self.rot = vec4(dir.x, dir.y, dir.z, self.rot.w);
         }
     }
 }
@@ -150,12 +146,6 @@ struct Pyramid {
     mesh: Mesh<Vertex>,
 }
 
-impl MeshTrait<GameObject, Vertex> for Pyramid {
-    fn get_mesh(&self) -> &Mesh<Vertex> {
-        &self.mesh
-    }
-}
-
 impl Pyramid {
     fn new(pos: Vec3, rot: Vec4, mesh: Mesh<Vertex>) -> Self {
         Self { pos, rot, mesh }
@@ -165,17 +155,10 @@ impl Pyramid {
 impl_posrot!(Pyramid);
 
 impl Object<GameObject> for Pyramid {
-    fn update(world: &mut World<GameObject>, _: u32)
-    where
-        Self: Sized,
-    {
-        world.objects.pyramid.rot.w += 0.01;
-
-        world
-            .objects
-            .pyramid
-            .mesh
-            .update_mesh(world.objects.pyramid.pos, world.objects.pyramid.rot)
+    fn update(&mut self, _: &mut UpdateCtx<GameObject>) {
+        self.rot.w += 0.01;
+
+        self.mesh.transform = Transform3D::new(self.pos).rot(self.rot);
     }
 }
 
@@ -185,11 +168,9 @@ struct GameObject {
 }
 
 impl GameObjectTrait for GameObject {
-    fn update(&self) -> fn(world: &mut World<GameObject>) {
-        |world: &mut World<GameObject>| {
-            Camera::update(world, 0);
-            Pyramid::update(world, 0)
-        }
+    fn update(&mut self, ctx: &mut UpdateCtx<Self>) {
+        self.camera.update(ctx);
+        self.pyramid.update(ctx)
     }
 
     fn get_camera(&self) -> &dyn CameraTrait<Self> {
@@ -199,6 +180,18 @@ impl GameObjectTrait for GameObject {
     fn set_camera(&mut self) -> &mut dyn CameraTrait<Self> {
         &mut self.camera
     }
+
+    fn get_controllable_keys(&mut self) -> Vec<&mut dyn ControllableKey> {
+        vec![&mut self.camera]
+    }
+
+    fn get_controllable_mice(&mut self) -> Vec<&mut dyn ControllableMouse> {
+        vec![&mut self.camera]
+    }
+
+    fn get_draw_commands(&self) -> Vec<DrawCommand<'_>> {
+        vec![DrawCommand::new(&self.pyramid.mesh)]
+    }
 }
 
 fn main() {
@@ -218,34 +211,13 @@ fn main() {
 
     let index = vec![[0, 1, 4], [1, 2, 4], [2, 3, 4], [0, 3, 4]];
 
-    // Create a new device state
-    let device_state = DeviceState::new();
-    let mouse: Mouse = device_state.clone().into();
-
-    let sdl = SDL::init(InitFlags::Everything).expect("couldn't start SDL");
-    sdl.gl_set_attribute(SdlGlAttr::MajorVersion, 3).unwrap();
-    sdl.gl_set_attribute(SdlGlAttr::MinorVersion, 3).unwrap();
-    sdl.gl_set_attribute(SdlGlAttr::Profile, GlProfile::Core)
-        .unwrap();
-    #[cfg(target_os = "macos")]
-    {
-        sdl.gl_set_attribute(SdlGlAttr::Flags, ContextFlag::ForwardCompatible)
-            .unwrap();
-    }
-    let win = sdl
-        .create_gl_window(
-            WINDOW_TITLE,
-            WindowPosition::Centered,
-            WIDTH.into(),
-            HEIGHT.into(),
-            WindowFlags::Shown,
-        )
-        .expect("couldn't make a window and context");
-    win.set_swap_interval(SwapInterval::Vsync);
-
-    unsafe {
-        load_gl_with(|f_name| win.get_proc_address(f_name));
-    }
+    let app = App::new(
+        AppConfig::default()
+            .title(WINDOW_TITLE)
+            .size(WIDTH.into(), HEIGHT.into())
+            .clear_color([0.2, 0.3, 0.3, 1.0]),
+    )
+    .expect("couldn't make a window and context");
 
     clear_color(0.2, 0.3, 0.3, 1.0); // sets background color
 
@@ -253,8 +225,9 @@ fn main() {
     let pyramid = Pyramid::new(
         vec3(0.0, 0.0, 0.0),
         vec4(0.0, 1.0, 0.0, 0.0),
-        Mesh::new(vert, vec![3, 2], index).unwrap(),
+        Mesh::from_vertices(vert, index),
     );
+    pyramid.mesh.upload();
 
     let shader_program = ShaderProgram::from_vert_frag(vert_shader, frag_shader).unwrap();
     shader_program.use_program();
@@ -265,38 +238,25 @@ fn main() {
         vec4(0.0, 0.0, 1.0, 0.0),
         CameraSettingsBuilder::default()
             .screen_size(vec2(WIDTH.into(), HEIGHT.into()))
-            .shader_program(shader_program)
+            .shader_program(shader_program.clone())
             .build(),
         "camera_matrix".to_string(),
     );
 
     let game_objects = GameObject { camera, pyramid };
 
-    let mut world = World::<GameObject>::new(
-        Enviroment::new(
-            vec2(WIDTH.into(), HEIGHT.into()),
-            win,
-            shader_program,
-            device_state,
-            mouse,
-        ),
-        game_objects,
-    );
-
     // textures
     let img = image::io::Reader::open("data/image.jpg")
         .unwrap()
         .decode()
         .unwrap();
     let mut texture = Texture::from_image(
-    GL_TEXTURE0,
-    GL_TEXTURE_2D,
-    hash_map!{
-      "GL_TEXTURE_MIN_FILTER" => number::MultiSingularNumber::Number(number::Number::Integer(GL_NEAREST as i32)),
-      "GL_TEXTURE_MAG_FILTER" => number::MultiSingularNumber::Number(number::Number::Integer(GL_LINEAR as i32)),
-      "GL_TEXTURE_WRAP_S" => number::MultiSingularNumber::Number(number::Number::Integer(GL_REPEAT as i32)),
-      "GL_TEXTURE_WRAP_T" => number::MultiSingularNumber::Number(number::Number::Integer(GL_REPEAT as i32))
-    },
+    TextureUnit::Texture0,
+    TextureTarget::Texture2D,
+    TextureSettings::default()
+      .filter(TextureFilter::Nearest, TextureFilter::Linear)
+      .wrap(TextureWrap::Repeat)
+      .params(),
     0,
     img
   ).unwrap();
@@ -305,29 +265,13 @@ fn main() {
     Uniform::new(&shader_program, "tex_color");
 
     // enable depth buffer
-    enable(GL_DEPTH_TEST);
-    world.update();
-    // Location of the world
-    'main_loop: loop {
-        world.env.mouse.mouse = world.env.device.get_mouse();
-
-        // handle events this frame
-        while let Some(event) = sdl.poll_events().and_then(Result::ok) {
-            match event {
-                Event::Quit(_) => break 'main_loop,
-                _ => (),
-            }
-        }
-
-        texture.bind(GL_TEXTURE_2D);
-        world.update();
-
-        // and then draw!
-        unsafe {
-            glClear(GL_COLOR_BUFFER_BIT);
-            glClear(GL_DEPTH_BUFFER_BIT);
-            glDrawElements(GL_TRIANGLES, 48, GL_UNSIGNED_INT, 0 as *const _);
-        }
-        world.env.win.swap_window();
-    }
+    RenderState::default().apply();
+    // the camera and pyramid move a fixed amount every update, so it has to be a fixed rate
+    app.run_fixed(
+        game_objects,
+        shader_program,
+        GameLoop::new(60.0),
+        |_world| (),
+        |_world, _alpha| texture.bind(TextureTarget::Texture2D),
+    );
 }
@@ -0,0 +1,594 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write},
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{error::LighthouseError, graphics::recovery::ContextId};
+use ogl33::{
+    glDeleteQueries, glFinish, glGenQueries, glGetQueryObjectui64v, glQueryCounter,
+    GL_QUERY_RESULT, GL_QUERY_RESULT_AVAILABLE, GL_TIMESTAMP,
+};
+
+/// What separates the names of a section and the sections it is in, e.g. `update/objects`
+pub const SECTION_SEPARATOR: char = '/';
+
+/// Gets how deep the section at path is, sections that aren't in another one are 0 deep
+pub fn section_depth(path: &str) -> usize {
+    path.matches(SECTION_SEPARATOR).count()
+}
+
+/// Gets the name of the section at path without the sections it is in
+pub fn section_name(path: &str) -> &str {
+    path.rsplit(SECTION_SEPARATOR).next().unwrap_or(path)
+}
+
+/// Gets where a new section at path goes in sections so it stays right after the other
+/// sections inside of the same section
+fn subtree_end(sections: &[(String, Duration)], path: &str) -> usize {
+    let Some((parent, _)) = path.rsplit_once(SECTION_SEPARATOR) else {
+        return sections.len();
+    };
+    sections
+        .iter()
+        .rposition(|(section, _)| {
+            section == parent
+                || section
+                    .strip_prefix(parent)
+                    .is_some_and(|rest| rest.starts_with(SECTION_SEPARATOR))
+        })
+        .map_or(sections.len(), |index| index + 1)
+}
+
+/// Writes text as a json string, quotes included
+fn write_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Writes a complete event of the chrome tracing format and a comma after it
+fn write_event(
+    out: &mut String,
+    name: &str,
+    category: &str,
+    thread: u32,
+    start: Duration,
+    duration: Duration,
+) {
+    out.push_str("{\"name\":");
+    write_json_string(out, name);
+    let _ = write!(
+        out,
+        ",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":{}}},",
+        category,
+        start.as_secs_f64() * 1_000_000.0,
+        duration.as_secs_f64() * 1_000_000.0,
+        thread
+    );
+}
+
+/// When a section began and how long it took
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Span {
+    /// The path of the section, see [SECTION_SEPARATOR]
+    pub path: String,
+    /// When the section began, since the profiler was created
+    pub start: Duration,
+    /// How long the section took
+    pub duration: Duration,
+}
+
+/// The timer queries of a section the gpu hasn't finished yet
+struct GpuQuery {
+    path: String,
+    begin: u32,
+    end: u32,
+}
+
+/// The times of one frame
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FrameSample {
+    /// The number of the frame, the first frame of a profiler is 0
+    pub index: u64,
+    /// When the frame began, since the profiler was created
+    pub start: Duration,
+    /// How long the whole frame took
+    pub total: Duration,
+    /// How long every section of the frame took, by the path of the section, in the order
+    /// they first began. A section that began inside of another one is after it and its
+    /// path starts with the path of the other one, see [SECTION_SEPARATOR]
+    pub sections: Vec<(String, Duration)>,
+    /// Every time a section was timed on the cpu, in the order they ended
+    pub spans: Vec<Span>,
+    /// Every time a section was timed on the gpu, only with [Profiler::gpu_timing]
+    ///
+    /// The gpu has its own clock, so these start at [FrameSample::start] plus how long
+    /// after the first gpu span of the frame they began. They are filled in a few frames
+    /// later, once the gpu is done with the frame
+    pub gpu_spans: Vec<Span>,
+}
+
+/// Statistics of the frames a [Profiler] remembers
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FrameStats {
+    /// How many frames the statistics are from
+    pub frames: usize,
+    /// The average time of a frame
+    pub average: Duration,
+    /// The fastest frame
+    pub min: Duration,
+    /// The slowest frame
+    pub max: Duration,
+    /// 99 out of 100 frames were faster than this, shows stutters the average hides
+    pub p99: Duration,
+    /// The average frames per second
+    pub fps: f32,
+    /// The average time of every section by its path, in the order they first began
+    pub sections: Vec<(String, Duration)>,
+}
+
+impl FrameStats {
+    /// Gets the average time of the section at path
+    pub fn section(&self, path: &str) -> Option<Duration> {
+        self.sections
+            .iter()
+            .find(|(section, _)| section == path)
+            .map(|(_, duration)| *duration)
+    }
+
+    /// Gets the sections that are directly inside of the section at path
+    pub fn children<'a>(
+        &'a self,
+        path: &'a str,
+    ) -> impl Iterator<Item = &'a (String, Duration)> + 'a {
+        self.sections.iter().filter(move |(section, _)| {
+            section
+                .strip_prefix(path)
+                .and_then(|rest| rest.strip_prefix(SECTION_SEPARATOR))
+                .is_some_and(|rest| !rest.contains(SECTION_SEPARATOR))
+        })
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "{} frames, {:.1} fps, avg {:.3} ms, min {:.3} ms, max {:.3} ms, p99 {:.3} ms",
+            self.frames,
+            self.fps,
+            ms(self.average),
+            ms(self.min),
+            ms(self.max),
+            ms(self.p99)
+        )?;
+        for (path, duration) in &self.sections {
+            writeln!(
+                f,
+                "{:indent$}{}: {:.3} ms",
+                "",
+                section_name(path),
+                ms(*duration),
+                indent = 2 + section_depth(path) * 2
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Times frames and named sections of them on the cpu
+///
+/// Sections that begin while another one is open are inside of it, so the report is a tree,
+/// e.g. the [World](crate::ECS::world::World) times its stages and the systems and objects
+/// in them like `update/systems/physics`
+///
+/// The last [Profiler::history] frames are kept to work out the [FrameStats]. Opengl runs
+/// after the calls return, so with [Profiler::sync_gpu] the profiler waits for the gpu at
+/// the end of every frame to count its time too. With [Profiler::gpu_timing] every section
+/// is also timed on the gpu with timer queries, which doesn't wait for the gpu
+///
+/// The kept frames can be saved with [Profiler::write_chrome_trace] and opened in
+/// `chrome://tracing` or [Perfetto](https://ui.perfetto.dev) as a flame graph
+///
+/// # Example
+/// ```
+/// let mut profiler = Profiler::new().gpu_timing(true);
+///
+/// 'main_loop: loop {
+///     profiler.begin_frame();
+///     profiler.time("update", || world.update());
+///     profiler.begin("draw");
+///     profiler.time("shadows", || shadows.draw()); // timed as draw/shadows
+///     // draw the scene
+///     profiler.end("draw");
+///     profiler.end_frame();
+/// }
+///
+/// println!("{}", profiler.stats());
+/// profiler.write_chrome_trace("frames.json").unwrap();
+/// ```
+pub struct Profiler {
+    /// How many frames are kept
+    pub history: usize,
+    /// Whether the end of a frame waits for the gpu to finish
+    pub sync_gpu: bool,
+    /// Whether the sections are timed on the gpu too, needs an opengl context
+    pub gpu_timing: bool,
+    frames: VecDeque<FrameSample>,
+    frame_start: Option<Instant>,
+    frame_index: u64,
+    current: Vec<(String, Duration)>,
+    spans: Vec<Span>,
+    /// The paths of the open sections, when they began and the query of their gpu begin
+    open: Vec<(String, Instant, Option<u32>)>,
+    /// When the profiler was created, every span starts from it
+    epoch: Instant,
+    gpu_current: Vec<GpuQuery>,
+    /// The queries of the frames the gpu isn't done with yet by the index of the frame
+    gpu_pending: VecDeque<(u64, Vec<GpuQuery>)>,
+    free_queries: Vec<u32>,
+    /// The context the queries are made in, None before the first one
+    context: Option<ContextId>,
+}
+
+impl Profiler {
+    /// Creates a new profiler that keeps the last 240 frames
+    pub fn new() -> Self {
+        Profiler {
+            history: 240,
+            sync_gpu: false,
+            gpu_timing: false,
+            frames: VecDeque::new(),
+            frame_start: None,
+            frame_index: 0,
+            current: Vec::new(),
+            spans: Vec::new(),
+            open: Vec::new(),
+            epoch: Instant::now(),
+            gpu_current: Vec::new(),
+            gpu_pending: VecDeque::new(),
+            free_queries: Vec::new(),
+            context: None,
+        }
+    }
+
+    /// Sets how many frames are kept
+    pub fn history(mut self, history: usize) -> Self {
+        self.history = history.max(1);
+        self
+    }
+
+    /// Sets whether the end of a frame waits for the gpu to finish
+    pub fn sync_gpu(mut self, sync_gpu: bool) -> Self {
+        self.sync_gpu = sync_gpu;
+        self
+    }
+
+    /// Sets whether the sections are timed on the gpu too
+    pub fn gpu_timing(mut self, gpu_timing: bool) -> Self {
+        self.gpu_timing = gpu_timing;
+        self
+    }
+
+    /// Starts timing a frame
+    pub fn begin_frame(&mut self) {
+        self.current.clear();
+        self.spans.clear();
+        self.open.clear();
+        let unfinished = std::mem::take(&mut self.gpu_current);
+        self.free(unfinished);
+        self.frame_start = Some(Instant::now());
+    }
+
+    /// Stops timing the frame, sections that are still open are ended
+    pub fn end_frame(&mut self) {
+        if self.sync_gpu {
+            unsafe { glFinish() }
+        }
+        self.close(0);
+        let Some(start) = self.frame_start.take() else {
+            return;
+        };
+
+        self.frames.push_back(FrameSample {
+            index: self.frame_index,
+            start: start - self.epoch,
+            total: start.elapsed(),
+            sections: std::mem::take(&mut self.current),
+            spans: std::mem::take(&mut self.spans),
+            gpu_spans: Vec::new(),
+        });
+        if !self.gpu_current.is_empty() {
+            let queries = std::mem::take(&mut self.gpu_current);
+            self.gpu_pending.push_back((self.frame_index, queries));
+        }
+        self.frame_index += 1;
+        while self.frames.len() > self.history {
+            self.frames.pop_front();
+        }
+        self.collect_gpu();
+    }
+
+    /// Reads the queries of the frames the gpu is done with into their [FrameSample::gpu_spans]
+    fn collect_gpu(&mut self) {
+        while let Some((_, queries)) = self.gpu_pending.front() {
+            // the queries finish in order, so the frame is done when its last one is
+            let last = queries.last().map_or(0, |query| query.end);
+            let mut available = 0;
+            unsafe { glGetQueryObjectui64v(last, GL_QUERY_RESULT_AVAILABLE, &mut available) };
+            if available == 0 {
+                break;
+            }
+
+            let (index, queries) = self.gpu_pending.pop_front().unwrap();
+            let read = |query: u32| {
+                let mut time = 0;
+                unsafe { glGetQueryObjectui64v(query, GL_QUERY_RESULT, &mut time) };
+                time
+            };
+            let times: Vec<(u64, u64)> = queries
+                .iter()
+                .map(|query| (read(query.begin), read(query.end)))
+                .collect();
+            let first = times.iter().map(|(begin, _)| *begin).min().unwrap_or(0);
+
+            if let Some(frame) = self.frames.iter_mut().find(|frame| frame.index == index) {
+                frame.gpu_spans = queries
+                    .iter()
+                    .zip(&times)
+                    .map(|(query, (begin, end))| Span {
+                        path: query.path.clone(),
+                        start: frame.start + Duration::from_nanos(begin - first),
+                        duration: Duration::from_nanos(end.saturating_sub(*begin)),
+                    })
+                    .collect();
+            }
+            self.free(queries);
+        }
+    }
+
+    /// Records the gpu time into a query that isn't used and gets the query
+    fn timestamp(&mut self) -> u32 {
+        self.context.get_or_insert_with(ContextId::current);
+        let query = self.free_queries.pop().unwrap_or_else(|| {
+            let mut query = 0;
+            unsafe { glGenQueries(1, &mut query) };
+            query
+        });
+        unsafe { glQueryCounter(query, GL_TIMESTAMP) };
+        query
+    }
+
+    /// Puts the queries back so they can be used again
+    fn free(&mut self, queries: Vec<GpuQuery>) {
+        self.free_queries
+            .extend(queries.iter().flat_map(|query| [query.begin, query.end]));
+    }
+
+    /// Starts timing a section called name inside of the last section that is still open
+    pub fn begin(&mut self, name: &str) {
+        let path = match self.open.last() {
+            Some((parent, _, _)) => format!("{}{}{}", parent, SECTION_SEPARATOR, name),
+            None => name.to_string(),
+        };
+        if !self.current.iter().any(|(section, _)| *section == path) {
+            let index = subtree_end(&self.current, &path);
+            self.current.insert(index, (path.clone(), Duration::ZERO));
+        }
+        let query = self.gpu_timing.then(|| self.timestamp());
+        self.open.push((path, Instant::now(), query));
+    }
+
+    /// Stops timing the last open section called name, sections inside of it that are still
+    /// open are ended too. A section that is ended twice in a frame adds up
+    pub fn end(&mut self, name: &str) {
+        let Some(index) = self
+            .open
+            .iter()
+            .rposition(|(open, _, _)| section_name(open) == name)
+        else {
+            return;
+        };
+        self.close(index);
+    }
+
+    /// Ends the open section at index and every section opened after it
+    fn close(&mut self, index: usize) {
+        if index >= self.open.len() {
+            return;
+        }
+        let now = Instant::now();
+        // the innermost section ends first
+        let closed: Vec<_> = self.open.drain(index..).rev().collect();
+        for (path, start, query) in closed {
+            if let Some((_, duration)) = self
+                .current
+                .iter_mut()
+                .find(|(section, _)| *section == path)
+            {
+                *duration += now - start;
+            }
+            if let Some(begin) = query {
+                let end = self.timestamp();
+                self.gpu_current.push(GpuQuery {
+                    path: path.clone(),
+                    begin,
+                    end,
+                });
+            }
+            self.spans.push(Span {
+                path,
+                start: start - self.epoch,
+                duration: now - start,
+            });
+        }
+    }
+
+    /// Gets the path of the last section that is still open
+    pub fn current_section(&self) -> Option<&str> {
+        self.open.last().map(|(path, _, _)| path.as_str())
+    }
+
+    /// Times f as a section called name
+    pub fn time<R>(&mut self, name: &str, f: impl FnOnce() -> R) -> R {
+        self.begin(name);
+        let out = f();
+        self.end(name);
+        out
+    }
+
+    /// Gets the frames that are kept, the oldest first
+    pub fn frames(&self) -> impl Iterator<Item = &FrameSample> {
+        self.frames.iter()
+    }
+
+    /// Gets the last frame that was ended
+    pub fn last_frame(&self) -> Option<&FrameSample> {
+        self.frames.back()
+    }
+
+    /// Works out the statistics of the frames that are kept
+    pub fn stats(&self) -> FrameStats {
+        if self.frames.is_empty() {
+            return FrameStats::default();
+        }
+
+        let mut totals: Vec<Duration> = self.frames.iter().map(|frame| frame.total).collect();
+        totals.sort_unstable();
+        let sum: Duration = totals.iter().sum();
+        let average = sum / totals.len() as u32;
+        let p99 = totals[((totals.len() - 1) as f32 * 0.99).round() as usize];
+
+        let mut sections: Vec<(String, Duration)> = Vec::new();
+        for frame in &self.frames {
+            for (name, duration) in &frame.sections {
+                match sections.iter_mut().find(|(section, _)| section == name) {
+                    Some((_, total)) => *total += *duration,
+                    None => {
+                        let index = subtree_end(&sections, name);
+                        sections.insert(index, (name.clone(), *duration));
+                    }
+                }
+            }
+        }
+        for (_, duration) in &mut sections {
+            *duration /= totals.len() as u32;
+        }
+
+        FrameStats {
+            frames: totals.len(),
+            average,
+            min: totals[0],
+            max: totals[totals.len() - 1],
+            p99,
+            fps: if average.is_zero() {
+                0.0
+            } else {
+                1.0 / average.as_secs_f32()
+            },
+            sections,
+        }
+    }
+
+    /// Gets the kept frames in the [chrome tracing](https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU)
+    /// json format, which `chrome://tracing`, [Perfetto](https://ui.perfetto.dev) and
+    /// [speedscope](https://www.speedscope.app) can open
+    ///
+    /// Every frame is a span called `frame`, the cpu sections are on the `cpu` thread and
+    /// the gpu sections on the `gpu` thread, named by their paths
+    pub fn chrome_trace(&self) -> String {
+        let mut out = String::from("{\"traceEvents\":[");
+        for (tid, thread) in [(1, "cpu"), (2, "gpu")] {
+            let _ = write!(
+                out,
+                "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}},",
+                tid, thread
+            );
+        }
+        for frame in &self.frames {
+            write_event(&mut out, "frame", "cpu", 1, frame.start, frame.total);
+            for span in &frame.spans {
+                write_event(&mut out, &span.path, "cpu", 1, span.start, span.duration);
+            }
+            for span in &frame.gpu_spans {
+                write_event(&mut out, &span.path, "gpu", 2, span.start, span.duration);
+            }
+        }
+
+        // every event ends with a comma, the last one can't
+        out.pop();
+        out.push_str("],\"displayTimeUnit\":\"ms\"}");
+        out
+    }
+
+    /// Saves [Profiler::chrome_trace] to the file at path
+    pub fn write_chrome_trace(&self, path: impl AsRef<Path>) -> Result<(), LighthouseError> {
+        let path = path.as_ref();
+        fs::write(path, self.chrome_trace())
+            .map_err(|err| LighthouseError::Io(path.to_path_buf(), err))
+    }
+
+    /// Forgets every frame
+    pub fn reset(&mut self) {
+        self.frames.clear();
+        self.current.clear();
+        self.spans.clear();
+        self.open.clear();
+        self.frame_start = None;
+        let unfinished = std::mem::take(&mut self.gpu_current);
+        self.free(unfinished);
+        while let Some((_, queries)) = self.gpu_pending.pop_front() {
+            self.free(queries);
+        }
+    }
+
+    /// Forgets the gpu queries without deleting them, for when the opengl context they were
+    /// made in is gone
+    pub fn forget_gpu_queries(&mut self) {
+        self.gpu_current.clear();
+        self.gpu_pending.clear();
+        self.free_queries.clear();
+        self.open.iter_mut().for_each(|(_, _, query)| *query = None);
+        self.context = None;
+    }
+
+    /// Deletes the gpu queries of the profiler now instead of when it is dropped, has to be
+    /// called while the opengl context is still alive
+    pub fn delete(&mut self) {
+        self.reset();
+        if !self.free_queries.is_empty() {
+            unsafe { glDeleteQueries(self.free_queries.len() as i32, self.free_queries.as_ptr()) };
+        }
+        self.free_queries.clear();
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        if self.context.is_some_and(ContextId::is_current) {
+            self.delete();
+        }
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}